@@ -0,0 +1,30 @@
+//! Build script: record dependency versions for toolchain pinning
+
+use std::path::Path;
+
+fn main() {
+    let lock_path = Path::new(&std::env::var("CARGO_MANIFEST_DIR").unwrap()).join("Cargo.lock");
+    println!("cargo:rerun-if-changed={}", lock_path.display());
+
+    let lock = std::fs::read_to_string(&lock_path).unwrap_or_default();
+
+    for (package, var) in [
+        ("musk", "SPRAY_MUSK_VERSION"),
+        ("simplicity-lang", "SPRAY_SIMPLICITY_VERSION"),
+    ] {
+        let version = locked_version(&lock, package).unwrap_or("unknown");
+        println!("cargo:rustc-env={var}={version}");
+    }
+}
+
+/// Find the version of `package` in a Cargo.lock file
+fn locked_version<'a>(lock: &'a str, package: &str) -> Option<&'a str> {
+    let name_line = format!("name = \"{package}\"");
+    let mut lines = lock.lines();
+    lines.find(|line| line.trim() == name_line)?;
+    lines
+        .next()?
+        .trim()
+        .strip_prefix("version = \"")?
+        .strip_suffix('"')
+}
@@ -0,0 +1,355 @@
+//! Alert rules for contract monitoring
+//!
+//! A rules file names the contracts to watch and the conditions to alert
+//! on. Each rule applies to one contract and fires on the
+//! [`WatchEvent`]s that meet its condition:
+//!
+//! ```toml
+//! [[contracts]]
+//! name = "vault"
+//! file = "vault.simf"
+//! args = "vault.args.json"
+//!
+//! [[contracts]]
+//! name = "escrow"
+//! address = "ert1p..."
+//!
+//! [[rules]]
+//! name = "unexpected-spend"
+//! contract = "vault"
+//! when = { spent_without_output_to = "ert1q..." }
+//! webhook = "http://127.0.0.1:9000/alerts"
+//! exit_code = 2
+//!
+//! [[rules]]
+//! name = "low-balance"
+//! contract = "vault"
+//! when = { balance_below = 100000 }
+//! ```
+//!
+//! Conditions are `"funded"`, `"spent"`, `{ spent_without_output_to =
+//! "<address>" }` and `{ balance_below = <sats> }`; a balance alert fires
+//! when an event takes the contract's balance from at or above the
+//! threshold to below it. A rule with a `webhook` posts the [`Alert`] there
+//! as JSON, over plain HTTP; a rule with an `exit_code` stops the monitor
+//! with that status once the block is processed.
+//!
+//! # Example
+//!
+//! ```
+//! use spray::alerts::AlertRules;
+//! use spray::monitor::WatchEvent;
+//!
+//! let rules = AlertRules::from_toml(
+//!     r#"
+//!     [[contracts]]
+//!     name = "vault"
+//!     address = "ert1qw508d6qejxtdg4y5r3zarvary0c5xw7kuu73e0"
+//!
+//!     [[rules]]
+//!     name = "low-balance"
+//!     contract = "vault"
+//!     when = { balance_below = 1000 }
+//!     exit_code = 2
+//!     "#,
+//! )
+//! .unwrap();
+//!
+//! let event = WatchEvent::Spent {
+//!     contract: "vault".into(),
+//!     txid: "aa".into(),
+//!     outpoints: vec!["bb:0".into()],
+//!     outputs: vec![],
+//!     height: 120,
+//!     balance_before: 5000,
+//!     balance_after: 0,
+//! };
+//! let alerts = rules.evaluate(&event);
+//! assert_eq!(alerts[0].rule, "low-balance");
+//! assert_eq!(rules.exit_code(&alerts), Some(2));
+//! ```
+
+use crate::error::SprayError;
+use crate::hex;
+use crate::monitor::WatchEvent;
+use musk::elements::Address;
+use serde::{Deserialize, Serialize};
+use std::io::{Read, Write};
+use std::net::TcpStream;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::Duration;
+
+/// Timeout for connecting to and talking to a webhook
+const WEBHOOK_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// A contract to watch
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WatchedContract {
+    /// Name rules refer to the contract by
+    pub name: String,
+    /// Program (.simf or compiled .json), if not given by address
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub file: Option<PathBuf>,
+    /// Arguments file of the program
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub args: Option<PathBuf>,
+    /// Contract address, if not given by program
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub address: Option<String>,
+}
+
+/// When a rule fires
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Condition {
+    /// An output paying to the contract is confirmed
+    Funded,
+    /// The contract is spent
+    Spent,
+    /// The contract is spent by a transaction with no output to the address
+    SpentWithoutOutputTo(String),
+    /// The contract's balance drops below the threshold, in satoshis
+    BalanceBelow(u64),
+}
+
+impl Condition {
+    /// Describe why `event` meets the condition, or `None` if it does not
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the condition's address is invalid.
+    pub fn check(&self, event: &WatchEvent) -> Result<Option<String>, SprayError> {
+        let (before, after) = event.balances();
+        Ok(match (self, event) {
+            (Self::Funded, WatchEvent::Funded { vout, amount, .. }) => Some(format!(
+                "funded by {}:{vout} ({})",
+                event.txid(),
+                amount.map_or_else(|| "confidential".to_string(), |a| format!("{a} sat"))
+            )),
+            (Self::Spent, WatchEvent::Spent { outpoints, .. }) => Some(format!(
+                "{} spent by {}",
+                outpoints.join(", "),
+                event.txid()
+            )),
+            (Self::SpentWithoutOutputTo(address), WatchEvent::Spent { outputs, .. }) => {
+                let script = script_hex(address)?;
+                (!outputs.iter().any(|output| output.script_pubkey == script))
+                    .then(|| format!("spent by {} without an output to {address}", event.txid()))
+            }
+            (Self::BalanceBelow(threshold), _) => (before >= *threshold && after < *threshold)
+                .then(|| {
+                    format!("balance dropped from {before} to {after} sat, below {threshold}")
+                }),
+            _ => None,
+        })
+    }
+}
+
+/// A named condition on a contract, and what to do when it is met
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Rule {
+    /// Name of the rule
+    pub name: String,
+    /// Name of the contract the rule applies to
+    pub contract: String,
+    /// When the rule fires
+    pub when: Condition,
+    /// URL to post alerts to
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub webhook: Option<String>,
+    /// Status to exit the monitor with once the rule fires
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub exit_code: Option<i32>,
+}
+
+/// A rule that fired
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Alert {
+    /// Name of the rule
+    pub rule: String,
+    /// Name of the contract
+    pub contract: String,
+    /// Why the rule fired
+    pub message: String,
+    /// The event that made it fire
+    pub event: WatchEvent,
+}
+
+/// Contracts to watch and rules to evaluate against their events
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AlertRules {
+    /// Watched contracts
+    #[serde(default)]
+    pub contracts: Vec<WatchedContract>,
+    /// Alert rules
+    #[serde(default)]
+    pub rules: Vec<Rule>,
+}
+
+impl AlertRules {
+    /// Load a rules file
+    ///
+    /// Relative program and arguments paths are resolved against the file's
+    /// directory.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be read or is invalid.
+    pub fn load(path: &Path) -> Result<Self, SprayError> {
+        let contents = std::fs::read_to_string(path)?;
+        let mut rules = Self::from_toml(&contents)
+            .map_err(|e| SprayError::ConfigError(format!("{}: {e}", path.display())))?;
+
+        let base = path.parent().unwrap_or_else(|| Path::new(""));
+        for contract in &mut rules.contracts {
+            for file in [&mut contract.file, &mut contract.args]
+                .into_iter()
+                .flatten()
+            {
+                if file.is_relative() {
+                    *file = base.join(&*file);
+                }
+            }
+        }
+        Ok(rules)
+    }
+
+    /// Parse and validate rules
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the TOML is invalid, a contract is not given by
+    /// exactly one of program and address, a rule refers to an unknown
+    /// contract, or an address is invalid.
+    pub fn from_toml(contents: &str) -> Result<Self, SprayError> {
+        let rules: Self =
+            toml::from_str(contents).map_err(|e| SprayError::ConfigError(e.to_string()))?;
+
+        for contract in &rules.contracts {
+            if contract.file.is_some() == contract.address.is_some() {
+                return Err(SprayError::ConfigError(format!(
+                    "Contract '{}' needs exactly one of file and address",
+                    contract.name
+                )));
+            }
+            if let Some(address) = &contract.address {
+                script_hex(address)?;
+            }
+        }
+        for rule in &rules.rules {
+            if !rules.contracts.iter().any(|c| c.name == rule.contract) {
+                return Err(SprayError::ConfigError(format!(
+                    "Rule '{}' refers to unknown contract '{}'",
+                    rule.name, rule.contract
+                )));
+            }
+            if let Condition::SpentWithoutOutputTo(address) = &rule.when {
+                script_hex(address)?;
+            }
+        }
+        Ok(rules)
+    }
+
+    /// The alerts `event` raises, in rule order
+    ///
+    /// Rules whose address cannot be parsed never fire; [`Self::from_toml`]
+    /// rejects them.
+    #[must_use]
+    pub fn evaluate(&self, event: &WatchEvent) -> Vec<Alert> {
+        self.rules
+            .iter()
+            .filter(|rule| rule.contract == event.contract())
+            .filter_map(|rule| {
+                let message = rule.when.check(event).ok().flatten()?;
+                Some(Alert {
+                    rule: rule.name.clone(),
+                    contract: rule.contract.clone(),
+                    message,
+                    event: event.clone(),
+                })
+            })
+            .collect()
+    }
+
+    /// The rule named `name`
+    #[must_use]
+    pub fn rule(&self, name: &str) -> Option<&Rule> {
+        self.rules.iter().find(|rule| rule.name == name)
+    }
+
+    /// The status to exit with after `alerts`: the highest exit code of the
+    /// rules that fired, if any has one
+    #[must_use]
+    pub fn exit_code(&self, alerts: &[Alert]) -> Option<i32> {
+        alerts
+            .iter()
+            .filter_map(|alert| self.rule(&alert.rule)?.exit_code)
+            .max()
+    }
+}
+
+/// Output script (hex) of `address`
+fn script_hex(address: &str) -> Result<String, SprayError> {
+    let address = Address::from_str(address)
+        .map_err(|e| SprayError::ParseError(format!("Invalid address {address}: {e}")))?;
+    Ok(hex::encode(address.script_pubkey().as_bytes()))
+}
+
+/// Post `alert` as JSON to the `http://` URL `url`
+///
+/// # Errors
+///
+/// Returns an error if the URL is not plain HTTP, the request fails or the
+/// webhook does not answer with a 2xx status.
+pub fn post_webhook(url: &str, alert: &Alert) -> Result<(), SprayError> {
+    let webhook_error =
+        |e: &dyn std::fmt::Display| SprayError::ConfigError(format!("Webhook {url} failed: {e}"));
+    let rest = url.strip_prefix("http://").ok_or_else(|| {
+        webhook_error(&"only http:// URLs are supported; relay to https through a local proxy")
+    })?;
+    let (host, path) = rest
+        .split_once('/')
+        .map_or((rest, "/".to_string()), |(host, path)| {
+            (host, format!("/{path}"))
+        });
+    let authority = if host.contains(':') {
+        host.to_string()
+    } else {
+        format!("{host}:80")
+    };
+
+    let body = serde_json::to_string(alert)?;
+    let request = format!(
+        "POST {path} HTTP/1.1\r\nHost: {host}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+        body.len()
+    );
+
+    let address = std::net::ToSocketAddrs::to_socket_addrs(&authority)
+        .map_err(|e| webhook_error(&e))?
+        .next()
+        .ok_or_else(|| webhook_error(&"host not found"))?;
+    let mut stream =
+        TcpStream::connect_timeout(&address, WEBHOOK_TIMEOUT).map_err(|e| webhook_error(&e))?;
+    stream
+        .set_read_timeout(Some(WEBHOOK_TIMEOUT))
+        .map_err(|e| webhook_error(&e))?;
+    stream
+        .write_all(request.as_bytes())
+        .map_err(|e| webhook_error(&e))?;
+
+    let mut response = String::new();
+    stream
+        .read_to_string(&mut response)
+        .map_err(|e| webhook_error(&e))?;
+    let status = response
+        .split_whitespace()
+        .nth(1)
+        .and_then(|code| code.parse::<u16>().ok())
+        .ok_or_else(|| webhook_error(&"invalid HTTP response"))?;
+    if (200..300).contains(&status) {
+        Ok(())
+    } else {
+        Err(webhook_error(&format!("HTTP status {status}")))
+    }
+}
@@ -0,0 +1,285 @@
+//! Audit bundles
+//!
+//! An audit bundle packages everything needed to review and reproduce a
+//! contract into one archive: the source, the arguments it was instantiated
+//! with, the compiled artifact, its CMR, a cost report, a test report and
+//! the deployment records from the registry. A `manifest.json` at the root
+//! of the archive lists every other file with its SHA-256 digest, so that a
+//! counterparty can check that nothing was altered in transit.
+//!
+//! Bundles are written as uncompressed POSIX (ustar) tar archives, which
+//! every platform can unpack with standard tools.
+//!
+//! # Example
+//!
+//! ```
+//! use spray::audit::AuditBundle;
+//!
+//! let mut bundle = AuditBundle::new("p2pk", "deadbeef");
+//! bundle.add("source/p2pk.simf", b"fn main() {}".to_vec());
+//!
+//! let manifest = bundle.manifest();
+//! assert_eq!(manifest.files.len(), 1);
+//! assert_eq!(manifest.files[0].size, 12);
+//!
+//! let path = std::env::temp_dir().join(format!("spray-doc-audit-{}.tar", std::process::id()));
+//! bundle.write(&path).unwrap();
+//! let read = AuditBundle::read(&path).unwrap();
+//! assert_eq!(read.manifest(), manifest);
+//! # std::fs::remove_file(&path).unwrap();
+//! ```
+
+use crate::error::SprayError;
+use musk::elements::hashes::{sha256, Hash};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+/// Name of the manifest inside the archive
+pub const MANIFEST_FILE: &str = "manifest.json";
+
+/// Size of a tar block
+const BLOCK: usize = 512;
+
+/// Longest file name a ustar header holds without a prefix
+const MAX_NAME_LEN: usize = 100;
+
+/// Contents of `manifest.json`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AuditManifest {
+    /// Contract name, from the program's file name
+    pub contract: String,
+    /// Commitment Merkle Root of the program (hex)
+    pub cmr: String,
+    /// Version of spray that created the bundle
+    pub spray_version: String,
+    /// Unix timestamp of the bundle's creation
+    pub created: u64,
+    /// Every other file in the archive
+    pub files: Vec<ManifestEntry>,
+}
+
+/// A file listed in the manifest
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ManifestEntry {
+    /// Path inside the archive
+    pub path: String,
+    /// Size in bytes
+    pub size: u64,
+    /// SHA-256 digest of the contents (hex)
+    pub sha256: String,
+}
+
+/// Size and weight of a contract and a spend of it
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CostReport {
+    /// Program size in bytes
+    pub program_size: usize,
+    /// Weight of a spend with the bundled witness, verified locally
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub spend_weight: Option<usize>,
+    /// Virtual size of that spend
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub spend_vsize: Option<usize>,
+    /// Fee of that spend, in satoshis
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fee: Option<u64>,
+    /// Why the bundled witness failed to verify, if it did
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Files of an audit bundle, in archive order
+#[derive(Debug, Clone)]
+pub struct AuditBundle {
+    contract: String,
+    cmr: String,
+    created: u64,
+    files: Vec<(String, Vec<u8>)>,
+}
+
+impl AuditBundle {
+    /// An empty bundle for the contract `contract` with CMR `cmr`
+    #[must_use]
+    pub fn new(contract: &str, cmr: &str) -> Self {
+        Self {
+            contract: contract.to_string(),
+            cmr: cmr.to_string(),
+            created: crate::registry::now(),
+            files: Vec::new(),
+        }
+    }
+
+    /// Add a file at `path` inside the archive, replacing any file there
+    pub fn add(&mut self, path: &str, contents: Vec<u8>) {
+        self.files.retain(|(existing, _)| existing != path);
+        self.files.push((path.to_string(), contents));
+    }
+
+    /// Files in the bundle, excluding the manifest
+    #[must_use]
+    pub fn files(&self) -> &[(String, Vec<u8>)] {
+        &self.files
+    }
+
+    /// The manifest describing the bundle's files
+    #[must_use]
+    pub fn manifest(&self) -> AuditManifest {
+        AuditManifest {
+            contract: self.contract.clone(),
+            cmr: self.cmr.clone(),
+            spray_version: env!("CARGO_PKG_VERSION").to_string(),
+            created: self.created,
+            files: self
+                .files
+                .iter()
+                .map(|(path, contents)| ManifestEntry {
+                    path: path.clone(),
+                    size: contents.len() as u64,
+                    sha256: sha256::Hash::hash(contents).to_string(),
+                })
+                .collect(),
+        }
+    }
+
+    /// Write the bundle as a tar archive, with the manifest first
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a file name is too long for a tar header or the
+    /// archive cannot be written.
+    pub fn write(&self, path: &Path) -> Result<(), SprayError> {
+        let manifest = serde_json::to_vec_pretty(&self.manifest())?;
+        let mut archive = Vec::new();
+        append_entry(&mut archive, MANIFEST_FILE, &manifest, self.created)?;
+        for (name, contents) in &self.files {
+            append_entry(&mut archive, name, contents, self.created)?;
+        }
+        archive.resize(archive.len() + 2 * BLOCK, 0);
+        std::fs::write(path, archive)?;
+        Ok(())
+    }
+
+    /// Read a bundle written by [`AuditBundle::write`], checking every file
+    /// against the manifest
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the archive is malformed, has no manifest, or a
+    /// file is missing, unlisted or does not match its digest.
+    pub fn read(path: &Path) -> Result<Self, SprayError> {
+        let archive = std::fs::read(path)?;
+        let mut entries = read_entries(&archive)?;
+        let manifest_index = entries
+            .iter()
+            .position(|(name, _)| name == MANIFEST_FILE)
+            .ok_or_else(|| {
+                SprayError::FileFormatError(format!("No {MANIFEST_FILE} in the bundle"))
+            })?;
+        let (_, manifest) = entries.remove(manifest_index);
+        let manifest: AuditManifest = serde_json::from_slice(&manifest)?;
+
+        let bundle = Self {
+            contract: manifest.contract.clone(),
+            cmr: manifest.cmr.clone(),
+            created: manifest.created,
+            files: entries,
+        };
+        let actual = bundle.manifest();
+        for expected in &manifest.files {
+            match actual.files.iter().find(|f| f.path == expected.path) {
+                None => {
+                    return Err(SprayError::FileFormatError(format!(
+                        "{} is listed in the manifest but missing",
+                        expected.path
+                    )))
+                }
+                Some(file) if file != expected => {
+                    return Err(SprayError::FileFormatError(format!(
+                        "{} does not match its manifest digest",
+                        expected.path
+                    )))
+                }
+                Some(_) => {}
+            }
+        }
+        if let Some(extra) = actual
+            .files
+            .iter()
+            .find(|f| !manifest.files.iter().any(|e| e.path == f.path))
+        {
+            return Err(SprayError::FileFormatError(format!(
+                "{} is not listed in the manifest",
+                extra.path
+            )));
+        }
+        Ok(bundle)
+    }
+}
+
+/// Append a regular file entry to a tar archive
+fn append_entry(
+    archive: &mut Vec<u8>,
+    name: &str,
+    contents: &[u8],
+    mtime: u64,
+) -> Result<(), SprayError> {
+    if name.len() > MAX_NAME_LEN {
+        return Err(SprayError::FileFormatError(format!(
+            "File name too long for the archive: {name}"
+        )));
+    }
+
+    let mut header = [0u8; BLOCK];
+    let mut field = |offset: usize, value: &[u8]| {
+        header[offset..offset + value.len()].copy_from_slice(value);
+    };
+    field(0, name.as_bytes());
+    field(100, b"0000644\0");
+    field(108, b"0000000\0");
+    field(116, b"0000000\0");
+    field(124, format!("{:011o}\0", contents.len()).as_bytes());
+    field(136, format!("{mtime:011o}\0").as_bytes());
+    field(148, b"        ");
+    field(156, b"0");
+    field(257, b"ustar\0");
+    field(263, b"00");
+    let checksum: u32 = header.iter().map(|&b| u32::from(b)).sum();
+    header[148..156].copy_from_slice(format!("{checksum:06o}\0 ").as_bytes());
+
+    archive.extend_from_slice(&header);
+    archive.extend_from_slice(contents);
+    archive.resize(archive.len().div_ceil(BLOCK) * BLOCK, 0);
+    Ok(())
+}
+
+/// Parse the regular file entries of a tar archive
+fn read_entries(archive: &[u8]) -> Result<Vec<(String, Vec<u8>)>, SprayError> {
+    let malformed = || SprayError::FileFormatError("Malformed audit bundle".into());
+    let mut entries = Vec::new();
+    let mut offset = 0;
+    while offset + BLOCK <= archive.len() {
+        let header = &archive[offset..offset + BLOCK];
+        if header.iter().all(|&b| b == 0) {
+            break;
+        }
+
+        let name_len = header[..MAX_NAME_LEN]
+            .iter()
+            .position(|&b| b == 0)
+            .unwrap_or(MAX_NAME_LEN);
+        let name = std::str::from_utf8(&header[..name_len]).map_err(|_| malformed())?;
+        let size = std::str::from_utf8(&header[124..136])
+            .ok()
+            .map(|s| s.trim_matches(|c: char| c == '\0' || c == ' '))
+            .and_then(|s| usize::from_str_radix(s, 8).ok())
+            .ok_or_else(malformed)?;
+
+        let start = offset + BLOCK;
+        let contents = archive.get(start..start + size).ok_or_else(malformed)?;
+        if matches!(header[156], b'0' | 0) {
+            entries.push((name.to_string(), contents.to_vec()));
+        }
+        offset = start + size.div_ceil(BLOCK) * BLOCK;
+    }
+    Ok(entries)
+}
@@ -0,0 +1,180 @@
+//! Program benchmarks
+//!
+//! `spray bench` measures what contract authors optimize for: how long a
+//! program takes to compile and to satisfy, how large it is before and after
+//! pruning, and what its satisfied form costs against its witness budget
+//! (see [`crate::budget`]). Compilation and satisfaction are repeated over a
+//! number of iterations and summarized as a [`Timing`]; sizes and cost do not
+//! vary between iterations and are measured once.
+//!
+//! # Example
+//!
+//! ```
+//! use spray::bench::Timing;
+//! use std::time::Duration;
+//!
+//! let samples = [3, 1, 2].map(Duration::from_millis);
+//! let timing = Timing::from_samples(&samples).unwrap();
+//! assert_eq!(timing.median_us, 2_000);
+//! assert_eq!(timing.min_us, 1_000);
+//! assert_eq!(timing.to_string(), "2.000 ms (min 1.000 ms, max 3.000 ms)");
+//! ```
+
+use crate::budget::Budget;
+use crate::error::SprayError;
+use crate::file_loader;
+use crate::pset::SimplicityLeaf;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+/// Default number of iterations per program
+pub const DEFAULT_ITERATIONS: usize = 10;
+
+/// Summary of repeated measurements, in microseconds
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Timing {
+    /// Fastest measurement
+    pub min_us: u64,
+    /// Median measurement
+    pub median_us: u64,
+    /// Mean of the measurements
+    pub mean_us: u64,
+    /// Slowest measurement
+    pub max_us: u64,
+}
+
+impl Timing {
+    /// Summarize `samples`, or `None` if there are none
+    #[must_use]
+    pub fn from_samples(samples: &[Duration]) -> Option<Self> {
+        #[allow(clippy::cast_possible_truncation)]
+        let mut micros: Vec<u64> = samples.iter().map(|d| d.as_micros() as u64).collect();
+        micros.sort_unstable();
+        Some(Self {
+            min_us: *micros.first()?,
+            median_us: micros[micros.len() / 2],
+            mean_us: micros.iter().sum::<u64>() / micros.len() as u64,
+            max_us: *micros.last()?,
+        })
+    }
+}
+
+impl fmt::Display for Timing {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} (min {}, max {})",
+            format_us(self.median_us),
+            format_us(self.min_us),
+            format_us(self.max_us)
+        )
+    }
+}
+
+/// Measurements of one program
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BenchResult {
+    /// Path of the program
+    pub program: String,
+    /// Number of iterations measured
+    pub iterations: usize,
+    /// Time to parse, compile and instantiate the program
+    pub compile: Timing,
+    /// Time to satisfy the program with its witness, if it has one
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub satisfy: Option<Timing>,
+    /// Size of the unsatisfied program in bytes
+    pub program_size: usize,
+    /// Size of the satisfied, pruned program in bytes
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub satisfied_size: Option<usize>,
+    /// Size of the encoded witness in bytes
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub witness_size: Option<usize>,
+    /// Cost of the satisfied program against its witness budget
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub budget: Option<Budget>,
+}
+
+/// Benchmark the program in `file` over `iterations` iterations
+///
+/// The program is instantiated with the arguments in `args`, and satisfied
+/// with the witness in `witness` if given; without a witness only
+/// compilation is measured.
+///
+/// # Errors
+///
+/// Returns an error if a file cannot be loaded, the program fails to
+/// compile, or the witness does not satisfy it.
+pub fn bench_program(
+    file: &Path,
+    args: Option<&Path>,
+    witness: Option<&Path>,
+    iterations: usize,
+) -> Result<BenchResult, SprayError> {
+    let iterations = iterations.max(1);
+    let source = crate::include::expand(file)?;
+    let arguments = match args {
+        Some(path) => file_loader::load_arguments(path)?,
+        None => musk::Arguments::default(),
+    };
+    let witness_values = witness.map(file_loader::load_witness).transpose()?;
+
+    let mut compile_samples = Vec::with_capacity(iterations);
+    let mut satisfy_samples = Vec::new();
+    let mut compiled = None;
+    let mut satisfied = None;
+    for _ in 0..iterations {
+        let start = Instant::now();
+        let program = musk::Program::from_source(&source)?.instantiate(arguments.clone())?;
+        compile_samples.push(start.elapsed());
+
+        if let Some(witness_values) = &witness_values {
+            let start = Instant::now();
+            let program = program.satisfy(witness_values.clone())?;
+            satisfy_samples.push(start.elapsed());
+            satisfied = Some(program);
+        }
+        compiled = Some(program);
+    }
+    let compiled = compiled.ok_or_else(|| SprayError::TestError("No iterations run".into()))?;
+
+    let (satisfied_size, witness_size, budget) = match &satisfied {
+        Some(satisfied) => {
+            let (program_bytes, witness_bytes) = satisfied.encode();
+            let leaf = SimplicityLeaf::new(compiled.cmr().to_byte_array())?;
+            let (satisfied_size, witness_size) = (program_bytes.len(), witness_bytes.len());
+            let stack = [
+                witness_bytes,
+                program_bytes,
+                leaf.script.to_bytes(),
+                leaf.control_block.serialize(),
+            ];
+            (
+                Some(satisfied_size),
+                Some(witness_size),
+                Some(Budget::new(satisfied.redeem(), &stack)),
+            )
+        }
+        None => (None, None, None),
+    };
+
+    Ok(BenchResult {
+        program: file.display().to_string(),
+        iterations,
+        compile: Timing::from_samples(&compile_samples)
+            .ok_or_else(|| SprayError::TestError("No iterations run".into()))?,
+        satisfy: Timing::from_samples(&satisfy_samples),
+        program_size: compiled.inner().commit().to_vec_without_witness().len(),
+        satisfied_size,
+        witness_size,
+        budget,
+    })
+}
+
+/// Render microseconds as milliseconds with three decimals
+fn format_us(us: u64) -> String {
+    format!("{}.{:03} ms", us / 1000, us % 1000)
+}
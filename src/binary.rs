@@ -0,0 +1,377 @@
+//! Managed `elementsd` binaries
+//!
+//! By default test environments run whatever `elementsd` the `elementsd`
+//! crate finds: `$ELEMENTSD_EXE`, or the first one on the `PATH`. A project
+//! can instead pin a release in the `[elementsd]` table of `spray.toml`:
+//!
+//! ```toml
+//! [elementsd]
+//! version = "23.2.4"
+//! sha256 = "<checksum of the release archive>"
+//! ```
+//!
+//! The release is then downloaded from the Elements GitHub releases into a
+//! per-user cache on first use, checked against `sha256`, and every
+//! environment of the project runs that binary. Downloads shell out to
+//! `curl` and `tar`.
+//!
+//! The pin is required. spray does not check the PGP signature of the
+//! release's `SHA256SUMS.asc`, so it never installs a release on the word of
+//! that file alone: without `sha256`, it only reports the checksum the file
+//! lists, to be pinned once the signature has been checked.
+//!
+//! # Example
+//!
+//! ```
+//! use spray::binary::{parse_checksums, Release};
+//!
+//! let release = Release::new("23.2.4", "x86_64-linux-gnu");
+//! assert_eq!(release.asset(), "elements-23.2.4-x86_64-linux-gnu.tar.gz");
+//!
+//! let sums = format!("{}  {}\n", "ab".repeat(32), release.asset());
+//! assert_eq!(parse_checksums(&sums, &release.asset()), Some("ab".repeat(32)));
+//! ```
+
+use crate::error::SprayError;
+use musk::elements::hashes::{sha256, Hash, HashEngine};
+use serde::Deserialize;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// Base URL of Elements releases
+pub const RELEASES_URL: &str = "https://github.com/ElementsProject/elements/releases/download";
+
+/// Pinned `elementsd` release from the `[elementsd]` table
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct ElementsdConfig {
+    /// Release version, e.g. `"23.2.4"`; without it the `elementsd` crate's
+    /// lookup is used
+    pub version: Option<String>,
+    /// Expected SHA-256 (hex) of the release archive, required with `version`
+    pub sha256: Option<String>,
+    /// Download URL of the archive, overriding the GitHub release
+    pub url: Option<String>,
+    /// Directory of downloaded releases, relative to `spray.toml`
+    pub cache_dir: Option<PathBuf>,
+}
+
+/// An Elements release for one platform
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Release {
+    version: String,
+    platform: String,
+    url: Option<String>,
+}
+
+impl Release {
+    /// The release `version` for `platform`, e.g. `"x86_64-linux-gnu"`
+    #[must_use]
+    pub fn new(version: impl Into<String>, platform: impl Into<String>) -> Self {
+        Self {
+            version: version.into(),
+            platform: platform.into(),
+            url: None,
+        }
+    }
+
+    /// The release `version` for the platform spray runs on
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if Elements publishes no release binaries for the
+    /// platform.
+    pub fn for_host(version: impl Into<String>) -> Result<Self, SprayError> {
+        let platform = host_platform().ok_or_else(|| {
+            SprayError::EnvironmentError(format!(
+                "No elementsd release binaries for {}-{}; set ELEMENTSD_EXE instead",
+                std::env::consts::ARCH,
+                std::env::consts::OS
+            ))
+        })?;
+        Ok(Self::new(version, platform))
+    }
+
+    /// Download the archive from `url` instead of the GitHub release
+    #[must_use]
+    pub fn with_url(mut self, url: impl Into<String>) -> Self {
+        self.url = Some(url.into());
+        self
+    }
+
+    /// Release version
+    #[must_use]
+    pub fn version(&self) -> &str {
+        &self.version
+    }
+
+    /// File name of the release archive
+    #[must_use]
+    pub fn asset(&self) -> String {
+        format!("elements-{}-{}.tar.gz", self.version, self.platform)
+    }
+
+    /// Download URL of the release archive
+    #[must_use]
+    pub fn url(&self) -> String {
+        self.url
+            .clone()
+            .unwrap_or_else(|| format!("{RELEASES_URL}/elements-{}/{}", self.version, self.asset()))
+    }
+
+    /// Download URL of the release's checksum list
+    #[must_use]
+    pub fn checksums_url(&self) -> String {
+        format!("{RELEASES_URL}/elements-{}/SHA256SUMS.asc", self.version)
+    }
+
+    /// Path of the release's `elementsd` under `cache_dir`
+    #[must_use]
+    pub fn exe_path(&self, cache_dir: &Path) -> PathBuf {
+        cache_dir
+            .join(&self.version)
+            .join("bin")
+            .join(format!("elementsd{}", std::env::consts::EXE_SUFFIX))
+    }
+}
+
+/// Platform name of Elements release binaries for the host
+#[must_use]
+pub fn host_platform() -> Option<&'static str> {
+    match (std::env::consts::ARCH, std::env::consts::OS) {
+        ("x86_64", "linux") => Some("x86_64-linux-gnu"),
+        ("aarch64", "linux") => Some("aarch64-linux-gnu"),
+        ("x86_64", "macos") => Some("x86_64-apple-darwin"),
+        ("aarch64", "macos") => Some("arm64-apple-darwin"),
+        _ => None,
+    }
+}
+
+/// Default directory of downloaded releases, under the user's cache
+/// directory
+#[must_use]
+pub fn default_cache_dir() -> PathBuf {
+    std::env::var_os("XDG_CACHE_HOME")
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".cache")))
+        .unwrap_or_else(std::env::temp_dir)
+        .join("spray")
+        .join("elementsd")
+}
+
+/// Find the checksum of `asset` in a `SHA256SUMS` list
+#[must_use]
+pub fn parse_checksums(sums: &str, asset: &str) -> Option<String> {
+    sums.lines().find_map(|line| {
+        let (hash, name) = line.split_once(char::is_whitespace)?;
+        let name = name.trim_start().trim_start_matches('*');
+        (name == asset && hash.len() == 64 && hash.chars().all(|c| c.is_ascii_hexdigit()))
+            .then(|| hash.to_ascii_lowercase())
+    })
+}
+
+/// SHA-256 (hex) of a file's contents
+///
+/// # Errors
+///
+/// Returns an error if the file cannot be read.
+pub fn sha256_file(path: &Path) -> Result<String, SprayError> {
+    let mut file = std::fs::File::open(path)?;
+    let mut engine = sha256::Hash::engine();
+    let mut buf = [0u8; 64 * 1024];
+    loop {
+        let n = file.read(&mut buf)?;
+        if n == 0 {
+            break;
+        }
+        engine.input(&buf[..n]);
+    }
+    Ok(sha256::Hash::from_engine(engine).to_string())
+}
+
+/// Check that a file's SHA-256 is `expected`
+///
+/// # Errors
+///
+/// Returns an error if the file cannot be read or its checksum differs.
+pub fn verify(path: &Path, expected: &str) -> Result<(), SprayError> {
+    let actual = sha256_file(path)?;
+    if actual.eq_ignore_ascii_case(expected) {
+        Ok(())
+    } else {
+        Err(SprayError::EnvironmentError(format!(
+            "Checksum mismatch for {}: expected {expected}, got {actual}",
+            path.display()
+        )))
+    }
+}
+
+/// Download, verify and unpack `release` into `cache_dir`, unless it is
+/// there already, and return the path of its `elementsd`
+///
+/// The archive is checked against the pinned checksum `sha256`, and
+/// unpacked under a directory of `cache_dir` named after it, so that a
+/// release installed under one pin is never reused under another.
+///
+/// # Errors
+///
+/// Returns an error if `sha256` is not a SHA-256 in hex, the download fails,
+/// the checksum does not match, or the archive does not contain
+/// `bin/elementsd`.
+pub fn install(release: &Release, sha256: &str, cache_dir: &Path) -> Result<PathBuf, SprayError> {
+    if sha256.len() != 64 || !sha256.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(SprayError::ConfigError(format!(
+            "Invalid sha256 pin for elementsd {}: {sha256:?}",
+            release.version()
+        )));
+    }
+    let cache_dir = &cache_dir.join(sha256.to_ascii_lowercase());
+    let exe = release.exe_path(cache_dir);
+    if exe.is_file() {
+        return Ok(exe);
+    }
+
+    std::fs::create_dir_all(cache_dir)?;
+    let staging = cache_dir.join(format!(".{}-{}", release.version(), std::process::id()));
+    let _ = std::fs::remove_dir_all(&staging);
+    std::fs::create_dir_all(&staging)?;
+    let result = install_in(release, sha256, &staging, cache_dir);
+    let _ = std::fs::remove_dir_all(&staging);
+    result?;
+
+    if exe.is_file() {
+        Ok(exe)
+    } else {
+        Err(SprayError::EnvironmentError(format!(
+            "{} does not contain bin/elementsd",
+            release.asset()
+        )))
+    }
+}
+
+/// Download and unpack `release` in `staging`, then move it into place
+fn install_in(
+    release: &Release,
+    sha256: &str,
+    staging: &Path,
+    cache_dir: &Path,
+) -> Result<(), SprayError> {
+    let archive = staging.join(release.asset());
+    crate::status!(
+        "Downloading elementsd {} from {}",
+        release.version(),
+        release.url()
+    );
+    download(&release.url(), &archive)?;
+    verify(&archive, sha256)?;
+
+    let unpacked = staging.join("unpacked");
+    std::fs::create_dir_all(&unpacked)?;
+    run(Command::new("tar")
+        .arg("-xzf")
+        .arg(&archive)
+        .arg("-C")
+        .arg(&unpacked)
+        .arg("--strip-components=1"))?;
+
+    let target = cache_dir.join(release.version());
+    if !target.exists() {
+        std::fs::rename(&unpacked, &target)?;
+    }
+    Ok(())
+}
+
+/// Download `url` to `path` with `curl`
+fn download(url: &str, path: &Path) -> Result<(), SprayError> {
+    run(Command::new("curl")
+        .args([
+            "--fail",
+            "--silent",
+            "--show-error",
+            "--location",
+            "--output",
+        ])
+        .arg(path)
+        .arg(url))
+}
+
+/// Run a command, failing on a non-zero exit
+fn run(command: &mut Command) -> Result<(), SprayError> {
+    let program = command.get_program().to_string_lossy().into_owned();
+    let output = command
+        .output()
+        .map_err(|e| SprayError::EnvironmentError(format!("Failed to run {program}: {e}")))?;
+    if output.status.success() {
+        Ok(())
+    } else {
+        Err(SprayError::EnvironmentError(format!(
+            "{program} failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        )))
+    }
+}
+
+/// The `elementsd` to run test environments with
+///
+/// This is the release pinned in the nearest `spray.toml`, downloaded on
+/// first use, or otherwise whatever the `elementsd` crate finds.
+///
+/// # Errors
+///
+/// Returns an error if the project file cannot be loaded, the pinned
+/// release has no `sha256` or cannot be installed, or no `elementsd` is
+/// found.
+#[cfg(feature = "daemon")]
+pub fn exe_path() -> Result<PathBuf, SprayError> {
+    let pinned = crate::project::ProjectConfig::discover()?.and_then(|(path, project)| {
+        let config = project.elementsd;
+        config.version.is_some().then(|| {
+            let dir = path.parent().map(Path::to_path_buf).unwrap_or_default();
+            (dir, config)
+        })
+    });
+
+    let Some((project_dir, config)) = pinned else {
+        return elementsd::exe_path().map(PathBuf::from).map_err(|e| {
+            SprayError::DaemonError(format!(
+                "elementsd not found ({e}); install it, set ELEMENTSD_EXE, or pin a version in the [elementsd] table of spray.toml"
+            ))
+        });
+    };
+
+    let mut release = Release::for_host(config.version.clone().unwrap_or_default())?;
+    if let Some(url) = &config.url {
+        release = release.with_url(url);
+    }
+    let cache_dir = config
+        .cache_dir
+        .as_ref()
+        .map_or_else(default_cache_dir, |dir| project_dir.join(dir));
+    match config.sha256.as_deref() {
+        Some(sha256) => install(&release, sha256, &cache_dir),
+        None => Err(unpinned(&release, &cache_dir)),
+    }
+}
+
+/// The error for a release pinned without a checksum, naming the checksum
+/// the release's unverified `SHA256SUMS.asc` lists, if it can be fetched
+#[cfg(feature = "daemon")]
+fn unpinned(release: &Release, cache_dir: &Path) -> SprayError {
+    let sums_path = cache_dir.join(format!(".SHA256SUMS-{}.asc", std::process::id()));
+    let listed = std::fs::create_dir_all(cache_dir)
+        .map_err(SprayError::from)
+        .and_then(|()| download(&release.checksums_url(), &sums_path))
+        .and_then(|()| Ok(std::fs::read_to_string(&sums_path)?))
+        .ok()
+        .and_then(|sums| parse_checksums(&sums, &release.asset()));
+    let _ = std::fs::remove_file(&sums_path);
+
+    let hint = listed.map_or_else(
+        || format!("see {}", release.checksums_url()),
+        |sha256| format!("{} lists {sha256}", release.checksums_url()),
+    );
+    SprayError::ConfigError(format!(
+        "elementsd {} has no sha256 in the [elementsd] table of spray.toml; {hint}. Check the PGP signature of that file before pinning its checksum",
+        release.version()
+    ))
+}
@@ -0,0 +1,154 @@
+//! Simplicity cost budgets
+//!
+//! Consensus bounds how expensive a Simplicity program may be to execute by
+//! the size of the witness spending it: a spend's budget is the weight of its
+//! input's witness stack plus [`BUDGET_OVERHEAD_WU`] weight units, and the
+//! cost of the satisfied program, as computed by static analysis, must not
+//! exceed it. A contract whose spends use most of their budget is close to
+//! the consensus limit: a leaner witness, or a more expensive branch, may
+//! make it unspendable without padding.
+//!
+//! [`Budget`] compares the cost of the program carried by a spend with the
+//! budget its witness stack implies. Costs and budgets are kept in
+//! milliweight units, the unit programs are costed in.
+//!
+//! # Example
+//!
+//! ```
+//! use spray::budget::{budget_mwu, Budget};
+//!
+//! // 1 byte for the number of elements, 1 + 100 for the one element
+//! assert_eq!(budget_mwu(&[vec![0; 100]]), (102 + 50) * 1000);
+//!
+//! let budget = Budget { cost_mwu: 76_000, budget_mwu: 152_000 };
+//! assert!(budget.is_within());
+//! assert_eq!(budget.to_string(), "76.000 WU of 152.000 WU budget (50.0%)");
+//! ```
+
+use crate::error::SprayError;
+use musk::elements::Transaction;
+use serde::{Deserialize, Serialize};
+use simplicity::jet::Elements;
+use simplicity::{BitIter, RedeemNode};
+use std::fmt;
+
+/// Weight units added to the witness stack's weight to give the budget
+pub const BUDGET_OVERHEAD_WU: u64 = 50;
+
+/// Cost of a spend's program against its budget
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Budget {
+    /// Cost of executing the satisfied program, in milliweight units
+    pub cost_mwu: u64,
+    /// Budget implied by the witness stack, in milliweight units
+    pub budget_mwu: u64,
+}
+
+impl Budget {
+    /// The cost of `program` against the budget of `script_witness`, the
+    /// witness stack spending it
+    #[must_use]
+    pub fn new(program: &RedeemNode<Elements>, script_witness: &[Vec<u8>]) -> Self {
+        // `Cost` displays as its value in milliweight units
+        let cost_mwu = program
+            .bounds()
+            .cost
+            .to_string()
+            .parse()
+            .unwrap_or(u64::MAX);
+        Self {
+            cost_mwu,
+            budget_mwu: budget_mwu(script_witness),
+        }
+    }
+
+    /// The budget of a Simplicity spend's witness stack: the witness, the
+    /// program, the CMR script and the control block
+    ///
+    /// Returns `None` for stacks with fewer than two elements.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the stack does not hold a valid program and
+    /// witness.
+    pub fn for_witness_stack(script_witness: &[Vec<u8>]) -> Result<Option<Self>, SprayError> {
+        let [witness, program, ..] = script_witness else {
+            return Ok(None);
+        };
+        let program = RedeemNode::<Elements>::decode(
+            BitIter::from(program.iter().copied()),
+            BitIter::from(witness.iter().copied()),
+        )
+        .map_err(|e| SprayError::ParseError(format!("Invalid Simplicity program: {e}")))?;
+        Ok(Some(Self::new(&program, script_witness)))
+    }
+
+    /// The budget of the first Simplicity spend among `tx`'s inputs
+    #[must_use]
+    pub fn for_transaction(tx: &Transaction) -> Option<Self> {
+        tx.input
+            .iter()
+            .find_map(|input| Self::for_witness_stack(&input.witness.script_witness).ok()?)
+    }
+
+    /// Share of the budget the program uses, in percent
+    #[must_use]
+    #[allow(clippy::cast_precision_loss)]
+    pub fn usage(&self) -> f64 {
+        if self.budget_mwu == 0 {
+            return f64::INFINITY;
+        }
+        self.cost_mwu as f64 * 100.0 / self.budget_mwu as f64
+    }
+
+    /// Whether the cost is within the budget
+    #[must_use]
+    pub const fn is_within(&self) -> bool {
+        self.cost_mwu <= self.budget_mwu
+    }
+
+    /// Milliweight units left in the budget
+    #[must_use]
+    pub const fn headroom_mwu(&self) -> u64 {
+        self.budget_mwu.saturating_sub(self.cost_mwu)
+    }
+}
+
+impl fmt::Display for Budget {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} WU of {} WU budget ({:.1}%)",
+            format_mwu(self.cost_mwu),
+            format_mwu(self.budget_mwu),
+            self.usage()
+        )
+    }
+}
+
+/// Budget of a witness stack in milliweight units: its serialized size plus
+/// [`BUDGET_OVERHEAD_WU`]
+#[must_use]
+pub fn budget_mwu(script_witness: &[Vec<u8>]) -> u64 {
+    let size: u64 = varint_len(script_witness.len())
+        + script_witness
+            .iter()
+            .map(|element| varint_len(element.len()) + element.len() as u64)
+            .sum::<u64>();
+    (size + BUDGET_OVERHEAD_WU) * 1000
+}
+
+/// Render milliweight units as weight units with three decimals
+fn format_mwu(mwu: u64) -> String {
+    format!("{}.{:03}", mwu / 1000, mwu % 1000)
+}
+
+/// Serialized length of a Bitcoin variable-length integer
+const fn varint_len(n: usize) -> u64 {
+    match n {
+        0..=0xfc => 1,
+        0xfd..=0xffff => 3,
+        0x1_0000..=0xffff_ffff => 5,
+        _ => 9,
+    }
+}
@@ -0,0 +1,245 @@
+//! On-disk cache of compiled programs
+//!
+//! Instantiating a large program takes a while, which adds up when the same
+//! program is compiled over and over in an edit/compile loop. This module
+//! caches [`CompiledOutput`]s under `target/spray/cache`, keyed by a hash of
+//! the source, the arguments, the network and the toolchain versions, so that
+//! any change to one of them misses the cache.
+//!
+//! [`CompileCache::compile`] is the compile path of the commands that only
+//! need a program's bytes, CMR and address, such as `spray compile` and
+//! `spray deploy`. A spendable [`InstantiatedProgram`] cannot be rebuilt
+//! from a cached artifact, so the commands that spend a program load it
+//! through a [`ProgramCache`] instead, which keeps every program compiled in
+//! the process: a manifest suite or `spray test --watch` then compiles each
+//! program once per source and arguments rather than once per test or run.
+//!
+//! # Example
+//!
+//! ```
+//! use serde_json::json;
+//! use spray::cache::CompileCache;
+//!
+//! let key = CompileCache::key("fn main() {}", &json!({ "N": 1 }), "regtest");
+//! assert_eq!(key.len(), 64);
+//! assert_ne!(key, CompileCache::key("fn main() {}", &json!({ "N": 2 }), "regtest"));
+//! ```
+
+use crate::compiled::CompiledOutput;
+use crate::error::SprayError;
+use crate::file_loader;
+use crate::project::{MUSK_VERSION, SIMPLICITY_VERSION, SPRAY_VERSION};
+use musk::elements::hashes::{sha256, Hash, HashEngine};
+use musk::{InstantiatedProgram, Network};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, PoisonError};
+
+/// Default cache directory, relative to the working directory
+pub const DEFAULT_CACHE_DIR: &str = "target/spray/cache";
+
+/// A cached compilation
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CacheEntry {
+    /// Address of the program on the network it was compiled for
+    pub address: String,
+    /// The compiled program
+    pub output: CompiledOutput,
+}
+
+/// Compiled programs stored on disk, one JSON file per key
+#[derive(Debug, Clone)]
+pub struct CompileCache {
+    dir: PathBuf,
+}
+
+impl Default for CompileCache {
+    fn default() -> Self {
+        Self::new(DEFAULT_CACHE_DIR)
+    }
+}
+
+impl CompileCache {
+    /// A cache in `dir`, which is created on the first write
+    #[must_use]
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    /// The cache directory
+    #[must_use]
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Cache key for `source` instantiated with `arguments` on `network`
+    ///
+    /// `arguments` is the merged argument table; its keys are sorted when
+    /// serialized, so equal tables give equal keys.
+    #[must_use]
+    pub fn key(source: &str, arguments: &Value, network: &str) -> String {
+        let mut engine = sha256::Hash::engine();
+        for part in [
+            SPRAY_VERSION,
+            MUSK_VERSION,
+            SIMPLICITY_VERSION,
+            network,
+            source,
+            &arguments.to_string(),
+        ] {
+            // Length-prefix each part so that no two inputs collide
+            engine.input(&(part.len() as u64).to_le_bytes());
+            engine.input(part.as_bytes());
+        }
+        sha256::Hash::from_engine(engine).to_string()
+    }
+
+    /// Path of the entry for `key`
+    fn entry_path(&self, key: &str) -> PathBuf {
+        self.dir.join(format!("{key}.json"))
+    }
+
+    /// Look up a cached compilation
+    ///
+    /// Missing and unreadable entries are both treated as a miss.
+    #[must_use]
+    pub fn get(&self, key: &str) -> Option<CacheEntry> {
+        let contents = std::fs::read_to_string(self.entry_path(key)).ok()?;
+        serde_json::from_str(&contents).ok()
+    }
+
+    /// Store a compilation
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the cache directory or entry cannot be written.
+    pub fn put(&self, key: &str, entry: &CacheEntry) -> Result<(), SprayError> {
+        std::fs::create_dir_all(&self.dir)?;
+        std::fs::write(self.entry_path(key), serde_json::to_string(entry)?)?;
+        Ok(())
+    }
+
+    /// Compile `source` with the arguments of `args_file` and `overrides`
+    /// for `network`, or take the compilation from the cache
+    ///
+    /// A fresh compilation is stored; failing to store it is logged, not
+    /// returned.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the arguments cannot be loaded or the source
+    /// fails to compile.
+    pub fn compile(
+        &self,
+        source: &str,
+        args_file: Option<&Path>,
+        overrides: &[(String, String)],
+        network: Network,
+    ) -> Result<CacheEntry, SprayError> {
+        let key = Self::key(
+            source,
+            &file_loader::arguments_value(args_file, overrides)?,
+            &network.to_string(),
+        );
+        if let Some(entry) = self.get(&key) {
+            tracing::debug!(key = %key, "compile cache hit");
+            return Ok(entry);
+        }
+
+        let entry = Self::compile_fresh(source, args_file, overrides, network)?;
+        if let Err(e) = self.put(&key, &entry) {
+            tracing::warn!(key = %key, error = %e, "failed to cache compilation");
+        }
+        Ok(entry)
+    }
+
+    /// Compile `source` like [`compile`](Self::compile), without looking
+    /// up or storing the compilation
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the arguments cannot be loaded or the source
+    /// fails to compile.
+    pub fn compile_fresh(
+        source: &str,
+        args_file: Option<&Path>,
+        overrides: &[(String, String)],
+        network: Network,
+    ) -> Result<CacheEntry, SprayError> {
+        let arguments = file_loader::load_arguments_with(args_file, overrides)?;
+        let compiled = musk::Program::from_source(source)?.instantiate(arguments)?;
+        Ok(CacheEntry {
+            address: compiled.address(network.address_params()).to_string(),
+            output: CompiledOutput::from_compiled(&compiled, Some(source.to_string())),
+        })
+    }
+}
+
+/// Programs instantiated by this process, keyed like a [`CompileCache`]
+#[derive(Default)]
+pub struct ProgramCache {
+    programs: Mutex<HashMap<String, InstantiatedProgram>>,
+}
+
+impl ProgramCache {
+    /// Instantiate a program from a `.simf` source or a compiled `.json`
+    /// artifact with the arguments of `args_file` and `overrides`, reusing
+    /// an earlier instantiation of the same source and arguments
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the program or arguments cannot be loaded, an
+    /// artifact has no source, or the source fails to compile.
+    pub fn load(
+        &self,
+        path: &Path,
+        args_file: Option<&Path>,
+        overrides: &[(String, String)],
+    ) -> Result<InstantiatedProgram, SprayError> {
+        let source = if path.extension().and_then(|e| e.to_str()) == Some("simf") {
+            crate::include::expand(path)?
+        } else {
+            CompiledOutput::load(path)?.source.ok_or_else(|| {
+                SprayError::FileFormatError("Compiled program must include source field".into())
+            })?
+        };
+        self.instantiate(&source, args_file, overrides)
+    }
+
+    /// Instantiate `source` with the arguments of `args_file` and
+    /// `overrides`, reusing an earlier instantiation of the same source and
+    /// arguments
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the arguments cannot be loaded or the source
+    /// fails to compile.
+    pub fn instantiate(
+        &self,
+        source: &str,
+        args_file: Option<&Path>,
+        overrides: &[(String, String)],
+    ) -> Result<InstantiatedProgram, SprayError> {
+        // Instantiated programs do not depend on the network
+        let key = CompileCache::key(
+            source,
+            &file_loader::arguments_value(args_file, overrides)?,
+            "",
+        );
+        if let Some(program) = self.programs().get(&key) {
+            tracing::debug!(key = %key, "program cache hit");
+            return Ok(program.clone());
+        }
+
+        let arguments = file_loader::load_arguments_with(args_file, overrides)?;
+        let program = musk::Program::from_source(source)?.instantiate(arguments)?;
+        self.programs().insert(key, program.clone());
+        Ok(program)
+    }
+
+    fn programs(&self) -> std::sync::MutexGuard<'_, HashMap<String, InstantiatedProgram>> {
+        self.programs.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
@@ -0,0 +1,205 @@
+//! Custom chain parameters
+//!
+//! Private Elements federations run chains with their own address prefixes,
+//! policy asset and genesis block. Such a chain is described in the `[chain]`
+//! table of the node config file passed with `--config`, next to the RPC
+//! connection:
+//!
+//! ```toml
+//! [chain]
+//! genesis_hash = "<hex>"        # checked against the node's block 0
+//! policy_asset = "<asset id>"   # the chain's fee asset
+//! magic = "a1b2c3d4"            # network magic, telling chains apart
+//!
+//! [chain.address_params]
+//! p2pkh_prefix = 56
+//! p2sh_prefix = 58
+//! blinded_prefix = 12
+//! bech_hrp = "fed"
+//! blech_hrp = "fedc"
+//! ```
+//!
+//! Every key is optional; without a `[chain]` table the network's standard
+//! parameters apply. With one, addresses are derived with its address
+//! parameters, deployments default to its policy asset, and sighashes commit
+//! to its genesis hash.
+//!
+//! # Example
+//!
+//! ```
+//! use spray::chain::ChainParams;
+//!
+//! let chain = ChainParams::from_toml(
+//!     r#"
+//!     [chain]
+//!     magic = "a1b2c3d4"
+//!
+//!     [chain.address_params]
+//!     p2pkh_prefix = 56
+//!     p2sh_prefix = 58
+//!     blinded_prefix = 12
+//!     bech_hrp = "fed"
+//!     blech_hrp = "fedc"
+//!     "#,
+//! )
+//! .unwrap();
+//! assert!(chain.is_custom());
+//! assert_eq!(chain.address_params.unwrap().bech_hrp, "fed");
+//! assert_eq!(chain.magic, Some([0xa1, 0xb2, 0xc3, 0xd4]));
+//! ```
+
+use crate::error::SprayError;
+use crate::hex;
+use musk::elements::{AddressParams, AssetId, BlockHash};
+use serde::Deserialize;
+use std::path::Path;
+use std::str::FromStr;
+
+/// Parameters of a custom chain, from the `[chain]` table of a node config
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ChainParams {
+    /// Address parameters, if the chain has its own
+    pub address_params: Option<&'static AddressParams>,
+    /// Policy asset, if it differs from the node's default
+    pub policy_asset: Option<AssetId>,
+    /// Network magic
+    pub magic: Option<[u8; 4]>,
+    /// Genesis block hash
+    pub genesis_hash: Option<BlockHash>,
+}
+
+/// Raw `[chain]` table
+#[derive(Debug, Default, Deserialize)]
+struct ChainTable {
+    genesis_hash: Option<String>,
+    policy_asset: Option<String>,
+    magic: Option<String>,
+    address_params: Option<AddressParamsTable>,
+}
+
+/// Raw `[chain.address_params]` table
+#[derive(Debug, Deserialize)]
+struct AddressParamsTable {
+    p2pkh_prefix: u8,
+    p2sh_prefix: u8,
+    blinded_prefix: u8,
+    bech_hrp: String,
+    blech_hrp: String,
+}
+
+/// A node config file, of which only `[chain]` is read here
+#[derive(Debug, Deserialize)]
+struct NodeConfig {
+    #[serde(default)]
+    chain: ChainTable,
+}
+
+impl ChainParams {
+    /// Read the `[chain]` table of the node config file at `path`
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be read or its `[chain]` table is
+    /// invalid.
+    pub fn load(path: &Path) -> Result<Self, SprayError> {
+        let contents = std::fs::read_to_string(path)?;
+        Self::from_toml(&contents)
+            .map_err(|e| SprayError::ConfigError(format!("{}: {e}", path.display())))
+    }
+
+    /// Parse the `[chain]` table of a node config
+    ///
+    /// Address parameters are leaked, so that they can be used wherever the
+    /// standard networks' static parameters are; a process loads them once
+    /// per backend.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the config is not TOML or a value is invalid.
+    pub fn from_toml(contents: &str) -> Result<Self, SprayError> {
+        let config: NodeConfig =
+            toml::from_str(contents).map_err(|e| SprayError::ConfigError(e.to_string()))?;
+        let table = config.chain;
+
+        let genesis_hash = table
+            .genesis_hash
+            .map(|hash| {
+                BlockHash::from_str(&hash)
+                    .map_err(|e| SprayError::ParseError(format!("Invalid genesis_hash: {e}")))
+            })
+            .transpose()?;
+        let policy_asset = table
+            .policy_asset
+            .map(|asset| {
+                AssetId::from_str(&asset)
+                    .map_err(|e| SprayError::ParseError(format!("Invalid policy_asset: {e}")))
+            })
+            .transpose()?;
+        let magic = table.magic.map(|magic| parse_magic(&magic)).transpose()?;
+        let address_params = table.address_params.map(|params| {
+            let params: &'static AddressParams = Box::leak(Box::new(AddressParams {
+                p2pkh_prefix: params.p2pkh_prefix,
+                p2sh_prefix: params.p2sh_prefix,
+                blinded_prefix: params.blinded_prefix,
+                bech_hrp: Box::leak(params.bech_hrp.into_boxed_str()),
+                blech_hrp: Box::leak(params.blech_hrp.into_boxed_str()),
+            }));
+            params
+        });
+
+        Ok(Self {
+            address_params,
+            policy_asset,
+            magic,
+            genesis_hash,
+        })
+    }
+
+    /// Whether any parameter differs from the network's standard ones
+    #[must_use]
+    pub const fn is_custom(&self) -> bool {
+        self.address_params.is_some()
+            || self.policy_asset.is_some()
+            || self.magic.is_some()
+            || self.genesis_hash.is_some()
+    }
+
+    /// Check the node's genesis block against the configured one
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a genesis hash is configured and differs from
+    /// `actual`.
+    pub fn check_genesis(&self, actual: BlockHash) -> Result<(), SprayError> {
+        match self.genesis_hash {
+            Some(expected) if expected != actual => Err(SprayError::ConfigError(format!(
+                "Node is on a different chain: genesis block {actual}, expected {expected}"
+            ))),
+            _ => Ok(()),
+        }
+    }
+
+    /// A tag telling this chain apart from others on the same network, e.g.
+    /// for cache keys
+    #[must_use]
+    pub fn tag(&self) -> Option<String> {
+        self.magic.map(|magic| hex::encode(&magic)).or_else(|| {
+            self.address_params
+                .map(|params| params.bech_hrp.to_string())
+        })
+    }
+}
+
+/// Parse a network magic given as 8 hex digits
+fn parse_magic(magic: &str) -> Result<[u8; 4], SprayError> {
+    let invalid =
+        || SprayError::ParseError(format!("Invalid magic {magic}: expected 8 hex digits"));
+    if magic.len() != 8 || !magic.is_ascii() {
+        return Err(invalid());
+    }
+    let mut bytes = [0; 4];
+    for (i, byte) in bytes.iter_mut().enumerate() {
+        *byte = u8::from_str_radix(&magic[2 * i..2 * i + 2], 16).map_err(|_| invalid())?;
+    }
+    Ok(bytes)
+}
@@ -1,16 +1,58 @@
 //! `NodeClient` implementation for `ElementsD`
 
-use elementsd::bitcoind::bitcoincore_rpc::RpcApi;
-use elementsd::ElementsD;
-use musk::client::{ClientResult, NodeClient, Utxo};
-use musk::elements::{encode::deserialize, hex::FromHex, Address, BlockHash, Transaction, Txid};
-use std::str::FromStr;
+#[cfg(feature = "daemon")]
+use {
+    elementsd::bitcoind::bitcoincore_rpc::RpcApi,
+    elementsd::ElementsD,
+    musk::client::{ClientResult, NodeClient, Utxo},
+    musk::elements::{encode::deserialize, hex::FromHex, Address, BlockHash, Transaction, Txid},
+    std::str::FromStr,
+};
+
+/// Format a satoshi amount as an exact fixed-point BTC string (e.g. `"0.00012345"`)
+///
+/// Elements accepts amounts as decimal strings, which avoids the rounding
+/// that converting through `f64` can introduce.
+///
+/// # Example
+///
+/// ```
+/// use spray::client::format_btc_amount;
+///
+/// assert_eq!(format_btc_amount(1), "0.00000001");
+/// assert_eq!(format_btc_amount(123_456_789), "1.23456789");
+/// ```
+#[must_use]
+pub fn format_btc_amount(sats: u64) -> String {
+    format!("{}.{:08}", sats / 100_000_000, sats % 100_000_000)
+}
+
+/// Convert a BTC amount from an RPC response to satoshis
+///
+/// Rounds to the nearest satoshi, which is exact for every valid amount since
+/// the total supply fits well within `f64` precision.
+///
+/// # Example
+///
+/// ```
+/// use spray::client::btc_to_sats;
+///
+/// assert_eq!(btc_to_sats(0.0000454), 4_540);
+/// assert_eq!(btc_to_sats(1.23456789), 123_456_789);
+/// ```
+#[must_use]
+#[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+pub fn btc_to_sats(btc: f64) -> u64 {
+    (btc * 100_000_000.0).round() as u64
+}
 
 /// `NodeClient` implementation wrapping `ElementsD`
+#[cfg(feature = "daemon")]
 pub struct ElementsClient<'a> {
     daemon: &'a ElementsD,
 }
 
+#[cfg(feature = "daemon")]
 impl<'a> ElementsClient<'a> {
     #[must_use]
     pub const fn new(daemon: &'a ElementsD) -> Self {
@@ -18,12 +60,12 @@ impl<'a> ElementsClient<'a> {
     }
 }
 
+#[cfg(feature = "daemon")]
 impl NodeClient for ElementsClient<'_> {
     fn send_to_address(&self, addr: &Address, amount: u64) -> ClientResult<Txid> {
         let addr_str = addr.to_string();
-        // Convert satoshis to BTC (Elements uses BTC units)
-        #[allow(clippy::cast_precision_loss)]
-        let amount_btc = amount as f64 / 100_000_000.0;
+        // Elements uses BTC units; pass an exact decimal string
+        let amount_btc = format_btc_amount(amount);
 
         let txid_str = self
             .daemon
@@ -111,10 +153,19 @@ impl NodeClient for ElementsClient<'_> {
         Ok(hashes)
     }
 
-    fn get_utxos(&self, _address: &Address) -> ClientResult<Vec<Utxo>> {
-        // This is a simplified implementation
-        // In a real implementation, you'd use listunspent or similar
-        Ok(Vec::new())
+    fn get_utxos(&self, address: &Address) -> ClientResult<Vec<Utxo>> {
+        let call = |method: &str, params: &[serde_json::Value]| {
+            self.daemon
+                .client()
+                .call::<serde_json::Value>(method, params)
+                .map_err(|e| crate::error::SprayError::RpcError(e.to_string()))
+        };
+        let (holdings, _) = crate::holdings::fetch(call, address)
+            .map_err(|e| musk::ProgramError::IoError(std::io::Error::other(e.to_string())))?;
+        Ok(holdings
+            .iter()
+            .filter_map(|holding| holding.to_utxo(address.script_pubkey()))
+            .collect())
     }
 
     fn get_new_address(&self) -> ClientResult<Address> {
@@ -0,0 +1,88 @@
+//! Artifact command implementations
+
+use crate::error::SprayError;
+use crate::project::ProjectConfig;
+use crate::signing::{self, Keystore};
+use colored::Colorize;
+use std::path::Path;
+
+/// Execute the `artifact keygen` command
+///
+/// Generates a signing key named `name` in `keystore` and prints its public
+/// key, to be listed under `trusted_keys` in `spray.toml`.
+///
+/// # Errors
+///
+/// Returns an error if the key already exists or cannot be written.
+pub fn artifact_keygen_command(name: &str, keystore: &Path) -> Result<(), SprayError> {
+    let pubkey = Keystore::new(keystore).generate(name)?;
+    crate::status!("{}", format!("✓ Generated key '{name}'").green().bold());
+    crate::status!("  {} {}", "Keystore:".bold(), keystore.display());
+    crate::output::print_result(&format!("  {} {pubkey}", "Public key:".bold()));
+    Ok(())
+}
+
+/// Execute the `artifact sign` command
+///
+/// Signs `artifact` with the key named `key` from `keystore` and writes the
+/// detached signature next to it.
+///
+/// # Errors
+///
+/// Returns an error if the key or artifact cannot be read or the signature
+/// cannot be written.
+pub fn artifact_sign_command(
+    artifact: &Path,
+    key: &str,
+    keystore: &Path,
+) -> Result<(), SprayError> {
+    let secret_key = Keystore::new(keystore).secret_key(key)?;
+    let signature = signing::sign(&std::fs::read(artifact)?, &secret_key, key);
+    let sig_path = signing::signature_path(artifact);
+    signature.save(&sig_path)?;
+
+    crate::status!("{}", "✓ Artifact signed".green().bold());
+    crate::status!("  {} {}", "Artifact:".bold(), artifact.display());
+    crate::output::print_result(&format!("  {} {}", "Digest:".bold(), signature.digest));
+    crate::output::print_result(&format!(
+        "  {} {} ({key})",
+        "Signer:".bold(),
+        signature.pubkey
+    ));
+    crate::status!("  {} {}", "Signature:".bold(), sig_path.display());
+    Ok(())
+}
+
+/// Execute the `artifact verify` command
+///
+/// Verifies the detached signature of `artifact` against `trusted` public
+/// keys, or else the `trusted_keys` of the nearest `spray.toml`.
+///
+/// # Errors
+///
+/// Returns an error if the signature is missing or does not verify.
+pub fn artifact_verify_command(artifact: &Path, trusted: &[String]) -> Result<(), SprayError> {
+    let trusted = if trusted.is_empty() {
+        ProjectConfig::discover()?
+            .map(|(_, config)| config.artifacts.trusted_keys)
+            .unwrap_or_default()
+    } else {
+        trusted.to_vec()
+    };
+    if trusted.is_empty() {
+        return Err(SprayError::ConfigError(
+            "No trusted keys: pass --trusted or set trusted_keys in spray.toml".into(),
+        ));
+    }
+
+    let signature = signing::verify_artifact(artifact, &trusted)?;
+    crate::status!("{}", "✓ Signature valid".green().bold());
+    crate::output::print_result(&format!("  {} {}", "Digest:".bold(), signature.digest));
+    crate::output::print_result(&format!(
+        "  {} {} ({})",
+        "Signer:".bold(),
+        signature.pubkey,
+        signature.key
+    ));
+    Ok(())
+}
@@ -0,0 +1,122 @@
+//! Audit bundle command implementation
+
+use crate::audit::{AuditBundle, CostReport};
+use crate::commands::redeem::load_program;
+use crate::compiled::CompiledOutput;
+use crate::error::SprayError;
+use crate::file_loader;
+use crate::registry::{DeploymentRecord, Registry};
+use crate::verify::{self, SpendContext};
+use colored::Colorize;
+use std::path::{Path, PathBuf};
+
+/// Execute the audit-bundle command
+///
+/// Compiles the contract in `contract` and writes a tar archive to `out`
+/// (`<contract>-audit.tar` by default) holding its source, arguments,
+/// compiled artifact, CMR, a cost report and every deployment of it recorded
+/// in the registry at `registry_path`. With `witness`, the witness is
+/// included and verified locally for the cost report; with `test_report`, a
+/// report from `spray test --report` is included.
+///
+/// # Errors
+///
+/// Returns an error if the contract, witness, test report or registry cannot
+/// be loaded, or the archive cannot be written.
+pub fn audit_bundle_command(
+    contract: &Path,
+    args: Option<PathBuf>,
+    arg_overrides: &[(String, String)],
+    witness: Option<PathBuf>,
+    test_report: Option<PathBuf>,
+    registry_path: &Path,
+    out: Option<PathBuf>,
+) -> Result<(), SprayError> {
+    crate::status!("{}", "Creating audit bundle...".cyan().bold());
+    crate::status!();
+
+    let name = contract
+        .file_stem()
+        .and_then(|s| s.to_str())
+        .unwrap_or("contract")
+        .to_string();
+    let program = load_program(contract, args.as_deref(), arg_overrides)?;
+
+    let source = if contract.extension().and_then(|e| e.to_str()) == Some("simf") {
+        Some(crate::include::expand(contract)?)
+    } else {
+        CompiledOutput::load(contract)?.source
+    };
+    let compiled = CompiledOutput::from_compiled(&program, source.clone());
+    crate::status!("{} {}", "Contract:".dimmed(), contract.display());
+    crate::status!("  {} {}", "CMR:".bold(), compiled.cmr);
+
+    let mut bundle = AuditBundle::new(&name, &compiled.cmr);
+    if let Some(source) = source {
+        bundle.add(&format!("source/{name}.simf"), source.into_bytes());
+    }
+    let arguments = file_loader::arguments_value(args.as_deref(), arg_overrides)?;
+    bundle.add("arguments.json", serde_json::to_vec_pretty(&arguments)?);
+    bundle.add("compiled.json", serde_json::to_vec_pretty(&compiled)?);
+    bundle.add("cmr.txt", format!("{}\n", compiled.cmr).into_bytes());
+
+    let mut cost = CostReport {
+        program_size: compiled.program_size,
+        ..CostReport::default()
+    };
+    if let Some(witness_path) = &witness {
+        bundle.add("witness.json", witness_json(witness_path)?);
+        let context = SpendContext::synthetic(&program, verify::DEFAULT_AMOUNT);
+        let values = file_loader::load_witness(witness_path)?;
+        match verify::verify(&program, values, &context)?.result {
+            Ok(weight) => {
+                cost.spend_weight = Some(weight);
+                cost.spend_vsize = Some(weight.div_ceil(4));
+                cost.fee = Some(context.fee);
+            }
+            Err((kind, error)) => {
+                crate::status!("  {} {kind}: {error}", "Witness failed to verify:".yellow());
+                cost.error = Some(error);
+            }
+        }
+    }
+    bundle.add("cost.json", serde_json::to_vec_pretty(&cost)?);
+
+    if let Some(report) = &test_report {
+        bundle.add("test-report.json", std::fs::read(report)?);
+    }
+
+    let registry = Registry::load(registry_path)?;
+    let deployments: Vec<&DeploymentRecord> = registry
+        .records()
+        .iter()
+        .filter(|r| r.cmr == compiled.cmr)
+        .collect();
+    crate::status!("  {} {}", "Deployments:".bold(), deployments.len());
+    bundle.add("deployments.json", serde_json::to_vec_pretty(&deployments)?);
+
+    let out = out.unwrap_or_else(|| PathBuf::from(format!("{name}-audit.tar")));
+    bundle.write(&out)?;
+
+    crate::status!();
+    for entry in bundle.manifest().files {
+        crate::output::print_result(&format!(
+            "  {} {} ({} bytes)",
+            entry.sha256.dimmed(),
+            entry.path,
+            entry.size
+        ));
+    }
+    crate::status!();
+    crate::status!(
+        "{} {}",
+        "✓ Audit bundle written to".green().bold(),
+        out.display()
+    );
+    Ok(())
+}
+
+/// The witness file as JSON, converting TOML so the bundle is uniform
+fn witness_json(path: &Path) -> Result<Vec<u8>, SprayError> {
+    Ok(serde_json::to_vec_pretty(&file_loader::read_value(path)?)?)
+}
@@ -0,0 +1,74 @@
+//! Balance command implementation
+
+use crate::error::SprayError;
+use crate::holdings::{self, Balance};
+use crate::network::BackendConfig;
+use colored::Colorize;
+use musk::Network;
+use serde_json::json;
+
+/// Execute the balance command
+///
+/// Sums the confirmed and unconfirmed outputs paying to the contract named
+/// by `target`: an address, a CMR, or a compiled artifact file.
+///
+/// # Errors
+///
+/// Returns an error if the contract cannot be resolved or the node cannot
+/// be queried.
+pub fn balance_command(
+    target: &str,
+    network: Network,
+    config: &BackendConfig,
+) -> Result<(), SprayError> {
+    crate::status!("{}", "Checking contract balance...".cyan().bold());
+    crate::status!();
+
+    crate::status!("{} {network}", "Network:".dimmed());
+    let backend = config.create(network)?;
+    let address = holdings::resolve_address(target, backend.address_params())?;
+    crate::status!("{} {address}", "Address:".dimmed());
+
+    let (holdings, source) =
+        holdings::fetch(|method, params| backend.call(method, params), &address)?;
+    let balance = Balance::new(&holdings);
+    crate::status!("{} {source}", "Source:".dimmed());
+    crate::status!();
+
+    if balance.assets.is_empty() {
+        crate::status!("  {}", "(no explicit funds)".dimmed());
+    }
+    for (asset, amounts) in &balance.assets {
+        crate::status!("  {} {asset}", "Asset:".bold());
+        crate::status!("    {:12} {} sat", "Confirmed:", amounts.confirmed);
+        crate::status!("    {:12} {} sat", "Unconfirmed:", amounts.unconfirmed);
+        crate::output::print_result(&format!(
+            "{asset} {} {}",
+            amounts.confirmed, amounts.unconfirmed
+        ));
+    }
+    crate::status!();
+    crate::status!("{} {}", "Outputs:".dimmed(), balance.utxos);
+    if balance.confidential > 0 {
+        crate::status!(
+            "{}",
+            format!(
+                "{} confidential outputs are not included in the sums",
+                balance.confidential
+            )
+            .yellow()
+        );
+    }
+
+    crate::output::emit(
+        "balance.success",
+        true,
+        &json!({
+            "address": address.to_string(),
+            "source": source,
+            "assets": balance.assets,
+            "utxos": balance.utxos,
+            "confidential": balance.confidential,
+        }),
+    )
+}
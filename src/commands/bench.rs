@@ -0,0 +1,96 @@
+//! Bench command implementation
+
+use crate::bench::{bench_program, BenchResult};
+use crate::error::SprayError;
+use crate::manifest::TestSpec;
+use colored::Colorize;
+use serde_json::json;
+use std::path::{Path, PathBuf};
+
+/// Execute the bench command
+///
+/// Benchmarks every program in `files` over `iterations` iterations. Each
+/// program takes its arguments and witness from `args` and `witness` if
+/// given, which requires a single program, or else from its companion files
+/// (see [`TestSpec::for_program`]). With `report`, the results are also
+/// written there as JSON.
+///
+/// # Errors
+///
+/// Returns an error if `args` or `witness` is given for several programs, or
+/// a program fails to load, compile or satisfy.
+pub fn bench_command(
+    files: &[PathBuf],
+    args: Option<PathBuf>,
+    witness: Option<PathBuf>,
+    iterations: usize,
+    report: Option<PathBuf>,
+) -> Result<(), SprayError> {
+    if files.len() > 1 && (args.is_some() || witness.is_some()) {
+        return Err(SprayError::ConfigError(
+            "--args and --witness take a single program; name companion files after each program instead".into(),
+        ));
+    }
+
+    crate::status!("{}", "Benchmarking programs...".cyan().bold());
+    crate::status!("{} {iterations}", "Iterations:".dimmed());
+
+    let mut results = Vec::new();
+    for file in files {
+        let spec = TestSpec::for_program(file);
+        let args = args.clone().or(spec.args);
+        let witness = witness.clone().or(spec.witness);
+
+        crate::status!();
+        crate::status!("{} {}", "⏳".yellow(), file.display().to_string().bold());
+        let result = bench_program(file, args.as_deref(), witness.as_deref(), iterations)?;
+        print_result(&result);
+        results.push(result);
+    }
+
+    if let Some(path) = &report {
+        write_report(path, &results)?;
+        crate::status!();
+        crate::status!("{} {}", "Report written to:".dimmed(), path.display());
+    }
+
+    crate::output::emit(
+        "bench.success",
+        true,
+        &json!({
+            "iterations": iterations,
+            "programs": results,
+        }),
+    )
+}
+
+/// Print the measurements of one program
+fn print_result(result: &BenchResult) {
+    crate::status!("  {} {}", "Compile:".bold(), result.compile);
+    match &result.satisfy {
+        Some(satisfy) => crate::status!("  {} {satisfy}", "Satisfy:".bold()),
+        None => crate::status!("  {} {}", "Satisfy:".bold(), "no witness".dimmed()),
+    }
+    crate::status!("  {} {} bytes", "Size:".bold(), result.program_size);
+    if let (Some(satisfied), Some(witness)) = (result.satisfied_size, result.witness_size) {
+        crate::status!(
+            "  {} {satisfied} bytes pruned, {witness} bytes witness",
+            "Satisfied:".bold()
+        );
+    }
+    if let Some(budget) = &result.budget {
+        let line = format!("  {} {budget}", "Cost:".bold());
+        if budget.is_within() {
+            crate::status!("{line}");
+        } else {
+            crate::status!("{line} {}", "(over budget)".red());
+        }
+    }
+}
+
+/// Write `results` to `path` as JSON
+fn write_report(path: &Path, results: &[BenchResult]) -> Result<(), SprayError> {
+    let json = serde_json::to_string_pretty(results)?;
+    std::fs::write(path, json)?;
+    Ok(())
+}
@@ -1,10 +1,21 @@
 //! Compile command implementation
 
+use crate::cache::{CacheEntry, CompileCache};
+use crate::chain::ChainParams;
 use crate::compiled::CompiledOutput;
 use crate::error::SprayError;
 use crate::file_loader;
+use crate::hex;
+use crate::include;
+use crate::logging::elapsed_ms;
+use crate::messages;
 use colored::Colorize;
+use std::io::Read;
 use std::path::{Path, PathBuf};
+use std::time::Instant;
+
+/// Path that stands for standard input
+pub const STDIN: &str = "-";
 
 /// Output format for compiled programs
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -24,132 +35,271 @@ impl OutputFormat {
             _ => None,
         }
     }
+
+    /// File extension of artifacts in this format
+    #[must_use]
+    pub const fn extension(self) -> &'static str {
+        match self {
+            Self::Json => "json",
+            Self::Base64 => "base64",
+            Self::Hex => "hex",
+        }
+    }
+}
+
+/// Path of the artifact of `file` in `dir`, named after the program
+#[must_use]
+pub fn artifact_path(dir: &Path, file: &Path, format: OutputFormat) -> PathBuf {
+    let name = file.file_stem().unwrap_or(file.as_os_str());
+    dir.join(format!("{}.{}", name.to_string_lossy(), format.extension()))
+}
+
+/// Read program source from `file`, or from `stdin` if `file` is [`STDIN`],
+/// with its includes resolved (see [`crate::include`])
+///
+/// # Errors
+///
+/// Returns an error if the source or an included file cannot be read.
+pub fn read_source(file: &Path, mut stdin: impl Read) -> Result<String, SprayError> {
+    if file == Path::new(STDIN) {
+        let mut source = String::new();
+        stdin.read_to_string(&mut source)?;
+        include::expand_source(&source, &std::env::current_dir()?)
+    } else {
+        include::expand(file)
+    }
 }
 
 /// Execute the compile command
 ///
+/// If `file` is [`STDIN`], the source is read from standard input.
+/// `arg_overrides` are `KEY=VALUE` argument values merged over the arguments
+/// file.
+///
+/// Status is printed to stderr, so that stdout carries only the artifact in
+/// `output_format`. With `out`, the artifact is written to that file instead;
+/// without it, a program file is compiled to `<artifacts_dir>/<name>.<format>`
+/// if the `[defaults]` of `spray.toml` set an `artifacts_dir`.
+/// In JSON mode, stdout carries a document with the artifact, its CMR and
+/// address instead.
+///
+/// Unless `no_cache` is set, compilations without a witness are looked up in
+/// and stored to the [`CompileCache`].
+///
+/// With `config`, a node config whose `[chain]` table describes a custom
+/// chain, the address is derived with that chain's address parameters; the
+/// node itself is not contacted.
+///
 /// # Errors
 ///
 /// Returns an error if compilation fails or file operations fail.
-#[allow(clippy::too_many_lines)]
+#[allow(clippy::too_many_arguments, clippy::too_many_lines)]
 pub fn compile_command(
     file: &Path,
     args: Option<PathBuf>,
+    arg_overrides: &[(String, String)],
     witness: Option<PathBuf>,
     output_format: OutputFormat,
+    out: Option<PathBuf>,
+    no_cache: bool,
     network: musk::Network,
+    config: Option<PathBuf>,
 ) -> Result<(), SprayError> {
-    println!("{}", "Compiling Simplicity program...".cyan().bold());
-    println!();
+    let span = tracing::info_span!("compile", file = %file.display(), %network);
+    let _span = span.enter();
+    let start = Instant::now();
+
+    let out = match out {
+        None if file != Path::new(STDIN) => crate::project::defaults()?
+            .artifacts_dir
+            .map(|dir| artifact_path(&dir, file, output_format)),
+        out => out,
+    };
+
+    let chain = config
+        .as_deref()
+        .map(ChainParams::load)
+        .transpose()?
+        .unwrap_or_default();
+
+    crate::status!("{}", "Compiling Simplicity program...".cyan().bold());
+    crate::status!();
 
     // Load program
-    println!("{} {}", "Loading program from:".dimmed(), file.display());
-    let source = std::fs::read_to_string(file)?;
-    let program = musk::Program::from_source(&source)?;
+    if file == Path::new(STDIN) {
+        crate::status!("{} <stdin>", "Loading program from:".dimmed());
+    } else {
+        crate::status!("{} {}", "Loading program from:".dimmed(), file.display());
+    }
+    let source = read_source(file, std::io::stdin().lock())?;
 
     // Load arguments if provided
-    let arguments = if let Some(args_path) = args {
-        println!(
+    if let Some(args_path) = &args {
+        crate::status!(
             "{} {}",
             "Loading arguments from:".dimmed(),
             args_path.display()
         );
-        file_loader::load_arguments(&args_path)?
-    } else {
-        musk::Arguments::default()
-    };
+    }
+    for (key, value) in arg_overrides {
+        crate::status!("{} {key} = {value}", "Argument override:".dimmed());
+    }
+    let arguments = file_loader::load_arguments_with(args.as_deref(), arg_overrides)?;
 
-    // Compile program
-    println!("{}", "Compiling...".dimmed());
-    let compiled = program.instantiate(arguments)?;
+    // Programs compiled without a witness are cached by source and arguments
+    let cache = (!no_cache && witness.is_none()).then(CompileCache::default);
+    let key = CompileCache::key(
+        &source,
+        &file_loader::arguments_value(args.as_deref(), arg_overrides)?,
+        &chain
+            .tag()
+            .map_or_else(|| network.to_string(), |tag| format!("{network}:{tag}")),
+    );
+    let cached = cache.as_ref().and_then(|cache| cache.get(&key));
 
-    // Get CMR
-    let cmr = compiled.cmr();
-    let cmr_hex = hex::encode(cmr.as_ref());
+    let (output, address) = if let Some(entry) = cached {
+        crate::status!("{}", "Using cached compilation".dimmed());
+        tracing::debug!(key = %key, "compile cache hit");
+        (entry.output, entry.address)
+    } else {
+        // Compile program
+        crate::status!("{}", "Compiling...".dimmed());
+        let program = musk::Program::from_source(&source)?;
+        let compiled = program.instantiate(arguments)?;
 
-    // Get address for the network
-    let address = compiled.address(network.address_params());
+        // Get address for the network
+        let address = compiled
+            .address(
+                chain
+                    .address_params
+                    .unwrap_or_else(|| network.address_params()),
+            )
+            .to_string();
 
-    // Create output based on whether witness was provided
-    let output = if let Some(witness_path) = witness {
-        println!(
-            "{} {}",
-            "Loading witness from:".dimmed(),
-            witness_path.display()
-        );
-        let witness_values = file_loader::load_witness(&witness_path)?;
-        let satisfied = compiled.satisfy(witness_values)?;
-        CompiledOutput::from_satisfied(&satisfied, &compiled, Some(source))
-    } else {
-        CompiledOutput::from_compiled(&compiled, Some(source))
+        // Create output based on whether witness was provided
+        let output = if let Some(witness_path) = witness {
+            crate::status!(
+                "{} {}",
+                "Loading witness from:".dimmed(),
+                witness_path.display()
+            );
+            let witness_values = file_loader::load_witness(&witness_path)?;
+            let satisfied = compiled.satisfy(witness_values)?;
+            CompiledOutput::from_satisfied(&satisfied, &compiled, Some(source))
+        } else {
+            CompiledOutput::from_compiled(&compiled, Some(source))
+        };
+
+        if let Some(cache) = &cache {
+            let entry = CacheEntry {
+                address: address.clone(),
+                output: output.clone(),
+            };
+            if let Err(e) = cache.put(&key, &entry) {
+                eprintln!("{} {e}", "Warning: failed to cache compilation:".yellow());
+            }
+        }
+        (output, address)
     };
+    let cmr_hex = &output.cmr;
+    tracing::info!(
+        cmr = %cmr_hex,
+        %address,
+        program_size = output.program_size,
+        duration_ms = elapsed_ms(start),
+        "compiled"
+    );
 
-    println!();
-    println!("{}", "✓ Compilation successful!".green().bold());
-    println!();
+    crate::status!();
+    crate::status!(
+        "{}",
+        format!("✓ {}", messages::text("compile.success"))
+            .green()
+            .bold()
+    );
+    crate::status!();
 
     // Display basic info
-    println!("{}", "Program Information:".bold());
-    println!("  {} {}", "CMR:".bold(), cmr_hex);
-    println!("  {} {}", "Address:".bold(), address);
-    println!("  {} {} bytes", "Size:".bold(), output.program_size);
+    crate::status!("{}", "Program Information:".bold());
+    crate::status!("  {} {}", "CMR:".bold(), cmr_hex);
+    crate::status!("  {} {}", "Address:".bold(), address);
+    crate::status!("  {} {} bytes", "Size:".bold(), output.program_size);
 
     if let Some(ref witness) = output.witness {
         use base64::{engine::general_purpose::STANDARD, Engine};
         if let Ok(witness_bytes) = STANDARD.decode(witness) {
-            println!("  {} {} bytes", "Witness size:".bold(), witness_bytes.len());
+            crate::status!("  {} {} bytes", "Witness size:".bold(), witness_bytes.len());
         }
     }
 
-    println!();
+    crate::status!();
 
     // Output in requested format
-    match output_format {
-        OutputFormat::Json => {
-            let json = serde_json::to_string_pretty(&output)?;
-            println!("{json}");
+    let parts = render_artifact(&output, output_format)?;
+    if let Some(out) = &out {
+        let contents: String = parts
+            .iter()
+            .map(|(_, value)| format!("{value}\n"))
+            .collect();
+        if let Some(dir) = out.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+            std::fs::create_dir_all(dir)?;
+        }
+        std::fs::write(out, contents)?;
+        crate::status!("{} {}", "Output written to:".dimmed(), out.display());
+    } else if !crate::output::is_json() {
+        for (i, (label, value)) in parts.iter().enumerate() {
+            if let Some(label) = label {
+                if i > 0 {
+                    crate::status!();
+                }
+                crate::status!("{}", label.bold());
+            }
+            println!("{value}");
         }
+    }
+
+    crate::output::emit(
+        "compile.success",
+        true,
+        &serde_json::json!({
+            "cmr": output.cmr,
+            "address": address,
+            "program_size": output.program_size,
+            "out": out,
+            "artifact": output,
+        }),
+    )
+}
+
+/// Render `output` in `format` as labeled parts: the program and, for
+/// base64 and hex, the witness if there is one
+fn render_artifact(
+    output: &CompiledOutput,
+    format: OutputFormat,
+) -> Result<Vec<(Option<&'static str>, String)>, SprayError> {
+    use base64::{engine::general_purpose::STANDARD, Engine};
+
+    let mut parts = Vec::new();
+    match format {
+        OutputFormat::Json => parts.push((None, serde_json::to_string_pretty(output)?)),
         OutputFormat::Base64 => {
-            println!("{}", "Program (base64):".bold());
-            println!("{}", output.program);
-            if let Some(witness) = output.witness {
-                println!();
-                println!("{}", "Witness (base64):".bold());
-                println!("{witness}");
+            parts.push((Some("Program (base64):"), output.program.clone()));
+            if let Some(witness) = &output.witness {
+                parts.push((Some("Witness (base64):"), witness.clone()));
             }
         }
         OutputFormat::Hex => {
-            use base64::{engine::general_purpose::STANDARD, Engine};
             let program_bytes = STANDARD
                 .decode(&output.program)
                 .map_err(|e| SprayError::ParseError(format!("Failed to decode program: {e}")))?;
-            println!("{}", "Program (hex):".bold());
-            println!("{}", hex::encode(&program_bytes));
-            if let Some(witness) = output.witness {
-                let witness_bytes = STANDARD.decode(&witness).map_err(|e| {
+            parts.push((Some("Program (hex):"), hex::encode(&program_bytes)));
+            if let Some(witness) = &output.witness {
+                let witness_bytes = STANDARD.decode(witness).map_err(|e| {
                     SprayError::ParseError(format!("Failed to decode witness: {e}"))
                 })?;
-                println!();
-                println!("{}", "Witness (hex):".bold());
-                println!("{}", hex::encode(&witness_bytes));
+                parts.push((Some("Witness (hex):"), hex::encode(&witness_bytes)));
             }
         }
     }
-
-    Ok(())
-}
-
-// Add hex module
-#[doc(hidden)]
-mod hex {
-    use std::fmt::Write;
-
-    pub fn encode(bytes: &[u8]) -> String {
-        bytes
-            .iter()
-            .fold(String::with_capacity(bytes.len() * 2), |mut acc, b| {
-                let _ = write!(acc, "{b:02x}");
-                acc
-            })
-    }
+    Ok(parts)
 }
@@ -0,0 +1,192 @@
+//! Debug command implementation
+
+use crate::commands::redeem::load_program;
+use crate::commands::verify::spend_context;
+use crate::error::SprayError;
+use crate::file_loader;
+use crate::prompt::Prompt;
+use crate::sourcemap::SourceMap;
+use crate::trace::{Breakpoint, Resume, Trace, TraceStep, Tracer};
+use colored::Colorize;
+use std::io::{BufRead, Write};
+use std::path::{Path, PathBuf};
+
+/// Steps printed by the `p` debugger command without a count
+const DEFAULT_PRINT_STEPS: usize = 10;
+
+/// Execute the debug command
+///
+/// Satisfies the program in `program_file` with the witness in
+/// `witness_file` and executes it step by step on the bit machine, in the
+/// same spend context as `spray verify`. Every node is printed as it
+/// finishes executing (only jet calls with `jets_only`), with the inputs and
+/// outputs of jets, followed by where execution failed, if it did, with the
+/// source it maps back to.
+///
+/// With `breakpoints`, execution pauses after each call of a jet they name,
+/// showing the jet's input and output and where it is called in the source,
+/// and asks on stdin how to go on: `c` continues to the next breakpoint, `s`
+/// steps to the next node, `p [n]` prints the last `n` steps with their
+/// values and `f` finishes without pausing again.
+///
+/// Returns `false` if execution failed.
+///
+/// # Errors
+///
+/// Returns an error if the program, witness or UTXO description cannot be
+/// loaded, a breakpoint's source line calls no jet, the witness does not fit
+/// the program, or the program exceeds the bit machine's limits.
+#[allow(clippy::too_many_arguments)]
+pub fn debug_command(
+    program_file: &Path,
+    witness_file: &Path,
+    args: Option<PathBuf>,
+    utxo_file: Option<PathBuf>,
+    amount: u64,
+    genesis_hash: Option<String>,
+    lock_time: Option<u32>,
+    sequence: Option<u32>,
+    fee: u64,
+    jets_only: bool,
+    breakpoints: &[Breakpoint],
+) -> Result<bool, SprayError> {
+    crate::status!("{}", "Tracing program execution...".cyan().bold());
+    crate::status!();
+
+    crate::status!("{} {}", "Program:".dimmed(), program_file.display());
+    let program = load_program(program_file, args.as_deref(), &[])?;
+    crate::status!("{} {}", "Witness:".dimmed(), witness_file.display());
+    let witness = file_loader::load_witness(witness_file)?;
+    let context = spend_context(
+        &program,
+        utxo_file,
+        amount,
+        genesis_hash,
+        lock_time,
+        sequence,
+        fee,
+    )?;
+
+    let map = SourceMap::load(program_file).unwrap_or_default();
+    let mut tracer = Tracer::new().jets_only(jets_only);
+    for breakpoint in breakpoints {
+        for op in breakpoint.ops(&map)? {
+            crate::status!("{} {op} ({breakpoint})", "Breakpoint:".dimmed());
+            tracer = tracer.break_on(op);
+        }
+    }
+
+    let mut prompt = Prompt::new(std::io::stdin().lock(), crate::output::terminal());
+    let trace = tracer.trace_spend_with(&program, witness, &context, |steps| {
+        pause(&mut prompt, &map, steps)
+    })?;
+
+    crate::status!();
+    let width = trace.steps.len().to_string().len();
+    for (i, step) in trace.steps.iter().enumerate() {
+        let line = format!("{:>width$}  {step}", i + 1);
+        if step.failed {
+            crate::output::print_result(&line.red().bold().to_string());
+        } else if step.op.starts_with("jet_") {
+            crate::output::print_result(&line.cyan().to_string());
+        } else {
+            crate::output::print_result(&line.dimmed().to_string());
+        }
+    }
+
+    crate::status!();
+    crate::output::print_result(&format!(
+        "{} {} steps, {} jet calls",
+        "Executed:".bold(),
+        trace.steps.len(),
+        trace.jets().count()
+    ));
+    match (&trace.error, trace.failure()) {
+        (None, _) => {
+            crate::status!("{}", "✓ Program executed successfully".green().bold());
+            Ok(true)
+        }
+        (Some(error), failure) => {
+            crate::status!("{}", format!("✗ Execution failed: {error}").red().bold());
+            if let Some(step) = failure {
+                let at = if step.failed { "at" } else { "after" };
+                crate::status!("  {at} {step}");
+            }
+            print_source_location(program_file, &trace);
+            Ok(false)
+        }
+    }
+}
+
+/// Show the node execution paused at, the last of `steps`, and ask how to go
+/// on
+///
+/// The end of input finishes execution.
+fn pause<R: BufRead, W: Write>(
+    prompt: &mut Prompt<R, W>,
+    map: &SourceMap,
+    steps: &[TraceStep],
+) -> Resume {
+    let Some(step) = steps.last() else {
+        return Resume::Continue;
+    };
+    crate::say!();
+    crate::say!("{} {step}", "Paused at".yellow().bold());
+    if let Some(input) = &step.input {
+        crate::say!("  {} {input}", "input: ".dimmed());
+    }
+    if let Some(output) = &step.output {
+        crate::say!("  {} {output}", "output:".dimmed());
+    }
+    for site in map.calls(&step.op) {
+        crate::say!(
+            "  {} {}:{}:{}  {}",
+            "called at".dimmed(),
+            site.file.display(),
+            site.line,
+            site.column,
+            site.text.trim()
+        );
+    }
+
+    loop {
+        let Ok(answer) = prompt.ask("(c)ontinue, (s)tep, (p)rint [n], (f)inish", Some("c")) else {
+            return Resume::Finish;
+        };
+        let mut words = answer.split_whitespace();
+        match words.next().unwrap_or("c") {
+            "c" | "continue" => return Resume::Continue,
+            "s" | "step" => return Resume::Step,
+            "f" | "finish" => return Resume::Finish,
+            "p" | "print" => {
+                let count = words
+                    .next()
+                    .and_then(|n| n.parse().ok())
+                    .unwrap_or(DEFAULT_PRINT_STEPS);
+                for step in &steps[steps.len().saturating_sub(count)..] {
+                    crate::say!("  {step}");
+                }
+            }
+            other => crate::say!("{} {other}", "Unknown command:".yellow()),
+        }
+    }
+}
+
+/// Print the source a failed execution of the program in `program_file`
+/// maps back to, if it does
+pub(crate) fn print_source_location(program_file: &Path, trace: &Trace) {
+    let Some(snippet) = SourceMap::load(program_file)
+        .ok()
+        .and_then(|map| map.render(trace))
+    else {
+        return;
+    };
+    crate::status!();
+    for (i, line) in snippet.lines().enumerate() {
+        if i == 0 {
+            crate::status!("{}", line.red().bold());
+        } else {
+            crate::status!("{line}");
+        }
+    }
+}
@@ -0,0 +1,27 @@
+//! Decode command implementation
+
+use crate::error::SprayError;
+use crate::value::ValueType;
+use colored::Colorize;
+
+/// Execute the decode command
+///
+/// Renders a hex-encoded value according to its Simplicity type.
+///
+/// # Errors
+///
+/// Returns an error if the type cannot be parsed or the value is not valid
+/// hex or is too short for the type.
+pub fn decode_command(ty: &str, value: &str) -> Result<(), SprayError> {
+    let ty: ValueType = ty.parse()?;
+    let rendered = ty.format_hex(value)?;
+
+    crate::output::print_result(&format!(
+        "{} {ty} ({} bits)",
+        "Type:".bold(),
+        ty.bit_width()
+    ));
+    crate::output::print_result(&format!("{} {rendered}", "Value:".bold()));
+
+    Ok(())
+}
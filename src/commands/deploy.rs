@@ -1,33 +1,125 @@
 //! Deploy command implementation
 
+use crate::cache::CompileCache;
 use crate::compiled::CompiledOutput;
 use crate::error::SprayError;
-use crate::file_loader;
+use crate::hex;
+use crate::logging::elapsed_ms;
+use crate::messages;
+use crate::network::BackendConfig;
+use crate::precompiled::PrecompiledProgram;
+use crate::preset::Preset;
+use crate::project::ProjectConfig;
+use crate::registry::{self, DeploymentRecord, Registry};
+use crate::signing;
 use colored::Colorize;
 use musk::client::NodeClient;
 use musk::Network;
+use serde_json::json;
 use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::Instant;
 
 /// Execute the deploy command
 ///
+/// Every deployment is recorded in the registry at `registry_path`, along
+/// with the paths of the program and arguments files, and any
+/// `arg_overrides` (`KEY=VALUE` argument values merged over the arguments
+/// file). Deployments to an ephemeral node are not recorded, since their
+/// chain is gone when the command exits. When `resume` is set and an identical deployment (same CMR, amount, asset and
+/// network) is still unconfirmed in the mempool, no new funding transaction
+/// is broadcast and the existing one is reported instead.
+///
+/// Programs are compiled through the [`CompileCache`], unless `no_cache` is
+/// set or the project requires signed artifacts.
+///
+/// With `dry_run`, the funding transaction is built and signed by the wallet
+/// but neither broadcast nor recorded; its raw hex and fee are printed.
+/// Without `amount`, the network [`Preset`]'s funding amount is locked.
+///
+/// If the nearest `spray.toml` sets `require_signature`, `file` must have a
+/// valid detached signature by one of its `trusted_keys`.
+///
 /// # Errors
 ///
 /// Returns an error if deployment fails or file operations fail.
-#[allow(clippy::too_many_arguments)]
+#[allow(clippy::too_many_arguments, clippy::too_many_lines)]
 pub fn deploy_command(
     file: &Path,
     args: Option<PathBuf>,
+    arg_overrides: &[(String, String)],
     amount: Option<u64>,
     asset: Option<String>,
     network: Network,
-    config: Option<PathBuf>,
+    config: &BackendConfig,
+    registry_path: &Path,
+    resume: bool,
+    wait: bool,
+    dry_run: bool,
+    no_cache: bool,
 ) -> Result<(), SprayError> {
-    println!("{}", "Deploying Simplicity program...".cyan().bold());
-    println!();
+    let span = tracing::info_span!(
+        "deploy",
+        file = %file.display(),
+        %network,
+        cmr = tracing::field::Empty,
+        txid = tracing::field::Empty,
+    );
+    let _span = span.enter();
+    let start = Instant::now();
+
+    crate::status!("{}", "Deploying Simplicity program...".cyan().bold());
+    crate::status!();
+
+    // Enforce the project's artifact signing policy: a signed artifact's
+    // program bytes are deployed as they are
+    let mut signed = false;
+    if let Some((project_file, project)) = ProjectConfig::discover()? {
+        if project.artifacts.require_signature {
+            let signature = signing::verify_deployment(
+                file,
+                args.as_deref(),
+                arg_overrides,
+                &project.artifacts.trusted_keys,
+            )
+            .map_err(|e| {
+                SprayError::SignatureError(format!(
+                    "{} requires a signed artifact: {e}",
+                    project_file.display()
+                ))
+            })?;
+            crate::status!(
+                "{} {} ({})",
+                "✓ Artifact signed by".green(),
+                signature.pubkey,
+                signature.key
+            );
+            signed = true;
+        }
+    }
+
+    // Signed deployments never trust an entry they did not compile
+    let no_cache = no_cache || signed;
+    let compile = |source: &str| {
+        if no_cache {
+            CompileCache::compile_fresh(source, args.as_deref(), arg_overrides, network)
+        } else {
+            CompileCache::default().compile(source, args.as_deref(), arg_overrides, network)
+        }
+    };
 
     // Create network backend
-    println!("{} {network}", "Network:".dimmed());
-    let backend = crate::network::create_backend(network, config)?;
+    crate::status!("{} {network}", "Network:".dimmed());
+    let backend = config.create(network)?;
+    let preset = Preset::for_network(network)?;
+    // Deterministic funding only spends the policy asset
+    if asset.is_some() && backend.is_deterministic() {
+        return Err(SprayError::ConfigError(
+            "--deterministic cannot fund an issued asset; drop --asset or --deterministic".into(),
+        ));
+    }
+    // A custom chain's policy asset is funded explicitly
+    let asset = asset.or_else(|| backend.policy_asset().map(|asset| asset.to_string()));
 
     // Detect file type and compile if needed
     let ext = file
@@ -35,50 +127,50 @@ pub fn deploy_command(
         .and_then(|e| e.to_str())
         .ok_or_else(|| SprayError::FileFormatError("No file extension found".into()))?;
 
-    let compiled = match ext {
+    let address_params = backend.address_params();
+    let (address, cmr) = match ext {
         "simf" => {
             // Compile from source
-            println!("{} {}", "Compiling from source:".dimmed(), file.display());
-            let source = std::fs::read_to_string(file)?;
-            let program = musk::Program::from_source(&source)?;
+            crate::status!("{} {}", "Compiling from source:".dimmed(), file.display());
+            let source = crate::include::expand(file)?;
 
-            let arguments = if let Some(args_path) = args {
-                println!(
+            if let Some(args_path) = &args {
+                crate::status!(
                     "{} {}",
                     "Loading arguments from:".dimmed(),
                     args_path.display()
                 );
-                file_loader::load_arguments(&args_path)?
-            } else {
-                musk::Arguments::default()
-            };
+            }
+            for (key, value) in arg_overrides {
+                crate::status!("{} {key} = {value}", "Argument override:".dimmed());
+            }
 
-            println!("{}", "Compiling...".dimmed());
-            program.instantiate(arguments)?
+            crate::status!("{}", "Compiling...".dimmed());
+            let entry = compile(&source)?;
+            let program = PrecompiledProgram::from_output(&entry.output)?;
+            (program.address(address_params)?, program.cmr())
         }
         "json" => {
             // Load pre-compiled
-            println!(
+            crate::status!(
                 "{} {}",
                 "Loading pre-compiled program:".dimmed(),
                 file.display()
             );
-            let json_str = std::fs::read_to_string(file)?;
-            let output: CompiledOutput = serde_json::from_str(&json_str)?;
-
-            // For now, we need to recompile from source if it's available
-            if let Some(source) = output.source {
-                let program = musk::Program::from_source(&source)?;
-                let arguments = if let Some(args_path) = args {
-                    file_loader::load_arguments(&args_path)?
-                } else {
-                    musk::Arguments::default()
-                };
-                program.instantiate(arguments)?
+            let output = CompiledOutput::load(file)?;
+
+            // Recompile from source if it's available, else fund the
+            // address of the program bytes; signed bytes are never replaced
+            if let Some(source) = output.source.as_ref().filter(|_| !signed) {
+                let entry = compile(source)?;
+                let program = PrecompiledProgram::from_output(&entry.output)?;
+                (program.address(address_params)?, program.cmr())
             } else {
-                return Err(SprayError::FileFormatError(
-                    "Pre-compiled JSON must include source field for deployment".into(),
-                ));
+                if !signed {
+                    crate::status!("{}", "No source; using the program bytes".dimmed());
+                }
+                let program = PrecompiledProgram::from_output(&output)?;
+                (program.address(address_params)?, program.cmr())
             }
         }
         _ => {
@@ -89,50 +181,176 @@ pub fn deploy_command(
     };
 
     // Get program address
-    let address = compiled.address(backend.address_params());
-    println!();
-    println!("{}", "Program address:".bold());
-    println!("  {address}");
-    println!();
-
-    // Determine amount (default 1 BTC)
-    let amount_sats = amount.unwrap_or(100_000_000);
-    println!("{} {} sat", "Sending amount:".dimmed(), amount_sats);
-
-    // Send funds to program address
-    println!("{}", "Creating funding transaction...".dimmed());
-    let txid = backend
-        .send_to_address(&address, amount_sats)
-        .map_err(|e| SprayError::RpcError(e.to_string()))?;
-
-    // Get the transaction to find the vout
-    let tx = backend
+    crate::status!();
+    crate::status!("{}", "Program address:".bold());
+    crate::status!("  {address}");
+    crate::status!();
+
+    let amount_sats = amount.unwrap_or(preset.funding_amount);
+    crate::status!("{} {} sat", "Sending amount:".dimmed(), amount_sats);
+
+    if dry_run {
+        crate::status!("{}", "Building funding transaction...".dimmed());
+        let funding = backend.build_funding_transaction(&address, amount_sats, asset.as_deref())?;
+
+        crate::status!();
+        crate::status!("{}", messages::text("deploy.dry_run").yellow().bold());
+        crate::status!("  {} {} sat", "Estimated fee:".bold(), funding.fee);
+        crate::status!();
+        crate::status!("{}", "Raw transaction (hex):".dimmed());
+        crate::output::print_result(&funding.hex);
+        return crate::output::emit(
+            "deploy.dry_run",
+            true,
+            &json!({
+                "dry_run": true,
+                "address": address.to_string(),
+                "amount": amount_sats,
+                "fee": funding.fee,
+                "hex": funding.hex,
+            }),
+        );
+    }
+
+    let cmr = hex::encode(cmr.as_ref());
+    span.record("cmr", cmr.as_str());
+    let network_name = network.to_string();
+    let registry = Registry::load(registry_path)?;
+    let record = !backend.is_ephemeral();
+
+    // Look for an identical deployment that is still waiting to confirm
+    let mut pending = None;
+    if resume && record {
+        for record in registry.find_matching(&cmr, amount_sats, asset.as_deref(), &network_name) {
+            let txid = musk::Txid::from_str(&record.txid)
+                .map_err(|e| SprayError::ParseError(format!("Invalid txid in registry: {e}")))?;
+            if backend.in_mempool(&txid)? {
+                pending = Some((txid, record.vout));
+                break;
+            }
+        }
+    }
+
+    let resumed = pending.is_some();
+    let (txid, vout) = if let Some((txid, vout)) = pending {
+        crate::status!(
+            "{} {txid}:{vout}",
+            "Found identical unconfirmed deployment, resuming:".yellow()
+        );
+        (txid, vout)
+    } else {
+        // Send funds to program address
+        crate::status!("{}", "Creating funding transaction...".dimmed());
+        let height = backend.block_count().ok();
+        let txid = if let Some(asset_id) = &asset {
+            backend.send_asset_to_address(&address, amount_sats, asset_id)?
+        } else {
+            backend
+                .send_to_address(&address, amount_sats)
+                .map_err(|e| SprayError::RpcError(e.to_string()))?
+        };
+
+        // Get the transaction to find the vout
+        let tx = backend
+            .get_transaction(&txid)
+            .map_err(|e| SprayError::RpcError(e.to_string()))?;
+
+        // Find the output index
+        let script_pubkey = address.script_pubkey();
+        let vout = tx
+            .output
+            .iter()
+            .position(|output| output.script_pubkey == script_pubkey)
+            .ok_or_else(|| SprayError::TestError("Could not find output in transaction".into()))?;
+        #[allow(clippy::cast_possible_truncation)]
+        let vout = vout as u32;
+
+        if record {
+            let record = DeploymentRecord {
+                txid: txid.to_string(),
+                vout,
+                cmr,
+                network: network_name,
+                amount: amount_sats,
+                asset: asset.clone(),
+                timestamp: registry::now(),
+                artifact: Some(registry::record_path(file)),
+                args: args.as_deref().map(registry::record_path),
+                arg_overrides: arg_overrides.iter().cloned().collect(),
+                height,
+                ..Default::default()
+            };
+            Registry::update(registry_path, |registry| {
+                registry.add(record);
+                Ok(())
+            })?;
+        }
+
+        (txid, vout)
+    };
+
+    span.record("txid", tracing::field::display(txid));
+    tracing::info!(
+        vout,
+        amount = amount_sats,
+        resumed,
+        duration_ms = elapsed_ms(start),
+        "funded"
+    );
+
+    if wait {
+        crate::status!("{}", "Waiting for confirmation...".dimmed());
+        backend.wait_for_confirmations(&txid, preset.confirmations, preset.confirmation_timeout)?;
+    }
+
+    crate::status!();
+    crate::status!(
+        "{}",
+        format!("✓ {}", messages::text("deploy.success"))
+            .green()
+            .bold()
+    );
+    crate::status!();
+    crate::status!("{}", "Funding details:".bold());
+    crate::status!("  {} {txid}", "Txid:".bold());
+    crate::status!("  {} {vout}", "Vout:".bold());
+    crate::status!("  {} {amount_sats} sat", "Amount:".bold());
+    if let Some(url) = preset.tx_url(&txid.to_string()) {
+        crate::status!("  {} {url}", "Explorer:".bold());
+    }
+
+    // Report the asset actually locked in the contract output
+    let funded_asset = backend
         .get_transaction(&txid)
-        .map_err(|e| SprayError::RpcError(e.to_string()))?;
-
-    // Find the output index
-    let script_pubkey = address.script_pubkey();
-    let vout = tx
-        .output
-        .iter()
-        .position(|output| output.script_pubkey == script_pubkey)
-        .ok_or_else(|| SprayError::TestError("Could not find output in transaction".into()))?;
-
-    println!();
-    println!("{}", "✓ Deployment successful!".green().bold());
-    println!();
-    println!("{}", "Funding details:".bold());
-    println!("  {} {txid}", "Txid:".bold());
-    println!("  {} {vout}", "Vout:".bold());
-    println!("  {} {amount_sats} sat", "Amount:".bold());
-
-    if let Some(asset_id) = asset {
-        println!("  {} {asset_id}", "Asset:".bold());
+        .ok()
+        .and_then(|tx| tx.output.get(vout as usize).map(|o| o.asset));
+    let asset_id = match (funded_asset, asset) {
+        (Some(musk::elements::confidential::Asset::Explicit(id)), _) => Some(id.to_string()),
+        (_, asset) => asset,
+    };
+    if let Some(asset_id) = &asset_id {
+        crate::status!("  {} {asset_id}", "Asset:".bold());
     }
 
-    println!();
-    println!("{}", "To spend from this UTXO:".dimmed());
-    println!("  spray redeem {txid}:{vout} <witness.json>");
+    crate::status!();
+    crate::status!("{}", "To spend from this UTXO:".dimmed());
+    crate::status!("  spray redeem {txid}:{vout} <witness.json>");
+    crate::status!(
+        "{}",
+        "(the program is looked up in the registry; pass --compiled to override)".dimmed()
+    );
+    crate::output::print_result(&format!("{txid}:{vout}"));
 
-    Ok(())
+    crate::output::emit(
+        "deploy.success",
+        true,
+        &json!({
+            "address": address.to_string(),
+            "txid": txid.to_string(),
+            "vout": vout,
+            "amount": amount_sats,
+            "asset": asset_id,
+            "resumed": resumed,
+        }),
+    )
 }
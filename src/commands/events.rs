@@ -0,0 +1,98 @@
+//! Events command implementation
+
+use crate::commands::redeem::load_program;
+use crate::compiled::CompiledOutput;
+use crate::error::SprayError;
+use crate::events::{self, EventSchema};
+use crate::network::BackendConfig;
+use crate::registry::Registry;
+use crate::rescan;
+use colored::Colorize;
+use musk::Network;
+use std::path::{Path, PathBuf};
+
+/// Execute the events command
+///
+/// Scans the chain from `from_height`, or else from the earliest deployment
+/// of the program in `file` recorded in the registry at `registry_path`, and
+/// decodes the events its transactions carry according to the schema at
+/// `schema_path`. The events are printed as JSON, or written to `out`.
+///
+/// # Errors
+///
+/// Returns an error if the program or schema cannot be loaded, or the scan
+/// fails.
+#[allow(clippy::too_many_arguments)]
+pub fn events_command(
+    file: &Path,
+    args: Option<PathBuf>,
+    schema_path: &Path,
+    from_height: Option<u64>,
+    out: Option<PathBuf>,
+    network: Network,
+    config: &BackendConfig,
+    registry_path: &Path,
+) -> Result<(), SprayError> {
+    crate::status!("{}", "Scanning contract events...".cyan().bold());
+    crate::status!();
+
+    crate::status!("{} {network}", "Network:".dimmed());
+    let backend = config.create(network)?;
+
+    let compiled = load_program(file, args.as_deref(), &[])?;
+    let cmr = CompiledOutput::from_compiled(&compiled, None).cmr;
+    let address = compiled.address(backend.address_params());
+    crate::status!("  {} {}", "Address:".bold(), address.to_unconfidential());
+    crate::status!("  {} {cmr}", "CMR:".bold());
+
+    let schema = EventSchema::load(schema_path)?;
+    crate::status!(
+        "{} {} ({} event types)",
+        "Schema:".dimmed(),
+        schema_path.display(),
+        schema.events.len()
+    );
+
+    let registry = Registry::load(registry_path)?;
+    let start = from_height
+        .or_else(|| rescan::rescan_start(registry.records(), &cmr, &network.to_string()));
+    let start = if let Some(start) = start {
+        crate::status!("{} {start}", "Scanning from height:".dimmed());
+        start
+    } else {
+        crate::status!(
+            "{}",
+            "No deployment height recorded, scanning the whole chain".yellow()
+        );
+        0
+    };
+
+    let found = events::scan(
+        &backend,
+        &address.script_pubkey(),
+        &schema,
+        start,
+        |height, tip| {
+            if !crate::output::is_quiet() {
+                eprint!("\r  {} {height}/{tip}", "Scanned to".dimmed());
+            }
+        },
+    )?;
+    crate::status!();
+
+    crate::status!();
+    crate::status!(
+        "{}",
+        format!("✓ Found {} events", found.len()).green().bold()
+    );
+
+    let json = serde_json::to_string_pretty(&found)?;
+    if let Some(out) = out {
+        std::fs::write(&out, json)?;
+        crate::status!("{} {}", "Events written to:".dimmed(), out.display());
+    } else {
+        crate::status!();
+        crate::output::print_result(&json);
+    }
+    Ok(())
+}
@@ -0,0 +1,68 @@
+//! Examples command implementation
+
+use crate::commands::manifest_command;
+use crate::error::SprayError;
+use crate::eta::DEFAULT_SLOW_FACTOR;
+use crate::gallery::{self, EXAMPLES};
+use crate::network::BackendConfig;
+use colored::Colorize;
+
+/// List the bundled examples
+pub fn examples_list_command() {
+    let width = EXAMPLES
+        .iter()
+        .map(|example| example.name.len())
+        .max()
+        .unwrap_or_default();
+    for example in EXAMPLES {
+        println!(
+            "{}  {}",
+            format!("{:width$}", example.name).bold(),
+            example.description
+        );
+    }
+}
+
+/// Print the source of the example `name`
+///
+/// # Errors
+///
+/// Returns an error if there is no such example.
+pub fn examples_show_command(name: &str) -> Result<(), SprayError> {
+    let example = gallery::find(name)?;
+    crate::status!("{} {}", "Example:".dimmed(), example.description);
+    print!("{}", example.source);
+    Ok(())
+}
+
+/// Run the example spend of `name` on a regtest backend created with the
+/// settings of `backend`, an ephemeral node by default
+///
+/// Returns `true` if the spend passed.
+///
+/// # Errors
+///
+/// Returns an error if there is no such example, its files cannot be
+/// written, or the daemon cannot be started.
+pub fn examples_run_command(name: &str, backend: &BackendConfig) -> Result<bool, SprayError> {
+    let example = gallery::find(name)?;
+    let dir = std::env::temp_dir().join(format!(
+        "spray-example-{}-{}",
+        example.name,
+        std::process::id()
+    ));
+    let result = example.write(&dir).and_then(|manifest| {
+        manifest_command(
+            &manifest,
+            false,
+            None,
+            None,
+            false,
+            None,
+            DEFAULT_SLOW_FACTOR,
+            backend,
+        )
+    });
+    let _ = std::fs::remove_dir_all(&dir);
+    result
+}
@@ -0,0 +1,133 @@
+//! Forensics command implementation
+
+use crate::commands::redeem::load_program;
+use crate::error::SprayError;
+use crate::forensics::{find_input, parse_key, SpendAnalysis};
+use crate::network::BackendConfig;
+use colored::Colorize;
+use musk::client::NodeClient;
+use musk::elements::Txid;
+use musk::Network;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+/// Execute the forensics command
+///
+/// Fetches the spending transaction `txid` and the outputs it spent,
+/// recomputes the sighash its contract input signed, and reports which of
+/// `keys` (`NAME=PUBKEY` or bare public keys) made each signature in the
+/// witness. The contract input is the first one spending the program in
+/// `contract`, unless `input` is given. With `out`, the analysis is also
+/// written there as JSON.
+///
+/// # Errors
+///
+/// Returns an error if a key or the txid is invalid, the transactions cannot
+/// be fetched, no input spends the contract, or the input is not a
+/// Simplicity spend.
+#[allow(clippy::too_many_arguments)]
+pub fn forensics_command(
+    txid: &str,
+    contract: &Path,
+    args: Option<PathBuf>,
+    input: Option<u32>,
+    keys: &[String],
+    out: Option<PathBuf>,
+    network: Network,
+    config: &BackendConfig,
+) -> Result<(), SprayError> {
+    let keys = keys
+        .iter()
+        .map(|key| parse_key(key))
+        .collect::<Result<Vec<_>, _>>()?;
+    let txid = Txid::from_str(txid)
+        .map_err(|e| SprayError::InvalidUtxoRef(format!("Invalid txid: {e}")))?;
+    let program = load_program(contract, args.as_deref(), &[])?;
+    let mut backend = config.create(network)?;
+
+    crate::status!("{}", "Analyzing spend...".cyan().bold());
+    let tx = backend
+        .get_transaction(&txid)
+        .map_err(|e| SprayError::RpcError(e.to_string()))?;
+    let input_index = match input {
+        Some(index) => index,
+        None => find_input(&tx, program.cmr()).ok_or_else(|| {
+            SprayError::TestError(format!(
+                "No input of {txid} spends the program with CMR {}",
+                program.cmr()
+            ))
+        })?,
+    };
+
+    // The sighash commits to the outputs spent by every input
+    let prevouts = tx
+        .input
+        .iter()
+        .map(|input| {
+            let outpoint = input.previous_output;
+            let funding = backend
+                .get_transaction(&outpoint.txid)
+                .map_err(|e| SprayError::RpcError(e.to_string()))?;
+            funding
+                .output
+                .get(outpoint.vout as usize)
+                .cloned()
+                .ok_or_else(|| {
+                    SprayError::InvalidUtxoRef(format!(
+                        "Vout {} not found in {}",
+                        outpoint.vout, outpoint.txid
+                    ))
+                })
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    let genesis_hash = backend.genesis_hash()?;
+
+    let analysis = SpendAnalysis::new(&tx, &prevouts, input_index, genesis_hash, &keys)?;
+    if analysis.cmr != program.cmr().to_string() {
+        crate::status!(
+            "{} input {input_index} spends CMR {}, not the contract's {}",
+            "⚠".yellow(),
+            analysis.cmr,
+            program.cmr()
+        );
+    }
+
+    crate::status!();
+    crate::output::print_result(&format!("{} {}", "Transaction:".bold(), analysis.txid));
+    crate::output::print_result(&format!("{} {}", "Input:".bold(), analysis.input_index));
+    crate::output::print_result(&format!("{} {}", "CMR:".bold(), analysis.cmr));
+    crate::output::print_result(&format!(
+        "{} {}",
+        "Genesis hash:".bold(),
+        analysis.genesis_hash
+    ));
+    crate::output::print_result(&format!("{} {}", "Sighash:".bold(), analysis.sighash));
+
+    crate::status!();
+    if analysis.signatures.is_empty() {
+        crate::output::print_result(&format!("{}", "No signatures in the witness".yellow()));
+    }
+    for signature in &analysis.signatures {
+        match &signature.signer {
+            Some(signer) => crate::output::print_result(&format!(
+                "  {} witness %{} signed by {}",
+                "✓".green(),
+                signature.node,
+                signer.bold()
+            )),
+            None => crate::output::print_result(&format!(
+                "  {} witness %{} matches no declared key",
+                "?".yellow(),
+                signature.node
+            )),
+        }
+        crate::output::print_result(&format!("    {}", signature.signature.dimmed()));
+    }
+
+    if let Some(path) = out {
+        std::fs::write(&path, serde_json::to_string_pretty(&analysis)?)?;
+        crate::status!();
+        crate::status!("{} {}", "Analysis written to:".dimmed(), path.display());
+    }
+    Ok(())
+}
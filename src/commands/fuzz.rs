@@ -0,0 +1,206 @@
+//! Fuzz command implementation
+
+use crate::commands::redeem::load_program;
+use crate::env::{TestEnv, TestEnvBuilder};
+use crate::error::SprayError;
+use crate::file_loader;
+use crate::fuzz::{FuzzReport, Mutator, Outcome};
+use crate::test::{FailureStage, TestCase, TestResult};
+use crate::value::ValueType;
+use colored::Colorize;
+use musk::{InstantiatedProgram, WitnessValues};
+use serde_json::{Map, Value};
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+
+/// Execute the fuzz command
+///
+/// Checks that the contract in `contract` accepts the witness in `witness`
+/// on an ephemeral regtest daemon, then tries up to `iterations` mutants of
+/// it (see [`crate::fuzz`]), each against a fresh UTXO, and prints the
+/// mutants that were accepted. The mutations are drawn with `seed`, or a
+/// random seed which is printed. With `deterministic`, the daemon runs in
+/// [deterministic mode](crate::deterministic) and the default seed is the
+/// fixed one. With `report`, the [`FuzzReport`] is also written there as
+/// JSON. Returns whether every mutant was rejected.
+///
+/// The witness must not depend on the sighash.
+///
+/// # Errors
+///
+/// Returns an error if the contract or witness cannot be loaded, the
+/// daemon cannot be started, a UTXO cannot be funded, the witness has no
+/// values to mutate, or the original witness is rejected.
+pub fn fuzz_command(
+    contract: &Path,
+    args: Option<PathBuf>,
+    witness: &Path,
+    iterations: usize,
+    seed: Option<u64>,
+    report_path: Option<PathBuf>,
+    deterministic: bool,
+) -> Result<bool, SprayError> {
+    crate::status!("{}", "Fuzzing witness...".cyan().bold());
+    crate::status!();
+
+    let program = load_program(contract, args.as_deref(), &[])?;
+    let document = file_loader::read_value(witness)?;
+    let original = witness_map(&document)?.clone();
+    let types: HashMap<String, ValueType> = crate::compiled::witness_types(&program)
+        .into_iter()
+        .filter_map(|(name, ty)| ty.parse().ok().map(|ty| (name, ty)))
+        .collect();
+
+    let seed = seed.unwrap_or_else(|| crate::deterministic::seed(deterministic));
+    crate::status!("{} {iterations}", "Iterations:".dimmed());
+    crate::status!("{} {seed}", "Seed:".dimmed());
+    crate::status!("{}", "Starting regtest daemon...".dimmed());
+    let env = TestEnvBuilder::new().deterministic(deterministic).build()?;
+
+    if let Outcome::Rejected { stage, reason } = attempt(&env, &program, &document, &original)? {
+        return Err(SprayError::TestError(format!(
+            "The original witness is rejected ({stage}): {reason}"
+        )));
+    }
+    crate::status!("{} original witness accepted", "✓".green());
+    crate::status!();
+
+    let mut report = FuzzReport::new(seed);
+    let mut mutator = Mutator::new(seed);
+    let mut seen = HashSet::new();
+    for _ in 0..iterations {
+        let mutant = mutator
+            .mutate(&original, &types)
+            .ok_or_else(|| SprayError::TestError("The witness has no values to mutate".into()))?;
+        if !seen.insert((mutant.witness.clone(), mutant.value.to_string())) {
+            continue;
+        }
+
+        let outcome = attempt(&env, &program, &document, &mutant.apply(&original))?;
+        match &outcome {
+            Outcome::Rejected { stage, .. } => {
+                crate::output::print_result(&format!(
+                    "  {} {mutant} {}",
+                    "✓".green(),
+                    format!("({stage})").dimmed()
+                ));
+            }
+            Outcome::Accepted => crate::output::print_result(&format!(
+                "  {} {mutant} {}",
+                "✗".red(),
+                "accepted".red()
+            )),
+        }
+        report.add(mutant, &outcome);
+    }
+
+    print_summary(&report);
+    if let Some(path) = report_path {
+        std::fs::write(&path, serde_json::to_string_pretty(&report)?)?;
+        crate::status!("{} {}", "Report written to:".dimmed(), path.display());
+    }
+
+    Ok(report.passed())
+}
+
+/// The witness values of a witness file, from its `witness` table if it has
+/// one
+fn witness_map(document: &Value) -> Result<&Map<String, Value>, SprayError> {
+    document
+        .get("witness")
+        .filter(|section| section.is_object())
+        .unwrap_or(document)
+        .as_object()
+        .ok_or_else(|| SprayError::FileFormatError("Expected a table of name/value pairs".into()))
+}
+
+/// Spend a fresh UTXO of `program` with `witness` in place of the values of
+/// `document`
+///
+/// # Errors
+///
+/// Returns an error if the UTXO cannot be funded or confirmed. Rejections
+/// are returned as [`Outcome::Rejected`].
+fn attempt(
+    env: &TestEnv,
+    program: &InstantiatedProgram,
+    document: &Value,
+    witness: &Map<String, Value>,
+) -> Result<Outcome, SprayError> {
+    let mut document = document.clone();
+    if document.get("witness").is_some_and(Value::is_object) {
+        document["witness"] = Value::Object(witness.clone());
+    } else {
+        document = Value::Object(witness.clone());
+    }
+    let witness_values: WitnessValues = match serde_json::from_value(document) {
+        Ok(values) => values,
+        Err(e) => {
+            return Ok(Outcome::Rejected {
+                stage: FailureStage::Satisfaction,
+                reason: e.to_string(),
+            })
+        }
+    };
+
+    let mut test = TestCase::new(env, program.clone())
+        .name("fuzz")
+        .witness(move |_| witness_values.clone());
+    test.create_utxo()?;
+    env.generate(1)?;
+
+    Ok(match test.run_staged() {
+        Ok(TestResult::Success { .. }) => Outcome::Accepted,
+        Ok(TestResult::Failure { error }) => Outcome::Rejected {
+            stage: FailureStage::Satisfaction,
+            reason: error,
+        },
+        Err((FailureStage::Funding, e)) => return Err(e),
+        Err((stage, e)) => Outcome::Rejected {
+            stage,
+            reason: e.to_string(),
+        },
+    })
+}
+
+/// Print rejections by stage and the accepted mutants
+fn print_summary(report: &FuzzReport) {
+    crate::status!();
+    if report.passed() {
+        crate::status!(
+            "{}",
+            format!("✓ All {} mutants rejected", report.mutants)
+                .green()
+                .bold()
+        );
+    } else {
+        crate::status!(
+            "{}",
+            format!(
+                "✗ {} of {} mutants accepted",
+                report.accepted.len(),
+                report.mutants
+            )
+            .red()
+            .bold()
+        );
+    }
+    for (stage, n) in &report.rejected {
+        crate::output::print_result(&format!("  {} {n}", format!("Rejected at {stage}:").bold()));
+    }
+    if !report.passed() {
+        crate::status!();
+        crate::status!("{}", "Accepted mutants:".bold());
+        for mutant in &report.accepted {
+            crate::output::print_result(&format!("  {mutant}"));
+        }
+        crate::status!(
+            "{}",
+            format!(
+                "Rerun with --seed {} to reproduce; every mutant above should have been rejected",
+                report.seed
+            )
+            .dimmed()
+        );
+    }
+}
@@ -0,0 +1,135 @@
+//! Gc command implementation
+
+use crate::cache::DEFAULT_CACHE_DIR;
+use crate::error::SprayError;
+use crate::gc::{self, format_size, GcConfig, GcKind, GcPolicy};
+use crate::project::ProjectConfig;
+use colored::Colorize;
+use std::path::PathBuf;
+use std::time::{Duration, SystemTime};
+
+/// Execute the gc command
+///
+/// Prunes the compilation cache, the report directories of the nearest
+/// `spray.toml` and `report_dirs`, and the data directories of test
+/// environments, by the `[gc]` policy of `spray.toml`. `max_age_days` and
+/// `max_size_mb` override the policy. With `dry_run`, nothing is removed.
+///
+/// # Errors
+///
+/// Returns an error if the project file cannot be loaded or a directory
+/// cannot be read. Entries that cannot be removed are reported, not
+/// returned.
+pub fn gc_command(
+    max_age_days: Option<u64>,
+    max_size_mb: Option<u64>,
+    report_dirs: Vec<PathBuf>,
+    dry_run: bool,
+) -> Result<(), SprayError> {
+    crate::status!("{}", "Collecting garbage...".cyan().bold());
+    crate::status!();
+
+    let (project_dir, mut config) = match ProjectConfig::discover()? {
+        Some((path, project)) => (
+            path.parent().map(PathBuf::from).unwrap_or_default(),
+            project.gc,
+        ),
+        None => (PathBuf::new(), GcConfig::default()),
+    };
+    config.max_age_days = max_age_days.or(config.max_age_days);
+    config.max_size_mb = max_size_mb.or(config.max_size_mb);
+    let policy = GcPolicy::from_config(&config);
+    if let Some(max_age) = policy.max_age {
+        crate::status!(
+            "{} {} days",
+            "Max age:".dimmed(),
+            max_age.as_secs() / (24 * 60 * 60)
+        );
+    }
+    if let Some(max_size) = policy.max_size {
+        crate::status!(
+            "{} {} per kind",
+            "Max size:".dimmed(),
+            format_size(max_size)
+        );
+    }
+
+    let mut entries = gc::scan(&PathBuf::from(DEFAULT_CACHE_DIR), GcKind::Cache)?;
+    for dir in config
+        .report_dirs
+        .iter()
+        .map(|dir| project_dir.join(dir))
+        .chain(report_dirs)
+    {
+        entries.extend(gc::scan(&dir, GcKind::Report)?);
+    }
+    entries.extend(gc::scan_datadirs(&std::env::temp_dir())?);
+
+    let total: u64 = entries.iter().map(|entry| entry.size).sum();
+    crate::status!(
+        "{} {} entries, {}",
+        "Found:".dimmed(),
+        entries.len(),
+        format_size(total)
+    );
+    crate::status!();
+
+    let now = SystemTime::now();
+    let selected = policy.select(&entries, now);
+    let mut freed = 0;
+    for entry in &selected {
+        let age = now.duration_since(entry.modified).unwrap_or_default();
+        let why = match entry.owner_alive {
+            Some(false) => "owner exited".to_string(),
+            _ => format!("{} old", format_age(age)),
+        };
+        let line = format!(
+            "  {:>8}  {:>9}  {}  {}",
+            entry.kind.to_string(),
+            format_size(entry.size),
+            entry.path.display(),
+            format!("({why})").dimmed()
+        );
+        if dry_run {
+            crate::output::print_result(&line);
+            freed += entry.size;
+            continue;
+        }
+        match gc::remove(entry) {
+            Ok(()) => {
+                crate::output::print_result(&line);
+                freed += entry.size;
+            }
+            Err(e) => eprintln!(
+                "  {} {}: {e}",
+                "Failed to remove".red(),
+                entry.path.display()
+            ),
+        }
+    }
+
+    if selected.is_empty() {
+        crate::status!("{}", "Nothing to collect".green());
+    } else {
+        crate::status!();
+        let verb = if dry_run { "Would free" } else { "Freed" };
+        crate::status!(
+            "{} {} {} from {} entries",
+            "✓".green().bold(),
+            verb,
+            format_size(freed).bold(),
+            selected.len()
+        );
+    }
+    Ok(())
+}
+
+/// Format an age in whole days, or hours below a day
+fn format_age(age: Duration) -> String {
+    let hours = age.as_secs() / (60 * 60);
+    if hours < 24 {
+        format!("{hours}h")
+    } else {
+        format!("{}d", hours / 24)
+    }
+}
@@ -0,0 +1,90 @@
+//! Import command implementation
+
+use crate::commands::redeem::load_program;
+use crate::compiled::CompiledOutput;
+use crate::error::SprayError;
+use crate::network::BackendConfig;
+use crate::registry::Registry;
+use crate::rescan;
+use colored::Colorize;
+use musk::Network;
+use std::path::{Path, PathBuf};
+
+/// Execute the import command
+///
+/// Imports the address of the program in `file` into the node's wallet as
+/// watch-only, then rescans from `from_height`, or else from the earliest
+/// deployment of the program recorded in the registry at `registry_path`.
+/// Without either, the whole chain is rescanned.
+///
+/// # Errors
+///
+/// Returns an error if the program cannot be loaded, or the import or
+/// rescan fails.
+#[allow(clippy::too_many_arguments)]
+pub fn import_command(
+    file: &Path,
+    args: Option<PathBuf>,
+    from_height: Option<u64>,
+    chunk_size: u64,
+    no_rescan: bool,
+    network: Network,
+    config: &BackendConfig,
+    registry_path: &Path,
+) -> Result<(), SprayError> {
+    crate::status!("{}", "Importing contract address...".cyan().bold());
+    crate::status!();
+
+    crate::status!("{} {network}", "Network:".dimmed());
+    let backend = config.create(network)?;
+
+    let compiled = load_program(file, args.as_deref(), &[])?;
+    let cmr = CompiledOutput::from_compiled(&compiled, None).cmr;
+    let address = compiled
+        .address(backend.address_params())
+        .to_unconfidential();
+    crate::status!("  {} {address}", "Address:".bold());
+    crate::status!("  {} {cmr}", "CMR:".bold());
+
+    rescan::import_address(&backend, &address, &format!("spray:{cmr}"))?;
+    crate::status!("{}", "✓ Address imported".green());
+
+    if no_rescan {
+        crate::status!(
+            "{}",
+            "Skipping rescan: existing UTXOs will not be found".yellow()
+        );
+        return Ok(());
+    }
+
+    let registry = Registry::load(registry_path)?;
+    let start = from_height
+        .or_else(|| rescan::rescan_start(registry.records(), &cmr, &network.to_string()));
+    let start = if let Some(start) = start {
+        crate::status!("{} {start}", "Rescanning from height:".dimmed());
+        start
+    } else {
+        crate::status!(
+            "{}",
+            "No deployment height recorded, rescanning the whole chain".yellow()
+        );
+        0
+    };
+
+    rescan::rescan(&backend, start, chunk_size, |height, tip| {
+        let done = height.saturating_sub(start) + 1;
+        let total = tip.saturating_sub(start) + 1;
+        if !crate::output::is_quiet() {
+            eprint!(
+                "\r  {} {height}/{tip} ({}%)",
+                "Rescanned to".dimmed(),
+                done * 100 / total
+            );
+        }
+    })?;
+    crate::status!();
+
+    crate::status!();
+    crate::status!("{}", "✓ Rescan complete".green().bold());
+    Ok(())
+}
@@ -57,8 +57,8 @@ password = "password"
 ///
 /// Returns an error if file/directory operations fail.
 pub fn init_command(force: bool) -> Result<(), SprayError> {
-    println!("{}", "Initializing Simplicity project...".cyan().bold());
-    println!();
+    crate::status!("{}", "Initializing Simplicity project...".cyan().bold());
+    crate::status!();
 
     let musk_dir = Path::new("musk");
     let musk_conf = Path::new("musk.conf");
@@ -66,9 +66,9 @@ pub fn init_command(force: bool) -> Result<(), SprayError> {
     // Check if musk directory exists
     if musk_dir.exists() {
         if force {
-            println!("{} {}", "Directory exists:".yellow(), musk_dir.display());
+            crate::status!("{} {}", "Directory exists:".yellow(), musk_dir.display());
         } else {
-            println!(
+            crate::status!(
                 "{} {} {}",
                 "✓".green(),
                 "Directory already exists:".dimmed(),
@@ -77,7 +77,7 @@ pub fn init_command(force: bool) -> Result<(), SprayError> {
         }
     } else {
         fs::create_dir(musk_dir)?;
-        println!(
+        crate::status!(
             "{} {} {}",
             "✓".green(),
             "Created directory:".dimmed(),
@@ -87,20 +87,20 @@ pub fn init_command(force: bool) -> Result<(), SprayError> {
 
     // Check if musk.conf exists
     if musk_conf.exists() && !force {
-        println!(
+        crate::status!(
             "{} {} {}",
             "✓".green(),
             "Config already exists:".dimmed(),
             musk_conf.display()
         );
-        println!();
-        println!("{}", "Use --force to overwrite existing musk.conf".yellow());
+        crate::status!();
+        crate::status!("{}", "Use --force to overwrite existing musk.conf".yellow());
     } else {
         if musk_conf.exists() {
-            println!("{} {}", "Overwriting:".yellow(), musk_conf.display());
+            crate::status!("{} {}", "Overwriting:".yellow(), musk_conf.display());
         }
         fs::write(musk_conf, MUSK_CONF_TEMPLATE)?;
-        println!(
+        crate::status!(
             "{} {} {}",
             "✓".green(),
             "Created config:".dimmed(),
@@ -108,20 +108,20 @@ pub fn init_command(force: bool) -> Result<(), SprayError> {
         );
     }
 
-    println!();
-    println!("{}", "✓ Project initialized!".green().bold());
-    println!();
-    println!("{}", "Next steps:".bold());
-    println!(
+    crate::status!();
+    crate::status!("{}", "✓ Project initialized!".green().bold());
+    crate::status!();
+    crate::status!("{}", "Next steps:".bold());
+    crate::status!(
         "  1. Edit {} with your node credentials",
         "musk.conf".cyan()
     );
-    println!(
+    crate::status!(
         "  2. Add your {} files to the {} directory",
         ".simf".cyan(),
         "musk/".cyan()
     );
-    println!("  3. Run {} to test your programs", "spray test".cyan());
+    crate::status!("  3. Run {} to test your programs", "spray test".cyan());
 
     Ok(())
 }
@@ -0,0 +1,128 @@
+//! Inspect command implementation
+
+use crate::compiled::{CompiledOutput, ADDRESS_NETWORKS};
+use crate::error::SprayError;
+use crate::interop;
+use colored::Colorize;
+use std::path::Path;
+
+/// Execute the inspect command
+///
+/// `input` is a compiled artifact file (spray, hal-simplicity or simc
+/// output), a file holding a bare program, or a bare program given as base64
+/// or hex. With `show_source`, the embedded source is printed in full; with
+/// `disasm`, the decoded program nodes are printed as well.
+///
+/// # Errors
+///
+/// Returns an error if the input cannot be read or decoded, or an embedded
+/// source fails to compile.
+pub fn inspect_command(input: &str, show_source: bool, disasm: bool) -> Result<(), SprayError> {
+    let path = Path::new(input);
+    let (origin, mut compiled) = if path.is_file() {
+        let contents = std::fs::read_to_string(path)?;
+        let compiled = match interop::normalize(&contents) {
+            Ok(artifact) => artifact.compiled,
+            Err(_) => CompiledOutput::from_program_text(&contents)?,
+        };
+        (path.display().to_string(), compiled)
+    } else {
+        (
+            "program given on the command line".to_string(),
+            CompiledOutput::from_program_text(input)?,
+        )
+    };
+
+    // Recover the CMR of a bare program by decoding it
+    let disassembly = compiled.disassemble();
+    if compiled.cmr.is_empty() {
+        if let Some(root) = disassembly.as_ref().ok().and_then(|d| d.root()) {
+            compiled.cmr.clone_from(&root.cmr);
+            compiled = compiled.with_addresses();
+        }
+    }
+
+    crate::status!("{} {origin}", "Artifact:".bold());
+    crate::status!();
+
+    if compiled.cmr.is_empty() {
+        crate::output::print_result(&format!(
+            "  {} {}",
+            "CMR:".bold(),
+            "unknown (program does not decode)".yellow()
+        ));
+    } else {
+        crate::output::print_result(&format!("  {} {}", "CMR:".bold(), compiled.cmr));
+    }
+    crate::output::print_result(&format!(
+        "  {} {} bytes",
+        "Program size:".bold(),
+        compiled.program_size
+    ));
+    crate::output::print_result(&format!(
+        "  {} {}",
+        "Witness attached:".bold(),
+        match compiled.decode_witness() {
+            Ok(witness) if !witness.is_empty() => format!("yes ({} bytes)", witness.len()),
+            _ => "no".to_string(),
+        }
+    ));
+
+    crate::status!();
+    crate::status!("{}", "Witness types:".bold());
+    if compiled.witness_types.is_empty() {
+        crate::output::print_result(&format!("  {}", "(none declared)".dimmed()));
+    } else {
+        let mut types: Vec<_> = compiled.witness_types.iter().collect();
+        types.sort();
+        let width = types.iter().map(|(name, _)| name.len()).max().unwrap_or(0);
+        for (name, ty) in types {
+            crate::output::print_result(&format!("  {name:width$}  {ty}"));
+        }
+    }
+
+    crate::status!();
+    crate::status!("{}", "Addresses:".bold());
+    if compiled.cmr.is_empty() {
+        crate::output::print_result(&format!("  {}", "(CMR unknown)".dimmed()));
+    } else {
+        for network in ADDRESS_NETWORKS {
+            if let Some(params) = interop::address_params(network) {
+                let address = interop::derive_address(&compiled.cmr, params)?;
+                crate::output::print_result(&format!(
+                    "  {:8} {address}",
+                    format!("{network}:").dimmed()
+                ));
+            }
+        }
+        crate::output::print_result(&format!(
+            "  {:8} {}",
+            "script:".dimmed(),
+            compiled.script_pubkey
+        ));
+    }
+
+    crate::status!();
+    crate::status!("{}", "Source:".bold());
+    match &compiled.source {
+        Some(source) if show_source => crate::output::print_result(source),
+        Some(source) => crate::output::print_result(&format!(
+            "  {} ({} lines; pass --source to print it)",
+            "embedded".green(),
+            source.lines().count()
+        )),
+        None => crate::output::print_result(&format!("  {}", "(not embedded)".dimmed())),
+    }
+
+    if disasm {
+        let disassembly = disassembly?;
+        crate::status!();
+        crate::status!("{}", "Disassembly:".bold());
+        crate::output::print_result(disassembly.to_string().trim_end());
+        crate::status!();
+        crate::status!("{}", "Tree:".bold());
+        crate::output::print_result(disassembly.tree().trim_end());
+    }
+
+    Ok(())
+}
@@ -0,0 +1,114 @@
+//! Interop command implementation
+
+use crate::compiled::CompiledOutput;
+use crate::error::SprayError;
+use crate::file_loader;
+use crate::interop::{self, Check};
+use colored::Colorize;
+use musk::Network;
+use std::path::{Path, PathBuf};
+
+/// Execute the interop command
+///
+/// Normalizes an artifact produced by another toolchain and compares its
+/// CMR, program encoding and addresses against spray's computation. With
+/// `source`, the program is compiled by spray (with `args`) as the reference;
+/// spray's own taproot derivation is then also checked against musk's.
+///
+/// Returns `false` if any check failed.
+///
+/// # Errors
+///
+/// Returns an error if the artifact cannot be read or normalized, or the
+/// source fails to compile.
+pub fn interop_command(
+    artifact_file: &Path,
+    source: Option<PathBuf>,
+    args: Option<PathBuf>,
+    out: Option<PathBuf>,
+) -> Result<bool, SprayError> {
+    crate::status!("{}", "Checking artifact compatibility...".cyan().bold());
+    crate::status!();
+
+    let contents = std::fs::read_to_string(artifact_file)?;
+    let artifact = interop::normalize(&contents)?;
+    crate::status!(
+        "{} {} ({})",
+        "Artifact:".dimmed(),
+        artifact_file.display(),
+        artifact.format
+    );
+
+    let mut checks = Vec::new();
+    let reference = if let Some(source_path) = source {
+        crate::status!(
+            "{} {}",
+            "Compiling reference:".dimmed(),
+            source_path.display()
+        );
+        let source = crate::include::expand(&source_path)?;
+        let arguments = args.map_or_else(
+            || Ok(musk::Arguments::default()),
+            |path| file_loader::load_arguments(&path),
+        )?;
+        let compiled = musk::Program::from_source(&source)?.instantiate(arguments)?;
+        let reference = CompiledOutput::from_compiled(&compiled, Some(source));
+
+        // spray's own derivation must agree with musk before judging others
+        let params = Network::Regtest.address_params();
+        checks.push(Check {
+            name: "taproot derivation".into(),
+            spray: interop::derive_address(&reference.cmr, params)?.to_string(),
+            artifact: compiled.address(params).to_unconfidential().to_string(),
+        });
+
+        Some(reference)
+    } else {
+        None
+    };
+
+    checks.extend(artifact.check(reference.as_ref())?);
+
+    crate::status!();
+    if checks.is_empty() {
+        crate::status!(
+            "{}",
+            "Nothing to compare: pass --source to check the CMR and program".yellow()
+        );
+    }
+    for check in &checks {
+        if check.passed() {
+            crate::output::print_result(&format!("  {} {}", "✓".green(), check.name));
+        } else {
+            crate::output::print_result(&format!("  {} {}", "✗".red(), check.name.bold()));
+            crate::output::print_result(&format!("    {} {}", "spray:   ".dimmed(), check.spray));
+            crate::output::print_result(&format!(
+                "    {} {}",
+                "artifact:".dimmed(),
+                check.artifact
+            ));
+        }
+    }
+
+    if let Some(out_path) = out {
+        let mut compiled = artifact.compiled;
+        if let Some(reference) = &reference {
+            compiled.source.clone_from(&reference.source);
+            if compiled.cmr.is_empty() {
+                compiled.cmr.clone_from(&reference.cmr);
+            }
+        }
+        std::fs::write(&out_path, serde_json::to_string_pretty(&compiled)?)?;
+        crate::status!();
+        crate::status!("{} {}", "Normalized artifact:".dimmed(), out_path.display());
+    }
+
+    let failed = checks.iter().filter(|c| !c.passed()).count();
+    crate::status!();
+    if failed == 0 {
+        crate::status!("{}", "✓ Artifact is compatible".green().bold());
+    } else {
+        crate::status!("{}", format!("✗ {failed} check(s) failed").red().bold());
+    }
+    Ok(failed == 0)
+}
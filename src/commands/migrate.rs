@@ -0,0 +1,201 @@
+//! Migrate-funds command implementation
+
+use crate::commands::redeem::parse_utxo_ref;
+use crate::compiled::CompiledOutput;
+use crate::error::SprayError;
+use crate::file_loader;
+use crate::hex;
+use crate::network::BackendConfig;
+use crate::registry::{self, DeploymentRecord, Registry};
+use crate::sweep::Sweep;
+use colored::Colorize;
+use musk::client::{NodeClient, Utxo};
+use musk::elements::confidential;
+use musk::Network;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+/// Execute the migrate-funds command
+///
+/// Sweeps every unspent UTXO recorded in the registry for the contract in
+/// `from_file` into the address of the contract in `to_file` with a single
+/// [`Sweep`] transaction, and records its outputs as new deployments of the
+/// new contract.
+///
+/// Each input is satisfied with the witness file resolved against that
+/// input's sighash, so entries asking to be signed (see
+/// [`SigningWitness`](crate::witness::SigningWitness)) are signed per input.
+///
+/// # Errors
+///
+/// Returns an error if the artifacts cannot be loaded, no unspent UTXOs are
+/// found, or the sweep fails to build or broadcast.
+#[allow(clippy::too_many_arguments, clippy::too_many_lines)]
+pub fn migrate_funds_command(
+    from_file: &Path,
+    from_args: Option<PathBuf>,
+    to_file: &Path,
+    to_args: Option<PathBuf>,
+    witness_file: &Path,
+    fee: u64,
+    network: Network,
+    config: &BackendConfig,
+    registry_path: &Path,
+) -> Result<(), SprayError> {
+    crate::status!("{}", "Migrating funds between contracts...".cyan().bold());
+    crate::status!();
+
+    let load_args = |path: Option<&Path>| {
+        path.map_or_else(
+            || Ok(musk::Arguments::default()),
+            file_loader::load_arguments,
+        )
+    };
+
+    crate::status!("{} {}", "Old contract:".dimmed(), from_file.display());
+    let old = CompiledOutput::load(from_file)?.instantiate(load_args(from_args.as_deref())?)?;
+    crate::status!("{} {}", "New contract:".dimmed(), to_file.display());
+    let new = CompiledOutput::load(to_file)?.instantiate(load_args(to_args.as_deref())?)?;
+
+    crate::status!(
+        "{} {}",
+        "Loading witness from:".dimmed(),
+        witness_file.display()
+    );
+    let witness = file_loader::load_signing_witness(Some(witness_file), &[], &HashMap::new())?;
+
+    crate::status!("{} {network}", "Network:".dimmed());
+    let mut backend = config.create(network)?;
+    let genesis_hash = backend.genesis_hash()?;
+
+    let old_cmr = hex::encode(old.cmr().as_ref());
+    let new_cmr = hex::encode(new.cmr().as_ref());
+    let network_name = network.to_string();
+    let new_address = new.address(backend.address_params());
+    let old_script = old.address(backend.address_params()).script_pubkey();
+
+    let registry = Registry::load(registry_path)?;
+    let candidates: Vec<(String, u32)> = registry
+        .find_by_cmr(&old_cmr, &network_name)
+        .map(|r| (r.txid.clone(), r.vout))
+        .collect();
+
+    // Keep only outpoints that are still unspent
+    let mut utxos = Vec::new();
+    for (txid_str, vout) in candidates {
+        let (txid, vout) = parse_utxo_ref(&format!("{txid_str}:{vout}"))?;
+        let unspent = backend.call("gettxout", &[txid_str.into(), vout.into()])?;
+        if unspent.is_null() {
+            continue;
+        }
+
+        let tx = backend
+            .get_transaction(&txid)
+            .map_err(|e| SprayError::RpcError(e.to_string()))?;
+        let output = tx.output.get(vout as usize).ok_or_else(|| {
+            SprayError::InvalidUtxoRef(format!("Vout {vout} not found in transaction"))
+        })?;
+
+        if output.script_pubkey != old_script {
+            continue;
+        }
+
+        let confidential::Value::Explicit(amount) = output.value else {
+            return Err(SprayError::TestError("Non-explicit value in UTXO".into()));
+        };
+
+        utxos.push(Utxo {
+            txid,
+            vout,
+            amount,
+            script_pubkey: output.script_pubkey.clone(),
+            asset: output.asset,
+        });
+    }
+
+    if utxos.is_empty() {
+        return Err(SprayError::TestError(format!(
+            "No unspent UTXOs for contract {old_cmr} found in {}",
+            registry.path().display()
+        )));
+    }
+
+    crate::status!();
+    crate::status!(
+        "{} {} UTXO(s) -> {new_address}",
+        "Sweeping".bold(),
+        utxos.len()
+    );
+
+    // Lease every outpoint first, so that no input is spent by another run
+    // while the sweep is built
+    let run_id = registry::run_id();
+    let _leases = utxos
+        .iter()
+        .map(|utxo| {
+            registry.lease(
+                &utxo.txid.to_string(),
+                utxo.vout,
+                &run_id,
+                registry::DEFAULT_LEASE_TTL,
+            )
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    let swept: Vec<String> = utxos
+        .iter()
+        .map(|utxo| format!("{}:{}", utxo.txid, utxo.vout))
+        .collect();
+
+    let sweep =
+        Sweep::new(old, utxos, new_address.script_pubkey(), fee)?.genesis_hash(genesis_hash);
+    let tx = sweep.finalize(&witness)?;
+    let height = backend.block_count().ok();
+    let txid = backend
+        .broadcast(&tx)
+        .map_err(|e| SprayError::RpcError(e.to_string()))?;
+
+    for outpoint in &swept {
+        crate::status!("  {} {outpoint}", "✓".green());
+    }
+    let mut records = Vec::new();
+    for (vout, (asset, amount)) in sweep.outputs().iter().enumerate() {
+        #[allow(clippy::cast_possible_truncation)]
+        let vout = vout as u32;
+        crate::status!(
+            "  {} {txid}:{vout} ({amount} sat of {asset})",
+            "->".dimmed()
+        );
+        crate::output::print_result(&format!("{txid}:{vout}"));
+        records.push(DeploymentRecord {
+            txid: txid.to_string(),
+            vout,
+            cmr: new_cmr.clone(),
+            network: network_name.clone(),
+            amount: *amount,
+            asset: Some(asset.to_string()),
+            timestamp: registry::now(),
+            migrated_from: Some(swept.join(",")),
+            artifact: Some(registry::record_path(to_file)),
+            args: to_args.as_deref().map(registry::record_path),
+            height,
+            ..Default::default()
+        });
+    }
+
+    Registry::update(registry_path, |registry| {
+        for record in records {
+            registry.add(record);
+        }
+        Ok(())
+    })?;
+
+    crate::status!();
+    crate::status!("{}", "✓ Migration successful!".green().bold());
+    crate::status!(
+        "  {} {}",
+        "Recorded in:".dimmed(),
+        registry.path().display()
+    );
+
+    Ok(())
+}
@@ -1,11 +1,75 @@
 //! Command implementations for spray CLI
 
+pub mod artifact;
+pub mod audit;
+pub mod balance;
+pub mod bench;
 pub mod compile;
+pub mod debug;
+pub mod decode;
 pub mod deploy;
+pub mod events;
+#[cfg(feature = "examples")]
+pub mod examples;
+pub mod forensics;
+pub mod fuzz;
+pub mod gc;
+pub mod import;
 pub mod init;
+pub mod inspect;
+pub mod interop;
+pub mod migrate;
+pub mod monitor;
+pub mod new;
+pub mod policy;
 pub mod redeem;
+pub mod scaffold;
+pub mod selftest;
+pub mod serve;
+pub mod sighash;
+pub mod soak;
+pub mod status;
+pub mod stress;
+pub mod suite;
+pub mod test;
+pub mod tui;
+pub mod utxo;
+pub mod verify;
+pub mod watch;
 
+pub use artifact::{artifact_keygen_command, artifact_sign_command, artifact_verify_command};
+pub use audit::audit_bundle_command;
+pub use balance::balance_command;
+pub use bench::bench_command;
 pub use compile::compile_command;
+pub use debug::debug_command;
+pub use decode::decode_command;
 pub use deploy::deploy_command;
+pub use events::events_command;
+#[cfg(feature = "examples")]
+pub use examples::{examples_list_command, examples_run_command, examples_show_command};
+pub use forensics::forensics_command;
+pub use fuzz::fuzz_command;
+pub use gc::gc_command;
+pub use import::import_command;
 pub use init::init_command;
-pub use redeem::{parse_utxo_ref, redeem_command};
+pub use inspect::inspect_command;
+pub use interop::interop_command;
+pub use migrate::migrate_funds_command;
+pub use monitor::monitor_command;
+pub use new::new_command;
+pub use policy::compare_policy_command;
+pub use redeem::{parse_utxo_ref, redeem_command, redeem_interactive_command};
+pub use scaffold::scaffold_command;
+pub use selftest::selftest_command;
+pub use serve::serve_command;
+pub use sighash::sighash_command;
+pub use soak::soak_command;
+pub use status::status_command;
+pub use stress::stress_command;
+pub use suite::{manifest_command, programs_command};
+pub use test::{test_watch_command, WatchOptions};
+pub use tui::tui_command;
+pub use utxo::{utxo_export_command, utxos_command};
+pub use verify::verify_command;
+pub use watch::watch_command;
@@ -0,0 +1,197 @@
+//! Monitor command implementation
+
+use crate::alerts::{post_webhook, Alert, AlertRules};
+use crate::client::btc_to_sats;
+use crate::commands::redeem::load_program;
+use crate::error::SprayError;
+use crate::monitor::{Monitor, WatchEvent};
+use crate::network::{BackendConfig, NetworkBackend};
+use colored::Colorize;
+use musk::elements::Address;
+use musk::Network;
+use serde_json::json;
+use std::path::Path;
+use std::str::FromStr;
+use std::time::Duration;
+
+/// Execute the monitor command
+///
+/// Watches the contracts of the rules file at `rules_path` and evaluates its
+/// rules against every event. Monitoring starts from the contracts' current
+/// unspent outputs and follows new blocks, or replays the chain from
+/// `from_height`. The chain tip is polled every `interval`; with `once`, the
+/// command returns when it reaches the tip instead.
+///
+/// Returns the exit code of the rules that fired, once a block raises alerts
+/// of rules with an exit code.
+///
+/// # Errors
+///
+/// Returns an error if the rules or a contract cannot be loaded, or the node
+/// cannot be queried. Failing webhooks are reported, not returned.
+pub fn monitor_command(
+    rules_path: &Path,
+    from_height: Option<u64>,
+    interval: Duration,
+    once: bool,
+    network: Network,
+    config: &BackendConfig,
+) -> Result<Option<i32>, SprayError> {
+    crate::status!("{}", "Monitoring contracts...".cyan().bold());
+    crate::status!();
+
+    let rules = AlertRules::load(rules_path)?;
+    crate::status!("{} {network}", "Network:".dimmed());
+    let backend = config.create(network)?;
+
+    let mut contracts = Vec::new();
+    for contract in &rules.contracts {
+        let script = match (&contract.file, &contract.address) {
+            (Some(file), _) => load_program(file, contract.args.as_deref(), &[])?
+                .address(backend.address_params())
+                .script_pubkey(),
+            (None, Some(address)) => Address::from_str(address)
+                .map_err(|e| SprayError::ParseError(format!("Invalid address {address}: {e}")))?
+                .script_pubkey(),
+            (None, None) => {
+                return Err(SprayError::ConfigError(format!(
+                    "Contract '{}' needs a file or an address",
+                    contract.name
+                )))
+            }
+        };
+        contracts.push((contract.name.clone(), script));
+    }
+    let mut monitor = Monitor::new(contracts);
+    crate::status!(
+        "{} {} contracts, {} rules",
+        "Watching:".dimmed(),
+        rules.contracts.len(),
+        rules.rules.len()
+    );
+
+    let mut next = match from_height {
+        Some(height) => height,
+        None => {
+            seed_unspent(&backend, &mut monitor)?;
+            backend.block_count()? + 1
+        }
+    };
+    for (name, _) in monitor.contracts() {
+        crate::status!(
+            "  {} {} sat in {} outputs",
+            format!("{name}:").bold(),
+            monitor.balance(name),
+            monitor.utxo_count(name)
+        );
+    }
+    crate::status!("{} {next}", "Following from height:".dimmed());
+    crate::status!();
+
+    loop {
+        let tip = backend.block_count()?;
+        while next <= tip {
+            let hash = backend.call("getblockhash", &[next.into()])?;
+            let block = backend.call("getblock", &[hash, 2.into()])?;
+            let mut alerts = Vec::new();
+            for event in monitor.process_block(&block, next)? {
+                print_event(&event);
+                alerts.extend(rules.evaluate(&event));
+            }
+            for alert in &alerts {
+                raise(&rules, alert);
+            }
+            if let Some(code) = rules.exit_code(&alerts) {
+                return Ok(Some(code));
+            }
+            next += 1;
+        }
+        if once {
+            return Ok(None);
+        }
+        std::thread::sleep(interval);
+    }
+}
+
+/// Record the contracts' current unspent outputs with `scantxoutset`
+fn seed_unspent(backend: &NetworkBackend, monitor: &mut Monitor) -> Result<(), SprayError> {
+    let contracts: Vec<(String, String)> = monitor
+        .contracts()
+        .map(|(name, script)| (name.to_string(), script.to_string()))
+        .collect();
+    let descriptors: Vec<_> = contracts
+        .iter()
+        .map(|(_, script)| json!(format!("raw({script})")))
+        .collect();
+    let scan = backend.call("scantxoutset", &["start".into(), descriptors.into()])?;
+
+    for unspent in scan["unspents"].as_array().into_iter().flatten() {
+        let script = unspent["scriptPubKey"].as_str().unwrap_or_default();
+        let (Some((name, _)), Some(txid), Some(vout)) = (
+            contracts.iter().find(|(_, s)| s == script),
+            unspent["txid"].as_str(),
+            unspent["vout"].as_u64().and_then(|v| u32::try_from(v).ok()),
+        ) else {
+            continue;
+        };
+        monitor.add_unspent(
+            name,
+            txid,
+            vout,
+            unspent["amount"].as_f64().map(btc_to_sats),
+        );
+    }
+    Ok(())
+}
+
+/// Print an event
+fn print_event(event: &WatchEvent) {
+    let (_, balance) = event.balances();
+    match event {
+        WatchEvent::Funded {
+            contract,
+            txid,
+            vout,
+            amount,
+            height,
+            ..
+        } => crate::output::print_result(&format!(
+            "  {} {} funded by {txid}:{vout} ({}) at height {height}, balance {balance} sat",
+            "+".green(),
+            contract.bold(),
+            amount.map_or_else(|| "confidential".to_string(), |a| format!("{a} sat"))
+        )),
+        WatchEvent::Spent {
+            contract,
+            txid,
+            outpoints,
+            height,
+            ..
+        } => crate::output::print_result(&format!(
+            "  {} {} spent by {txid} ({} outputs) at height {height}, balance {balance} sat",
+            "-".yellow(),
+            contract.bold(),
+            outpoints.len()
+        )),
+    }
+}
+
+/// Print an alert and post it to its rule's webhook
+fn raise(rules: &AlertRules, alert: &Alert) {
+    crate::output::print_result(&format!(
+        "  {} {} {}: {}",
+        "⚠".red().bold(),
+        format!("[{}]", alert.rule).red().bold(),
+        alert.contract,
+        alert.message
+    ));
+    let Some(url) = rules
+        .rule(&alert.rule)
+        .and_then(|rule| rule.webhook.as_deref())
+    else {
+        return;
+    };
+    if let Err(e) = post_webhook(url, alert) {
+        eprintln!("    {} {e}", "Webhook failed:".red());
+    }
+}
@@ -0,0 +1,37 @@
+//! New command implementation
+
+use crate::error::SprayError;
+use crate::template::{scaffold, Template};
+use colored::Colorize;
+use std::path::Path;
+
+/// Execute the new command
+///
+/// Creates the project `name` from `template` in a new directory of that
+/// name.
+///
+/// # Errors
+///
+/// Returns an error if the directory exists and is not empty, or a file
+/// cannot be written.
+pub fn new_command(name: &Path, template: Template) -> Result<(), SprayError> {
+    crate::status!(
+        "{} {} {}",
+        "Creating".cyan().bold(),
+        name.display().to_string().cyan().bold(),
+        format!("from the {template} template").dimmed()
+    );
+    crate::status!();
+
+    for path in scaffold(name, template)? {
+        crate::status!("{} {}", "✓".green(), path.display());
+    }
+
+    crate::status!();
+    crate::status!("{}", "✓ Project created!".green().bold());
+    crate::status!();
+    crate::status!("{}", "Next steps:".bold());
+    crate::status!("  cd {}", name.display());
+    crate::status!("  spray test --manifest spray-tests.toml");
+    Ok(())
+}
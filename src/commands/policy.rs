@@ -0,0 +1,201 @@
+//! Compare-policy command implementation
+
+use crate::cache::ProgramCache;
+use crate::error::SprayError;
+use crate::manifest::TestSpec;
+use crate::network::{BackendConfig, NetworkBackend};
+use crate::policy::{CaseOutcome, DescriptorPolicy, PolicyCase, PolicyCases};
+use crate::preset::Preset;
+use colored::Colorize;
+use musk::client::NodeClient;
+use musk::elements::OutPoint;
+use musk::Network;
+use std::path::{Path, PathBuf};
+
+/// Execute the compare-policy command
+///
+/// Runs every case in `cases_file` against both the Simplicity contract in
+/// `file` and the output `descriptor`, each funded with `amount` and spent
+/// with `fee`, and reports the cases where only one side accepted the spend.
+/// Without `fee`, the network's [`Preset`] gives it.
+///
+/// Returns `false` if any case behaved differently.
+///
+/// # Errors
+///
+/// Returns an error if the cases cannot be loaded, the backend cannot be
+/// created, or the descriptor cannot be imported. Rejected spends are
+/// recorded as case outcomes instead.
+#[allow(clippy::too_many_arguments)]
+pub fn compare_policy_command(
+    descriptor: &str,
+    file: &Path,
+    args: Option<PathBuf>,
+    cases_file: &Path,
+    amount: u64,
+    fee: Option<u64>,
+    network: Network,
+    config: &BackendConfig,
+) -> Result<bool, SprayError> {
+    crate::status!(
+        "{}",
+        "Comparing Simplicity contract with descriptor policy..."
+            .cyan()
+            .bold()
+    );
+    crate::status!();
+
+    let cases = PolicyCases::load(cases_file)?;
+    if cases.cases.is_empty() {
+        return Err(SprayError::ConfigError(format!(
+            "No cases in {}",
+            cases_file.display()
+        )));
+    }
+
+    crate::status!("{} {network}", "Network:".dimmed());
+    let mut backend = config.create(network)?;
+    let preset = Preset::for_network(network)?;
+    let fee = fee.unwrap_or(preset.fee);
+
+    crate::status!("{}", "Importing descriptor...".dimmed());
+    let policy = DescriptorPolicy::import(&backend, descriptor)?;
+    crate::status!("  {} {}", "Descriptor address:".bold(), policy.address());
+    crate::status!();
+
+    let programs = ProgramCache::default();
+    let mut outcomes = Vec::new();
+    for case in &cases.cases {
+        crate::status!("{} {}", "⏳".yellow(), case.name.bold());
+
+        let spec = TestSpec {
+            name: case.name.clone(),
+            file: file.to_path_buf(),
+            args: args.clone(),
+            witness: case.witness.clone(),
+            amount: Some(amount),
+            fee: Some(fee),
+            conf_target: None,
+            lock_time: case.lock_time,
+            sequence: case.sequence,
+            funding: None,
+            package: false,
+        };
+        let simplicity = spec
+            .run(&mut backend, &preset, &programs)
+            .map(|txid| txid.to_string())
+            .map_err(|(stage, e)| {
+                let error = e.to_string();
+                format!("{}: {error}", stage.refine(&error))
+            });
+
+        let descriptor = spend_descriptor(&backend, &preset, &policy, case, amount, fee)
+            .map_err(|e| e.to_string());
+
+        outcomes.push(CaseOutcome {
+            name: case.name.clone(),
+            simplicity,
+            descriptor,
+        });
+    }
+
+    crate::status!();
+    crate::status!("{}", "─".repeat(60).dimmed());
+    let mut differences = 0;
+    for outcome in &outcomes {
+        let side = |result: &Result<String, String>| match result {
+            Ok(_) => "accepted".green(),
+            Err(_) => "rejected".red(),
+        };
+
+        if outcome.agrees() {
+            crate::output::print_result(&format!(
+                "{} {}: both {}",
+                "✓".green(),
+                outcome.name.bold(),
+                side(&outcome.simplicity)
+            ));
+        } else {
+            differences += 1;
+            crate::output::print_result(&format!("{} {}", "✗".red(), outcome.name.bold()));
+            for (label, result) in [
+                ("Simplicity:", &outcome.simplicity),
+                ("Descriptor:", &outcome.descriptor),
+            ] {
+                match result {
+                    Ok(txid) => crate::output::print_result(&format!(
+                        "    {} {} ({txid})",
+                        label.dimmed(),
+                        side(result)
+                    )),
+                    Err(error) => crate::output::print_result(&format!(
+                        "    {} {} ({error})",
+                        label.dimmed(),
+                        side(result)
+                    )),
+                }
+            }
+        }
+    }
+    crate::status!("{}", "─".repeat(60).dimmed());
+
+    if differences == 0 {
+        crate::status!(
+            "{}",
+            format!("✓ All {} case(s) behave the same", outcomes.len())
+                .green()
+                .bold()
+        );
+    } else {
+        crate::status!(
+            "{}",
+            format!("✗ {differences} of {} case(s) differ", outcomes.len())
+                .red()
+                .bold()
+        );
+    }
+
+    Ok(differences == 0)
+}
+
+/// Fund the descriptor address and spend it under the case's conditions
+fn spend_descriptor(
+    backend: &NetworkBackend,
+    preset: &Preset,
+    policy: &DescriptorPolicy,
+    case: &PolicyCase,
+    amount: u64,
+    fee: u64,
+) -> Result<musk::Txid, SprayError> {
+    let funding_txid = backend
+        .send_to_address(policy.address(), amount)
+        .map_err(|e| SprayError::RpcError(e.to_string()))?;
+    backend.confirm_with(&funding_txid, preset)?;
+
+    let tx = backend
+        .get_transaction(&funding_txid)
+        .map_err(|e| SprayError::RpcError(e.to_string()))?;
+    let script_pubkey = policy.address().script_pubkey();
+    let vout = tx
+        .output
+        .iter()
+        .position(|o| o.script_pubkey == script_pubkey)
+        .ok_or_else(|| SprayError::TestError("Could not find output in transaction".into()))?;
+
+    let destination = backend
+        .get_new_address()
+        .map_err(|e| SprayError::RpcError(e.to_string()))?
+        .to_unconfidential();
+
+    #[allow(clippy::cast_possible_truncation)]
+    let outpoint = OutPoint::new(funding_txid, vout as u32);
+    policy.spend(
+        backend,
+        outpoint,
+        amount,
+        fee,
+        &destination,
+        case.lock_time,
+        case.sequence,
+    )
+}
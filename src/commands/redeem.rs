@@ -1,13 +1,34 @@
 //! Redeem command implementation
 
+use crate::budget::Budget;
+use crate::cache::ProgramCache;
 use crate::compiled::CompiledOutput;
+use crate::confidential as blinding;
 use crate::error::SprayError;
+use crate::fee::FeePolicy;
 use crate::file_loader;
+use crate::hex;
+use crate::logging::elapsed_ms;
+use crate::messages;
+use crate::network::BackendConfig;
+use crate::precompiled::{PrecompiledProgram, PrecompiledSpend, WitnessList};
+use crate::preset::Preset;
+use crate::prompt::Prompt;
+use crate::pset::SimplicityLeaf;
+use crate::registry::{self, Registry};
+use crate::retry::Backoff;
+use crate::signing::{sign_digest, Keystore};
+use crate::value::ValueType;
 use colored::Colorize;
 use musk::client::{NodeClient, Utxo};
-use musk::elements::{confidential, encode::serialize_hex, LockTime, Sequence};
+use musk::elements::{
+    confidential, encode::serialize_hex, Address, AssetId, BlockHash, LockTime, OutPoint, Sequence,
+    Transaction, TxOut,
+};
 use musk::{Network, SpendBuilder};
+use serde_json::json;
 use std::path::{Path, PathBuf};
+use std::time::Instant;
 
 /// Parse a UTXO reference in the format "txid:vout"
 ///
@@ -34,32 +55,75 @@ pub fn parse_utxo_ref(s: &str) -> Result<(musk::Txid, u32), SprayError> {
 
 /// Execute the redeem command
 ///
+/// The fee follows `fee`; rate-based policies are applied to the size of the
+/// finalized, witness-included transaction.
+///
+/// With `dry_run`, the spend is built and finalized but not broadcast; its
+/// raw hex, sighash and fee are printed instead.
+///
+/// A compiled artifact without source is spent from its program bytes; its
+/// witness file is then a list of values, one per witness node (see
+/// [`crate::precompiled`]).
+///
+/// Without `compiled_file`, the program (and its arguments) recorded for the
+/// UTXO in the registry at `registry_path` is used. The UTXO is leased in the
+/// registry while the spend is broadcast. A broadcast rejected for missing
+/// inputs, e.g. because the funding transaction has not reached an external
+/// node yet, is retried according to `backoff`.
+///
+/// Values in `wit_overrides` take precedence over the witness file and are
+/// parsed according to the type each witness is declared with.
+///
+/// With `export_pset`, the unsigned spend is written there instead; no
+/// witness is loaded, so the spend's final size is unknown and `fee` must
+/// be fixed.
+///
 /// # Errors
 ///
-/// Returns an error if redemption fails or file operations fail.
+/// Returns an error if redemption fails or file operations fail, if no
+/// `witness_file` is given without `export_pset`, or if an export's `fee`
+/// is not fixed.
 #[allow(clippy::too_many_arguments)]
 pub fn redeem_command(
     utxo_ref: &str,
-    witness_file: &Path,
+    witness_file: Option<&Path>,
+    wit_overrides: &[(String, String)],
     compiled_file: Option<PathBuf>,
     dest: Option<String>,
-    fee: Option<u64>,
+    fee: FeePolicy,
+    blinding_key: Option<String>,
+    blind: bool,
+    export_pset: Option<PathBuf>,
+    dry_run: bool,
     network: Network,
-    config: Option<PathBuf>,
+    config: &BackendConfig,
+    registry_path: &Path,
+    backoff: Backoff,
 ) -> Result<(), SprayError> {
-    println!("{}", "Redeeming from Simplicity program...".cyan().bold());
-    println!();
+    let span = tracing::info_span!(
+        "redeem",
+        utxo = utxo_ref,
+        %network,
+        cmr = tracing::field::Empty,
+        txid = tracing::field::Empty,
+    );
+    let _span = span.enter();
+    let start = Instant::now();
+
+    crate::status!("{}", "Redeeming from Simplicity program...".cyan().bold());
+    crate::status!();
 
     // Parse UTXO reference
     let (txid, vout) = parse_utxo_ref(utxo_ref)?;
-    println!("{} {txid}:{vout}", "UTXO:".dimmed());
+    crate::status!("{} {txid}:{vout}", "UTXO:".dimmed());
 
     // Create network backend
-    println!("{} {network}", "Network:".dimmed());
-    let mut backend = crate::network::create_backend(network, config)?;
+    crate::status!("{} {network}", "Network:".dimmed());
+    let mut backend = config.create(network)?;
+    let preset = Preset::for_network(network)?;
 
     // Get the transaction to find the UTXO
-    println!("{}", "Fetching UTXO...".dimmed());
+    crate::status!("{}", "Fetching UTXO...".dimmed());
     let tx = backend
         .get_transaction(&txid)
         .map_err(|e| SprayError::RpcError(e.to_string()))?;
@@ -68,46 +132,60 @@ pub fn redeem_command(
         SprayError::InvalidUtxoRef(format!("Vout {vout} not found in transaction"))
     })?;
 
-    // Extract amount and asset
-    let confidential::Value::Explicit(amount) = output.value else {
-        return Err(SprayError::TestError("Non-explicit value in UTXO".into()));
-    };
-
-    let confidential::Asset::Explicit(asset) = output.asset else {
-        return Err(SprayError::TestError("Non-explicit asset in UTXO".into()));
+    // Extract amount and asset, unblinding confidential outputs
+    let secrets =
+        if let (confidential::Value::Explicit(amount), confidential::Asset::Explicit(asset)) =
+            (output.value, output.asset)
+        {
+            blinding::explicit_secrets(asset, amount)
+        } else {
+            crate::status!("{}", "Unblinding confidential output...".dimmed());
+            let key = if let Some(key) = &blinding_key {
+                blinding::parse_blinding_key(key)?
+            } else {
+                wallet_blinding_key(&backend, &output.script_pubkey, network)?
+            };
+            blinding::unblind_output(output, key)?
+        };
+    let confidential_input = output.value.is_confidential() || output.asset.is_confidential();
+    let amount = secrets.value;
+    let asset = secrets.asset;
+
+    crate::status!("  {} {} sat", "Amount:".bold(), amount);
+    crate::status!("  {} {asset}", "Asset:".bold());
+
+    // Load compiled program, falling back to the one recorded at deployment
+    let (program_file, args_file, arg_overrides) = if let Some(compiled_file) = compiled_file {
+        (compiled_file, None, Vec::new())
+    } else {
+        lookup_program(registry_path, &txid.to_string(), vout)?
     };
 
-    println!("  {} {} sat", "Amount:".bold(), amount);
-    println!("  {} {asset}", "Asset:".bold());
-
-    // Load compiled program
-    let compiled_file = compiled_file.ok_or_else(|| {
-        SprayError::FileFormatError("--compiled <file> is required for redeem command".into())
-    })?;
-
-    println!();
-    println!(
+    crate::status!();
+    crate::status!(
         "{} {}",
         "Loading program from:".dimmed(),
-        compiled_file.display()
-    );
-    let json_str = std::fs::read_to_string(&compiled_file)?;
-    let output_data: CompiledOutput = serde_json::from_str(&json_str)?;
-
-    let source = output_data.source.ok_or_else(|| {
-        SprayError::FileFormatError("Compiled program must include source field".into())
-    })?;
-
-    let program = musk::Program::from_source(&source)?;
-    let compiled = program.instantiate(musk::Arguments::default())?;
-
-    // Load witness
-    println!(
-        "{} {}",
-        "Loading witness from:".dimmed(),
-        witness_file.display()
+        program_file.display()
     );
-    let witness_values = file_loader::load_witness(witness_file)?;
+    let program = load_redeemable(&program_file, args_file.as_deref(), &arg_overrides)?;
+    span.record("cmr", tracing::field::display(program.cmr()));
+
+    // Load witness, unless the spend is exported to be signed elsewhere
+    let witness = if export_pset.is_none() {
+        let witness_file = witness_file.ok_or_else(|| {
+            SprayError::ConfigError(
+                "redeem requires a witness file unless --export-pset is given".into(),
+            )
+        })?;
+        crate::status!(
+            "{} {}",
+            "Loading witness from:".dimmed(),
+            witness_file.display()
+        );
+        Some(program.load_witness(witness_file, wit_overrides)?)
+    } else {
+        None
+    };
 
     // Build UTXO struct
     let utxo = Utxo {
@@ -132,68 +210,665 @@ pub fn redeem_command(
             .map_err(|e| SprayError::RpcError(e.to_string()))?
     };
 
-    // Determine fee (default 3000 sat)
-    let fee_amount = fee.unwrap_or(3_000);
+    // A blinded input must be balanced by at least one blinded output
+    let blinded = confidential_input || blind;
+    let dest_output = |fee_amount: u64| -> Result<TxOut, SprayError> {
+        let output_amount = amount
+            .checked_sub(fee_amount)
+            .ok_or_else(|| SprayError::TestError("Insufficient funds for fee".into()))?;
+        if blinded {
+            blinding::blind_last_output(
+                &destination,
+                output_amount,
+                asset,
+                &[secrets],
+                &[blinding::explicit_secrets(asset, fee_amount)],
+                config.deterministic,
+            )
+        } else {
+            Ok(blinding::explicit_output(
+                destination.script_pubkey(),
+                output_amount,
+                asset,
+            ))
+        }
+    };
+
+    // Build the spend and compute its sighash
+    let build = |fee_amount: u64| -> Result<(RedeemSpend, [u8; 32]), SprayError> {
+        let mut builder = program.spend(&utxo, output, genesis_hash);
+        builder.add_output(dest_output(fee_amount)?);
+        builder.add_fee(fee_amount, asset);
+
+        let sighash = builder.sighash_all()?;
+        Ok((builder, sighash))
+    };
+
+    // Determine fee, measuring the finalized transaction for rate-based
+    // policies; an export is finalized elsewhere, so it pays a fixed fee
+    let fee_amount = match (&witness, &fee) {
+        (Some(witness), _) => fee.compute(
+            |conf_target| Some(preset.feerate(backend.estimate_feerate(conf_target))),
+            |fee_amount| {
+                let (builder, _) = build(fee_amount)?;
+                builder.finalize(witness)
+            },
+        )?,
+        (None, FeePolicy::Fixed(fee_amount)) => *fee_amount,
+        (None, _) => return Err(SprayError::ConfigError(
+            "--export-pset needs a fixed --fee: the spend's size is only known once it is signed"
+                .into(),
+        )),
+    };
     let output_amount = amount
         .checked_sub(fee_amount)
         .ok_or_else(|| SprayError::TestError("Insufficient funds for fee".into()))?;
+    preset.check_dust(output_amount)?;
+
+    crate::status!();
+    crate::status!("{}", "Building spending transaction...".dimmed());
+    crate::status!("  {} {}", "Destination:".bold(), destination);
+    crate::status!("  {} {} sat", "Output amount:".bold(), output_amount);
+    crate::status!("  {} {} sat", "Fee:".bold(), fee_amount);
+    if let Some(conf_target) = fee.conf_target() {
+        crate::status!("  {} {conf_target} blocks", "Confirmation target:".bold());
+    }
+    if blinded {
+        crate::status!("  {} yes", "Blinded:".bold());
+    }
 
-    println!();
-    println!("{}", "Building spending transaction...".dimmed());
-    println!("  {} {}", "Destination:".bold(), destination);
-    println!("  {} {} sat", "Output amount:".bold(), output_amount);
-    println!("  {} {} sat", "Fee:".bold(), fee_amount);
+    // Export an unsigned PSET instead of finalizing
+    if let Some(pset_path) = export_pset {
+        let leaf = SimplicityLeaf::new(program.cmr().to_byte_array())?;
+        let pset = leaf.spend_pset(
+            OutPoint::new(txid, vout),
+            output.clone(),
+            Sequence::MAX,
+            LockTime::ZERO,
+            vec![dest_output(fee_amount)?, TxOut::new_fee(fee_amount, asset)],
+        )?;
+        std::fs::write(&pset_path, pset.to_string())?;
+
+        crate::status!();
+        crate::status!(
+            "{}",
+            format!("✓ {}", messages::text("redeem.pset_exported"))
+                .green()
+                .bold()
+        );
+        crate::status!("  {} {}", "File:".bold(), pset_path.display());
+        return crate::output::emit(
+            "redeem.pset_exported",
+            true,
+            &json!({
+                "utxo": format!("{txid}:{vout}"),
+                "pset": pset_path,
+                "destination": destination.to_string(),
+                "output_amount": output_amount,
+                "fee": fee_amount,
+            }),
+        );
+    }
+
+    // Exports returned above, so the witness was loaded
+    let Some(witness) = witness else {
+        return Err(SprayError::ConfigError(
+            "redeem requires a witness file unless --export-pset is given".into(),
+        ));
+    };
 
     // Build the spend
-    let mut builder = SpendBuilder::new(compiled, utxo)
-        .genesis_hash(genesis_hash)
-        .lock_time(LockTime::ZERO)
-        .sequence(Sequence::MAX);
+    let (builder, sighash) = build(fee_amount)?;
 
-    builder.add_output_simple(destination.script_pubkey(), output_amount, asset);
-    builder.add_fee(fee_amount, asset);
+    crate::status!("  {} {}", "Sighash:".dimmed(), hex::encode(&sighash));
 
-    // Compute sighash
-    let sighash = builder.sighash_all().map_err(SprayError::SpendError)?;
+    // Finalize with witness
+    crate::status!("{}", "Finalizing transaction...".dimmed());
+    let tx = builder.finalize(&witness)?;
+    let budget = Budget::for_transaction(&tx);
+    if let Some(budget) = &budget {
+        crate::status!("  {} {budget}", "Cost:".bold());
+        if !budget.is_within() {
+            crate::status!(
+                "  {}",
+                "The program's cost exceeds the witness budget; nodes will reject the spend".red()
+            );
+        }
+    }
 
-    println!("  {} {}", "Sighash:".dimmed(), hex::encode(&sighash));
+    if dry_run {
+        let vsize = tx.weight().div_ceil(4);
+
+        crate::status!();
+        crate::status!("{}", messages::text("redeem.dry_run").yellow().bold());
+        crate::status!("  {} {}", "Txid:".bold(), tx.txid());
+        crate::status!("  {} {}", "Sighash:".bold(), hex::encode(&sighash));
+        crate::status!("  {} {fee_amount} sat", "Fee:".bold());
+        crate::status!("  {} {vsize} vB", "Size:".bold());
+        crate::status!();
+        crate::status!("{}", "Raw transaction (hex):".dimmed());
+        crate::output::print_result(&serialize_hex(&tx));
+        return crate::output::emit(
+            "redeem.dry_run",
+            true,
+            &json!({
+                "dry_run": true,
+                "utxo": format!("{txid}:{vout}"),
+                "txid": tx.txid().to_string(),
+                "sighash": hex::encode(&sighash),
+                "destination": destination.to_string(),
+                "output_amount": output_amount,
+                "fee": fee_amount,
+                "vsize": vsize,
+                "budget": budget,
+                "hex": serialize_hex(&tx),
+            }),
+        );
+    }
 
-    // Finalize with witness
-    println!("{}", "Finalizing transaction...".dimmed());
-    let tx = builder
-        .finalize(witness_values)
-        .map_err(SprayError::SpendError)?;
+    // Hold the outpoint so concurrent runs cannot broadcast a conflicting spend
+    let _lease = Registry::load(registry_path)?.lease(
+        &txid.to_string(),
+        vout,
+        &registry::run_id(),
+        registry::DEFAULT_LEASE_TTL,
+    )?;
 
     // Broadcast
-    println!("{}", "Broadcasting transaction...".dimmed());
-    let spend_txid = backend
-        .broadcast(&tx)
-        .map_err(|e| SprayError::RpcError(e.to_string()))?;
+    crate::status!("{}", "Broadcasting transaction...".dimmed());
+    let spend_txid = backend.broadcast_with_retry(&tx, backoff, |attempt, delay, error| {
+        tracing::warn!(attempt, delay_secs = delay.as_secs(), %error, "inputs not found, retrying");
+        crate::status!(
+            "  {} {error}; retry {attempt}/{} in {}s",
+            "Inputs not found:".yellow(),
+            backoff.retries,
+            delay.as_secs()
+        );
+    })?;
 
-    println!();
-    println!("{}", "✓ Redemption successful!".green().bold());
-    println!();
-    println!("{}", "Transaction details:".bold());
-    println!("  {} {spend_txid}", "Txid:".bold());
+    span.record("txid", tracing::field::display(spend_txid));
+    tracing::info!(
+        fee = fee_amount,
+        duration_ms = elapsed_ms(start),
+        "redeemed"
+    );
 
-    println!();
-    println!("{}", "Raw transaction (hex):".dimmed());
-    println!("{}", serialize_hex(&tx));
+    crate::status!();
+    crate::status!(
+        "{}",
+        format!("✓ {}", messages::text("redeem.success"))
+            .green()
+            .bold()
+    );
+    crate::status!();
+    crate::status!("{}", "Transaction details:".bold());
+    crate::status!("  {} {spend_txid}", "Txid:".bold());
+    if let Some(url) = preset.tx_url(&spend_txid.to_string()) {
+        crate::status!("  {} {url}", "Explorer:".bold());
+    }
 
-    Ok(())
+    crate::status!();
+    crate::status!("{}", "Raw transaction (hex):".dimmed());
+    crate::status!("{}", serialize_hex(&tx));
+    crate::output::print_result(&spend_txid.to_string());
+
+    crate::output::emit(
+        "redeem.success",
+        true,
+        &json!({
+            "utxo": format!("{txid}:{vout}"),
+            "txid": spend_txid.to_string(),
+            "sighash": hex::encode(&sighash),
+            "destination": destination.to_string(),
+            "output_amount": output_amount,
+            "fee": fee_amount,
+            "budget": budget,
+            "hex": serialize_hex(&tx),
+        }),
+    )
 }
 
-// Add hex module
-#[doc(hidden)]
-mod hex {
-    use std::fmt::Write;
-
-    pub fn encode(bytes: &[u8]) -> String {
-        bytes
-            .iter()
-            .fold(String::with_capacity(bytes.len() * 2), |mut acc, b| {
-                let _ = write!(acc, "{b:02x}");
-                acc
+/// Execute `spray redeem --interactive`
+///
+/// Walks through a redemption step by step: choosing an unspent deployment
+/// on `network` from the registry at `registry_path`, the destination and a
+/// fixed fee, and a value for each witness of the deployed program, asked
+/// for by its declared type. A witness answer `@<key>` is replaced by a
+/// signature over the spend's sighash with that key from the keystore in
+/// `keystore_dir`.
+///
+/// The values are saved to a witness file, and the transaction plan and the
+/// equivalent `spray redeem` command line are shown. Once confirmed, the
+/// spend is broadcast, or dry run, with [`redeem_command`].
+///
+/// # Errors
+///
+/// Returns an error if the registry has no unspent deployment on `network`,
+/// the chosen output is confidential, input ends early, or redemption fails.
+pub fn redeem_interactive_command(
+    network: Network,
+    config: &BackendConfig,
+    registry_path: &Path,
+    keystore_dir: &Path,
+    backoff: Backoff,
+) -> Result<(), SprayError> {
+    crate::status!("{}", "Interactive redemption".cyan().bold());
+    crate::status!();
+
+    let mut prompt = Prompt::new(std::io::stdin().lock(), crate::output::terminal());
+    let mut backend = config.create(network)?;
+    let preset = Preset::for_network(network)?;
+
+    // Choose a deployment
+    let registry = Registry::load(registry_path)?;
+    let network_name = network.to_string();
+    let deployments: Vec<&registry::DeploymentRecord> = registry
+        .records()
+        .iter()
+        .filter(|record| record.network == network_name && record.artifact.is_some())
+        .filter(|record| {
+            backend
+                .call(
+                    "gettxout",
+                    &[record.txid.clone().into(), record.vout.into()],
+                )
+                .map_or(true, |unspent| !unspent.is_null())
+        })
+        .collect();
+    if deployments.is_empty() {
+        return Err(SprayError::ConfigError(format!(
+            "No unspent deployments on {network} in {}",
+            registry.path().display()
+        )));
+    }
+    let options: Vec<String> = deployments
+        .iter()
+        .map(|record| {
+            let program = Path::new(record.artifact.as_deref().unwrap_or_default())
+                .file_name()
+                .map(|name| name.to_string_lossy().into_owned())
+                .unwrap_or_default();
+            format!(
+                "{}:{}  {program}, {} sat",
+                record.txid, record.vout, record.amount
+            )
+        })
+        .collect();
+    let record = deployments[prompt.choose("Deployment to redeem:", &options)?];
+    let utxo_ref = format!("{}:{}", record.txid, record.vout);
+    let (txid, vout) = parse_utxo_ref(&utxo_ref)?;
+
+    // Load its program and output
+    let artifact = PathBuf::from(record.artifact.as_deref().unwrap_or_default());
+    let arg_overrides: Vec<(String, String)> = record.arg_overrides.clone().into_iter().collect();
+    let compiled = load_program(
+        &artifact,
+        record.args.as_deref().map(Path::new),
+        &arg_overrides,
+    )?;
+    let tx = backend
+        .get_transaction(&txid)
+        .map_err(|e| SprayError::RpcError(e.to_string()))?;
+    let output = tx.output.get(vout as usize).ok_or_else(|| {
+        SprayError::InvalidUtxoRef(format!("Vout {vout} not found in transaction"))
+    })?;
+    let (confidential::Value::Explicit(amount), confidential::Asset::Explicit(asset)) =
+        (output.value, output.asset)
+    else {
+        return Err(SprayError::ConfigError(format!(
+            "{utxo_ref} is confidential; use spray redeem --blinding-key"
+        )));
+    };
+
+    // Destination and fee
+    crate::say!();
+    let destination: Address = prompt.ask_parsed(
+        "Destination address ('new' for a new wallet address)",
+        Some("new"),
+        |answer| match answer {
+            "new" => backend
+                .get_new_address()
+                .map_err(|e| SprayError::RpcError(e.to_string())),
+            address => address
+                .parse()
+                .map_err(|e| SprayError::ParseError(format!("Invalid destination address: {e}"))),
+        },
+    )?;
+    let default_fee = preset.fee.to_string();
+    let max_fee = amount.saturating_sub(preset.dust_limit);
+    let fee_amount = prompt.ask_parsed("Fee in satoshis", Some(&default_fee), |answer| {
+        answer
+            .parse::<u64>()
+            .ok()
+            .filter(|fee| *fee <= max_fee)
+            .ok_or_else(|| {
+                SprayError::ParseError(format!(
+                    "Enter a fee of at most {max_fee} sat, leaving {} sat above dust",
+                    preset.dust_limit
+                ))
             })
+    })?;
+    let output_amount = amount - fee_amount;
+
+    // The sighash signatures commit to, computed as redeem_command does
+    let utxo = Utxo {
+        txid,
+        vout,
+        amount,
+        script_pubkey: output.script_pubkey.clone(),
+        asset: output.asset,
+    };
+    let mut builder = SpendBuilder::new(compiled.clone(), utxo)
+        .genesis_hash(backend.genesis_hash()?)
+        .lock_time(LockTime::ZERO)
+        .sequence(Sequence::MAX);
+    builder.add_output(blinding::explicit_output(
+        destination.script_pubkey(),
+        output_amount,
+        asset,
+    ));
+    builder.add_fee(fee_amount, asset);
+    let sighash = builder.sighash_all().map_err(SprayError::SpendError)?;
+
+    // Witness values
+    crate::say!();
+    crate::status!("{} {}", "Sighash:".dimmed(), hex::encode(sighash));
+    crate::say!(
+        "{}",
+        "Answer @<key> to sign the sighash with a keystore key".dimmed()
+    );
+    let keystore = Keystore::new(keystore_dir);
+    let mut sign = |key: &str| -> Result<String, SprayError> {
+        let signature = sign_digest(sighash, &keystore.secret_key(key)?);
+        Ok(format!("0x{signature}"))
+    };
+    let mut types: Vec<(String, String)> = crate::compiled::witness_types(&compiled)
+        .into_iter()
+        .collect();
+    types.sort();
+    let mut witness = serde_json::Map::new();
+    for (name, ty) in &types {
+        let value = match ty.parse::<ValueType>() {
+            Ok(value_type) => prompt.value(name, &value_type, &mut sign)?,
+            Err(_) => prompt.ask(&format!("{name} ({ty})"), None)?,
+        };
+        witness.insert(name.clone(), json!({ "value": value, "type": ty }));
+    }
+
+    let default_path = format!(
+        "{}.wit.json",
+        artifact
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().into_owned())
+            .unwrap_or_else(|| "redeem".into())
+    );
+    let witness_path = PathBuf::from(prompt.ask("Save witness to", Some(&default_path))?);
+    std::fs::write(&witness_path, serde_json::to_string_pretty(&witness)?)?;
+
+    // Preview
+    let command = format!(
+        "spray redeem {utxo_ref} {} --dest {destination} --fee {fee_amount} --network {network}",
+        witness_path.display()
+    );
+    crate::say!();
+    crate::say!("{}", "Transaction plan:".bold());
+    crate::say!("  {} {utxo_ref} ({amount} sat)", "Spend:".bold());
+    crate::say!("  {} {}", "Program:".bold(), artifact.display());
+    crate::say!("  {} {destination}", "Destination:".bold());
+    crate::say!("  {} {output_amount} sat", "Output amount:".bold());
+    crate::say!("  {} {fee_amount} sat", "Fee:".bold());
+    crate::say!("  {} {}", "Sighash:".bold(), hex::encode(sighash));
+    for (name, value) in &witness {
+        crate::say!("  {} {name} = {}", "Witness:".bold(), value["value"]);
     }
+    crate::say!("  {} {}", "Witness file:".bold(), witness_path.display());
+    crate::say!();
+    crate::status!("{}", "Equivalent command:".dimmed());
+    crate::say!("  {command}");
+    crate::say!();
+
+    let actions = [
+        "Broadcast".to_string(),
+        "Dry run (finalize without broadcasting)".to_string(),
+        "Cancel".to_string(),
+    ];
+    let dry_run = match prompt.choose("Proceed?", &actions)? {
+        0 => false,
+        1 => true,
+        _ => {
+            crate::say!("{}", messages::text("redeem.cancelled").yellow());
+            return crate::output::emit(
+                "redeem.cancelled",
+                true,
+                &json!({
+                    "utxo": utxo_ref,
+                    "witness_file": witness_path.display().to_string(),
+                    "equivalent_command": command,
+                }),
+            );
+        }
+    };
+    crate::say!();
+
+    redeem_command(
+        &utxo_ref,
+        Some(&witness_path),
+        &[],
+        None,
+        Some(destination.to_string()),
+        FeePolicy::Fixed(fee_amount),
+        None,
+        false,
+        None,
+        dry_run,
+        network,
+        config,
+        registry_path,
+        backoff,
+    )
+}
+
+/// Find the program and arguments files, and the argument overrides,
+/// recorded for a deployment
+fn lookup_program(
+    registry_path: &Path,
+    txid: &str,
+    vout: u32,
+) -> Result<(PathBuf, Option<PathBuf>, Vec<(String, String)>), SprayError> {
+    let registry = Registry::load(registry_path)?;
+    let record = registry.find(txid, vout).ok_or_else(|| {
+        SprayError::FileFormatError(format!(
+            "{txid}:{vout} is not in {}; pass --compiled <file>",
+            registry.path().display()
+        ))
+    })?;
+    let artifact = record.artifact.as_ref().ok_or_else(|| {
+        SprayError::FileFormatError(format!(
+            "No program recorded for {txid}:{vout}; pass --compiled <file>"
+        ))
+    })?;
+
+    Ok((
+        PathBuf::from(artifact),
+        record.args.as_ref().map(PathBuf::from),
+        record.arg_overrides.clone().into_iter().collect(),
+    ))
+}
+
+/// Instantiate a program from a `.simf` source or a compiled `.json` artifact
+///
+/// Commands that load a program once go through a fresh [`ProgramCache`];
+/// commands that load programs repeatedly keep their own.
+pub(crate) fn load_program(
+    path: &Path,
+    args_file: Option<&Path>,
+    arg_overrides: &[(String, String)],
+) -> Result<musk::InstantiatedProgram, SprayError> {
+    ProgramCache::default().load(path, args_file, arg_overrides)
+}
+
+/// A program to redeem a UTXO with
+enum Redeemable {
+    /// Instantiated from source
+    Source(musk::InstantiatedProgram),
+    /// Decoded from the bytes of a compiled artifact without source
+    Bytes(PrecompiledProgram),
+}
+
+/// Witness of a [`Redeemable`]
+enum RedeemWitness {
+    /// Named values, for a program with source
+    Values(musk::WitnessValues),
+    /// Values by witness node, for a program without source
+    List(WitnessList),
+}
+
+/// Spending transaction of a [`Redeemable`]
+enum RedeemSpend {
+    /// Built by musk from the instantiated program
+    Source(SpendBuilder),
+    /// Built from the program bytes
+    Bytes(PrecompiledSpend),
+}
+
+impl Redeemable {
+    fn cmr(&self) -> simplicity::Cmr {
+        match self {
+            Self::Source(compiled) => compiled.cmr(),
+            Self::Bytes(program) => program.cmr(),
+        }
+    }
+
+    /// Load the witness in `path`; overrides need witness names, so only
+    /// apply to programs with source
+    fn load_witness(
+        &self,
+        path: &Path,
+        overrides: &[(String, String)],
+    ) -> Result<RedeemWitness, SprayError> {
+        match self {
+            Self::Source(compiled) => file_loader::load_witness_with(
+                Some(path),
+                overrides,
+                &crate::compiled::witness_types(compiled),
+            )
+            .map(RedeemWitness::Values),
+            Self::Bytes(_) if !overrides.is_empty() => Err(SprayError::ConfigError(
+                "Witness overrides need the program's source; edit the witness list instead".into(),
+            )),
+            Self::Bytes(_) => WitnessList::load(path).map(RedeemWitness::List),
+        }
+    }
+
+    /// Start a spend of `prevout`, the contract output `utxo` refers to
+    fn spend(&self, utxo: &Utxo, prevout: &TxOut, genesis_hash: BlockHash) -> RedeemSpend {
+        match self {
+            Self::Source(compiled) => RedeemSpend::Source(
+                SpendBuilder::new(compiled.clone(), utxo.clone())
+                    .genesis_hash(genesis_hash)
+                    .lock_time(LockTime::ZERO)
+                    .sequence(Sequence::MAX),
+            ),
+            Self::Bytes(program) => RedeemSpend::Bytes(
+                PrecompiledSpend::new(
+                    program.clone(),
+                    OutPoint::new(utxo.txid, utxo.vout),
+                    prevout.clone(),
+                )
+                .genesis_hash(genesis_hash)
+                .lock_time(LockTime::ZERO)
+                .sequence(Sequence::MAX),
+            ),
+        }
+    }
+}
+
+impl RedeemSpend {
+    fn add_output(&mut self, output: TxOut) {
+        match self {
+            Self::Source(builder) => builder.add_output(output),
+            Self::Bytes(spend) => spend.add_output(output),
+        }
+    }
+
+    fn add_fee(&mut self, amount: u64, asset: AssetId) {
+        match self {
+            Self::Source(builder) => builder.add_fee(amount, asset),
+            Self::Bytes(spend) => spend.add_fee(amount, asset),
+        }
+    }
+
+    fn sighash_all(&self) -> Result<[u8; 32], SprayError> {
+        match self {
+            Self::Source(builder) => builder.sighash_all().map_err(SprayError::SpendError),
+            Self::Bytes(spend) => spend.sighash_all(),
+        }
+    }
+
+    fn finalize(self, witness: &RedeemWitness) -> Result<Transaction, SprayError> {
+        match (self, witness) {
+            (Self::Source(builder), RedeemWitness::Values(values)) => builder
+                .finalize(values.clone())
+                .map_err(SprayError::SpendError),
+            (Self::Bytes(spend), RedeemWitness::List(list)) => spend.finalize(list),
+            _ => Err(SprayError::TestError(
+                "Witness does not match the program".into(),
+            )),
+        }
+    }
+}
+
+/// Load the program to redeem with from a `.simf` source or a compiled
+/// `.json` artifact, which is spent from its program bytes if it has no
+/// source
+fn load_redeemable(
+    path: &Path,
+    args_file: Option<&Path>,
+    arg_overrides: &[(String, String)],
+) -> Result<Redeemable, SprayError> {
+    if path.extension().and_then(|e| e.to_str()) == Some("simf") {
+        return load_program(path, args_file, arg_overrides).map(Redeemable::Source);
+    }
+    let output = CompiledOutput::load(path)?;
+    if let Some(source) = &output.source {
+        return ProgramCache::default()
+            .instantiate(source, args_file, arg_overrides)
+            .map(Redeemable::Source);
+    }
+    if args_file.is_some() || !arg_overrides.is_empty() {
+        return Err(SprayError::ConfigError(
+            "Arguments cannot be applied to a program without source".into(),
+        ));
+    }
+    crate::status!("  {}", "No source; spending the program bytes".dimmed());
+    PrecompiledProgram::from_output(&output).map(Redeemable::Bytes)
+}
+
+/// Fetch the blinding key for a contract output from the node's wallet
+///
+/// This works when the contract address (or its blinding key) was imported
+/// into the wallet, e.g. with `importblindingkey`.
+fn wallet_blinding_key(
+    backend: &crate::network::NetworkBackend,
+    script_pubkey: &musk::elements::Script,
+    network: Network,
+) -> Result<musk::elements::secp256k1_zkp::SecretKey, SprayError> {
+    let address =
+        Address::from_script(script_pubkey, None, network.address_params()).ok_or_else(|| {
+            SprayError::TestError("Cannot derive an address for the confidential output".into())
+        })?;
+
+    let key = backend
+        .call("dumpblindingkey", &[address.to_string().into()])
+        .map_err(|e| {
+            SprayError::TestError(format!(
+                "Output is confidential and the wallet has no blinding key for {address} \
+                 (pass --blinding-key): {e}"
+            ))
+        })?;
+
+    let key = key
+        .as_str()
+        .ok_or_else(|| SprayError::RpcError("Invalid dumpblindingkey response".into()))?;
+    blinding::parse_blinding_key(key)
 }
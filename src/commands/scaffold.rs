@@ -0,0 +1,51 @@
+//! Scaffold command implementation
+
+use crate::error::SprayError;
+use crate::scaffold::Scaffold;
+use colored::Colorize;
+use std::path::Path;
+
+/// Execute the scaffold command
+///
+/// Writes arguments and witness templates for the program in `file` to
+/// `out_dir`, or next to the program.
+///
+/// # Errors
+///
+/// Returns an error if the program does not compile, a template exists and
+/// `force` is not set, or a file cannot be written.
+pub fn scaffold_command(
+    file: &Path,
+    out_dir: Option<&Path>,
+    force: bool,
+) -> Result<(), SprayError> {
+    crate::status!(
+        "{} {}",
+        "Scaffolding".cyan().bold(),
+        file.display().to_string().cyan().bold()
+    );
+    crate::status!();
+
+    let scaffold = Scaffold::load(file)?;
+    for (heading, types) in [
+        ("Parameters:", &scaffold.parameters),
+        ("Witnesses:", &scaffold.witnesses),
+    ] {
+        crate::status!("{}", heading.bold());
+        if types.is_empty() {
+            crate::status!("  {}", "(none)".dimmed());
+        }
+        for (name, ty) in types {
+            crate::status!("  {name}: {}", ty.dimmed());
+        }
+    }
+    crate::status!();
+
+    let dir = out_dir.unwrap_or_else(|| file.parent().unwrap_or_else(|| Path::new("")));
+    for path in scaffold.write(dir, force)? {
+        crate::status!("{} {}", "✓".green(), path.display());
+    }
+    crate::status!();
+    crate::status!("{}", "Replace the placeholder values before use.".dimmed());
+    Ok(())
+}
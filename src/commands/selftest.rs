@@ -0,0 +1,196 @@
+//! Self-test command implementation
+
+use crate::error::SprayError;
+use crate::network::{create_backend, NetworkBackend};
+use crate::preset::Preset;
+use colored::Colorize;
+use musk::client::{NodeClient, Utxo};
+use musk::elements::{confidential, LockTime, Sequence};
+use musk::{Network, SpendBuilder};
+use std::time::{Duration, Instant};
+
+/// Trivial contract used by the self-test
+const SELFTEST_PROGRAM: &str = "fn main() { assert!(true); }";
+
+/// Amount locked in the self-test contract
+const SELFTEST_AMOUNT: u64 = 100_000;
+
+/// Timeout for each confirmation
+const CONFIRMATION_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Run a single check, printing its outcome and duration
+fn check<T>(name: &str, f: impl FnOnce() -> Result<T, SprayError>) -> Result<T, SprayError> {
+    let start = Instant::now();
+    let result = f();
+    let elapsed = start.elapsed().as_millis();
+
+    match &result {
+        Ok(_) => crate::output::print_result(&format!(
+            "  {} {name} {}",
+            "✓".green().bold(),
+            format!("({elapsed}ms)").dimmed()
+        )),
+        Err(e) => crate::output::print_result(&format!(
+            "  {} {name}: {}",
+            "✗".red().bold(),
+            e.to_string().red()
+        )),
+    }
+
+    result
+}
+
+/// Execute the selftest command
+///
+/// Boots an ephemeral regtest daemon, then compiles, deploys, redeems and
+/// confirms a trivial contract, checking each step.
+///
+/// Returns `true` if every check passed. Checks stop at the first failure.
+#[must_use]
+pub fn selftest_command() -> bool {
+    crate::status!("{}", "Running spray self-test...".cyan().bold());
+    crate::status!();
+
+    let start = Instant::now();
+    let passed = run_checks().is_ok();
+
+    crate::status!();
+    if passed {
+        crate::status!(
+            "{} {}",
+            "✓ Self-test passed".green().bold(),
+            format!("({}ms)", start.elapsed().as_millis()).dimmed()
+        );
+    } else {
+        crate::status!("{}", "✗ Self-test failed".red().bold());
+    }
+
+    passed
+}
+
+/// Run every check in order, stopping at the first failure
+fn run_checks() -> Result<(), SprayError> {
+    let mut backend = check("boot ephemeral daemon", || {
+        create_backend(Network::Regtest, None)
+    })?;
+
+    check("node supports Simplicity", || {
+        let info = backend.check_compatibility()?;
+        crate::output::print_result(&format!(
+            "    {} {} ({})",
+            "Node:".dimmed(),
+            info.version_string(),
+            info.subversion
+        ));
+        Ok(())
+    })?;
+
+    let compiled = check("compile trivial contract", || {
+        let program = musk::Program::from_source(SELFTEST_PROGRAM)?;
+        Ok(program.instantiate(musk::Arguments::default())?)
+    })?;
+
+    let utxo = check("deploy contract", || deploy(&mut backend, &compiled))?;
+    let contract_txid = utxo.txid;
+    let contract_vout = utxo.vout;
+
+    let spend_txid = check("redeem contract", || {
+        redeem(&mut backend, compiled.clone(), utxo)
+    })?;
+
+    check("confirm redemption", || {
+        backend.confirm(&spend_txid, CONFIRMATION_TIMEOUT)
+    })?;
+
+    check("verify contract UTXO is spent", || {
+        verify_spent(&backend, &contract_txid, contract_vout)
+    })?;
+
+    Ok(())
+}
+
+/// Fund the contract address and return the confirmed contract UTXO
+fn deploy(
+    backend: &mut NetworkBackend,
+    compiled: &musk::InstantiatedProgram,
+) -> Result<Utxo, SprayError> {
+    let address = compiled.address(backend.address_params());
+    let txid = backend
+        .send_to_address(&address, SELFTEST_AMOUNT)
+        .map_err(|e| SprayError::RpcError(e.to_string()))?;
+    backend.confirm(&txid, CONFIRMATION_TIMEOUT)?;
+
+    let tx = backend
+        .get_transaction(&txid)
+        .map_err(|e| SprayError::RpcError(e.to_string()))?;
+    let script_pubkey = address.script_pubkey();
+    let (vout, output) = tx
+        .output
+        .iter()
+        .enumerate()
+        .find(|(_, o)| o.script_pubkey == script_pubkey)
+        .ok_or_else(|| SprayError::TestError("UTXO not found in transaction".into()))?;
+
+    let confidential::Value::Explicit(amount) = output.value else {
+        return Err(SprayError::TestError("Non-explicit value in UTXO".into()));
+    };
+    if amount != SELFTEST_AMOUNT {
+        return Err(SprayError::TestError(format!(
+            "Contract UTXO holds {amount} sat, expected {SELFTEST_AMOUNT}"
+        )));
+    }
+
+    Ok(Utxo {
+        txid,
+        #[allow(clippy::cast_possible_truncation)]
+        vout: vout as u32,
+        amount,
+        script_pubkey: output.script_pubkey.clone(),
+        asset: output.asset,
+    })
+}
+
+/// Spend the contract UTXO back to the wallet
+fn redeem(
+    backend: &mut NetworkBackend,
+    compiled: musk::InstantiatedProgram,
+    utxo: Utxo,
+) -> Result<musk::Txid, SprayError> {
+    let confidential::Asset::Explicit(asset) = utxo.asset else {
+        return Err(SprayError::TestError("Non-explicit asset in UTXO".into()));
+    };
+
+    let genesis_hash = backend.genesis_hash()?;
+    let destination = backend
+        .get_new_address()
+        .map_err(|e| SprayError::RpcError(e.to_string()))?;
+
+    // The built-in preset, so that the self-test does not depend on spray.toml
+    let fee = Preset::builtin(Network::Regtest).fee;
+    let mut builder = SpendBuilder::new(compiled, utxo)
+        .genesis_hash(genesis_hash)
+        .lock_time(LockTime::ZERO)
+        .sequence(Sequence::MAX);
+    builder.add_output_simple(destination.script_pubkey(), SELFTEST_AMOUNT - fee, asset);
+    builder.add_fee(fee, asset);
+
+    let tx = builder
+        .finalize(musk::WitnessValues::default())
+        .map_err(SprayError::SpendError)?;
+
+    backend
+        .broadcast(&tx)
+        .map_err(|e| SprayError::RpcError(e.to_string()))
+}
+
+/// Check that the node no longer reports the contract UTXO as unspent
+fn verify_spent(backend: &NetworkBackend, txid: &musk::Txid, vout: u32) -> Result<(), SprayError> {
+    let unspent = backend.call("gettxout", &[txid.to_string().into(), vout.into()])?;
+    if unspent.is_null() {
+        Ok(())
+    } else {
+        Err(SprayError::TestError(format!(
+            "Contract UTXO {txid}:{vout} is still unspent"
+        )))
+    }
+}
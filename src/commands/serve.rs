@@ -0,0 +1,285 @@
+//! Serve command implementation
+
+use crate::env::{TestEnv, TestEnvBuilder};
+use crate::error::SprayError;
+use crate::network::{self, RPC_PASSWORD_ENV, RPC_URL_ENV, RPC_USER_ENV};
+use crate::serve::{self, Endpoint, Request};
+use colored::Colorize;
+use musk::Network;
+use serde_json::{json, Value};
+use std::io::{BufReader, Write};
+use std::net::{TcpListener, TcpStream};
+use std::path::PathBuf;
+use std::process::Command;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Lines of a failed command's stderr returned when it printed no document
+const STDERR_TAIL_LINES: usize = 20;
+
+/// Execute the serve command
+///
+/// Answers the requests of the HTTP API (see [`crate::serve`]) on
+/// `bind:port`, reading each connection on a thread of its own, with
+/// [`serve::IO_TIMEOUT`], and running their commands one at a time. Past
+/// [`serve::MAX_CONNECTIONS`] open connections, new ones are answered
+/// `503`.
+/// Requests must carry `token`, or a random token printed at startup. On
+/// regtest without a config file or [`RPC_URL_ENV`], a regtest node mining
+/// a block every `block_time` is started for the lifetime of the server,
+/// and every request's command runs against it.
+///
+/// # Errors
+///
+/// Returns an error if the node cannot be started or the address cannot be
+/// bound.
+pub fn serve_command(
+    bind: &str,
+    port: u16,
+    block_time: Duration,
+    network: Network,
+    config: Option<PathBuf>,
+    token: Option<String>,
+) -> Result<(), SprayError> {
+    crate::status!("{}", "Starting spray API server...".cyan().bold());
+    crate::status!();
+
+    crate::status!("{} {network}", "Network:".dimmed());
+    let managed = matches!(network, Network::Regtest)
+        && config.is_none()
+        && network::rpc_config_from_env(network, None).is_none();
+    let (_env, node_env) = if managed {
+        crate::status!("{}", "Starting regtest node...".dimmed());
+        let env = TestEnvBuilder::new().block_time(block_time).build()?;
+        let node_env = node_env(&env)?;
+        crate::status!(
+            "{} {} (a block every {}s)",
+            "Node:".dimmed(),
+            env.daemon().params.rpc_socket,
+            block_time.as_secs_f64()
+        );
+        (Some(env), node_env)
+    } else {
+        (None, Vec::new())
+    };
+
+    let listener = TcpListener::bind((bind, port))?;
+    let server = Arc::new(Server {
+        exe: std::env::current_exe()?,
+        network,
+        config,
+        node_env,
+        dir: PathBuf::from(serve::DEFAULT_REQUESTS_DIR),
+        token: token.unwrap_or_else(serve::generate_token),
+        running: Mutex::new(()),
+        connections: AtomicUsize::new(0),
+    });
+    crate::status!("{} http://{bind}:{port}", "Listening:".dimmed());
+    crate::say!("{} {}", "Token:".dimmed(), server.token);
+    crate::status!("{}", "Ctrl-C to stop".dimmed());
+    crate::status!();
+
+    for (index, stream) in listener.incoming().enumerate() {
+        match stream {
+            Ok(stream) => {
+                let Some(slot) = Slot::take(&server) else {
+                    refuse(stream);
+                    continue;
+                };
+                std::thread::spawn(move || {
+                    slot.0.answer(stream, index);
+                    drop(slot);
+                });
+            }
+            Err(e) => crate::status!("{} {e}", "Connection failed:".yellow()),
+        }
+    }
+    Ok(())
+}
+
+/// What the requests' commands run with
+struct Server {
+    /// The spray binary
+    exe: PathBuf,
+    network: Network,
+    config: Option<PathBuf>,
+    /// Environment naming the managed node, if any
+    node_env: Vec<(&'static str, String)>,
+    /// Directory of the requests' directories
+    dir: PathBuf,
+    /// Token requests must carry
+    token: String,
+    /// Held while a request's command runs
+    running: Mutex<()>,
+    /// Connections being read or answered
+    connections: AtomicUsize,
+}
+
+/// One of the server's [`serve::MAX_CONNECTIONS`], given back when dropped
+struct Slot(Arc<Server>);
+
+impl Slot {
+    /// Take a slot of `server`, if one is free
+    fn take(server: &Arc<Server>) -> Option<Self> {
+        server
+            .connections
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |open| {
+                (open < serve::MAX_CONNECTIONS).then_some(open + 1)
+            })
+            .ok()
+            .map(|_| Self(Arc::clone(server)))
+    }
+}
+
+impl Drop for Slot {
+    fn drop(&mut self) {
+        self.0.connections.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+/// Answer `503` on `stream` without waiting for the client, so that the
+/// server keeps accepting connections
+fn refuse(mut stream: TcpStream) {
+    crate::status!("? {}", "503".red());
+    let body = serve::error_body(&SprayError::ConfigError(format!(
+        "Too many connections, at most {} at once",
+        serve::MAX_CONNECTIONS
+    )));
+    let _ = stream
+        .set_nonblocking(true)
+        .and_then(|()| stream.write_all(serve::response(503, &body).as_bytes()));
+}
+
+impl Server {
+    /// Answer the request on `stream`, the `index`th since the server
+    /// started
+    fn answer(&self, mut stream: TcpStream, index: usize) {
+        let timeouts = stream
+            .set_read_timeout(Some(serve::IO_TIMEOUT))
+            .and_then(|()| stream.set_write_timeout(Some(serve::IO_TIMEOUT)));
+        if let Err(e) = timeouts {
+            crate::status!("{} {e}", "Connection failed:".yellow());
+            return;
+        }
+
+        let (label, status, body) = match Request::read(BufReader::new(&stream)) {
+            Ok(request) => {
+                let label = format!("{} {}", request.method, request.path);
+                let (status, body) = self.handle(&request, index);
+                (label, status, body)
+            }
+            Err(e) => ("?".to_string(), 400, serve::error_body(&e)),
+        };
+
+        let status_text = if status == 200 {
+            status.to_string().green()
+        } else {
+            status.to_string().red()
+        };
+        crate::status!("{label} {status_text}");
+        if let Err(e) = stream.write_all(serve::response(status, &body).as_bytes()) {
+            crate::status!("{} {e}", "Failed to respond:".yellow());
+        }
+    }
+
+    /// The status and body answering `request`
+    fn handle(&self, request: &Request, index: usize) -> (u16, Value) {
+        let Some(endpoint) = Endpoint::route(&request.method, &request.path) else {
+            let error =
+                SprayError::ParseError(format!("No endpoint {} {}", request.method, request.path));
+            return (404, serve::error_body(&error));
+        };
+        if let Err((status, error)) = serve::authorize(request, endpoint, &self.token) {
+            return (status, serve::error_body(&error));
+        }
+        if endpoint == Endpoint::Health {
+            return match crate::output::document(
+                "serve.health",
+                true,
+                &json!({ "network": network_arg(self.network) }),
+            ) {
+                Ok(document) => (200, document),
+                Err(e) => (500, serve::error_body(&e)),
+            };
+        }
+
+        let started = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |elapsed| elapsed.as_secs());
+        let dir = self.dir.join(format!("{started}-{index}"));
+        let answer = match endpoint.arguments(&request.body, &dir) {
+            Ok(args) => {
+                let _running = self.running.lock().unwrap_or_else(PoisonError::into_inner);
+                self.run(endpoint, &args)
+            }
+            Err(e) => (400, serve::error_body(&e)),
+        };
+        let _ = std::fs::remove_dir_all(&dir);
+        answer
+    }
+
+    /// Run spray with `args` and answer with its JSON document
+    fn run(&self, endpoint: Endpoint, args: &[String]) -> (u16, Value) {
+        // The server's options follow the subcommand, ahead of its `--`
+        let (subcommand, rest) = args.split_at(args.len().min(1));
+        let mut command = Command::new(&self.exe);
+        command
+            .args(["--json", "--no-color"])
+            .args(subcommand)
+            .envs(self.node_env.iter().cloned());
+        if endpoint.uses_node() {
+            command.args(["--network", network_arg(self.network)]);
+            if let Some(config) = &self.config {
+                command.arg("--config").arg(config);
+            }
+        }
+        command.args(rest);
+
+        let output = match command.output() {
+            Ok(output) => output,
+            Err(e) => return (500, serve::error_body(&e.into())),
+        };
+        match serde_json::from_slice::<Value>(&output.stdout) {
+            Ok(document) => (serve::status_of(&document), document),
+            Err(_) => {
+                let stderr = String::from_utf8_lossy(&output.stderr);
+                let lines: Vec<&str> = stderr.lines().collect();
+                let tail = lines[lines.len().saturating_sub(STDERR_TAIL_LINES)..].join("\n");
+                let error = SprayError::EnvironmentError(format!(
+                    "spray {} printed no JSON document ({}): {tail}",
+                    args.first().map_or("", String::as_str),
+                    output.status
+                ));
+                (500, serve::error_body(&error))
+            }
+        }
+    }
+}
+
+/// The environment pointing a command at the wallet of `env`'s node
+fn node_env(env: &TestEnv) -> Result<Vec<(&'static str, String)>, SprayError> {
+    let params = &env.daemon().params;
+    let cookie = std::fs::read_to_string(&params.cookie_file)?;
+    let (user, password) = cookie
+        .trim()
+        .split_once(':')
+        .ok_or_else(|| SprayError::EnvironmentError("Malformed node cookie file".into()))?;
+    Ok(vec![
+        (
+            RPC_URL_ENV,
+            network::wallet_url(&format!("http://{}", params.rpc_socket), "wallet"),
+        ),
+        (RPC_USER_ENV, user.to_string()),
+        (RPC_PASSWORD_ENV, password.to_string()),
+    ])
+}
+
+/// The `--network` value of `network`
+const fn network_arg(network: Network) -> &'static str {
+    match network {
+        Network::Regtest => "regtest",
+        Network::Testnet => "testnet",
+        Network::Liquid => "liquid",
+    }
+}
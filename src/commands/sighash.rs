@@ -0,0 +1,270 @@
+//! Sighash command implementation
+
+use crate::commands::redeem::parse_utxo_ref;
+use crate::compiled::CompiledOutput;
+use crate::confidential as blinding;
+use crate::error::SprayError;
+use crate::hex;
+use crate::network::BackendConfig;
+use crate::pset::SimplicityLeaf;
+use crate::sighash::SighashExplanation;
+use colored::Colorize;
+use musk::client::{NodeClient, Utxo};
+use musk::elements::{
+    confidential, LockTime, OutPoint, Sequence, Transaction, TxIn, TxInWitness, TxOut,
+};
+use musk::{Network, SpendBuilder};
+use std::path::PathBuf;
+
+/// Execute the sighash command
+///
+/// Computes the `sig_all_hash` of a spend of `utxo_ref` to `dest` paying a
+/// fixed `fee`, exactly as `spray redeem` would build it. With `explain`, the
+/// committed fields are printed; with `compare`, they are checked against an
+/// explanation saved by `out` (or produced by another tool) and every
+/// differing field is reported.
+///
+/// Returns `false` if `compare` found differences.
+///
+/// # Errors
+///
+/// Returns an error if the UTXO is confidential, or if fetching the UTXO,
+/// loading the program or building the spend fails.
+#[allow(clippy::too_many_arguments, clippy::too_many_lines)]
+pub fn sighash_command(
+    utxo_ref: &str,
+    compiled_file: &std::path::Path,
+    dest: Option<String>,
+    fee: u64,
+    explain: bool,
+    compare: Option<PathBuf>,
+    out: Option<PathBuf>,
+    network: Network,
+    config: &BackendConfig,
+) -> Result<bool, SprayError> {
+    let (txid, vout) = parse_utxo_ref(utxo_ref)?;
+    let mut backend = config.create(network)?;
+
+    let tx = backend
+        .get_transaction(&txid)
+        .map_err(|e| SprayError::RpcError(e.to_string()))?;
+    let prevout = tx.output.get(vout as usize).cloned().ok_or_else(|| {
+        SprayError::InvalidUtxoRef(format!("Vout {vout} not found in transaction"))
+    })?;
+
+    let (confidential::Value::Explicit(amount), confidential::Asset::Explicit(asset)) =
+        (prevout.value, prevout.asset)
+    else {
+        return Err(SprayError::TestError(
+            "Confidential UTXOs are not supported by spray sighash".into(),
+        ));
+    };
+
+    let compiled = CompiledOutput::load(compiled_file)?.instantiate(musk::Arguments::default())?;
+    let genesis_hash = backend.genesis_hash()?;
+
+    let destination = if let Some(dest_str) = dest {
+        dest_str
+            .parse()
+            .map_err(|e| SprayError::ParseError(format!("Invalid destination address: {e}")))?
+    } else {
+        backend
+            .get_new_address()
+            .map_err(|e| SprayError::RpcError(e.to_string()))?
+    };
+    let output_amount = amount
+        .checked_sub(fee)
+        .ok_or_else(|| SprayError::TestError("Insufficient funds for fee".into()))?;
+    let outputs = vec![
+        blinding::explicit_output(destination.script_pubkey(), output_amount, asset),
+        TxOut::new_fee(fee, asset),
+    ];
+
+    let utxo = Utxo {
+        txid,
+        vout,
+        amount,
+        script_pubkey: prevout.script_pubkey.clone(),
+        asset: prevout.asset,
+    };
+    let mut builder = SpendBuilder::new(compiled.clone(), utxo)
+        .genesis_hash(genesis_hash)
+        .lock_time(LockTime::ZERO)
+        .sequence(Sequence::MAX);
+    builder.add_output(outputs[0].clone());
+    builder.add_fee(fee, asset);
+    let sighash = builder.sighash_all().map_err(SprayError::SpendError)?;
+
+    crate::output::print_result(&format!("{} {}", "Sighash:".bold(), hex::encode(&sighash)));
+    if !explain && compare.is_none() && out.is_none() {
+        return Ok(true);
+    }
+
+    // The unsigned transaction the sighash commits to
+    let unsigned = Transaction {
+        version: 2,
+        lock_time: LockTime::ZERO,
+        input: vec![TxIn {
+            previous_output: OutPoint::new(txid, vout),
+            is_pegin: false,
+            script_sig: musk::elements::Script::new(),
+            sequence: Sequence::MAX,
+            asset_issuance: musk::elements::AssetIssuance::default(),
+            witness: TxInWitness::default(),
+        }],
+        output: outputs,
+    };
+    let leaf = SimplicityLeaf::new(compiled.cmr().to_byte_array())?;
+    let explanation =
+        SighashExplanation::new(sighash, genesis_hash, &unsigned, &[prevout], 0, &leaf)?;
+
+    if explain {
+        print_explanation(&explanation);
+    }
+
+    if let Some(out_path) = out {
+        std::fs::write(&out_path, serde_json::to_string_pretty(&explanation)?)?;
+        crate::status!(
+            "{} {}",
+            "Explanation written to:".dimmed(),
+            out_path.display()
+        );
+    }
+
+    let Some(expected_path) = compare else {
+        return Ok(true);
+    };
+    let expected = SighashExplanation::load(&expected_path)?;
+    let diffs = explanation.diff(&expected)?;
+
+    crate::status!();
+    if diffs.is_empty() {
+        crate::status!(
+            "{}",
+            format!("✓ All fields match {}", expected_path.display())
+                .green()
+                .bold()
+        );
+        return Ok(true);
+    }
+
+    crate::status!(
+        "{}",
+        format!(
+            "✗ {} field(s) differ from {}",
+            diffs.len(),
+            expected_path.display()
+        )
+        .red()
+        .bold()
+    );
+    for diff in &diffs {
+        crate::output::print_result(&format!("  {}", diff.path.bold()));
+        crate::output::print_result(&format!(
+            "    {} {}",
+            "expected:".dimmed(),
+            diff.expected.as_deref().unwrap_or("<absent>")
+        ));
+        crate::output::print_result(&format!(
+            "    {} {}",
+            "actual:  ".dimmed(),
+            diff.actual.as_deref().unwrap_or("<absent>")
+        ));
+    }
+    Ok(false)
+}
+
+/// Print the committed fields of a sighash
+fn print_explanation(explanation: &SighashExplanation) {
+    crate::status!();
+    crate::status!("{}", "Transaction:".bold());
+    crate::output::print_result(&format!(
+        "  {} {}",
+        "Genesis hash:".dimmed(),
+        explanation.genesis_hash
+    ));
+    crate::output::print_result(&format!(
+        "  {} {}",
+        "Version:".dimmed(),
+        explanation.version
+    ));
+    crate::output::print_result(&format!(
+        "  {} {}",
+        "Lock time:".dimmed(),
+        explanation.lock_time
+    ));
+    crate::output::print_result(&format!(
+        "  {} {}",
+        "Input index:".dimmed(),
+        explanation.input_index
+    ));
+
+    for (i, input) in explanation.inputs.iter().enumerate() {
+        crate::status!();
+        crate::status!("{}", format!("Input {i}:").bold());
+        crate::output::print_result(&format!("  {} {}", "Outpoint:".dimmed(), input.outpoint));
+        crate::output::print_result(&format!(
+            "  {} {:#010x}",
+            "Sequence:".dimmed(),
+            input.sequence
+        ));
+        crate::output::print_result(&format!(
+            "  {} {}",
+            "Prevout asset:".dimmed(),
+            input.prevout_asset
+        ));
+        crate::output::print_result(&format!(
+            "  {} {}",
+            "Prevout amount:".dimmed(),
+            input.prevout_value
+        ));
+        crate::output::print_result(&format!(
+            "  {} {}",
+            "Prevout script:".dimmed(),
+            input.prevout_script
+        ));
+        if let Some(issuance) = &input.issuance {
+            crate::output::print_result(&format!("  {} {issuance}", "Issuance:".dimmed()));
+        }
+    }
+
+    for (i, output) in explanation.outputs.iter().enumerate() {
+        crate::status!();
+        crate::status!("{}", format!("Output {i}:").bold());
+        crate::output::print_result(&format!("  {} {}", "Asset:".dimmed(), output.asset));
+        crate::output::print_result(&format!("  {} {}", "Amount:".dimmed(), output.value));
+        if let Some(nonce) = &output.nonce {
+            crate::output::print_result(&format!("  {} {nonce}", "Nonce:".dimmed()));
+        }
+        crate::output::print_result(&format!(
+            "  {} {}",
+            "Script:".dimmed(),
+            output.script_pubkey
+        ));
+        crate::output::print_result(&format!("  {} {}", "Proofs:".dimmed(), output.has_proofs));
+    }
+
+    crate::status!();
+    crate::status!("{}", "Tap leaf:".bold());
+    crate::output::print_result(&format!(
+        "  {} {}",
+        "Script (CMR):".dimmed(),
+        explanation.tap_leaf.script
+    ));
+    crate::output::print_result(&format!(
+        "  {} {:#04x}",
+        "Leaf version:".dimmed(),
+        explanation.tap_leaf.leaf_version
+    ));
+    crate::output::print_result(&format!(
+        "  {} {}",
+        "Control block:".dimmed(),
+        explanation.tap_leaf.control_block
+    ));
+
+    crate::status!();
+    crate::status!("{}", "Component hashes:".bold());
+    for (name, digest) in &explanation.digests {
+        crate::output::print_result(&format!("  {} {digest}", format!("{name}:").dimmed()));
+    }
+}
@@ -0,0 +1,197 @@
+//! Soak command implementation
+
+use crate::commands::redeem::load_program;
+use crate::commands::stress::{print_summary, run_round, sample};
+use crate::env::TestEnv;
+use crate::error::SprayError;
+use crate::file_loader;
+use crate::soak::{Fault, FaultSchedule, InjectedFault, SoakReport};
+use crate::stress::StressReport;
+use colored::Colorize;
+use elementsd::bitcoind::bitcoincore_rpc::RpcApi;
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+
+/// A redeemed instance's spend, kept for rebroadcasting
+struct Spend {
+    txid: String,
+    hex: String,
+}
+
+/// Execute the soak command
+///
+/// Runs a stress test of `count` instances of `contract`, `concurrency` at
+/// a time, on a restartable regtest daemon, injecting faults from
+/// `schedule` between rounds. Afterwards, spends that are neither confirmed
+/// nor in the mempool are rebroadcast and blocks mined, up to
+/// `recovery_attempts` times. Returns whether every instance was redeemed and
+/// every spend confirmed.
+///
+/// # Errors
+///
+/// Returns an error if the contract or witness cannot be loaded, or the
+/// daemon cannot be started or fails to come back after a fault.
+#[allow(clippy::too_many_arguments, clippy::too_many_lines)]
+pub fn soak_command(
+    contract: &Path,
+    args: Option<PathBuf>,
+    witness: Option<PathBuf>,
+    count: usize,
+    concurrency: usize,
+    schedule: &FaultSchedule,
+    recovery_attempts: u32,
+    report_path: Option<PathBuf>,
+) -> Result<bool, SprayError> {
+    crate::status!("{}", "Soak testing contract...".cyan().bold());
+    crate::status!();
+
+    let program = load_program(contract, args.as_deref(), &[])?;
+    let witness_values = witness
+        .as_deref()
+        .map(file_loader::load_witness)
+        .transpose()?
+        .unwrap_or_default();
+
+    let concurrency = concurrency.max(1);
+    crate::status!("{} {count}", "Instances:".dimmed());
+    crate::status!("{} {concurrency}", "Concurrency:".dimmed());
+    crate::status!("{}", "Starting restartable regtest daemon...".dimmed());
+    let mut env = TestEnv::new_restartable()?;
+
+    let mut report = SoakReport {
+        stress: StressReport::new(count, concurrency),
+        ..SoakReport::default()
+    };
+    report.stress.resources.push(sample(&env, "start")?);
+    let mut spends = Vec::new();
+    let start = Instant::now();
+
+    let indices: Vec<usize> = (0..count).collect();
+    for (round, chunk) in indices.chunks(concurrency).enumerate() {
+        let instances = run_round(&env, &program, &witness_values, chunk)?;
+        for txid in instances.iter().filter_map(|i| i.txid.as_ref()) {
+            let tx = call(&env, "gettransaction", &[txid.as_str().into()])?;
+            let hex = tx["hex"]
+                .as_str()
+                .ok_or_else(|| SprayError::RpcError("Invalid gettransaction response".into()))?;
+            spends.push(Spend {
+                txid: txid.clone(),
+                hex: hex.to_string(),
+            });
+        }
+        for instance in instances {
+            report.stress.add(instance);
+        }
+
+        if let Some(fault) = schedule.fault_for_round(round) {
+            crate::status!("  {} {fault} after round {round}", "Injecting".yellow());
+            let error = inject(&mut env, fault).err().map(|e| e.to_string());
+            if let Some(error) = &error {
+                crate::status!("  {} {error}", "Fault injection failed:".red());
+            }
+            report.faults.push(InjectedFault {
+                round,
+                fault,
+                error,
+            });
+        }
+
+        report
+            .stress
+            .resources
+            .push(sample(&env, &format!("round {round} spent"))?);
+        env.generate(1)?;
+        crate::status!(
+            "  {} {}/{count}",
+            "Completed".dimmed(),
+            report.stress.instances.len()
+        );
+    }
+
+    // Recover: rebroadcast lost spends until all of them confirm
+    crate::status!();
+    crate::status!("{}", "Waiting for every spend to confirm...".dimmed());
+    for attempt in 0..=recovery_attempts {
+        let mut unconfirmed = Vec::new();
+        for spend in &spends {
+            if confirmations(&env, &spend.txid) < 1 {
+                unconfirmed.push(spend);
+            }
+        }
+        report.unconfirmed = unconfirmed.iter().map(|s| s.txid.clone()).collect();
+        if unconfirmed.is_empty() || attempt == recovery_attempts {
+            break;
+        }
+
+        for spend in unconfirmed {
+            if call(&env, "getmempoolentry", &[spend.txid.as_str().into()]).is_err()
+                && call(&env, "sendrawtransaction", &[spend.hex.as_str().into()]).is_ok()
+            {
+                report.rebroadcasts += 1;
+            }
+        }
+        env.generate(1)?;
+    }
+
+    report.stress.elapsed = start.elapsed();
+    report.stress.resources.push(sample(&env, "end")?);
+
+    print_summary(&report.stress);
+    crate::status!("{}", "Faults:".bold());
+    crate::output::print_result(&format!("  {} {}", "Injected:".bold(), report.faults.len()));
+    crate::output::print_result(&format!(
+        "  {} {}",
+        "Rebroadcasts:".bold(),
+        report.rebroadcasts
+    ));
+    if report.unconfirmed.is_empty() {
+        crate::status!("{}", "✓ Every spend confirmed".green().bold());
+    } else {
+        crate::status!(
+            "{}",
+            format!("✗ {} spends never confirmed", report.unconfirmed.len())
+                .red()
+                .bold()
+        );
+        for txid in &report.unconfirmed {
+            crate::output::print_result(&format!("  {txid}"));
+        }
+    }
+
+    if let Some(path) = report_path {
+        std::fs::write(&path, serde_json::to_string_pretty(&report)?)?;
+        crate::status!("{} {}", "Report written to:".dimmed(), path.display());
+    }
+
+    Ok(report.passed())
+}
+
+/// Inject `fault` into the environment
+fn inject(env: &mut TestEnv, fault: Fault) -> Result<(), SprayError> {
+    match fault {
+        Fault::Restart => env.restart(true),
+        Fault::Reorg(depth) => env.reorg(depth).map(|_| ()),
+        Fault::MempoolEviction => env.restart(false),
+    }
+}
+
+/// Confirmations of a wallet transaction, negative if it conflicts with
+/// the chain and zero if unknown
+fn confirmations(env: &TestEnv, txid: &str) -> i64 {
+    call(env, "gettransaction", &[txid.into()])
+        .ok()
+        .and_then(|tx| tx["confirmations"].as_i64())
+        .unwrap_or(0)
+}
+
+/// Make an RPC call against the environment's daemon
+fn call(
+    env: &TestEnv,
+    method: &str,
+    params: &[serde_json::Value],
+) -> Result<serde_json::Value, SprayError> {
+    env.daemon()
+        .client()
+        .call::<serde_json::Value>(method, params)
+        .map_err(|e| SprayError::RpcError(e.to_string()))
+}
@@ -0,0 +1,95 @@
+//! Status command implementation
+
+use crate::error::SprayError;
+use crate::history::{self, Status};
+use crate::network::BackendConfig;
+use crate::registry::{DeploymentRecord, Registry};
+use colored::Colorize;
+use musk::Network;
+use std::path::Path;
+
+/// Execute the status command
+///
+/// Reconstructs from chain data which of the deployments on `network`
+/// recorded in the registry at `registry_path` were unspent at `at_height`
+/// (the chain tip by default), optionally only those of the program with
+/// CMR `cmr`.
+///
+/// # Errors
+///
+/// Returns an error if the registry cannot be loaded, `at_height` is above
+/// the chain tip, or the chain cannot be scanned.
+pub fn status_command(
+    at_height: Option<u64>,
+    cmr: Option<&str>,
+    network: Network,
+    config: &BackendConfig,
+    registry_path: &Path,
+) -> Result<(), SprayError> {
+    crate::status!("{}", "Reconstructing deployment status...".cyan().bold());
+    crate::status!();
+
+    let registry = Registry::load(registry_path)?;
+    let network_name = network.to_string();
+    let records: Vec<DeploymentRecord> = registry
+        .records()
+        .iter()
+        .filter(|r| r.network == network_name && cmr.map_or(true, |cmr| r.cmr == cmr))
+        .cloned()
+        .collect();
+    if records.is_empty() {
+        crate::status!("{}", "No matching deployments in the registry".yellow());
+        return Ok(());
+    }
+
+    crate::status!("{} {network}", "Network:".dimmed());
+    let backend = config.create(network)?;
+    let tip = backend.block_count()?;
+    let height = at_height.unwrap_or(tip);
+    if height > tip {
+        return Err(SprayError::ConfigError(format!(
+            "Height {height} is above the chain tip ({tip})"
+        )));
+    }
+
+    let start = history::scan_start(&records);
+    crate::status!("{} {start} to {height}", "Scanning blocks:".dimmed());
+    let history = history::reconstruct(&backend, &records, start, height, |h, to| {
+        if !crate::output::is_quiet() {
+            eprint!("\r  {} {h}/{to}", "Scanned to".dimmed());
+        }
+    })?;
+    crate::status!();
+
+    crate::status!();
+    crate::status!("{} {height}", "Deployments at height".bold());
+    let mut unspent = 0;
+    for (index, record) in records.iter().enumerate() {
+        let status = history.status_at(index, height);
+        let rendered = status.to_string();
+        let rendered = match status {
+            Status::Unspent { .. } => {
+                unspent += 1;
+                rendered.green()
+            }
+            Status::Spent { .. } => rendered.dimmed(),
+            Status::Unfunded => rendered.yellow(),
+        };
+        crate::output::print_result(&format!(
+            "  {}:{} ({} sat) {rendered}",
+            record.txid, record.vout, record.amount
+        ));
+    }
+
+    crate::status!();
+    crate::status!(
+        "{}",
+        format!(
+            "✓ {unspent} of {} deployments unspent at height {height}",
+            records.len()
+        )
+        .green()
+        .bold()
+    );
+    Ok(())
+}
@@ -0,0 +1,242 @@
+//! Stress command implementation
+
+use crate::commands::redeem::load_program;
+use crate::env::TestEnv;
+use crate::error::SprayError;
+use crate::file_loader;
+use crate::stress::{Instance, ResourceSample, StressReport};
+use crate::test::{FailureStage, TestCase, TestResult};
+use colored::Colorize;
+use elementsd::bitcoind::bitcoincore_rpc::RpcApi;
+use musk::{InstantiatedProgram, WitnessValues};
+use std::path::{Path, PathBuf};
+use std::sync::Barrier;
+use std::time::Instant;
+
+/// Execute the stress command
+///
+/// Deploys and redeems `count` instances of the contract in `contract`
+/// against an ephemeral regtest daemon, `concurrency` at a time, and prints
+/// throughput, latency percentiles, failures by stage and the daemon's peak
+/// mempool usage. With `report`, the full [`StressReport`] is written there
+/// as JSON. Returns whether every instance was redeemed.
+///
+/// # Errors
+///
+/// Returns an error if the contract or witness cannot be loaded or the
+/// daemon cannot be started. Failing instances are counted, not returned.
+pub fn stress_command(
+    contract: &Path,
+    args: Option<PathBuf>,
+    witness: Option<PathBuf>,
+    count: usize,
+    concurrency: usize,
+    report_path: Option<PathBuf>,
+) -> Result<bool, SprayError> {
+    crate::status!("{}", "Stress testing contract...".cyan().bold());
+    crate::status!();
+
+    let program = load_program(contract, args.as_deref(), &[])?;
+    let witness_values = witness
+        .as_deref()
+        .map(file_loader::load_witness)
+        .transpose()?
+        .unwrap_or_default();
+
+    let concurrency = concurrency.max(1);
+    crate::status!("{} {count}", "Instances:".dimmed());
+    crate::status!("{} {concurrency}", "Concurrency:".dimmed());
+    crate::status!("{}", "Starting regtest daemon...".dimmed());
+    let env = TestEnv::new()?;
+
+    let mut report = StressReport::new(count, concurrency);
+    report.resources.push(sample(&env, "start")?);
+    let start = Instant::now();
+
+    let indices: Vec<usize> = (0..count).collect();
+    for (round, chunk) in indices.chunks(concurrency).enumerate() {
+        let instances = run_round(&env, &program, &witness_values, chunk)?;
+
+        report
+            .resources
+            .push(sample(&env, &format!("round {round} spent"))?);
+        env.generate(1)?;
+
+        let failed = instances.iter().filter(|i| i.failure.is_some()).count();
+        for instance in instances {
+            report.add(instance);
+        }
+        crate::status!(
+            "  {} {}/{count} ({failed} failed in this round)",
+            "Completed".dimmed(),
+            report.instances.len()
+        );
+    }
+
+    report.elapsed = start.elapsed();
+    report.resources.push(sample(&env, "end")?);
+
+    print_summary(&report);
+    if let Some(path) = report_path {
+        report.write_json(&path)?;
+        crate::status!("{} {}", "Report written to:".dimmed(), path.display());
+    }
+
+    Ok(report.succeeded() == count)
+}
+
+/// Deploy and redeem one instance per entry of `chunk` in parallel
+///
+/// All instances are funded, one block confirms the funding, and then all of
+/// them are spent. The spends are left in the mempool.
+///
+/// # Errors
+///
+/// Returns an error if the funding block cannot be mined. Failing instances
+/// are returned, not raised.
+pub(crate) fn run_round(
+    env: &TestEnv,
+    program: &InstantiatedProgram,
+    witness_values: &WitnessValues,
+    chunk: &[usize],
+) -> Result<Vec<Instance>, SprayError> {
+    // Instance threads meet the main thread at the barrier twice: once
+    // funded, and once the funding is confirmed
+    let barrier = Barrier::new(chunk.len() + 1);
+    std::thread::scope(|scope| {
+        let handles: Vec<_> = chunk
+            .iter()
+            .map(|&i| {
+                let barrier = &barrier;
+                let (program, witness_values) = (program.clone(), witness_values.clone());
+                scope.spawn(move || {
+                    let mut test = TestCase::new(env, program)
+                        .name(&format!("stress-{i}"))
+                        .witness(move |_| witness_values.clone());
+
+                    let deploy_start = Instant::now();
+                    let funded = test.create_utxo();
+                    let deploy = deploy_start.elapsed();
+                    barrier.wait();
+                    barrier.wait();
+
+                    if let Err(e) = funded {
+                        return Instance::failed(FailureStage::Funding, e.to_string());
+                    }
+                    let redeem_start = Instant::now();
+                    match test.run_staged() {
+                        Ok(TestResult::Success { txid, .. }) => {
+                            Instance::succeeded(deploy, redeem_start.elapsed())
+                                .with_txid(txid.to_string())
+                        }
+                        Ok(TestResult::Failure { error }) => {
+                            Instance::failed(FailureStage::Satisfaction, error)
+                        }
+                        Err((stage, e)) => {
+                            let error = e.to_string();
+                            Instance::failed(stage.refine(&error), error)
+                        }
+                    }
+                })
+            })
+            .collect();
+
+        barrier.wait();
+        let mined = env.generate(1);
+        barrier.wait();
+
+        let instances: Vec<Instance> = handles
+            .into_iter()
+            .map(|handle| {
+                handle.join().unwrap_or_else(|_| {
+                    Instance::failed(FailureStage::Satisfaction, "instance panicked")
+                })
+            })
+            .collect();
+        mined.map(|()| instances)
+    })
+}
+
+/// Sample the daemon's memory and mempool usage
+pub(crate) fn sample(env: &TestEnv, label: &str) -> Result<ResourceSample, SprayError> {
+    let call = |method: &str| {
+        env.daemon()
+            .client()
+            .call::<serde_json::Value>(method, &[])
+            .map_err(|e| SprayError::RpcError(e.to_string()))
+    };
+    Ok(ResourceSample::from_rpc(
+        label,
+        &call("getmemoryinfo")?,
+        &call("getmempoolinfo")?,
+    ))
+}
+
+/// Print throughput, latencies, failures and resource usage
+pub(crate) fn print_summary(report: &StressReport) {
+    let ms = |d: Option<std::time::Duration>| {
+        d.map_or_else(|| "-".to_string(), |d| format!("{} ms", d.as_millis()))
+    };
+
+    crate::status!();
+    if report.succeeded() == report.instances.len() {
+        crate::status!(
+            "{}",
+            format!("✓ {} instances redeemed", report.succeeded())
+                .green()
+                .bold()
+        );
+    } else {
+        crate::status!(
+            "{}",
+            format!(
+                "⚠ {} of {} instances failed ({:.1}%)",
+                report.instances.len() - report.succeeded(),
+                report.instances.len(),
+                report.failure_rate() * 100.0
+            )
+            .yellow()
+            .bold()
+        );
+        for (stage, n) in report.failures() {
+            crate::output::print_result(&format!("  {} {n}", format!("{stage}:").bold()));
+        }
+    }
+
+    crate::status!();
+    crate::status!("{}", "Throughput:".bold());
+    crate::output::print_result(&format!(
+        "  {} {:.2} redeems/s over {:.1} s",
+        "Rate:".bold(),
+        report.throughput(),
+        report.elapsed.as_secs_f64()
+    ));
+    crate::status!("{}", "Latency (p50 / p95 / max):".bold());
+    crate::output::print_result(&format!(
+        "  {} {} / {} / {}",
+        "Deploy:".bold(),
+        ms(report.deploy_percentile(50)),
+        ms(report.deploy_percentile(95)),
+        ms(report.deploy_percentile(100))
+    ));
+    crate::output::print_result(&format!(
+        "  {} {} / {} / {}",
+        "Redeem:".bold(),
+        ms(report.redeem_percentile(50)),
+        ms(report.redeem_percentile(95)),
+        ms(report.redeem_percentile(100))
+    ));
+    crate::status!("{}", "Daemon:".bold());
+    crate::output::print_result(&format!(
+        "  {} {} bytes",
+        "Peak mempool usage:".bold(),
+        report.peak_mempool_usage()
+    ));
+    if let Some(end) = report.resources.last() {
+        crate::output::print_result(&format!(
+            "  {} {} bytes",
+            "Locked memory in use:".bold(),
+            end.memory_used
+        ));
+    }
+}
@@ -0,0 +1,329 @@
+//! Manifest-driven test suite execution
+
+use crate::error::SprayError;
+use crate::eta::{DurationHistory, Eta};
+use crate::manifest::{discover_programs, run_concurrently, Manifest, Progress, SuiteSpec};
+use crate::network::BackendConfig;
+use crate::report::{format_ms, SuiteReport, TestRecord, DEFAULT_SLOWEST_COUNT};
+use colored::Colorize;
+use std::path::{Path, PathBuf};
+
+/// Execute the test suites declared in a manifest
+///
+/// Without `all_networks`, only suites targeting a local regtest node run,
+/// one after another. With `all_networks`, every suite runs concurrently
+/// against its own backend. When `report_dir` is given, each suite's report
+/// is written there as `<suite name>.json`. With `conf_target`, the fee of
+/// every test is estimated for that many blocks, overriding the manifest.
+/// In TAP mode, the tests of all suites form one stream, named
+/// `<suite>/<test>`, and in JSON mode one report under those names. With `coverage`, the branch coverage of each suite's
+/// programs is printed after its summary; it is not collected with
+/// `all_networks`. Every suite's backend is created with the settings of
+/// `backend` and the suite's own config file.
+///
+/// With `history`, an estimate of the time left is printed after each test,
+/// and tests taking more than `slow_factor` times their usual duration are
+/// flagged as they finish and listed at the end. Neither is shown with
+/// `all_networks`.
+///
+/// Returns `true` if every test in every suite passed.
+///
+/// # Errors
+///
+/// Returns an error if the manifest cannot be loaded or a report cannot be
+/// written.
+pub fn manifest_command(
+    manifest_path: &Path,
+    all_networks: bool,
+    report_dir: Option<&Path>,
+    conf_target: Option<u16>,
+    coverage: bool,
+    history: Option<&DurationHistory>,
+    slow_factor: f64,
+    backend: &BackendConfig,
+) -> Result<bool, SprayError> {
+    let mut manifest = Manifest::load(manifest_path)?;
+    manifest.set_backend(backend);
+    if let Some(conf_target) = conf_target {
+        manifest.set_conf_target(conf_target);
+    }
+
+    let suites: Vec<&SuiteSpec> = if all_networks {
+        manifest.suites.iter().collect()
+    } else {
+        manifest.local_suites().collect()
+    };
+
+    if suites.is_empty() {
+        return Err(SprayError::ConfigError(format!(
+            "No suites to run in {}",
+            manifest_path.display()
+        )));
+    }
+
+    crate::status!("\n{}", "Running suites...".bold().cyan());
+    for suite in &suites {
+        crate::status!(
+            "  {} ({}, {} tests)",
+            suite.name.bold(),
+            suite.network,
+            suite.tests.len()
+        );
+    }
+    crate::status!("{}", "─".repeat(60).dimmed());
+
+    let mut eta = history.filter(|_| !all_networks).map(|history| {
+        Eta::new(
+            history,
+            suites
+                .iter()
+                .flat_map(|suite| suite.tests.iter().map(|test| test.name.as_str())),
+            slow_factor,
+        )
+    });
+    let results: Vec<Result<SuiteReport, SprayError>> = if all_networks {
+        run_concurrently(&suites)
+    } else {
+        let mut offset = 0;
+        suites
+            .iter()
+            .map(|suite| {
+                let result = run_timed(suite, coverage, eta.as_mut(), offset);
+                offset += suite.tests.len();
+                result
+            })
+            .collect()
+    };
+
+    crate::status!("{}", "─".repeat(60).dimmed());
+
+    if let Some(dir) = report_dir {
+        std::fs::create_dir_all(dir)?;
+    }
+
+    let mut all_passed = true;
+    let mut records = Vec::new();
+    let mut coverages = Vec::new();
+    for (suite, result) in suites.iter().zip(results) {
+        match result {
+            Ok(report) => {
+                records.extend(report.tests.iter().map(|record| TestRecord {
+                    name: format!("{}/{}", suite.name, record.name),
+                    ..record.clone()
+                }));
+                let passed = report.passed();
+                let failed = report.failed();
+                all_passed &= failed == 0;
+
+                let status = if failed == 0 {
+                    "✓".green().bold()
+                } else {
+                    "⚠".yellow().bold()
+                };
+                crate::status!(
+                    "{status} {}: {} passed, {} failed",
+                    suite.name.bold(),
+                    passed.to_string().green(),
+                    failed.to_string().red()
+                );
+                for coverage in &report.coverage {
+                    coverage.print();
+                }
+
+                if let Some(dir) = report_dir {
+                    report.write_json(&dir.join(format!("{}.json", suite.name)))?;
+                }
+                coverages.extend(report.coverage);
+            }
+            Err(e) => {
+                all_passed = false;
+                records.push(TestRecord::failure(&suite.name, 0, e.to_string()));
+                crate::status!(
+                    "{} {}: {}",
+                    "❌".red(),
+                    suite.name.bold(),
+                    e.to_string().red()
+                );
+            }
+        }
+    }
+    if let Some(eta) = &eta {
+        eta.print_slow();
+    }
+    crate::output::emit_tap(&records);
+    crate::output::emit(
+        if all_passed {
+            "test.passed"
+        } else {
+            "test.failed"
+        },
+        all_passed,
+        &SuiteReport {
+            coverage: coverages,
+            ..SuiteReport::new(records, DEFAULT_SLOWEST_COUNT)
+        },
+    )?;
+
+    Ok(all_passed)
+}
+
+/// Run a suite, with coverage if `coverage`, updating `eta` as its tests
+/// finish; the suite's tests start at `offset` in the estimate
+fn run_timed(
+    suite: &SuiteSpec,
+    coverage: bool,
+    mut eta: Option<&mut Eta>,
+    offset: usize,
+) -> Result<SuiteReport, SprayError> {
+    let progress = |event: Progress<'_>| {
+        let (Some(eta), Progress::Finished { index, record, .. }) = (eta.as_deref_mut(), event)
+        else {
+            return;
+        };
+        if let Some(slow) = eta.finish(offset + index, record.duration_ms) {
+            crate::status!("  {} {slow}", "⚠ Slower than usual:".yellow());
+        }
+        crate::status!(
+            "  {} {}/{} tests done, about {} left",
+            "ETA:".dimmed(),
+            eta.finished(),
+            eta.total(),
+            format_ms(eta.remaining_ms())
+        );
+    };
+    if coverage {
+        suite.run_with_coverage_and_progress(progress)
+    } else {
+        suite.run_with_progress(progress)
+    }
+}
+
+/// The regtest suite of `programs` and every `.simf` file directly inside
+/// `dir`, named after `dir`, on a backend created with the settings of
+/// `backend`
+///
+/// # Errors
+///
+/// Returns an error if `dir` cannot be read or no programs are found.
+pub fn program_suite(
+    programs: &[PathBuf],
+    dir: Option<&Path>,
+    backend: &BackendConfig,
+) -> Result<SuiteSpec, SprayError> {
+    let mut programs = programs.to_vec();
+    if let Some(dir) = dir {
+        programs.extend(discover_programs(dir)?);
+    }
+    if programs.is_empty() {
+        return Err(SprayError::ConfigError("No .simf programs to test".into()));
+    }
+
+    let name = dir.and_then(Path::file_name).map_or_else(
+        || "programs".to_string(),
+        |n| n.to_string_lossy().into_owned(),
+    );
+    Ok(SuiteSpec {
+        backend: backend.clone(),
+        ..SuiteSpec::for_programs(&name, &programs)
+    })
+}
+
+/// Execute `spray test` for several programs
+///
+/// Runs `programs`, and every `.simf` file directly inside `dir`, as one
+/// regtest suite sharing a single backend, created with the settings of
+/// `backend`. Each program's arguments and witness are taken from
+/// `<name>.args.json` and `<name>.wit.json` (or their TOML equivalents)
+/// next to it. With `report`, the suite report is
+/// written there as JSON. With `coverage`, the branch coverage of each
+/// program is printed and included in the report. With `history`, the time
+/// left and slower-than-usual tests are shown as in [`manifest_command`].
+///
+/// Returns `true` if every program passed.
+///
+/// # Errors
+///
+/// Returns an error if `dir` cannot be read, no programs are found, the
+/// daemon cannot be started or the report cannot be written.
+pub fn programs_command(
+    programs: &[PathBuf],
+    dir: Option<&Path>,
+    report_path: Option<&Path>,
+    coverage: bool,
+    history: Option<&DurationHistory>,
+    slow_factor: f64,
+    backend: &BackendConfig,
+) -> Result<bool, SprayError> {
+    let suite = program_suite(programs, dir, backend)?;
+
+    crate::status!("\n{}", "Running tests...".bold().cyan());
+    for test in &suite.tests {
+        let mut inputs = Vec::new();
+        if let Some(args) = &test.args {
+            inputs.push(format!("args: {}", args.display()));
+        }
+        if let Some(witness) = &test.witness {
+            inputs.push(format!("witness: {}", witness.display()));
+        }
+        crate::status!(
+            "  {} {}",
+            test.file.display().to_string().bold(),
+            if inputs.is_empty() {
+                String::new()
+            } else {
+                format!("({})", inputs.join(", ")).dimmed().to_string()
+            }
+        );
+    }
+    crate::status!("{}", "─".repeat(60).dimmed());
+
+    let mut eta = history.map(|history| {
+        Eta::new(
+            history,
+            suite.tests.iter().map(|test| test.name.as_str()),
+            slow_factor,
+        )
+    });
+    let report = run_timed(&suite, coverage, eta.as_mut(), 0)?;
+    crate::status!("{}", "─".repeat(60).dimmed());
+
+    let failed = report.failed();
+    if failed == 0 {
+        crate::status!(
+            "\n{} {} tests passed",
+            "✓".green().bold(),
+            report.passed().to_string().green().bold()
+        );
+    } else {
+        crate::status!(
+            "\n{} {} passed, {} failed",
+            "⚠".yellow().bold(),
+            report.passed().to_string().green(),
+            failed.to_string().red().bold()
+        );
+    }
+    report.print_failure_stages();
+    report.print_timing();
+    if let Some(eta) = &eta {
+        eta.print_slow();
+    }
+    for coverage in &report.coverage {
+        coverage.print();
+    }
+
+    if let Some(path) = report_path {
+        report.write_json(path)?;
+    }
+    crate::output::emit(
+        if failed == 0 {
+            "test.passed"
+        } else {
+            "test.failed"
+        },
+        failed == 0,
+        &report,
+    )?;
+    crate::output::emit_tap(&report.tests);
+
+    Ok(failed == 0)
+}
@@ -0,0 +1,216 @@
+//! Test command implementation
+
+use crate::cache::ProgramCache;
+use crate::destination::Destination;
+use crate::env::TestEnvBuilder;
+use crate::error::SprayError;
+use crate::file_loader;
+use crate::report::{SuiteReport, TestRecord};
+use crate::runner::TestRunner;
+use crate::sourcemap::SourceMap;
+use crate::test::{FailureStage, TestCase};
+use crate::trace::{self, Tracer};
+use crate::verify::{SpendContext, DEFAULT_AMOUNT};
+use crate::watcher::{FileWatcher, DEFAULT_POLL_INTERVAL};
+use crate::witness::SigningWitness;
+use colored::Colorize;
+use musk::elements::hashes::Hash;
+use musk::elements::{LockTime, Sequence};
+use musk::InstantiatedProgram;
+use std::path::{Path, PathBuf};
+
+/// Options shared by every run of a watched test
+pub struct WatchOptions<'a> {
+    /// Path to the .simf program file
+    pub file: &'a Path,
+    /// Path to the arguments file
+    pub args: Option<&'a Path>,
+    /// Argument overrides merged over the arguments file
+    pub arg_overrides: &'a [(String, String)],
+    /// Path to the witness file
+    pub witness: Option<&'a Path>,
+    /// Witness overrides merged over the witness file
+    pub wit_overrides: &'a [(String, String)],
+    /// Test name
+    pub name: &'a str,
+    /// Lock time for the spending transaction
+    pub lock_time: Option<u32>,
+    /// Sequence number for the spending transaction
+    pub sequence: Option<u32>,
+    /// Submit the funding transaction and the spend together as a package
+    pub package: bool,
+    /// Where the spend pays to, instead of the wallet
+    pub destination: Option<&'a Destination>,
+    /// Path to write a JSON report to after every run
+    pub report: Option<&'a Path>,
+    /// Run the daemon in [deterministic mode](crate::deterministic)
+    pub deterministic: bool,
+    /// Lines of `debug.log` attached to node-side failures, see
+    /// [`TestRunner::log_tail`]
+    pub log_tail: usize,
+}
+
+/// Execute `spray test --watch`
+///
+/// Starts one regtest daemon and keeps it alive while the program,
+/// arguments and witness files are watched. The test runs once immediately
+/// and again, recompiled from the current files, whenever one of them
+/// changes; a program whose source and arguments are unchanged since an
+/// earlier run is not compiled again. Compilation and test failures are printed and watching
+/// continues; the command runs until interrupted. In JSON mode, every run
+/// prints its report, or its error, as a document.
+///
+/// # Errors
+///
+/// Returns an error if the test environment cannot be started.
+pub fn test_watch_command(options: &WatchOptions<'_>) -> Result<(), SprayError> {
+    crate::status!("{}", "Initializing test environment...".dimmed());
+    let env = TestEnvBuilder::new()
+        .deterministic(options.deterministic)
+        .build()?;
+    let runner = TestRunner::with_env(env).log_tail(options.log_tail);
+    let programs = ProgramCache::default();
+
+    let mut watcher = FileWatcher::new(watched_files(options.file, options.args, options.witness));
+
+    loop {
+        if let Err(e) = run_once(&runner, &programs, options) {
+            if crate::output::is_json() {
+                println!("{}", crate::output::error_document(&e));
+            } else {
+                println!("{} {}", "❌".red(), e.to_string().red());
+            }
+        }
+
+        crate::say!();
+        crate::say!(
+            "{}",
+            "Watching for changes (press Ctrl-C to stop)...".dimmed()
+        );
+        for path in watcher.paths() {
+            crate::status!("  {}", path.display().to_string().dimmed());
+        }
+
+        let changed = watcher.wait(DEFAULT_POLL_INTERVAL);
+        crate::say!();
+        crate::status!("{}", "─".repeat(60).dimmed());
+        for path in changed {
+            crate::say!("{} {}", "Changed:".yellow(), path.display());
+        }
+    }
+}
+
+/// Recompile and run the test once
+fn run_once(
+    runner: &TestRunner,
+    programs: &ProgramCache,
+    options: &WatchOptions<'_>,
+) -> Result<(), SprayError> {
+    let compiled = match programs.load(options.file, options.args, options.arg_overrides) {
+        Ok(compiled) => compiled,
+        Err(e) => {
+            write_report(
+                options.report,
+                TestRecord::failure(options.name, 0, e.to_string())
+                    .with_stage(FailureStage::Compile),
+            )?;
+            return Err(e);
+        }
+    };
+
+    let witness = file_loader::load_signing_witness(
+        options.witness,
+        options.wit_overrides,
+        &crate::compiled::witness_types(&compiled),
+    )?;
+
+    let mut test = TestCase::new(runner.env(), compiled.clone())
+        .name(options.name)
+        .witness_provider(witness.clone());
+    if let Some(lt) = options.lock_time {
+        test = test.lock_time(LockTime::from_consensus(lt));
+    }
+    if let Some(seq) = options.sequence {
+        test = test.sequence(Sequence::from_consensus(seq));
+    }
+    test = test.package(options.package);
+    if let Some(destination) = options.destination {
+        test = test.destination(destination.clone());
+    }
+
+    let (_, record) = runner.run_timed(test);
+    if matches!(
+        record.stage,
+        Some(FailureStage::Satisfaction | FailureStage::NodeRejection)
+    ) {
+        print_source_location(options, &compiled, &witness);
+    }
+    let report = SuiteReport::new(vec![record], 1);
+    if let Some(path) = options.report {
+        report.write_json(path)?;
+    }
+    let passed = report.failed() == 0;
+    crate::output::emit(
+        if passed { "test.passed" } else { "test.failed" },
+        passed,
+        &report,
+    )
+}
+
+/// Print the source a failed spend maps back to, if it does
+///
+/// Neither the node nor satisfaction errors say where the program failed, so
+/// the spend is executed again locally, in a synthetic context with the same
+/// lock time and sequence, and its trace is mapped to the source.
+fn print_source_location(
+    options: &WatchOptions<'_>,
+    program: &InstantiatedProgram,
+    witness: &SigningWitness,
+) {
+    let mut context = SpendContext::synthetic(program, DEFAULT_AMOUNT);
+    if let Some(lt) = options.lock_time {
+        context.lock_time = LockTime::from_consensus(lt);
+    }
+    if let Some(seq) = options.sequence {
+        context.sequence = Sequence::from_consensus(seq);
+    }
+    let Ok(env) = trace::environment(program, &context) else {
+        return;
+    };
+    let sighash = env.c_tx_env().sighash_all().to_byte_array();
+    let snippet = witness
+        .resolve(sighash)
+        .and_then(|values| {
+            Tracer::new()
+                .jets_only(true)
+                .trace_spend(program, values, &context)
+        })
+        .ok()
+        .zip(SourceMap::load(options.file).ok())
+        .and_then(|(trace, map)| map.render(&trace));
+    if let Some(snippet) = snippet {
+        for line in snippet.lines() {
+            crate::status!("   {line}");
+        }
+    }
+}
+
+/// Write a single-test report, if a report path was given
+fn write_report(path: Option<&Path>, record: TestRecord) -> Result<(), SprayError> {
+    if let Some(path) = path {
+        SuiteReport::new(vec![record], 1).write_json(path)?;
+    }
+    Ok(())
+}
+
+/// Files watched for a test: the program, the files it includes, and any
+/// arguments or witness file
+#[must_use]
+pub fn watched_files(file: &Path, args: Option<&Path>, witness: Option<&Path>) -> Vec<PathBuf> {
+    std::iter::once(file)
+        .chain(args)
+        .chain(witness)
+        .map(Path::to_path_buf)
+        .chain(crate::include::includes(file).unwrap_or_default())
+        .collect()
+}
@@ -0,0 +1,224 @@
+//! TUI command implementation
+
+use crate::commands::suite::program_suite;
+use crate::dashboard::{spend_events, Dashboard, TestStatus};
+use crate::error::SprayError;
+use crate::events::EventSchema;
+use crate::manifest::{Manifest, Progress, SuiteSpec};
+use crate::network::BackendConfig;
+use crate::report::TestRecord;
+use std::io::{IsTerminal, Write};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Sender};
+
+/// Something for the dashboard to show
+enum Update {
+    /// The test at this position of the run started
+    Started(usize),
+    /// The test at this position of the run finished, with its chain events
+    Finished(usize, TestRecord, Vec<String>),
+    /// A suite finished, or could not start with this error
+    SuiteDone(usize, Option<String>),
+    /// A line typed by the user
+    Input(String),
+}
+
+/// Execute `spray tui`
+///
+/// Runs the suites of a manifest, or `programs` and the `.simf` files in
+/// `dir` as one regtest suite, and draws a dashboard of the run that is
+/// redrawn as tests start and finish. As in `spray test --manifest`, only
+/// local regtest suites run unless `all_networks` is set, in which case
+/// every suite runs concurrently, and backends are created with the
+/// settings of `backend`. With `events`, contract events in the spends are
+/// decoded with that schema.
+///
+/// When stdin is a terminal, typing a test number and Enter expands or
+/// collapses its details, `f` expands every failure, and `q` quits once the
+/// run has finished. When stdout is not a terminal, only the final frame is
+/// drawn.
+///
+/// Returns `true` if every test passed.
+///
+/// # Errors
+///
+/// Returns an error if the manifest, programs or schema cannot be loaded.
+pub fn tui_command(
+    manifest_path: Option<&Path>,
+    programs: &[PathBuf],
+    dir: Option<&Path>,
+    all_networks: bool,
+    events: Option<&Path>,
+    backend: &BackendConfig,
+) -> Result<bool, SprayError> {
+    let schema = events.map(EventSchema::load).transpose()?;
+
+    let mut manifest = manifest_path.map(Manifest::load).transpose()?;
+    if let Some(manifest) = &mut manifest {
+        manifest.set_backend(backend);
+    }
+    let programs = match &manifest {
+        Some(_) => None,
+        None => Some(program_suite(programs, dir, backend)?),
+    };
+    let suites: Vec<&SuiteSpec> = match (&manifest, &programs) {
+        (Some(manifest), _) if all_networks => manifest.suites.iter().collect(),
+        (Some(manifest), _) => manifest.local_suites().collect(),
+        (None, suite) => suite.iter().collect(),
+    };
+    if suites.iter().all(|suite| suite.tests.is_empty()) {
+        return Err(SprayError::ConfigError("No tests to run".into()));
+    }
+
+    let offsets: Vec<usize> = suites
+        .iter()
+        .scan(0, |next, suite| {
+            let offset = *next;
+            *next += suite.tests.len();
+            Some(offset)
+        })
+        .collect();
+    let mut dashboard = Dashboard::new(suites.iter().flat_map(|suite| {
+        suite
+            .tests
+            .iter()
+            .map(|test| (suite.name.as_str(), test.name.as_str()))
+    }));
+
+    let live = std::io::stdout().is_terminal();
+    let interactive = live && std::io::stdin().is_terminal();
+    let (tx, rx) = mpsc::channel();
+    if interactive {
+        let tx = tx.clone();
+        std::thread::spawn(move || {
+            for line in std::io::stdin().lines() {
+                let Ok(line) = line else { break };
+                if tx.send(Update::Input(line)).is_err() {
+                    break;
+                }
+            }
+        });
+    }
+
+    let was_quiet = crate::output::is_quiet();
+    crate::output::set_quiet(true);
+    std::thread::scope(|scope| {
+        let schema = schema.as_ref();
+        if all_networks {
+            for (i, suite) in suites.iter().enumerate() {
+                let tx = tx.clone();
+                let offset = offsets[i];
+                scope.spawn(move || run_suite(suite, i, offset, schema, &tx));
+            }
+        } else {
+            let tx = tx.clone();
+            let suites = &suites;
+            let offsets = &offsets;
+            scope.spawn(move || {
+                for (i, suite) in suites.iter().enumerate() {
+                    run_suite(suite, i, offsets[i], schema, &tx);
+                }
+            });
+        }
+        drop(tx);
+
+        let mut remaining = suites.len();
+        if live {
+            draw(&dashboard, interactive, remaining == 0);
+        }
+        for update in &rx {
+            match update {
+                Update::Started(index) => dashboard.start(index),
+                Update::Finished(index, record, events) => {
+                    dashboard.finish(index, record);
+                    for event in events {
+                        dashboard.event(event);
+                    }
+                }
+                Update::SuiteDone(i, error) => {
+                    remaining -= 1;
+                    if let Some(error) = error {
+                        for (j, test) in suites[i].tests.iter().enumerate() {
+                            let index = offsets[i] + j;
+                            if !matches!(
+                                dashboard.status(index),
+                                TestStatus::Passed | TestStatus::Failed
+                            ) {
+                                dashboard.finish(
+                                    index,
+                                    TestRecord::failure(&test.name, 0, error.clone()),
+                                );
+                            }
+                        }
+                    }
+                }
+                Update::Input(input) => match input.trim() {
+                    "q" if remaining == 0 => break,
+                    "f" => dashboard.expand_failures(),
+                    n => {
+                        if let Some(index) = n.parse::<usize>().ok().and_then(|n| n.checked_sub(1))
+                        {
+                            dashboard.toggle(index);
+                        }
+                    }
+                },
+            }
+            if live {
+                draw(&dashboard, interactive, remaining == 0);
+            }
+        }
+    });
+    crate::output::set_quiet(was_quiet);
+
+    if !live {
+        dashboard.expand_failures();
+        print!("{}", dashboard.render());
+    }
+    Ok(dashboard.failed() == 0)
+}
+
+/// Run a suite, forwarding its progress to the dashboard
+fn run_suite(
+    suite: &SuiteSpec,
+    i: usize,
+    offset: usize,
+    schema: Option<&EventSchema>,
+    tx: &Sender<Update>,
+) {
+    let result = suite.run_with_progress(|progress| {
+        let update = match progress {
+            Progress::Started { index, .. } => Update::Started(offset + index),
+            Progress::Finished {
+                index,
+                test,
+                record,
+                spend,
+            } => {
+                let events = spend
+                    .map(|spend| spend_events(spend, schema))
+                    .unwrap_or_default()
+                    .into_iter()
+                    .map(|event| format!("{}/{}: {event}", suite.name, test.name))
+                    .collect();
+                Update::Finished(offset + index, record.clone(), events)
+            }
+        };
+        let _ = tx.send(update);
+    });
+    let _ = tx.send(Update::SuiteDone(i, result.err().map(|e| e.to_string())));
+}
+
+/// Redraw the dashboard over the previous frame, with the key help if
+/// `interactive`
+fn draw(dashboard: &Dashboard, interactive: bool, done: bool) {
+    let help = match (interactive, done) {
+        (false, _) => "",
+        (true, false) => "Number + Enter: expand/collapse, f: expand failures",
+        (true, true) => {
+            "Run finished. Number + Enter: expand/collapse, f: expand failures, q: quit"
+        }
+    };
+    let mut stdout = std::io::stdout().lock();
+    let _ = write!(stdout, "\x1b[H\x1b[J{}\n{help}\n", dashboard.render());
+    let _ = stdout.flush();
+}
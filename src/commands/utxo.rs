@@ -0,0 +1,140 @@
+//! UTXO command implementations
+
+use crate::commands::redeem::parse_utxo_ref;
+use crate::error::SprayError;
+use crate::holdings::{self, Holding};
+use crate::network::BackendConfig;
+use crate::utxo::UtxoDescription;
+use colored::Colorize;
+use musk::client::NodeClient;
+use musk::Network;
+use serde_json::json;
+use std::path::PathBuf;
+
+/// Execute the `utxo export` command
+///
+/// Fetches the funding transaction of `utxo_ref` and writes a
+/// self-contained [`UtxoDescription`] to `out`, or prints it to stdout.
+///
+/// # Errors
+///
+/// Returns an error if the UTXO cannot be fetched or the file cannot be written.
+pub fn utxo_export_command(
+    utxo_ref: &str,
+    out: Option<PathBuf>,
+    network: Network,
+    config: &BackendConfig,
+) -> Result<(), SprayError> {
+    let (txid, vout) = parse_utxo_ref(utxo_ref)?;
+
+    crate::status!("{}", "Exporting UTXO description...".cyan().bold());
+    crate::status!("{} {txid}:{vout}", "UTXO:".dimmed());
+    crate::status!("{} {network}", "Network:".dimmed());
+
+    let mut backend = config.create(network)?;
+
+    let tx = backend
+        .get_transaction(&txid)
+        .map_err(|e| SprayError::RpcError(e.to_string()))?;
+    let genesis_hash = backend.genesis_hash()?;
+    let confirmations = backend.confirmations(&txid).ok();
+
+    let description =
+        UtxoDescription::new(&network.to_string(), genesis_hash, &tx, vout, confirmations)?;
+
+    if description.amount.is_none() {
+        crate::status!(
+            "{}",
+            "Warning: output is confidential; it must be unblinded before spending".yellow()
+        );
+    }
+
+    if let Some(path) = out {
+        description.save(&path)?;
+        crate::status!();
+        crate::status!(
+            "{} {}",
+            "✓ UTXO description written to".green().bold(),
+            path.display()
+        );
+    } else {
+        crate::output::print_result(&serde_json::to_string_pretty(&description)?);
+    }
+
+    Ok(())
+}
+
+/// Execute the `utxos` command
+///
+/// Lists the unspent outputs paying to the contract named by `target`: an
+/// address, a CMR, or a compiled artifact file. In human mode the outputs
+/// are printed as a table, one per line on stdout.
+///
+/// # Errors
+///
+/// Returns an error if the contract cannot be resolved or the node cannot
+/// be queried.
+pub fn utxos_command(
+    target: &str,
+    network: Network,
+    config: &BackendConfig,
+) -> Result<(), SprayError> {
+    crate::status!("{}", "Listing contract UTXOs...".cyan().bold());
+    crate::status!();
+
+    crate::status!("{} {network}", "Network:".dimmed());
+    let backend = config.create(network)?;
+    let address = holdings::resolve_address(target, backend.address_params())?;
+    crate::status!("{} {address}", "Address:".dimmed());
+
+    let (mut utxos, source) =
+        holdings::fetch(|method, params| backend.call(method, params), &address)?;
+    // Oldest first
+    utxos.sort_by(|a, b| {
+        b.confirmations
+            .cmp(&a.confirmations)
+            .then_with(|| (&a.txid, a.vout).cmp(&(&b.txid, b.vout)))
+    });
+    crate::status!("{} {source}", "Source:".dimmed());
+    crate::status!();
+
+    if utxos.is_empty() {
+        crate::status!("  {}", "(no unspent outputs)".dimmed());
+    } else {
+        crate::status!(
+            "{}",
+            format!(
+                "{:<69} {:>16} {:<64} {:>13}",
+                "OUTPOINT", "AMOUNT (sat)", "ASSET", "CONFIRMATIONS"
+            )
+            .bold()
+        );
+        for utxo in &utxos {
+            crate::output::print_result(&table_row(utxo));
+        }
+    }
+    crate::status!();
+    crate::status!("{} {}", "Outputs:".dimmed(), utxos.len());
+
+    crate::output::emit(
+        "utxos.success",
+        true,
+        &json!({
+            "address": address.to_string(),
+            "source": source,
+            "utxos": utxos,
+        }),
+    )
+}
+
+/// A line of the `utxos` table
+fn table_row(utxo: &Holding) -> String {
+    format!(
+        "{:<69} {:>16} {:<64} {:>13}",
+        format!("{}:{}", utxo.txid, utxo.vout),
+        utxo.amount
+            .map_or_else(|| "confidential".to_string(), |amount| amount.to_string()),
+        utxo.asset.as_deref().unwrap_or("confidential"),
+        utxo.confirmations
+    )
+}
@@ -0,0 +1,135 @@
+//! Verify command implementation
+
+use crate::commands::debug::print_source_location;
+use crate::commands::redeem::load_program;
+use crate::error::SprayError;
+use crate::file_loader;
+use crate::hex;
+use crate::trace::Tracer;
+use crate::utxo::UtxoDescription;
+use crate::verify::{self, SpendContext};
+use colored::Colorize;
+use musk::elements::{BlockHash, LockTime, Sequence};
+use musk::InstantiatedProgram;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+/// Execute the verify command
+///
+/// Satisfies and executes the program in `program_file` with the witness in
+/// `witness_file`, without a node. The spend is built from the UTXO
+/// description in `utxo_file` if given, or else from a synthetic UTXO of
+/// `amount` satoshis.
+///
+/// Returns `false` if the witness does not satisfy the program. The failing
+/// execution is then traced to point at the source it failed in.
+///
+/// # Errors
+///
+/// Returns an error if the program, witness or UTXO description cannot be
+/// loaded, or the spend cannot be built.
+#[allow(clippy::too_many_arguments)]
+pub fn verify_command(
+    program_file: &Path,
+    witness_file: &Path,
+    args: Option<PathBuf>,
+    utxo_file: Option<PathBuf>,
+    amount: u64,
+    genesis_hash: Option<String>,
+    lock_time: Option<u32>,
+    sequence: Option<u32>,
+    fee: u64,
+) -> Result<bool, SprayError> {
+    crate::status!("{}", "Verifying witness locally...".cyan().bold());
+    crate::status!();
+
+    crate::status!("{} {}", "Program:".dimmed(), program_file.display());
+    let program = load_program(program_file, args.as_deref(), &[])?;
+    crate::status!("{} {}", "Witness:".dimmed(), witness_file.display());
+    let witness = file_loader::load_witness(witness_file)?;
+    let context = spend_context(
+        &program,
+        utxo_file,
+        amount,
+        genesis_hash,
+        lock_time,
+        sequence,
+        fee,
+    )?;
+
+    let verification = verify::verify(&program, witness, &context)?;
+    crate::status!(
+        "{} {}",
+        "Sighash:".dimmed(),
+        hex::encode(&verification.sighash)
+    );
+
+    crate::status!();
+    match verification.result {
+        Ok(weight) => {
+            crate::status!("{}", "✓ Witness satisfies the program".green().bold());
+            crate::output::print_result(&format!(
+                "  {} {} vB",
+                "Spend size:".bold(),
+                weight.div_ceil(4)
+            ));
+            Ok(true)
+        }
+        Err((kind, error)) => {
+            crate::status!("{}", format!("✗ Verification failed: {kind}").red().bold());
+            crate::output::print_result(&format!("  {error}"));
+            let witness = file_loader::load_witness(witness_file)?;
+            if let Ok(trace) = Tracer::new()
+                .jets_only(true)
+                .trace_spend(&program, witness, &context)
+            {
+                print_source_location(program_file, &trace);
+            }
+            Ok(false)
+        }
+    }
+}
+
+/// Build the spend context of a local verification, printing the UTXO used
+///
+/// The UTXO is taken from the description in `utxo_file` if given, or else
+/// is a synthetic UTXO of `amount` satoshis.
+///
+/// # Errors
+///
+/// Returns an error if the UTXO description cannot be loaded or the genesis
+/// hash is invalid.
+pub(crate) fn spend_context(
+    program: &InstantiatedProgram,
+    utxo_file: Option<PathBuf>,
+    amount: u64,
+    genesis_hash: Option<String>,
+    lock_time: Option<u32>,
+    sequence: Option<u32>,
+    fee: u64,
+) -> Result<SpendContext, SprayError> {
+    let mut context = if let Some(utxo_file) = utxo_file {
+        crate::status!("{} {}", "UTXO:".dimmed(), utxo_file.display());
+        let description = UtxoDescription::load(&utxo_file)?;
+        SpendContext {
+            utxo: description.to_utxo()?,
+            genesis_hash: description.genesis_hash()?,
+            ..SpendContext::synthetic(program, amount)
+        }
+    } else {
+        crate::status!("{} synthetic, {amount} sat", "UTXO:".dimmed());
+        SpendContext::synthetic(program, amount)
+    };
+    if let Some(hash) = genesis_hash {
+        context.genesis_hash = BlockHash::from_str(&hash)
+            .map_err(|e| SprayError::ParseError(format!("Invalid genesis hash: {e}")))?;
+    }
+    if let Some(lock_time) = lock_time {
+        context.lock_time = LockTime::from_consensus(lock_time);
+    }
+    if let Some(sequence) = sequence {
+        context.sequence = Sequence::from_consensus(sequence);
+    }
+    context.fee = fee;
+    Ok(context)
+}
@@ -0,0 +1,124 @@
+//! Watch command implementation
+
+use crate::error::SprayError;
+use crate::holdings::{self, Balance, FundingEvent, FundingWatch};
+use crate::network::BackendConfig;
+use colored::Colorize;
+use musk::Network;
+use serde_json::json;
+use std::io::Write;
+use std::path::Path;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Execute the watch command
+///
+/// Polls the outputs paying to the contract named by `target` (an address,
+/// a CMR, or a compiled artifact file) every `interval`, and prints each
+/// output as it arrives and confirms. With `record`, the events are also
+/// appended to that file as JSON lines. With `once`, the command returns
+/// after the first output that was not there when watching started.
+///
+/// # Errors
+///
+/// Returns an error if the contract cannot be resolved, the node cannot be
+/// queried, or the record file cannot be written.
+pub fn watch_command(
+    target: &str,
+    interval: Duration,
+    once: bool,
+    record: Option<&Path>,
+    network: Network,
+    config: &BackendConfig,
+) -> Result<(), SprayError> {
+    crate::status!("{}", "Watching contract address...".cyan().bold());
+    crate::status!();
+
+    crate::status!("{} {network}", "Network:".dimmed());
+    let backend = config.create(network)?;
+    let address = holdings::resolve_address(target, backend.address_params())?;
+    crate::status!("{} {address}", "Address:".dimmed());
+
+    let fetch = || holdings::fetch(|method, params| backend.call(method, params), &address);
+    let (existing, source) = fetch()?;
+    crate::status!("{} {source}", "Source:".dimmed());
+    let balance = Balance::new(&existing);
+    crate::status!(
+        "{} {} outputs already there",
+        "Existing:".dimmed(),
+        balance.utxos
+    );
+    crate::status!(
+        "{}",
+        format!("Polling every {}s; Ctrl-C to stop", interval.as_secs_f64()).dimmed()
+    );
+    crate::status!();
+
+    let mut watch = FundingWatch::new(&existing);
+    loop {
+        std::thread::sleep(interval);
+        let (current, _) = fetch()?;
+        for event in watch.update(current) {
+            print_event(&event);
+            if let Some(path) = record {
+                append_record(path, &address.to_string(), &event)?;
+            }
+            if once {
+                if let FundingEvent::Received(holding) = event {
+                    return crate::output::emit(
+                        "watch.funded",
+                        true,
+                        &json!({
+                            "address": address.to_string(),
+                            "utxo": holding,
+                        }),
+                    );
+                }
+            }
+        }
+    }
+}
+
+/// Print an event to stdout
+fn print_event(event: &FundingEvent) {
+    let holding = event.holding();
+    let amount = holding.amount.map_or_else(
+        || "confidential".to_string(),
+        |amount| format!("{amount} sat"),
+    );
+    let line = match event {
+        FundingEvent::Received(_) => format!(
+            "{} {}:{} ({amount}, {})",
+            "+".green(),
+            holding.txid,
+            holding.vout,
+            if holding.is_confirmed() {
+                format!("{} confirmations", holding.confirmations)
+            } else {
+                "in mempool".to_string()
+            }
+        ),
+        FundingEvent::Confirmed(_) => format!(
+            "{} {}:{} confirmed ({amount})",
+            "✓".green(),
+            holding.txid,
+            holding.vout
+        ),
+    };
+    crate::output::print_result(&line);
+}
+
+/// Append `event` to the record file at `path` as a JSON line
+fn append_record(path: &Path, address: &str, event: &FundingEvent) -> Result<(), SprayError> {
+    let mut line = serde_json::to_value(event)?;
+    line["address"] = address.into();
+    line["time"] = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_secs())
+        .into();
+    let mut file = std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)?;
+    writeln!(file, "{line}")?;
+    Ok(())
+}
@@ -0,0 +1,143 @@
+//! Node version and feature compatibility checks
+//!
+//! This module provides the [`NodeInfo`] type, which captures the version and
+//! softfork deployments reported by a node, and checks them against what
+//! spray needs. Stock Elements builds without Simplicity otherwise only show up
+//! as inexplicable broadcast failures.
+//!
+//! # Example
+//!
+//! ```
+//! use spray::compat::NodeInfo;
+//! use serde_json::json;
+//!
+//! let network_info = json!({ "version": 230300, "subversion": "/Elements Core:23.3.0/" });
+//! let deployments = json!({ "deployments": { "simplicity": { "active": true } } });
+//!
+//! let info = NodeInfo::from_rpc(&network_info, &deployments).unwrap();
+//! assert_eq!(info.version_string(), "23.3.0");
+//! assert!(info.check().is_ok());
+//! ```
+
+use crate::error::SprayError;
+use serde_json::Value;
+use std::collections::BTreeMap;
+
+/// Oldest Elements version with Simplicity support
+pub const MIN_ELEMENTS_VERSION: u64 = 230_300;
+
+/// Softfork deployments that must be active on the node
+pub const REQUIRED_DEPLOYMENTS: &[&str] = &["simplicity"];
+
+/// Version and softfork status of a node
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NodeInfo {
+    /// Numeric client version, e.g. `230300` for 23.3.0
+    pub version: u64,
+    /// User agent string, e.g. `/Elements Core:23.3.0/`
+    pub subversion: String,
+    /// Known deployments and whether they are active
+    pub deployments: BTreeMap<String, bool>,
+}
+
+impl NodeInfo {
+    /// Build from `getnetworkinfo` and `getdeploymentinfo` responses
+    ///
+    /// The deployments may also be given as a `getblockchaininfo` response,
+    /// which older nodes use to report softforks.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the network info has no numeric version.
+    pub fn from_rpc(network_info: &Value, deployment_info: &Value) -> Result<Self, SprayError> {
+        let version = network_info["version"]
+            .as_u64()
+            .ok_or_else(|| SprayError::RpcError("Missing version in getnetworkinfo".into()))?;
+        let subversion = network_info["subversion"]
+            .as_str()
+            .unwrap_or_default()
+            .to_string();
+
+        let forks = deployment_info
+            .get("deployments")
+            .or_else(|| deployment_info.get("softforks"))
+            .and_then(Value::as_object);
+
+        let deployments = forks
+            .map(|forks| {
+                forks
+                    .iter()
+                    .map(|(name, fork)| (name.clone(), fork["active"].as_bool().unwrap_or(false)))
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        Ok(Self {
+            version,
+            subversion,
+            deployments,
+        })
+    }
+
+    /// Version as `major.minor.patch`
+    #[must_use]
+    pub fn version_string(&self) -> String {
+        format!(
+            "{}.{}.{}",
+            self.version / 10_000,
+            (self.version / 100) % 100,
+            self.version % 100
+        )
+    }
+
+    /// Whether a deployment is known to the node and active
+    #[must_use]
+    pub fn is_active(&self, deployment: &str) -> bool {
+        self.deployments.get(deployment).copied().unwrap_or(false)
+    }
+
+    /// Check the node against [`MIN_ELEMENTS_VERSION`] and [`REQUIRED_DEPLOYMENTS`]
+    ///
+    /// # Errors
+    ///
+    /// Returns [`SprayError::IncompatibleNode`] describing the first problem found.
+    pub fn check(&self) -> Result<(), SprayError> {
+        self.check_deployments(REQUIRED_DEPLOYMENTS)
+    }
+
+    /// Check the node version and that every deployment in `required` is active
+    ///
+    /// # Errors
+    ///
+    /// Returns [`SprayError::IncompatibleNode`] describing the first problem found.
+    pub fn check_deployments(&self, required: &[&str]) -> Result<(), SprayError> {
+        if self.version < MIN_ELEMENTS_VERSION {
+            return Err(SprayError::IncompatibleNode(format!(
+                "node runs Elements {} ({}), but Simplicity requires Elements 23.3.0 or newer",
+                self.version_string(),
+                self.subversion
+            )));
+        }
+
+        for deployment in required {
+            match self.deployments.get(*deployment) {
+                Some(true) => {}
+                Some(false) => {
+                    return Err(SprayError::IncompatibleNode(format!(
+                        "the '{deployment}' deployment is not active on this node \
+                         (on regtest, start elementsd with -evbparams={deployment}:-1:::)"
+                    )));
+                }
+                None => {
+                    return Err(SprayError::IncompatibleNode(format!(
+                        "node {} does not know the '{deployment}' deployment; \
+                         it may be a build without {deployment} support",
+                        self.subversion
+                    )));
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
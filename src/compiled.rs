@@ -3,28 +3,55 @@
 //! This module provides types for serializing compiled Simplicity programs
 //! to JSON for storage and later deployment.
 //!
+//! Artifacts carry the [`FORMAT_VERSION`] they were written with, so that
+//! they survive upgrades: fields unknown to an older spray are ignored,
+//! fields missing from an older artifact take their defaults, and an
+//! artifact written in a newer format than this spray reads is rejected with
+//! an error saying so, instead of being misread. Artifacts from before the
+//! format was versioned read as version `0`.
+//!
 //! # Example
 //!
 //! ```
-//! use spray::compiled::CompiledOutput;
-//! use std::collections::HashMap;
+//! use spray::compiled::{CompiledOutput, FORMAT_VERSION};
+//! use std::collections::{BTreeMap, HashMap};
 //!
 //! let output = CompiledOutput {
+//!     format_version: FORMAT_VERSION,
 //!     cmr: "deadbeef".to_string(),
 //!     program: "SGVsbG8=".to_string(),
 //!     witness: None,
 //!     witness_types: HashMap::new(),
 //!     program_size: 5,
 //!     source: None,
+//!     addresses: BTreeMap::new(),
+//!     script_pubkey: String::new(),
 //! };
 //!
 //! let json = serde_json::to_string(&output).unwrap();
 //! let parsed: CompiledOutput = serde_json::from_str(&json).unwrap();
 //! assert_eq!(parsed.cmr, output.cmr);
+//!
+//! let newer = json.replace("\"format_version\":1", "\"format_version\":99");
+//! assert!(CompiledOutput::from_json(&newer).is_err());
 //! ```
 
-use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use crate::error::SprayError;
+use crate::hex;
+use crate::interop;
+use musk::elements::AddressParams;
+use serde::{Deserialize, Deserializer, Serialize};
+use serde_json::Value;
+use std::collections::{BTreeMap, HashMap};
+
+/// Networks whose contract addresses artifacts carry
+pub const ADDRESS_NETWORKS: [&str; 3] = ["liquid", "testnet", "regtest"];
+
+/// Version of the artifact format this spray writes, and the newest it reads
+///
+/// Bumped only by changes older versions would misread; a new optional
+/// field does not need a bump, since older versions ignore it.
+pub const FORMAT_VERSION: u32 = 1;
 
 /// Serialized format for compiled Simplicity programs
 ///
@@ -44,7 +71,11 @@ use std::collections::HashMap;
 /// assert_eq!(output.cmr.len(), 64);
 /// ```
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[non_exhaustive]
 pub struct CompiledOutput {
+    /// Version of the artifact format, see [`FORMAT_VERSION`]
+    #[serde(default, deserialize_with = "deserialize_format_version")]
+    pub format_version: u32,
     /// Commitment Merkle Root (hex)
     pub cmr: String,
     /// Program bytes (base64 encoded)
@@ -52,16 +83,51 @@ pub struct CompiledOutput {
     /// Witness bytes (base64 encoded), if witness was provided
     #[serde(skip_serializing_if = "Option::is_none")]
     pub witness: Option<String>,
-    /// Witness types declared in the program
+    /// Witness types declared in the program, by witness name
     pub witness_types: HashMap<String, String>,
     /// Program size in bytes
     pub program_size: usize,
     /// Source code (optional, for reference)
     #[serde(skip_serializing_if = "Option::is_none")]
     pub source: Option<String>,
+    /// Unconfidential contract address by network, see [`ADDRESS_NETWORKS`]
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub addresses: BTreeMap<String, String>,
+    /// Output script of the contract (hex), the same on every network
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub script_pubkey: String,
 }
 
 impl CompiledOutput {
+    /// An artifact of the program `program` with CMR `cmr` (hex), without
+    /// witness, source or addresses
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use spray::compiled::CompiledOutput;
+    ///
+    /// let output = CompiledOutput::new("deadbeef", b"Hello");
+    /// assert_eq!(output.program, "SGVsbG8=");
+    /// assert_eq!(output.program_size, 5);
+    /// ```
+    #[must_use]
+    pub fn new(cmr: impl Into<String>, program: &[u8]) -> Self {
+        use base64::{engine::general_purpose::STANDARD, Engine};
+
+        Self {
+            format_version: FORMAT_VERSION,
+            cmr: cmr.into(),
+            program: STANDARD.encode(program),
+            witness: None,
+            witness_types: HashMap::new(),
+            program_size: program.len(),
+            source: None,
+            addresses: BTreeMap::new(),
+            script_pubkey: String::new(),
+        }
+    }
+
     /// Create a new compiled output from a musk `InstantiatedProgram`
     #[must_use]
     pub fn from_compiled(compiled: &musk::InstantiatedProgram, source: Option<String>) -> Self {
@@ -70,17 +136,18 @@ impl CompiledOutput {
         let program_bytes = compiled.inner().commit().to_vec_without_witness();
         let cmr = compiled.cmr();
 
-        // Convert witness types to string map
-        let witness_types = HashMap::new(); // TODO: Extract from compiled.inner().witness_types()
-
         Self {
+            format_version: FORMAT_VERSION,
             cmr: hex::encode(cmr.as_ref()),
             program: STANDARD.encode(&program_bytes),
             witness: None,
-            witness_types,
+            witness_types: witness_types(compiled),
             program_size: program_bytes.len(),
             source,
+            addresses: BTreeMap::new(),
+            script_pubkey: String::new(),
         }
+        .with_addresses()
     }
 
     /// Create from a satisfied program (includes witness)
@@ -95,16 +162,163 @@ impl CompiledOutput {
         let (program_bytes, witness_bytes) = satisfied.encode();
         let cmr = compiled.cmr();
 
-        let witness_types = HashMap::new(); // TODO: Extract from witness_types
-
         Self {
+            format_version: FORMAT_VERSION,
             cmr: hex::encode(cmr.as_ref()),
             program: STANDARD.encode(&program_bytes),
             witness: Some(STANDARD.encode(&witness_bytes)),
-            witness_types,
+            witness_types: witness_types(compiled),
             program_size: program_bytes.len(),
             source,
+            addresses: BTreeMap::new(),
+            script_pubkey: String::new(),
+        }
+        .with_addresses()
+    }
+
+    /// Fill in [`CompiledOutput::addresses`] and
+    /// [`CompiledOutput::script_pubkey`] from the CMR
+    ///
+    /// Both are left as they are if the CMR is unknown or invalid.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use spray::compiled::CompiledOutput;
+    /// use musk::{Arguments, Program};
+    ///
+    /// let program = Program::from_source("fn main() { assert!(true); }").unwrap();
+    /// let compiled = program.instantiate(Arguments::default()).unwrap();
+    /// let output = CompiledOutput::from_compiled(&compiled, None);
+    ///
+    /// assert!(output.addresses["liquid"].starts_with("ex1p"));
+    /// assert!(output.addresses["regtest"].starts_with("ert1p"));
+    /// assert!(output.script_pubkey.starts_with("5120"));
+    /// ```
+    #[must_use]
+    pub fn with_addresses(mut self) -> Self {
+        let Ok(address) = interop::derive_address(&self.cmr, &AddressParams::ELEMENTS) else {
+            return self;
+        };
+        self.script_pubkey = hex::encode(address.script_pubkey().as_bytes());
+        self.addresses = ADDRESS_NETWORKS
+            .into_iter()
+            .filter_map(|network| {
+                let params = interop::address_params(network)?;
+                let address = interop::derive_address(&self.cmr, params).ok()?;
+                Some((network.to_string(), address.to_string()))
+            })
+            .collect();
+        self
+    }
+
+    /// Load a compiled output from a JSON file
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be read or parsed, or was written
+    /// in a newer format than this spray reads.
+    pub fn load(path: &std::path::Path) -> Result<Self, SprayError> {
+        let json_str = std::fs::read_to_string(path)?;
+        Self::from_json(&json_str).map_err(|e| match e {
+            SprayError::FileFormatError(e) => {
+                SprayError::FileFormatError(format!("{}: {e}", path.display()))
+            }
+            e => e,
+        })
+    }
+
+    /// Parse a compiled output from JSON
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the JSON is not a compiled output, or was written
+    /// in a newer format than this spray reads.
+    pub fn from_json(json: &str) -> Result<Self, SprayError> {
+        let value: Value = serde_json::from_str(json)?;
+        if let Some(version) = value.get("format_version").and_then(Value::as_u64) {
+            check_format_version(version).map_err(SprayError::FileFormatError)?;
         }
+        Ok(serde_json::from_value(value)?)
+    }
+
+    /// Re-instantiate the program from the embedded source
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the output has no source or the source fails to
+    /// compile with the given arguments.
+    pub fn instantiate(
+        &self,
+        arguments: musk::Arguments,
+    ) -> Result<musk::InstantiatedProgram, SprayError> {
+        let source = self.source.as_ref().ok_or_else(|| {
+            SprayError::FileFormatError("Compiled program must include source field".into())
+        })?;
+
+        let program = musk::Program::from_source(source)?;
+        Ok(program.instantiate(arguments)?)
+    }
+
+    /// Wrap a bare program encoded as base64 or hex
+    ///
+    /// The CMR of a bare program is unknown, so `cmr` is left empty.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `text` is neither valid hex nor valid base64.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use spray::compiled::CompiledOutput;
+    ///
+    /// let from_hex = CompiledOutput::from_program_text("48656c6c6f").unwrap();
+    /// let from_base64 = CompiledOutput::from_program_text("SGVsbG8=").unwrap();
+    /// assert_eq!(from_hex.program, from_base64.program);
+    /// assert_eq!(from_hex.program_size, 5);
+    /// assert!(from_hex.cmr.is_empty());
+    /// ```
+    pub fn from_program_text(text: &str) -> Result<Self, SprayError> {
+        use base64::{engine::general_purpose::STANDARD, Engine};
+
+        let text = text.trim();
+        let hex_digits = text.trim_start_matches("0x");
+        let bytes = if hex_digits.len() % 2 == 0
+            && !hex_digits.is_empty()
+            && hex_digits.chars().all(|c| c.is_ascii_hexdigit())
+        {
+            (0..hex_digits.len())
+                .step_by(2)
+                .map(|i| u8::from_str_radix(&hex_digits[i..i + 2], 16))
+                .collect::<Result<Vec<u8>, _>>()
+                .map_err(|e| SprayError::ParseError(e.to_string()))?
+        } else {
+            STANDARD.decode(text).map_err(|e| {
+                SprayError::ParseError(format!("Program is neither hex nor base64: {e}"))
+            })?
+        };
+
+        Ok(Self::new(String::new(), &bytes))
+    }
+
+    /// Decode the program into its combinator and jet nodes
+    ///
+    /// If a witness is attached, the program is decoded for redemption and
+    /// each node also carries its IMR.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the program or witness is not valid base64 or not
+    /// a valid Simplicity encoding.
+    pub fn disassemble(&self) -> Result<crate::disasm::Disassembly, SprayError> {
+        let program = self
+            .decode_program()
+            .map_err(|e| SprayError::ParseError(e.to_string()))?;
+        let witness = self
+            .decode_witness()
+            .map_err(|e| SprayError::ParseError(e.to_string()))?;
+        crate::disasm::Disassembly::decode(&program, (!witness.is_empty()).then_some(&witness[..]))
     }
 
     /// Decode the program bytes from base64
@@ -116,16 +330,19 @@ impl CompiledOutput {
     /// # Example
     ///
     /// ```
-    /// use spray::compiled::CompiledOutput;
-    /// use std::collections::HashMap;
+    /// use spray::compiled::{CompiledOutput, FORMAT_VERSION};
+    /// use std::collections::{BTreeMap, HashMap};
     ///
     /// let output = CompiledOutput {
+    ///     format_version: FORMAT_VERSION,
     ///     cmr: "deadbeef".to_string(),
     ///     program: "SGVsbG8gV29ybGQ=".to_string(), // "Hello World"
     ///     witness: None,
     ///     witness_types: HashMap::new(),
     ///     program_size: 11,
     ///     source: None,
+    ///     addresses: BTreeMap::new(),
+    ///     script_pubkey: String::new(),
     /// };
     ///
     /// let bytes = output.decode_program().unwrap();
@@ -147,28 +364,34 @@ impl CompiledOutput {
     /// # Example
     ///
     /// ```
-    /// use spray::compiled::CompiledOutput;
-    /// use std::collections::HashMap;
+    /// use spray::compiled::{CompiledOutput, FORMAT_VERSION};
+    /// use std::collections::{BTreeMap, HashMap};
     ///
     /// // With witness
     /// let output = CompiledOutput {
+    ///     format_version: FORMAT_VERSION,
     ///     cmr: "deadbeef".to_string(),
     ///     program: "AA==".to_string(),
     ///     witness: Some("dGVzdA==".to_string()), // "test"
     ///     witness_types: HashMap::new(),
     ///     program_size: 1,
     ///     source: None,
+    ///     addresses: BTreeMap::new(),
+    ///     script_pubkey: String::new(),
     /// };
     /// assert_eq!(output.decode_witness().unwrap(), b"test");
     ///
     /// // Without witness
     /// let output_no_witness = CompiledOutput {
+    ///     format_version: FORMAT_VERSION,
     ///     cmr: "deadbeef".to_string(),
     ///     program: "AA==".to_string(),
     ///     witness: None,
     ///     witness_types: HashMap::new(),
     ///     program_size: 1,
     ///     source: None,
+    ///     addresses: BTreeMap::new(),
+    ///     script_pubkey: String::new(),
     /// };
     /// assert!(output_no_witness.decode_witness().unwrap().is_empty());
     /// ```
@@ -181,17 +404,33 @@ impl CompiledOutput {
     }
 }
 
-// Add hex dependency
-#[doc(hidden)]
-mod hex {
-    use std::fmt::Write;
-
-    pub fn encode(bytes: &[u8]) -> String {
-        bytes
-            .iter()
-            .fold(String::with_capacity(bytes.len() * 2), |mut acc, b| {
-                let _ = write!(acc, "{b:02x}");
-                acc
-            })
+/// Check that an artifact of format `version` can be read
+fn check_format_version(version: u64) -> Result<(), String> {
+    if version > u64::from(FORMAT_VERSION) {
+        return Err(format!(
+            "Artifact format version {version} is newer than this spray reads (up to {FORMAT_VERSION}); upgrade spray to load it"
+        ));
     }
+    Ok(())
+}
+
+/// Deserialize a format version, rejecting versions newer than
+/// [`FORMAT_VERSION`]
+fn deserialize_format_version<'de, D: Deserializer<'de>>(deserializer: D) -> Result<u32, D::Error> {
+    let version = u32::deserialize(deserializer)?;
+    check_format_version(version.into()).map_err(serde::de::Error::custom)?;
+    Ok(version)
+}
+
+/// Names and types of the witnesses a program declares
+///
+/// Types are rendered in SimplicityHL syntax, e.g. `u32` or `[u8; 64]`.
+#[must_use]
+pub fn witness_types(compiled: &musk::InstantiatedProgram) -> HashMap<String, String> {
+    compiled
+        .inner()
+        .witness_types()
+        .iter()
+        .map(|(name, ty)| (name.to_string(), ty.to_string()))
+        .collect()
 }
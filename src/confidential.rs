@@ -0,0 +1,107 @@
+//! Confidential transaction helpers
+//!
+//! This module provides helpers for unblinding confidential contract UTXOs
+//! and for building blinded outputs that balance a spend.
+
+use crate::error::SprayError;
+use musk::elements::confidential::{self, AssetBlindingFactor, ValueBlindingFactor};
+use musk::elements::secp256k1_zkp::{Secp256k1, SecretKey};
+use musk::elements::{Address, AssetId, Script, TxOut, TxOutSecrets, TxOutWitness};
+use std::str::FromStr;
+
+/// Parse a hex-encoded blinding private key
+///
+/// # Errors
+///
+/// Returns an error if the string is not a valid 32-byte secret key.
+pub fn parse_blinding_key(s: &str) -> Result<SecretKey, SprayError> {
+    SecretKey::from_str(s.trim_start_matches("0x"))
+        .map_err(|e| SprayError::ParseError(format!("Invalid blinding key: {e}")))
+}
+
+/// Recover the amount, asset and blinding factors of a confidential output
+///
+/// # Errors
+///
+/// Returns an error if the output cannot be unblinded with `blinding_key`.
+pub fn unblind_output(txout: &TxOut, blinding_key: SecretKey) -> Result<TxOutSecrets, SprayError> {
+    let secp = Secp256k1::new();
+    txout
+        .unblind(&secp, blinding_key)
+        .map_err(|e| SprayError::TestError(format!("Failed to unblind output: {e}")))
+}
+
+/// Secrets of an explicit (unblinded) output, with zero blinding factors
+#[must_use]
+pub fn explicit_secrets(asset: AssetId, value: u64) -> TxOutSecrets {
+    TxOutSecrets::new(
+        asset,
+        AssetBlindingFactor::zero(),
+        value,
+        ValueBlindingFactor::zero(),
+    )
+}
+
+/// Build an explicit (unblinded) output
+#[must_use]
+pub fn explicit_output(script_pubkey: Script, value: u64, asset: AssetId) -> TxOut {
+    TxOut {
+        asset: confidential::Asset::Explicit(asset),
+        value: confidential::Value::Explicit(value),
+        nonce: confidential::Nonce::Null,
+        script_pubkey,
+        witness: TxOutWitness::default(),
+    }
+}
+
+/// Build a blinded output paying `value` of `asset` to a confidential address
+///
+/// The output is blinded as the last blinded output of the transaction: its
+/// value blinding factor is chosen so the transaction balances against
+/// `spent` (the secrets of every input) and `outputs` (the secrets of every
+/// other output, including the fee). With `deterministic`, the blinding
+/// factors are drawn from a stream of the fixed seed separated by the
+/// address, amount, asset and balancing secrets, see
+/// [`crate::deterministic`].
+///
+/// # Errors
+///
+/// Returns an error if `address` has no blinding key or blinding fails.
+pub fn blind_last_output(
+    address: &Address,
+    value: u64,
+    asset: AssetId,
+    spent: &[TxOutSecrets],
+    outputs: &[TxOutSecrets],
+    deterministic: bool,
+) -> Result<TxOut, SprayError> {
+    if address.blinding_pubkey.is_none() {
+        return Err(SprayError::ConfigError(format!(
+            "Destination {address} is not a confidential address"
+        )));
+    }
+
+    let secp = Secp256k1::new();
+    let mut domain = format!("{address}:{value}:{asset}");
+    for secrets in spent.iter().chain(outputs) {
+        domain.push_str(&format!(
+            ";{}:{}:{}:{}",
+            secrets.asset, secrets.value, secrets.asset_bf, secrets.value_bf
+        ));
+    }
+    let mut rng = crate::deterministic::rng(deterministic, domain.as_bytes());
+    let outputs: Vec<&TxOutSecrets> = outputs.iter().collect();
+
+    let (txout, _asset_bf, _value_bf, _ephemeral_sk) = TxOut::new_last_confidential(
+        &mut rng,
+        &secp,
+        value,
+        address.clone(),
+        asset,
+        spent,
+        &outputs,
+    )
+    .map_err(|e| SprayError::TestError(format!("Failed to blind output: {e}")))?;
+
+    Ok(txout)
+}
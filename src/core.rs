@@ -0,0 +1,53 @@
+//! Stable library API
+//!
+//! Everything reachable through this module follows semantic versioning:
+//! it changes incompatibly only in a major release. It covers the
+//! environment-agnostic parts of spray — compiled artifacts, spend planning
+//! and local verification, witness providers and test reports — and needs
+//! neither a node nor the `daemon` and `cli` features. [`SprayError`],
+//! [`FailureStage`], [`TestResult`] and [`CompiledOutput`] are
+//! `#[non_exhaustive]`, so that variants and fields can be added in a minor
+//! release: match them with a wildcard arm, and build results and artifacts
+//! with their constructors, e.g. [`TestResult::failure`] and
+//! [`CompiledOutput::new`].
+//!
+//! The other modules of the crate, including daemon orchestration
+//! (`TestEnv`, `TestRunner`) and the CLI's `commands`, serve the `spray`
+//! binary and may change in any release. Downstream integrators should depend on
+//! `spray::core` and build on `default-features = false`.
+//!
+//! # Example
+//!
+//! ```
+//! use spray::core::{FeePolicy, FixedWitness, SuiteReport, TestRecord, WitnessProvider};
+//!
+//! let fee = FeePolicy::default();
+//! let witness = FixedWitness::default();
+//! let _ = witness.witness([0; 32]);
+//!
+//! let report = SuiteReport::new(vec![TestRecord::failure("t", 5, "boom".into())], 1);
+//! assert_eq!(report.failed(), 1);
+//! # let _ = fee;
+//! ```
+
+// Compiled artifacts
+pub use crate::compiled::{witness_types, CompiledOutput};
+
+// Errors
+pub use crate::error::SprayError;
+
+// Spend planning
+pub use crate::destination::{Destination, Resolved};
+pub use crate::fee::FeePolicy;
+pub use crate::funding::{FundingOutput, FundingShape};
+pub use crate::pset::SimplicityLeaf;
+
+// Local verification
+pub use crate::verify::{verify, FailureKind, SpendContext, Verification};
+
+// Witness providers
+pub use crate::witness::{FixedWitness, WitnessProvider};
+
+// Reports
+pub use crate::report::{SuiteReport, TestRecord};
+pub use crate::test::{FailureStage, SpendDetails, TestResult};
@@ -0,0 +1,218 @@
+//! Branch coverage
+//!
+//! Satisfying a program for a spend runs it on the bit machine and prunes
+//! every branch that execution did not take: a `case` whose right branch
+//! was never taken becomes `assertl`, one whose left branch was never taken
+//! becomes `assertr`, and consensus requires spends to carry the pruned
+//! program. Since pruning keeps CMRs, the program in a passed test's spend
+//! tells exactly which branches of each `case` of the committed program
+//! that test took.
+//!
+//! [`Coverage`] accumulates this over the spends of a suite and reports the
+//! branches that no test exercised.
+//!
+//! # Example
+//!
+//! ```
+//! use spray::coverage::{BranchPoint, Coverage};
+//!
+//! let mut coverage = Coverage {
+//!     program: "vault.simf".into(),
+//!     cmr: "00".repeat(32),
+//!     spends: 1,
+//!     branches: vec![BranchPoint {
+//!         index: 4,
+//!         cmr: "11".repeat(32),
+//!         left: true,
+//!         right: false,
+//!     }],
+//! };
+//! assert_eq!(coverage.taken(), 1);
+//! assert_eq!(coverage.total(), 2);
+//! assert_eq!(coverage.dead().collect::<Vec<_>>(), vec![(&coverage.branches[0], "right")]);
+//!
+//! coverage.branches[0].right = true;
+//! assert_eq!(coverage.dead().count(), 0);
+//! ```
+
+use crate::error::SprayError;
+use colored::Colorize;
+use musk::elements::Transaction;
+use serde::{Deserialize, Serialize};
+use simplicity::dag::{DagLike, InternalSharing};
+use simplicity::jet::Elements;
+use simplicity::node::Inner;
+use simplicity::{BitIter, CommitNode, RedeemNode};
+
+/// A `case` of the committed program and which of its branches were taken
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BranchPoint {
+    /// Post-order index of the node, as in `spray inspect --disasm`
+    pub index: usize,
+    /// CMR of the node (hex)
+    pub cmr: String,
+    /// Whether a spend took the left branch
+    pub left: bool,
+    /// Whether a spend took the right branch
+    pub right: bool,
+}
+
+/// Branch coverage of a program across spends
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Coverage {
+    /// Program the coverage is for, e.g. its file name
+    #[serde(default)]
+    pub program: String,
+    /// CMR of the program (hex)
+    pub cmr: String,
+    /// Number of spends recorded
+    pub spends: usize,
+    /// Every `case` of the program, in post order
+    pub branches: Vec<BranchPoint>,
+}
+
+impl Coverage {
+    /// Track the branches of a committed program
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `program` is not a valid encoding.
+    pub fn new(program: &[u8]) -> Result<Self, SprayError> {
+        let root = CommitNode::<Elements>::decode(BitIter::from(program.iter().copied()))
+            .map_err(|e| SprayError::ParseError(format!("Invalid Simplicity program: {e}")))?;
+        let branches = root
+            .post_order_iter::<InternalSharing>()
+            .filter(|item| matches!(item.node.inner(), Inner::Case(..)))
+            .map(|item| BranchPoint {
+                index: item.index,
+                cmr: item.node.cmr().to_string(),
+                left: false,
+                right: false,
+            })
+            .collect();
+        Ok(Self {
+            program: String::new(),
+            cmr: root.cmr().to_string(),
+            spends: 0,
+            branches,
+        })
+    }
+
+    /// Label the coverage with the program's name
+    #[must_use]
+    pub fn named(mut self, program: &str) -> Self {
+        self.program = program.to_string();
+        self
+    }
+
+    /// Track the branches of an instantiated program
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the program cannot be encoded and decoded.
+    pub fn for_program(program: &musk::InstantiatedProgram) -> Result<Self, SprayError> {
+        Self::new(&program.inner().commit().to_vec_without_witness())
+    }
+
+    /// Record the branches taken by a pruned program and its witness
+    ///
+    /// Returns `false`, recording nothing, if the program has a different
+    /// CMR.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the program or witness is not a valid encoding.
+    pub fn record(&mut self, program: &[u8], witness: &[u8]) -> Result<bool, SprayError> {
+        let root = RedeemNode::<Elements>::decode(
+            BitIter::from(program.iter().copied()),
+            BitIter::from(witness.iter().copied()),
+        )
+        .map_err(|e| SprayError::ParseError(format!("Invalid Simplicity program: {e}")))?;
+        if root.cmr().to_string() != self.cmr {
+            return Ok(false);
+        }
+
+        for item in root.post_order_iter::<InternalSharing>() {
+            let (left, right) = match item.node.inner() {
+                Inner::Case(..) => (true, true),
+                Inner::AssertL(..) => (true, false),
+                Inner::AssertR(..) => (false, true),
+                _ => continue,
+            };
+            let cmr = item.node.cmr().to_string();
+            for branch in self.branches.iter_mut().filter(|b| b.cmr == cmr) {
+                branch.left |= left;
+                branch.right |= right;
+            }
+        }
+        self.spends += 1;
+        Ok(true)
+    }
+
+    /// Record the branches taken by the spend of this program in `tx`
+    ///
+    /// Each input's witness stack is checked for a Simplicity witness and
+    /// program with this program's CMR. Returns whether one was found.
+    pub fn record_spend(&mut self, tx: &Transaction) -> bool {
+        tx.input
+            .iter()
+            .any(|input| match input.witness.script_witness.as_slice() {
+                [witness, program, ..] => self.record(program, witness).unwrap_or(false),
+                _ => false,
+            })
+    }
+
+    /// Number of branches taken by at least one spend
+    #[must_use]
+    pub fn taken(&self) -> usize {
+        self.branches
+            .iter()
+            .map(|b| usize::from(b.left) + usize::from(b.right))
+            .sum()
+    }
+
+    /// Number of branches in the program
+    #[must_use]
+    pub fn total(&self) -> usize {
+        self.branches.len() * 2
+    }
+
+    /// Branches no spend took, as the branch point and `"left"` or `"right"`
+    pub fn dead(&self) -> impl Iterator<Item = (&BranchPoint, &'static str)> {
+        self.branches.iter().flat_map(|branch| {
+            let left = (!branch.left).then_some((branch, "left"));
+            let right = (!branch.right).then_some((branch, "right"));
+            left.into_iter().chain(right)
+        })
+    }
+
+    /// Print the coverage summary and dead branches
+    pub fn print(&self) {
+        crate::status!();
+        crate::status!("{} {}", "Branch coverage:".bold(), self.program);
+        if self.branches.is_empty() {
+            crate::status!("  {}", "no case branches".dimmed());
+            return;
+        }
+
+        let taken = self.taken();
+        let total = self.total();
+        let summary = format!(
+            "{taken}/{total} branches taken ({}%) across {} spend(s)",
+            taken * 100 / total,
+            self.spends
+        );
+        if taken == total {
+            crate::status!("  {}", summary.green());
+        } else {
+            crate::status!("  {}", summary.yellow());
+            for (branch, side) in self.dead() {
+                crate::status!(
+                    "  {} %{} case: {side} branch never taken",
+                    "dead".red(),
+                    branch.index
+                );
+            }
+        }
+    }
+}
@@ -0,0 +1,296 @@
+//! Live test dashboard
+//!
+//! Scrolling logs are hard to follow during long regtest or testnet runs.
+//! `spray tui` instead draws the state of the run and redraws it as tests
+//! start and finish: overall progress, the status of every test, the most
+//! recent chain events (the outputs each test spent, the spends it
+//! broadcast and the contract events they carry) and the details of failed
+//! tests, which can be expanded and collapsed.
+//!
+//! [`Dashboard`] holds that state and renders it to a string; the command
+//! feeds it the [`Progress`](crate::manifest::Progress) of each suite and
+//! draws the frames.
+//!
+//! # Example
+//!
+//! ```
+//! use spray::dashboard::{Dashboard, TestStatus};
+//! use spray::report::TestRecord;
+//!
+//! let mut dashboard = Dashboard::new([("local", "p2pk"), ("local", "vault")]);
+//! dashboard.start(1);
+//! assert_eq!(dashboard.status(1), TestStatus::Running);
+//!
+//! dashboard.finish(1, TestRecord::failure("vault", 900, "Assertion failed".into()));
+//! assert_eq!(dashboard.finished(), 1);
+//! assert!(!dashboard.render().contains("Assertion failed"));
+//!
+//! dashboard.toggle(1);
+//! assert!(dashboard.render().contains("Assertion failed"));
+//! ```
+
+use crate::events::{op_return_payload, EventSchema};
+use crate::report::{format_ms, TestRecord};
+use crate::test::SpendDetails;
+use colored::Colorize;
+use std::collections::VecDeque;
+use std::time::Instant;
+
+/// Number of chain events kept on the dashboard
+pub const RECENT_EVENTS: usize = 8;
+
+/// Width of the progress bar in characters
+const BAR_WIDTH: usize = 40;
+
+/// Where a test is in the run
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TestStatus {
+    /// Not started yet
+    Pending,
+    /// Running now
+    Running,
+    /// Finished and passed
+    Passed,
+    /// Finished and failed
+    Failed,
+}
+
+/// A test on the dashboard
+#[derive(Debug, Clone)]
+struct Entry {
+    suite: String,
+    name: String,
+    status: TestStatus,
+    record: Option<TestRecord>,
+    expanded: bool,
+}
+
+/// State of a run, as shown by `spray tui`
+#[derive(Debug, Clone)]
+pub struct Dashboard {
+    tests: Vec<Entry>,
+    events: VecDeque<String>,
+    started: Instant,
+}
+
+impl Dashboard {
+    /// A dashboard for `tests`, given as suite and test names in run order
+    pub fn new<'a>(tests: impl IntoIterator<Item = (&'a str, &'a str)>) -> Self {
+        let tests = tests
+            .into_iter()
+            .map(|(suite, name)| Entry {
+                suite: suite.to_string(),
+                name: name.to_string(),
+                status: TestStatus::Pending,
+                record: None,
+                expanded: false,
+            })
+            .collect();
+        Self {
+            tests,
+            events: VecDeque::new(),
+            started: Instant::now(),
+        }
+    }
+
+    /// Number of tests in the run
+    #[must_use]
+    pub fn total(&self) -> usize {
+        self.tests.len()
+    }
+
+    /// Number of tests that have finished
+    #[must_use]
+    pub fn finished(&self) -> usize {
+        self.tests
+            .iter()
+            .filter(|t| matches!(t.status, TestStatus::Passed | TestStatus::Failed))
+            .count()
+    }
+
+    /// Number of tests that have failed
+    #[must_use]
+    pub fn failed(&self) -> usize {
+        self.tests
+            .iter()
+            .filter(|t| t.status == TestStatus::Failed)
+            .count()
+    }
+
+    /// Status of the `index`th test
+    ///
+    /// # Panics
+    ///
+    /// Panics if `index` is out of range.
+    #[must_use]
+    pub fn status(&self, index: usize) -> TestStatus {
+        self.tests[index].status
+    }
+
+    /// Mark the `index`th test as running
+    pub fn start(&mut self, index: usize) {
+        if let Some(test) = self.tests.get_mut(index) {
+            test.status = TestStatus::Running;
+        }
+    }
+
+    /// Record the outcome of the `index`th test
+    pub fn finish(&mut self, index: usize, record: TestRecord) {
+        if let Some(test) = self.tests.get_mut(index) {
+            test.status = if record.success {
+                TestStatus::Passed
+            } else {
+                TestStatus::Failed
+            };
+            test.record = Some(record);
+        }
+    }
+
+    /// Expand or collapse the details of the `index`th test
+    pub fn toggle(&mut self, index: usize) {
+        if let Some(test) = self.tests.get_mut(index) {
+            test.expanded = !test.expanded;
+        }
+    }
+
+    /// Expand the details of every failed test
+    pub fn expand_failures(&mut self) {
+        for test in &mut self.tests {
+            test.expanded |= test.status == TestStatus::Failed;
+        }
+    }
+
+    /// Add a chain event, dropping the oldest beyond [`RECENT_EVENTS`]
+    pub fn event(&mut self, event: impl Into<String>) {
+        self.events.push_back(event.into());
+        while self.events.len() > RECENT_EVENTS {
+            self.events.pop_front();
+        }
+    }
+
+    /// Render the dashboard
+    #[must_use]
+    pub fn render(&self) -> String {
+        let total = self.total();
+        let finished = self.finished();
+        let failed = self.failed();
+
+        let mut lines = vec![format!(
+            "{} {finished}/{total} tests, {} passed, {} failed, {}",
+            "spray tui".bold().cyan(),
+            (finished - failed).to_string().green(),
+            failed.to_string().red(),
+            format_ms(u64::try_from(self.started.elapsed().as_millis()).unwrap_or(u64::MAX))
+        )];
+        let filled = (finished * BAR_WIDTH)
+            .checked_div(total)
+            .unwrap_or(BAR_WIDTH);
+        let bar = format!("[{}{}]", "█".repeat(filled), "░".repeat(BAR_WIDTH - filled));
+        lines.push(if failed == 0 {
+            bar.green().to_string()
+        } else {
+            bar.yellow().to_string()
+        });
+
+        lines.push(String::new());
+        lines.push("Tests".bold().to_string());
+        for (i, test) in self.tests.iter().enumerate() {
+            let icon = match test.status {
+                TestStatus::Pending => "·".dimmed(),
+                TestStatus::Running => "⏳".yellow(),
+                TestStatus::Passed => "✅".green(),
+                TestStatus::Failed => "❌".red(),
+            };
+            let duration = test
+                .record
+                .as_ref()
+                .map(|r| format_ms(r.duration_ms))
+                .unwrap_or_default();
+            let stage = test
+                .record
+                .as_ref()
+                .and_then(|r| r.stage)
+                .map(|stage| format!(" [{stage}]"))
+                .unwrap_or_default();
+            lines.push(format!(
+                "{:>4} {icon} {}/{} {}{}",
+                i + 1,
+                test.suite,
+                test.name.bold(),
+                duration.dimmed(),
+                stage.red()
+            ));
+            if test.expanded {
+                lines.extend(details(test.record.as_ref()));
+            }
+        }
+
+        lines.push(String::new());
+        lines.push("Recent chain events".bold().to_string());
+        if self.events.is_empty() {
+            lines.push(format!("  {}", "none yet".dimmed()));
+        }
+        lines.extend(self.events.iter().map(|event| format!("  {event}")));
+
+        let mut frame = lines.join("\n");
+        frame.push('\n');
+        frame
+    }
+}
+
+/// Expanded details of a test
+fn details(record: Option<&TestRecord>) -> Vec<String> {
+    let Some(record) = record else {
+        return vec![format!("       {}", "no details yet".dimmed())];
+    };
+    let mut lines = Vec::new();
+    if let Some(txid) = &record.txid {
+        lines.push(format!("       txid: {txid}"));
+    }
+    if let Some(stage) = record.stage {
+        lines.push(format!("       stage: {stage}"));
+    }
+    if let Some(error) = &record.error {
+        let mut error_lines = error.lines();
+        if let Some(first) = error_lines.next() {
+            lines.push(format!("       error: {}", first.red()));
+        }
+        lines.extend(error_lines.map(|line| format!("              {}", line.red())));
+    }
+    lines
+}
+
+/// Chain events of a test's spend: the outputs it spent, the spend itself
+/// and, with `schema`, the contract events in its `OP_RETURN` outputs
+#[must_use]
+pub fn spend_events(spend: &SpendDetails, schema: Option<&EventSchema>) -> Vec<String> {
+    let mut events: Vec<String> = spend
+        .tx
+        .input
+        .iter()
+        .map(|input| format!("spent {}", input.previous_output))
+        .collect();
+    events.push(format!(
+        "broadcast {} ({} vB, fee {} sat)",
+        spend.tx.txid(),
+        spend.vsize(),
+        spend.fee
+    ));
+
+    let Some(schema) = schema else {
+        return events;
+    };
+    for output in &spend.tx.output {
+        let Some(payload) = op_return_payload(&output.script_pubkey) else {
+            continue;
+        };
+        match schema.decode(&payload) {
+            Some(Ok((name, fields))) => {
+                events.push(format!("event {name} {}", serde_json::Value::from(fields)));
+            }
+            Some(Err(e)) => events.push(format!("event not decoded: {e}")),
+            None => {}
+        }
+    }
+    events
+}
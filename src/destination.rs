@@ -0,0 +1,180 @@
+//! Spend destinations
+//!
+//! By default a test spends its contract output to a fresh address from the
+//! node's wallet. That needs a wallet, and gives a different script every
+//! run. A [`Destination`] can instead name a fixed address or an output
+//! descriptor, so that tests work against backends without wallets and
+//! covenants that constrain the spend's outputs can be tested against a
+//! known script.
+//!
+//! Destinations are written as `wallet`, an address, or a descriptor such as
+//! `raw(0014...)`, `addr(...)` or `wpkh(...)`. `raw()` and `addr()` are
+//! resolved locally; other descriptors are resolved with the node's
+//! `deriveaddresses` call, taking index 0 of ranged descriptors.
+//!
+//! # Example
+//!
+//! ```
+//! use spray::destination::Destination;
+//!
+//! let destination: Destination = "raw(51)".parse().unwrap();
+//! let resolved = destination
+//!     .resolve(|_, _| unreachable!(), || unreachable!())
+//!     .unwrap();
+//! assert_eq!(resolved.script_pubkey.as_bytes(), &[0x51]);
+//! assert!(resolved.address.is_none());
+//!
+//! assert_eq!("wallet".parse::<Destination>().unwrap(), Destination::Wallet);
+//! assert!("not an address".parse::<Destination>().is_err());
+//! ```
+
+use crate::error::SprayError;
+use musk::elements::hex::FromHex;
+use musk::elements::{Address, Script};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::fmt;
+use std::str::FromStr;
+
+/// Where a test's spend pays to
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub enum Destination {
+    /// A fresh address from the node's wallet
+    #[default]
+    Wallet,
+    /// A fixed address
+    Address(Address),
+    /// An output descriptor
+    Descriptor(String),
+}
+
+/// A destination resolved to an output script
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Resolved {
+    /// Script of the destination output
+    pub script_pubkey: Script,
+    /// Address of the destination, if it has one; needed to blind the output
+    pub address: Option<Address>,
+}
+
+impl From<Address> for Resolved {
+    fn from(address: Address) -> Self {
+        Self {
+            script_pubkey: address.script_pubkey(),
+            address: Some(address),
+        }
+    }
+}
+
+impl Destination {
+    /// Resolve the destination to an output script
+    ///
+    /// `call` makes RPC calls for descriptors that cannot be resolved
+    /// locally; `new_address` provides a wallet address for
+    /// [`Destination::Wallet`].
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the wallet or node call fails or the descriptor
+    /// does not derive a single valid address.
+    pub fn resolve<C, W>(&self, call: C, new_address: W) -> Result<Resolved, SprayError>
+    where
+        C: Fn(&str, &[Value]) -> Result<Value, SprayError>,
+        W: FnOnce() -> Result<Address, SprayError>,
+    {
+        match self {
+            Self::Wallet => new_address().map(Resolved::from),
+            Self::Address(address) => Ok(address.clone().into()),
+            Self::Descriptor(descriptor) => {
+                let body = descriptor.split('#').next().unwrap_or(descriptor);
+                if let Some(hex) = strip_call(body, "raw") {
+                    let bytes = Vec::<u8>::from_hex(hex).map_err(|e| {
+                        SprayError::ParseError(format!("Invalid raw() script: {e}"))
+                    })?;
+                    return Ok(Resolved {
+                        script_pubkey: Script::from(bytes),
+                        address: None,
+                    });
+                }
+                if let Some(address) = strip_call(body, "addr") {
+                    return parse_address(address).map(Resolved::from);
+                }
+
+                let info = call("getdescriptorinfo", &[body.into()])?;
+                let checksummed = info["descriptor"].as_str().ok_or_else(|| {
+                    SprayError::RpcError("Invalid getdescriptorinfo response".into())
+                })?;
+                let mut params = vec![Value::from(checksummed)];
+                if info["isrange"].as_bool() == Some(true) {
+                    params.push(serde_json::json!([0, 0]));
+                }
+                let derived = call("deriveaddresses", &params)?;
+                match derived.as_array().map(Vec::as_slice) {
+                    Some([address]) => address
+                        .as_str()
+                        .ok_or_else(|| {
+                            SprayError::RpcError("Invalid deriveaddresses response".into())
+                        })
+                        .and_then(parse_address)
+                        .map(Resolved::from),
+                    _ => Err(SprayError::ConfigError(format!(
+                        "Descriptor {descriptor} does not derive a single address"
+                    ))),
+                }
+            }
+        }
+    }
+}
+
+/// The argument of `name(...)`, if `s` is such a call
+fn strip_call<'a>(s: &'a str, name: &str) -> Option<&'a str> {
+    s.strip_prefix(name)?.strip_prefix('(')?.strip_suffix(')')
+}
+
+/// Parse an address
+fn parse_address(s: &str) -> Result<Address, SprayError> {
+    Address::from_str(s).map_err(|e| SprayError::ParseError(format!("Invalid address {s}: {e}")))
+}
+
+impl FromStr for Destination {
+    type Err = SprayError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        if s == "wallet" {
+            Ok(Self::Wallet)
+        } else if s.contains('(') {
+            if !s.split('#').next().unwrap_or(s).ends_with(')') {
+                return Err(SprayError::ParseError(format!("Invalid descriptor: {s}")));
+            }
+            Ok(Self::Descriptor(s.to_string()))
+        } else {
+            parse_address(s).map(Self::Address)
+        }
+    }
+}
+
+impl TryFrom<String> for Destination {
+    type Error = SprayError;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        s.parse()
+    }
+}
+
+impl From<Destination> for String {
+    fn from(destination: Destination) -> Self {
+        destination.to_string()
+    }
+}
+
+impl fmt::Display for Destination {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Wallet => write!(f, "wallet"),
+            Self::Address(address) => write!(f, "{address}"),
+            Self::Descriptor(descriptor) => write!(f, "{descriptor}"),
+        }
+    }
+}
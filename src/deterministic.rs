@@ -0,0 +1,191 @@
+//! Deterministic test mode
+//!
+//! Two runs of the same suite normally produce different txids and
+//! sighashes: the wallet of an ephemeral node has fresh keys, the node's
+//! clock moves on, coin selection and blinding are random, and fuzz and
+//! property runs draw random seeds. With `spray --deterministic`:
+//!
+//! - fuzz and property runs default to the seed [`SEED`], and blinding
+//!   factors are drawn from streams derived from it ([`rng`])
+//! - the wallet of an ephemeral node is a legacy wallet with the fixed HD
+//!   seed [`WALLET_SEED_WIF`], so it derives the same addresses every run
+//! - the node's clock is pinned to [`MOCKTIME`] with `setmocktime`
+//! - contracts are funded by [`fund`] from an explicitly chosen wallet
+//!   output, with the fixed fee [`FUNDING_FEE`] and explicit change, rather
+//!   than by `sendtoaddress`
+//!
+//! so that the same programs, arguments and witnesses give byte-identical
+//! transactions, e.g. for golden-file comparisons or reproducible bug
+//! reports. Blinding factors are predictable in this mode, which is fine
+//! for tests and nothing else. `deploy --asset` is refused, since [`fund`]
+//! only funds the policy asset.
+//!
+//! The mode is a setting of each environment, turned on with
+//! [`TestEnvBuilder::deterministic`](crate::env::TestEnvBuilder::deterministic)
+//! or, for the backends of a command, with
+//! [`BackendConfig::deterministic`](crate::network::BackendConfig::deterministic).
+//! A node spray does not start cannot be pinned, so such a config refuses
+//! external nodes.
+//!
+//! # Example
+//!
+//! ```
+//! use serde_json::json;
+//! use spray::deterministic::select_input;
+//!
+//! let unspent = json!([
+//!     { "txid": "bb", "vout": 0, "amount": 5.0, "asset": "aa" },
+//!     { "txid": "cc", "vout": 1, "amount": 0.5, "asset": "aa" },
+//!     { "txid": "dd", "vout": 0, "amount": 0.5, "asset": "aa", "amountcommitment": "08ff" },
+//! ]);
+//! // The smallest explicit output of the asset that covers the amount
+//! let input = select_input(&unspent, "aa", 10_000_000).unwrap();
+//! assert_eq!((input.txid.as_str(), input.vout), ("cc", 1));
+//! assert!(select_input(&unspent, "aa", 600_000_000).is_none());
+//! ```
+
+use crate::client::{btc_to_sats, format_btc_amount};
+use crate::error::SprayError;
+use musk::elements::hashes::{sha256, Hash, HashEngine};
+use musk::elements::{Address, Txid};
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use serde_json::{json, Value};
+use std::str::FromStr;
+
+/// Seed of random draws in deterministic mode
+pub const SEED: u64 = 0x5350_5241_5900;
+
+/// Unix time the node's clock is pinned to in deterministic mode
+pub const MOCKTIME: u64 = 1_700_000_000;
+
+/// HD seed (regtest WIF) of the wallet of ephemeral nodes in deterministic
+/// mode
+pub const WALLET_SEED_WIF: &str = "cQw7xFucLZSJUR7owf6NJtXgL5SKrKGL2czciofdGNjMexn7oWad";
+
+/// Fee of funding transactions built in deterministic mode, in satoshis
+pub const FUNDING_FEE: u64 = 1_000;
+
+/// [`SEED`] if `deterministic`, a random seed otherwise
+#[must_use]
+pub fn seed(deterministic: bool) -> u64 {
+    if deterministic {
+        SEED
+    } else {
+        rand::random()
+    }
+}
+
+/// A random number generator seeded from [`SEED`] and `domain` if
+/// `deterministic`, and from the system's entropy otherwise
+///
+/// `domain` separates the streams of different draws, e.g. the outputs
+/// being blinded, so that they do not get the same blinding factors.
+#[must_use]
+pub fn rng(deterministic: bool, domain: &[u8]) -> StdRng {
+    if deterministic {
+        let mut engine = sha256::Hash::engine();
+        engine.input(&SEED.to_le_bytes());
+        engine.input(domain);
+        StdRng::from_seed(sha256::Hash::from_engine(engine).to_byte_array())
+    } else {
+        StdRng::from_entropy()
+    }
+}
+
+/// A wallet output chosen to fund a contract
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FundingInput {
+    /// Transaction of the output
+    pub txid: String,
+    /// Index of the output
+    pub vout: u32,
+    /// Amount in satoshis
+    pub amount: u64,
+}
+
+/// Choose the wallet output to fund `needed` satoshis of `asset` from, given
+/// the result of `listunspent`
+///
+/// Only explicit, spendable outputs of the asset are considered, so that
+/// no blinding is needed; of those that cover `needed`, the smallest is
+/// chosen, ties broken by outpoint.
+#[must_use]
+pub fn select_input(unspent: &Value, asset: &str, needed: u64) -> Option<FundingInput> {
+    unspent
+        .as_array()?
+        .iter()
+        .filter(|output| {
+            output["asset"].as_str() == Some(asset)
+                && output.get("amountcommitment").is_none()
+                && output["spendable"].as_bool() != Some(false)
+        })
+        .filter_map(|output| {
+            Some(FundingInput {
+                txid: output["txid"].as_str()?.to_string(),
+                vout: u32::try_from(output["vout"].as_u64()?).ok()?,
+                amount: btc_to_sats(output["amount"].as_f64()?),
+            })
+        })
+        .filter(|input| input.amount >= needed)
+        .min_by(|a, b| (a.amount, &a.txid, a.vout).cmp(&(b.amount, &b.txid, b.vout)))
+}
+
+/// Fund `address` with `amount` satoshis of the policy asset from a
+/// deterministically chosen wallet output, with explicit change
+///
+/// `call` makes an RPC call against the node of the wallet.
+///
+/// # Errors
+///
+/// Returns an error if the wallet has no explicit output covering the
+/// amount and fee, or the transaction cannot be signed or broadcast.
+pub fn fund(
+    call: impl Fn(&str, &[Value]) -> Result<Value, SprayError>,
+    address: &Address,
+    amount: u64,
+) -> Result<Txid, SprayError> {
+    let labels = call("dumpassetlabels", &[])?;
+    let asset = labels["bitcoin"]
+        .as_str()
+        .ok_or_else(|| SprayError::RpcError("Invalid dumpassetlabels response".into()))?;
+
+    let needed = amount + FUNDING_FEE;
+    let unspent = call("listunspent", &[])?;
+    let input = select_input(&unspent, asset, needed).ok_or_else(|| {
+        SprayError::InsufficientFunds(format!(
+            "No explicit wallet output covers {needed} sat for deterministic funding"
+        ))
+    })?;
+
+    let mut outputs = vec![json!({ address.to_string(): format_btc_amount(amount) })];
+    let change = input.amount - needed;
+    if change > 0 {
+        let change_address = call("getrawchangeaddress", &[])?;
+        let info = call("getaddressinfo", &[change_address])?;
+        let unconfidential = info["unconfidential"]
+            .as_str()
+            .ok_or_else(|| SprayError::RpcError("Invalid getaddressinfo response".into()))?;
+        outputs.push(json!({ unconfidential: format_btc_amount(change) }));
+    }
+    outputs.push(json!({ "fee": format_btc_amount(FUNDING_FEE) }));
+
+    let raw = call(
+        "createrawtransaction",
+        &[
+            json!([{ "txid": input.txid, "vout": input.vout }]),
+            Value::Array(outputs),
+        ],
+    )?;
+    let signed = call("signrawtransactionwithwallet", &[raw])?;
+    if signed["complete"].as_bool() != Some(true) {
+        return Err(SprayError::RpcError(
+            "Wallet could not sign the funding transaction".into(),
+        ));
+    }
+    let txid = call("sendrawtransaction", &[signed["hex"].clone()])?;
+    let txid = txid
+        .as_str()
+        .ok_or_else(|| SprayError::RpcError("Invalid txid response".into()))?;
+    Txid::from_str(txid).map_err(|e| SprayError::RpcError(e.to_string()))
+}
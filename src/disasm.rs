@@ -0,0 +1,208 @@
+//! Simplicity program disassembly
+//!
+//! This module decodes the program bytes of a [`CompiledOutput`] into its
+//! combinator and jet nodes. Nodes are numbered in post order, the order in
+//! which they are encoded, so a node's children always have lower indices and
+//! the root is the last node. Each node carries its CMR; when a witness is
+//! attached the program is decoded for redemption, which also yields IMRs.
+//!
+//! [`CompiledOutput`]: crate::compiled::CompiledOutput
+//!
+//! # Example
+//!
+//! ```
+//! use spray::disasm::{Disassembly, DisasmNode};
+//!
+//! let node = |index, op: &str, children: Vec<usize>| DisasmNode {
+//!     index,
+//!     op: op.to_string(),
+//!     children,
+//!     cmr: String::new(),
+//!     imr: None,
+//! };
+//! let disassembly = Disassembly {
+//!     nodes: vec![node(0, "iden", vec![]), node(1, "take", vec![0])],
+//! };
+//!
+//! assert_eq!(disassembly.root().unwrap().op, "take");
+//! assert_eq!(disassembly.tree(), "%1 take\n  %0 iden\n");
+//! ```
+
+use crate::error::SprayError;
+use simplicity::dag::{DagLike, InternalSharing};
+use simplicity::jet::Elements;
+use simplicity::node::Inner;
+use simplicity::{BitIter, CommitNode, RedeemNode};
+use std::fmt::{self, Write};
+
+/// A decoded program node
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DisasmNode {
+    /// Position of the node in post order
+    pub index: usize,
+    /// Combinator or jet, e.g. `comp`, `jet_eq_32` or `const 0x01`
+    pub op: String,
+    /// Indices of the node's children
+    pub children: Vec<usize>,
+    /// Commitment Merkle root (hex)
+    pub cmr: String,
+    /// Identity Merkle root (hex), known only when a witness is attached
+    pub imr: Option<String>,
+}
+
+/// Nodes of a decoded program, in post order
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Disassembly {
+    /// Nodes, root last
+    pub nodes: Vec<DisasmNode>,
+}
+
+impl Disassembly {
+    /// Decode a program, and its witness if given
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the bytes are not a valid encoding.
+    pub fn decode(program: &[u8], witness: Option<&[u8]>) -> Result<Self, SprayError> {
+        let decode_error = |e: simplicity::DecodeError| {
+            SprayError::ParseError(format!("Invalid Simplicity program: {e}"))
+        };
+
+        let nodes = match witness {
+            Some(witness) => {
+                let root = RedeemNode::<Elements>::decode(
+                    BitIter::from(program.iter().copied()),
+                    BitIter::from(witness.iter().copied()),
+                )
+                .map_err(decode_error)?;
+                root.post_order_iter::<InternalSharing>()
+                    .map(|item| {
+                        let mut node = disasm_node(
+                            item.index,
+                            item.node.inner(),
+                            item.left_index,
+                            item.right_index,
+                        );
+                        node.cmr = item.node.cmr().to_string();
+                        node.imr = Some(item.node.imr().to_string());
+                        node
+                    })
+                    .collect()
+            }
+            None => {
+                let root = CommitNode::<Elements>::decode(BitIter::from(program.iter().copied()))
+                    .map_err(decode_error)?;
+                root.post_order_iter::<InternalSharing>()
+                    .map(|item| {
+                        let mut node = disasm_node(
+                            item.index,
+                            item.node.inner(),
+                            item.left_index,
+                            item.right_index,
+                        );
+                        node.cmr = item.node.cmr().to_string();
+                        node
+                    })
+                    .collect()
+            }
+        };
+
+        Ok(Self { nodes })
+    }
+
+    /// The root node, i.e. the program itself
+    #[must_use]
+    pub fn root(&self) -> Option<&DisasmNode> {
+        self.nodes.last()
+    }
+
+    /// Render the nodes as an indented tree starting at the root
+    ///
+    /// A node shared by several parents is expanded only where it first
+    /// appears; later references show its index only.
+    #[must_use]
+    pub fn tree(&self) -> String {
+        let mut out = String::new();
+        let mut expanded = vec![false; self.nodes.len()];
+        if let Some(root) = self.root() {
+            self.render(root.index, 0, &mut expanded, &mut out);
+        }
+        out
+    }
+
+    /// Append node `index` and its children to `out`
+    fn render(&self, index: usize, depth: usize, expanded: &mut [bool], out: &mut String) {
+        let node = &self.nodes[index];
+        let indent = "  ".repeat(depth);
+        if expanded[index] {
+            let _ = writeln!(out, "{indent}%{index} (shared)");
+            return;
+        }
+        expanded[index] = true;
+
+        let _ = writeln!(out, "{indent}%{index} {}", node.op);
+        for &child in &node.children {
+            self.render(child, depth + 1, expanded, out);
+        }
+    }
+}
+
+impl fmt::Display for Disassembly {
+    /// One line per node: index, operation, children and Merkle root
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let width = self.nodes.len().saturating_sub(1).to_string().len();
+        for node in &self.nodes {
+            let children: Vec<String> = node.children.iter().map(|c| format!("%{c}")).collect();
+            let op = format!("{} {}", node.op, children.join(" "));
+            let (label, root) = node
+                .imr
+                .as_ref()
+                .map_or(("cmr", &node.cmr), |imr| ("imr", imr));
+            writeln!(
+                f,
+                "%{:<width$}  {:<32}  {label} {root}",
+                node.index,
+                op.trim_end()
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Build a node from a decoded combinator and its child indices
+fn disasm_node<C, J: fmt::Display, X, W>(
+    index: usize,
+    inner: &Inner<C, J, X, W>,
+    left: Option<usize>,
+    right: Option<usize>,
+) -> DisasmNode {
+    DisasmNode {
+        index,
+        op: op_name(inner),
+        children: left.into_iter().chain(right).collect(),
+        cmr: String::new(),
+        imr: None,
+    }
+}
+
+/// Name of a combinator or jet, e.g. `comp`, `jet_eq_32` or `const 0x01`
+pub(crate) fn op_name<C, J: fmt::Display, X, W>(inner: &Inner<C, J, X, W>) -> String {
+    match inner {
+        Inner::Iden => "iden".to_string(),
+        Inner::Unit => "unit".to_string(),
+        Inner::InjL(_) => "injl".to_string(),
+        Inner::InjR(_) => "injr".to_string(),
+        Inner::Take(_) => "take".to_string(),
+        Inner::Drop(_) => "drop".to_string(),
+        Inner::Comp(..) => "comp".to_string(),
+        Inner::Case(..) => "case".to_string(),
+        Inner::AssertL(_, cmr) => format!("assertl <{cmr}>"),
+        Inner::AssertR(cmr, _) => format!("assertr <{cmr}>"),
+        Inner::Pair(..) => "pair".to_string(),
+        Inner::Disconnect(..) => "disconnect".to_string(),
+        Inner::Witness(_) => "witness".to_string(),
+        Inner::Fail(_) => "fail".to_string(),
+        Inner::Jet(jet) => format!("jet_{jet}"),
+        Inner::Word(word) => format!("const {word}"),
+    }
+}
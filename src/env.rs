@@ -1,14 +1,289 @@
 //! Test environment management
 
+use crate::client::ElementsClient;
 use crate::error::SprayError;
-use elementsd::bitcoind::bitcoincore_rpc::RpcApi;
+use elementsd::bitcoind::bitcoincore_rpc::{Auth, Client, RpcApi};
 use elementsd::ElementsD;
+use std::io::{Read, Seek, SeekFrom};
+use std::net::SocketAddrV4;
+use std::path::{Path, PathBuf};
 use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
+/// How long to wait for cluster nodes to connect or synchronize
+const CLUSTER_SYNC_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Interval between polls while waiting for cluster nodes
+const CLUSTER_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// How long to wait for a daemon to shut down before restarting it
+const SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Bytes read from the end of `debug.log` for its last lines
+const LOG_TAIL_BYTES: u64 = 64 * 1024;
+
+/// Lines of `debug.log` attached to failures by `spray test --verbose`, see
+/// [`TestRunner::log_tail`](crate::TestRunner::log_tail)
+pub const DEFAULT_LOG_TAIL: usize = 20;
+
+/// Format the last `lines` lines of a log for appending to an error message
+///
+/// ```
+/// use spray::env::log_excerpt;
+///
+/// let excerpt = log_excerpt("a\nb\nc\n", 2);
+/// assert_eq!(excerpt, "\n  debug.log (last 2 lines):\n    b\n    c");
+/// assert_eq!(log_excerpt("", 2), "");
+/// ```
+#[must_use]
+pub fn log_excerpt(log: &str, lines: usize) -> String {
+    let all: Vec<&str> = log.lines().collect();
+    let tail = &all[all.len().saturating_sub(lines)..];
+    if tail.is_empty() {
+        return String::new();
+    }
+    let mut excerpt = format!("\n  debug.log (last {} lines):", tail.len());
+    for line in tail {
+        excerpt.push_str("\n    ");
+        excerpt.push_str(line);
+    }
+    excerpt
+}
+
+/// Classify a failure to start `elementsd`, which reports a daemon that
+/// never answered as a timeout
+fn startup_error(detail: String) -> SprayError {
+    let lower = detail.to_lowercase();
+    if lower.contains("timeout") || lower.contains("timed out") {
+        SprayError::DaemonStartupTimeout(detail)
+    } else {
+        SprayError::DaemonError(detail)
+    }
+}
 
 /// Test environment managing an Elements daemon
+///
+/// An environment created with [`TestEnv::new_cluster`] additionally manages
+/// peer daemons connected to the primary one over P2P. One created with
+/// [`TestEnv::new_restartable`] keeps its data directory across
+/// [`TestEnv::restart`]s. [`TestEnvBuilder`] configures the primary daemon
+/// further.
 pub struct TestEnv {
     daemon: ElementsD,
+    peers: Vec<ElementsD>,
     genesis_hash: musk::elements::BlockHash,
+    data_dir: Option<PathBuf>,
+    remove_data_dir: bool,
+    args: Vec<String>,
+    p2p_socket: Option<SocketAddrV4>,
+    miner: Option<Miner>,
+    deterministic: bool,
+}
+
+/// Options of a [`TestEnv`]'s primary daemon
+///
+/// ```no_run
+/// use spray::env::TestEnvBuilder;
+/// use std::time::Duration;
+///
+/// let env = TestEnvBuilder::new()
+///     .rpc_port(18884)
+///     .data_dir("/tmp/spray-debug")
+///     .block_time(Duration::from_secs(10))
+///     .arg("-acceptnonstdtxn=1")
+///     .build()?;
+/// # Ok::<(), spray::SprayError>(())
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct TestEnvBuilder {
+    rpc_port: Option<u16>,
+    p2p_port: Option<u16>,
+    data_dir: Option<PathBuf>,
+    keep_data_dir: bool,
+    remove_data_dir: bool,
+    block_time: Option<Duration>,
+    args: Vec<String>,
+    deterministic: bool,
+}
+
+impl TestEnvBuilder {
+    /// Options of a default environment, as created by [`TestEnv::new`]
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Also serve RPC on `127.0.0.1:port`, e.g. for wallets or explorers
+    /// that expect a fixed port
+    ///
+    /// The daemon keeps answering spray on its own free port; clients of the
+    /// fixed port authenticate with the `.cookie` file of the data directory.
+    #[must_use]
+    pub const fn rpc_port(mut self, port: u16) -> Self {
+        self.rpc_port = Some(port);
+        self
+    }
+
+    /// Listen for P2P connections on `port`
+    #[must_use]
+    pub const fn p2p_port(mut self, port: u16) -> Self {
+        self.p2p_port = Some(port);
+        self
+    }
+
+    /// Run the daemon in `dir` rather than in a temporary directory, which
+    /// makes the environment restartable
+    ///
+    /// The directory is the caller's, so it is left in place when the
+    /// environment is dropped, unless
+    /// [`remove_data_dir`](Self::remove_data_dir) is set.
+    #[must_use]
+    pub fn data_dir(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.data_dir = Some(dir.into());
+        self
+    }
+
+    /// Keep the data directory after the environment is dropped, to inspect
+    /// the chain, wallet or `debug.log` of a failed run
+    ///
+    /// Without [`data_dir`](Self::data_dir), the daemon runs in a directory
+    /// spray creates under the system temporary directory.
+    #[must_use]
+    pub const fn keep_data_dir(mut self, keep: bool) -> Self {
+        self.keep_data_dir = keep;
+        self
+    }
+
+    /// Remove the directory given to [`data_dir`](Self::data_dir) when the
+    /// environment is dropped
+    #[must_use]
+    pub const fn remove_data_dir(mut self, remove: bool) -> Self {
+        self.remove_data_dir = remove;
+        self
+    }
+
+    /// Mine a block every `interval` in the background, as on a live
+    /// network, in addition to the blocks tests mine themselves
+    #[must_use]
+    pub const fn block_time(mut self, interval: Duration) -> Self {
+        self.block_time = Some(interval);
+        self
+    }
+
+    /// Run the daemon in [deterministic mode](crate::deterministic): a
+    /// wallet with a fixed seed, a pinned clock and funding from explicitly
+    /// chosen outputs
+    #[must_use]
+    pub const fn deterministic(mut self, deterministic: bool) -> Self {
+        self.deterministic = deterministic;
+        self
+    }
+
+    /// Pass an extra command-line argument to `elementsd`, e.g.
+    /// `"-acceptnonstdtxn=1"`
+    ///
+    /// Arguments override spray's defaults of the same name.
+    #[must_use]
+    pub fn arg(mut self, arg: impl Into<String>) -> Self {
+        self.args.push(arg.into());
+        self
+    }
+
+    /// Start the daemon and set up its funded wallet
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the daemon fails to start or the wallet cannot be
+    /// set up, e.g. because a fixed port is in use.
+    pub fn build(self) -> Result<TestEnv, SprayError> {
+        let (data_dir, remove_data_dir) = match self.data_dir {
+            Some(dir) => (Some(dir), self.remove_data_dir),
+            None if self.keep_data_dir => (Some(crate::gc::create_datadir()?), false),
+            None => (None, false),
+        };
+
+        let mut args = Vec::new();
+        if let Some(port) = self.rpc_port {
+            args.push("-rpcallowip=127.0.0.1".to_string());
+            args.push("-rpcbind=127.0.0.1".to_string());
+            args.push(format!("-rpcbind=127.0.0.1:{port}"));
+        }
+        if let Some(port) = self.p2p_port {
+            args.push(format!("-port={port}"));
+        }
+        args.extend(self.args);
+
+        let daemon = TestEnv::start_daemon_in(self.p2p_port.is_some(), data_dir.as_deref(), &args)?;
+        let mut env = TestEnv::with_daemon(daemon, self.deterministic)?;
+        env.data_dir = data_dir;
+        env.remove_data_dir = remove_data_dir;
+        env.args = args;
+        env.p2p_socket = self
+            .p2p_port
+            .map(|port| SocketAddrV4::new(std::net::Ipv4Addr::LOCALHOST, port));
+        if let Some(interval) = self.block_time {
+            env.miner = Some(Miner::start(&env.daemon, interval)?);
+        }
+        Ok(env)
+    }
+}
+
+/// Background thread mining a block at a fixed interval
+struct Miner {
+    interval: Duration,
+    stop: Arc<AtomicBool>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl Miner {
+    /// Start mining on `daemon` every `interval`
+    fn start(daemon: &ElementsD, interval: Duration) -> Result<Self, SprayError> {
+        let address = daemon
+            .client()
+            .call::<serde_json::Value>("getnewaddress", &[])
+            .map_err(|e| SprayError::RpcError(e.to_string()))?;
+        let client = Client::new(
+            &format!("http://{}", daemon.params.rpc_socket),
+            Auth::CookieFile(daemon.params.cookie_file.clone()),
+        )
+        .map_err(|e| SprayError::RpcError(e.to_string()))?;
+
+        tracing::debug!(?interval, "mining in the background");
+        let stop = Arc::new(AtomicBool::new(false));
+        let stopped = Arc::clone(&stop);
+        let handle = std::thread::spawn(move || {
+            let mut last = Instant::now();
+            while !stopped.load(Ordering::Relaxed) {
+                std::thread::sleep(CLUSTER_POLL_INTERVAL);
+                if last.elapsed() >= interval {
+                    // A failed block is retried at the next interval
+                    let _ = client.call::<serde_json::Value>(
+                        "generatetoaddress",
+                        &[1.into(), address.clone()],
+                    );
+                    last = Instant::now();
+                }
+            }
+        });
+
+        Ok(Self {
+            interval,
+            stop,
+            handle: Some(handle),
+        })
+    }
+}
+
+impl Drop for Miner {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
 }
 
 impl TestEnv {
@@ -16,14 +291,142 @@ impl TestEnv {
     ///
     /// # Errors
     ///
-    /// Returns an error if the daemon fails to start, wallet creation fails,
-    /// or the genesis hash cannot be retrieved.
+    /// Returns an error if no `elementsd` is found (or the release pinned in
+    /// `spray.toml` cannot be installed), the daemon fails to start, wallet
+    /// creation fails, or the genesis hash cannot be retrieved.
+    pub fn new() -> Result<Self, SprayError> {
+        TestEnvBuilder::new().build()
+    }
+
+    /// Create a test environment whose daemon can be restarted
     ///
-    /// # Panics
+    /// The daemon's data directory is kept under the system temporary
+    /// directory, rather than in a directory deleted with the daemon, and is
+    /// removed when the environment is dropped.
     ///
-    /// Panics if `elementsd::exe_path()` returns `None`, indicating the
-    /// `elementsd` executable is not found.
-    pub fn new() -> Result<Self, SprayError> {
+    /// # Errors
+    ///
+    /// Returns an error if the daemon fails to start or the wallet cannot be
+    /// set up.
+    pub fn new_restartable() -> Result<Self, SprayError> {
+        TestEnvBuilder::new()
+            .data_dir(crate::gc::create_datadir()?)
+            .remove_data_dir(true)
+            .build()
+    }
+
+    /// Set up the funded wallet on a freshly started primary daemon
+    fn with_daemon(daemon: ElementsD, deterministic: bool) -> Result<Self, SprayError> {
+        if deterministic {
+            Self::create_deterministic_wallet(&daemon)?;
+            Self::pin_mocktime(&daemon)?;
+        } else {
+            Self::create_wallet(&daemon)?;
+        }
+
+        // Rescan blockchain
+        let _rescan = daemon
+            .client()
+            .call::<serde_json::Value>("rescanblockchain", &[])
+            .map_err(|e| SprayError::RpcError(e.to_string()))?;
+
+        // Get genesis hash
+        let genesis_str = daemon
+            .client()
+            .call::<serde_json::Value>("getblockhash", &[0u32.into()])
+            .map_err(|e| SprayError::RpcError(e.to_string()))?;
+
+        let genesis_hash = musk::elements::BlockHash::from_str(
+            genesis_str
+                .as_str()
+                .ok_or_else(|| SprayError::EnvironmentError("Invalid genesis hash".into()))?,
+        )
+        .map_err(|e| SprayError::EnvironmentError(e.to_string()))?;
+        tracing::debug!(%genesis_hash, "wallet ready");
+
+        Ok(Self {
+            daemon,
+            peers: Vec::new(),
+            genesis_hash,
+            data_dir: None,
+            remove_data_dir: false,
+            args: Vec::new(),
+            p2p_socket: None,
+            miner: None,
+            deterministic,
+        })
+    }
+
+    /// Create a test environment with `n` regtest daemons connected over P2P
+    ///
+    /// Node 0 is the primary daemon (returned by [`TestEnv::daemon`]) and holds
+    /// the pre-funded wallet. Every other node gets an empty wallet and is
+    /// connected to the primary with `addnode`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `n` is zero, any daemon fails to start, or the
+    /// nodes fail to connect within the timeout.
+    pub fn new_cluster(n: usize) -> Result<Self, SprayError> {
+        if n == 0 {
+            return Err(SprayError::EnvironmentError(
+                "Cluster must contain at least one node".into(),
+            ));
+        }
+
+        // Enable P2P on the primary so peers can reach it
+        let mut env = Self::with_daemon(Self::start_daemon(n > 1)?, false)?;
+        if n == 1 {
+            return Ok(env);
+        }
+
+        let primary_socket =
+            env.daemon.params.p2p_socket.ok_or_else(|| {
+                SprayError::EnvironmentError("Primary node has no P2P socket".into())
+            })?;
+
+        for _ in 1..n {
+            let peer = Self::start_daemon(true)?;
+            Self::create_wallet(&peer)?;
+
+            peer.client()
+                .call::<serde_json::Value>(
+                    "addnode",
+                    &[primary_socket.to_string().into(), "onetry".into()],
+                )
+                .map_err(|e| SprayError::RpcError(e.to_string()))?;
+
+            env.peers.push(peer);
+        }
+
+        env.wait_until("peers to connect", |env| {
+            for node in env.nodes() {
+                let count = node
+                    .client()
+                    .call::<serde_json::Value>("getconnectioncount", &[])
+                    .map_err(|e| SprayError::RpcError(e.to_string()))?;
+                if count.as_u64().unwrap_or(0) == 0 {
+                    return Ok(false);
+                }
+            }
+            Ok(true)
+        })?;
+
+        Ok(env)
+    }
+
+    /// Start a regtest daemon with Simplicity enabled
+    fn start_daemon(p2p: bool) -> Result<ElementsD, SprayError> {
+        Self::start_daemon_in(p2p, None, &[])
+    }
+
+    /// Start a regtest daemon with Simplicity enabled in `data_dir`, or in a
+    /// temporary directory, passing it `extra_args`
+    fn start_daemon_in(
+        p2p: bool,
+        data_dir: Option<&Path>,
+        extra_args: &[String],
+    ) -> Result<ElementsD, SprayError> {
         let mut conf = elementsd::Conf::new(None);
 
         // Increase initial free coins for testing
@@ -41,10 +444,26 @@ impl TestEnv {
         // Enable Simplicity
         conf.0.args.push("-evbparams=simplicity:-1:::");
 
-        let daemon = ElementsD::with_conf(elementsd::exe_path().unwrap(), &conf)
-            .map_err(|e| SprayError::DaemonError(e.to_string()))?;
+        if p2p {
+            conf.0.p2p = elementsd::bitcoind::P2P::Yes;
+        }
+
+        conf.0.args.extend(extra_args.iter().map(String::as_str));
+        conf.0.staticdir = data_dir.map(Path::to_path_buf);
 
-        // Create wallet
+        let daemon = ElementsD::with_conf(crate::binary::exe_path()?, &conf)
+            .map_err(|e| startup_error(e.to_string()))?;
+        tracing::debug!(
+            rpc = %daemon.params.rpc_socket,
+            p2p = ?daemon.params.p2p_socket,
+            data_dir = ?data_dir,
+            "started elementsd"
+        );
+        Ok(daemon)
+    }
+
+    /// Create the default wallet on a daemon
+    fn create_wallet(daemon: &ElementsD) -> Result<(), SprayError> {
         let create = daemon
             .client()
             .call::<serde_json::Value>("createwallet", &["wallet".into()])
@@ -56,29 +475,46 @@ impl TestEnv {
             ));
         }
 
-        // Rescan blockchain
-        let _rescan = daemon
-            .client()
-            .call::<serde_json::Value>("rescanblockchain", &[])
-            .map_err(|e| SprayError::RpcError(e.to_string()))?;
+        Ok(())
+    }
 
-        // Get genesis hash
-        let genesis_str = daemon
-            .client()
-            .call::<serde_json::Value>("getblockhash", &[0u32.into()])
-            .map_err(|e| SprayError::RpcError(e.to_string()))?;
+    /// Create the default wallet as a legacy wallet with the fixed HD seed
+    /// of [deterministic mode](crate::deterministic)
+    fn create_deterministic_wallet(daemon: &ElementsD) -> Result<(), SprayError> {
+        let call = |method: &str, params: &[serde_json::Value]| {
+            daemon
+                .client()
+                .call::<serde_json::Value>(method, params)
+                .map_err(|e| SprayError::RpcError(e.to_string()))
+        };
 
-        let genesis_hash = musk::elements::BlockHash::from_str(
-            genesis_str
-                .as_str()
-                .ok_or_else(|| SprayError::EnvironmentError("Invalid genesis hash".into()))?,
-        )
-        .map_err(|e| SprayError::EnvironmentError(e.to_string()))?;
+        // createwallet name disable_private_keys blank passphrase avoid_reuse descriptors
+        call(
+            "createwallet",
+            &[
+                "wallet".into(),
+                false.into(),
+                true.into(),
+                "".into(),
+                false.into(),
+                false.into(),
+            ],
+        )?;
+        call(
+            "sethdseed",
+            &[true.into(), crate::deterministic::WALLET_SEED_WIF.into()],
+        )?;
+        Ok(())
+    }
 
-        Ok(Self {
-            daemon,
-            genesis_hash,
-        })
+    /// Pin the daemon's clock to the mocktime of
+    /// [deterministic mode](crate::deterministic)
+    fn pin_mocktime(daemon: &ElementsD) -> Result<(), SprayError> {
+        daemon
+            .client()
+            .call::<serde_json::Value>("setmocktime", &[crate::deterministic::MOCKTIME.into()])
+            .map_err(|e| SprayError::RpcError(e.to_string()))?;
+        Ok(())
     }
 
     /// Get a reference to the daemon
@@ -87,6 +523,152 @@ impl TestEnv {
         &self.daemon
     }
 
+    /// Whether the environment runs in
+    /// [deterministic mode](crate::deterministic)
+    #[must_use]
+    pub const fn is_deterministic(&self) -> bool {
+        self.deterministic
+    }
+
+    /// Data directory of the primary daemon, if it does not run in a
+    /// temporary directory
+    #[must_use]
+    pub fn data_dir(&self) -> Option<&Path> {
+        self.data_dir.as_deref()
+    }
+
+    /// Path of the primary daemon's `debug.log`, if it exists
+    #[must_use]
+    pub fn debug_log(&self) -> Option<PathBuf> {
+        let dir = self
+            .data_dir
+            .clone()
+            .unwrap_or_else(|| self.daemon.workdir());
+        let chain_dirs = std::fs::read_dir(&dir)
+            .into_iter()
+            .flatten()
+            .filter_map(Result::ok)
+            .map(|entry| entry.path());
+        std::iter::once(dir.clone())
+            .chain(chain_dirs)
+            .map(|dir| dir.join("debug.log"))
+            .find(|path| path.is_file())
+    }
+
+    /// The last `lines` lines of the primary daemon's `debug.log`, formatted
+    /// by [`log_excerpt`], or an empty string if the log cannot be read
+    #[must_use]
+    pub fn log_tail(&self, lines: usize) -> String {
+        let Some(path) = self.debug_log() else {
+            return String::new();
+        };
+        let mut contents = Vec::new();
+        let read = std::fs::File::open(path).and_then(|mut file| {
+            let len = file.metadata()?.len();
+            file.seek(SeekFrom::Start(len.saturating_sub(LOG_TAIL_BYTES)))?;
+            file.read_to_end(&mut contents)
+        });
+        match read {
+            Ok(_) => log_excerpt(&String::from_utf8_lossy(&contents), lines),
+            Err(_) => String::new(),
+        }
+    }
+
+    /// P2P address of the primary daemon, if it listens for connections
+    #[must_use]
+    pub fn p2p_socket(&self) -> Option<SocketAddrV4> {
+        self.p2p_socket.or(self.daemon.params.p2p_socket)
+    }
+
+    /// Number of daemons in this environment (1 unless created as a cluster)
+    #[must_use]
+    pub fn node_count(&self) -> usize {
+        self.peers.len() + 1
+    }
+
+    /// Get the daemon at `index`, where index 0 is the primary daemon
+    #[must_use]
+    pub fn node(&self, index: usize) -> Option<&ElementsD> {
+        match index {
+            0 => Some(&self.daemon),
+            i => self.peers.get(i - 1),
+        }
+    }
+
+    /// Iterate over all daemons, starting with the primary
+    pub fn nodes(&self) -> impl Iterator<Item = &ElementsD> {
+        std::iter::once(&self.daemon).chain(self.peers.iter())
+    }
+
+    /// Get a `NodeClient` for the daemon at `index`
+    #[must_use]
+    pub fn client(&self, index: usize) -> Option<ElementsClient<'_>> {
+        self.node(index).map(ElementsClient::new)
+    }
+
+    /// Wait until every node reports the same best block hash
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if an RPC call fails or the nodes do not converge
+    /// within the timeout.
+    pub fn sync_blocks(&self) -> Result<(), SprayError> {
+        self.wait_until("block sync", |env| env.all_nodes_agree("getbestblockhash"))
+    }
+
+    /// Wait until every node reports the same mempool contents
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if an RPC call fails or the mempools do not converge
+    /// within the timeout.
+    pub fn sync_mempools(&self) -> Result<(), SprayError> {
+        self.wait_until("mempool sync", |env| env.all_nodes_agree("getrawmempool"))
+    }
+
+    /// Check whether all nodes return the same result for a parameterless RPC
+    fn all_nodes_agree(&self, method: &str) -> Result<bool, SprayError> {
+        let mut results = self.nodes().map(|node| {
+            node.client()
+                .call::<serde_json::Value>(method, &[])
+                .map_err(|e| SprayError::RpcError(e.to_string()))
+        });
+
+        let first = results.next().transpose()?;
+        for result in results {
+            let mut value = result?;
+            let mut expected = first.clone().unwrap_or_default();
+            // Mempool ordering is not significant
+            if let (Some(a), Some(b)) = (value.as_array_mut(), expected.as_array_mut()) {
+                a.sort_by(|x, y| x.to_string().cmp(&y.to_string()));
+                b.sort_by(|x, y| x.to_string().cmp(&y.to_string()));
+            }
+            if value != expected {
+                return Ok(false);
+            }
+        }
+
+        Ok(true)
+    }
+
+    /// Poll `condition` until it holds or the cluster timeout elapses
+    fn wait_until<F>(&self, what: &str, condition: F) -> Result<(), SprayError>
+    where
+        F: Fn(&Self) -> Result<bool, SprayError>,
+    {
+        let start = Instant::now();
+        while start.elapsed() < CLUSTER_SYNC_TIMEOUT {
+            if condition(self)? {
+                return Ok(());
+            }
+            std::thread::sleep(CLUSTER_POLL_INTERVAL);
+        }
+
+        Err(SprayError::EnvironmentError(format!(
+            "Timed out waiting for {what}"
+        )))
+    }
+
     /// Get the genesis block hash
     #[must_use]
     pub const fn genesis_hash(&self) -> musk::elements::BlockHash {
@@ -113,13 +695,176 @@ impl TestEnv {
             .client()
             .call::<serde_json::Value>("generatetoaddress", &[blocks.into(), address_str.into()])
             .map_err(|e| SprayError::RpcError(e.to_string()))?;
+        tracing::trace!(blocks, "generated blocks");
 
         Ok(())
     }
+
+    /// Get the current block height
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the RPC call fails or the response is malformed.
+    pub fn block_count(&self) -> Result<u64, SprayError> {
+        self.daemon
+            .client()
+            .call::<serde_json::Value>("getblockcount", &[])
+            .map_err(|e| SprayError::RpcError(e.to_string()))?
+            .as_u64()
+            .ok_or_else(|| SprayError::RpcError("Invalid block count response".into()))
+    }
+
+    /// Get the hash of the block at the given height
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the RPC call fails or the hash cannot be parsed.
+    pub fn block_hash(&self, height: u64) -> Result<musk::elements::BlockHash, SprayError> {
+        let hash_str = self
+            .daemon
+            .client()
+            .call::<serde_json::Value>("getblockhash", &[height.into()])
+            .map_err(|e| SprayError::RpcError(e.to_string()))?;
+
+        musk::elements::BlockHash::from_str(
+            hash_str
+                .as_str()
+                .ok_or_else(|| SprayError::RpcError("Invalid block hash response".into()))?,
+        )
+        .map_err(|e| SprayError::RpcError(e.to_string()))
+    }
+
+    /// Mark a block as invalid, disconnecting it and all of its descendants
+    ///
+    /// Transactions from the disconnected blocks are returned to the mempool.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the RPC call to `invalidateblock` fails.
+    pub fn invalidate_block(&self, hash: &musk::elements::BlockHash) -> Result<(), SprayError> {
+        self.daemon
+            .client()
+            .call::<serde_json::Value>("invalidateblock", &[hash.to_string().into()])
+            .map_err(|e| SprayError::RpcError(e.to_string()))?;
+
+        Ok(())
+    }
+
+    /// Remove the invalid mark from a block previously passed to
+    /// [`TestEnv::invalidate_block`], allowing it to become active again
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the RPC call to `reconsiderblock` fails.
+    pub fn reconsider_block(&self, hash: &musk::elements::BlockHash) -> Result<(), SprayError> {
+        self.daemon
+            .client()
+            .call::<serde_json::Value>("reconsiderblock", &[hash.to_string().into()])
+            .map_err(|e| SprayError::RpcError(e.to_string()))?;
+
+        Ok(())
+    }
+
+    /// Restart the primary daemon on its data directory
+    ///
+    /// The daemon is shut down cleanly and started again with the same chain
+    /// and wallet. With `keep_mempool`, the mempool is saved and reloaded as on
+    /// a normal restart; otherwise it is dropped, evicting every unconfirmed
+    /// transaction except those the wallet rebroadcasts itself.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the environment was not created with
+    /// [`TestEnv::new_restartable`] or a data directory, or the daemon fails
+    /// to stop or start.
+    pub fn restart(&mut self, keep_mempool: bool) -> Result<(), SprayError> {
+        let dir = self.data_dir.clone().ok_or_else(|| {
+            SprayError::EnvironmentError(
+                "Only environments created with TestEnv::new_restartable or a data directory can be restarted".into(),
+            )
+        })?;
+
+        let interval = self.miner.take().map(|miner| miner.interval);
+        self.stop_daemon()?;
+        let mut extra_args = self.args.clone();
+        if !keep_mempool {
+            extra_args.push("-persistmempool=0".to_string());
+        }
+        let daemon = Self::start_daemon_in(self.p2p_socket.is_some(), Some(&dir), &extra_args)?;
+        daemon
+            .client()
+            .call::<serde_json::Value>("loadwallet", &["wallet".into()])
+            .map_err(|e| SprayError::RpcError(e.to_string()))?;
+        if self.deterministic {
+            Self::pin_mocktime(&daemon)?;
+        }
+        if let Some(interval) = interval {
+            self.miner = Some(Miner::start(&daemon, interval)?);
+        }
+        tracing::info!(keep_mempool, "restarted elementsd");
+        self.daemon = daemon;
+        Ok(())
+    }
+
+    /// Ask the primary daemon to shut down and wait until it stops answering
+    fn stop_daemon(&self) -> Result<(), SprayError> {
+        self.daemon
+            .client()
+            .call::<serde_json::Value>("stop", &[])
+            .map_err(|e| SprayError::RpcError(e.to_string()))?;
+
+        let start = Instant::now();
+        while self.block_count().is_ok() {
+            if start.elapsed() > SHUTDOWN_TIMEOUT {
+                return Err(SprayError::DaemonError(
+                    "Timed out waiting for the daemon to stop".into(),
+                ));
+            }
+            std::thread::sleep(CLUSTER_POLL_INTERVAL);
+        }
+        // The RPC server stops before the data directory lock is released
+        std::thread::sleep(Duration::from_secs(1));
+        Ok(())
+    }
+
+    /// Simulate a chain reorganization of the given depth
+    ///
+    /// Invalidates the last `depth` blocks and mines `depth + 1` replacement
+    /// blocks, so the new chain is strictly longer than the old one. Returns
+    /// the hash of the first disconnected block, which can be passed to
+    /// [`TestEnv::reconsider_block`] to inspect the original chain again.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `depth` is zero or exceeds the chain height, or if
+    /// any of the underlying RPC calls fail.
+    pub fn reorg(&self, depth: u32) -> Result<musk::elements::BlockHash, SprayError> {
+        let height = self.block_count()?;
+
+        if depth == 0 || u64::from(depth) > height {
+            return Err(SprayError::EnvironmentError(format!(
+                "Invalid reorg depth {depth} for chain height {height}"
+            )));
+        }
+
+        let fork_hash = self.block_hash(height - u64::from(depth) + 1)?;
+        self.invalidate_block(&fork_hash)?;
+        self.generate(depth + 1)?;
+
+        Ok(fork_hash)
+    }
 }
 
 impl Drop for TestEnv {
     fn drop(&mut self) {
-        // Daemon will be cleaned up automatically
+        self.miner = None;
+        // Daemons will be cleaned up automatically, but a data directory
+        // must be stopped in, and removed only if it is ours to remove
+        if let Some(dir) = self.data_dir.take() {
+            let _ = self.stop_daemon();
+            if self.remove_data_dir {
+                let _ = std::fs::remove_dir_all(dir);
+            }
+        }
     }
 }
@@ -1,11 +1,16 @@
 //! Error types for spray operations
 //!
 //! This module defines the [`SprayError`] type which encompasses all errors
-//! that can occur during spray operations.
+//! that can occur during spray operations. Each kind of error has a stable
+//! message ID, a stable numeric [code](SprayError::code) and an
+//! [exit status](ExitCode), which `--json` error documents carry. Errors can
+//! be wrapped in [context](SprayError::context), e.g. the file being loaded;
+//! the kind, code and exit status are those of the underlying error.
 //!
 //! # Example
 //!
 //! ```
+//! use spray::error::ExitCode;
 //! use spray::SprayError;
 //!
 //! // Create error variants directly
@@ -16,8 +21,19 @@
 //! // Errors implement Display
 //! assert!(env_err.to_string().contains("environment"));
 //! assert!(config_err.to_string().contains("Configuration"));
+//!
+//! // Each kind of error exits the binary with the status of its class
+//! assert_eq!(config_err.exit_code(), ExitCode::ConfigError);
+//! assert_eq!(ExitCode::ConfigError.code(), 5);
+//!
+//! // Context wraps an error without changing its kind
+//! let err = parse_err.context("Failed to load witness.json");
+//! assert_eq!(err.to_string(), "Failed to load witness.json");
+//! assert_eq!(err.message_id(), "error.parse");
+//! assert_eq!(err.detail(), "Failed to load witness.json: invalid syntax");
 //! ```
 
+use std::fmt::Display;
 use thiserror::Error;
 
 /// Errors that can occur during spray operations
@@ -25,6 +41,7 @@ use thiserror::Error;
 /// Each variant represents a different category of error that can occur
 /// when using spray for program testing.
 #[derive(Debug, Error)]
+#[non_exhaustive]
 pub enum SprayError {
     #[error("Failed to setup test environment: {0}")]
     EnvironmentError(String),
@@ -61,4 +78,228 @@ pub enum SprayError {
 
     #[error("Invalid UTXO reference: {0}")]
     InvalidUtxoRef(String),
+
+    #[error("Incompatible node: {0}")]
+    IncompatibleNode(String),
+
+    #[error("Outpoint locked: {0}")]
+    OutpointLocked(String),
+
+    #[error("Signature error: {0}")]
+    SignatureError(String),
+
+    #[error("Node unavailable: {0}")]
+    NodeUnavailable(String),
+
+    #[error("Transaction rejected by the mempool: {0}")]
+    MempoolReject(String),
+
+    #[error("Insufficient funds: {0}")]
+    InsufficientFunds(String),
+
+    #[error("Witness type mismatch: {0}")]
+    WitnessTypeMismatch(String),
+
+    #[error("Daemon startup timed out: {0}")]
+    DaemonStartupTimeout(String),
+
+    #[error("{context}")]
+    Context {
+        context: String,
+        #[source]
+        source: Box<SprayError>,
+    },
+}
+
+/// Exit status of the `spray` binary, by class of failure
+///
+/// Status 2 is left to argument errors, which `clap` reports itself.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExitCode {
+    /// Any other failure, and checks such as `spray verify` that fail
+    Failure = 1,
+    /// A test suite, stress, fuzz or soak run had failures
+    TestFailure = 3,
+    /// The program does not compile or satisfy
+    CompileError = 4,
+    /// A configuration or input file is missing or invalid
+    ConfigError = 5,
+    /// The node cannot be started, reached, or does not support Simplicity
+    NodeUnavailable = 6,
+    /// The node rejected a broadcast transaction
+    BroadcastRejected = 7,
+}
+
+impl ExitCode {
+    /// The process exit status
+    #[must_use]
+    pub const fn code(self) -> i32 {
+        self as i32
+    }
+}
+
+impl SprayError {
+    /// Classify the message of a failed RPC call, e.g. a wallet without
+    /// enough funds as [`SprayError::InsufficientFunds`]
+    #[must_use]
+    pub fn rpc(detail: impl Into<String>) -> Self {
+        let detail = detail.into();
+        if detail.to_lowercase().contains("insufficient funds") {
+            Self::InsufficientFunds(detail)
+        } else {
+            Self::RpcError(detail)
+        }
+    }
+
+    /// Wrap the error in `context`, e.g. what was being done when it
+    /// happened
+    #[must_use]
+    pub fn context(self, context: impl Display) -> Self {
+        Self::Context {
+            context: context.to_string(),
+            source: Box::new(self),
+        }
+    }
+
+    /// The underlying error, without any context
+    #[must_use]
+    pub fn root_cause(&self) -> &Self {
+        match self {
+            Self::Context { source, .. } => source.root_cause(),
+            error => error,
+        }
+    }
+
+    /// Stable message ID of the error's kind, see [`crate::messages`]
+    #[must_use]
+    pub fn message_id(&self) -> &'static str {
+        match self {
+            Self::EnvironmentError(_) => "error.environment",
+            Self::DaemonError(_) => "error.daemon",
+            Self::TestError(_) => "error.test",
+            Self::ProgramError(_) => "error.program",
+            Self::SpendError(_) => "error.spend",
+            Self::IoError(_) => "error.io",
+            Self::JsonError(_) => "error.json",
+            Self::RpcError(_) => "error.rpc",
+            Self::ConfigError(_) => "error.config",
+            Self::ParseError(_) => "error.parse",
+            Self::FileFormatError(_) => "error.file_format",
+            Self::InvalidUtxoRef(_) => "error.invalid_utxo_ref",
+            Self::IncompatibleNode(_) => "error.incompatible_node",
+            Self::OutpointLocked(_) => "error.outpoint_locked",
+            Self::SignatureError(_) => "error.signature",
+            Self::NodeUnavailable(_) => "error.node_unavailable",
+            Self::MempoolReject(_) => "error.mempool_reject",
+            Self::InsufficientFunds(_) => "error.insufficient_funds",
+            Self::WitnessTypeMismatch(_) => "error.witness_type_mismatch",
+            Self::DaemonStartupTimeout(_) => "error.daemon_startup_timeout",
+            Self::Context { source, .. } => source.message_id(),
+        }
+    }
+
+    /// Stable numeric code of the error's kind
+    ///
+    /// Codes are grouped by hundreds: 1xx the environment and node, 2xx
+    /// RPC and broadcasting, 3xx programs and spends, 4xx tests, 5xx files
+    /// and input. Codes are never reused.
+    #[must_use]
+    pub fn code(&self) -> u16 {
+        match self {
+            Self::EnvironmentError(_) => 100,
+            Self::DaemonError(_) => 101,
+            Self::DaemonStartupTimeout(_) => 102,
+            Self::NodeUnavailable(_) => 103,
+            Self::IncompatibleNode(_) => 104,
+            Self::RpcError(_) => 200,
+            Self::MempoolReject(_) => 201,
+            Self::InsufficientFunds(_) => 202,
+            Self::OutpointLocked(_) => 203,
+            Self::ProgramError(_) => 300,
+            Self::SpendError(_) => 301,
+            Self::WitnessTypeMismatch(_) => 302,
+            Self::SignatureError(_) => 303,
+            Self::TestError(_) => 400,
+            Self::IoError(_) => 500,
+            Self::JsonError(_) => 501,
+            Self::ConfigError(_) => 502,
+            Self::ParseError(_) => 503,
+            Self::FileFormatError(_) => 504,
+            Self::InvalidUtxoRef(_) => 505,
+            Self::Context { source, .. } => source.code(),
+        }
+    }
+
+    /// Class of failure the binary exits with on this error
+    #[must_use]
+    pub fn exit_code(&self) -> ExitCode {
+        match self {
+            Self::Context { source, .. } => source.exit_code(),
+            Self::ProgramError(musk::ProgramError::IoError(_)) => ExitCode::Failure,
+            Self::ProgramError(_) => ExitCode::CompileError,
+            Self::ConfigError(_) | Self::FileFormatError(_) => ExitCode::ConfigError,
+            Self::DaemonError(_)
+            | Self::DaemonStartupTimeout(_)
+            | Self::IncompatibleNode(_)
+            | Self::NodeUnavailable(_) => ExitCode::NodeUnavailable,
+            Self::MempoolReject(_) => ExitCode::BroadcastRejected,
+            _ => ExitCode::Failure,
+        }
+    }
+
+    /// The error's details, without the kind's prefix, after any context
+    #[must_use]
+    pub fn detail(&self) -> String {
+        match self {
+            Self::EnvironmentError(s)
+            | Self::DaemonError(s)
+            | Self::TestError(s)
+            | Self::RpcError(s)
+            | Self::ConfigError(s)
+            | Self::ParseError(s)
+            | Self::FileFormatError(s)
+            | Self::InvalidUtxoRef(s)
+            | Self::IncompatibleNode(s)
+            | Self::OutpointLocked(s)
+            | Self::SignatureError(s)
+            | Self::NodeUnavailable(s)
+            | Self::MempoolReject(s)
+            | Self::InsufficientFunds(s)
+            | Self::WitnessTypeMismatch(s)
+            | Self::DaemonStartupTimeout(s) => s.clone(),
+            Self::ProgramError(e) => e.to_string(),
+            Self::SpendError(e) => e.to_string(),
+            Self::IoError(e) => e.to_string(),
+            Self::JsonError(e) => e.to_string(),
+            Self::Context { context, source } => format!("{context}: {}", source.detail()),
+        }
+    }
+}
+
+/// Attach context to the error of a `Result`
+pub trait ResultExt<T> {
+    /// Wrap the error in `context`, see [`SprayError::context`]
+    ///
+    /// # Errors
+    ///
+    /// Returns the wrapped error, if any.
+    fn context(self, context: impl Display) -> Result<T, SprayError>;
+
+    /// Wrap the error in the context returned by `f`, which is only called
+    /// on error
+    ///
+    /// # Errors
+    ///
+    /// Returns the wrapped error, if any.
+    fn with_context<C: Display>(self, f: impl FnOnce() -> C) -> Result<T, SprayError>;
+}
+
+impl<T, E: Into<SprayError>> ResultExt<T> for Result<T, E> {
+    fn context(self, context: impl Display) -> Result<T, SprayError> {
+        self.map_err(|e| e.into().context(context))
+    }
+
+    fn with_context<C: Display>(self, f: impl FnOnce() -> C) -> Result<T, SprayError> {
+        self.map_err(|e| e.into().context(f()))
+    }
 }
@@ -0,0 +1,246 @@
+//! Completion-time estimates for suite runs
+//!
+//! A suite that usually takes ten minutes and is still running after twenty
+//! points at its environment: a slow daemon or a saturated testnet. Given the
+//! durations tests took in earlier reports (written with `--report` or
+//! `--report-dir`), [`Eta`] estimates how long a running suite has left and
+//! flags tests that take much longer than they used to.
+//!
+//! Tests are matched to their history by name. A test's expected duration is
+//! the median of its passing runs; tests without history are expected to
+//! take as long as the tests finished so far in the run did on average.
+//!
+//! # Example
+//!
+//! ```
+//! use spray::eta::{DurationHistory, Eta};
+//! use spray::report::{SuiteReport, TestRecord};
+//!
+//! let passed = TestRecord {
+//!     success: true,
+//!     error: None,
+//!     ..TestRecord::failure("spend", 1_000, String::new())
+//! };
+//! let mut history = DurationHistory::new();
+//! history.add(&SuiteReport::new(vec![passed], 1));
+//! assert_eq!(history.expected_ms("spend"), Some(1_000));
+//!
+//! let mut eta = Eta::new(&history, ["spend", "new"], 2.0);
+//! assert_eq!(eta.remaining_ms(), 2_000);
+//!
+//! let slow = eta.finish(0, 3_000).unwrap();
+//! assert_eq!(slow.to_string(), "spend took 3s, 3.0x its usual 1s");
+//! assert_eq!(eta.remaining_ms(), 3_000);
+//! ```
+
+use crate::error::SprayError;
+use crate::report::{format_ms, SuiteReport};
+use colored::Colorize;
+use std::collections::HashMap;
+use std::fmt;
+use std::path::PathBuf;
+
+/// How many times its usual duration a test may take before it is flagged
+pub const DEFAULT_SLOW_FACTOR: f64 = 2.0;
+
+/// Durations of earlier passing runs, by test name
+#[derive(Debug, Clone, Default)]
+pub struct DurationHistory {
+    durations: HashMap<String, Vec<u64>>,
+}
+
+impl DurationHistory {
+    /// An empty history
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Load suite reports from `paths`, each a JSON report file or a
+    /// directory of them
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a path cannot be read or a file is not a suite
+    /// report.
+    pub fn load(paths: &[PathBuf]) -> Result<Self, SprayError> {
+        let mut history = Self::new();
+        for path in paths {
+            let files = if path.is_dir() {
+                let mut files: Vec<PathBuf> = std::fs::read_dir(path)?
+                    .map(|entry| entry.map(|e| e.path()))
+                    .collect::<Result<_, _>>()?;
+                files.retain(|file| file.extension().is_some_and(|ext| ext == "json"));
+                files.sort();
+                files
+            } else {
+                vec![path.clone()]
+            };
+            for file in files {
+                let report: SuiteReport = serde_json::from_str(&std::fs::read_to_string(&file)?)
+                    .map_err(|e| {
+                        SprayError::FileFormatError(format!(
+                            "{} is not a suite report: {e}",
+                            file.display()
+                        ))
+                    })?;
+                history.add(&report);
+            }
+        }
+        Ok(history)
+    }
+
+    /// Add the durations of the passed tests in `report`
+    ///
+    /// Failed tests are left out, as they often stop early.
+    pub fn add(&mut self, report: &SuiteReport) {
+        for record in report.tests.iter().filter(|record| record.success) {
+            self.durations
+                .entry(record.name.clone())
+                .or_default()
+                .push(record.duration_ms);
+        }
+    }
+
+    /// Whether no durations are known
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.durations.is_empty()
+    }
+
+    /// Median duration of the test named `name`, if it has history
+    #[must_use]
+    pub fn expected_ms(&self, name: &str) -> Option<u64> {
+        let mut durations = self.durations.get(name)?.clone();
+        durations.sort_unstable();
+        durations.get(durations.len() / 2).copied()
+    }
+}
+
+/// A test that took longer than its history allows
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SlowTest {
+    /// Test name
+    pub name: String,
+    /// How long it took in milliseconds
+    pub duration_ms: u64,
+    /// How long it usually takes in milliseconds
+    pub expected_ms: u64,
+}
+
+impl fmt::Display for SlowTest {
+    #[allow(clippy::cast_precision_loss)]
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} took {}, {:.1}x its usual {}",
+            self.name,
+            format_ms(self.duration_ms),
+            self.duration_ms as f64 / self.expected_ms.max(1) as f64,
+            format_ms(self.expected_ms)
+        )
+    }
+}
+
+/// Remaining-time estimate for a run, updated as tests finish
+#[derive(Debug, Clone)]
+pub struct Eta {
+    names: Vec<String>,
+    expected: Vec<Option<u64>>,
+    durations: Vec<Option<u64>>,
+    slow_factor: f64,
+    slow: Vec<SlowTest>,
+}
+
+impl Eta {
+    /// An estimate for running `tests`, given by name in run order, flagging
+    /// tests that take more than `slow_factor` times their usual duration
+    pub fn new<'a>(
+        history: &DurationHistory,
+        tests: impl IntoIterator<Item = &'a str>,
+        slow_factor: f64,
+    ) -> Self {
+        let names: Vec<String> = tests.into_iter().map(str::to_string).collect();
+        let expected = names.iter().map(|name| history.expected_ms(name)).collect();
+        Self {
+            durations: vec![None; names.len()],
+            names,
+            expected,
+            slow_factor,
+            slow: Vec::new(),
+        }
+    }
+
+    /// Number of tests in the run
+    #[must_use]
+    pub fn total(&self) -> usize {
+        self.names.len()
+    }
+
+    /// Number of tests that have finished
+    #[must_use]
+    pub fn finished(&self) -> usize {
+        self.durations.iter().flatten().count()
+    }
+
+    /// Record that the `index`th test took `duration_ms`, returning it if it
+    /// was slower than usual
+    pub fn finish(&mut self, index: usize, duration_ms: u64) -> Option<SlowTest> {
+        *self.durations.get_mut(index)? = Some(duration_ms);
+        let expected_ms = self.expected[index]?;
+        #[allow(clippy::cast_precision_loss)]
+        let slow = duration_ms as f64 > expected_ms as f64 * self.slow_factor;
+        if !slow {
+            return None;
+        }
+        let slow = SlowTest {
+            name: self.names[index].clone(),
+            duration_ms,
+            expected_ms,
+        };
+        self.slow.push(slow.clone());
+        Some(slow)
+    }
+
+    /// Estimated milliseconds until every test has finished
+    ///
+    /// Tests without history count with the average duration of the tests
+    /// finished so far, or of the expected durations before any has.
+    #[must_use]
+    pub fn remaining_ms(&self) -> u64 {
+        let finished: Vec<u64> = self.durations.iter().flatten().copied().collect();
+        let known: Vec<u64> = self.expected.iter().flatten().copied().collect();
+        let average = [&finished, &known]
+            .into_iter()
+            .find(|durations| !durations.is_empty())
+            .map_or(0, |durations| {
+                durations.iter().sum::<u64>() / durations.len() as u64
+            });
+
+        self.durations
+            .iter()
+            .zip(&self.expected)
+            .filter(|(duration, _)| duration.is_none())
+            .map(|(_, expected)| expected.unwrap_or(average))
+            .sum()
+    }
+
+    /// Tests flagged as slower than usual so far
+    #[must_use]
+    pub fn slow(&self) -> &[SlowTest] {
+        &self.slow
+    }
+
+    /// Print the tests flagged as slower than usual, if any
+    pub fn print_slow(&self) {
+        if self.slow.is_empty() {
+            return;
+        }
+
+        crate::status!();
+        crate::status!("{}", "Slower than usual:".bold());
+        for slow in &self.slow {
+            crate::status!("  {} {slow}", "⚠".yellow());
+        }
+    }
+}
@@ -0,0 +1,295 @@
+//! Contract event extraction
+//!
+//! Covenant contracts can make their state transitions observable by
+//! committing "event" data in an `OP_RETURN` output of the transactions that
+//! fund or spend them. By convention, the pushed data of such an output
+//! starts with a tag identifying the event, followed by the event's fields,
+//! each a SimplicityHL value padded to whole bytes.
+//!
+//! An [`EventSchema`] names the tags and field types, so that events can be
+//! decoded into JSON. [`scan`] walks the chain, tracks the contract's outputs
+//! and decodes the events of every transaction touching the contract.
+//!
+//! # Example
+//!
+//! ```
+//! use serde_json::json;
+//! use spray::events::EventSchema;
+//!
+//! let schema: EventSchema = serde_json::from_value(json!({
+//!     "events": [{
+//!         "name": "Deposit",
+//!         "tag": "0x01",
+//!         "fields": [
+//!             { "name": "amount", "type": "u32" },
+//!             { "name": "final", "type": "bool" }
+//!         ]
+//!     }]
+//! }))
+//! .unwrap();
+//!
+//! let (name, fields) = schema.decode(&[0x01, 0, 0, 0x01, 0x00, 0x80]).unwrap().unwrap();
+//! assert_eq!(name, "Deposit");
+//! assert_eq!(fields["amount"], json!(256));
+//! assert_eq!(fields["final"], json!(true));
+//!
+//! assert!(schema.decode(&[0x02]).is_none());
+//! ```
+
+use crate::error::SprayError;
+use crate::file_loader;
+use crate::hex;
+use crate::network::NetworkBackend;
+use crate::value::ValueType;
+use musk::elements::hex::FromHex;
+use musk::elements::script::Instruction;
+use musk::elements::Script;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::collections::HashSet;
+use std::path::Path;
+
+/// Events a contract can emit
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct EventSchema {
+    /// Event definitions, matched by tag in order
+    #[serde(default)]
+    pub events: Vec<EventSpec>,
+}
+
+/// A single event type
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EventSpec {
+    /// Event name, e.g. `Deposit`
+    pub name: String,
+    /// Hex prefix of the pushed data identifying the event
+    pub tag: String,
+    /// Fields following the tag, in order
+    #[serde(default)]
+    pub fields: Vec<FieldSpec>,
+}
+
+/// A field of an event
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FieldSpec {
+    /// Field name
+    pub name: String,
+    /// SimplicityHL type of the field, e.g. `u64` or `[u8; 32]`
+    #[serde(rename = "type")]
+    pub ty: String,
+}
+
+/// A decoded event
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Event {
+    /// Transaction carrying the event
+    pub txid: String,
+    /// Index of the `OP_RETURN` output
+    pub vout: u32,
+    /// Height of the block containing the transaction
+    pub height: u64,
+    /// Event name from the schema
+    pub event: String,
+    /// Decoded field values
+    pub fields: Map<String, Value>,
+}
+
+impl EventSchema {
+    /// Load a schema from a JSON or TOML file
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be read or is not a valid schema.
+    pub fn load(path: &Path) -> Result<Self, SprayError> {
+        let value = file_loader::read_value(path)?;
+        let schema: Self = serde_json::from_value(value)
+            .map_err(|e| SprayError::ParseError(format!("Invalid event schema: {e}")))?;
+        for spec in &schema.events {
+            spec.tag_bytes()?;
+            for field in &spec.fields {
+                field.ty.parse::<ValueType>()?;
+            }
+        }
+        Ok(schema)
+    }
+
+    /// Decode the pushed data of an `OP_RETURN` output
+    ///
+    /// Returns `None` if no event's tag matches. Fields are rendered as JSON
+    /// numbers, booleans or arrays where possible, and as strings otherwise.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the tag matches but the fields cannot be decoded.
+    pub fn decode(
+        &self,
+        payload: &[u8],
+    ) -> Option<Result<(String, Map<String, Value>), SprayError>> {
+        self.events.iter().find_map(|spec| {
+            let tag = spec.tag_bytes().ok()?;
+            let data = payload.strip_prefix(tag.as_slice())?;
+            Some(
+                spec.decode_fields(data)
+                    .map(|fields| (spec.name.clone(), fields)),
+            )
+        })
+    }
+}
+
+impl EventSpec {
+    /// The tag as bytes
+    fn tag_bytes(&self) -> Result<Vec<u8>, SprayError> {
+        Vec::<u8>::from_hex(self.tag.trim_start_matches("0x")).map_err(|e| {
+            SprayError::ParseError(format!("Invalid tag for event '{}': {e}", self.name))
+        })
+    }
+
+    /// Decode the fields following the tag
+    fn decode_fields(&self, mut data: &[u8]) -> Result<Map<String, Value>, SprayError> {
+        let mut fields = Map::new();
+        for field in &self.fields {
+            let ty: ValueType = field.ty.parse()?;
+            let len = ty.bit_width().div_ceil(8);
+            if data.len() < len {
+                return Err(SprayError::ParseError(format!(
+                    "Event '{}' too short for field '{}'",
+                    self.name, field.name
+                )));
+            }
+            let (bytes, rest) = data.split_at(len);
+            data = rest;
+
+            let rendered = if len == 0 {
+                ty.format_hex("00")?
+            } else {
+                ty.format_hex(&hex::encode(bytes))?
+            };
+            let value = serde_json::from_str(&rendered).unwrap_or(Value::String(rendered));
+            fields.insert(field.name.clone(), value);
+        }
+        Ok(fields)
+    }
+}
+
+/// Concatenated pushed data of an `OP_RETURN` script, if it is one
+#[must_use]
+pub fn op_return_payload(script: &Script) -> Option<Vec<u8>> {
+    if !script.is_op_return() {
+        return None;
+    }
+    let mut payload = Vec::new();
+    for instruction in script.instructions().skip(1) {
+        if let Ok(Instruction::PushBytes(bytes)) = instruction {
+            payload.extend_from_slice(bytes);
+        }
+    }
+    Some(payload)
+}
+
+/// Decode the events of a block as returned by `getblock <hash> 2`
+///
+/// A transaction touches the contract if it pays to `script_pubkey` or spends
+/// one of `outpoints`; outputs paying to the contract are added to
+/// `outpoints` so that later spends of them are recognized.
+///
+/// # Errors
+///
+/// Returns an error if the block is malformed or an event cannot be decoded.
+pub fn block_events(
+    block: &Value,
+    height: u64,
+    script_pubkey: &Script,
+    outpoints: &mut HashSet<(String, u32)>,
+    schema: &EventSchema,
+) -> Result<Vec<Event>, SprayError> {
+    let malformed = || SprayError::RpcError("Invalid getblock response".into());
+    let contract_hex = hex::encode(script_pubkey.as_bytes());
+
+    let mut events = Vec::new();
+    for tx in block["tx"].as_array().ok_or_else(malformed)? {
+        let txid = tx["txid"].as_str().ok_or_else(malformed)?;
+        let outputs = tx["vout"].as_array().ok_or_else(malformed)?;
+
+        // Count rather than `any`, so that every spent outpoint is removed
+        let spent = tx["vin"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter(
+                |input| match (input["txid"].as_str(), input["vout"].as_u64()) {
+                    (Some(prev), Some(vout)) => u32::try_from(vout)
+                        .is_ok_and(|vout| outpoints.remove(&(prev.to_string(), vout))),
+                    _ => false,
+                },
+            )
+            .count();
+        let spends_contract = spent > 0;
+
+        let mut pays_contract = false;
+        for (n, output) in (0u32..).zip(outputs) {
+            if output["scriptPubKey"]["hex"].as_str() == Some(contract_hex.as_str()) {
+                outpoints.insert((txid.to_string(), n));
+                pays_contract = true;
+            }
+        }
+
+        if !spends_contract && !pays_contract {
+            continue;
+        }
+
+        for (n, output) in (0u32..).zip(outputs) {
+            let Some(hex) = output["scriptPubKey"]["hex"].as_str() else {
+                continue;
+            };
+            let script = Script::from(Vec::<u8>::from_hex(hex).map_err(|_| malformed())?);
+            let Some(payload) = op_return_payload(&script) else {
+                continue;
+            };
+            if let Some(decoded) = schema.decode(&payload) {
+                let (event, fields) = decoded?;
+                events.push(Event {
+                    txid: txid.to_string(),
+                    vout: n,
+                    height,
+                    event,
+                    fields,
+                });
+            }
+        }
+    }
+    Ok(events)
+}
+
+/// Scan blocks from `start` to the chain tip for events of the contract
+/// locked by `script_pubkey`
+///
+/// `progress` is called after each block with its height and the tip height.
+///
+/// # Errors
+///
+/// Returns an error if a block cannot be fetched or an event cannot be
+/// decoded.
+pub fn scan(
+    backend: &NetworkBackend,
+    script_pubkey: &Script,
+    schema: &EventSchema,
+    start: u64,
+    mut progress: impl FnMut(u64, u64),
+) -> Result<Vec<Event>, SprayError> {
+    let tip = backend.block_count()?;
+    let mut outpoints = HashSet::new();
+    let mut events = Vec::new();
+    for height in start..=tip {
+        let hash = backend.call("getblockhash", &[height.into()])?;
+        let block = backend.call("getblock", &[hash, 2.into()])?;
+        events.extend(block_events(
+            &block,
+            height,
+            script_pubkey,
+            &mut outpoints,
+            schema,
+        )?);
+        progress(height, tip);
+    }
+    Ok(events)
+}
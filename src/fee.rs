@@ -0,0 +1,137 @@
+//! Fee policies for spending transactions
+//!
+//! This module provides the [`FeePolicy`] type, which decides the fee of a
+//! spend either as a fixed amount or from a fee rate applied to the size of
+//! the finalized, witness-included transaction.
+//!
+//! # Example
+//!
+//! ```
+//! use spray::fee::{fee_for_vsize, FeePolicy};
+//!
+//! assert_eq!(FeePolicy::default(), FeePolicy::Fixed(3_000));
+//! assert_eq!(fee_for_vsize(0.1, 1_234), 124);
+//! assert!(spray::fee::parse_feerate("NaN").is_err());
+//! ```
+
+use crate::error::SprayError;
+use musk::elements::Transaction;
+use serde_json::Value;
+
+/// Fee used when no policy is given, in satoshis
+pub const DEFAULT_FEE: u64 = 3_000;
+
+/// Fee rate used when the node has no estimate, in sat/vB
+///
+/// This is the default minimum relay fee rate on Liquid.
+pub const FALLBACK_FEERATE: f64 = 0.1;
+
+/// How the fee of a spending transaction is determined
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FeePolicy {
+    /// A fixed fee in satoshis
+    Fixed(u64),
+    /// A fee rate in sat/vB
+    Rate(f64),
+    /// The node's `estimatesmartfee` rate for a confirmation target in blocks,
+    /// falling back to [`FALLBACK_FEERATE`]
+    Estimate {
+        /// Confirmation target in blocks
+        conf_target: u16,
+    },
+}
+
+impl Default for FeePolicy {
+    fn default() -> Self {
+        Self::Fixed(DEFAULT_FEE)
+    }
+}
+
+impl FeePolicy {
+    /// Fee rate in sat/vB, or `None` for a fixed fee
+    ///
+    /// `estimate` is called with the confirmation target to query the node.
+    pub fn feerate(&self, estimate: impl FnOnce(u16) -> Option<f64>) -> Option<f64> {
+        match *self {
+            Self::Fixed(_) => None,
+            Self::Rate(rate) => Some(rate),
+            Self::Estimate { conf_target } => {
+                Some(estimate(conf_target).unwrap_or(FALLBACK_FEERATE))
+            }
+        }
+    }
+
+    /// Confirmation target of an estimated policy, in blocks
+    #[must_use]
+    pub const fn conf_target(&self) -> Option<u16> {
+        match *self {
+            Self::Estimate { conf_target } => Some(conf_target),
+            Self::Fixed(_) | Self::Rate(_) => None,
+        }
+    }
+
+    /// Compute the fee of a transaction built by `build`
+    ///
+    /// For rate-based policies the transaction is built once with
+    /// [`DEFAULT_FEE`] to measure its finalized size; the fee amount itself
+    /// does not affect the size.
+    ///
+    /// # Errors
+    ///
+    /// Returns the error of `build`.
+    pub fn compute<E>(
+        &self,
+        estimate: impl FnOnce(u16) -> Option<f64>,
+        build: impl FnOnce(u64) -> Result<Transaction, E>,
+    ) -> Result<u64, E> {
+        match (self, self.feerate(estimate)) {
+            (Self::Fixed(fee), _) => Ok(*fee),
+            (_, Some(rate)) => {
+                let tx = build(DEFAULT_FEE)?;
+                Ok(fee_for_vsize(rate, tx.weight().div_ceil(4)))
+            }
+            (_, None) => Ok(DEFAULT_FEE),
+        }
+    }
+}
+
+/// Fee for a transaction of `vsize` virtual bytes at `rate` sat/vB, rounded up
+#[must_use]
+#[allow(
+    clippy::cast_possible_truncation,
+    clippy::cast_precision_loss,
+    clippy::cast_sign_loss
+)]
+pub fn fee_for_vsize(rate: f64, vsize: usize) -> u64 {
+    (rate * vsize as f64).ceil() as u64
+}
+
+/// Parse a fee rate in sat/vB, e.g. the value of `--feerate`
+///
+/// # Errors
+///
+/// Returns an error if `s` is not a number, or is not finite and positive.
+pub fn parse_feerate(s: &str) -> Result<f64, SprayError> {
+    let rate: f64 = s
+        .trim()
+        .parse()
+        .map_err(|_| SprayError::ParseError(format!("Invalid fee rate: {s}")))?;
+    if !rate.is_finite() || rate <= 0.0 {
+        return Err(SprayError::ParseError(format!(
+            "Fee rate must be a positive number of sat/vB, got {s}"
+        )));
+    }
+    Ok(rate)
+}
+
+/// Extract the fee rate in sat/vB from an `estimatesmartfee` response
+///
+/// Returns `None` when the node has no estimate (e.g. on a fresh regtest
+/// chain), which it reports through an `errors` array instead of `feerate`.
+#[must_use]
+pub fn parse_estimatesmartfee(response: &Value) -> Option<f64> {
+    // BTC/kvB -> sat/vB
+    response["feerate"]
+        .as_f64()
+        .map(|btc_per_kvb| btc_per_kvb * 100_000_000.0 / 1_000.0)
+}
@@ -9,11 +9,47 @@
 //! - `.toml` - TOML format
 //!
 //! The format is automatically detected based on file extension.
+//!
+//! Individual values can be overridden with `KEY=VALUE` pairs, e.g. from
+//! `--arg` and `--wit` on the command line; overrides take precedence over
+//! the file.
 
 use crate::error::SprayError;
+use crate::value::ValueType;
+use crate::witness::SigningWitness;
 use musk::{Arguments, WitnessValues};
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+use std::collections::HashMap;
 use std::path::Path;
 
+/// Parse a `KEY=VALUE` override
+///
+/// # Errors
+///
+/// Returns an error if there is no `=` or the key is empty.
+///
+/// # Example
+///
+/// ```
+/// use spray::file_loader::parse_override;
+///
+/// let (key, value) = parse_override("ALICE_PUBLIC_KEY=0xabc").unwrap();
+/// assert_eq!(key, "ALICE_PUBLIC_KEY");
+/// assert_eq!(value, "0xabc");
+/// assert!(parse_override("ALICE_PUBLIC_KEY").is_err());
+/// ```
+pub fn parse_override(s: &str) -> Result<(String, String), SprayError> {
+    match s.split_once('=') {
+        Some((key, value)) if !key.trim().is_empty() => {
+            Ok((key.trim().to_string(), value.trim().to_string()))
+        }
+        _ => Err(SprayError::ParseError(format!(
+            "Expected KEY=VALUE, got '{s}'"
+        ))),
+    }
+}
+
 /// Load arguments from a JSON or TOML file
 ///
 /// Format is detected by file extension:
@@ -98,6 +134,177 @@ pub fn load_witness(path: &Path) -> Result<WitnessValues, SprayError> {
     }
 }
 
+/// Load arguments from an optional file, with `overrides` merged over it
+///
+/// # Errors
+///
+/// Returns an error if the file cannot be loaded or the merged values are
+/// not valid arguments.
+pub fn load_arguments_with(
+    path: Option<&Path>,
+    overrides: &[(String, String)],
+) -> Result<Arguments, SprayError> {
+    if overrides.is_empty() {
+        return path.map_or_else(|| Ok(Arguments::default()), load_arguments);
+    }
+    let overrides: Vec<(String, Value)> = overrides
+        .iter()
+        .map(|(key, raw)| (key.clone(), override_value(raw)))
+        .collect();
+    load_merged(path, "params", &overrides)
+}
+
+/// The argument table of an optional file with `overrides` merged over it
+///
+/// This is what [`load_arguments_with`] deserializes, e.g. for use as a cache
+/// key.
+///
+/// # Errors
+///
+/// Returns an error if the file cannot be loaded.
+pub fn arguments_value(
+    path: Option<&Path>,
+    overrides: &[(String, String)],
+) -> Result<Value, SprayError> {
+    let overrides: Vec<(String, Value)> = overrides
+        .iter()
+        .map(|(key, raw)| (key.clone(), override_value(raw)))
+        .collect();
+    load_merged(path, "params", &overrides)
+}
+
+/// Load witness values from an optional file, with `overrides` merged over it
+///
+/// `witness_types` maps witness names to their declared SimplicityHL types
+/// (see [`CompiledOutput::witness_types`]); each override is parsed for its
+/// witness's type, so e.g. a decimal `u256` becomes zero-padded hex. If no
+/// types are known, overrides are passed through as for arguments.
+///
+/// [`CompiledOutput::witness_types`]: crate::compiled::CompiledOutput::witness_types
+///
+/// # Errors
+///
+/// Returns an error if the file cannot be loaded, an override names an
+/// undeclared witness or is invalid for its type.
+pub fn load_witness_with(
+    path: Option<&Path>,
+    overrides: &[(String, String)],
+    witness_types: &HashMap<String, String>,
+) -> Result<WitnessValues, SprayError> {
+    if overrides.is_empty() {
+        return path.map_or_else(|| Ok(WitnessValues::default()), load_witness);
+    }
+    load_merged(
+        path,
+        "witness",
+        &witness_overrides(overrides, witness_types)?,
+    )
+}
+
+/// Load witness values as [`load_witness_with`] does, signing the entries
+/// that ask for it per spend (see [`SigningWitness`])
+///
+/// # Errors
+///
+/// Returns the errors of [`load_witness_with`], or an error if an entry
+/// asks to be signed with an invalid key.
+pub fn load_signing_witness(
+    path: Option<&Path>,
+    overrides: &[(String, String)],
+    witness_types: &HashMap<String, String>,
+) -> Result<SigningWitness, SprayError> {
+    let value: Value = load_merged(
+        path,
+        "witness",
+        &witness_overrides(overrides, witness_types)?,
+    )?;
+    SigningWitness::new(value)
+}
+
+/// Parse witness overrides for their witnesses' types
+fn witness_overrides(
+    overrides: &[(String, String)],
+    witness_types: &HashMap<String, String>,
+) -> Result<Vec<(String, Value)>, SprayError> {
+    overrides
+        .iter()
+        .map(|(key, raw)| {
+            if witness_types.is_empty() {
+                return Ok((key.clone(), override_value(raw)));
+            }
+            let ty: ValueType = witness_types
+                .get(key)
+                .ok_or_else(|| {
+                    let mut declared: Vec<&str> =
+                        witness_types.keys().map(String::as_str).collect();
+                    declared.sort_unstable();
+                    SprayError::ParseError(format!(
+                        "Unknown witness '{key}' (declared: {})",
+                        declared.join(", ")
+                    ))
+                })?
+                .parse()?;
+            let value = ty.parse_literal(raw).map_err(|e| {
+                SprayError::WitnessTypeMismatch(format!("witness '{key}': {}", e.detail()))
+            })?;
+            Ok((key.clone(), value))
+        })
+        .collect()
+}
+
+/// Load a file as a JSON value and deserialize it with `overrides` applied
+///
+/// Overrides go into the `section` table if the file has one (as TOML files
+/// do, e.g. `[params]`), and at the top level otherwise.
+fn load_merged<T: DeserializeOwned>(
+    path: Option<&Path>,
+    section: &str,
+    overrides: &[(String, Value)],
+) -> Result<T, SprayError> {
+    let mut value = match path {
+        Some(path) => read_value(path)?,
+        None => Value::Object(serde_json::Map::new()),
+    };
+
+    let target = if value.get(section).is_some_and(Value::is_object) {
+        &mut value[section]
+    } else {
+        &mut value
+    };
+    let map = target.as_object_mut().ok_or_else(|| {
+        SprayError::FileFormatError("Expected a table of name/value pairs".into())
+    })?;
+    for (key, value) in overrides {
+        map.insert(key.clone(), value.clone());
+    }
+
+    Ok(serde_json::from_value(value)?)
+}
+
+/// Read a JSON or TOML file into a JSON value
+pub(crate) fn read_value(path: &Path) -> Result<Value, SprayError> {
+    let contents = std::fs::read_to_string(path)?;
+    let ext = path
+        .extension()
+        .and_then(|e| e.to_str())
+        .ok_or_else(|| SprayError::FileFormatError("No file extension found".into()))?;
+
+    match ext {
+        "json" => serde_json::from_str(&contents).map_err(Into::into),
+        "toml" => toml::from_str(&contents)
+            .map_err(|e| SprayError::ParseError(format!("TOML parse error: {e}"))),
+        _ => Err(SprayError::FileFormatError(format!(
+            "Unsupported file extension: {ext}"
+        ))),
+    }
+}
+
+/// Interpret an override as a number if it is decimal, as a string otherwise
+fn override_value(raw: &str) -> Value {
+    raw.parse::<u64>()
+        .map_or_else(|_| Value::String(raw.to_string()), Value::from)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -109,4 +316,27 @@ mod tests {
         assert!(load_arguments(Path::new("test.txt")).is_err()); // Unsupported extension
         assert!(load_arguments(Path::new("test")).is_err()); // No extension
     }
+
+    #[test]
+    fn test_override_value() {
+        assert_eq!(override_value("42"), Value::from(42));
+        assert_eq!(override_value("0x2a"), Value::from("0x2a"));
+        assert_eq!(override_value("-1"), Value::from("-1"));
+    }
+
+    #[test]
+    fn test_overrides_merge_into_section() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("args.toml");
+        std::fs::write(&path, "[params]\nA = \"0x01\"\nB = \"0x02\"\n").unwrap();
+
+        let merged: Value =
+            load_merged(Some(&path), "params", &[("B".into(), Value::from("0x03"))]).unwrap();
+        assert_eq!(merged["params"]["A"], "0x01");
+        assert_eq!(merged["params"]["B"], "0x03");
+        assert!(merged.get("B").is_none());
+
+        let flat: Value = load_merged(None, "params", &[("C".into(), Value::from(7))]).unwrap();
+        assert_eq!(flat["C"], 7);
+    }
 }
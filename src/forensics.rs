@@ -0,0 +1,273 @@
+//! Forensic analysis of past spends
+//!
+//! To find out who authorized a contract spend that is already on chain,
+//! [`SpendAnalysis`] recomputes the `sig_all_hash` the spend's signatures
+//! were made over, from the transaction itself, the outputs it spent and the
+//! chain's genesis hash, and checks every signature in the spend's witness
+//! against a set of declared keys.
+//!
+//! Signatures are found by decoding the pruned program and witness from the
+//! input's witness stack: every witness value of 64 bytes is treated as a
+//! candidate BIP 340 signature. Witness values carry no names once encoded,
+//! so candidates are identified by their node index, as in
+//! `spray inspect --disasm`.
+//!
+//! # Example
+//!
+//! ```
+//! use spray::forensics::parse_key;
+//!
+//! let pubkey = "79be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798";
+//! let (name, _) = parse_key(&format!("alice={pubkey}")).unwrap();
+//! assert_eq!(name, "alice");
+//!
+//! let (name, _) = parse_key(pubkey).unwrap();
+//! assert_eq!(name, pubkey);
+//! assert!(parse_key("alice=00").is_err());
+//! ```
+
+use crate::error::SprayError;
+use crate::hex;
+use musk::elements::hashes::Hash;
+use musk::elements::secp256k1_zkp::{schnorr, Message, Secp256k1, XOnlyPublicKey};
+use musk::elements::taproot::ControlBlock;
+use musk::elements::{BlockHash, Transaction, TxOut};
+use serde::Serialize;
+use simplicity::dag::{DagLike, InternalSharing};
+use simplicity::jet::elements::{ElementsEnv, ElementsUtxo};
+use simplicity::jet::Elements;
+use simplicity::node::Inner;
+use simplicity::{BitIter, Cmr, RedeemNode};
+use std::str::FromStr;
+use std::sync::Arc;
+
+/// Length of a BIP 340 signature in bytes
+const SIGNATURE_LEN: usize = 64;
+
+/// First byte of a taproot annex
+const ANNEX_TAG: u8 = 0x50;
+
+/// A candidate signature found in a spend's witness
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct SignatureMatch {
+    /// Index of the witness node holding the signature
+    pub node: usize,
+    /// The signature (hex)
+    pub signature: String,
+    /// Name of the declared key it verifies against, if any
+    pub signer: Option<String>,
+}
+
+/// The recomputed sighash of a past spend, and who signed it
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct SpendAnalysis {
+    /// Spending transaction
+    pub txid: String,
+    /// Index of the contract input
+    pub input_index: u32,
+    /// CMR of the program spent
+    pub cmr: String,
+    /// Genesis hash the sighash commits to
+    pub genesis_hash: String,
+    /// The recomputed `sig_all_hash` (hex)
+    pub sighash: String,
+    /// Candidate signatures in the witness
+    pub signatures: Vec<SignatureMatch>,
+}
+
+impl SpendAnalysis {
+    /// Analyze input `input_index` of `tx`
+    ///
+    /// `prevouts` must hold the spent output of every input, in input order,
+    /// and `keys` the declared keys with their names.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the number of prevouts does not match the inputs,
+    /// or the input is not a Simplicity spend.
+    pub fn new(
+        tx: &Transaction,
+        prevouts: &[TxOut],
+        input_index: u32,
+        genesis_hash: BlockHash,
+        keys: &[(String, XOnlyPublicKey)],
+    ) -> Result<Self, SprayError> {
+        if prevouts.len() != tx.input.len() {
+            return Err(SprayError::TestError(format!(
+                "Expected {} prevouts, got {}",
+                tx.input.len(),
+                prevouts.len()
+            )));
+        }
+        let spend = SimplicitySpend::parse(tx, input_index)?;
+
+        let utxos = prevouts
+            .iter()
+            .map(|prevout| ElementsUtxo {
+                script_pubkey: prevout.script_pubkey.clone(),
+                asset: prevout.asset,
+                value: prevout.value,
+            })
+            .collect();
+        let env = ElementsEnv::new(
+            Arc::new(tx.clone()),
+            utxos,
+            input_index,
+            spend.cmr,
+            spend.control_block.clone(),
+            None,
+            genesis_hash,
+        );
+        let sighash = env.c_tx_env().sighash_all().to_byte_array();
+
+        let signatures = spend
+            .signatures()?
+            .into_iter()
+            .map(|(node, signature)| SignatureMatch {
+                node,
+                signature: signature.to_string(),
+                signer: identify_signer(sighash, &signature, keys).map(str::to_string),
+            })
+            .collect();
+
+        Ok(Self {
+            txid: tx.txid().to_string(),
+            input_index,
+            cmr: spend.cmr.to_string(),
+            genesis_hash: genesis_hash.to_string(),
+            sighash: hex::encode(&sighash),
+            signatures,
+        })
+    }
+
+    /// Names of the declared keys that signed, in witness order
+    pub fn signers(&self) -> impl Iterator<Item = &str> {
+        self.signatures
+            .iter()
+            .filter_map(|signature| signature.signer.as_deref())
+    }
+}
+
+/// The Simplicity parts of a taproot script-path witness
+struct SimplicitySpend<'tx> {
+    witness: &'tx [u8],
+    program: &'tx [u8],
+    cmr: Cmr,
+    control_block: ControlBlock,
+}
+
+impl<'tx> SimplicitySpend<'tx> {
+    /// Split the witness stack of input `input_index` of `tx`
+    fn parse(tx: &'tx Transaction, input_index: u32) -> Result<Self, SprayError> {
+        let input = tx.input.get(input_index as usize).ok_or_else(|| {
+            SprayError::InvalidUtxoRef(format!(
+                "Input {input_index} not found in transaction {}",
+                tx.txid()
+            ))
+        })?;
+        let not_simplicity = |why: &str| {
+            SprayError::ParseError(format!(
+                "Input {input_index} is not a Simplicity spend: {why}"
+            ))
+        };
+
+        let stack = &input.witness.script_witness;
+        if stack
+            .last()
+            .is_some_and(|last| last.first() == Some(&ANNEX_TAG))
+        {
+            return Err(not_simplicity("spends with an annex are not supported"));
+        }
+        let [witness, program, script, control_block] = stack.as_slice() else {
+            return Err(not_simplicity(&format!(
+                "expected 4 witness stack items, found {}",
+                stack.len()
+            )));
+        };
+        let cmr: [u8; 32] = script
+            .as_slice()
+            .try_into()
+            .map_err(|_| not_simplicity("leaf script is not a CMR"))?;
+        let control_block = ControlBlock::from_slice(control_block)
+            .map_err(|e| not_simplicity(&format!("invalid control block: {e}")))?;
+
+        Ok(Self {
+            witness,
+            program,
+            cmr: Cmr::from_byte_array(cmr),
+            control_block,
+        })
+    }
+
+    /// Witness values of signature length, by node index
+    fn signatures(&self) -> Result<Vec<(usize, schnorr::Signature)>, SprayError> {
+        let root = RedeemNode::<Elements>::decode(
+            BitIter::from(self.program.iter().copied()),
+            BitIter::from(self.witness.iter().copied()),
+        )
+        .map_err(|e| SprayError::ParseError(format!("Invalid Simplicity program: {e}")))?;
+
+        Ok(root
+            .post_order_iter::<InternalSharing>()
+            .filter_map(|item| {
+                let Inner::Witness(value) = item.node.inner() else {
+                    return None;
+                };
+                let bits: Vec<bool> = value.iter_compact().collect();
+                if bits.len() != SIGNATURE_LEN * 8 {
+                    return None;
+                }
+                let bytes: Vec<u8> = bits
+                    .chunks(8)
+                    .map(|byte| {
+                        byte.iter()
+                            .fold(0u8, |acc, &bit| (acc << 1) | u8::from(bit))
+                    })
+                    .collect();
+                schnorr::Signature::from_slice(&bytes)
+                    .ok()
+                    .map(|signature| (item.index, signature))
+            })
+            .collect())
+    }
+}
+
+/// Index of the first input of `tx` spending the program with `cmr`
+#[must_use]
+pub fn find_input(tx: &Transaction, cmr: Cmr) -> Option<u32> {
+    tx.input
+        .iter()
+        .position(|input| {
+            let stack = &input.witness.script_witness;
+            stack.len() >= 2 && stack[stack.len() - 2] == cmr.to_byte_array()
+        })
+        .and_then(|index| u32::try_from(index).ok())
+}
+
+/// Name of the first of `keys` that `signature` verifies against for
+/// `sighash`
+#[must_use]
+pub fn identify_signer<'k>(
+    sighash: [u8; 32],
+    signature: &schnorr::Signature,
+    keys: &'k [(String, XOnlyPublicKey)],
+) -> Option<&'k str> {
+    let secp = Secp256k1::verification_only();
+    let message = Message::from_digest(sighash);
+    keys.iter()
+        .find(|(_, pubkey)| secp.verify_schnorr(signature, &message, pubkey).is_ok())
+        .map(|(name, _)| name.as_str())
+}
+
+/// Parse a declared key, `NAME=PUBKEY` or a bare x-only public key (hex),
+/// which is then its own name
+///
+/// # Errors
+///
+/// Returns an error if the public key is invalid.
+pub fn parse_key(s: &str) -> Result<(String, XOnlyPublicKey), SprayError> {
+    let (name, pubkey) = s.split_once('=').unwrap_or((s, s));
+    let pubkey = XOnlyPublicKey::from_str(pubkey.trim())
+        .map_err(|e| SprayError::ParseError(format!("Invalid public key in '{s}': {e}")))?;
+    Ok((name.trim().to_string(), pubkey))
+}
@@ -0,0 +1,257 @@
+//! Controlled funding transaction shapes
+//!
+//! By default a contract is funded with `sendtoaddress`, which leaves the
+//! number and order of outputs to the wallet. Contracts that introspect their
+//! creating transaction need a known layout instead. A [`FundingShape`] lists
+//! the outputs of the funding transaction in order: the contract output,
+//! extra wallet outputs, `OP_RETURN` data outputs and payments to arbitrary
+//! addresses. The wallet's change output is always appended after them.
+//!
+//! # Example
+//!
+//! ```
+//! use spray::funding::FundingShape;
+//!
+//! let shape = FundingShape::new()
+//!     .op_return(b"hello".to_vec())
+//!     .contract()
+//!     .wallet(10_000);
+//!
+//! assert_eq!(shape.contract_index().unwrap(), 1);
+//! assert_eq!(shape.len(), 3);
+//! ```
+
+use crate::client::format_btc_amount;
+use crate::error::SprayError;
+use crate::hex;
+use musk::elements::{Address, Txid};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::str::FromStr;
+
+/// One output of a funding transaction
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum FundingOutput {
+    /// The contract output, holding the test amount
+    Contract,
+    /// An output to a fresh wallet address
+    Wallet {
+        /// Amount in satoshis
+        amount: u64,
+    },
+    /// An `OP_RETURN` output carrying `data` (hex)
+    OpReturn {
+        /// Payload (hex)
+        data: String,
+    },
+    /// An output to a fixed address
+    Address {
+        /// Destination address
+        address: String,
+        /// Amount in satoshis
+        amount: u64,
+    },
+}
+
+/// Ordered outputs of a funding transaction
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct FundingShape {
+    outputs: Vec<FundingOutput>,
+}
+
+impl FundingShape {
+    /// Create an empty shape
+    #[must_use]
+    pub const fn new() -> Self {
+        Self {
+            outputs: Vec::new(),
+        }
+    }
+
+    /// Append the contract output
+    #[must_use]
+    pub fn contract(mut self) -> Self {
+        self.outputs.push(FundingOutput::Contract);
+        self
+    }
+
+    /// Append an output to a fresh wallet address
+    #[must_use]
+    pub fn wallet(mut self, amount: u64) -> Self {
+        self.outputs.push(FundingOutput::Wallet { amount });
+        self
+    }
+
+    /// Append an `OP_RETURN` output carrying `data`
+    #[must_use]
+    pub fn op_return(mut self, data: Vec<u8>) -> Self {
+        self.outputs.push(FundingOutput::OpReturn {
+            data: hex::encode(&data),
+        });
+        self
+    }
+
+    /// Append an output paying `amount` to `address`
+    #[must_use]
+    pub fn address(mut self, address: &Address, amount: u64) -> Self {
+        self.outputs.push(FundingOutput::Address {
+            address: address.to_string(),
+            amount,
+        });
+        self
+    }
+
+    /// Outputs in order, excluding the wallet's change
+    #[must_use]
+    pub fn outputs(&self) -> &[FundingOutput] {
+        &self.outputs
+    }
+
+    /// Number of outputs, excluding the wallet's change
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.outputs.len()
+    }
+
+    /// Whether the shape has no outputs
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.outputs.is_empty()
+    }
+
+    /// Position of the contract output
+    ///
+    /// # Errors
+    ///
+    /// Returns an error unless the shape has exactly one contract output.
+    pub fn contract_index(&self) -> Result<u32, SprayError> {
+        let mut indices = self
+            .outputs
+            .iter()
+            .enumerate()
+            .filter(|(_, o)| **o == FundingOutput::Contract)
+            .map(|(i, _)| i);
+
+        match (indices.next(), indices.next()) {
+            #[allow(clippy::cast_possible_truncation)]
+            (Some(index), None) => Ok(index as u32),
+            (None, _) => Err(SprayError::ConfigError(
+                "Funding shape has no contract output".into(),
+            )),
+            (Some(_), Some(_)) => Err(SprayError::ConfigError(
+                "Funding shape has more than one contract output".into(),
+            )),
+        }
+    }
+
+    /// The `createrawtransaction` outputs array for this shape
+    ///
+    /// `new_address` is called for every wallet output.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the shape has no single contract output or
+    /// `new_address` fails.
+    pub fn raw_outputs(
+        &self,
+        contract: &Address,
+        amount: u64,
+        mut new_address: impl FnMut() -> Result<String, SprayError>,
+    ) -> Result<Value, SprayError> {
+        self.contract_index()?;
+
+        let outputs = self
+            .outputs
+            .iter()
+            .map(|output| {
+                Ok(match output {
+                    FundingOutput::Contract => {
+                        let address = contract.to_string();
+                        json!({ address: format_btc_amount(amount) })
+                    }
+                    FundingOutput::Wallet { amount } => {
+                        let address = new_address()?;
+                        json!({ address: format_btc_amount(*amount) })
+                    }
+                    FundingOutput::OpReturn { data } => json!({ "data": data }),
+                    FundingOutput::Address { address, amount } => {
+                        json!({ address: format_btc_amount(*amount) })
+                    }
+                })
+            })
+            .collect::<Result<Vec<Value>, SprayError>>()?;
+
+        Ok(Value::Array(outputs))
+    }
+
+    /// Build and sign a transaction of this shape funding `contract` with
+    /// `amount`, without broadcasting it
+    ///
+    /// `call` makes a wallet RPC call against the node. The wallet adds its
+    /// inputs, and its change output after the shape's outputs. Returns the
+    /// signed transaction (hex) and the contract output's index.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the shape is invalid or the wallet cannot fund or
+    /// sign the transaction.
+    pub fn build(
+        &self,
+        call: impl Fn(&str, &[Value]) -> Result<Value, SprayError>,
+        contract: &Address,
+        amount: u64,
+    ) -> Result<(String, u32), SprayError> {
+        let outputs = self.raw_outputs(contract, amount, || {
+            call("getnewaddress", &[])?
+                .as_str()
+                .map(str::to_string)
+                .ok_or_else(|| SprayError::RpcError("Invalid getnewaddress response".into()))
+        })?;
+
+        let raw = call("createrawtransaction", &[json!([]), outputs])?;
+        let funded = call(
+            "fundrawtransaction",
+            &[raw, json!({ "changePosition": self.len() })],
+        )?;
+        let blinded = call("blindrawtransaction", &[funded["hex"].clone()])?;
+        let signed = call("signrawtransactionwithwallet", &[blinded])?;
+        if signed["complete"].as_bool() != Some(true) {
+            return Err(SprayError::RpcError(
+                "Wallet could not sign the funding transaction".into(),
+            ));
+        }
+        let hex = signed["hex"]
+            .as_str()
+            .ok_or_else(|| SprayError::RpcError("Invalid signrawtransaction response".into()))?;
+
+        Ok((hex.to_string(), self.contract_index()?))
+    }
+
+    /// Fund `contract` with `amount` using a transaction of this shape
+    ///
+    /// The transaction is built as by [`build`](Self::build) and broadcast.
+    /// Returns the funding txid and the contract output's index.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the shape is invalid or the wallet cannot fund,
+    /// sign or broadcast the transaction.
+    pub fn fund(
+        &self,
+        call: impl Fn(&str, &[Value]) -> Result<Value, SprayError>,
+        contract: &Address,
+        amount: u64,
+    ) -> Result<(Txid, u32), SprayError> {
+        let (hex, index) = self.build(&call, contract, amount)?;
+
+        let txid = call("sendrawtransaction", &[hex.into()])?;
+        let txid = txid
+            .as_str()
+            .ok_or_else(|| SprayError::RpcError("Invalid txid response".into()))?;
+        let txid = Txid::from_str(txid).map_err(|e| SprayError::RpcError(e.to_string()))?;
+
+        Ok((txid, index))
+    }
+}
@@ -0,0 +1,355 @@
+//! Witness mutation fuzzing
+//!
+//! A contract should accept the witnesses it was written for and nothing
+//! else. `spray fuzz` starts from a witness the contract accepts and derives
+//! mutants from it, each differing in one value:
+//!
+//! - **bit flips**: one bit of an integer, byte string or boolean inverted;
+//! - **boundary values**: an integer replaced by `0`, `1`, its maximum or one
+//!   less, or a byte string by all zeros or all ones;
+//! - **wrong lengths**: a byte string one byte short or long, or an integer
+//!   one past its maximum.
+//!
+//! Every mutant is expected to be rejected, either before broadcast (it does
+//! not fit the witness types or fails to satisfy the program) or by the node.
+//! A mutant that is accepted points at a witness value the contract does not
+//! constrain.
+//!
+//! Mutations are drawn from a seeded generator, so a run can be repeated
+//! with the same seed.
+//!
+//! # Example
+//!
+//! ```
+//! use serde_json::{json, Map};
+//! use spray::fuzz::Mutator;
+//! use spray::value::ValueType;
+//! use std::collections::HashMap;
+//!
+//! let witness = json!({ "AMOUNT": 1000 });
+//! let types = HashMap::from([("AMOUNT".to_string(), "u32".parse::<ValueType>().unwrap())]);
+//!
+//! let mut mutator = Mutator::new(7);
+//! let mutant = mutator.mutate(witness.as_object().unwrap(), &types).unwrap();
+//! assert_eq!(mutant.witness, "AMOUNT");
+//! assert_ne!(mutant.value, json!(1000));
+//! ```
+
+use crate::test::FailureStage;
+use crate::value::ValueType;
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::{Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+
+/// Default number of mutants tried
+pub const DEFAULT_ITERATIONS: usize = 200;
+
+/// How a mutant differs from the original witness
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MutationKind {
+    /// One bit inverted
+    BitFlip,
+    /// A value at the edge of its type's range
+    Boundary,
+    /// A byte string of the wrong length, or an integer too wide for its type
+    WrongLength,
+}
+
+impl MutationKind {
+    /// All kinds of mutation
+    pub const ALL: [Self; 3] = [Self::BitFlip, Self::Boundary, Self::WrongLength];
+}
+
+impl fmt::Display for MutationKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::BitFlip => "bit flip",
+            Self::Boundary => "boundary value",
+            Self::WrongLength => "wrong length",
+        })
+    }
+}
+
+/// A witness with one value mutated
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Mutant {
+    /// Name of the mutated witness value
+    pub witness: String,
+    /// Kind of mutation
+    pub kind: MutationKind,
+    /// The mutated value
+    pub value: Value,
+}
+
+impl Mutant {
+    /// The witness `original` with this mutant's value in place
+    #[must_use]
+    pub fn apply(&self, original: &Map<String, Value>) -> Map<String, Value> {
+        let mut witness = original.clone();
+        witness.insert(self.witness.clone(), self.value.clone());
+        witness
+    }
+}
+
+impl fmt::Display for Mutant {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let value = match &self.value {
+            Value::String(literal) => literal.clone(),
+            other => other.to_string(),
+        };
+        write!(f, "{} = {value} ({})", self.witness, self.kind)
+    }
+}
+
+/// What became of a mutant
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "outcome", rename_all = "snake_case")]
+pub enum Outcome {
+    /// Rejected at `stage`
+    Rejected {
+        /// Where the mutant was rejected
+        stage: FailureStage,
+        /// Why
+        reason: String,
+    },
+    /// Accepted by the node
+    Accepted,
+}
+
+/// Results of a fuzzing run
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FuzzReport {
+    /// Seed the mutations were drawn with
+    pub seed: u64,
+    /// Number of distinct mutants tried
+    pub mutants: usize,
+    /// Number of rejected mutants, by stage
+    pub rejected: BTreeMap<FailureStage, usize>,
+    /// Mutants the node accepted
+    pub accepted: Vec<Mutant>,
+}
+
+impl FuzzReport {
+    /// An empty report for a run with `seed`
+    #[must_use]
+    pub fn new(seed: u64) -> Self {
+        Self {
+            seed,
+            ..Self::default()
+        }
+    }
+
+    /// Record the outcome of `mutant`
+    pub fn add(&mut self, mutant: Mutant, outcome: &Outcome) {
+        self.mutants += 1;
+        match outcome {
+            Outcome::Rejected { stage, .. } => *self.rejected.entry(*stage).or_default() += 1,
+            Outcome::Accepted => self.accepted.push(mutant),
+        }
+    }
+
+    /// Whether every mutant was rejected
+    #[must_use]
+    pub fn passed(&self) -> bool {
+        self.accepted.is_empty()
+    }
+}
+
+/// Draws mutants of a witness
+#[derive(Debug, Clone)]
+pub struct Mutator {
+    rng: StdRng,
+}
+
+impl Mutator {
+    /// A mutator drawing from a generator seeded with `seed`
+    #[must_use]
+    pub fn new(seed: u64) -> Self {
+        Self {
+            rng: StdRng::seed_from_u64(seed),
+        }
+    }
+
+    /// Draw a mutant of `witness`, whose values have the given `types`
+    ///
+    /// Values without a known type are mutated by their literal alone.
+    /// Returns `None` if no value of the witness can be mutated.
+    pub fn mutate(
+        &mut self,
+        witness: &Map<String, Value>,
+        types: &HashMap<String, ValueType>,
+    ) -> Option<Mutant> {
+        let mut names: Vec<&String> = witness.keys().collect();
+        names.sort_unstable();
+        names.shuffle(&mut self.rng);
+
+        for name in names {
+            let mut kinds = MutationKind::ALL;
+            kinds.shuffle(&mut self.rng);
+            for kind in kinds {
+                let original = &witness[name];
+                if let Some(value) = self.mutate_value(original, types.get(name), kind) {
+                    if &value != original {
+                        return Some(Mutant {
+                            witness: name.clone(),
+                            kind,
+                            value,
+                        });
+                    }
+                }
+            }
+        }
+        None
+    }
+
+    /// Apply a mutation of `kind` to `value`, if it has anything to mutate
+    fn mutate_value(
+        &mut self,
+        value: &Value,
+        ty: Option<&ValueType>,
+        kind: MutationKind,
+    ) -> Option<Value> {
+        let literal = match value {
+            Value::String(literal) => literal.clone(),
+            Value::Number(_) | Value::Bool(_) => value.to_string(),
+            _ => return None,
+        };
+
+        // A bare integer of known width is mutated within its type
+        if let (Some(ValueType::UInt(bits)), Ok(n)) = (ty, literal.parse::<u128>()) {
+            let bits = u32::from(*bits).min(128);
+            if bits == 0 {
+                return None;
+            }
+            let max = if bits == 128 {
+                u128::MAX
+            } else {
+                (1 << bits) - 1
+            };
+            let mutated = match kind {
+                MutationKind::BitFlip => n ^ (1 << self.rng.gen_range(0..bits)),
+                MutationKind::Boundary => *[0, 1, max - 1, max].choose(&mut self.rng)?,
+                MutationKind::WrongLength => max.checked_add(1)?,
+            };
+            return Some(match (value, u64::try_from(mutated)) {
+                (Value::Number(_), Ok(n)) => n.into(),
+                _ => mutated.to_string().into(),
+            });
+        }
+
+        let tokens = tokens(&literal);
+        let (start, end, token) = tokens
+            .iter()
+            .filter(|(_, _, token)| applies(kind, token))
+            .collect::<Vec<_>>()
+            .choose(&mut self.rng)
+            .map(|(start, end, token)| (*start, *end, *token))?;
+        let replacement = match (kind, token) {
+            (MutationKind::BitFlip, Token::Bool(b)) => (!b).to_string(),
+            (MutationKind::BitFlip, Token::Hex) => {
+                let digits = &literal[start + 2..end];
+                let bit = self.rng.gen_range(0..digits.len() * 4);
+                let mut digits: Vec<u32> = digits.chars().filter_map(|c| c.to_digit(16)).collect();
+                digits[bit / 4] ^= 1 << (3 - bit % 4);
+                let digits: String = digits
+                    .iter()
+                    .filter_map(|d| char::from_digit(*d, 16))
+                    .collect();
+                format!("0x{digits}")
+            }
+            (MutationKind::BitFlip, Token::Decimal) => {
+                let n: u128 = literal[start..end].parse().ok()?;
+                (n ^ (1 << self.rng.gen_range(0..8))).to_string()
+            }
+            (MutationKind::Boundary, Token::Hex) => {
+                let digit = if self.rng.gen() { "0" } else { "f" };
+                format!("0x{}", digit.repeat(end - start - 2))
+            }
+            (MutationKind::WrongLength, Token::Hex) => {
+                let digits = &literal[start + 2..end];
+                if digits.len() > 2 && self.rng.gen() {
+                    format!("0x{}", &digits[..digits.len() - 2])
+                } else {
+                    format!("0x{digits}00")
+                }
+            }
+            _ => return None,
+        };
+
+        let mutated = format!("{}{replacement}{}", &literal[..start], &literal[end..]);
+        Some(match value {
+            Value::Number(_) => mutated
+                .parse::<u64>()
+                .map_or_else(|_| mutated.clone().into(), Value::from),
+            Value::Bool(_) => mutated.parse::<bool>().map_or(Value::Null, Value::from),
+            _ => mutated.into(),
+        })
+    }
+}
+
+/// A mutable part of a literal
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Token {
+    /// `0x` followed by hex digits
+    Hex,
+    /// Decimal digits
+    Decimal,
+    /// `true` or `false`
+    Bool(bool),
+}
+
+/// Whether a mutation of `kind` can be applied to `token`
+const fn applies(kind: MutationKind, token: &Token) -> bool {
+    matches!(
+        (kind, token),
+        (MutationKind::BitFlip, _)
+            | (
+                MutationKind::Boundary | MutationKind::WrongLength,
+                Token::Hex
+            )
+    )
+}
+
+/// The mutable tokens of a literal, with their byte ranges
+fn tokens(literal: &str) -> Vec<(usize, usize, Token)> {
+    let bytes = literal.as_bytes();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    while i < bytes.len() {
+        let starts_word = i == 0 || !(bytes[i - 1].is_ascii_alphanumeric() || bytes[i - 1] == b'_');
+        let word_end = |from: usize, accept: fn(u8) -> bool| {
+            (from..bytes.len())
+                .find(|&j| !accept(bytes[j]))
+                .unwrap_or(bytes.len())
+        };
+
+        if starts_word && literal[i..].starts_with("0x") {
+            let end = word_end(i + 2, |b| b.is_ascii_hexdigit());
+            if end > i + 2 {
+                tokens.push((i, end, Token::Hex));
+            }
+            i = end.max(i + 2);
+        } else if starts_word && bytes[i].is_ascii_digit() {
+            let end = word_end(i, |b| b.is_ascii_digit());
+            tokens.push((i, end, Token::Decimal));
+            i = end;
+        } else if starts_word
+            && (literal[i..].starts_with("true") || literal[i..].starts_with("false"))
+        {
+            let value = literal[i..].starts_with("true");
+            let end = i + if value { 4 } else { 5 };
+            if end == bytes.len() || !bytes[end].is_ascii_alphanumeric() {
+                tokens.push((i, end, Token::Bool(value)));
+            }
+            i = end;
+        } else {
+            i += 1;
+        }
+    }
+    tokens
+}
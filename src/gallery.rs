@@ -0,0 +1,147 @@
+//! Bundled example programs
+//!
+//! With the `examples` feature, spray carries the common SimplicityHL
+//! example contracts, so that `spray examples list|show|run` can try them
+//! without a checkout of SimplicityHL. Each example comes with the
+//! arguments and witness of one spend that passes on regtest; signatures
+//! are made with test keys (see [`crate::witness::SigningWitness`]).
+//!
+//! # Example
+//!
+//! ```
+//! use spray::gallery;
+//!
+//! let htlc = gallery::find("htlc").unwrap();
+//! assert!(htlc.source.contains("fn main()"));
+//! assert!(htlc.manifest().contains("file = \"htlc.simf\""));
+//! assert!(gallery::find("nonexistent").is_err());
+//! ```
+
+use crate::error::SprayError;
+use std::path::{Path, PathBuf};
+
+/// A bundled example program
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Example {
+    /// Name, also the file name of the program
+    pub name: &'static str,
+    /// One-line description
+    pub description: &'static str,
+    /// SimplicityHL source
+    pub source: &'static str,
+    /// Arguments file (JSON), if the program takes arguments
+    pub args: Option<&'static str>,
+    /// Witness file (JSON), if the program takes a witness
+    pub witness: Option<&'static str>,
+    /// Destination of the example spend, if it must not pay to the wallet
+    pub destination: Option<&'static str>,
+}
+
+/// Every bundled example
+pub const EXAMPLES: &[Example] = &[
+    Example {
+        name: "cat",
+        description: "Concatenation by casting, spendable by anyone",
+        source: include_str!("../templates/cat/cat.simf"),
+        args: None,
+        witness: None,
+        destination: None,
+    },
+    Example {
+        name: "p2pk",
+        description: "Pay to public key",
+        source: include_str!("../templates/p2pk/p2pk.simf"),
+        args: Some(include_str!("../templates/p2pk/p2pk.args.json")),
+        witness: Some(include_str!("../templates/p2pk/p2pk.wit.json")),
+        destination: None,
+    },
+    Example {
+        name: "ctv",
+        description: "Covenant committing to the destination of the coins",
+        source: include_str!("../templates/ctv/ctv.simf"),
+        args: Some(include_str!("../templates/ctv/ctv.args.json")),
+        witness: None,
+        destination: Some("raw(0014751e76e8199196d454941c45d1b3a323f1433bd6)"),
+    },
+    Example {
+        name: "htlc",
+        description: "Hashed timelock contract",
+        source: include_str!("../templates/htlc/htlc.simf"),
+        args: Some(include_str!("../templates/htlc/htlc.args.json")),
+        witness: Some(include_str!("../templates/htlc/htlc.wit.json")),
+        destination: None,
+    },
+    Example {
+        name: "presigned_vault",
+        description: "Vault emptied by a presigned transaction or swept by a cold key",
+        source: include_str!("../templates/presigned_vault/presigned_vault.simf"),
+        args: Some(include_str!(
+            "../templates/presigned_vault/presigned_vault.args.json"
+        )),
+        witness: Some(include_str!(
+            "../templates/presigned_vault/presigned_vault.wit.json"
+        )),
+        destination: None,
+    },
+];
+
+/// The bundled example named `name`
+///
+/// # Errors
+///
+/// Returns an error if there is no such example.
+pub fn find(name: &str) -> Result<&'static Example, SprayError> {
+    EXAMPLES
+        .iter()
+        .find(|example| example.name == name)
+        .ok_or_else(|| {
+            let names: Vec<&str> = EXAMPLES.iter().map(|example| example.name).collect();
+            SprayError::ConfigError(format!(
+                "Unknown example '{name}' (available: {})",
+                names.join(", ")
+            ))
+        })
+}
+
+impl Example {
+    /// A test manifest running the example spend from the files written by
+    /// [`Example::write`]
+    #[must_use]
+    pub fn manifest(&self) -> String {
+        let name = self.name;
+        let mut manifest = format!(
+            "[[suite]]\nname = \"examples\"\nnetwork = \"regtest\"\n\n[[suite.test]]\nname = \"{name}\"\nfile = \"{name}.simf\"\n"
+        );
+        if self.args.is_some() {
+            manifest.push_str(&format!("args = \"{name}.args.json\"\n"));
+        }
+        if self.witness.is_some() {
+            manifest.push_str(&format!("witness = \"{name}.wit.json\"\n"));
+        }
+        if let Some(destination) = self.destination {
+            manifest.push_str(&format!("destination = \"{destination}\"\n"));
+        }
+        manifest
+    }
+
+    /// Write the program, its arguments and witness, and a manifest to
+    /// `dir`, returning the path of the manifest
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a file cannot be written.
+    pub fn write(&self, dir: &Path) -> Result<PathBuf, SprayError> {
+        let name = self.name;
+        std::fs::create_dir_all(dir)?;
+        std::fs::write(dir.join(format!("{name}.simf")), self.source)?;
+        if let Some(args) = self.args {
+            std::fs::write(dir.join(format!("{name}.args.json")), args)?;
+        }
+        if let Some(witness) = self.witness {
+            std::fs::write(dir.join(format!("{name}.wit.json")), witness)?;
+        }
+        let manifest = dir.join("spray-tests.toml");
+        std::fs::write(&manifest, self.manifest())?;
+        Ok(manifest)
+    }
+}
@@ -0,0 +1,373 @@
+//! Garbage collection of spray's on-disk leftovers
+//!
+//! spray leaves files behind that nothing cleans up: the compilation cache
+//! under `target/spray/cache`, suite reports, and the data directories of
+//! restartable test environments (`spray-<run id>` in the system temporary
+//! directory), which outlive runs that are killed. `spray gc` prunes them
+//! according to a [`GcPolicy`], which the `[gc]` table of `spray.toml`
+//! configures:
+//!
+//! ```toml
+//! [gc]
+//! max_age_days = 14
+//! max_size_mb = 500
+//! report_dirs = ["reports"]
+//! ```
+//!
+//! Entries older than the maximum age are removed, then the oldest entries
+//! of each kind until the kind fits in the maximum size. The data directory
+//! of a run whose process is gone is removed regardless of its age; that of
+//! a running process never is. Only directories spray created, which hold a
+//! [`DATADIR_MARKER`] file, count as data directories, so that other tools'
+//! `spray-*` directories are never collected.
+//!
+//! # Example
+//!
+//! ```
+//! use spray::gc::{GcEntry, GcKind, GcPolicy};
+//! use std::time::{Duration, SystemTime};
+//!
+//! let now = SystemTime::now();
+//! let day = Duration::from_secs(24 * 60 * 60);
+//! let entry = |name: &str, age: u32| GcEntry {
+//!     kind: GcKind::Cache,
+//!     path: name.into(),
+//!     size: 1_000,
+//!     modified: now - day * age,
+//!     owner_alive: None,
+//! };
+//!
+//! let policy = GcPolicy {
+//!     max_age: Some(day * 7),
+//!     max_size: Some(1_500),
+//! };
+//! let entries = [entry("old", 30), entry("recent", 2), entry("new", 0)];
+//! let pruned: Vec<_> = policy
+//!     .select(&entries, now)
+//!     .iter()
+//!     .map(|e| e.path.to_str().unwrap())
+//!     .collect();
+//! assert_eq!(pruned, ["old", "recent"]);
+//! ```
+
+use crate::error::SprayError;
+use serde::Deserialize;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{Duration, SystemTime};
+
+/// Default maximum age of entries, in days
+pub const DEFAULT_MAX_AGE_DAYS: u64 = 14;
+
+/// Prefix of the data directories of restartable test environments
+const DATADIR_PREFIX: &str = "spray-";
+
+/// File marking a directory as the data directory of a test environment
+pub const DATADIR_MARKER: &str = ".spray-datadir";
+
+/// What an entry is
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum GcKind {
+    /// A cached compilation
+    Cache,
+    /// A suite report or report archive
+    Report,
+    /// The data directory of a test environment
+    Datadir,
+}
+
+impl fmt::Display for GcKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Cache => "cache",
+            Self::Report => "report",
+            Self::Datadir => "datadir",
+        })
+    }
+}
+
+/// A file or directory that may be collected
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GcEntry {
+    /// What the entry is
+    pub kind: GcKind,
+    /// Path of the entry
+    pub path: PathBuf,
+    /// Total size in bytes, including the contents of directories
+    pub size: u64,
+    /// Time of the last modification
+    pub modified: SystemTime,
+    /// Whether the process owning the entry is running, if known
+    pub owner_alive: Option<bool>,
+}
+
+/// The `[gc]` table of `spray.toml`
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct GcConfig {
+    /// Maximum age of entries in days
+    #[serde(default)]
+    pub max_age_days: Option<u64>,
+    /// Maximum total size of each kind of entry, in megabytes
+    #[serde(default)]
+    pub max_size_mb: Option<u64>,
+    /// Directories holding suite reports, relative to `spray.toml`
+    #[serde(default)]
+    pub report_dirs: Vec<PathBuf>,
+}
+
+/// When entries are collected
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GcPolicy {
+    /// Remove entries last modified longer ago than this
+    pub max_age: Option<Duration>,
+    /// Remove the oldest entries of a kind until it takes at most this many
+    /// bytes
+    pub max_size: Option<u64>,
+}
+
+impl Default for GcPolicy {
+    fn default() -> Self {
+        Self {
+            max_age: Some(days(DEFAULT_MAX_AGE_DAYS)),
+            max_size: None,
+        }
+    }
+}
+
+impl GcPolicy {
+    /// The policy configured by `config`, with the default maximum age if it
+    /// sets none
+    #[must_use]
+    pub fn from_config(config: &GcConfig) -> Self {
+        Self {
+            max_age: Some(days(config.max_age_days.unwrap_or(DEFAULT_MAX_AGE_DAYS))),
+            max_size: config.max_size_mb.map(|mb| mb * 1024 * 1024),
+        }
+    }
+
+    /// The entries to remove as of `now`, in the order given
+    #[must_use]
+    pub fn select<'e>(&self, entries: &'e [GcEntry], now: SystemTime) -> Vec<&'e GcEntry> {
+        let age = |entry: &GcEntry| now.duration_since(entry.modified).unwrap_or_default();
+        let mut selected: Vec<bool> = entries
+            .iter()
+            .map(|entry| match entry.owner_alive {
+                Some(alive) => !alive,
+                None => self.max_age.is_some_and(|max| age(entry) > max),
+            })
+            .collect();
+
+        if let Some(max_size) = self.max_size {
+            let mut kinds: Vec<GcKind> = entries.iter().map(|entry| entry.kind).collect();
+            kinds.sort_unstable();
+            kinds.dedup();
+            for kind in kinds {
+                let mut kept: Vec<usize> = (0..entries.len())
+                    .filter(|&i| entries[i].kind == kind && !selected[i])
+                    .collect();
+                kept.sort_by_key(|&i| entries[i].modified);
+                let mut total: u64 = kept.iter().map(|&i| entries[i].size).sum();
+                for i in kept {
+                    if total <= max_size {
+                        break;
+                    }
+                    // Directories in use must not be removed, whatever they take
+                    if entries[i].owner_alive == Some(true) {
+                        continue;
+                    }
+                    selected[i] = true;
+                    total -= entries[i].size;
+                }
+            }
+        }
+
+        entries
+            .iter()
+            .zip(selected)
+            .filter_map(|(entry, selected)| selected.then_some(entry))
+            .collect()
+    }
+}
+
+/// The entries directly inside `dir`, none if it does not exist
+///
+/// # Errors
+///
+/// Returns an error if the directory cannot be read.
+pub fn scan(dir: &Path, kind: GcKind) -> Result<Vec<GcEntry>, SprayError> {
+    if !dir.is_dir() {
+        return Ok(Vec::new());
+    }
+    let mut entries = Vec::new();
+    for item in std::fs::read_dir(dir)? {
+        entries.push(entry(item?.path(), kind));
+    }
+    entries.sort_by(|a, b| a.path.cmp(&b.path));
+    Ok(entries)
+}
+
+/// Create a new data directory for a test environment of this run under
+/// the system temporary directory, marked for [`scan_datadirs`]
+///
+/// Each call creates a distinct `spray-<run id>-<n>` directory, so
+/// environments of the same run never share a chain.
+///
+/// # Errors
+///
+/// Returns an error if the directory or its marker cannot be written.
+pub fn create_datadir() -> Result<PathBuf, SprayError> {
+    static NEXT: AtomicUsize = AtomicUsize::new(0);
+    let run_id = crate::registry::run_id();
+    loop {
+        let n = NEXT.fetch_add(1, Ordering::Relaxed);
+        let dir = std::env::temp_dir().join(format!("{DATADIR_PREFIX}{run_id}-{n}"));
+        // Skip directories left behind by an earlier run with the same ID
+        match std::fs::create_dir(&dir) {
+            Ok(()) => {
+                mark_datadir(&dir)?;
+                return Ok(dir);
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => {}
+            Err(e) => return Err(e.into()),
+        }
+    }
+}
+
+/// Create `dir` if needed and mark it as the data directory of a test
+/// environment
+///
+/// # Errors
+///
+/// Returns an error if the directory or its marker cannot be written.
+pub fn mark_datadir(dir: &Path) -> Result<(), SprayError> {
+    std::fs::create_dir_all(dir)?;
+    std::fs::write(dir.join(DATADIR_MARKER), crate::registry::run_id())?;
+    Ok(())
+}
+
+/// The data directories of test environments in `temp_dir`: directories
+/// named `spray-*` holding a [`DATADIR_MARKER`]
+///
+/// # Errors
+///
+/// Returns an error if the directory cannot be read.
+pub fn scan_datadirs(temp_dir: &Path) -> Result<Vec<GcEntry>, SprayError> {
+    let mut entries = Vec::new();
+    for item in std::fs::read_dir(temp_dir)? {
+        let path = item?.path();
+        let Some(name) = path.file_name().and_then(|name| name.to_str()) else {
+            continue;
+        };
+        if !name.starts_with(DATADIR_PREFIX) || !path.join(DATADIR_MARKER).is_file() {
+            continue;
+        }
+        let owner_alive = owner_alive(name);
+        entries.push(GcEntry {
+            owner_alive,
+            ..entry(path, GcKind::Datadir)
+        });
+    }
+    entries.sort_by(|a, b| a.path.cmp(&b.path));
+    Ok(entries)
+}
+
+/// Whether the run owning the data directory `name` is still running
+///
+/// Known only for directories named after a process ID (`spray-pid-<pid>`,
+/// optionally followed by `-<n>`), on systems with `/proc`.
+#[must_use]
+pub fn owner_alive(name: &str) -> Option<bool> {
+    let rest = name.strip_prefix("spray-pid-")?;
+    let pid: u32 = rest
+        .split_once('-')
+        .map_or(rest, |(pid, _)| pid)
+        .parse()
+        .ok()?;
+    if pid == std::process::id() {
+        return Some(true);
+    }
+    let proc = Path::new("/proc");
+    proc.is_dir().then(|| proc.join(pid.to_string()).exists())
+}
+
+/// Remove an entry from disk
+///
+/// # Errors
+///
+/// Returns an error if the file or directory cannot be removed.
+pub fn remove(entry: &GcEntry) -> Result<(), SprayError> {
+    if entry.path.is_dir() {
+        std::fs::remove_dir_all(&entry.path)?;
+    } else {
+        std::fs::remove_file(&entry.path)?;
+    }
+    Ok(())
+}
+
+/// Total size of a file, or of the files in a directory tree
+#[must_use]
+pub fn disk_size(path: &Path) -> u64 {
+    let Ok(metadata) = std::fs::symlink_metadata(path) else {
+        return 0;
+    };
+    if !metadata.is_dir() {
+        return metadata.len();
+    }
+    std::fs::read_dir(path)
+        .into_iter()
+        .flatten()
+        .flatten()
+        .map(|item| disk_size(&item.path()))
+        .sum()
+}
+
+/// Format a byte count compactly (`"512 B"`, `"1.5 MB"`)
+#[must_use]
+#[allow(clippy::cast_precision_loss)]
+pub fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["KB", "MB", "GB", "TB"];
+    if bytes < 1024 {
+        return format!("{bytes} B");
+    }
+    let mut size = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    format!("{size:.1} {}", UNITS[unit])
+}
+
+/// Size and modification time of the file or directory at `path`
+fn entry(path: PathBuf, kind: GcKind) -> GcEntry {
+    GcEntry {
+        kind,
+        size: disk_size(&path),
+        modified: modified(&path),
+        owner_alive: None,
+        path,
+    }
+}
+
+/// Latest modification time in a file or directory tree
+fn modified(path: &Path) -> SystemTime {
+    let Ok(metadata) = std::fs::symlink_metadata(path) else {
+        return SystemTime::UNIX_EPOCH;
+    };
+    let own = metadata.modified().unwrap_or(SystemTime::UNIX_EPOCH);
+    if !metadata.is_dir() {
+        return own;
+    }
+    std::fs::read_dir(path)
+        .into_iter()
+        .flatten()
+        .flatten()
+        .map(|item| modified(&item.path()))
+        .fold(own, SystemTime::max)
+}
+
+/// A duration of `n` days
+const fn days(n: u64) -> Duration {
+    Duration::from_secs(n * 24 * 60 * 60)
+}
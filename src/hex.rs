@@ -0,0 +1,23 @@
+//! Hex encoding
+//!
+//! Decoding goes through musk's `FromHex`; this is the one encoder the crate
+//! uses for txids, scripts, sighashes and program bytes.
+//!
+//! # Example
+//!
+//! ```
+//! assert_eq!(spray::hex::encode(&[0x00, 0xab, 0x10]), "00ab10");
+//! ```
+
+use std::fmt::Write;
+
+/// Lowercase hex encoding of `bytes`
+#[must_use]
+pub fn encode(bytes: &[u8]) -> String {
+    bytes
+        .iter()
+        .fold(String::with_capacity(bytes.len() * 2), |mut acc, b| {
+            let _ = write!(acc, "{b:02x}");
+            acc
+        })
+}
@@ -0,0 +1,206 @@
+//! Historical deployment status
+//!
+//! The registry records every deployment, but not what became of it. This
+//! module reconstructs, from chain data alone, which deployments were funded
+//! and which were still unspent at a given block height: blocks are walked
+//! from the earliest recorded deployment height, noting the block in which
+//! each deployment's funding transaction confirmed and the block and
+//! transaction that spent it.
+//!
+//! # Example
+//!
+//! ```
+//! use serde_json::json;
+//! use spray::history::{DeploymentHistory, Status};
+//! use spray::registry::DeploymentRecord;
+//!
+//! let record = DeploymentRecord {
+//!     txid: "aa".repeat(32),
+//!     vout: 1,
+//!     network: "regtest".into(),
+//!     ..Default::default()
+//! };
+//! let mut history = DeploymentHistory::new(&[record]);
+//!
+//! let funding = json!({ "tx": [{ "txid": "aa".repeat(32), "vin": [] }] });
+//! history.apply_block(&funding, 100).unwrap();
+//! assert_eq!(history.status_at(0, 150), Status::Unspent { funded_at: 100 });
+//!
+//! let spend = json!({ "tx": [{ "txid": "bb".repeat(32), "vin": [{ "txid": "aa".repeat(32), "vout": 1 }] }] });
+//! history.apply_block(&spend, 120).unwrap();
+//! assert_eq!(history.status_at(0, 110), Status::Unspent { funded_at: 100 });
+//! assert!(matches!(history.status_at(0, 120), Status::Spent { spent_at: 120, .. }));
+//! assert_eq!(history.status_at(0, 99), Status::Unfunded);
+//! ```
+
+use crate::error::SprayError;
+use crate::network::NetworkBackend;
+use crate::registry::DeploymentRecord;
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::fmt;
+
+/// What had become of a deployment at some height
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum Status {
+    /// The funding transaction had not confirmed yet
+    Unfunded,
+    /// Funded and not yet spent
+    Unspent {
+        /// Height of the funding block
+        funded_at: u64,
+    },
+    /// Funded and spent
+    Spent {
+        /// Height of the funding block
+        funded_at: u64,
+        /// Height of the spending block
+        spent_at: u64,
+        /// Spending transaction
+        spending_txid: String,
+    },
+}
+
+impl fmt::Display for Status {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Unfunded => write!(f, "unfunded"),
+            Self::Unspent { funded_at } => write!(f, "unspent (funded at {funded_at})"),
+            Self::Spent {
+                funded_at,
+                spent_at,
+                spending_txid,
+            } => write!(
+                f,
+                "spent at {spent_at} by {spending_txid} (funded at {funded_at})"
+            ),
+        }
+    }
+}
+
+/// Where a deployment's outpoint was created and spent on chain
+#[derive(Debug, Clone, Default)]
+struct Timeline {
+    funded_at: Option<u64>,
+    spent: Option<(u64, String)>,
+}
+
+/// Chain history of a set of deployments
+#[derive(Debug, Clone)]
+pub struct DeploymentHistory {
+    /// Deployment outpoints, in registry order
+    outpoints: Vec<(String, u32)>,
+    timelines: HashMap<(String, u32), Timeline>,
+}
+
+impl DeploymentHistory {
+    /// Track the outpoints of `records`
+    #[must_use]
+    pub fn new(records: &[DeploymentRecord]) -> Self {
+        let outpoints: Vec<(String, u32)> =
+            records.iter().map(|r| (r.txid.clone(), r.vout)).collect();
+        let timelines = outpoints
+            .iter()
+            .map(|outpoint| (outpoint.clone(), Timeline::default()))
+            .collect();
+        Self {
+            outpoints,
+            timelines,
+        }
+    }
+
+    /// Record the fundings and spends in a block as returned by
+    /// `getblock <hash> 2`
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the block is malformed.
+    pub fn apply_block(&mut self, block: &Value, height: u64) -> Result<(), SprayError> {
+        let malformed = || SprayError::RpcError("Invalid getblock response".into());
+        for tx in block["tx"].as_array().ok_or_else(malformed)? {
+            let txid = tx["txid"].as_str().ok_or_else(malformed)?;
+
+            for timeline in self
+                .timelines
+                .iter_mut()
+                .filter(|((funding, _), _)| funding == txid)
+                .map(|(_, timeline)| timeline)
+            {
+                timeline.funded_at.get_or_insert(height);
+            }
+
+            for input in tx["vin"].as_array().into_iter().flatten() {
+                let (Some(prev), Some(vout)) = (input["txid"].as_str(), input["vout"].as_u64())
+                else {
+                    continue;
+                };
+                let Ok(vout) = u32::try_from(vout) else {
+                    continue;
+                };
+                if let Some(timeline) = self.timelines.get_mut(&(prev.to_string(), vout)) {
+                    timeline.spent.get_or_insert((height, txid.to_string()));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Status of the `index`th deployment at `height`
+    ///
+    /// # Panics
+    ///
+    /// Panics if `index` is out of range.
+    #[must_use]
+    pub fn status_at(&self, index: usize, height: u64) -> Status {
+        let timeline = &self.timelines[&self.outpoints[index]];
+        match (timeline.funded_at, &timeline.spent) {
+            (Some(funded_at), Some((spent_at, txid))) if *spent_at <= height => Status::Spent {
+                funded_at,
+                spent_at: *spent_at,
+                spending_txid: txid.clone(),
+            },
+            (Some(funded_at), _) if funded_at <= height => Status::Unspent { funded_at },
+            _ => Status::Unfunded,
+        }
+    }
+}
+
+/// Height to start scanning from for `records`
+///
+/// The lowest recorded deployment height, or the genesis block if any
+/// record has no height.
+#[must_use]
+pub fn scan_start(records: &[DeploymentRecord]) -> u64 {
+    records
+        .iter()
+        .map(|r| r.height)
+        .collect::<Option<Vec<u64>>>()
+        .and_then(|heights| heights.into_iter().min())
+        .unwrap_or(0)
+}
+
+/// Reconstruct the history of `records` from `start` up to `height`
+///
+/// `progress` is called after each block with its height and `height`.
+///
+/// # Errors
+///
+/// Returns an error if a block cannot be fetched or is malformed.
+pub fn reconstruct(
+    backend: &NetworkBackend,
+    records: &[DeploymentRecord],
+    start: u64,
+    height: u64,
+    mut progress: impl FnMut(u64, u64),
+) -> Result<DeploymentHistory, SprayError> {
+    let mut history = DeploymentHistory::new(records);
+    for h in start..=height {
+        let hash = backend.call("getblockhash", &[h.into()])?;
+        let block = backend.call("getblock", &[hash, 2.into()])?;
+        history.apply_block(&block, h)?;
+        progress(h, height);
+    }
+    Ok(history)
+}
@@ -0,0 +1,359 @@
+//! Funds held by a contract
+//!
+//! `spray utxos` lists the outputs paying to a contract address, and
+//! `spray balance` sums them per asset. A contract is named by its address,
+//! its CMR, or a compiled artifact (see [`resolve_address`]). The same
+//! lookup backs the backends' `get_utxos`.
+//!
+//! The outputs are found in one of two ways, reported as a [`Source`]:
+//!
+//! - If the node's wallet watches the address (e.g. after `spray import`),
+//!   `listunspent` lists them with their confirmations, mempool included.
+//! - Otherwise `scantxoutset` finds the confirmed ones in the UTXO set, and
+//!   the mempool is searched for unconfirmed outputs to the address.
+//!
+//! Outputs with a confidential amount or asset are counted but cannot be
+//! summed.
+//!
+//! `spray watch` polls the outputs of an address, and [`FundingWatch`] turns
+//! successive polls into [`FundingEvent`]s as funds arrive and confirm.
+//!
+//! # Example
+//!
+//! ```
+//! use serde_json::json;
+//! use spray::holdings::{from_listunspent, Balance};
+//!
+//! let unspent = json!([
+//!     { "txid": "aa", "vout": 0, "amount": 0.0001, "asset": "5ac9", "confirmations": 3 },
+//!     { "txid": "bb", "vout": 1, "amount": 0.00002, "asset": "5ac9", "confirmations": 0 },
+//! ]);
+//! let holdings = from_listunspent(&unspent);
+//! let balance = Balance::new(&holdings);
+//! assert_eq!(balance.assets["5ac9"].confirmed, 10_000);
+//! assert_eq!(balance.assets["5ac9"].unconfirmed, 2_000);
+//! ```
+
+use crate::client::btc_to_sats;
+use crate::error::SprayError;
+use crate::hex;
+use crate::interop;
+use crate::precompiled::PrecompiledProgram;
+use musk::client::Utxo;
+use musk::elements::{confidential, Address, AddressParams, AssetId, Script, Txid};
+use serde::Serialize;
+use serde_json::{json, Value};
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::path::Path;
+use std::str::FromStr;
+
+/// An unspent output paying to a contract
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Holding {
+    /// Transaction holding the output
+    pub txid: String,
+    /// Index of the output
+    pub vout: u32,
+    /// Amount in satoshis, if explicit
+    pub amount: Option<u64>,
+    /// Asset ID, if explicit
+    pub asset: Option<String>,
+    /// Confirmations, 0 while in the mempool
+    pub confirmations: u64,
+}
+
+impl Holding {
+    /// Whether the output is in a block
+    #[must_use]
+    pub const fn is_confirmed(&self) -> bool {
+        self.confirmations > 0
+    }
+
+    /// The output as a musk [`Utxo`] locked by `script_pubkey`, or `None` if
+    /// its amount or asset is confidential
+    #[must_use]
+    pub fn to_utxo(&self, script_pubkey: Script) -> Option<Utxo> {
+        Some(Utxo {
+            txid: Txid::from_str(&self.txid).ok()?,
+            vout: self.vout,
+            amount: self.amount?,
+            script_pubkey,
+            asset: confidential::Asset::Explicit(AssetId::from_str(self.asset.as_deref()?).ok()?),
+        })
+    }
+}
+
+/// Confirmed and unconfirmed amounts of one asset
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct AssetBalance {
+    /// Satoshis in confirmed outputs
+    pub confirmed: u64,
+    /// Satoshis in outputs still in the mempool
+    pub unconfirmed: u64,
+}
+
+/// Sums of the explicit outputs of a contract, per asset
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct Balance {
+    /// Amounts by asset ID
+    pub assets: BTreeMap<String, AssetBalance>,
+    /// Number of outputs
+    pub utxos: usize,
+    /// Number of outputs with a confidential amount or asset, not summed
+    pub confidential: usize,
+}
+
+impl Balance {
+    /// Sum `holdings`
+    #[must_use]
+    pub fn new(holdings: &[Holding]) -> Self {
+        let mut balance = Self {
+            utxos: holdings.len(),
+            ..Self::default()
+        };
+        for holding in holdings {
+            let (Some(amount), Some(asset)) = (holding.amount, &holding.asset) else {
+                balance.confidential += 1;
+                continue;
+            };
+            let entry = balance.assets.entry(asset.clone()).or_default();
+            if holding.is_confirmed() {
+                entry.confirmed += amount;
+            } else {
+                entry.unconfirmed += amount;
+            }
+        }
+        balance
+    }
+}
+
+/// A change in the outputs of a watched contract
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum FundingEvent {
+    /// An output paying to the contract appeared
+    Received(Holding),
+    /// An output first seen in the mempool got its first confirmation
+    Confirmed(Holding),
+}
+
+impl FundingEvent {
+    /// The output the event is about
+    #[must_use]
+    pub const fn holding(&self) -> &Holding {
+        match self {
+            Self::Received(holding) | Self::Confirmed(holding) => holding,
+        }
+    }
+}
+
+/// Follows the outputs of a contract between polls
+#[derive(Debug, Clone, Default)]
+pub struct FundingWatch {
+    /// Whether each output seen so far was confirmed, by outpoint
+    known: HashMap<(String, u32), bool>,
+}
+
+impl FundingWatch {
+    /// Watch from `holdings`, the outputs already there
+    #[must_use]
+    pub fn new(holdings: &[Holding]) -> Self {
+        let mut watch = Self::default();
+        watch.update(holdings.to_vec());
+        watch
+    }
+
+    /// The events between the last poll and `holdings`, the outputs now
+    ///
+    /// An output that arrives already confirmed is only
+    /// [received](FundingEvent::Received).
+    pub fn update(&mut self, holdings: Vec<Holding>) -> Vec<FundingEvent> {
+        let mut events = Vec::new();
+        for holding in holdings {
+            let outpoint = (holding.txid.clone(), holding.vout);
+            match self.known.insert(outpoint, holding.is_confirmed()) {
+                None => events.push(FundingEvent::Received(holding)),
+                Some(false) if holding.is_confirmed() => {
+                    events.push(FundingEvent::Confirmed(holding));
+                }
+                Some(_) => {}
+            }
+        }
+        events
+    }
+}
+
+/// How the outputs of a contract were found
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Source {
+    /// The node's wallet watches the address
+    Wallet,
+    /// The UTXO set and the mempool were searched
+    UtxoSet,
+}
+
+impl fmt::Display for Source {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Wallet => "wallet (listunspent)",
+            Self::UtxoSet => "UTXO set scan and mempool",
+        })
+    }
+}
+
+/// The address of a contract on the network with `params`, from its
+/// address, its CMR (hex), or a compiled artifact file
+///
+/// # Errors
+///
+/// Returns an error if `target` is none of these, an artifact cannot be
+/// read, or an address is for another network.
+pub fn resolve_address(
+    target: &str,
+    params: &'static AddressParams,
+) -> Result<Address, SprayError> {
+    let path = Path::new(target);
+    if path.is_file() {
+        let contents = std::fs::read_to_string(path)?;
+        let compiled = interop::normalize(&contents)
+            .map_err(|e| SprayError::FileFormatError(format!("{}: {e}", path.display())))?
+            .compiled;
+        let cmr = if compiled.cmr.is_empty() {
+            PrecompiledProgram::from_output(&compiled)?
+                .cmr()
+                .to_string()
+        } else {
+            compiled.cmr
+        };
+        return interop::derive_address(&cmr, params);
+    }
+    if target.len() == 64 && target.chars().all(|c| c.is_ascii_hexdigit()) {
+        return interop::derive_address(target, params);
+    }
+
+    let address = Address::from_str(target).map_err(|e| {
+        SprayError::ParseError(format!(
+            "'{target}' is not an address, a CMR or an artifact file: {e}"
+        ))
+    })?;
+    if address.params != params {
+        return Err(SprayError::ConfigError(format!(
+            "{target} is an address of another network"
+        )));
+    }
+    Ok(address)
+}
+
+/// The unspent outputs paying to `address`, and how they were found
+///
+/// `call` makes an RPC call against the node, e.g.
+/// [`NetworkBackend::call`](crate::network::NetworkBackend::call).
+///
+/// # Errors
+///
+/// Returns an error if the node cannot be queried.
+pub fn fetch(
+    call: impl Fn(&str, &[Value]) -> Result<Value, SprayError>,
+    address: &Address,
+) -> Result<(Vec<Holding>, Source), SprayError> {
+    let address = address.to_unconfidential();
+    let watched = call("getaddressinfo", &[address.to_string().into()]).is_ok_and(|info| {
+        info["ismine"].as_bool() == Some(true) || info["iswatchonly"].as_bool() == Some(true)
+    });
+    if watched {
+        // listunspent minconf maxconf addresses include_unsafe
+        let unspent = call(
+            "listunspent",
+            &[
+                0.into(),
+                9_999_999.into(),
+                json!([address.to_string()]),
+                true.into(),
+            ],
+        )?;
+        return Ok((from_listunspent(&unspent), Source::Wallet));
+    }
+
+    let script = hex::encode(address.script_pubkey().as_bytes());
+    let scan = call(
+        "scantxoutset",
+        &["start".into(), json!([format!("raw({script})")])],
+    )?;
+    let tip = call("getblockcount", &[])?
+        .as_u64()
+        .ok_or_else(|| SprayError::RpcError("Invalid getblockcount response".into()))?;
+    let mut holdings = from_scan(&scan, tip);
+
+    let mempool = call("getrawmempool", &[])?;
+    for txid in mempool.as_array().into_iter().flatten() {
+        // A transaction may leave the mempool between the two calls
+        if let Ok(tx) = call("getrawtransaction", &[txid.clone(), true.into()]) {
+            holdings.extend(from_mempool_tx(&tx, &script));
+        }
+    }
+    Ok((holdings, Source::UtxoSet))
+}
+
+/// Outputs in a `listunspent` response
+#[must_use]
+pub fn from_listunspent(unspent: &Value) -> Vec<Holding> {
+    unspent
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|entry| {
+            Some(Holding {
+                txid: entry["txid"].as_str()?.to_string(),
+                vout: u32::try_from(entry["vout"].as_u64()?).ok()?,
+                amount: entry["amount"].as_f64().map(btc_to_sats),
+                asset: entry["asset"].as_str().map(str::to_string),
+                confirmations: entry["confirmations"].as_u64().unwrap_or(0),
+            })
+        })
+        .collect()
+}
+
+/// Outputs in a `scantxoutset` response, confirmed below `tip`
+#[must_use]
+pub fn from_scan(scan: &Value, tip: u64) -> Vec<Holding> {
+    scan["unspents"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|entry| {
+            let height = entry["height"].as_u64()?;
+            Some(Holding {
+                txid: entry["txid"].as_str()?.to_string(),
+                vout: u32::try_from(entry["vout"].as_u64()?).ok()?,
+                amount: entry["amount"].as_f64().map(btc_to_sats),
+                asset: entry["asset"].as_str().map(str::to_string),
+                confirmations: (tip + 1).saturating_sub(height),
+            })
+        })
+        .collect()
+}
+
+/// Outputs of a decoded mempool transaction paying to `script` (hex)
+#[must_use]
+pub fn from_mempool_tx(tx: &Value, script: &str) -> Vec<Holding> {
+    let Some(txid) = tx["txid"].as_str() else {
+        return Vec::new();
+    };
+    tx["vout"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter(|output| output["scriptPubKey"]["hex"].as_str() == Some(script))
+        .filter_map(|output| {
+            Some(Holding {
+                txid: txid.to_string(),
+                vout: u32::try_from(output["n"].as_u64()?).ok()?,
+                amount: output["value"].as_f64().map(btc_to_sats),
+                asset: output["asset"].as_str().map(str::to_string),
+                confirmations: 0,
+            })
+        })
+        .collect()
+}
@@ -0,0 +1,207 @@
+//! Multi-file contracts
+//!
+//! SimplicityHL compiles a single source file. Larger contracts can be split
+//! across files with `include` directives, which spray resolves before
+//! compiling, so every command sees the contract as a unit:
+//!
+//! ```text
+//! include "helpers/checksig.simf";
+//!
+//! fn main() {
+//!     checksig(param::OWNER_PUBLIC_KEY, witness::OWNER_SIGNATURE);
+//! }
+//! ```
+//!
+//! A directive takes a line of its own. Its path is resolved relative to
+//! the including file, or else inside the [`INCLUDE_DIR`] directory of the
+//! working directory. The included source replaces the directive. Each file
+//! is spliced in once, where it is first included, so helpers can include
+//! what they need without duplicate definitions; an include cycle is an
+//! error.
+//!
+//! # Example
+//!
+//! ```
+//! use spray::include;
+//!
+//! let dir = std::env::temp_dir().join(format!("spray-include-doc-{}", std::process::id()));
+//! std::fs::create_dir_all(&dir).unwrap();
+//! std::fs::write(dir.join("helpers.simf"), "fn one() -> u8 { 1 }\n").unwrap();
+//! std::fs::write(
+//!     dir.join("main.simf"),
+//!     "include \"helpers.simf\";\nfn main() { assert!(jet::eq_8(one(), 1)); }\n",
+//! )
+//! .unwrap();
+//!
+//! let source = include::expand(&dir.join("main.simf")).unwrap();
+//! assert!(source.starts_with("fn one() -> u8 { 1 }\n"));
+//! assert_eq!(include::includes(&dir.join("main.simf")).unwrap().len(), 1);
+//! # std::fs::remove_dir_all(&dir).unwrap();
+//! ```
+
+use crate::error::SprayError;
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+
+/// Directory searched for included files not found next to the includer
+pub const INCLUDE_DIR: &str = "musk";
+
+/// Read `path` with its includes resolved
+///
+/// # Errors
+///
+/// Returns an error if a file cannot be read, an included file is not
+/// found, a directive is malformed, or the includes form a cycle.
+pub fn expand(path: &Path) -> Result<String, SprayError> {
+    let mut expander = Expander::default();
+    expander.file(path)?;
+    Ok(expander.out)
+}
+
+/// A source with its includes resolved, and where each of its lines came
+/// from
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Expanded {
+    /// The expanded source
+    pub source: String,
+    /// File and line number (1-based) of each line of `source`
+    pub origins: Vec<(PathBuf, usize)>,
+}
+
+/// Read `path` with its includes resolved, keeping track of the file and
+/// line each line of the result came from
+///
+/// # Errors
+///
+/// Returns the errors of [`expand`].
+pub fn expand_mapped(path: &Path) -> Result<Expanded, SprayError> {
+    let mut expander = Expander::default();
+    expander.file(path)?;
+    Ok(Expanded {
+        source: expander.out,
+        origins: expander.origins,
+    })
+}
+
+/// Resolve the includes of `source`, read from elsewhere than a file (e.g.
+/// standard input), relative to `dir`
+///
+/// # Errors
+///
+/// Returns the errors of [`expand`].
+pub fn expand_source(source: &str, dir: &Path) -> Result<String, SprayError> {
+    let mut expander = Expander::default();
+    expander.source(source, dir, Path::new("<stdin>"))?;
+    Ok(expander.out)
+}
+
+/// The files `path` includes, directly or indirectly, in order
+///
+/// # Errors
+///
+/// Returns the errors of [`expand`].
+pub fn includes(path: &Path) -> Result<Vec<PathBuf>, SprayError> {
+    let mut expander = Expander::default();
+    expander.file(path)?;
+    Ok(expander.included)
+}
+
+/// The path of an `include` directive, if `line` is one
+///
+/// # Errors
+///
+/// Returns an error if the line starts an include but is not of the form
+/// `include "<path>";`.
+pub fn parse_directive(line: &str) -> Result<Option<&str>, String> {
+    let Some(rest) = line.trim().strip_prefix("include") else {
+        return Ok(None);
+    };
+    if !rest.starts_with(char::is_whitespace) && !rest.starts_with('"') {
+        return Ok(None);
+    }
+    rest.trim()
+        .strip_suffix(';')
+        .map(str::trim_end)
+        .and_then(|quoted| quoted.strip_prefix('"')?.strip_suffix('"'))
+        .filter(|path| !path.is_empty())
+        .map(Some)
+        .ok_or_else(|| "expected include \"<path>\";".to_string())
+}
+
+/// Splices included files into one source
+#[derive(Default)]
+struct Expander {
+    out: String,
+    /// File and line of each line of `out`
+    origins: Vec<(PathBuf, usize)>,
+    /// Files being expanded, innermost last
+    stack: Vec<PathBuf>,
+    /// Files spliced in so far
+    seen: HashSet<PathBuf>,
+    /// Included files, in order
+    included: Vec<PathBuf>,
+}
+
+impl Expander {
+    fn file(&mut self, path: &Path) -> Result<(), SprayError> {
+        let canonical = path.canonicalize().unwrap_or_else(|_| path.to_path_buf());
+        if self.stack.contains(&canonical) {
+            let cycle: Vec<String> = self
+                .stack
+                .iter()
+                .chain([&canonical])
+                .map(|path| path.display().to_string())
+                .collect();
+            return Err(SprayError::ParseError(format!(
+                "Include cycle: {}",
+                cycle.join(" -> ")
+            )));
+        }
+        if !self.seen.insert(canonical.clone()) {
+            return Ok(());
+        }
+
+        let source = std::fs::read_to_string(path)?;
+        let dir = path.parent().unwrap_or_else(|| Path::new(""));
+        self.stack.push(canonical);
+        let result = self.source(&source, dir, path);
+        self.stack.pop();
+        result
+    }
+
+    fn source(&mut self, source: &str, dir: &Path, name: &Path) -> Result<(), SprayError> {
+        for (index, line) in source.lines().enumerate() {
+            let location = || format!("{}:{}", name.display(), index + 1);
+            match parse_directive(line) {
+                Ok(Some(include)) => {
+                    let path = resolve(include, dir).ok_or_else(|| {
+                        SprayError::ParseError(format!(
+                            "{}: included file {include} not found",
+                            location()
+                        ))
+                    })?;
+                    if !self.included.contains(&path) {
+                        self.included.push(path.clone());
+                    }
+                    self.file(&path)?;
+                }
+                Ok(None) => {
+                    self.out.push_str(line);
+                    self.out.push('\n');
+                    self.origins.push((name.to_path_buf(), index + 1));
+                }
+                Err(e) => {
+                    return Err(SprayError::ParseError(format!("{}: {e}", location())));
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Find an included file next to its includer, or in [`INCLUDE_DIR`]
+fn resolve(include: &str, dir: &Path) -> Option<PathBuf> {
+    [dir.join(include), Path::new(INCLUDE_DIR).join(include)]
+        .into_iter()
+        .find(|path| path.is_file())
+}
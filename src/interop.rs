@@ -0,0 +1,267 @@
+//! Interoperability checks against other Simplicity toolchains
+//!
+//! This module normalizes program artifacts produced by other toolchains
+//! (`hal-simplicity`, the SimplicityHL compiler `simc`, or spray itself) into
+//! a [`CompiledOutput`], and checks that the CMR, program encoding and
+//! addresses they claim match spray's own computation. Cross-toolchain
+//! mismatches are then caught before any funds are sent to an address.
+//!
+//! # Example
+//!
+//! ```
+//! use spray::interop::{normalize, ArtifactFormat};
+//!
+//! let artifact = normalize("Program:\nAA==\n").unwrap();
+//! assert_eq!(artifact.format, ArtifactFormat::Simc);
+//! assert_eq!(artifact.compiled.program, "AA==");
+//! assert_eq!(artifact.compiled.program_size, 1);
+//! ```
+
+use crate::compiled::{CompiledOutput, FORMAT_VERSION};
+use crate::error::SprayError;
+use crate::pset::SimplicityLeaf;
+use musk::elements::{Address, AddressParams};
+use serde_json::Value;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+
+/// Toolchain an artifact was produced by
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ArtifactFormat {
+    /// `spray compile` JSON output
+    Spray,
+    /// `hal-simplicity simplicity info` JSON output
+    HalSimplicity,
+    /// `simc` output, either text or `--json`
+    Simc,
+}
+
+impl fmt::Display for ArtifactFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Spray => write!(f, "spray"),
+            Self::HalSimplicity => write!(f, "hal-simplicity"),
+            Self::Simc => write!(f, "simc"),
+        }
+    }
+}
+
+/// An artifact from another toolchain, normalized into spray's format
+#[derive(Debug, Clone)]
+pub struct NormalizedArtifact {
+    /// Toolchain the artifact was produced by
+    pub format: ArtifactFormat,
+    /// Program data; `cmr` is empty if the artifact does not state one
+    pub compiled: CompiledOutput,
+    /// Addresses claimed by the artifact, keyed by network name
+    pub addresses: Vec<(String, String)>,
+}
+
+/// Outcome of comparing one value between spray and an artifact
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Check {
+    /// What was compared, e.g. `cmr` or `address (liquid)`
+    pub name: String,
+    /// Value computed by spray
+    pub spray: String,
+    /// Value stated by the artifact
+    pub artifact: String,
+}
+
+impl Check {
+    /// Whether both sides agree
+    #[must_use]
+    pub fn passed(&self) -> bool {
+        self.spray == self.artifact
+    }
+}
+
+/// Normalize an artifact produced by any supported toolchain
+///
+/// # Errors
+///
+/// Returns an error if the format is not recognized or the program is not
+/// valid base64.
+pub fn normalize(contents: &str) -> Result<NormalizedArtifact, SprayError> {
+    let Ok(json) = serde_json::from_str::<Value>(contents) else {
+        return normalize_simc_text(contents);
+    };
+
+    let string = |key: &str| json.get(key).and_then(Value::as_str).map(str::to_string);
+
+    if let Some(program) = string("commit_base64") {
+        let addresses = [
+            ("liquid", "liquid_address_unconf"),
+            ("testnet", "liquid_testnet_address_unconf"),
+        ]
+        .into_iter()
+        .filter_map(|(network, key)| string(key).map(|addr| (network.to_string(), addr)))
+        .collect();
+
+        return Ok(NormalizedArtifact {
+            format: ArtifactFormat::HalSimplicity,
+            compiled: compiled_output(string("cmr").unwrap_or_default(), program, None)?,
+            addresses,
+        });
+    }
+
+    if json.get("witness_types").is_some() {
+        return Ok(NormalizedArtifact {
+            format: ArtifactFormat::Spray,
+            compiled: serde_json::from_value(json)?,
+            addresses: Vec::new(),
+        });
+    }
+
+    if let Some(program) = string("program") {
+        return Ok(NormalizedArtifact {
+            format: ArtifactFormat::Simc,
+            compiled: compiled_output(
+                string("cmr").unwrap_or_default(),
+                program,
+                string("witness"),
+            )?,
+            addresses: Vec::new(),
+        });
+    }
+
+    Err(SprayError::FileFormatError(
+        "Unrecognized artifact: expected spray, hal-simplicity or simc output".into(),
+    ))
+}
+
+/// Normalize the text output of `simc`
+fn normalize_simc_text(contents: &str) -> Result<NormalizedArtifact, SprayError> {
+    let mut program = None;
+    let mut witness = None;
+    let mut lines = contents.lines().map(str::trim);
+    while let Some(line) = lines.next() {
+        match line {
+            "Program:" => program = lines.next().map(str::to_string),
+            "Witness:" => witness = lines.next().map(str::to_string),
+            _ => {}
+        }
+    }
+
+    let program = program.ok_or_else(|| {
+        SprayError::FileFormatError("Unrecognized artifact: no 'Program:' section".into())
+    })?;
+
+    Ok(NormalizedArtifact {
+        format: ArtifactFormat::Simc,
+        compiled: compiled_output(String::new(), program, witness)?,
+        addresses: Vec::new(),
+    })
+}
+
+/// Build a [`CompiledOutput`] from base64 program data
+fn compiled_output(
+    cmr: String,
+    program: String,
+    witness: Option<String>,
+) -> Result<CompiledOutput, SprayError> {
+    let mut compiled = CompiledOutput {
+        format_version: FORMAT_VERSION,
+        cmr: cmr.to_lowercase(),
+        program,
+        witness,
+        witness_types: HashMap::new(),
+        program_size: 0,
+        source: None,
+        addresses: BTreeMap::new(),
+        script_pubkey: String::new(),
+    };
+    compiled.program_size = compiled
+        .decode_program()
+        .map_err(|e| SprayError::FileFormatError(format!("Invalid program base64: {e}")))?
+        .len();
+    Ok(compiled.with_addresses())
+}
+
+/// Address parameters for a network name
+#[must_use]
+pub fn address_params(network: &str) -> Option<&'static AddressParams> {
+    match network {
+        "liquid" => Some(&AddressParams::LIQUID),
+        "testnet" => Some(&AddressParams::LIQUID_TESTNET),
+        "regtest" => Some(&AddressParams::ELEMENTS),
+        _ => None,
+    }
+}
+
+/// Derive the unconfidential address of a program from its CMR
+///
+/// # Errors
+///
+/// Returns an error if the CMR is not 32 bytes of hex or the taproot output
+/// cannot be built.
+pub fn derive_address(cmr: &str, params: &'static AddressParams) -> Result<Address, SprayError> {
+    let bytes = (0..cmr.len())
+        .step_by(2)
+        .map(|i| {
+            cmr.get(i..i + 2)
+                .and_then(|b| u8::from_str_radix(b, 16).ok())
+        })
+        .collect::<Option<Vec<u8>>>()
+        .and_then(|bytes| <[u8; 32]>::try_from(bytes).ok())
+        .ok_or_else(|| SprayError::ParseError(format!("Invalid CMR: {cmr}")))?;
+
+    let leaf = SimplicityLeaf::new(bytes)?;
+    Address::from_script(&leaf.script_pubkey, None, params)
+        .ok_or_else(|| SprayError::TestError("Cannot derive an address for the CMR".into()))
+}
+
+impl NormalizedArtifact {
+    /// Compare the artifact against spray's computation
+    ///
+    /// With `reference` (the same program compiled by spray), the CMR and
+    /// program encoding are compared. Every address the artifact claims is
+    /// compared against the address spray derives from the CMR: the
+    /// reference's if given, the artifact's otherwise.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if no CMR is available to derive addresses from.
+    pub fn check(&self, reference: Option<&CompiledOutput>) -> Result<Vec<Check>, SprayError> {
+        let mut checks = Vec::new();
+
+        if let Some(reference) = reference {
+            if !self.compiled.cmr.is_empty() {
+                checks.push(Check {
+                    name: "cmr".into(),
+                    spray: reference.cmr.clone(),
+                    artifact: self.compiled.cmr.clone(),
+                });
+            }
+            checks.push(Check {
+                name: "program".into(),
+                spray: reference.program.clone(),
+                artifact: self.compiled.program.clone(),
+            });
+        }
+
+        if self.addresses.is_empty() {
+            return Ok(checks);
+        }
+
+        let cmr = reference.map_or(self.compiled.cmr.as_str(), |r| r.cmr.as_str());
+        if cmr.is_empty() {
+            return Err(SprayError::FileFormatError(
+                "Artifact states no CMR; pass the program source to check its addresses".into(),
+            ));
+        }
+
+        for (network, claimed) in &self.addresses {
+            let params = address_params(network).ok_or_else(|| {
+                SprayError::FileFormatError(format!("Unknown network '{network}'"))
+            })?;
+            checks.push(Check {
+                name: format!("address ({network})"),
+                spray: derive_address(cmr, params)?.to_string(),
+                artifact: claimed.clone(),
+            });
+        }
+
+        Ok(checks)
+    }
+}
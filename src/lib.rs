@@ -21,25 +21,125 @@
 //!
 //! let result = test.run()?;
 //! ```
+//!
+//! # Features
+//!
+//! - `daemon` (default): ephemeral regtest daemons through `elementsd`;
+//!   enables [`TestEnv`], [`TestCase`], [`TestRunner`] and
+//!   [`NetworkBackend::Ephemeral`]
+//! - `cli` (default, implies `daemon`): the `spray` binary and the
+//!   [`commands`] module
+//! - `examples` (default): the bundled example programs of [`gallery`]
+//! - `tokio`: async variants of node clients and the test runner, see
+//!   [`nonblocking`]
+//! - `wasm`: randomness from the browser's crypto API, for
+//!   `wasm32-unknown-unknown` builds
+//!
+//! The semver-stable part of the API is gathered in [`core`].
+//!
+//! Building with `default-features = false` leaves artifact handling, spend
+//! construction, local verification and external-node backends, without the
+//! daemon management stack. That subset builds for targets elementsd cannot
+//! run on, such as `wasm32-unknown-unknown` (with `wasm`) and musl.
+
+#[cfg(all(feature = "daemon", target_arch = "wasm32"))]
+compile_error!(
+    "the `daemon` feature runs elementsd and is not available on wasm32; \
+     build with `default-features = false`"
+);
 
+pub mod alerts;
+pub mod audit;
+pub mod bench;
+pub mod binary;
+pub mod budget;
+pub mod cache;
+pub mod chain;
 pub mod client;
+pub mod compat;
 pub mod compiled;
+pub mod confidential;
+pub mod core;
+pub mod coverage;
+pub mod dashboard;
+pub mod destination;
+pub mod deterministic;
+pub mod disasm;
+#[cfg(feature = "daemon")]
 pub mod env;
 pub mod error;
+pub mod eta;
+pub mod events;
+pub mod fee;
 pub mod file_loader;
+pub mod forensics;
+pub mod funding;
+pub mod fuzz;
+#[cfg(feature = "examples")]
+pub mod gallery;
+pub mod gc;
+pub mod hex;
+pub mod history;
+pub mod holdings;
+pub mod include;
+pub mod interop;
+pub mod logging;
+pub mod manifest;
+pub mod messages;
+pub mod monitor;
 pub mod network;
+pub mod nigiri;
+#[cfg(feature = "tokio")]
+pub mod nonblocking;
+pub mod output;
+pub mod package;
+pub mod policy;
+pub mod precompiled;
+pub mod preset;
+pub mod project;
+pub mod prompt;
+pub mod property;
+pub mod pset;
+pub mod registry;
+pub mod report;
+pub mod rescan;
+pub mod retry;
+#[cfg(feature = "daemon")]
 pub mod runner;
+pub mod scaffold;
+pub mod serve;
+pub mod sighash;
+pub mod signing;
+pub mod soak;
+pub mod sourcemap;
+pub mod stress;
+pub mod sweep;
+pub mod template;
 pub mod test;
+pub mod trace;
+pub mod utxo;
+pub mod value;
+pub mod vcr;
+pub mod verify;
+pub mod watcher;
+pub mod witness;
 
+#[cfg(feature = "cli")]
 pub mod commands;
 
 // Re-export main types
 pub use compiled::CompiledOutput;
-pub use env::TestEnv;
+#[cfg(feature = "daemon")]
+pub use env::{TestEnv, TestEnvBuilder};
 pub use error::SprayError;
-pub use network::{create_backend, NetworkBackend};
+pub use fee::FeePolicy;
+pub use network::{create_backend, BackendConfig, NetworkBackend};
+pub use report::SuiteReport;
+#[cfg(feature = "daemon")]
 pub use runner::TestRunner;
-pub use test::{TestCase, TestResult};
+#[cfg(feature = "daemon")]
+pub use test::TestCase;
+pub use test::{FailureStage, SpendDetails, TestResult};
 
 // Re-export musk for convenience
 pub use musk;
@@ -0,0 +1,57 @@
+//! Structured logging
+//!
+//! Besides its human-readable output, spray emits [`tracing`] spans and
+//! events: a span per `compile`, `deploy` and `redeem` command with the
+//! program's CMR and the transaction's txid, a span per test run by a
+//! [`TestRunner`](crate::TestRunner) with its outcome, and events for the
+//! lifecycle of [`TestEnv`](crate::TestEnv) daemons. Library consumers
+//! capture them with any `tracing` subscriber; the CLI prints them to stderr
+//! with `--log-level <filter>` or `RUST_LOG`.
+//!
+//! # Example
+//!
+//! ```
+//! use spray::logging::elapsed_ms;
+//! use std::time::Instant;
+//!
+//! let start = Instant::now();
+//! assert!(elapsed_ms(start) < 60_000);
+//! ```
+
+use std::time::Instant;
+
+/// Milliseconds since `start`, for `duration_ms` fields
+#[must_use]
+pub fn elapsed_ms(start: Instant) -> u64 {
+    u64::try_from(start.elapsed().as_millis()).unwrap_or(u64::MAX)
+}
+
+/// Print spray's spans and events to stderr, filtered by `filter` (e.g.
+/// `"spray=debug"`) or else by `RUST_LOG`
+///
+/// Without either, nothing is installed, so that human-readable output is
+/// not interleaved with log lines.
+///
+/// # Errors
+///
+/// Returns an error if the filter is invalid or a subscriber is already
+/// installed.
+#[cfg(feature = "cli")]
+pub fn init(filter: Option<&str>) -> Result<(), crate::SprayError> {
+    use tracing_subscriber::EnvFilter;
+
+    let filter = match filter {
+        Some(filter) => EnvFilter::try_new(filter),
+        None if std::env::var_os(EnvFilter::DEFAULT_ENV).is_some() => {
+            EnvFilter::try_from_default_env()
+        }
+        None => return Ok(()),
+    }
+    .map_err(|e| crate::SprayError::ConfigError(format!("Invalid log filter: {e}")))?;
+
+    tracing_subscriber::fmt()
+        .with_env_filter(filter)
+        .with_writer(std::io::stderr)
+        .try_init()
+        .map_err(|e| crate::SprayError::ConfigError(e.to_string()))
+}
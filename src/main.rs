@@ -1,14 +1,72 @@
 //! Spray CLI - Testing workbench for Simplicity programs
 
-use clap::{Parser, Subcommand, ValueEnum};
+use clap::{CommandFactory, FromArgMatches, Parser, Subcommand, ValueEnum};
 use colored::Colorize;
-use spray::{commands, musk, SprayError, TestCase, TestRunner};
+use spray::error::ExitCode;
+use spray::network::BackendConfig;
+use spray::output::Format;
+use spray::project::{ProjectDefaults, CONFIG_ENV, NETWORK_ENV};
+use spray::{commands, musk, FeePolicy, SprayError, TestCase, TestEnvBuilder, TestRunner};
+use std::ffi::{OsStr, OsString};
 use std::path::PathBuf;
+use std::time::Duration;
 
 #[derive(Parser)]
 #[command(name = "spray")]
 #[command(about = "Testing workbench for Simplicity programs", long_about = None)]
 struct Cli {
+    /// Print a single JSON document to stdout, and everything else to stderr
+    /// (compile, deploy, redeem and test)
+    #[arg(long, global = true)]
+    json: bool,
+
+    /// Record the command's node traffic to a fixture file
+    #[arg(long, global = true, value_name = "FILE", conflicts_with = "replay")]
+    record: Option<PathBuf>,
+
+    /// Answer the command's node requests from a fixture recorded with
+    /// --record, without a node
+    #[arg(long, global = true, value_name = "FILE")]
+    replay: Option<PathBuf>,
+
+    /// Fix seeds, wallet keys, the node's clock and funding so that runs
+    /// produce identical txids
+    #[arg(long, global = true)]
+    deterministic: bool,
+
+    /// Node of regtest commands without --config: a fresh ephemeral
+    /// elementsd, a running Nigiri, or Nigiri if it is running
+    #[arg(long, global = true, value_enum, default_value_t = BackendArg::Ephemeral)]
+    backend: BackendArg,
+
+    /// Print structured logs to stderr, filtered like RUST_LOG (e.g.
+    /// "spray=debug"); RUST_LOG is used if not given
+    #[arg(long, global = true, value_name = "FILTER")]
+    log_level: Option<String>,
+
+    /// Drop progress and other decorative output; results and errors are
+    /// still printed
+    #[arg(short, long, global = true)]
+    quiet: bool,
+
+    /// Print no ANSI colors (also off with NO_COLOR, or when not writing to
+    /// a terminal)
+    #[arg(long, global = true)]
+    no_color: bool,
+
+    /// Wallet of the external node to use, overriding the config's rpcwallet
+    #[arg(long, global = true, value_name = "NAME")]
+    wallet: Option<String>,
+
+    /// Retries of an RPC call to an external node that timed out or hit a
+    /// transient server error
+    #[arg(long, global = true, default_value_t = spray::retry::DEFAULT_RPC_RETRIES)]
+    rpc_retries: u32,
+
+    /// Milliseconds to wait before the first RPC retry, doubled after each
+    #[arg(long, global = true, value_name = "MS", default_value_t = spray::retry::DEFAULT_RPC_INITIAL_DELAY_MS)]
+    rpc_retry_delay: u64,
+
     #[command(subcommand)]
     command: Commands,
 }
@@ -30,6 +88,42 @@ impl From<NetworkArg> for musk::Network {
     }
 }
 
+#[derive(Clone, Copy, Debug, ValueEnum)]
+enum BackendArg {
+    Ephemeral,
+    Nigiri,
+    Auto,
+}
+
+impl From<BackendArg> for spray::nigiri::BackendKind {
+    fn from(arg: BackendArg) -> Self {
+        match arg {
+            BackendArg::Ephemeral => Self::Ephemeral,
+            BackendArg::Nigiri => Self::Nigiri,
+            BackendArg::Auto => Self::Auto,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, ValueEnum)]
+enum TemplateArg {
+    P2pk,
+    Htlc,
+    Vault,
+    Ctv,
+}
+
+impl From<TemplateArg> for spray::template::Template {
+    fn from(arg: TemplateArg) -> Self {
+        match arg {
+            TemplateArg::P2pk => Self::P2pk,
+            TemplateArg::Htlc => Self::Htlc,
+            TemplateArg::Vault => Self::Vault,
+            TemplateArg::Ctv => Self::Ctv,
+        }
+    }
+}
+
 #[derive(Clone, Copy, Debug, ValueEnum)]
 enum OutputFormat {
     Json,
@@ -41,13 +135,17 @@ enum OutputFormat {
 enum Commands {
     /// Compile a Simplicity program
     Compile {
-        /// Path to the .simf program file
+        /// Path to the .simf program file, or `-` to read from stdin
         file: PathBuf,
 
         /// Path to arguments file (JSON or TOML)
         #[arg(short, long)]
         args: Option<PathBuf>,
 
+        /// Argument value merged over the arguments file (repeatable)
+        #[arg(long = "arg", value_name = "KEY=VALUE", value_parser = parse_override)]
+        arg: Vec<(String, String)>,
+
         /// Path to witness file (JSON or TOML)
         #[arg(short, long)]
         witness: Option<PathBuf>,
@@ -56,9 +154,28 @@ enum Commands {
         #[arg(short, long, value_enum, default_value = "json")]
         output: OutputFormat,
 
+        /// Write the compiled artifact to this file instead of stdout
+        #[arg(long, value_name = "FILE", value_parser = parse_out_file)]
+        out: Option<PathBuf>,
+
+        /// Always compile, bypassing the compile cache
+        #[arg(long)]
+        no_cache: bool,
+
         /// Network (for address generation)
-        #[arg(short, long, value_enum, default_value = "regtest")]
+        #[arg(
+            short,
+            long,
+            value_enum,
+            default_value = "regtest",
+            env = "SPRAY_NETWORK"
+        )]
         network: NetworkArg,
+
+        /// Node config whose `[chain]` table describes a custom chain (for
+        /// address generation)
+        #[arg(short, long, env = "SPRAY_CONFIG")]
+        config: Option<PathBuf>,
     },
 
     /// Deploy a program to the network
@@ -70,32 +187,85 @@ enum Commands {
         #[arg(short, long)]
         args: Option<PathBuf>,
 
-        /// Amount to fund (in satoshis)
-        #[arg(long, default_value = "100000000")]
-        amount: u64,
+        /// Argument value merged over the arguments file (repeatable)
+        #[arg(long = "arg", value_name = "KEY=VALUE", value_parser = parse_override)]
+        arg: Vec<(String, String)>,
+
+        /// Amount to fund in satoshis (default: the network preset's)
+        #[arg(long)]
+        amount: Option<u64>,
 
-        /// Asset ID (hex)
+        /// Asset to fund with: asset ID (hex) or node asset label
+        /// (defaults to the policy asset)
         #[arg(long)]
         asset: Option<String>,
 
         /// Network
-        #[arg(short, long, value_enum, default_value = "regtest")]
+        #[arg(
+            short,
+            long,
+            value_enum,
+            default_value = "regtest",
+            env = "SPRAY_NETWORK"
+        )]
         network: NetworkArg,
 
         /// Config file (required for testnet/liquid)
-        #[arg(short, long)]
+        #[arg(short, long, env = "SPRAY_CONFIG")]
         config: Option<PathBuf>,
+
+        /// Deployment registry file
+        #[arg(long, default_value = spray::registry::DEFAULT_REGISTRY_FILE)]
+        registry: PathBuf,
+
+        /// Always fund a new UTXO, even if an identical deployment is pending
+        #[arg(long)]
+        no_resume: bool,
+
+        /// Wait for the funding transaction to confirm
+        #[arg(long)]
+        wait: bool,
+
+        /// Build and sign the funding transaction without broadcasting it
+        #[arg(long)]
+        dry_run: bool,
+
+        /// Always compile, bypassing the compile cache
+        #[arg(long)]
+        no_cache: bool,
     },
 
     /// Redeem from a program UTXO
     Redeem {
         /// UTXO reference in format "txid:vout"
-        utxo: String,
+        #[arg(required_unless_present = "interactive")]
+        utxo: Option<String>,
 
-        /// Path to witness file (JSON or TOML)
-        witness: PathBuf,
+        /// Path to witness file (JSON or TOML; not needed with --export-pset)
+        #[arg(required_unless_present_any = ["interactive", "export_pset"])]
+        witness: Option<PathBuf>,
+
+        /// Choose a deployment, witness values and fee step by step
+        #[arg(
+            long,
+            conflicts_with_all = [
+                "utxo", "witness", "wit", "compiled", "dest", "fee", "feerate", "conf_target",
+                "blinding_key", "blind", "export_pset", "dry_run"
+            ]
+        )]
+        interactive: bool,
+
+        /// Keystore directory for signing witness values (with --interactive)
+        #[arg(long, default_value = spray::signing::DEFAULT_KEYSTORE_DIR, requires = "interactive")]
+        keystore: PathBuf,
+
+        /// Witness value merged over the witness file (repeatable)
+        #[arg(long = "wit", value_name = "KEY=VALUE", value_parser = parse_override)]
+        wit: Vec<(String, String)>,
 
         /// Path to compiled program file (.json with source)
+        ///
+        /// Defaults to the program recorded for the UTXO in the registry.
         #[arg(short, long)]
         compiled: Option<PathBuf>,
 
@@ -103,206 +273,2096 @@ enum Commands {
         #[arg(short, long)]
         dest: Option<String>,
 
-        /// Fee in satoshis
-        #[arg(short, long, default_value = "3000")]
-        fee: u64,
+        /// Fee in satoshis (default: the network preset's)
+        #[arg(short, long, conflicts_with_all = ["feerate", "conf_target"])]
+        fee: Option<u64>,
+
+        /// Fee rate in sat/vB, applied to the finalized transaction size
+        #[arg(long, conflicts_with = "conf_target", value_parser = parse_feerate)]
+        feerate: Option<f64>,
+
+        /// Confirmation target in blocks for the node's fee rate estimate
+        #[arg(long)]
+        conf_target: Option<u16>,
+
+        /// Blinding private key (hex) for a confidential UTXO
+        ///
+        /// Defaults to the key held by the node's wallet, if any.
+        #[arg(long)]
+        blinding_key: Option<String>,
+
+        /// Blind the destination output (requires a confidential address)
+        #[arg(long)]
+        blind: bool,
+
+        /// Write the unsigned spend as a v2 PSET instead of broadcasting
+        /// (pays a fixed fee)
+        #[arg(long, value_name = "FILE", conflicts_with_all = ["feerate", "conf_target"])]
+        export_pset: Option<PathBuf>,
+
+        /// Build and finalize the spend without broadcasting it
+        #[arg(long)]
+        dry_run: bool,
 
         /// Network
-        #[arg(short, long, value_enum, default_value = "regtest")]
+        #[arg(
+            short,
+            long,
+            value_enum,
+            default_value = "regtest",
+            env = "SPRAY_NETWORK"
+        )]
         network: NetworkArg,
 
         /// Config file (required for testnet/liquid)
-        #[arg(short, long)]
+        #[arg(short, long, env = "SPRAY_CONFIG")]
         config: Option<PathBuf>,
+
+        /// Deployment registry file
+        #[arg(long, default_value = spray::registry::DEFAULT_REGISTRY_FILE)]
+        registry: PathBuf,
+
+        /// Retries of a broadcast rejected for missing inputs (external nodes)
+        #[arg(long, default_value_t = spray::retry::DEFAULT_RETRIES)]
+        broadcast_retries: u32,
+
+        /// Seconds to wait before the first broadcast retry, doubled after each
+        #[arg(long, default_value_t = spray::retry::DEFAULT_INITIAL_DELAY.as_secs())]
+        retry_delay: u64,
     },
 
-    /// Test a Simplicity program (compile + deploy + redeem)
-    Test {
-        /// Path to the .simf program file
+    /// Compute the sighash of a spend and explain its committed fields
+    Sighash {
+        /// UTXO to spend (txid:vout)
+        utxo: String,
+
+        /// Path to compiled program (.json)
+        #[arg(long)]
+        compiled: PathBuf,
+
+        /// Destination address (default: new wallet address)
         #[arg(short, long)]
-        file: PathBuf,
+        dest: Option<String>,
 
-        /// Path to arguments file (JSON or TOML)
+        /// Fee in satoshis (default: the network preset's)
         #[arg(short, long)]
-        args: Option<PathBuf>,
+        fee: Option<u64>,
 
-        /// Path to witness file (JSON or TOML)
+        /// Print every field the sighash commits to
+        #[arg(long)]
+        explain: bool,
+
+        /// Compare the committed fields against an explanation (.json)
+        #[arg(long)]
+        compare: Option<PathBuf>,
+
+        /// Write the explanation as JSON to this path
         #[arg(short, long)]
-        witness: Option<PathBuf>,
+        out: Option<PathBuf>,
 
-        /// Test name
-        #[arg(short, long, default_value = "Program test")]
-        name: String,
+        /// Network
+        #[arg(
+            short,
+            long,
+            value_enum,
+            default_value = "regtest",
+            env = "SPRAY_NETWORK"
+        )]
+        network: NetworkArg,
 
-        /// Lock time for the spending transaction
+        /// Config file (required for testnet/liquid)
+        #[arg(short, long, env = "SPRAY_CONFIG")]
+        config: Option<PathBuf>,
+    },
+
+    /// Recompute the sighash of a past spend and identify its signers
+    Forensics {
+        /// Spending transaction id
+        txid: String,
+
+        /// Path to the contract (.simf or compiled .json)
         #[arg(long)]
-        lock_time: Option<u32>,
+        contract: PathBuf,
 
-        /// Sequence number for the spending transaction
+        /// Path to arguments file (JSON or TOML)
+        #[arg(short, long)]
+        args: Option<PathBuf>,
+
+        /// Index of the contract input (default: the first spending the contract)
         #[arg(long)]
-        sequence: Option<u32>,
+        input: Option<u32>,
 
-        /// Network (currently only regtest is supported for test command)
-        #[arg(long, value_enum, default_value = "regtest")]
+        /// Declared key, as NAME=PUBKEY or a bare x-only public key (repeatable)
+        #[arg(short, long = "key")]
+        keys: Vec<String>,
+
+        /// Write the analysis as JSON to this path
+        #[arg(short, long)]
+        out: Option<PathBuf>,
+
+        /// Network
+        #[arg(
+            short,
+            long,
+            value_enum,
+            default_value = "regtest",
+            env = "SPRAY_NETWORK"
+        )]
         network: NetworkArg,
 
-        /// Verbose output
+        /// Config file (required for testnet/liquid)
+        #[arg(short, long, env = "SPRAY_CONFIG")]
+        config: Option<PathBuf>,
+    },
+
+    /// Import a contract address into the node's wallet and rescan for it
+    Import {
+        /// Path to the program (.simf or compiled .json)
+        file: PathBuf,
+
+        /// Path to arguments file (JSON or TOML)
         #[arg(short, long)]
-        verbose: bool,
+        args: Option<PathBuf>,
+
+        /// Rescan from this height instead of the recorded deployment height
+        #[arg(long, conflicts_with = "no_rescan")]
+        from_height: Option<u64>,
+
+        /// Blocks rescanned per step
+        #[arg(long, default_value_t = spray::rescan::DEFAULT_CHUNK_SIZE)]
+        chunk_size: u64,
+
+        /// Import without rescanning
+        #[arg(long)]
+        no_rescan: bool,
+
+        /// Network
+        #[arg(
+            short,
+            long,
+            value_enum,
+            default_value = "regtest",
+            env = "SPRAY_NETWORK"
+        )]
+        network: NetworkArg,
+
+        /// Config file (required for testnet/liquid)
+        #[arg(short, long, env = "SPRAY_CONFIG")]
+        config: Option<PathBuf>,
+
+        /// Deployment registry file
+        #[arg(long, default_value = spray::registry::DEFAULT_REGISTRY_FILE)]
+        registry: PathBuf,
     },
 
-    /// Start an interactive REPL
-    Repl,
+    /// Measure compile time, satisfaction time, size and cost of programs
+    Bench {
+        /// Programs to benchmark (.simf)
+        #[arg(required = true)]
+        files: Vec<PathBuf>,
 
-    /// Manage Elements regtest daemon
-    Daemon,
+        /// Path to arguments file (JSON or TOML; default: the companion file)
+        #[arg(short, long)]
+        args: Option<PathBuf>,
 
-    /// Initialize a new Simplicity project
-    Init {
-        /// Overwrite existing musk.conf if present
+        /// Path to witness file (JSON or TOML; default: the companion file)
         #[arg(short, long)]
-        force: bool,
+        witness: Option<PathBuf>,
+
+        /// Number of times each program is compiled and satisfied
+        #[arg(long, default_value_t = spray::bench::DEFAULT_ITERATIONS)]
+        iterations: usize,
+
+        /// Write the results as JSON to this file
+        #[arg(long)]
+        report: Option<PathBuf>,
     },
-}
 
-#[allow(clippy::too_many_lines)]
-fn main() -> Result<(), SprayError> {
-    let cli = Cli::parse();
+    /// Deploy and redeem many instances of a contract on regtest
+    Stress {
+        /// Path to the contract (.simf or compiled .json)
+        #[arg(long)]
+        contract: PathBuf,
 
-    match cli.command {
-        Commands::Compile {
-            file,
-            args,
-            witness,
-            output,
-            network,
-        } => {
-            let output_fmt = match output {
-                OutputFormat::Json => commands::compile::OutputFormat::Json,
-                OutputFormat::Base64 => commands::compile::OutputFormat::Base64,
-                OutputFormat::Hex => commands::compile::OutputFormat::Hex,
-            };
-            commands::compile_command(&file, args, witness, output_fmt, network.into())?;
-        }
+        /// Path to arguments file (JSON or TOML)
+        #[arg(short, long)]
+        args: Option<PathBuf>,
 
-        Commands::Deploy {
-            file,
-            args,
-            amount,
-            asset,
-            network,
-            config,
-        } => {
-            commands::deploy_command(&file, args, Some(amount), asset, network.into(), config)?;
-        }
+        /// Path to witness file (JSON or TOML)
+        #[arg(short, long)]
+        witness: Option<PathBuf>,
 
-        Commands::Redeem {
-            utxo,
-            witness,
-            compiled,
-            dest,
-            fee,
-            network,
-            config,
-        } => {
-            commands::redeem_command(
-                &utxo,
-                &witness,
-                compiled,
-                dest,
-                Some(fee),
-                network.into(),
-                config,
-            )?;
-        }
+        /// Number of instances to deploy and redeem
+        #[arg(long, default_value_t = spray::stress::DEFAULT_COUNT)]
+        count: usize,
 
-        Commands::Test {
-            file,
-            args,
-            witness,
-            name,
-            lock_time,
-            sequence,
-            network,
-            verbose,
-        } => {
-            // Only regtest is supported for test command
-            if !matches!(network, NetworkArg::Regtest) {
-                return Err(SprayError::ConfigError(
-                    "Test command currently only supports --network regtest".into(),
-                ));
-            }
+        /// Number of instances run in parallel
+        #[arg(long, default_value_t = spray::stress::DEFAULT_CONCURRENCY)]
+        concurrency: usize,
 
-            if verbose {
-                println!("{}", "Initializing test environment...".dimmed());
-            }
+        /// Write the full report as JSON to this file
+        #[arg(long)]
+        report: Option<PathBuf>,
+    },
 
-            let runner = TestRunner::new()?;
+    /// Mutate a valid witness and check that every mutant is rejected
+    Fuzz {
+        /// Path to the contract (.simf or compiled .json)
+        #[arg(long)]
+        contract: PathBuf,
 
-            if verbose {
-                println!("{}", "Loading program...".dimmed());
-            }
+        /// Path to arguments file (JSON or TOML)
+        #[arg(short, long)]
+        args: Option<PathBuf>,
 
-            // Load program
-            let program = musk::Program::from_file(&file)?;
+        /// Path to a witness file the contract accepts (JSON or TOML)
+        #[arg(short, long)]
+        witness: PathBuf,
 
-            // Load arguments if provided
-            let arguments = if let Some(args_path) = args {
-                if verbose {
-                    println!(
-                        "{} {}",
-                        "Loading arguments from:".dimmed(),
-                        args_path.display()
-                    );
-                }
-                spray::file_loader::load_arguments(&args_path)?
-            } else {
-                musk::Arguments::default()
-            };
+        /// Number of mutants to try
+        #[arg(long, default_value_t = spray::fuzz::DEFAULT_ITERATIONS)]
+        iterations: usize,
 
-            // Compile program
-            let compiled = program.instantiate(arguments)?;
+        /// Seed for the mutations, to reproduce a run
+        #[arg(long)]
+        seed: Option<u64>,
 
-            // Create witness function
-            let witness_fn: Box<dyn Fn([u8; 32]) -> musk::WitnessValues> =
-                if let Some(witness_path) = witness {
-                    // Load witness from file
-                    let witness_values = spray::file_loader::load_witness(&witness_path)?;
-                    Box::new(move |_sighash| witness_values.clone())
-                } else {
-                    // Empty witness
-                    Box::new(|_sighash| musk::WitnessValues::default())
-                };
+        /// Write the report as JSON to this file
+        #[arg(long)]
+        report: Option<PathBuf>,
+    },
 
-            // Create test case
-            let mut test = TestCase::new(runner.env(), compiled).name(&name);
+    /// Stress test a contract while restarting, reorging and evicting
+    Soak {
+        /// Path to the contract (.simf or compiled .json)
+        #[arg(long)]
+        contract: PathBuf,
 
-            test = test.witness(witness_fn);
+        /// Path to arguments file (JSON or TOML)
+        #[arg(short, long)]
+        args: Option<PathBuf>,
 
-            if let Some(lt) = lock_time {
-                test = test.lock_time(musk::elements::LockTime::from_consensus(lt));
-            }
+        /// Path to witness file (JSON or TOML)
+        #[arg(short, long)]
+        witness: Option<PathBuf>,
 
-            if let Some(seq) = sequence {
-                test = test.sequence(musk::elements::Sequence::from_consensus(seq));
-            }
+        /// Number of instances to deploy and redeem
+        #[arg(long, default_value_t = spray::stress::DEFAULT_COUNT)]
+        count: usize,
 
-            // Run test
-            let result = runner.run_test(test);
+        /// Number of instances run in parallel
+        #[arg(long, default_value_t = spray::stress::DEFAULT_CONCURRENCY)]
+        concurrency: usize,
 
-            if result.is_failure() {
-                std::process::exit(1);
-            }
-        }
+        /// Faults to inject in turn: restart, reorg:<depth> or evict
+        #[arg(
+            long,
+            value_delimiter = ',',
+            default_value = "restart,reorg:1,evict",
+            value_parser = parse_fault
+        )]
+        faults: Vec<spray::soak::Fault>,
+
+        /// Rounds between two faults
+        #[arg(long, default_value_t = spray::soak::DEFAULT_FAULT_INTERVAL)]
+        fault_every: usize,
+
+        /// Rebroadcast-and-mine attempts before unconfirmed spends fail the run
+        #[arg(long, default_value_t = spray::soak::DEFAULT_RECOVERY_ATTEMPTS)]
+        recovery_attempts: u32,
+
+        /// Write the full report as JSON to this file
+        #[arg(long)]
+        report: Option<PathBuf>,
+    },
+
+    /// Decode the events a contract's transactions carry in OP_RETURN outputs
+    Events {
+        /// Path to the program (.simf or compiled .json)
+        file: PathBuf,
+
+        /// Path to arguments file (JSON or TOML)
+        #[arg(short, long)]
+        args: Option<PathBuf>,
+
+        /// Event schema (JSON or TOML)
+        #[arg(short, long)]
+        schema: PathBuf,
+
+        /// Scan from this height instead of the recorded deployment height
+        #[arg(long)]
+        from_height: Option<u64>,
+
+        /// Write the events to this file instead of stdout
+        #[arg(short, long)]
+        out: Option<PathBuf>,
+
+        /// Network
+        #[arg(
+            short,
+            long,
+            value_enum,
+            default_value = "regtest",
+            env = "SPRAY_NETWORK"
+        )]
+        network: NetworkArg,
+
+        /// Config file (required for testnet/liquid)
+        #[arg(short, long, env = "SPRAY_CONFIG")]
+        config: Option<PathBuf>,
+
+        /// Deployment registry file
+        #[arg(long, default_value = spray::registry::DEFAULT_REGISTRY_FILE)]
+        registry: PathBuf,
+    },
+
+    /// Follow the chain and raise alerts on contract events
+    Monitor {
+        /// Rules file naming the contracts to watch and the alert rules (TOML)
+        #[arg(short, long)]
+        rules: PathBuf,
+
+        /// Replay the chain from this height instead of starting at the tip
+        #[arg(long)]
+        from_height: Option<u64>,
+
+        /// Seconds between polls of the chain tip
+        #[arg(long, default_value_t = 5)]
+        interval: u64,
+
+        /// Stop once the chain tip is reached
+        #[arg(long)]
+        once: bool,
+
+        /// Network
+        #[arg(
+            short,
+            long,
+            value_enum,
+            default_value = "regtest",
+            env = "SPRAY_NETWORK"
+        )]
+        network: NetworkArg,
+
+        /// Config file (required for testnet/liquid)
+        #[arg(short, long, env = "SPRAY_CONFIG")]
+        config: Option<PathBuf>,
+    },
+
+    /// Show which recorded deployments were unspent at a block height
+    Status {
+        /// Reconstruct the status at this height (default: chain tip)
+        #[arg(long)]
+        at_height: Option<u64>,
+
+        /// Only show deployments of the program with this CMR
+        #[arg(long)]
+        cmr: Option<String>,
+
+        /// Network
+        #[arg(
+            short,
+            long,
+            value_enum,
+            default_value = "regtest",
+            env = "SPRAY_NETWORK"
+        )]
+        network: NetworkArg,
+
+        /// Config file (required for testnet/liquid)
+        #[arg(short, long, env = "SPRAY_CONFIG")]
+        config: Option<PathBuf>,
+
+        /// Deployment registry file
+        #[arg(long, default_value = spray::registry::DEFAULT_REGISTRY_FILE)]
+        registry: PathBuf,
+    },
+
+    /// Check an artifact from another Simplicity toolchain against spray
+    Interop {
+        /// Artifact file (spray, hal-simplicity or simc output)
+        artifact: PathBuf,
+
+        /// Program source to compile as the reference
+        #[arg(short, long)]
+        source: Option<PathBuf>,
+
+        /// Path to arguments file for the reference (JSON or TOML)
+        #[arg(short, long, requires = "source")]
+        args: Option<PathBuf>,
+
+        /// Write the normalized artifact as spray JSON to this path
+        #[arg(short, long)]
+        out: Option<PathBuf>,
+    },
+
+    /// Run paired spends against a contract and an equivalent descriptor
+    ComparePolicy {
+        /// Output descriptor claiming the same semantics, e.g. "eltr(...)"
+        ///
+        /// Private keys in the descriptor are used by the wallet to sign.
+        #[arg(long)]
+        descriptor: String,
+
+        /// Path to the .simf program file
+        #[arg(short, long)]
+        file: PathBuf,
+
+        /// Path to arguments file (JSON or TOML)
+        #[arg(short, long)]
+        args: Option<PathBuf>,
+
+        /// Cases file (TOML) listing the paired spends
+        #[arg(long)]
+        cases: PathBuf,
+
+        /// Amount to fund each side with, in satoshis
+        #[arg(long, default_value = "100000")]
+        amount: u64,
+
+        /// Fee in satoshis (default: the network preset's)
+        #[arg(long)]
+        fee: Option<u64>,
+
+        /// Network
+        #[arg(
+            short,
+            long,
+            value_enum,
+            default_value = "regtest",
+            env = "SPRAY_NETWORK"
+        )]
+        network: NetworkArg,
+
+        /// Config file (required for testnet/liquid)
+        #[arg(short, long, env = "SPRAY_CONFIG")]
+        config: Option<PathBuf>,
+    },
+
+    /// Move all funds locked by one contract version into another
+    MigrateFunds {
+        /// Compiled artifact of the old contract (.json with source)
+        #[arg(long)]
+        from: PathBuf,
+
+        /// Arguments file for the old contract
+        #[arg(long)]
+        from_args: Option<PathBuf>,
+
+        /// Compiled artifact of the new contract (.json with source)
+        #[arg(long)]
+        to: PathBuf,
+
+        /// Arguments file for the new contract
+        #[arg(long)]
+        to_args: Option<PathBuf>,
+
+        /// Witness file satisfying the old contract
+        #[arg(short, long)]
+        witness: PathBuf,
+
+        /// Fee in satoshis, per migrated UTXO (default: the network preset's)
+        #[arg(short, long)]
+        fee: Option<u64>,
+
+        /// Network
+        #[arg(
+            short,
+            long,
+            value_enum,
+            default_value = "regtest",
+            env = "SPRAY_NETWORK"
+        )]
+        network: NetworkArg,
+
+        /// Config file (required for testnet/liquid)
+        #[arg(short, long, env = "SPRAY_CONFIG")]
+        config: Option<PathBuf>,
+
+        /// Deployment registry file
+        #[arg(long, default_value = spray::registry::DEFAULT_REGISTRY_FILE)]
+        registry: PathBuf,
+    },
+
+    /// Work with contract UTXOs
+    Utxo {
+        #[command(subcommand)]
+        action: UtxoAction,
+    },
+
+    /// Sign and verify compiled artifacts
+    Artifact {
+        #[command(subcommand)]
+        action: ArtifactAction,
+    },
+
+    /// Package a contract's source, artifact, reports and deployments for audit
+    AuditBundle {
+        /// Path to the contract (.simf or compiled .json)
+        contract: PathBuf,
+
+        /// Path to arguments file (JSON or TOML)
+        #[arg(short, long)]
+        args: Option<PathBuf>,
+
+        /// Argument value merged over the arguments file (repeatable)
+        #[arg(long = "arg", value_name = "KEY=VALUE", value_parser = parse_override)]
+        arg: Vec<(String, String)>,
+
+        /// Witness to include and verify locally for the cost report
+        #[arg(short, long)]
+        witness: Option<PathBuf>,
+
+        /// Report from `spray test --report` to include
+        #[arg(long)]
+        test_report: Option<PathBuf>,
+
+        /// Deployment registry file
+        #[arg(long, default_value = spray::registry::DEFAULT_REGISTRY_FILE)]
+        registry: PathBuf,
+
+        /// Archive to write (default: <contract>-audit.tar)
+        #[arg(short, long)]
+        out: Option<PathBuf>,
+    },
+
+    /// Test a Simplicity program (compile + deploy + redeem)
+    Test {
+        /// Path to the .simf program file
+        #[arg(
+            short,
+            long,
+            conflicts_with_all = ["programs", "dir"]
+        )]
+        file: Option<PathBuf>,
+
+        /// Programs to run as one suite, e.g. `musk/*.simf`, each with
+        /// optional `<name>.args.json` and `<name>.wit.json` files
+        #[arg(conflicts_with = "manifest")]
+        programs: Vec<PathBuf>,
+
+        /// Run every .simf program in this directory as one suite (default:
+        /// `contracts_dir` of spray.toml)
+        #[arg(long, conflicts_with = "manifest")]
+        dir: Option<PathBuf>,
+
+        /// Run the suites declared in a test manifest instead of a single file
+        #[arg(long, conflicts_with = "file")]
+        manifest: Option<PathBuf>,
+
+        /// Run manifest suites for every network concurrently (default: regtest only)
+        #[arg(long, requires = "manifest")]
+        all_networks: bool,
+
+        /// Directory for per-suite JSON reports when running a manifest
+        #[arg(long, requires = "manifest")]
+        report_dir: Option<PathBuf>,
+
+        /// Estimate every manifest test's fee for this many blocks
+        #[arg(long, requires = "manifest")]
+        conf_target: Option<u16>,
+
+        /// Path to arguments file (JSON or TOML)
+        #[arg(short, long)]
+        args: Option<PathBuf>,
+
+        /// Argument value merged over the arguments file (repeatable)
+        #[arg(long = "arg", value_name = "KEY=VALUE", value_parser = parse_override)]
+        arg: Vec<(String, String)>,
+
+        /// Path to witness file (JSON or TOML)
+        #[arg(short, long)]
+        witness: Option<PathBuf>,
+
+        /// Witness value merged over the witness file (repeatable)
+        #[arg(long = "wit", value_name = "KEY=VALUE", value_parser = parse_override)]
+        wit: Vec<(String, String)>,
+
+        /// Test name
+        #[arg(short, long, default_value = "Program test")]
+        name: String,
+
+        /// Lock time for the spending transaction
+        #[arg(long)]
+        lock_time: Option<u32>,
+
+        /// Sequence number for the spending transaction
+        #[arg(long)]
+        sequence: Option<u32>,
+
+        /// Submit the funding transaction and the spend together as a package
+        #[arg(long)]
+        package: bool,
+
+        /// Pay the spend to this address or descriptor instead of the wallet
+        #[arg(long, value_parser = parse_destination)]
+        destination: Option<spray::destination::Destination>,
+
+        /// Network (currently only regtest is supported for test command)
+        #[arg(long, value_enum, default_value = "regtest")]
+        network: NetworkArg,
+
+        /// Verbose output, including the tail of the node's debug.log on
+        /// funding and broadcast failures
+        #[arg(short, long)]
+        verbose: bool,
+
+        /// Write a JSON report with timing data to this path
+        #[arg(long)]
+        report: Option<PathBuf>,
+
+        /// Rerun the test against a kept-alive daemon whenever the program,
+        /// arguments or witness file changes
+        #[arg(long, conflicts_with_all = ["manifest", "programs", "dir"])]
+        watch: bool,
+
+        /// Print results to stdout as a TAP stream, and everything else to
+        /// stderr
+        #[arg(long, conflicts_with_all = ["watch", "json"])]
+        tap: bool,
+
+        /// Report which branches of each program's `case` combinators the
+        /// tests took, and which no test exercised
+        #[arg(long, conflicts_with_all = ["watch", "all_networks"])]
+        coverage: bool,
+
+        /// Earlier JSON report, or directory of reports, to estimate the time
+        /// left from (repeatable; suites and program runs only)
+        #[arg(long, value_name = "REPORT", conflicts_with_all = ["watch", "all_networks"])]
+        history: Vec<PathBuf>,
+
+        /// Flag tests taking more than this many times their usual duration
+        #[arg(long, default_value_t = spray::eta::DEFAULT_SLOW_FACTOR)]
+        slow_factor: f64,
+    },
+
+    /// Run test suites with a live dashboard of their progress
+    Tui {
+        /// Programs to run as one suite, each with optional
+        /// `<name>.args.json` and `<name>.wit.json` files
+        #[arg(conflicts_with = "manifest")]
+        programs: Vec<PathBuf>,
+
+        /// Run every .simf program in this directory as one suite (default:
+        /// `contracts_dir` of spray.toml)
+        #[arg(long, conflicts_with = "manifest")]
+        dir: Option<PathBuf>,
+
+        /// Run the suites declared in a test manifest
+        #[arg(long)]
+        manifest: Option<PathBuf>,
+
+        /// Run manifest suites for every network concurrently (default: regtest only)
+        #[arg(long, requires = "manifest")]
+        all_networks: bool,
+
+        /// Event schema (JSON or TOML) to decode contract events in spends
+        #[arg(long)]
+        events: Option<PathBuf>,
+    },
+
+    /// Show the CMR, size, witness types and addresses of a compiled program
+    Inspect {
+        /// Compiled .json file, program file, or a base64/hex program
+        input: String,
+
+        /// Print the embedded source in full
+        #[arg(long)]
+        source: bool,
+
+        /// Print the decoded combinator and jet nodes
+        #[arg(long)]
+        disasm: bool,
+    },
+
+    /// Check a witness against a program locally, without a node
+    Verify {
+        /// Path to the program (.simf or compiled .json)
+        file: PathBuf,
+
+        /// Path to witness file (JSON or TOML)
+        witness: PathBuf,
+
+        /// Path to arguments file (JSON or TOML)
+        #[arg(short, long)]
+        args: Option<PathBuf>,
+
+        /// UTXO description from `spray utxo export` to spend from
+        #[arg(long)]
+        utxo: Option<PathBuf>,
+
+        /// Amount of the synthetic UTXO in satoshis
+        #[arg(long, default_value_t = spray::verify::DEFAULT_AMOUNT, conflicts_with = "utxo")]
+        amount: u64,
+
+        /// Genesis hash committed to by the sighash
+        #[arg(long)]
+        genesis_hash: Option<String>,
+
+        /// Lock time for the spending transaction
+        #[arg(long)]
+        lock_time: Option<u32>,
+
+        /// Sequence number for the spending transaction
+        #[arg(long)]
+        sequence: Option<u32>,
+
+        /// Fee in satoshis (default: the regtest preset's)
+        #[arg(short, long)]
+        fee: Option<u64>,
+    },
+
+    /// Execute a program step by step, printing every node and jet call
+    Debug {
+        /// Path to the program (.simf or compiled .json)
+        file: PathBuf,
+
+        /// Path to witness file (JSON or TOML)
+        witness: PathBuf,
+
+        /// Path to arguments file (JSON or TOML)
+        #[arg(short, long)]
+        args: Option<PathBuf>,
+
+        /// UTXO description from `spray utxo export` to spend from
+        #[arg(long)]
+        utxo: Option<PathBuf>,
+
+        /// Amount of the synthetic UTXO in satoshis
+        #[arg(long, default_value_t = spray::verify::DEFAULT_AMOUNT, conflicts_with = "utxo")]
+        amount: u64,
+
+        /// Genesis hash committed to by the sighash
+        #[arg(long)]
+        genesis_hash: Option<String>,
+
+        /// Lock time for the spending transaction
+        #[arg(long)]
+        lock_time: Option<u32>,
+
+        /// Sequence number for the spending transaction
+        #[arg(long)]
+        sequence: Option<u32>,
+
+        /// Fee in satoshis (default: the regtest preset's)
+        #[arg(short, long)]
+        fee: Option<u64>,
+
+        /// Print only jet calls
+        #[arg(long)]
+        jets_only: bool,
+
+        /// Pause after calls of a jet (e.g. `eq_32`) or the jets on a source
+        /// line (`file:line`), and ask how to go on (repeatable)
+        #[arg(long = "break", value_name = "JET|FILE:LINE")]
+        breakpoints: Vec<spray::trace::Breakpoint>,
+    },
+
+    /// Decode a hex-encoded value according to its Simplicity type
+    Decode {
+        /// Value type, e.g. "u32", "(u8, bool)" or "[u8; 4]"
+        #[arg(short = 't', long = "type")]
+        ty: String,
+
+        /// Value as hex, read from the most significant bit
+        value: String,
+    },
+
+    /// Run an end-to-end sanity check of the local environment
+    Selftest,
+
+    /// Prune old compilation caches, reports and test environment data directories
+    Gc {
+        /// Remove entries older than this many days (default: spray.toml's [gc], or 14)
+        #[arg(long)]
+        max_age: Option<u64>,
+
+        /// Remove the oldest entries of each kind until it takes at most this many MB
+        #[arg(long)]
+        max_size: Option<u64>,
+
+        /// Directory of suite reports to prune (repeatable)
+        #[arg(long = "reports")]
+        report_dirs: Vec<PathBuf>,
+
+        /// List what would be removed without removing it
+        #[arg(long)]
+        dry_run: bool,
+    },
+
+    /// Start an interactive REPL
+    Repl,
+
+    /// Manage Elements regtest daemon
+    Daemon,
+
+    /// Browse and run the bundled example programs
+    #[cfg(feature = "examples")]
+    Examples {
+        #[command(subcommand)]
+        action: ExamplesAction,
+    },
+
+    /// Show the funds held by a contract address
+    Balance {
+        /// Contract address, CMR, or compiled artifact (.json)
+        target: String,
+
+        /// Network
+        #[arg(
+            short,
+            long,
+            value_enum,
+            default_value = "regtest",
+            env = "SPRAY_NETWORK"
+        )]
+        network: NetworkArg,
+
+        /// Config file (required for testnet/liquid)
+        #[arg(short, long, env = "SPRAY_CONFIG")]
+        config: Option<PathBuf>,
+    },
+
+    /// List the unspent outputs at a contract address
+    Utxos {
+        /// Contract address, CMR, or compiled artifact (.json)
+        target: String,
+
+        /// Network
+        #[arg(
+            short,
+            long,
+            value_enum,
+            default_value = "regtest",
+            env = "SPRAY_NETWORK"
+        )]
+        network: NetworkArg,
+
+        /// Config file (required for testnet/liquid)
+        #[arg(short, long, env = "SPRAY_CONFIG")]
+        config: Option<PathBuf>,
+    },
+
+    /// Watch a contract address for incoming funds
+    Watch {
+        /// Contract address, CMR, or compiled artifact (.json)
+        target: String,
+
+        /// Seconds between polls
+        #[arg(long, default_value_t = 5)]
+        interval: u64,
+
+        /// Exit after the first new output
+        #[arg(long)]
+        once: bool,
+
+        /// Append events to this file as JSON lines
+        #[arg(long)]
+        record: Option<PathBuf>,
+
+        /// Network
+        #[arg(
+            short,
+            long,
+            value_enum,
+            default_value = "regtest",
+            env = "SPRAY_NETWORK"
+        )]
+        network: NetworkArg,
+
+        /// Config file (required for testnet/liquid)
+        #[arg(short, long, env = "SPRAY_CONFIG")]
+        config: Option<PathBuf>,
+    },
+
+    /// Serve compile, deploy, redeem and test over a JSON HTTP API
+    Serve {
+        /// Address to listen on
+        #[arg(long, default_value = "127.0.0.1")]
+        bind: String,
+
+        /// Port to listen on
+        #[arg(short, long, default_value_t = spray::serve::DEFAULT_PORT)]
+        port: u16,
+
+        /// Seconds between blocks of the regtest node started for the server
+        #[arg(long, default_value_t = 1)]
+        block_time: u64,
+
+        /// Network
+        #[arg(
+            short,
+            long,
+            value_enum,
+            default_value = "regtest",
+            env = "SPRAY_NETWORK"
+        )]
+        network: NetworkArg,
+
+        /// Config file (required for testnet/liquid)
+        #[arg(short, long, env = "SPRAY_CONFIG")]
+        config: Option<PathBuf>,
+
+        /// Token requests must send as `Authorization: Bearer <token>`
+        /// (default: a random token, printed at startup)
+        #[arg(long, env = "SPRAY_SERVE_TOKEN")]
+        token: Option<String>,
+    },
+
+    /// Write arguments and witness templates for a program
+    Scaffold {
+        /// Path to the program (.simf)
+        file: PathBuf,
+
+        /// Directory to write the templates to (default: the program's)
+        #[arg(short, long)]
+        out_dir: Option<PathBuf>,
+
+        /// Overwrite existing templates
+        #[arg(short, long)]
+        force: bool,
+    },
+
+    /// Create a project directory from a contract template
+    New {
+        /// Name of the project directory
+        name: PathBuf,
+
+        /// Contract template
+        #[arg(short, long, value_enum, default_value = "p2pk")]
+        template: TemplateArg,
+    },
+
+    /// Initialize a new Simplicity project
+    Init {
+        /// Overwrite existing musk.conf if present
+        #[arg(short, long)]
+        force: bool,
+    },
+}
+
+#[cfg(feature = "examples")]
+#[derive(Subcommand)]
+enum ExamplesAction {
+    /// List the examples
+    List,
+
+    /// Print an example's source
+    Show {
+        /// Example name
+        name: String,
+    },
+
+    /// Compile, fund and spend an example on an ephemeral regtest node
+    Run {
+        /// Example name
+        name: String,
+    },
+}
+
+#[derive(Subcommand)]
+enum UtxoAction {
+    /// Export a self-contained UTXO description for offline spend building
+    Export {
+        /// UTXO reference in format "txid:vout"
+        utxo: String,
+
+        /// Output file (defaults to stdout)
+        #[arg(short, long)]
+        out: Option<PathBuf>,
+
+        /// Network
+        #[arg(
+            short,
+            long,
+            value_enum,
+            default_value = "regtest",
+            env = "SPRAY_NETWORK"
+        )]
+        network: NetworkArg,
+
+        /// Config file (required for testnet/liquid)
+        #[arg(short, long, env = "SPRAY_CONFIG")]
+        config: Option<PathBuf>,
+    },
+}
+
+#[derive(Subcommand)]
+enum ArtifactAction {
+    /// Generate a signing key in the keystore
+    Keygen {
+        /// Key name
+        name: String,
+
+        /// Keystore directory
+        #[arg(long, default_value = spray::signing::DEFAULT_KEYSTORE_DIR)]
+        keystore: PathBuf,
+    },
+
+    /// Write a detached signature for an artifact
+    Sign {
+        /// Artifact to sign
+        artifact: PathBuf,
+
+        /// Name of the key in the keystore
+        #[arg(short, long)]
+        key: String,
+
+        /// Keystore directory
+        #[arg(long, default_value = spray::signing::DEFAULT_KEYSTORE_DIR)]
+        keystore: PathBuf,
+    },
+
+    /// Verify an artifact's detached signature
+    Verify {
+        /// Artifact to verify
+        artifact: PathBuf,
+
+        /// Trusted public key (repeatable; defaults to spray.toml's trusted_keys)
+        #[arg(long)]
+        trusted: Vec<String>,
+    },
+}
+
+/// The fee given on the command line, or else the default fee of
+/// `spray.toml`, or else the preset fee of `network`
+fn fee_or_preset(fee: Option<u64>, network: musk::Network) -> Result<u64, SprayError> {
+    match fee.or(spray::project::defaults()?.fee) {
+        Some(fee) => Ok(fee),
+        None => Ok(spray::preset::Preset::for_network(network)?.fee),
+    }
+}
+
+/// Parse a `KEY=VALUE` override for clap
+fn parse_override(s: &str) -> Result<(String, String), String> {
+    spray::file_loader::parse_override(s).map_err(|e| e.to_string())
+}
+
+/// Parse the artifact file of `spray compile --out` for clap, refusing
+/// output format names that were meant for `--output`
+fn parse_out_file(s: &str) -> Result<PathBuf, String> {
+    if OutputFormat::from_str(s, true).is_ok() {
+        return Err(format!(
+            "'{s}' is an output format; use --output {s} to choose the format"
+        ));
+    }
+    Ok(PathBuf::from(s))
+}
+
+/// Parse a spend destination for clap
+fn parse_destination(s: &str) -> Result<spray::destination::Destination, String> {
+    s.parse().map_err(|e: SprayError| e.to_string())
+}
+
+/// Parse a fee rate for clap
+fn parse_feerate(s: &str) -> Result<f64, String> {
+    spray::fee::parse_feerate(s).map_err(|e| e.to_string())
+}
+
+/// Parse a soak fault for clap
+fn parse_fault(s: &str) -> Result<spray::soak::Fault, String> {
+    s.parse().map_err(|e: SprayError| e.to_string())
+}
+
+/// The command-line parser, with the `[defaults]` of the nearest
+/// `spray.toml` in place of the built-in defaults of the flags read from
+/// SPRAY_NETWORK and SPRAY_CONFIG
+fn command(defaults: Option<&ProjectDefaults>) -> clap::Command {
+    let mut values = Vec::new();
+    if let Some(defaults) = defaults {
+        if let Some(network) = &defaults.network {
+            values.push((NETWORK_ENV, OsString::from(network)));
+        }
+        if let Some(config) = defaults.node_config() {
+            values.push((CONFIG_ENV, config.as_os_str().to_owned()));
+        }
+    }
+    with_defaults(Cli::command(), &values)
+}
+
+/// `command` and its subcommands with each flag read from one of the
+/// environment variables of `values` defaulting to the variable's value
+fn with_defaults(mut command: clap::Command, values: &[(&str, OsString)]) -> clap::Command {
+    for (env, value) in values {
+        let ids: Vec<clap::Id> = command
+            .get_arguments()
+            .filter(|arg| arg.get_env() == Some(OsStr::new(env)))
+            .map(|arg| arg.get_id().clone())
+            .collect();
+        for id in ids {
+            command = command.mut_arg(id, |arg| arg.default_value(value.clone()));
+        }
+    }
+    let names: Vec<String> = command
+        .get_subcommands()
+        .map(|sub| sub.get_name().to_string())
+        .collect();
+    for name in names {
+        command = command.mut_subcommand(name, |sub| with_defaults(sub, values));
+    }
+    command
+}
+
+#[allow(clippy::too_many_lines)]
+fn main() {
+    // Errors loading spray.toml are reported once the output format is known
+    let defaults = spray::project::defaults();
+    let cli = Cli::from_arg_matches(&command(defaults.as_ref().ok()).get_matches())
+        .unwrap_or_else(|e| e.exit());
+    if !spray::output::use_color(cli.no_color) {
+        colored::control::set_override(false);
+    }
+    spray::output::set_quiet(cli.quiet);
+    spray::output::set_format(if cli.json {
+        Format::Json
+    } else if matches!(cli.command, Commands::Test { tap: true, .. }) {
+        Format::Tap
+    } else {
+        Format::Human
+    });
+
+    if let Err(e) = defaults.and_then(|_| run(cli)) {
+        match spray::output::format() {
+            Format::Json => println!("{}", spray::output::error_document(&e)),
+            Format::Tap => println!("Bail out! {e}"),
+            Format::Human => eprintln!(
+                "{} {}",
+                "Error:".red().bold(),
+                spray::messages::catalog().error(&e)
+            ),
+        }
+        std::process::exit(e.exit_code().code());
+    }
+}
+
+/// Whether `command` can print a JSON document
+const fn supports_json(command: &Commands) -> bool {
+    match command {
+        Commands::Compile { .. }
+        | Commands::Deploy { .. }
+        | Commands::Redeem { .. }
+        | Commands::Test { .. }
+        | Commands::Bench { .. }
+        | Commands::Balance { .. }
+        | Commands::Utxos { .. } => true,
+        Commands::Watch { once, .. } => *once,
+        _ => false,
+    }
+}
+
+/// Whether `command` talks to a node of its own rather than through a
+/// backend, so that its requests cannot be recorded or replayed
+fn bypasses_backend(command: &Commands) -> bool {
+    match command {
+        Commands::Test { file, manifest, .. } => file.is_some() && manifest.is_none(),
+        Commands::Stress { .. }
+        | Commands::Fuzz { .. }
+        | Commands::Soak { .. }
+        | Commands::Serve { .. } => true,
+        _ => false,
+    }
+}
+
+/// Whether `command` runs nodes that --deterministic cannot pin: concurrent
+/// funding from one wallet, daemon restarts, or commands of its own
+fn rejects_deterministic(command: &Commands) -> bool {
+    matches!(
+        command,
+        Commands::Stress { .. } | Commands::Soak { .. } | Commands::Serve { .. }
+    )
+}
+
+fn run(cli: Cli) -> Result<(), SprayError> {
+    spray::logging::init(cli.log_level.as_deref())?;
+    spray::project::enforce_toolchain()?;
+
+    if (cli.record.is_some() || cli.replay.is_some()) && bypasses_backend(&cli.command) {
+        return Err(SprayError::ConfigError(
+            "--record and --replay are not supported by test --file, stress, fuzz, soak and serve, which run their own node"
+                .into(),
+        ));
+    }
+    if cli.deterministic && rejects_deterministic(&cli.command) {
+        return Err(SprayError::ConfigError(
+            "--deterministic is not supported by stress, soak and serve".into(),
+        ));
+    }
+    // Settings of every backend the command creates, with the command's
+    // own --config
+    let mode = match (&cli.record, &cli.replay) {
+        (Some(path), _) => Some(spray::vcr::Mode::Record(spray::vcr::Recorder::new(
+            path.clone(),
+        ))),
+        (None, Some(path)) => Some(spray::vcr::Mode::Replay(spray::vcr::Replay::load(path)?)),
+        (None, None) => None,
+    };
+    let backend = BackendConfig {
+        config: None,
+        wallet: cli.wallet.clone(),
+        kind: cli.backend.into(),
+        mode,
+        deterministic: cli.deterministic,
+        rpc_backoff: spray::retry::Backoff::new(
+            cli.rpc_retries,
+            Duration::from_millis(cli.rpc_retry_delay),
+        ),
+    };
+
+    if cli.json && !supports_json(&cli.command) {
+        return Err(SprayError::ConfigError(
+            "--json is supported by compile, deploy, redeem, test, bench, balance, utxos and watch --once"
+                .into(),
+        ));
+    }
+
+    match cli.command {
+        Commands::Compile {
+            file,
+            args,
+            arg,
+            witness,
+            output,
+            out,
+            no_cache,
+            network,
+            config,
+        } => {
+            let output_fmt = match output {
+                OutputFormat::Json => commands::compile::OutputFormat::Json,
+                OutputFormat::Base64 => commands::compile::OutputFormat::Base64,
+                OutputFormat::Hex => commands::compile::OutputFormat::Hex,
+            };
+            commands::compile_command(
+                &file,
+                args,
+                &arg,
+                witness,
+                output_fmt,
+                out,
+                no_cache,
+                network.into(),
+                config,
+            )?;
+        }
+
+        Commands::Deploy {
+            file,
+            args,
+            arg,
+            amount,
+            asset,
+            network,
+            config,
+            registry,
+            no_resume,
+            wait,
+            dry_run,
+            no_cache,
+        } => {
+            commands::deploy_command(
+                &file,
+                args,
+                &arg,
+                amount,
+                asset,
+                network.into(),
+                &backend.with_config(config),
+                &registry,
+                !no_resume,
+                wait,
+                dry_run,
+                no_cache,
+            )?;
+        }
+
+        Commands::Redeem {
+            interactive: true,
+            network,
+            config,
+            registry,
+            keystore,
+            broadcast_retries,
+            retry_delay,
+            ..
+        } => {
+            commands::redeem_interactive_command(
+                network.into(),
+                &backend.with_config(config),
+                &registry,
+                &keystore,
+                spray::retry::Backoff::new(broadcast_retries, Duration::from_secs(retry_delay)),
+            )?;
+        }
+
+        Commands::Redeem {
+            utxo: Some(utxo),
+            witness,
+            interactive: false,
+            keystore: _,
+            wit,
+            compiled,
+            dest,
+            fee,
+            feerate,
+            conf_target,
+            blinding_key,
+            blind,
+            export_pset,
+            dry_run,
+            network,
+            config,
+            registry,
+            broadcast_retries,
+            retry_delay,
+        } => {
+            commands::redeem_command(
+                &utxo,
+                witness.as_deref(),
+                &wit,
+                compiled,
+                dest,
+                match (fee, feerate, conf_target) {
+                    (_, _, Some(conf_target)) => FeePolicy::Estimate { conf_target },
+                    (_, Some(rate), None) => FeePolicy::Rate(rate),
+                    (Some(fee), None, None) => FeePolicy::Fixed(fee),
+                    (None, None, None) => FeePolicy::Fixed(fee_or_preset(None, network.into())?),
+                },
+                blinding_key,
+                blind,
+                export_pset,
+                dry_run,
+                network.into(),
+                &backend.with_config(config),
+                &registry,
+                spray::retry::Backoff::new(broadcast_retries, Duration::from_secs(retry_delay)),
+            )?;
+        }
+
+        Commands::Redeem { .. } => {
+            return Err(SprayError::ConfigError(
+                "redeem requires a UTXO and a witness file, or --interactive".into(),
+            ));
+        }
+
+        Commands::Sighash {
+            utxo,
+            compiled,
+            dest,
+            fee,
+            explain,
+            compare,
+            out,
+            network,
+            config,
+        } => {
+            let matched = commands::sighash_command(
+                &utxo,
+                &compiled,
+                dest,
+                fee_or_preset(fee, network.into())?,
+                explain,
+                compare,
+                out,
+                network.into(),
+                &backend.with_config(config),
+            )?;
+            if !matched {
+                std::process::exit(ExitCode::Failure.code());
+            }
+        }
+
+        Commands::Forensics {
+            txid,
+            contract,
+            args,
+            input,
+            keys,
+            out,
+            network,
+            config,
+        } => {
+            commands::forensics_command(
+                &txid,
+                &contract,
+                args,
+                input,
+                &keys,
+                out,
+                network.into(),
+                &backend.with_config(config),
+            )?;
+        }
+
+        Commands::Import {
+            file,
+            args,
+            from_height,
+            chunk_size,
+            no_rescan,
+            network,
+            config,
+            registry,
+        } => {
+            commands::import_command(
+                &file,
+                args,
+                from_height,
+                chunk_size,
+                no_rescan,
+                network.into(),
+                &backend.with_config(config),
+                &registry,
+            )?;
+        }
+
+        Commands::Bench {
+            files,
+            args,
+            witness,
+            iterations,
+            report,
+        } => {
+            commands::bench_command(&files, args, witness, iterations, report)?;
+        }
+
+        Commands::Stress {
+            contract,
+            args,
+            witness,
+            count,
+            concurrency,
+            report,
+        } => {
+            if !commands::stress_command(&contract, args, witness, count, concurrency, report)? {
+                std::process::exit(ExitCode::TestFailure.code());
+            }
+        }
+
+        Commands::Fuzz {
+            contract,
+            args,
+            witness,
+            iterations,
+            seed,
+            report,
+        } => {
+            if !commands::fuzz_command(
+                &contract,
+                args,
+                &witness,
+                iterations,
+                seed,
+                report,
+                cli.deterministic,
+            )? {
+                std::process::exit(ExitCode::TestFailure.code());
+            }
+        }
+
+        Commands::Soak {
+            contract,
+            args,
+            witness,
+            count,
+            concurrency,
+            faults,
+            fault_every,
+            recovery_attempts,
+            report,
+        } => {
+            let schedule = spray::soak::FaultSchedule::new(faults, fault_every);
+            if !commands::soak_command(
+                &contract,
+                args,
+                witness,
+                count,
+                concurrency,
+                &schedule,
+                recovery_attempts,
+                report,
+            )? {
+                std::process::exit(ExitCode::TestFailure.code());
+            }
+        }
+
+        Commands::Monitor {
+            rules,
+            from_height,
+            interval,
+            once,
+            network,
+            config,
+        } => {
+            if let Some(code) = commands::monitor_command(
+                &rules,
+                from_height,
+                Duration::from_secs(interval),
+                once,
+                network.into(),
+                &backend.with_config(config),
+            )? {
+                std::process::exit(code);
+            }
+        }
+
+        Commands::Events {
+            file,
+            args,
+            schema,
+            from_height,
+            out,
+            network,
+            config,
+            registry,
+        } => {
+            commands::events_command(
+                &file,
+                args,
+                &schema,
+                from_height,
+                out,
+                network.into(),
+                &backend.with_config(config),
+                &registry,
+            )?;
+        }
+
+        Commands::Status {
+            at_height,
+            cmr,
+            network,
+            config,
+            registry,
+        } => {
+            commands::status_command(
+                at_height,
+                cmr.as_deref(),
+                network.into(),
+                &backend.with_config(config),
+                &registry,
+            )?;
+        }
+
+        Commands::Interop {
+            artifact,
+            source,
+            args,
+            out,
+        } => {
+            if !commands::interop_command(&artifact, source, args, out)? {
+                std::process::exit(ExitCode::Failure.code());
+            }
+        }
+
+        Commands::ComparePolicy {
+            descriptor,
+            file,
+            args,
+            cases,
+            amount,
+            fee,
+            network,
+            config,
+        } => {
+            if !commands::compare_policy_command(
+                &descriptor,
+                &file,
+                args,
+                &cases,
+                amount,
+                fee,
+                network.into(),
+                &backend.with_config(config),
+            )? {
+                std::process::exit(ExitCode::Failure.code());
+            }
+        }
+
+        Commands::MigrateFunds {
+            from,
+            from_args,
+            to,
+            to_args,
+            witness,
+            fee,
+            network,
+            config,
+            registry,
+        } => {
+            commands::migrate_funds_command(
+                &from,
+                from_args,
+                &to,
+                to_args,
+                &witness,
+                fee_or_preset(fee, network.into())?,
+                network.into(),
+                &backend.with_config(config),
+                &registry,
+            )?;
+        }
+
+        Commands::Artifact { action } => match action {
+            ArtifactAction::Keygen { name, keystore } => {
+                commands::artifact_keygen_command(&name, &keystore)?;
+            }
+            ArtifactAction::Sign {
+                artifact,
+                key,
+                keystore,
+            } => {
+                commands::artifact_sign_command(&artifact, &key, &keystore)?;
+            }
+            ArtifactAction::Verify { artifact, trusted } => {
+                commands::artifact_verify_command(&artifact, &trusted)?;
+            }
+        },
+
+        Commands::AuditBundle {
+            contract,
+            args,
+            arg,
+            witness,
+            test_report,
+            registry,
+            out,
+        } => {
+            commands::audit_bundle_command(
+                &contract,
+                args,
+                &arg,
+                witness,
+                test_report,
+                &registry,
+                out,
+            )?;
+        }
+
+        Commands::Utxo { action } => match action {
+            UtxoAction::Export {
+                utxo,
+                out,
+                network,
+                config,
+            } => {
+                commands::utxo_export_command(
+                    &utxo,
+                    out,
+                    network.into(),
+                    &backend.with_config(config),
+                )?;
+            }
+        },
+
+        Commands::Test {
+            file,
+            programs,
+            dir,
+            manifest,
+            all_networks,
+            report_dir,
+            conf_target,
+            args,
+            arg,
+            witness,
+            wit,
+            name,
+            lock_time,
+            sequence,
+            package,
+            destination,
+            network,
+            verbose,
+            report,
+            watch,
+            tap: _,
+            coverage,
+            history,
+            slow_factor,
+        } => {
+            let log_tail = if verbose {
+                spray::env::DEFAULT_LOG_TAIL
+            } else {
+                0
+            };
+            let history = if history.is_empty() {
+                None
+            } else {
+                Some(spray::eta::DurationHistory::load(&history)?)
+            };
+            let dir = if file.is_none() && programs.is_empty() && manifest.is_none() {
+                dir.or(spray::project::defaults()?.contracts_dir)
+            } else {
+                dir
+            };
+            if let Some(manifest_path) = manifest {
+                let passed = commands::manifest_command(
+                    &manifest_path,
+                    all_networks,
+                    report_dir.as_deref(),
+                    conf_target,
+                    coverage,
+                    history.as_ref(),
+                    slow_factor,
+                    &backend,
+                )?;
+                if !passed {
+                    std::process::exit(ExitCode::TestFailure.code());
+                }
+                return Ok(());
+            }
+
+            if !programs.is_empty() || dir.is_some() {
+                let passed = commands::programs_command(
+                    &programs,
+                    dir.as_deref(),
+                    report.as_deref(),
+                    coverage,
+                    history.as_ref(),
+                    slow_factor,
+                    &backend,
+                )?;
+                if !passed {
+                    std::process::exit(ExitCode::TestFailure.code());
+                }
+                return Ok(());
+            }
+
+            let file = file.ok_or_else(|| {
+                SprayError::ConfigError(
+                    "One of --file, --manifest, --dir or program paths is required".into(),
+                )
+            })?;
+
+            // Only regtest is supported for test command
+            if !matches!(network, NetworkArg::Regtest) {
+                return Err(SprayError::ConfigError(
+                    "Test command currently only supports --network regtest".into(),
+                ));
+            }
+
+            if watch {
+                return commands::test_watch_command(&commands::WatchOptions {
+                    file: &file,
+                    args: args.as_deref(),
+                    arg_overrides: &arg,
+                    witness: witness.as_deref(),
+                    wit_overrides: &wit,
+                    name: &name,
+                    lock_time,
+                    sequence,
+                    package,
+                    destination: destination.as_ref(),
+                    report: report.as_deref(),
+                    deterministic: cli.deterministic,
+                    log_tail,
+                });
+            }
+
+            if verbose {
+                spray::status!("{}", "Initializing test environment...".dimmed());
+            }
+
+            let env = TestEnvBuilder::new()
+                .deterministic(cli.deterministic)
+                .build()?;
+            let runner = TestRunner::with_env(env).log_tail(log_tail);
+
+            if verbose {
+                spray::status!("{}", "Loading program...".dimmed());
+            }
+
+            // Load program
+            let program = musk::Program::from_source(&spray::include::expand(&file)?)?;
+
+            // Load arguments if provided
+            if verbose {
+                if let Some(args_path) = &args {
+                    spray::status!(
+                        "{} {}",
+                        "Loading arguments from:".dimmed(),
+                        args_path.display()
+                    );
+                }
+            }
+            let arguments = spray::file_loader::load_arguments_with(args.as_deref(), &arg)?;
+
+            // Compile program
+            let compiled = match program.instantiate(arguments) {
+                Ok(compiled) => compiled,
+                Err(e) => {
+                    if let Some(report_path) = &report {
+                        let record = spray::report::TestRecord::failure(&name, 0, e.to_string())
+                            .with_stage(spray::FailureStage::Compile);
+                        spray::SuiteReport::new(vec![record], 1).write_json(report_path)?;
+                    }
+                    return Err(e.into());
+                }
+            };
+
+            // Create witness function from the file and --wit overrides
+            let witness_values = spray::file_loader::load_witness_with(
+                witness.as_deref(),
+                &wit,
+                &spray::compiled::witness_types(&compiled),
+            )?;
+            let witness_fn: Box<dyn Fn([u8; 32]) -> musk::WitnessValues> =
+                Box::new(move |_sighash| witness_values.clone());
+
+            let branch_coverage = if coverage {
+                Some(
+                    spray::coverage::Coverage::for_program(&compiled)?
+                        .named(&file.display().to_string()),
+                )
+            } else {
+                None
+            };
+
+            // Create test case
+            let mut test = TestCase::new(runner.env(), compiled).name(&name);
+
+            test = test.witness(witness_fn);
+
+            if let Some(lt) = lock_time {
+                test = test.lock_time(musk::elements::LockTime::from_consensus(lt));
+            }
+
+            if let Some(seq) = sequence {
+                test = test.sequence(musk::elements::Sequence::from_consensus(seq));
+            }
+
+            test = test.package(package);
+
+            if let Some(destination) = destination {
+                test = test.destination(destination);
+            }
+
+            // Run test
+            let (result, record) = runner.run_timed(test);
+
+            let mut suite_report = spray::SuiteReport::new(vec![record], 1);
+            if let Some(mut branch_coverage) = branch_coverage {
+                if let Some(tx) = result.transaction() {
+                    branch_coverage.record_spend(tx);
+                }
+                branch_coverage.print();
+                suite_report.coverage.push(branch_coverage);
+            }
+            if let Some(report_path) = report {
+                suite_report.write_json(&report_path)?;
+            }
+            spray::output::emit(
+                if result.is_success() {
+                    "test.passed"
+                } else {
+                    "test.failed"
+                },
+                result.is_success(),
+                &suite_report,
+            )?;
+            spray::output::emit_tap(&suite_report.tests);
+
+            if result.is_failure() {
+                std::process::exit(ExitCode::TestFailure.code());
+            }
+        }
+
+        Commands::Tui {
+            programs,
+            dir,
+            manifest,
+            all_networks,
+            events,
+        } => {
+            let dir = if programs.is_empty() && manifest.is_none() {
+                dir.or(spray::project::defaults()?.contracts_dir)
+            } else {
+                dir
+            };
+            if manifest.is_none() && programs.is_empty() && dir.is_none() {
+                return Err(SprayError::ConfigError(
+                    "One of --manifest, --dir or program paths is required".into(),
+                ));
+            }
+            let passed = commands::tui_command(
+                manifest.as_deref(),
+                &programs,
+                dir.as_deref(),
+                all_networks,
+                events.as_deref(),
+                &backend,
+            )?;
+            if !passed {
+                std::process::exit(ExitCode::TestFailure.code());
+            }
+        }
+
+        Commands::Inspect {
+            input,
+            source,
+            disasm,
+        } => {
+            commands::inspect_command(&input, source, disasm)?;
+        }
+
+        Commands::Verify {
+            file,
+            witness,
+            args,
+            utxo,
+            amount,
+            genesis_hash,
+            lock_time,
+            sequence,
+            fee,
+        } => {
+            if !commands::verify_command(
+                &file,
+                &witness,
+                args,
+                utxo,
+                amount,
+                genesis_hash,
+                lock_time,
+                sequence,
+                fee_or_preset(fee, musk::Network::Regtest)?,
+            )? {
+                std::process::exit(ExitCode::Failure.code());
+            }
+        }
+
+        Commands::Debug {
+            file,
+            witness,
+            args,
+            utxo,
+            amount,
+            genesis_hash,
+            lock_time,
+            sequence,
+            fee,
+            jets_only,
+            breakpoints,
+        } => {
+            if !commands::debug_command(
+                &file,
+                &witness,
+                args,
+                utxo,
+                amount,
+                genesis_hash,
+                lock_time,
+                sequence,
+                fee_or_preset(fee, musk::Network::Regtest)?,
+                jets_only,
+                &breakpoints,
+            )? {
+                std::process::exit(ExitCode::Failure.code());
+            }
+        }
+
+        Commands::Decode { ty, value } => {
+            commands::decode_command(&ty, &value)?;
+        }
+
+        Commands::Selftest => {
+            if !commands::selftest_command() {
+                std::process::exit(ExitCode::TestFailure.code());
+            }
+        }
+
+        Commands::Gc {
+            max_age,
+            max_size,
+            report_dirs,
+            dry_run,
+        } => {
+            commands::gc_command(max_age, max_size, report_dirs, dry_run)?;
+        }
 
         Commands::Repl => {
-            println!("{}", "Interactive REPL not yet implemented".yellow());
-            println!("Use 'spray test --help' to see testing options");
+            spray::status!("{}", "Interactive REPL not yet implemented".yellow());
+            spray::status!("Use 'spray test --help' to see testing options");
         }
 
         Commands::Daemon => {
-            println!("{}", "Daemon management not yet implemented".yellow());
-            println!("The daemon is automatically started when running tests");
+            spray::status!("{}", "Daemon management not yet implemented".yellow());
+            spray::status!("The daemon is automatically started when running tests");
+        }
+
+        #[cfg(feature = "examples")]
+        Commands::Examples { action } => match action {
+            ExamplesAction::List => commands::examples_list_command(),
+            ExamplesAction::Show { name } => commands::examples_show_command(&name)?,
+            ExamplesAction::Run { name } => {
+                if !commands::examples_run_command(&name, &backend)? {
+                    std::process::exit(ExitCode::TestFailure.code());
+                }
+            }
+        },
+
+        Commands::Balance {
+            target,
+            network,
+            config,
+        } => {
+            commands::balance_command(&target, network.into(), &backend.with_config(config))?;
+        }
+
+        Commands::Utxos {
+            target,
+            network,
+            config,
+        } => {
+            commands::utxos_command(&target, network.into(), &backend.with_config(config))?;
+        }
+
+        Commands::Watch {
+            target,
+            interval,
+            once,
+            record,
+            network,
+            config,
+        } => {
+            commands::watch_command(
+                &target,
+                Duration::from_secs(interval),
+                once,
+                record.as_deref(),
+                network.into(),
+                &backend.with_config(config),
+            )?;
+        }
+
+        Commands::Serve {
+            bind,
+            port,
+            block_time,
+            network,
+            config,
+            token,
+        } => {
+            commands::serve_command(
+                &bind,
+                port,
+                Duration::from_secs(block_time),
+                network.into(),
+                config,
+                token,
+            )?;
+        }
+
+        Commands::Scaffold {
+            file,
+            out_dir,
+            force,
+        } => {
+            commands::scaffold_command(&file, out_dir.as_deref(), force)?;
+        }
+
+        Commands::New { name, template } => {
+            commands::new_command(&name, template.into())?;
         }
 
         Commands::Init { force } => {
@@ -0,0 +1,625 @@
+//! Test manifests
+//!
+//! A manifest is a TOML file declaring one or more test suites. Each suite
+//! runs against its own network backend: an ephemeral regtest node, or an
+//! external node configured with a `musk.toml` file. Suites are independent
+//! of each other, so suites on different networks can run concurrently.
+//!
+//! # Example
+//!
+//! ```toml
+//! [[suite]]
+//! name = "local"
+//! network = "regtest"
+//!
+//! [[suite.test]]
+//! name = "p2ms"
+//! file = "p2ms.simf"
+//! witness = "p2ms.wit"
+//!
+//! [[suite.test]]
+//! name = "introspects its funding transaction"
+//! file = "covenant.simf"
+//! funding = [
+//!     { kind = "op_return", data = "deadbeef" },
+//!     { kind = "contract" },
+//!     { kind = "wallet", amount = 50000 },
+//! ]
+//!
+//! [[suite]]
+//! name = "testnet-smoke"
+//! conf_target = 2
+//! network = "testnet"
+//! config = "musk.toml"
+//!
+//! [[suite.test]]
+//! name = "p2ms on testnet"
+//! file = "p2ms.simf"
+//! witness = "p2ms.wit"
+//! ```
+
+use crate::cache::ProgramCache;
+use crate::coverage::Coverage;
+use crate::destination::Destination;
+use crate::error::SprayError;
+use crate::fee::{FeePolicy, DEFAULT_FEE};
+use crate::file_loader;
+use crate::funding::FundingShape;
+use crate::network::{BackendConfig, NetworkBackend};
+use crate::preset::Preset;
+use crate::report::{SuiteReport, TestRecord, DEFAULT_SLOWEST_COUNT};
+use crate::test::{self, FailureStage, Spend, SpendDetails, TestResult};
+use crate::witness::SigningWitness;
+use colored::Colorize;
+use musk::client::NodeClient;
+use musk::elements::{LockTime, Sequence};
+use musk::{InstantiatedProgram, Network};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+
+/// A test of a suite starting or finishing, as reported by
+/// [`SuiteSpec::run_with_progress`]
+#[derive(Debug, Clone, Copy)]
+pub enum Progress<'a> {
+    /// A test is about to run
+    Started {
+        /// Position of the test in the suite
+        index: usize,
+        /// The test
+        test: &'a TestSpec,
+    },
+    /// A test has finished
+    Finished {
+        /// Position of the test in the suite
+        index: usize,
+        /// The test
+        test: &'a TestSpec,
+        /// Its outcome, as it appears in the suite report
+        record: &'a TestRecord,
+        /// The spend, if the test passed
+        spend: Option<&'a SpendDetails>,
+    },
+}
+
+/// Default manifest file name, relative to the working directory
+pub const DEFAULT_MANIFEST_FILE: &str = "spray-tests.toml";
+
+/// Suffixes of a program's arguments file, e.g. `foo.args.json` for
+/// `foo.simf`
+const ARGS_SUFFIXES: [&str; 2] = ["args.json", "args.toml"];
+
+/// Suffixes of a program's witness file, e.g. `foo.wit.json` for `foo.simf`
+const WITNESS_SUFFIXES: [&str; 2] = ["wit.json", "wit.toml"];
+
+/// A parsed test manifest
+#[derive(Debug, Clone, Deserialize)]
+pub struct Manifest {
+    /// Test suites, in declaration order
+    #[serde(default, rename = "suite")]
+    pub suites: Vec<SuiteSpec>,
+}
+
+/// A suite of tests bound to a single network
+#[derive(Debug, Clone, Deserialize)]
+pub struct SuiteSpec {
+    /// Suite name, also used as the report file name
+    pub name: String,
+    /// Network name: `regtest`, `testnet` or `liquid`
+    #[serde(default = "default_network")]
+    pub network: String,
+    /// Node config file, required for `testnet` and `liquid`
+    #[serde(default)]
+    pub config: Option<PathBuf>,
+    /// Confirmation target for tests that set neither `fee` nor their own
+    /// `conf_target`
+    #[serde(default)]
+    pub conf_target: Option<u16>,
+    /// Tests in this suite
+    #[serde(default, rename = "test")]
+    pub tests: Vec<TestSpec>,
+    /// Settings of the suite's backend besides its config file, set with
+    /// [`Manifest::set_backend`]
+    #[serde(skip)]
+    pub backend: BackendConfig,
+}
+
+/// A single file-based test
+#[derive(Debug, Clone, Deserialize)]
+pub struct TestSpec {
+    /// Test name
+    pub name: String,
+    /// Path to the `.simf` program
+    pub file: PathBuf,
+    /// Path to the arguments file (JSON or TOML)
+    #[serde(default)]
+    pub args: Option<PathBuf>,
+    /// Path to the witness file (JSON or TOML)
+    #[serde(default)]
+    pub witness: Option<PathBuf>,
+    /// Funding amount in satoshis
+    #[serde(default)]
+    pub amount: Option<u64>,
+    /// Fee in satoshis
+    #[serde(default)]
+    pub fee: Option<u64>,
+    /// Confirmation target in blocks for the node's fee rate estimate
+    ///
+    /// Takes precedence over `fee`.
+    #[serde(default)]
+    pub conf_target: Option<u16>,
+    /// Lock time for the spending transaction
+    #[serde(default)]
+    pub lock_time: Option<u32>,
+    /// Sequence number for the spending transaction
+    #[serde(default)]
+    pub sequence: Option<u32>,
+    /// Where the spend pays to: `wallet` (the default), an address or a
+    /// descriptor
+    #[serde(default)]
+    pub destination: Destination,
+    /// Outputs of the funding transaction, in order
+    ///
+    /// Defaults to whatever `sendtoaddress` produces.
+    #[serde(default)]
+    pub funding: Option<FundingShape>,
+    /// Submit the funding transaction and the spend together as a package
+    ///
+    /// The spend is built on the unconfirmed funding transaction, for
+    /// contracts designed around zero-conf chained spends.
+    #[serde(default)]
+    pub package: bool,
+}
+
+fn default_network() -> String {
+    "regtest".to_string()
+}
+
+/// Parse a network name as used in manifests and config files
+///
+/// # Errors
+///
+/// Returns an error if the name is not a known network.
+pub fn parse_network(name: &str) -> Result<Network, SprayError> {
+    match name {
+        "regtest" => Ok(Network::Regtest),
+        "testnet" => Ok(Network::Testnet),
+        "liquid" | "liquidv1" => Ok(Network::Liquid),
+        _ => Err(SprayError::ConfigError(format!("Unknown network: {name}"))),
+    }
+}
+
+impl Manifest {
+    /// Load a manifest from a TOML file
+    ///
+    /// Relative paths inside the manifest are resolved against the
+    /// manifest's directory.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be read or parsed, or declares an
+    /// unknown network.
+    pub fn load(path: &Path) -> Result<Self, SprayError> {
+        let contents = std::fs::read_to_string(path)?;
+        let mut manifest: Self = toml::from_str(&contents)
+            .map_err(|e| SprayError::ParseError(format!("TOML parse error: {e}")))?;
+
+        let base = path.parent().unwrap_or_else(|| Path::new("."));
+        for suite in &mut manifest.suites {
+            parse_network(&suite.network)?;
+            suite.config = suite.config.take().map(|p| base.join(p));
+            for test in &mut suite.tests {
+                test.file = base.join(&test.file);
+                test.args = test.args.take().map(|p| base.join(p));
+                test.witness = test.witness.take().map(|p| base.join(p));
+                if test.fee.is_none() {
+                    test.conf_target = test.conf_target.or(suite.conf_target);
+                }
+            }
+        }
+
+        Ok(manifest)
+    }
+
+    /// Estimate the fee of every test for `conf_target` blocks
+    ///
+    /// Overrides the fees and confirmation targets set in the manifest.
+    pub fn set_conf_target(&mut self, conf_target: u16) {
+        for suite in &mut self.suites {
+            suite.conf_target = Some(conf_target);
+            for test in &mut suite.tests {
+                test.conf_target = Some(conf_target);
+            }
+        }
+    }
+
+    /// Create every suite's backend with the settings of `backend`, e.g.
+    /// those of spray's global options
+    ///
+    /// Each suite keeps its own config file.
+    pub fn set_backend(&mut self, backend: &BackendConfig) {
+        for suite in &mut self.suites {
+            suite.backend = backend.clone();
+        }
+    }
+
+    /// Suites that run against a local regtest node without a config file
+    pub fn local_suites(&self) -> impl Iterator<Item = &SuiteSpec> {
+        self.suites
+            .iter()
+            .filter(|s| s.network == "regtest" && s.config.is_none())
+    }
+}
+
+/// The `.simf` programs directly inside `dir`, sorted by name
+///
+/// # Errors
+///
+/// Returns an error if the directory cannot be read.
+pub fn discover_programs(dir: &Path) -> Result<Vec<PathBuf>, SprayError> {
+    let mut programs = Vec::new();
+    for entry in std::fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_file() && path.extension().and_then(|e| e.to_str()) == Some("simf") {
+            programs.push(path);
+        }
+    }
+    programs.sort();
+    Ok(programs)
+}
+
+/// The first existing companion file of `program` with one of `suffixes`
+fn companion(program: &Path, suffixes: &[&str]) -> Option<PathBuf> {
+    suffixes
+        .iter()
+        .map(|suffix| program.with_extension(suffix))
+        .find(|path| path.is_file())
+}
+
+impl SuiteSpec {
+    /// A regtest suite with one test per program
+    ///
+    /// See [`TestSpec::for_program`] for how arguments and witness files are
+    /// found.
+    #[must_use]
+    pub fn for_programs(name: &str, programs: &[PathBuf]) -> Self {
+        Self {
+            name: name.to_string(),
+            network: default_network(),
+            config: None,
+            conf_target: None,
+            tests: programs
+                .iter()
+                .map(|program| TestSpec::for_program(program))
+                .collect(),
+            backend: BackendConfig::default(),
+        }
+    }
+
+    /// Run every test in the suite against a freshly created backend
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the backend cannot be created. Individual test
+    /// failures are recorded in the report instead.
+    pub fn run(&self) -> Result<SuiteReport, SprayError> {
+        self.run_with_progress(|_| {})
+    }
+
+    /// Run the suite like [`SuiteSpec::run`], also measuring the branch
+    /// coverage of each program across the passed tests
+    ///
+    /// The report's `coverage` lists every program with a passed test.
+    /// Programs are told apart by CMR, so a program instantiated with
+    /// different arguments is covered separately.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the backend cannot be created.
+    pub fn run_with_coverage(&self) -> Result<SuiteReport, SprayError> {
+        self.run_with_coverage_and_progress(|_| {})
+    }
+
+    /// Run the suite like [`SuiteSpec::run_with_coverage`], calling
+    /// `progress` as each test starts and finishes
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the backend cannot be created.
+    pub fn run_with_coverage_and_progress(
+        &self,
+        mut progress: impl FnMut(Progress<'_>),
+    ) -> Result<SuiteReport, SprayError> {
+        let programs = ProgramCache::default();
+        let mut coverage: Vec<Coverage> = Vec::new();
+        let report = self.run_cached(&programs, |event| {
+            if let Progress::Finished {
+                test,
+                spend: Some(spend),
+                ..
+            } = event
+            {
+                let program_coverage = test
+                    .compile(&programs)
+                    .and_then(|(program, _)| Coverage::for_program(&program));
+                if let Ok(program_coverage) = program_coverage {
+                    let index = coverage
+                        .iter()
+                        .position(|c| c.cmr == program_coverage.cmr)
+                        .unwrap_or_else(|| {
+                            coverage.push(program_coverage.named(&test.file.display().to_string()));
+                            coverage.len() - 1
+                        });
+                    coverage[index].record_spend(&spend.tx);
+                }
+            }
+            progress(event);
+        })?;
+        Ok(SuiteReport { coverage, ..report })
+    }
+
+    /// Run the suite like [`SuiteSpec::run`], calling `progress` as each
+    /// test starts and finishes
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the backend cannot be created.
+    pub fn run_with_progress(
+        &self,
+        progress: impl FnMut(Progress<'_>),
+    ) -> Result<SuiteReport, SprayError> {
+        self.run_cached(&ProgramCache::default(), progress)
+    }
+
+    /// Run the suite like [`SuiteSpec::run_with_progress`], instantiating
+    /// its programs through `programs`
+    fn run_cached(
+        &self,
+        programs: &ProgramCache,
+        mut progress: impl FnMut(Progress<'_>),
+    ) -> Result<SuiteReport, SprayError> {
+        let network = parse_network(&self.network)?;
+        let mut backend = self
+            .backend
+            .with_config(self.config.clone())
+            .create(network)?;
+        let preset = Preset::for_network(network)?;
+
+        let mut records = Vec::new();
+        for (index, test) in self.tests.iter().enumerate() {
+            crate::status!("{} [{}] {}", "⏳".yellow(), self.name, test.name.bold());
+            progress(Progress::Started { index, test });
+
+            let start = Instant::now();
+            let (result, stage) = match test.run(&mut backend, &preset, programs) {
+                Ok(spend) => (TestResult::from_spend(spend), None),
+                Err((stage, e)) => {
+                    let error = e.to_string();
+                    let stage = stage.refine(&error);
+                    (TestResult::Failure { error }, Some(stage))
+                }
+            };
+            #[allow(clippy::cast_possible_truncation)]
+            let duration_ms = start.elapsed().as_millis() as u64;
+
+            match &result {
+                TestResult::Success { txid, .. } => crate::status!(
+                    "{} [{}] {} (txid: {txid})",
+                    "✅".green(),
+                    self.name,
+                    test.name.bold()
+                ),
+                TestResult::Failure { error } => crate::status!(
+                    "{} [{}] {}: {}",
+                    "❌".red(),
+                    self.name,
+                    test.name.bold(),
+                    error.red()
+                ),
+            }
+
+            let mut record = TestRecord::from_result(&test.name, duration_ms, &result);
+            record.stage = stage;
+            record.conf_target = test.fee_policy_with(preset.fee).conf_target();
+            if let Some(budget) = record.budget {
+                crate::status!("   {} {budget}", "Cost:".dimmed());
+            }
+            progress(Progress::Finished {
+                index,
+                test,
+                record: &record,
+                spend: result.spend(),
+            });
+            records.push(record);
+        }
+
+        Ok(SuiteReport::new(records, DEFAULT_SLOWEST_COUNT))
+    }
+}
+
+impl TestSpec {
+    /// A test of `program` named after its file stem
+    ///
+    /// By convention, `foo.simf` takes its arguments from `foo.args.json`
+    /// (or `foo.args.toml`) and its witness from `foo.wit.json` (or
+    /// `foo.wit.toml`) next to it, if they exist.
+    #[must_use]
+    pub fn for_program(program: &Path) -> Self {
+        Self {
+            name: program.file_stem().map_or_else(
+                || program.display().to_string(),
+                |s| s.to_string_lossy().into_owned(),
+            ),
+            file: program.to_path_buf(),
+            args: companion(program, &ARGS_SUFFIXES),
+            witness: companion(program, &WITNESS_SUFFIXES),
+            amount: None,
+            fee: None,
+            conf_target: None,
+            lock_time: None,
+            sequence: None,
+            destination: Destination::Wallet,
+            funding: None,
+            package: false,
+        }
+    }
+
+    /// Fee policy of the spending transaction
+    ///
+    /// A confirmation target estimates the fee rate with the node, falling
+    /// back to [`FALLBACK_FEERATE`](crate::fee::FALLBACK_FEERATE); otherwise
+    /// the fee is fixed, at [`DEFAULT_FEE`] if the test sets none.
+    #[must_use]
+    pub fn fee_policy(&self) -> FeePolicy {
+        self.fee_policy_with(DEFAULT_FEE)
+    }
+
+    /// Fee policy of the spending transaction, with a fixed fee of
+    /// `default_fee` if the test sets neither a fee nor a target
+    #[must_use]
+    pub fn fee_policy_with(&self, default_fee: u64) -> FeePolicy {
+        match (self.conf_target, self.fee) {
+            (Some(conf_target), _) => FeePolicy::Estimate { conf_target },
+            (None, Some(fee)) => FeePolicy::Fixed(fee),
+            (None, None) => FeePolicy::Fixed(default_fee),
+        }
+    }
+
+    /// Instantiate the program with its arguments through `programs` and
+    /// load its witness, whose signatures are made per spend (see
+    /// [`SigningWitness`])
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the program, arguments or witness cannot be
+    /// loaded, or the program fails to compile.
+    pub fn compile(
+        &self,
+        programs: &ProgramCache,
+    ) -> Result<(InstantiatedProgram, SigningWitness), SprayError> {
+        let compiled = programs.load(&self.file, self.args.as_deref(), &[])?;
+        let witness =
+            file_loader::load_signing_witness(self.witness.as_deref(), &[], &HashMap::new())?;
+        Ok((compiled, witness))
+    }
+
+    /// Compile (through `programs`), fund and spend the program against
+    /// `backend`, taking the defaults the test does not set from `preset`
+    ///
+    /// # Errors
+    ///
+    /// Returns the failing [`FailureStage`] together with the error.
+    pub fn run(
+        &self,
+        backend: &mut NetworkBackend,
+        preset: &Preset,
+        programs: &ProgramCache,
+    ) -> Result<SpendDetails, (FailureStage, SprayError)> {
+        let funding = |e: SprayError| (FailureStage::Funding, e);
+
+        // Compile
+        let (compiled, witness) = self
+            .compile(programs)
+            .map_err(|e| (FailureStage::Compile, e))?;
+
+        // Fund
+        let address = compiled.address(backend.address_params());
+        let amount = self.amount.unwrap_or(preset.funding_amount);
+        let (funding_txid, funding_tx) = test::fund(
+            |method, params| backend.call(method, params),
+            || {
+                backend
+                    .send_to_address(&address, amount)
+                    .map_err(|e| SprayError::RpcError(e.to_string()))
+            },
+            &address,
+            amount,
+            self.funding.as_ref(),
+            self.package,
+        )
+        .map_err(funding)?;
+        let tx = match &funding_tx {
+            Some(tx) => tx.clone(),
+            None => {
+                backend
+                    .confirm_with(&funding_txid, preset)
+                    .map_err(funding)?;
+                backend
+                    .get_transaction(&funding_txid)
+                    .map_err(|e| funding(SprayError::RpcError(e.to_string())))?
+            }
+        };
+        let utxo = test::find_utxo(&tx, funding_txid, &address.script_pubkey()).map_err(funding)?;
+
+        // Spend
+        let destination = self
+            .destination
+            .resolve(
+                |method, params| backend.call(method, params),
+                || {
+                    backend
+                        .get_new_address()
+                        .map_err(|e| SprayError::RpcError(e.to_string()))
+                },
+            )
+            .map_err(|e| (FailureStage::Config, e))?;
+        let spend = Spend {
+            program: &compiled,
+            utxo: &utxo,
+            genesis_hash: backend.genesis_hash().map_err(funding)?,
+            lock_time: self
+                .lock_time
+                .map_or(LockTime::ZERO, LockTime::from_consensus),
+            sequence: self
+                .sequence
+                .map_or(Sequence::MAX, Sequence::from_consensus),
+            destination: &destination,
+            blind: None,
+            preset,
+            fee_policy: self.fee_policy_with(preset.fee),
+            deterministic: backend.is_deterministic(),
+        }
+        .build(
+            |sighash| witness.resolve(sighash),
+            |conf_target| backend.estimate_feerate(conf_target),
+        )?;
+
+        test::submit(
+            |method, params| backend.call(method, params),
+            |tx| {
+                backend
+                    .broadcast(tx)
+                    .map(|_| ())
+                    .map_err(|e| SprayError::RpcError(e.to_string()))
+            },
+            funding_tx.as_ref(),
+            &spend.tx,
+        )?;
+
+        Ok(spend)
+    }
+}
+
+/// Run suites concurrently, one thread per suite
+///
+/// Returns the report (or setup error) of each suite, in the same order as
+/// `suites`.
+#[must_use]
+pub fn run_concurrently(suites: &[&SuiteSpec]) -> Vec<Result<SuiteReport, SprayError>> {
+    std::thread::scope(|scope| {
+        let handles: Vec<_> = suites
+            .iter()
+            .map(|suite| scope.spawn(move || suite.run()))
+            .collect();
+
+        handles
+            .into_iter()
+            .map(|handle| {
+                handle
+                    .join()
+                    .unwrap_or_else(|_| Err(SprayError::TestError("Suite thread panicked".into())))
+            })
+            .collect()
+    })
+}
@@ -0,0 +1,203 @@
+//! Message catalog
+//!
+//! Headline messages and error kinds have stable message IDs, such as
+//! `deploy.success` or `error.rpc`. JSON output carries the ID next to the
+//! English text, so that wrappers can map outcomes to their own UX without
+//! parsing prose. The catalog of templates behind the IDs can be
+//! translated; it renders the `message` of every JSON document, the error
+//! printed by a failing command, and the human-readable headlines of
+//! `compile`, `deploy` and `redeem` (success, dry run, PSET export,
+//! cancellation). Everything else a command prints, such as progress lines,
+//! labels and test summaries, is English only.
+//!
+//! A translation is a JSON or TOML file mapping message IDs to templates,
+//! selected with the `SPRAY_MESSAGES` environment variable. Nested tables
+//! are joined with dots, so `[deploy] success = "..."` sets `deploy.success`.
+//! IDs missing from the file keep their English template. Error templates
+//! take the error's details as `{detail}`.
+//!
+//! # Example
+//!
+//! ```
+//! use spray::messages::Catalog;
+//! use spray::SprayError;
+//!
+//! let english = Catalog::english();
+//! assert_eq!(english.render("deploy.success", &[]), "Deployment successful!");
+//!
+//! let error = SprayError::RpcError("connection refused".into());
+//! assert_eq!(english.error(&error), error.to_string());
+//!
+//! let german = english.with_overrides(&serde_json::json!({
+//!     "error": { "rpc": "RPC-Fehler: {detail}" }
+//! }));
+//! assert_eq!(german.error(&error), "RPC-Fehler: connection refused");
+//! assert_eq!(german.render("deploy.success", &[]), "Deployment successful!");
+//! ```
+
+use crate::error::SprayError;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::OnceLock;
+
+/// Environment variable naming a translation file
+pub const CATALOG_ENV: &str = "SPRAY_MESSAGES";
+
+/// Message IDs and their English templates
+pub const ENGLISH: &[(&str, &str)] = &[
+    ("compile.success", "Compilation successful!"),
+    ("deploy.success", "Deployment successful!"),
+    ("deploy.dry_run", "Dry run: transaction not broadcast"),
+    ("redeem.success", "Redemption successful!"),
+    ("redeem.dry_run", "Dry run: transaction not broadcast"),
+    ("redeem.pset_exported", "PSET exported!"),
+    (
+        "redeem.cancelled",
+        "Redemption cancelled; the witness file was kept",
+    ),
+    ("test.passed", "All tests passed"),
+    ("test.failed", "Some tests failed"),
+    ("bench.success", "Benchmark complete"),
+    ("balance.success", "Balance retrieved"),
+    ("utxos.success", "UTXOs listed"),
+    ("watch.funded", "Contract funded"),
+    ("serve.health", "Server running"),
+    (
+        "error.environment",
+        "Failed to setup test environment: {detail}",
+    ),
+    ("error.daemon", "Failed to start daemon: {detail}"),
+    ("error.test", "Test execution failed: {detail}"),
+    ("error.program", "Program error: {detail}"),
+    ("error.spend", "Spend error: {detail}"),
+    ("error.io", "IO error: {detail}"),
+    ("error.json", "JSON error: {detail}"),
+    ("error.rpc", "RPC error: {detail}"),
+    ("error.config", "Configuration error: {detail}"),
+    ("error.parse", "Parse error: {detail}"),
+    ("error.file_format", "File format error: {detail}"),
+    ("error.invalid_utxo_ref", "Invalid UTXO reference: {detail}"),
+    ("error.incompatible_node", "Incompatible node: {detail}"),
+    ("error.outpoint_locked", "Outpoint locked: {detail}"),
+    ("error.signature", "Signature error: {detail}"),
+    ("error.node_unavailable", "Node unavailable: {detail}"),
+    (
+        "error.mempool_reject",
+        "Transaction rejected by the mempool: {detail}",
+    ),
+    ("error.insufficient_funds", "Insufficient funds: {detail}"),
+    (
+        "error.witness_type_mismatch",
+        "Witness type mismatch: {detail}",
+    ),
+    (
+        "error.daemon_startup_timeout",
+        "Daemon startup timed out: {detail}",
+    ),
+];
+
+/// Templates by message ID
+#[derive(Debug, Clone)]
+pub struct Catalog {
+    templates: HashMap<String, String>,
+}
+
+impl Catalog {
+    /// The built-in English catalog
+    #[must_use]
+    pub fn english() -> Self {
+        Self {
+            templates: ENGLISH
+                .iter()
+                .map(|(id, template)| ((*id).to_string(), (*template).to_string()))
+                .collect(),
+        }
+    }
+
+    /// The English catalog with the translations in a JSON or TOML file
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be read or parsed.
+    pub fn load(path: &Path) -> Result<Self, SprayError> {
+        let contents = std::fs::read_to_string(path)?;
+        let overrides: Value = match path.extension().and_then(|e| e.to_str()) {
+            Some("toml") => toml::from_str(&contents)
+                .map_err(|e| SprayError::ParseError(format!("TOML parse error: {e}")))?,
+            _ => serde_json::from_str(&contents)?,
+        };
+        Ok(Self::english().with_overrides(&overrides))
+    }
+
+    /// This catalog with templates replaced by those in `overrides`, an
+    /// object of IDs (or nested tables of ID parts) to templates
+    #[must_use]
+    pub fn with_overrides(mut self, overrides: &Value) -> Self {
+        let mut stack = vec![(String::new(), overrides)];
+        while let Some((prefix, value)) = stack.pop() {
+            match value {
+                Value::Object(map) => {
+                    for (key, value) in map {
+                        let id = if prefix.is_empty() {
+                            key.clone()
+                        } else {
+                            format!("{prefix}.{key}")
+                        };
+                        stack.push((id, value));
+                    }
+                }
+                Value::String(template) => {
+                    self.templates.insert(prefix, template.clone());
+                }
+                _ => {}
+            }
+        }
+        self
+    }
+
+    /// Render message `id`, replacing each `{name}` with its value in
+    /// `params`
+    ///
+    /// Unknown IDs render as the ID itself.
+    #[must_use]
+    pub fn render(&self, id: &str, params: &[(&str, &str)]) -> String {
+        let mut text = self
+            .templates
+            .get(id)
+            .cloned()
+            .unwrap_or_else(|| id.to_string());
+        for (name, value) in params {
+            text = text.replace(&format!("{{{name}}}"), value);
+        }
+        text
+    }
+
+    /// Render an error from its message ID and details
+    #[must_use]
+    pub fn error(&self, error: &SprayError) -> String {
+        self.render(error.message_id(), &[("detail", &error.detail())])
+    }
+}
+
+/// The process-wide catalog: English, with the translations named by
+/// [`CATALOG_ENV`] if it is set
+///
+/// A translation that cannot be loaded is reported once and ignored.
+#[must_use]
+pub fn catalog() -> &'static Catalog {
+    static CATALOG: OnceLock<Catalog> = OnceLock::new();
+    CATALOG.get_or_init(|| match std::env::var_os(CATALOG_ENV) {
+        Some(path) => Catalog::load(Path::new(&path)).unwrap_or_else(|e| {
+            eprintln!("Warning: ignoring {CATALOG_ENV}: {e}");
+            Catalog::english()
+        }),
+        None => Catalog::english(),
+    })
+}
+
+/// Render message `id` from the process-wide catalog
+#[must_use]
+pub fn text(id: &str) -> String {
+    catalog().render(id, &[])
+}
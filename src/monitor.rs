@@ -0,0 +1,283 @@
+//! Contract monitoring
+//!
+//! `spray monitor` follows the chain block by block and reports what
+//! happens to a set of watched contracts: outputs paying to a contract and
+//! transactions spending them, each with the contract's balance before and
+//! after. [`Monitor`] holds the contracts' unspent outputs and turns blocks,
+//! as returned by `getblock <hash> 2`, into [`WatchEvent`]s, which the
+//! [`alerts`](crate::alerts) rules are evaluated against.
+//!
+//! Reorganizations are not tracked: a block is processed once, when it is
+//! first seen.
+//!
+//! # Example
+//!
+//! ```
+//! use musk::elements::Script;
+//! use serde_json::json;
+//! use spray::monitor::{Monitor, WatchEvent};
+//!
+//! let script = Script::from(vec![0x51, 0x20]);
+//! let mut monitor = Monitor::new([("vault".to_string(), script)]);
+//!
+//! let block = json!({ "tx": [{
+//!     "txid": "aa",
+//!     "vin": [],
+//!     "vout": [{ "value": 0.001, "scriptPubKey": { "hex": "5120" } }],
+//! }]});
+//! let events = monitor.process_block(&block, 101).unwrap();
+//! assert!(matches!(&events[0], WatchEvent::Funded { amount: Some(100_000), .. }));
+//! assert_eq!(monitor.balance("vault"), 100_000);
+//! ```
+
+use crate::client::btc_to_sats;
+use crate::error::SprayError;
+use crate::hex;
+use musk::elements::Script;
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::HashMap;
+
+/// An output of a transaction spending a contract
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct SpendOutput {
+    /// Output script (hex)
+    pub script_pubkey: String,
+    /// Amount in satoshis, `None` if confidential
+    pub amount: Option<u64>,
+}
+
+/// Something that happened to a watched contract
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum WatchEvent {
+    /// An output paying to the contract was confirmed
+    Funded {
+        /// Name of the contract
+        contract: String,
+        /// Funding transaction
+        txid: String,
+        /// Index of the output
+        vout: u32,
+        /// Amount in satoshis, `None` if confidential
+        amount: Option<u64>,
+        /// Height of the block
+        height: u64,
+        /// Explicit balance before the event
+        balance_before: u64,
+        /// Explicit balance after the event
+        balance_after: u64,
+    },
+    /// A transaction spending outputs of the contract was confirmed
+    Spent {
+        /// Name of the contract
+        contract: String,
+        /// Spending transaction
+        txid: String,
+        /// Contract outputs spent, as `txid:vout`
+        outpoints: Vec<String>,
+        /// Outputs of the spending transaction
+        outputs: Vec<SpendOutput>,
+        /// Height of the block
+        height: u64,
+        /// Explicit balance before the event
+        balance_before: u64,
+        /// Explicit balance after the event
+        balance_after: u64,
+    },
+}
+
+impl WatchEvent {
+    /// Name of the contract the event concerns
+    #[must_use]
+    pub fn contract(&self) -> &str {
+        match self {
+            Self::Funded { contract, .. } | Self::Spent { contract, .. } => contract,
+        }
+    }
+
+    /// The transaction the event concerns
+    #[must_use]
+    pub fn txid(&self) -> &str {
+        match self {
+            Self::Funded { txid, .. } | Self::Spent { txid, .. } => txid,
+        }
+    }
+
+    /// The contract's explicit balance before and after the event
+    #[must_use]
+    pub const fn balances(&self) -> (u64, u64) {
+        match self {
+            Self::Funded {
+                balance_before,
+                balance_after,
+                ..
+            }
+            | Self::Spent {
+                balance_before,
+                balance_after,
+                ..
+            } => (*balance_before, *balance_after),
+        }
+    }
+}
+
+/// An unspent output of a watched contract
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Unspent {
+    contract: String,
+    amount: Option<u64>,
+}
+
+/// Follows the unspent outputs of a set of contracts
+#[derive(Debug, Clone, Default)]
+pub struct Monitor {
+    /// Output script (hex) of each contract, by name
+    contracts: Vec<(String, String)>,
+    unspent: HashMap<(String, u32), Unspent>,
+}
+
+impl Monitor {
+    /// Watch the contracts locked by the given output scripts
+    #[must_use]
+    pub fn new(contracts: impl IntoIterator<Item = (String, Script)>) -> Self {
+        Self {
+            contracts: contracts
+                .into_iter()
+                .map(|(name, script)| (name, hex::encode(script.as_bytes())))
+                .collect(),
+            unspent: HashMap::new(),
+        }
+    }
+
+    /// The watched contracts with their output scripts (hex)
+    pub fn contracts(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.contracts
+            .iter()
+            .map(|(name, script)| (name.as_str(), script.as_str()))
+    }
+
+    /// Record an output of `contract` that is already unspent, e.g. as found
+    /// by `scantxoutset` when monitoring starts
+    pub fn add_unspent(&mut self, contract: &str, txid: &str, vout: u32, amount: Option<u64>) {
+        self.unspent.insert(
+            (txid.to_string(), vout),
+            Unspent {
+                contract: contract.to_string(),
+                amount,
+            },
+        );
+    }
+
+    /// Sum of the explicit amounts of `contract`'s unspent outputs
+    #[must_use]
+    pub fn balance(&self, contract: &str) -> u64 {
+        self.unspent
+            .values()
+            .filter(|unspent| unspent.contract == contract)
+            .filter_map(|unspent| unspent.amount)
+            .sum()
+    }
+
+    /// Number of unspent outputs of `contract`
+    #[must_use]
+    pub fn utxo_count(&self, contract: &str) -> usize {
+        self.unspent
+            .values()
+            .filter(|unspent| unspent.contract == contract)
+            .count()
+    }
+
+    /// Process a block returned by `getblock <hash> 2`
+    ///
+    /// Within a transaction, spends are reported before fundings.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the block is malformed.
+    pub fn process_block(
+        &mut self,
+        block: &Value,
+        height: u64,
+    ) -> Result<Vec<WatchEvent>, SprayError> {
+        let malformed = || SprayError::RpcError("Invalid getblock response".into());
+
+        let mut events = Vec::new();
+        for tx in block["tx"].as_array().ok_or_else(malformed)? {
+            let txid = tx["txid"].as_str().ok_or_else(malformed)?;
+            let outputs: Vec<SpendOutput> = tx["vout"]
+                .as_array()
+                .ok_or_else(malformed)?
+                .iter()
+                .map(|output| SpendOutput {
+                    script_pubkey: output["scriptPubKey"]["hex"]
+                        .as_str()
+                        .unwrap_or_default()
+                        .to_string(),
+                    amount: output["value"].as_f64().map(btc_to_sats),
+                })
+                .collect();
+
+            // Spent contract outputs, grouped by contract in watch order
+            let mut spent: Vec<(String, Vec<String>)> = Vec::new();
+            for input in tx["vin"].as_array().into_iter().flatten() {
+                let (Some(prev), Some(vout)) = (input["txid"].as_str(), input["vout"].as_u64())
+                else {
+                    continue;
+                };
+                let Ok(vout) = u32::try_from(vout) else {
+                    continue;
+                };
+                let Some(unspent) = self.unspent.get(&(prev.to_string(), vout)) else {
+                    continue;
+                };
+                let outpoint = format!("{prev}:{vout}");
+                match spent.iter_mut().find(|(name, _)| *name == unspent.contract) {
+                    Some((_, outpoints)) => outpoints.push(outpoint),
+                    None => spent.push((unspent.contract.clone(), vec![outpoint])),
+                }
+            }
+            for (contract, outpoints) in spent {
+                let balance_before = self.balance(&contract);
+                for outpoint in &outpoints {
+                    if let Some((prev, vout)) = outpoint.rsplit_once(':') {
+                        let vout = vout.parse().unwrap_or_default();
+                        self.unspent.remove(&(prev.to_string(), vout));
+                    }
+                }
+                events.push(WatchEvent::Spent {
+                    balance_after: self.balance(&contract),
+                    contract,
+                    txid: txid.to_string(),
+                    outpoints,
+                    outputs: outputs.clone(),
+                    height,
+                    balance_before,
+                });
+            }
+
+            for (vout, output) in (0u32..).zip(&outputs) {
+                let Some(contract) = self
+                    .contracts
+                    .iter()
+                    .find(|(_, script)| *script == output.script_pubkey)
+                    .map(|(name, _)| name.clone())
+                else {
+                    continue;
+                };
+                let balance_before = self.balance(&contract);
+                self.add_unspent(&contract, txid, vout, output.amount);
+                events.push(WatchEvent::Funded {
+                    balance_after: self.balance(&contract),
+                    contract,
+                    txid: txid.to_string(),
+                    vout,
+                    amount: output.amount,
+                    height,
+                    balance_before,
+                });
+            }
+        }
+        Ok(events)
+    }
+}
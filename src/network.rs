@@ -2,22 +2,64 @@
 //!
 //! Provides a unified interface over ephemeral regtest nodes and external nodes
 
-use crate::client::ElementsClient;
-use crate::env::TestEnv;
-use crate::error::SprayError;
+use crate::chain::ChainParams;
+use crate::client::{btc_to_sats, format_btc_amount};
+use crate::compat::NodeInfo;
+use crate::error::{ResultExt, SprayError};
+use crate::fee::parse_estimatesmartfee;
+use crate::nigiri::{BackendKind, Nigiri};
+use crate::preset::Preset;
+use crate::retry::{self, is_missing_inputs, retry_transient, Backoff};
+use crate::vcr::{self, Recordable, Recorder, Replay};
 use musk::client::{ClientResult, NodeClient, Utxo};
-use musk::elements::{Address, BlockHash, Transaction, Txid};
+use musk::elements::encode::{deserialize, serialize_hex};
+use musk::elements::hex::FromHex;
+use musk::elements::{Address, AssetId, BlockHash, Transaction, Txid};
 use musk::{Network, RpcClient};
-use std::path::PathBuf;
+use serde_json::{json, Value};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::{Duration, Instant};
+
+#[cfg(feature = "daemon")]
+use {
+    crate::client::ElementsClient,
+    crate::env::{TestEnv, TestEnvBuilder},
+    elementsd::bitcoind::bitcoincore_rpc::RpcApi,
+};
+
+/// Interval between polls while waiting for a confirmation
+const CONFIRMATION_POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// A signed wallet transaction that has not been broadcast
+#[derive(Debug, Clone)]
+pub struct FundedTransaction {
+    /// Consensus-serialized transaction (hex)
+    pub hex: String,
+    /// Fee paid by the transaction in satoshis
+    pub fee: u64,
+}
 
 /// Network backend abstraction
 ///
 /// Supports both ephemeral local regtest nodes and external nodes via RPC
 pub enum NetworkBackend {
     /// Ephemeral local regtest node (created on-demand, destroyed on drop)
+    #[cfg(feature = "daemon")]
     Ephemeral(TestEnv),
-    /// External node via RPC (regtest, testnet, or liquid mainnet)
-    External(RpcClient),
+    /// External node via RPC (regtest, testnet, liquid mainnet, or a custom
+    /// chain described by the config's `[chain]` table), with the backoff of
+    /// its transient RPC failures
+    External(RpcClient, ChainParams, Backoff),
+    /// A running Nigiri: RPC through its Elements node, funding and
+    /// broadcasting through its chopsticks server, see [`crate::nigiri`]
+    Nigiri(RpcClient, Nigiri),
+    /// Another backend whose traffic is recorded to a fixture file, see
+    /// [`crate::vcr`]
+    Recording(Box<NetworkBackend>, Recorder),
+    /// Recorded traffic served back without a node, see [`crate::vcr`]
+    Replay(Replay),
 }
 
 impl NetworkBackend {
@@ -28,116 +70,989 @@ impl NetworkBackend {
     /// Returns an error if the genesis hash cannot be retrieved from an external node.
     pub fn genesis_hash(&mut self) -> Result<BlockHash, SprayError> {
         match self {
+            #[cfg(feature = "daemon")]
             Self::Ephemeral(env) => Ok(env.genesis_hash()),
-            Self::External(client) => client
+            Self::External(
+                _,
+                ChainParams {
+                    genesis_hash: Some(hash),
+                    ..
+                },
+                _,
+            ) => Ok(*hash),
+            Self::External(client, ..) | Self::Nigiri(client, _) => client
                 .genesis_hash()
                 .map_err(|e| SprayError::RpcError(e.to_string())),
+            Self::Recording(inner, recorder) => {
+                let hash = inner.genesis_hash();
+                recorder.record(
+                    "genesis_hash",
+                    &[],
+                    hash.as_ref().map_err(vcr::error_detail),
+                );
+                hash
+            }
+            Self::Replay(replay) => replay.replay("genesis_hash", &[]),
         }
     }
 
-    /// Get address params for this network
+    /// Get address params for this network, or those of the custom chain
     #[must_use]
-    pub const fn address_params(&self) -> &'static musk::elements::AddressParams {
+    pub fn address_params(&self) -> &'static musk::elements::AddressParams {
         match self {
+            #[cfg(feature = "daemon")]
             Self::Ephemeral(_) => &musk::elements::AddressParams::ELEMENTS,
-            Self::External(client) => client.address_params(),
+            Self::External(client, chain, _) => chain
+                .address_params
+                .unwrap_or_else(|| client.address_params()),
+            Self::Nigiri(client, _) => client.address_params(),
+            Self::Recording(inner, _) => inner.address_params(),
+            Self::Replay(replay) => crate::interop::address_params(replay.network())
+                .unwrap_or(&musk::elements::AddressParams::ELEMENTS),
+        }
+    }
+
+    /// Parameters of the custom chain the backend is configured for, if any
+    #[must_use]
+    pub fn chain(&self) -> Option<&ChainParams> {
+        match self {
+            #[cfg(feature = "daemon")]
+            Self::Ephemeral(_) => None,
+            Self::External(_, chain, _) => Some(chain),
+            Self::Recording(inner, _) => inner.chain(),
+            Self::Nigiri(..) | Self::Replay(_) => None,
+        }
+    }
+
+    /// Whether the backend's chain is thrown away when the command exits:
+    /// an ephemeral node, or traffic replayed from a fixture
+    #[must_use]
+    pub fn is_ephemeral(&self) -> bool {
+        match self {
+            #[cfg(feature = "daemon")]
+            Self::Ephemeral(_) => true,
+            Self::Replay(_) => true,
+            Self::Recording(inner, _) => inner.is_ephemeral(),
+            Self::External(..) | Self::Nigiri(..) => false,
+        }
+    }
+
+    /// Whether the backend is an ephemeral node running in
+    /// [deterministic mode](crate::deterministic)
+    #[must_use]
+    pub fn is_deterministic(&self) -> bool {
+        match self {
+            #[cfg(feature = "daemon")]
+            Self::Ephemeral(env) => env.is_deterministic(),
+            Self::Recording(inner, _) => inner.is_deterministic(),
+            Self::External(..) | Self::Nigiri(..) | Self::Replay(_) => false,
+        }
+    }
+
+    /// Policy asset of the custom chain, if one is configured
+    #[must_use]
+    pub fn policy_asset(&self) -> Option<AssetId> {
+        self.chain().and_then(|chain| chain.policy_asset)
+    }
+
+    /// Make a raw JSON-RPC call against the backend node
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the RPC call fails. Transient failures of
+    /// external nodes are retried first, see [`crate::retry`].
+    pub fn call(&self, method: &str, params: &[Value]) -> Result<Value, SprayError> {
+        match self {
+            #[cfg(feature = "daemon")]
+            Self::Ephemeral(env) => env
+                .daemon()
+                .client()
+                .call::<serde_json::Value>(method, params)
+                .map_err(|e| SprayError::rpc(e.to_string())),
+            Self::External(client, _, backoff) => call_external(client, *backoff, method, params),
+            Self::Nigiri(client, _) => client
+                .call::<serde_json::Value>(method, params)
+                .map_err(|e| SprayError::rpc(e.to_string())),
+            Self::Recording(inner, recorder) => {
+                let result = inner.call(method, params);
+                recorder.record(method, params, result.as_ref().map_err(vcr::error_detail));
+                result
+            }
+            Self::Replay(replay) => replay.replay(method, params),
+        }
+    }
+
+    /// Query the node's version and softfork deployments
+    ///
+    /// Falls back to `getblockchaininfo` on nodes without `getdeploymentinfo`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the RPC calls fail.
+    pub fn node_info(&self) -> Result<NodeInfo, SprayError> {
+        let network_info = self.call("getnetworkinfo", &[])?;
+        let deployment_info = self
+            .call("getdeploymentinfo", &[])
+            .or_else(|_| self.call("getblockchaininfo", &[]))?;
+        NodeInfo::from_rpc(&network_info, &deployment_info)
+    }
+
+    /// Verify that the node can validate Simplicity spends
+    ///
+    /// # Errors
+    ///
+    /// Returns [`SprayError::IncompatibleNode`] if the node is too old or
+    /// Simplicity is not active, or [`SprayError::NodeUnavailable`] if the
+    /// node cannot be queried.
+    pub fn check_compatibility(&self) -> Result<NodeInfo, SprayError> {
+        let info = self.node_info().map_err(|e| match e {
+            SprayError::RpcError(detail) => SprayError::NodeUnavailable(detail),
+            e => e,
+        })?;
+        info.check()?;
+        Ok(info)
+    }
+
+    /// Send an issued asset to an address
+    ///
+    /// `asset` may be an asset ID (hex) or a label known to the node; it is
+    /// passed as the `assetlabel` argument of `sendtoaddress`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the RPC call fails, e.g. when the wallet holds
+    /// insufficient funds of the asset.
+    pub fn send_asset_to_address(
+        &self,
+        addr: &Address,
+        amount: u64,
+        asset: &str,
+    ) -> Result<Txid, SprayError> {
+        use serde_json::Value;
+
+        // sendtoaddress address amount comment comment_to subtractfeefromamount
+        //     replaceable conf_target estimate_mode avoid_reuse assetlabel
+        let txid = self.call(
+            "sendtoaddress",
+            &[
+                addr.to_string().into(),
+                format_btc_amount(amount).into(),
+                Value::Null,
+                Value::Null,
+                Value::Null,
+                Value::Null,
+                Value::Null,
+                Value::Null,
+                Value::Null,
+                asset.into(),
+            ],
+        )?;
+
+        let txid_str = txid
+            .as_str()
+            .ok_or_else(|| SprayError::RpcError("Invalid txid response".into()))?;
+        Txid::from_str(txid_str).map_err(|e| SprayError::RpcError(e.to_string()))
+    }
+
+    /// Build and sign a wallet transaction paying `amount` to `addr`, without
+    /// broadcasting it
+    ///
+    /// The transaction is funded, blinded and signed by the wallet exactly as
+    /// `sendtoaddress` would, but is only returned. `asset` optionally selects
+    /// an issued asset (ID or label) instead of the policy asset.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the wallet cannot fund or sign the transaction.
+    pub fn build_funding_transaction(
+        &self,
+        addr: &Address,
+        amount: u64,
+        asset: Option<&str>,
+    ) -> Result<FundedTransaction, SprayError> {
+        use serde_json::{json, Value};
+
+        let address = addr.to_string();
+        let output_assets = asset.map_or(Value::Null, |asset| json!({ &address: asset }));
+
+        // createrawtransaction inputs outputs locktime replaceable output_assets
+        let raw = self.call(
+            "createrawtransaction",
+            &[
+                json!([]),
+                json!([{ &address: format_btc_amount(amount) }]),
+                Value::Null,
+                Value::Null,
+                output_assets,
+            ],
+        )?;
+        let funded = self.call("fundrawtransaction", &[raw])?;
+        let fee = funded["fee"]
+            .as_f64()
+            .ok_or_else(|| SprayError::RpcError("Invalid fundrawtransaction response".into()))?;
+        let blinded = self.call("blindrawtransaction", &[funded["hex"].clone()])?;
+        let signed = self.call("signrawtransactionwithwallet", &[blinded])?;
+
+        if signed["complete"].as_bool() != Some(true) {
+            return Err(SprayError::RpcError(
+                "Wallet could not sign the funding transaction".into(),
+            ));
+        }
+        let hex = signed["hex"]
+            .as_str()
+            .ok_or_else(|| SprayError::RpcError("Invalid signrawtransaction response".into()))?;
+
+        Ok(FundedTransaction {
+            hex: hex.to_string(),
+            fee: btc_to_sats(fee),
+        })
+    }
+
+    /// Query the node's fee rate estimate in sat/vB for a confirmation target
+    ///
+    /// Returns `None` if the node has no estimate or the call fails.
+    #[must_use]
+    pub fn estimate_feerate(&self, conf_target: u16) -> Option<f64> {
+        self.call("estimatesmartfee", &[conf_target.into()])
+            .ok()
+            .as_ref()
+            .and_then(parse_estimatesmartfee)
+    }
+
+    /// Get the height of the node's best chain
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the RPC call fails.
+    pub fn block_count(&self) -> Result<u64, SprayError> {
+        self.call("getblockcount", &[])?
+            .as_u64()
+            .ok_or_else(|| SprayError::RpcError("Invalid getblockcount response".into()))
+    }
+
+    /// Check whether a transaction is currently in the node's mempool
+    ///
+    /// # Errors
+    ///
+    /// Returns an error only if the node cannot be reached; a transaction
+    /// missing from the mempool yields `Ok(false)`.
+    pub fn in_mempool(&self, txid: &Txid) -> Result<bool, SprayError> {
+        let mempool = self.call("getrawmempool", &[])?;
+        let txid = txid.to_string();
+        Ok(mempool
+            .as_array()
+            .is_some_and(|txids| txids.iter().any(|t| t.as_str() == Some(txid.as_str()))))
+    }
+
+    /// Get the number of confirmations of a wallet transaction
+    ///
+    /// Returns 0 for a transaction that is still in the mempool.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the transaction is unknown to the wallet.
+    pub fn confirmations(&self, txid: &Txid) -> Result<i64, SprayError> {
+        self.call("gettransaction", &[txid.to_string().into()])?
+            .get("confirmations")
+            .and_then(serde_json::Value::as_i64)
+            .ok_or_else(|| SprayError::RpcError("Invalid gettransaction response".into()))
+    }
+
+    /// Get a transaction confirmed, mining a block on ephemeral nodes and
+    /// waiting for a confirmation on external ones
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if block generation fails or the transaction does not
+    /// confirm within `timeout`.
+    pub fn confirm(&self, txid: &Txid, timeout: Duration) -> Result<(), SprayError> {
+        match self {
+            #[cfg(feature = "daemon")]
+            Self::Ephemeral(env) => env.generate(1),
+            Self::External(..) => self.wait_for_confirmation(txid, timeout),
+            Self::Nigiri(..) => self.mine(1),
+            Self::Recording(inner, _) => inner.confirm(txid, timeout),
+            Self::Replay(_) => Ok(()),
+        }
+    }
+
+    /// Get a transaction confirmed under `preset`'s confirmation policy,
+    /// mining that many blocks on ephemeral nodes and waiting for that many
+    /// confirmations on external ones
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if block generation fails or the transaction does not
+    /// confirm within the preset's timeout.
+    pub fn confirm_with(&self, txid: &Txid, preset: &Preset) -> Result<(), SprayError> {
+        match self {
+            #[cfg(feature = "daemon")]
+            Self::Ephemeral(env) => env.generate(preset.confirmations.max(1)),
+            Self::External(..) => {
+                self.wait_for_confirmations(txid, preset.confirmations, preset.confirmation_timeout)
+            }
+            Self::Nigiri(..) => self.mine(preset.confirmations.max(1)),
+            Self::Recording(inner, _) => inner.confirm_with(txid, preset),
+            Self::Replay(_) => Ok(()),
+        }
+    }
+
+    /// Broadcast `tx`, retrying while its inputs are missing
+    ///
+    /// On an external node the funding transaction may not have propagated
+    /// or confirmed yet when the spend is broadcast. Such rejections are
+    /// retried according to `backoff`, calling `on_retry` with the retry
+    /// number, the delay and the error before each wait. Ephemeral nodes and
+    /// other rejections fail immediately. Replayed retries do not wait.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`SprayError::MempoolReject`] with the last error if the
+    /// node still rejects the transaction.
+    pub fn broadcast_with_retry(
+        &self,
+        tx: &Transaction,
+        backoff: Backoff,
+        mut on_retry: impl FnMut(u32, Duration, &str),
+    ) -> Result<Txid, SprayError> {
+        let retries = self.broadcast_retries(backoff);
+
+        let mut attempt = 0;
+        loop {
+            match self.broadcast(tx) {
+                Ok(txid) => return Ok(txid),
+                Err(e) => {
+                    let error = e.to_string();
+                    if attempt >= retries || !is_missing_inputs(&error) {
+                        return Err(SprayError::MempoolReject(error));
+                    }
+                    let delay = backoff.delay(attempt);
+                    attempt += 1;
+                    on_retry(attempt, delay, &error);
+                    if !matches!(self, Self::Replay(_)) {
+                        std::thread::sleep(delay);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Number of times a broadcast missing its inputs is retried
+    fn broadcast_retries(&self, backoff: Backoff) -> u32 {
+        match self {
+            #[cfg(feature = "daemon")]
+            Self::Ephemeral(_) => 0,
+            Self::External(..) | Self::Nigiri(..) | Self::Replay(_) => backoff.retries,
+            Self::Recording(inner, _) => inner.broadcast_retries(backoff),
         }
     }
+
+    /// Mine `count` blocks with the node's wallet
+    fn mine(&self, count: u32) -> Result<(), SprayError> {
+        self.generate_blocks(count)
+            .map(drop)
+            .map_err(|e| SprayError::RpcError(e.to_string()))
+    }
+
+    /// Wait until a wallet transaction has at least one confirmation
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the transaction is unknown to the wallet or does
+    /// not confirm within `timeout`.
+    pub fn wait_for_confirmation(&self, txid: &Txid, timeout: Duration) -> Result<(), SprayError> {
+        self.wait_for_confirmations(txid, 1, timeout)
+    }
+
+    /// Wait until `txid` has at least `confirmations` confirmations
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the RPC calls fail or the transaction does not
+    /// reach that depth within `timeout`.
+    pub fn wait_for_confirmations(
+        &self,
+        txid: &Txid,
+        confirmations: u32,
+        timeout: Duration,
+    ) -> Result<(), SprayError> {
+        let start = Instant::now();
+        while self.confirmations(txid)? < i64::from(confirmations.max(1)) {
+            if start.elapsed() >= timeout {
+                return Err(SprayError::RpcError(format!(
+                    "Timed out waiting for {txid} to confirm"
+                )));
+            }
+            std::thread::sleep(CONFIRMATION_POLL_INTERVAL);
+        }
+        Ok(())
+    }
 }
 
 impl NodeClient for NetworkBackend {
     fn send_to_address(&self, addr: &Address, amount: u64) -> ClientResult<Txid> {
         match self {
+            #[cfg(feature = "daemon")]
             Self::Ephemeral(env) => {
+                if env.is_deterministic() {
+                    return crate::deterministic::fund(|m, p| self.call(m, p), addr, amount)
+                        .map_err(|e| {
+                            musk::ProgramError::IoError(std::io::Error::other(e.to_string()))
+                        });
+                }
                 let client = ElementsClient::new(env.daemon());
                 client.send_to_address(addr, amount)
             }
-            Self::External(client) => client.send_to_address(addr, amount),
+            // Call the RPC directly so the amount is passed as an exact decimal
+            Self::External(..) => {
+                let txid = self
+                    .call(
+                        "sendtoaddress",
+                        &[addr.to_string().into(), format_btc_amount(amount).into()],
+                    )
+                    .map_err(|e| {
+                        musk::ProgramError::IoError(std::io::Error::other(e.to_string()))
+                    })?;
+                let txid_str = txid.as_str().ok_or_else(|| {
+                    musk::ProgramError::IoError(std::io::Error::other("Invalid txid response"))
+                })?;
+                Txid::from_str(txid_str)
+                    .map_err(|e| musk::ProgramError::IoError(std::io::Error::other(e.to_string())))
+            }
+            Self::Nigiri(_, nigiri) => nigiri
+                .faucet(&addr.to_string(), amount)
+                .map_err(|e| musk::ProgramError::IoError(std::io::Error::other(e.to_string()))),
+            Self::Recording(inner, recorder) => record(
+                recorder,
+                "send_to_address",
+                &[json!(addr.to_string()), json!(amount)],
+                inner.send_to_address(addr, amount),
+            ),
+            Self::Replay(replay) => replay_client(
+                replay,
+                "send_to_address",
+                &[json!(addr.to_string()), json!(amount)],
+            ),
         }
     }
 
     fn get_transaction(&self, txid: &Txid) -> ClientResult<Transaction> {
         match self {
+            #[cfg(feature = "daemon")]
             Self::Ephemeral(env) => {
                 let client = ElementsClient::new(env.daemon());
                 client.get_transaction(txid)
             }
-            Self::External(client) => client.get_transaction(txid),
+            Self::External(client, _, backoff) => {
+                idempotent(*backoff, || client.get_transaction(txid))
+            }
+            Self::Nigiri(client, _) => client.get_transaction(txid),
+            Self::Recording(inner, recorder) => record(
+                recorder,
+                "get_transaction",
+                &[json!(txid.to_string())],
+                inner.get_transaction(txid),
+            ),
+            Self::Replay(replay) => {
+                replay_client(replay, "get_transaction", &[json!(txid.to_string())])
+            }
         }
     }
 
     fn broadcast(&self, tx: &Transaction) -> ClientResult<Txid> {
         match self {
+            #[cfg(feature = "daemon")]
             Self::Ephemeral(env) => {
                 let client = ElementsClient::new(env.daemon());
                 client.broadcast(tx)
             }
-            Self::External(client) => client.broadcast(tx),
+            Self::External(client, _, backoff) => retry_transient(
+                *backoff,
+                || client.broadcast(tx),
+                || is_known(client, &tx.txid()).then(|| tx.txid()),
+            ),
+            Self::Nigiri(_, nigiri) => nigiri
+                .broadcast(&serialize_hex(tx))
+                .map_err(|e| musk::ProgramError::IoError(std::io::Error::other(e.to_string()))),
+            Self::Recording(inner, recorder) => {
+                record(recorder, "broadcast", &[tx.to_value()], inner.broadcast(tx))
+            }
+            Self::Replay(replay) => replay_client(replay, "broadcast", &[tx.to_value()]),
         }
     }
 
     fn generate_blocks(&self, count: u32) -> ClientResult<Vec<BlockHash>> {
         match self {
+            #[cfg(feature = "daemon")]
             Self::Ephemeral(env) => {
                 let client = ElementsClient::new(env.daemon());
                 client.generate_blocks(count)
             }
-            Self::External(client) => client.generate_blocks(count),
+            Self::External(client, ..) | Self::Nigiri(client, _) => client.generate_blocks(count),
+            Self::Recording(inner, recorder) => record(
+                recorder,
+                "generate_blocks",
+                &[json!(count)],
+                inner.generate_blocks(count),
+            ),
+            Self::Replay(replay) => replay_client(replay, "generate_blocks", &[json!(count)]),
         }
     }
 
     fn get_utxos(&self, address: &Address) -> ClientResult<Vec<Utxo>> {
         match self {
+            #[cfg(feature = "daemon")]
             Self::Ephemeral(env) => {
                 let client = ElementsClient::new(env.daemon());
                 client.get_utxos(address)
             }
-            Self::External(client) => client.get_utxos(address),
+            Self::External(..) | Self::Nigiri(..) => {
+                let (holdings, _) =
+                    crate::holdings::fetch(|method, params| self.call(method, params), address)
+                        .map_err(|e| {
+                            musk::ProgramError::IoError(std::io::Error::other(e.to_string()))
+                        })?;
+                Ok(holdings
+                    .iter()
+                    .filter_map(|holding| holding.to_utxo(address.script_pubkey()))
+                    .collect())
+            }
+            Self::Recording(inner, recorder) => record(
+                recorder,
+                "get_utxos",
+                &[json!(address.to_string())],
+                inner.get_utxos(address),
+            ),
+            Self::Replay(replay) => {
+                replay_client(replay, "get_utxos", &[json!(address.to_string())])
+            }
         }
     }
 
     fn get_new_address(&self) -> ClientResult<Address> {
         match self {
+            #[cfg(feature = "daemon")]
             Self::Ephemeral(env) => {
                 let client = ElementsClient::new(env.daemon());
                 client.get_new_address()
             }
-            Self::External(client) => client.get_new_address(),
+            Self::External(client, _, backoff) => idempotent(*backoff, || client.get_new_address()),
+            Self::Nigiri(client, _) => client.get_new_address(),
+            Self::Recording(inner, recorder) => {
+                record(recorder, "get_new_address", &[], inner.get_new_address())
+            }
+            Self::Replay(replay) => replay_client(replay, "get_new_address", &[]),
+        }
+    }
+}
+
+/// Make an RPC call against an external node, retrying transient failures
+/// of idempotent methods and of broadcasts the node does not know yet
+fn call_external(
+    client: &RpcClient,
+    backoff: Backoff,
+    method: &str,
+    params: &[Value],
+) -> Result<Value, SprayError> {
+    let call = || {
+        client
+            .call::<Value>(method, params)
+            .map_err(|e| SprayError::rpc(e.to_string()))
+    };
+    if method == "sendrawtransaction" {
+        let txid = params
+            .first()
+            .and_then(Value::as_str)
+            .and_then(|hex| Vec::<u8>::from_hex(hex).ok())
+            .and_then(|bytes| deserialize::<Transaction>(&bytes).ok())
+            .map(|tx| tx.txid());
+        retry_transient(backoff, call, || {
+            let txid = txid?;
+            is_known(client, &txid).then(|| Value::String(txid.to_string()))
+        })
+    } else if retry::is_idempotent(method) {
+        retry_transient(backoff, call, || None)
+    } else {
+        call()
+    }
+}
+
+/// Run an idempotent node client call, retrying transient failures by
+/// `backoff`
+fn idempotent<T>(backoff: Backoff, call: impl FnMut() -> ClientResult<T>) -> ClientResult<T> {
+    retry_transient(backoff, call, || None)
+}
+
+/// Whether the node knows a transaction, in its mempool or (with
+/// `-txindex`) in a block
+fn is_known(client: &RpcClient, txid: &Txid) -> bool {
+    client
+        .call::<Value>("getrawtransaction", &[txid.to_string().into()])
+        .is_ok()
+}
+
+/// Record the outcome of a node client call
+fn record<T: Recordable>(
+    recorder: &Recorder,
+    method: &str,
+    params: &[Value],
+    result: ClientResult<T>,
+) -> ClientResult<T> {
+    recorder.record(method, params, result.as_ref().map_err(ToString::to_string));
+    result
+}
+
+/// Answer a node client call from a recording
+fn replay_client<T: Recordable>(
+    replay: &Replay,
+    method: &str,
+    params: &[Value],
+) -> ClientResult<T> {
+    replay
+        .replay(method, params)
+        .map_err(|e| musk::ProgramError::IoError(std::io::Error::other(vcr::error_detail(&e))))
+}
+
+/// How the backends of a command reach their node: the node config file
+/// and the settings of spray's global options
+///
+/// Every backend created from the same config shares its recording or
+/// replay, see [`crate::vcr`].
+///
+/// ```
+/// use spray::network::BackendConfig;
+/// use spray::nigiri::BackendKind;
+/// use spray::retry::DEFAULT_RPC_BACKOFF;
+///
+/// let config = BackendConfig::default();
+/// assert_eq!(config.config, None);
+/// assert_eq!(config.kind, BackendKind::Ephemeral);
+/// assert_eq!(config.rpc_backoff, DEFAULT_RPC_BACKOFF);
+/// assert!(!config.deterministic);
+/// ```
+#[derive(Debug, Clone)]
+pub struct BackendConfig {
+    /// Node config file (`musk.toml`); without one, [`RPC_URL_ENV`] or else
+    /// [`kind`](Self::kind) picks the node
+    pub config: Option<PathBuf>,
+    /// Wallet RPC calls to external nodes go to, overriding the config's
+    /// `rpcwallet`
+    pub wallet: Option<String>,
+    /// Node of regtest commands without a config
+    pub kind: BackendKind,
+    /// Whether traffic is recorded or replayed, see [`crate::vcr`]
+    pub mode: Option<vcr::Mode>,
+    /// Whether to run ephemeral nodes in
+    /// [deterministic mode](crate::deterministic), refusing other nodes
+    pub deterministic: bool,
+    /// Backoff of transient RPC failures of external nodes, see
+    /// [`crate::retry`]
+    pub rpc_backoff: Backoff,
+}
+
+impl Default for BackendConfig {
+    fn default() -> Self {
+        Self::new(None)
+    }
+}
+
+impl BackendConfig {
+    /// Default settings for the node config file `config`, if any
+    #[must_use]
+    pub const fn new(config: Option<PathBuf>) -> Self {
+        Self {
+            config,
+            wallet: None,
+            kind: BackendKind::Ephemeral,
+            mode: None,
+            deterministic: false,
+            rpc_backoff: retry::DEFAULT_RPC_BACKOFF,
+        }
+    }
+
+    /// The same settings for another node config file, e.g. that of a
+    /// manifest suite
+    #[must_use]
+    pub fn with_config(&self, config: Option<PathBuf>) -> Self {
+        Self {
+            config,
+            ..self.clone()
+        }
+    }
+
+    /// Create a backend for `network`
+    ///
+    /// Without a config file, the node named by [`RPC_URL_ENV`] is used if
+    /// that is set.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if:
+    /// - Testnet is specified without a config file or [`RPC_URL_ENV`]
+    /// - Config file cannot be read or parsed
+    /// - RPC client cannot be created
+    /// - The node does not support Simplicity
+    /// - The node's genesis block differs from the config's `[chain]` genesis hash
+    /// - Deterministic mode is on and the node is not an ephemeral regtest node
+    pub fn create(&self, network: Network) -> Result<NetworkBackend, SprayError> {
+        let backend = match &self.mode {
+            Some(vcr::Mode::Replay(replay)) => NetworkBackend::Replay(replay.clone()),
+            Some(vcr::Mode::Record(recorder)) => {
+                recorder.set_network(crate::preset::Preset::builtin(network).name);
+                NetworkBackend::Recording(Box::new(self.connect(network)?), recorder.clone())
+            }
+            None => self.connect(network)?,
+        };
+        // Only a node spray starts has the pinned wallet seed and clock
+        if self.deterministic && !backend.is_ephemeral() {
+            return Err(SprayError::ConfigError(
+                "--deterministic needs an ephemeral regtest node, not one from a config file or the environment"
+                    .into(),
+            ));
+        }
+        backend.check_compatibility()?;
+        if let Some(chain) = backend.chain().filter(|chain| chain.genesis_hash.is_some()) {
+            let genesis = backend.call("getblockhash", &[0.into()])?;
+            let genesis = genesis
+                .as_str()
+                .and_then(|hash| BlockHash::from_str(hash).ok())
+                .ok_or_else(|| SprayError::RpcError("Invalid getblockhash response".into()))?;
+            chain.check_genesis(genesis)?;
+        }
+        Ok(backend)
+    }
+
+    /// Create a backend without checking node compatibility
+    fn connect(&self, network: Network) -> Result<NetworkBackend, SprayError> {
+        let wallet = self.wallet.as_deref();
+        if self.config.is_none() {
+            if let Some(contents) = rpc_config_from_env(network, wallet) {
+                let client = client_from_toml(&contents)?;
+                return Ok(NetworkBackend::External(
+                    client,
+                    ChainParams::default(),
+                    self.rpc_backoff,
+                ));
+            }
+        }
+        if matches!(network, Network::Regtest) && self.config.is_none() {
+            if let Some(nigiri) = running_nigiri(self.kind)? {
+                return Ok(NetworkBackend::Nigiri(nigiri.connect()?, nigiri));
+            }
+        }
+
+        match (network, &self.config) {
+            // Regtest without config: use ephemeral node
+            #[cfg(feature = "daemon")]
+            (Network::Regtest, None) => {
+                let env = TestEnvBuilder::new()
+                    .deterministic(self.deterministic)
+                    .build()?;
+                Ok(NetworkBackend::Ephemeral(env))
+            }
+            // Regtest without config and without the daemon feature: error
+            #[cfg(not(feature = "daemon"))]
+            (Network::Regtest, None) => Err(SprayError::ConfigError(
+                "Regtest requires --config when spray is built without the `daemon` feature".into(),
+            )),
+            // Regtest with config or testnet: use external node
+            (_, Some(config_path)) => {
+                let client = connect_external(config_path, wallet)?;
+                let chain = ChainParams::load(config_path)
+                    .with_context(|| format!("Failed to load {}", config_path.display()))?;
+                Ok(NetworkBackend::External(client, chain, self.rpc_backoff))
+            }
+            // Testnet without config: error
+            (Network::Testnet, None) => Err(SprayError::ConfigError(
+                "Testnet requires --config <musk.toml> or SPRAY_RPC_URL to specify node connection".into(),
+            )),
+            // Liquid mainnet
+            (Network::Liquid, None) => Err(SprayError::ConfigError(
+                "Liquid mainnet requires --config <musk.toml> or SPRAY_RPC_URL to specify node connection".into(),
+            )),
         }
     }
 }
 
-/// Create a network backend based on network type and optional config
+/// Create a network backend for `network` with the default settings of the
+/// node config file `config`, see [`BackendConfig::create`]
 ///
 /// # Errors
 ///
-/// Returns an error if:
-/// - Testnet is specified without a config file
-/// - Config file cannot be read or parsed
-/// - RPC client cannot be created
+/// Returns the errors of [`BackendConfig::create`].
 pub fn create_backend(
     network: Network,
     config: Option<PathBuf>,
 ) -> Result<NetworkBackend, SprayError> {
-    match (network, config) {
-        // Regtest without config: use ephemeral node
-        (Network::Regtest, None) => {
-            let env = TestEnv::new()?;
-            Ok(NetworkBackend::Ephemeral(env))
-        }
-        // Regtest with config or testnet: use external node
-        (_, Some(config_path)) => {
-            let client = RpcClient::from_config_file(&config_path.to_string_lossy())
-                .map_err(|e| SprayError::RpcError(e.to_string()))?;
-            Ok(NetworkBackend::External(client))
-        }
-        // Testnet without config: error
-        (Network::Testnet, None) => Err(SprayError::ConfigError(
-            "Testnet requires --config <musk.toml> to specify node connection".into(),
-        )),
-        // Liquid mainnet
-        (Network::Liquid, None) => Err(SprayError::ConfigError(
-            "Liquid mainnet requires --config <musk.toml> to specify node connection".into(),
-        )),
+    BackendConfig::new(config).create(network)
+}
+
+/// The endpoint of `wallet` on the node at `url`
+///
+/// ```
+/// use spray::network::wallet_url;
+///
+/// assert_eq!(
+///     wallet_url("http://localhost:7041/", "hot wallet"),
+///     "http://localhost:7041/wallet/hot%20wallet"
+/// );
+/// ```
+#[must_use]
+pub fn wallet_url(url: &str, wallet: &str) -> String {
+    let name: String = wallet
+        .bytes()
+        .map(|b| {
+            if b.is_ascii_alphanumeric() || b"-._~".contains(&b) {
+                char::from(b).to_string()
+            } else {
+                format!("%{b:02X}")
+            }
+        })
+        .collect();
+    format!("{}/wallet/{name}", url.trim_end_matches('/'))
+}
+
+/// The node config `contents` with its `[rpc]` URL pointed at the
+/// endpoint of `wallet`, or of the `rpcwallet` key of the table, if either
+/// is set
+///
+/// ```
+/// use spray::network::config_for_wallet;
+///
+/// let config = "[rpc]\nurl = \"http://localhost:7041\"\nrpcwallet = \"cold\"\n";
+/// let rewritten = config_for_wallet(config, Some("hot")).unwrap().unwrap();
+/// assert!(rewritten.contains("url = \"http://localhost:7041/wallet/hot\""));
+/// assert!(!rewritten.contains("rpcwallet"));
+///
+/// let plain = "[rpc]\nurl = \"http://localhost:7041\"\n";
+/// assert_eq!(config_for_wallet(plain, None).unwrap(), None);
+/// ```
+///
+/// # Errors
+///
+/// Returns an error if `contents` is not valid TOML, or a wallet is set but
+/// the `[rpc]` table has no `url`.
+pub fn config_for_wallet(
+    contents: &str,
+    wallet: Option<&str>,
+) -> Result<Option<String>, SprayError> {
+    let mut config: toml::Table =
+        toml::from_str(contents).map_err(|e| SprayError::ConfigError(e.to_string()))?;
+    let rpc = config.get_mut("rpc").and_then(toml::Value::as_table_mut);
+    let configured = rpc
+        .as_ref()
+        .and_then(|rpc| rpc.get("rpcwallet"))
+        .and_then(toml::Value::as_str)
+        .map(str::to_string);
+
+    let (Some(rpc), Some(wallet)) = (rpc, wallet.map(str::to_string).or(configured)) else {
+        return Ok(None);
+    };
+    rpc.remove("rpcwallet");
+    let url = rpc
+        .get("url")
+        .and_then(toml::Value::as_str)
+        .ok_or_else(|| SprayError::ConfigError("[rpc] has no url".into()))?;
+    let url = wallet_url(url, &wallet);
+    tracing::debug!(%url, "using wallet endpoint");
+    rpc.insert("url".into(), url.into());
+    Ok(Some(config.to_string()))
+}
+
+/// Connect to the external node of a config file, at the endpoint of
+/// `wallet` or the `rpcwallet` key of its `[rpc]` table, if any
+fn connect_external(config_path: &Path, wallet: Option<&str>) -> Result<RpcClient, SprayError> {
+    let contents = std::fs::read_to_string(config_path)
+        .with_context(|| format!("Failed to read {}", config_path.display()))?;
+    match config_for_wallet(&contents, wallet).with_context(|| config_path.display())? {
+        Some(config) => client_from_toml(&config),
+        None => RpcClient::from_config_file(&config_path.to_string_lossy())
+            .map_err(|e| SprayError::RpcError(e.to_string())),
+    }
+}
+
+/// Environment variable with the RPC URL of an external node, used when no
+/// config file is given
+pub const RPC_URL_ENV: &str = "SPRAY_RPC_URL";
+
+/// Environment variable with the RPC user of [`RPC_URL_ENV`]'s node
+pub const RPC_USER_ENV: &str = "SPRAY_RPC_USER";
+
+/// Environment variable with the RPC password of [`RPC_URL_ENV`]'s node
+pub const RPC_PASSWORD_ENV: &str = "SPRAY_RPC_PASSWORD";
+
+/// Contents of a node config file for `network` from [`RPC_URL_ENV`],
+/// [`RPC_USER_ENV`] and [`RPC_PASSWORD_ENV`], if the URL is set
+///
+/// The URL points at `wallet`, if any.
+#[must_use]
+pub fn rpc_config_from_env(network: Network, wallet: Option<&str>) -> Option<String> {
+    let var = |name| std::env::var(name).ok().filter(|value| !value.is_empty());
+    let url = var(RPC_URL_ENV)?;
+    let url = match wallet {
+        Some(wallet) => wallet_url(&url, wallet),
+        None => url,
+    };
+    let mut rpc = toml::Table::new();
+    rpc.insert("url".into(), url.into());
+    for (key, name) in [("user", RPC_USER_ENV), ("password", RPC_PASSWORD_ENV)] {
+        if let Some(value) = var(name) {
+            rpc.insert(key.into(), value.into());
+        }
+    }
+    let mut config = toml::Table::new();
+    config.insert("network".into(), Preset::builtin(network).name.into());
+    config.insert("rpc".into(), rpc.into());
+    Some(config.to_string())
+}
+
+/// Create an RPC client from the contents of a node config file
+///
+/// The client only loads its settings from a file, so the contents, which
+/// may hold credentials, are written to a new file only the user can read,
+/// in a fresh directory of its own that is removed right after.
+pub(crate) fn client_from_toml(contents: &str) -> Result<RpcClient, SprayError> {
+    let dir = std::env::temp_dir().join(format!("spray-rpc-{:016x}", rand::random::<u64>()));
+    let mut builder = std::fs::DirBuilder::new();
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::DirBuilderExt;
+        builder.mode(0o700);
+    }
+    builder.create(&dir)?;
+
+    let path = dir.join("rpc.toml");
+    let client = write_private(&path, contents).and_then(|()| {
+        RpcClient::from_config_file(&path.to_string_lossy())
+            .map_err(|e| SprayError::RpcError(e.to_string()))
+    });
+    let _ = std::fs::remove_dir_all(&dir);
+    client
+}
+
+/// Write `contents` to a new file at `path` that only the user can read
+fn write_private(path: &Path, contents: &str) -> Result<(), SprayError> {
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    options.open(path)?.write_all(contents.as_bytes())?;
+    Ok(())
+}
+
+/// The Nigiri to attach regtest commands without a config to, by `kind`
+fn running_nigiri(kind: BackendKind) -> Result<Option<Nigiri>, SprayError> {
+    let nigiri = Nigiri::default();
+    match kind {
+        BackendKind::Ephemeral => Ok(None),
+        BackendKind::Auto => Ok(nigiri.is_running().then_some(nigiri)),
+        BackendKind::Nigiri if nigiri.is_running() => Ok(Some(nigiri)),
+        BackendKind::Nigiri => Err(SprayError::ConfigError(format!(
+            "Nigiri is not running at {}; start it with `nigiri start --liquid`",
+            nigiri.chopsticks_url()
+        ))),
     }
 }
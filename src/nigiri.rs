@@ -0,0 +1,182 @@
+//! Nigiri integration
+//!
+//! [Nigiri](https://github.com/vulpemventures/nigiri) runs a Liquid regtest
+//! stack in Docker: an Elements node and a chopsticks server, which wraps
+//! Esplora with a faucet and mines a block after every broadcast. With
+//! `spray --backend nigiri`, regtest commands without `--config` attach to
+//! a running `nigiri start --liquid` instead of starting an ephemeral
+//! `elementsd`: RPC calls go to Nigiri's node with its well-known
+//! credentials, and funding and broadcasting go through chopsticks.
+//! `--backend auto` uses Nigiri when its chopsticks server answers, and an
+//! ephemeral node otherwise.
+//!
+//! Nigiri's node must have Simplicity active for spends to be accepted;
+//! spray checks this when attaching, as for any other node.
+//!
+//! # Example
+//!
+//! ```
+//! use spray::nigiri::{BackendKind, Nigiri};
+//!
+//! assert_eq!(BackendKind::default(), BackendKind::Ephemeral);
+//!
+//! let nigiri = Nigiri::default();
+//! assert_eq!(nigiri.chopsticks_url(), "http://localhost:3001");
+//! assert!(nigiri.rpc_config().contains("url = \"http://localhost:18884\""));
+//! ```
+
+use crate::client::format_btc_amount;
+use crate::error::SprayError;
+use musk::elements::Txid;
+use musk::RpcClient;
+use serde_json::{json, Value};
+use std::process::Command;
+use std::str::FromStr;
+
+/// RPC URL of Nigiri's Elements node
+pub const RPC_URL: &str = "http://localhost:18884";
+
+/// RPC user of Nigiri's Elements node
+pub const RPC_USER: &str = "admin1";
+
+/// RPC password of Nigiri's Elements node
+pub const RPC_PASSWORD: &str = "123";
+
+/// URL of Nigiri's Liquid chopsticks server
+pub const CHOPSTICKS_URL: &str = "http://localhost:3001";
+
+/// Seconds to wait for chopsticks when probing for a running Nigiri
+const PROBE_TIMEOUT_SECS: u32 = 2;
+
+/// Which node regtest commands without `--config` run against
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum BackendKind {
+    /// A fresh ephemeral `elementsd`
+    #[default]
+    Ephemeral,
+    /// A running Nigiri
+    Nigiri,
+    /// Nigiri if it is running, an ephemeral node otherwise
+    Auto,
+}
+
+/// A running Nigiri, reached through its chopsticks server
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Nigiri {
+    chopsticks_url: String,
+}
+
+impl Default for Nigiri {
+    fn default() -> Self {
+        Self::new(CHOPSTICKS_URL)
+    }
+}
+
+impl Nigiri {
+    /// Nigiri with its chopsticks server at `chopsticks_url`
+    #[must_use]
+    pub fn new(chopsticks_url: impl Into<String>) -> Self {
+        Self {
+            chopsticks_url: chopsticks_url.into().trim_end_matches('/').to_string(),
+        }
+    }
+
+    /// URL of the chopsticks server
+    #[must_use]
+    pub fn chopsticks_url(&self) -> &str {
+        &self.chopsticks_url
+    }
+
+    /// Whether the chopsticks server answers
+    #[must_use]
+    pub fn is_running(&self) -> bool {
+        curl(&[
+            "--max-time",
+            &PROBE_TIMEOUT_SECS.to_string(),
+            &format!("{}/blocks/tip/height", self.chopsticks_url),
+        ])
+        .is_ok()
+    }
+
+    /// Contents of a `musk.toml` for Nigiri's Elements node
+    #[must_use]
+    pub fn rpc_config(&self) -> String {
+        format!(
+            "network = \"regtest\"\n\n[rpc]\nurl = \"{RPC_URL}\"\nuser = \"{RPC_USER}\"\npassword = \"{RPC_PASSWORD}\"\n"
+        )
+    }
+
+    /// Connect an RPC client to Nigiri's Elements node
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the client cannot be created.
+    pub fn connect(&self) -> Result<RpcClient, SprayError> {
+        crate::network::client_from_toml(&self.rpc_config())
+    }
+
+    /// Send `amount` satoshis to `address` from the chopsticks faucet
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the faucet cannot be reached or refuses.
+    pub fn faucet(&self, address: &str, amount: u64) -> Result<Txid, SprayError> {
+        let body = json!({
+            "address": address,
+            "amount": format_btc_amount(amount).parse::<f64>().unwrap_or_default(),
+        });
+        let response = curl(&[
+            "--header",
+            "Content-Type: application/json",
+            "--data",
+            &body.to_string(),
+            &format!("{}/faucet", self.chopsticks_url),
+        ])?;
+        parse_faucet_response(&response)
+    }
+
+    /// Broadcast a transaction (hex) through chopsticks, which mines it
+    ///
+    /// # Errors
+    ///
+    /// Returns an error with the node's rejection reason if the transaction
+    /// is not accepted.
+    pub fn broadcast(&self, hex: &str) -> Result<Txid, SprayError> {
+        let response = curl(&["--data", hex, &format!("{}/tx", self.chopsticks_url)])
+            .map_err(|e| SprayError::MempoolReject(e.detail()))?;
+        Txid::from_str(response.trim()).map_err(|_| SprayError::MempoolReject(response))
+    }
+}
+
+/// Parse the txid out of a chopsticks faucet response
+///
+/// # Errors
+///
+/// Returns an error if the response carries no txid.
+pub fn parse_faucet_response(response: &str) -> Result<Txid, SprayError> {
+    let value: Value = serde_json::from_str(response)
+        .map_err(|_| SprayError::RpcError(format!("Faucet error: {}", response.trim())))?;
+    value["txId"]
+        .as_str()
+        .and_then(|txid| Txid::from_str(txid).ok())
+        .ok_or_else(|| SprayError::RpcError(format!("Faucet error: {value}")))
+}
+
+/// Run `curl` with `args`, returning the response body
+fn curl(args: &[&str]) -> Result<String, SprayError> {
+    let output = Command::new("curl")
+        .args(["--silent", "--show-error", "--fail-with-body"])
+        .args(args)
+        .output()
+        .map_err(|e| SprayError::RpcError(format!("Failed to run curl: {e}")))?;
+    let body = String::from_utf8_lossy(&output.stdout).into_owned();
+    if output.status.success() {
+        Ok(body)
+    } else if body.trim().is_empty() {
+        Err(SprayError::RpcError(
+            String::from_utf8_lossy(&output.stderr).trim().to_string(),
+        ))
+    } else {
+        Err(SprayError::RpcError(body.trim().to_string()))
+    }
+}
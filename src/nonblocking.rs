@@ -0,0 +1,168 @@
+//! Async API for embedding spray in async services
+//!
+//! Node clients make blocking RPC calls. With the `tokio` feature, this
+//! module offers async variants for services running on a Tokio runtime,
+//! e.g. indexers or bots, that must not block their executor on a slow node:
+//!
+//! - [`AsyncNodeClient`] mirrors [`NodeClient`] for any shared client,
+//!   running each call on Tokio's blocking thread pool
+//! - [`connect`] creates a [`NetworkBackend`] and [`call`] makes raw RPC
+//!   calls against it the same way
+//!
+//! The test runner has no async variant: its test cases borrow the
+//! runner's environment, so a suite cannot move to the blocking thread pool.
+//!
+//! # Example
+//!
+//! ```no_run
+//! use musk::Network;
+//! use spray::nonblocking::{self, AsyncNodeClient};
+//!
+//! # async fn example() -> Result<(), spray::SprayError> {
+//! let backend = nonblocking::connect(Network::Testnet, Some("musk.toml".into())).await?;
+//! let height = nonblocking::call(&backend, "getblockcount", vec![]).await?;
+//! let address = backend.get_new_address().await?;
+//! println!("{address} at height {height}");
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::error::SprayError;
+use crate::network::{create_backend, NetworkBackend};
+use musk::client::{ClientResult, NodeClient, Utxo};
+use musk::elements::{Address, BlockHash, Transaction, Txid};
+use musk::Network;
+use serde_json::Value;
+use std::future::Future;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+/// Async counterpart of [`NodeClient`]
+///
+/// Implemented for every shared [`NodeClient`], including
+/// `Arc<NetworkBackend>`; each call runs on Tokio's blocking thread pool.
+pub trait AsyncNodeClient {
+    /// Send `amount` satoshis to `addr` from the node's wallet
+    fn send_to_address(
+        &self,
+        addr: &Address,
+        amount: u64,
+    ) -> impl Future<Output = ClientResult<Txid>> + Send;
+
+    /// Fetch a transaction by txid
+    fn get_transaction(
+        &self,
+        txid: &Txid,
+    ) -> impl Future<Output = ClientResult<Transaction>> + Send;
+
+    /// Broadcast a transaction
+    fn broadcast(&self, tx: &Transaction) -> impl Future<Output = ClientResult<Txid>> + Send;
+
+    /// Mine `count` blocks
+    fn generate_blocks(
+        &self,
+        count: u32,
+    ) -> impl Future<Output = ClientResult<Vec<BlockHash>>> + Send;
+
+    /// Unspent outputs of `address`
+    fn get_utxos(&self, address: &Address) -> impl Future<Output = ClientResult<Vec<Utxo>>> + Send;
+
+    /// A new address of the node's wallet
+    fn get_new_address(&self) -> impl Future<Output = ClientResult<Address>> + Send;
+}
+
+impl<C: NodeClient + Send + Sync + 'static> AsyncNodeClient for Arc<C> {
+    fn send_to_address(
+        &self,
+        addr: &Address,
+        amount: u64,
+    ) -> impl Future<Output = ClientResult<Txid>> + Send {
+        let addr = addr.clone();
+        blocking_client(self, move |client| client.send_to_address(&addr, amount))
+    }
+
+    fn get_transaction(
+        &self,
+        txid: &Txid,
+    ) -> impl Future<Output = ClientResult<Transaction>> + Send {
+        let txid = *txid;
+        blocking_client(self, move |client| client.get_transaction(&txid))
+    }
+
+    fn broadcast(&self, tx: &Transaction) -> impl Future<Output = ClientResult<Txid>> + Send {
+        let tx = tx.clone();
+        blocking_client(self, move |client| client.broadcast(&tx))
+    }
+
+    fn generate_blocks(
+        &self,
+        count: u32,
+    ) -> impl Future<Output = ClientResult<Vec<BlockHash>>> + Send {
+        blocking_client(self, move |client| client.generate_blocks(count))
+    }
+
+    fn get_utxos(&self, address: &Address) -> impl Future<Output = ClientResult<Vec<Utxo>>> + Send {
+        let address = address.clone();
+        blocking_client(self, move |client| client.get_utxos(&address))
+    }
+
+    fn get_new_address(&self) -> impl Future<Output = ClientResult<Address>> + Send {
+        blocking_client(self, NodeClient::get_new_address)
+    }
+}
+
+/// Create a network backend, see [`create_backend`], without blocking the
+/// runtime
+///
+/// # Errors
+///
+/// Returns the errors of [`create_backend`].
+pub async fn connect(
+    network: Network,
+    config: Option<PathBuf>,
+) -> Result<Arc<NetworkBackend>, SprayError> {
+    blocking(move || create_backend(network, config))
+        .await
+        .map(Arc::new)
+}
+
+/// Make a raw RPC call against `backend` without blocking the runtime
+///
+/// # Errors
+///
+/// Returns an error if the RPC call fails.
+pub async fn call(
+    backend: &Arc<NetworkBackend>,
+    method: &str,
+    params: Vec<Value>,
+) -> Result<Value, SprayError> {
+    let backend = Arc::clone(backend);
+    let method = method.to_string();
+    blocking(move || backend.call(&method, &params)).await
+}
+
+/// Run `f` with a clone of `client` on the blocking thread pool
+fn blocking_client<C, T, F>(client: &Arc<C>, f: F) -> impl Future<Output = ClientResult<T>> + Send
+where
+    C: Send + Sync + 'static,
+    T: Send + 'static,
+    F: FnOnce(&C) -> ClientResult<T> + Send + 'static,
+{
+    let client = Arc::clone(client);
+    async move {
+        tokio::task::spawn_blocking(move || f(&client))
+            .await
+            .map_err(|e| musk::ProgramError::IoError(std::io::Error::other(e.to_string())))?
+    }
+}
+
+/// Run `f` on the blocking thread pool
+async fn blocking<T, F>(f: F) -> Result<T, SprayError>
+where
+    T: Send + 'static,
+    F: FnOnce() -> Result<T, SprayError> + Send + 'static,
+{
+    tokio::task::spawn_blocking(f)
+        .await
+        .map_err(|e| SprayError::EnvironmentError(format!("Blocking task failed: {e}")))?
+}
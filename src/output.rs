@@ -0,0 +1,227 @@
+//! Machine-readable output
+//!
+//! With `spray --json`, a command writes exactly one JSON document to stdout
+//! and everything meant for people (progress, labels, colored summaries) to
+//! stderr, so that scripts can pipe stdout straight into a JSON parser.
+//!
+//! `spray test --tap` works the same way with a TAP stream in place of the
+//! JSON document.
+//!
+//! Progress and other decorative lines are printed with
+//! [`status!`](crate::status), which always goes to stderr, so that piping a
+//! command's stdout never captures banners, and is dropped while
+//! [quiet](set_quiet), e.g. with `spray --quiet` or while `spray tui` draws.
+//! A command's result goes to stdout: with [`print_result`] in human mode,
+//! and as a document printed with [`emit`] in JSON mode; a failing command
+//! emits [`error_document`] instead. `spray test --watch` emits a document
+//! for every run. Interactive commands ask their questions and show their
+//! plans with [`say!`](crate::say) and on [`terminal`], which move to stderr
+//! in JSON mode. Colors are turned off by `--no-color`,
+//! `NO_COLOR`, or either stream not being a terminal, see [`use_color`].
+//!
+//! Every document is an object with the command name under `"command"`,
+//! `"ok"`, and the [message ID](crate::messages) of its outcome under
+//! `"message_id"` with the rendered text under `"message"`, alongside the
+//! command's own fields. A failed command's document has `"ok": false`, the
+//! error's message ID, its details and its English text under `"error"`.
+//!
+//! # Example
+//!
+//! ```
+//! use serde_json::json;
+//! use spray::output;
+//!
+//! let fields = json!({ "txid": "aa", "vout": 0 });
+//! let document = output::document("deploy.success", true, &fields).unwrap();
+//! assert_eq!(document["command"], "deploy");
+//! assert_eq!(document["message_id"], "deploy.success");
+//! assert_eq!(document["ok"], true);
+//! assert_eq!(document["vout"], 0);
+//!
+//! let error = output::error_document(&spray::SprayError::ConfigError("bad".into()));
+//! assert_eq!(error["ok"], false);
+//! assert_eq!(error["message_id"], "error.config");
+//! assert_eq!(error["detail"], "bad");
+//! assert_eq!(error["error"], "Configuration error: bad");
+//! ```
+
+use crate::error::SprayError;
+use crate::report::TestRecord;
+use serde::Serialize;
+use serde_json::{json, Map, Value};
+use std::sync::atomic::{AtomicBool, AtomicU8, Ordering};
+
+/// What stdout carries
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[repr(u8)]
+pub enum Format {
+    /// Human-readable output
+    #[default]
+    Human,
+    /// A single JSON document
+    Json,
+    /// A TAP stream
+    Tap,
+}
+
+/// The process-wide output format
+static FORMAT: AtomicU8 = AtomicU8::new(Format::Human as u8);
+
+/// Set the output format for the rest of the process
+pub fn set_format(format: Format) {
+    FORMAT.store(format as u8, Ordering::Relaxed);
+}
+
+/// The current output format
+#[must_use]
+pub fn format() -> Format {
+    match FORMAT.load(Ordering::Relaxed) {
+        1 => Format::Json,
+        2 => Format::Tap,
+        _ => Format::Human,
+    }
+}
+
+/// Whether human-readable lines are dropped
+static QUIET: AtomicBool = AtomicBool::new(false);
+
+/// Drop (or stop dropping) human-readable lines for the rest of the process
+pub fn set_quiet(quiet: bool) {
+    QUIET.store(quiet, Ordering::Relaxed);
+}
+
+/// Whether human-readable lines are dropped
+#[must_use]
+pub fn is_quiet() -> bool {
+    QUIET.load(Ordering::Relaxed)
+}
+
+/// Whether commands should emit a JSON document
+#[must_use]
+pub fn is_json() -> bool {
+    format() == Format::Json
+}
+
+/// Whether stdout is reserved for machine-readable output
+#[must_use]
+pub fn is_machine() -> bool {
+    format() != Format::Human
+}
+
+/// Print a decorative line to stderr, unless quiet
+#[macro_export]
+macro_rules! status {
+    ($($arg:tt)*) => {
+        if !$crate::output::is_quiet() {
+            eprintln!($($arg)*);
+        }
+    };
+}
+
+/// Print a command's result to stdout in human mode, e.g. the `txid:vout`
+/// of a deployment, so that it can be piped into other tools
+///
+/// Nothing is printed in JSON and TAP mode, whose stdout carries the
+/// document or stream instead.
+pub fn print_result(line: &str) {
+    if !is_machine() {
+        println!("{line}");
+    }
+}
+
+/// Print a line of an interactive command's conversation, e.g. a plan to
+/// confirm: to stdout in human mode, and to stderr when stdout carries a
+/// document or stream
+#[macro_export]
+macro_rules! say {
+    ($($arg:tt)*) => {
+        if $crate::output::is_machine() {
+            eprintln!($($arg)*);
+        } else {
+            println!($($arg)*);
+        }
+    };
+}
+
+/// The stream an interactive command asks its questions on: stdout in human
+/// mode, and stderr when stdout carries a document or stream
+#[must_use]
+pub fn terminal() -> Box<dyn std::io::Write> {
+    if is_machine() {
+        Box::new(std::io::stderr())
+    } else {
+        Box::new(std::io::stdout())
+    }
+}
+
+/// Whether output should be colored: not if `no_color` is set, `NO_COLOR`
+/// is set to a non-empty value, or stdout or stderr is not a terminal
+#[must_use]
+pub fn use_color(no_color: bool) -> bool {
+    use std::io::IsTerminal;
+
+    !no_color
+        && std::env::var_os("NO_COLOR").map_or(true, |value| value.is_empty())
+        && std::io::stdout().is_terminal()
+        && std::io::stderr().is_terminal()
+}
+
+/// Build the document for an outcome with message ID `message_id` from the
+/// command's fields
+///
+/// The command is the part of the message ID before the first dot. `fields`
+/// that do not serialize to an object are placed under `"result"`.
+///
+/// # Errors
+///
+/// Returns an error if `fields` cannot be serialized.
+pub fn document(message_id: &str, ok: bool, fields: &impl Serialize) -> Result<Value, SprayError> {
+    let command = message_id.split('.').next().unwrap_or(message_id);
+    let mut document = Map::new();
+    document.insert("command".into(), command.into());
+    document.insert("ok".into(), ok.into());
+    document.insert("message_id".into(), message_id.into());
+    document.insert("message".into(), crate::messages::text(message_id).into());
+    match serde_json::to_value(fields)? {
+        Value::Object(fields) => document.extend(fields),
+        other => {
+            document.insert("result".into(), other);
+        }
+    }
+    Ok(Value::Object(document))
+}
+
+/// Print the document for an outcome to stdout, if in JSON mode
+///
+/// # Errors
+///
+/// Returns an error if `fields` cannot be serialized.
+pub fn emit(message_id: &str, ok: bool, fields: &impl Serialize) -> Result<(), SprayError> {
+    if is_json() {
+        println!(
+            "{}",
+            serde_json::to_string_pretty(&document(message_id, ok, fields)?)?
+        );
+    }
+    Ok(())
+}
+
+/// Print `tests` to stdout as a TAP stream, if in TAP mode
+pub fn emit_tap(tests: &[TestRecord]) {
+    if format() == Format::Tap {
+        print!("{}", crate::report::tap(tests));
+    }
+}
+
+/// The document printed when a command fails
+#[must_use]
+pub fn error_document(error: &SprayError) -> Value {
+    json!({
+        "ok": false,
+        "message_id": error.message_id(),
+        "code": error.code(),
+        "exit_code": error.exit_code().code(),
+        "detail": error.detail(),
+        "error": error.to_string(),
+    })
+}
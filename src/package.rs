@@ -0,0 +1,107 @@
+//! Package submission of a funding transaction and its spend
+//!
+//! Contracts designed around zero-conf chained spends are spent before their
+//! funding transaction confirms. This module submits the funding transaction
+//! and the contract spend together as a one-parent-one-child package with
+//! `submitpackage`, so the node evaluates them as a unit. Nodes without
+//! `submitpackage` receive both with `sendrawtransaction`, parent first, so
+//! the spend still enters the mempool on top of an unconfirmed parent.
+//!
+//! # Example
+//!
+//! ```
+//! use serde_json::json;
+//! use spray::package::check_submitpackage;
+//!
+//! let accepted = json!({ "package_msg": "success", "tx-results": {} });
+//! assert!(check_submitpackage(&accepted).is_ok());
+//!
+//! let rejected = json!({ "package_msg": "transaction failed", "tx-results": {
+//!     "ab": { "txid": "cd", "error": "non-mandatory-script-verify-flag" }
+//! }});
+//! assert!(check_submitpackage(&rejected).is_err());
+//! ```
+
+use crate::error::SprayError;
+use musk::elements::encode::deserialize;
+use musk::elements::hex::FromHex;
+use musk::elements::Transaction;
+use serde_json::{json, Value};
+use std::fmt;
+
+/// How a package was handed to the node
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SubmitMethod {
+    /// Both transactions in one `submitpackage` call
+    Package,
+    /// One `sendrawtransaction` call per transaction, parent first
+    Sequential,
+}
+
+impl fmt::Display for SubmitMethod {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Package => write!(f, "submitpackage"),
+            Self::Sequential => write!(f, "sequential sendrawtransaction"),
+        }
+    }
+}
+
+/// Submit `parent` and its spend `child` (both hex) to the node
+///
+/// `call` makes an RPC call against the node. `submitpackage` is used where
+/// the node supports it; otherwise both transactions are sent one after the
+/// other, without a block in between.
+///
+/// # Errors
+///
+/// Returns an error if the node rejects either transaction.
+pub fn submit_package(
+    call: impl Fn(&str, &[Value]) -> Result<Value, SprayError>,
+    parent: &str,
+    child: &str,
+) -> Result<SubmitMethod, SprayError> {
+    match call("submitpackage", &[json!([parent, child])]) {
+        Ok(response) => {
+            check_submitpackage(&response)?;
+            Ok(SubmitMethod::Package)
+        }
+        Err(e) if e.to_string().contains("Method not found") => {
+            call("sendrawtransaction", &[parent.into()])?;
+            call("sendrawtransaction", &[child.into()])?;
+            Ok(SubmitMethod::Sequential)
+        }
+        Err(e) => Err(e),
+    }
+}
+
+/// Check a `submitpackage` response for rejected transactions
+///
+/// # Errors
+///
+/// Returns [`SprayError::MempoolReject`] naming the first rejection if
+/// the package or any of its transactions was not accepted.
+pub fn check_submitpackage(response: &Value) -> Result<(), SprayError> {
+    let error = response["tx-results"]
+        .as_object()
+        .into_iter()
+        .flat_map(|results| results.values())
+        .find_map(|result| result["error"].as_str());
+
+    match (response["package_msg"].as_str(), error) {
+        (_, Some(error)) => Err(SprayError::MempoolReject(error.to_string())),
+        (Some(msg), None) if msg != "success" => Err(SprayError::MempoolReject(msg.to_string())),
+        _ => Ok(()),
+    }
+}
+
+/// Decode a consensus-serialized transaction from hex
+///
+/// # Errors
+///
+/// Returns an error if `hex` is not a valid transaction.
+pub fn decode_transaction(hex: &str) -> Result<Transaction, SprayError> {
+    let bytes = Vec::<u8>::from_hex(hex)
+        .map_err(|e| SprayError::ParseError(format!("Invalid transaction hex: {e}")))?;
+    deserialize(&bytes).map_err(|e| SprayError::ParseError(format!("Invalid transaction: {e}")))
+}
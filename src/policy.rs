@@ -0,0 +1,215 @@
+//! Descriptor policies as comparison baselines
+//!
+//! This module pairs a Simplicity contract with an output descriptor that
+//! claims the same spending semantics. Each [`PolicyCase`] is run against
+//! both: the contract is spent with the case's witness, and the descriptor
+//! output is spent by the node's wallet, with the same lock time and
+//! sequence. A case whose outcomes disagree is a behavioral difference.
+//!
+//! # Example
+//!
+//! ```toml
+//! [[case]]
+//! name = "owner before timeout"
+//! witness = "owner.wit"
+//!
+//! [[case]]
+//! name = "recovery after timeout"
+//! witness = "recovery.wit"
+//! lock_time = 200
+//! sequence = 4294967294
+//! ```
+
+use crate::client::format_btc_amount;
+use crate::error::SprayError;
+use crate::network::NetworkBackend;
+use musk::elements::{Address, OutPoint, Txid};
+use serde::Deserialize;
+use serde_json::json;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+/// A set of paired spend cases
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct PolicyCases {
+    /// Cases, in declaration order
+    #[serde(default, rename = "case")]
+    pub cases: Vec<PolicyCase>,
+}
+
+/// A spend attempted against both the contract and the descriptor
+#[derive(Debug, Clone, Deserialize)]
+pub struct PolicyCase {
+    /// Case name
+    pub name: String,
+    /// Witness file satisfying the contract (JSON or TOML)
+    #[serde(default)]
+    pub witness: Option<PathBuf>,
+    /// Lock time for both spending transactions
+    #[serde(default)]
+    pub lock_time: Option<u32>,
+    /// Sequence number for both spending transactions
+    #[serde(default)]
+    pub sequence: Option<u32>,
+}
+
+impl PolicyCases {
+    /// Load cases from a TOML file
+    ///
+    /// Witness paths are resolved against the file's directory.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be read or parsed.
+    pub fn load(path: &Path) -> Result<Self, SprayError> {
+        let contents = std::fs::read_to_string(path)?;
+        let mut cases: Self = toml::from_str(&contents)
+            .map_err(|e| SprayError::ParseError(format!("TOML parse error: {e}")))?;
+
+        let base = path.parent().unwrap_or_else(|| Path::new("."));
+        for case in &mut cases.cases {
+            case.witness = case.witness.take().map(|p| base.join(p));
+        }
+        Ok(cases)
+    }
+}
+
+/// Outcome of one case on both sides
+///
+/// Each side holds the spending txid if the spend was accepted, or the
+/// rejection reason.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CaseOutcome {
+    /// Case name
+    pub name: String,
+    /// Outcome of the Simplicity contract spend
+    pub simplicity: Result<String, String>,
+    /// Outcome of the descriptor spend
+    pub descriptor: Result<String, String>,
+}
+
+impl CaseOutcome {
+    /// Whether the contract and the descriptor behaved the same way
+    #[must_use]
+    pub const fn agrees(&self) -> bool {
+        self.simplicity.is_ok() == self.descriptor.is_ok()
+    }
+}
+
+/// An output descriptor imported into the node's wallet
+#[derive(Debug, Clone)]
+pub struct DescriptorPolicy {
+    /// Descriptor with its checksum, including any private keys
+    descriptor: String,
+    /// Address the descriptor resolves to
+    address: Address,
+}
+
+impl DescriptorPolicy {
+    /// Import `descriptor` into the node's wallet and derive its address
+    ///
+    /// The descriptor may carry private keys; the wallet uses them to sign
+    /// spends. The wallet must be a descriptor wallet.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the node rejects the descriptor or the import.
+    pub fn import(backend: &NetworkBackend, descriptor: &str) -> Result<Self, SprayError> {
+        let info = backend.call("getdescriptorinfo", &[descriptor.into()])?;
+        let checksum = info["checksum"]
+            .as_str()
+            .ok_or_else(|| SprayError::RpcError("Invalid getdescriptorinfo response".into()))?;
+        let descriptor = format!("{}#{checksum}", descriptor.split('#').next().unwrap_or(""));
+
+        let imported = backend.call(
+            "importdescriptors",
+            &[json!([{ "desc": descriptor, "timestamp": "now" }])],
+        )?;
+        if imported[0]["success"].as_bool() != Some(true) {
+            return Err(SprayError::RpcError(format!(
+                "Failed to import descriptor: {}",
+                imported[0]["error"]["message"]
+            )));
+        }
+
+        let addresses = backend.call("deriveaddresses", &[descriptor.clone().into()])?;
+        let address = addresses[0]
+            .as_str()
+            .ok_or_else(|| SprayError::RpcError("Invalid deriveaddresses response".into()))?;
+        let address = Address::from_str(address)
+            .map_err(|e| SprayError::ParseError(format!("Invalid descriptor address: {e}")))?;
+
+        Ok(Self {
+            descriptor,
+            address,
+        })
+    }
+
+    /// Descriptor with its checksum
+    #[must_use]
+    pub fn descriptor(&self) -> &str {
+        &self.descriptor
+    }
+
+    /// Address the descriptor resolves to
+    #[must_use]
+    pub const fn address(&self) -> &Address {
+        &self.address
+    }
+
+    /// Spend `outpoint` (holding `amount` of the policy asset) to
+    /// `destination`, signed by the wallet
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the wallet cannot satisfy the descriptor under
+    /// the given lock time and sequence, or the node rejects the spend.
+    #[allow(clippy::too_many_arguments)]
+    pub fn spend(
+        &self,
+        backend: &NetworkBackend,
+        outpoint: OutPoint,
+        amount: u64,
+        fee: u64,
+        destination: &Address,
+        lock_time: Option<u32>,
+        sequence: Option<u32>,
+    ) -> Result<Txid, SprayError> {
+        let output_amount = amount
+            .checked_sub(fee)
+            .ok_or_else(|| SprayError::TestError("Insufficient funds for fee".into()))?;
+
+        let mut input = json!({ "txid": outpoint.txid.to_string(), "vout": outpoint.vout });
+        if let Some(sequence) = sequence {
+            input["sequence"] = sequence.into();
+        }
+
+        // createrawtransaction inputs outputs locktime
+        let destination = destination.to_string();
+        let raw = backend.call(
+            "createrawtransaction",
+            &[
+                json!([input]),
+                json!([
+                    { destination: format_btc_amount(output_amount) },
+                    { "fee": format_btc_amount(fee) },
+                ]),
+                lock_time.unwrap_or(0).into(),
+            ],
+        )?;
+
+        let signed = backend.call("signrawtransactionwithwallet", &[raw])?;
+        if signed["complete"].as_bool() != Some(true) {
+            return Err(SprayError::TestError(format!(
+                "Wallet cannot satisfy the descriptor: {}",
+                signed["errors"][0]["error"]
+            )));
+        }
+
+        let txid = backend.call("sendrawtransaction", &[signed["hex"].clone()])?;
+        let txid = txid
+            .as_str()
+            .ok_or_else(|| SprayError::RpcError("Invalid txid response".into()))?;
+        Txid::from_str(txid).map_err(|e| SprayError::RpcError(e.to_string()))
+    }
+}
@@ -0,0 +1,399 @@
+//! Spending programs without their source
+//!
+//! A compiled artifact normally embeds its SimplicityHL source, which spray
+//! recompiles to satisfy the program with a named witness. Closed-source
+//! contracts, or contracts whose build must not be redone, can be shipped
+//! as program bytes only. [`PrecompiledProgram`] decodes the commitment from
+//! those bytes and [`PrecompiledSpend`] spends it directly: the witness
+//! values are encoded into the witness bit string, the program is satisfied
+//! and pruned against the spending transaction, and the result goes into
+//! the input's script witness.
+//!
+//! Without source, witnesses have no names. A [`WitnessList`] gives one
+//! value per witness node, in the order the nodes appear in the program
+//! (as numbered by `spray inspect --disasm`), each as hex or `0b` binary of
+//! the value's bits. An entry `{"sign": "<secret key hex>"}` stands for a
+//! BIP 340 signature over the spend's sighash with that key.
+//!
+//! # Example
+//!
+//! ```
+//! use serde_json::json;
+//! use spray::precompiled::{WitnessEntry, WitnessList};
+//!
+//! let list = WitnessList::parse(&json!(["0x0102", "0b1", {"value": "0xff"}])).unwrap();
+//! assert_eq!(list.entries().len(), 3);
+//! assert_eq!(list.entries()[1], WitnessEntry::Bits(vec![true]));
+//! assert!(WitnessList::parse(&json!({"X": "0x01"})).is_err());
+//! ```
+
+use crate::error::SprayError;
+use crate::pset::SimplicityLeaf;
+use crate::signing::sign_digest;
+use musk::elements::hashes::Hash;
+use musk::elements::secp256k1_zkp::SecretKey;
+use musk::elements::{
+    Address, AddressParams, AssetId, BlockHash, LockTime, OutPoint, Sequence, Transaction, TxIn,
+    TxOut,
+};
+use serde_json::Value;
+use simplicity::dag::{DagLike, InternalSharing};
+use simplicity::jet::elements::{ElementsEnv, ElementsUtxo};
+use simplicity::jet::Elements;
+use simplicity::node::Inner;
+use simplicity::{BitIter, Cmr, CommitNode, RedeemNode};
+use std::path::Path;
+use std::str::FromStr;
+use std::sync::Arc;
+
+/// A program decoded from its bytes, without source
+#[derive(Debug, Clone)]
+pub struct PrecompiledProgram {
+    commit: Arc<CommitNode<Elements>>,
+    bytes: Vec<u8>,
+}
+
+impl PrecompiledProgram {
+    /// Decode a program from its bytes, as in a compiled artifact
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the bytes are not a valid program encoding.
+    pub fn decode(bytes: &[u8]) -> Result<Self, SprayError> {
+        let commit = CommitNode::<Elements>::decode(BitIter::from(bytes.iter().copied()))
+            .map_err(|e| SprayError::ParseError(format!("Invalid Simplicity program: {e}")))?;
+        Ok(Self {
+            commit,
+            bytes: bytes.to_vec(),
+        })
+    }
+
+    /// Decode the program of a compiled artifact, checking it against the
+    /// artifact's CMR if it has one
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the program is not valid base64 or not a valid
+    /// encoding, or does not have the artifact's CMR.
+    pub fn from_output(output: &crate::compiled::CompiledOutput) -> Result<Self, SprayError> {
+        let bytes = output
+            .decode_program()
+            .map_err(|e| SprayError::FileFormatError(format!("Invalid program base64: {e}")))?;
+        let program = Self::decode(&bytes)?;
+        let cmr = program.cmr().to_string();
+        if !output.cmr.is_empty() && !output.cmr.eq_ignore_ascii_case(&cmr) {
+            return Err(SprayError::FileFormatError(format!(
+                "Program bytes have CMR {cmr}, but the artifact says {}",
+                output.cmr
+            )));
+        }
+        Ok(program)
+    }
+
+    /// Commitment Merkle root of the program
+    #[must_use]
+    pub fn cmr(&self) -> Cmr {
+        self.commit.cmr()
+    }
+
+    /// Address of the program on the network with `params`
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the program's taproot output cannot be built.
+    pub fn address(&self, params: &'static AddressParams) -> Result<Address, SprayError> {
+        let leaf = SimplicityLeaf::new(self.cmr().to_byte_array())?;
+        Address::from_script(&leaf.script_pubkey, None, params)
+            .ok_or_else(|| SprayError::TestError("Cannot derive the program's address".into()))
+    }
+
+    /// Types of the program's witness nodes, in the order their values are
+    /// given
+    #[must_use]
+    pub fn witness_types(&self) -> Vec<String> {
+        self.commit
+            .post_order_iter::<InternalSharing>()
+            .filter(|item| matches!(item.node.inner(), Inner::Witness(_)))
+            .map(|item| item.node.arrow().target.to_string())
+            .collect()
+    }
+}
+
+/// A witness value of a program without source
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum WitnessEntry {
+    /// The bits of the value
+    Bits(Vec<bool>),
+    /// A signature over the spend's sighash with this key
+    Sign(SecretKey),
+}
+
+/// Witness values of a program without source, one per witness node
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct WitnessList {
+    entries: Vec<WitnessEntry>,
+}
+
+impl WitnessList {
+    /// Load a witness list from a JSON file
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be read or is not a witness list.
+    pub fn load(path: &Path) -> Result<Self, SprayError> {
+        let value: Value = serde_json::from_str(&std::fs::read_to_string(path)?)?;
+        Self::parse(&value)
+    }
+
+    /// Parse a witness list: an array of hex or `0b` binary strings, objects
+    /// with such a `value`, or `{"sign": "<secret key hex>"}` objects
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `value` is not an array, or an entry is invalid.
+    pub fn parse(value: &Value) -> Result<Self, SprayError> {
+        let entries = value.as_array().ok_or_else(|| {
+            SprayError::ParseError(
+                "A program without source takes its witness as an array of values, one per witness node"
+                    .into(),
+            )
+        })?;
+        let entries = entries
+            .iter()
+            .enumerate()
+            .map(|(i, entry)| {
+                parse_entry(entry)
+                    .map_err(|e| SprayError::ParseError(format!("Witness value #{i}: {e}")))
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(Self { entries })
+    }
+
+    /// The values, in order
+    #[must_use]
+    pub fn entries(&self) -> &[WitnessEntry] {
+        &self.entries
+    }
+
+    /// Encode the values into a witness bit string, signing over `sighash`
+    #[must_use]
+    pub fn encode(&self, sighash: [u8; 32]) -> Vec<u8> {
+        let bits: Vec<bool> = self
+            .entries
+            .iter()
+            .flat_map(|entry| match entry {
+                WitnessEntry::Bits(bits) => bits.clone(),
+                WitnessEntry::Sign(key) => byte_bits(sign_digest(sighash, key).as_ref()),
+            })
+            .collect();
+        bits.chunks(8)
+            .map(|chunk| {
+                chunk
+                    .iter()
+                    .enumerate()
+                    .fold(0u8, |byte, (i, bit)| byte | (u8::from(*bit) << (7 - i)))
+            })
+            .collect()
+    }
+}
+
+/// Parse one witness list entry
+fn parse_entry(entry: &Value) -> Result<WitnessEntry, String> {
+    if let Some(key) = entry.get("sign") {
+        return key
+            .as_str()
+            .and_then(|key| SecretKey::from_str(key.trim_start_matches("0x")).ok())
+            .map(WitnessEntry::Sign)
+            .ok_or_else(|| "invalid secret key to sign with".to_string());
+    }
+    let text = entry
+        .as_str()
+        .or_else(|| entry.get("value").and_then(Value::as_str))
+        .ok_or_else(|| "expected a hex or 0b binary string".to_string())?;
+    parse_bits(text).map(WitnessEntry::Bits)
+}
+
+/// Bits of `0b` binary or (`0x`) hex text
+fn parse_bits(text: &str) -> Result<Vec<bool>, String> {
+    if let Some(binary) = text.strip_prefix("0b") {
+        return binary
+            .chars()
+            .map(|c| match c {
+                '0' => Ok(false),
+                '1' => Ok(true),
+                _ => Err(format!("invalid binary digit '{c}'")),
+            })
+            .collect();
+    }
+    let hex = text.trim_start_matches("0x");
+    if hex.len() % 2 != 0 {
+        return Err("hex has an odd number of digits".into());
+    }
+    let bytes = (0..hex.len())
+        .step_by(2)
+        .map(|i| {
+            hex.get(i..i + 2)
+                .and_then(|digits| u8::from_str_radix(digits, 16).ok())
+                .ok_or_else(|| format!("invalid hex '{text}'"))
+        })
+        .collect::<Result<Vec<u8>, _>>()?;
+    Ok(byte_bits(&bytes))
+}
+
+/// Bits of `bytes`, most significant first
+fn byte_bits(bytes: &[u8]) -> Vec<bool> {
+    bytes
+        .iter()
+        .flat_map(|byte| (0..8).rev().map(move |i| byte & (1 << i) != 0))
+        .collect()
+}
+
+/// A transaction spending a UTXO locked by a [`PrecompiledProgram`]
+#[derive(Debug, Clone)]
+pub struct PrecompiledSpend {
+    program: PrecompiledProgram,
+    outpoint: OutPoint,
+    prevout: TxOut,
+    genesis_hash: BlockHash,
+    lock_time: LockTime,
+    sequence: Sequence,
+    outputs: Vec<TxOut>,
+}
+
+impl PrecompiledSpend {
+    /// Spend `prevout`, the output at `outpoint`, with `program`
+    #[must_use]
+    pub fn new(program: PrecompiledProgram, outpoint: OutPoint, prevout: TxOut) -> Self {
+        Self {
+            program,
+            outpoint,
+            prevout,
+            genesis_hash: BlockHash::all_zeros(),
+            lock_time: LockTime::ZERO,
+            sequence: Sequence::MAX,
+            outputs: Vec::new(),
+        }
+    }
+
+    /// Set the genesis hash of the chain the spend is for
+    #[must_use]
+    pub const fn genesis_hash(mut self, genesis_hash: BlockHash) -> Self {
+        self.genesis_hash = genesis_hash;
+        self
+    }
+
+    /// Set the lock time of the spending transaction
+    #[must_use]
+    pub const fn lock_time(mut self, lock_time: LockTime) -> Self {
+        self.lock_time = lock_time;
+        self
+    }
+
+    /// Set the sequence of the spent input
+    #[must_use]
+    pub const fn sequence(mut self, sequence: Sequence) -> Self {
+        self.sequence = sequence;
+        self
+    }
+
+    /// Add an output
+    pub fn add_output(&mut self, output: TxOut) {
+        self.outputs.push(output);
+    }
+
+    /// Add a fee output of `amount` in `asset`
+    pub fn add_fee(&mut self, amount: u64, asset: AssetId) {
+        self.outputs.push(TxOut::new_fee(amount, asset));
+    }
+
+    /// The unsigned spending transaction
+    #[must_use]
+    pub fn transaction(&self) -> Transaction {
+        Transaction {
+            version: 2,
+            lock_time: self.lock_time,
+            input: vec![TxIn {
+                previous_output: self.outpoint,
+                sequence: self.sequence,
+                ..TxIn::default()
+            }],
+            output: self.outputs.clone(),
+        }
+    }
+
+    /// Sighash the witness signs, with `SIGHASH_ALL`
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the UTXO is not locked by the program.
+    pub fn sighash_all(&self) -> Result<[u8; 32], SprayError> {
+        let (env, _) = self.environment()?;
+        Ok(env.c_tx_env().sighash_all().to_byte_array())
+    }
+
+    /// Satisfy the program with `witness` and finalize the transaction
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the UTXO is not locked by the program, the values
+    /// do not fit the program's witness nodes, or the program fails.
+    pub fn finalize(&self, witness: &WitnessList) -> Result<Transaction, SprayError> {
+        let (env, leaf) = self.environment()?;
+        let sighash = env.c_tx_env().sighash_all().to_byte_array();
+        let witness_bytes = witness.encode(sighash);
+
+        let expected = self.program.witness_types();
+        if witness.entries().len() != expected.len() {
+            return Err(SprayError::ParseError(format!(
+                "The program has {} witness nodes ({}), but {} values were given",
+                expected.len(),
+                expected.join(", "),
+                witness.entries().len()
+            )));
+        }
+        let redeem = RedeemNode::<Elements>::decode(
+            BitIter::from(self.program.bytes.iter().copied()),
+            BitIter::from(witness_bytes.iter().copied()),
+        )
+        .map_err(|e| SprayError::ParseError(format!("Witness does not fit the program: {e}")))?;
+        let pruned = redeem
+            .prune(&env)
+            .map_err(|e| SprayError::TestError(format!("Program failed: {e}")))?;
+        let (program_bytes, witness_bytes) = pruned.to_vec_with_witness();
+
+        let mut tx = self.transaction();
+        tx.input[0].witness.script_witness = vec![
+            witness_bytes,
+            program_bytes,
+            leaf.script.to_bytes(),
+            leaf.control_block.serialize(),
+        ];
+        Ok(tx)
+    }
+
+    /// The transaction environment of the spend, and the program's leaf
+    fn environment(&self) -> Result<(ElementsEnv<Arc<Transaction>>, SimplicityLeaf), SprayError> {
+        let cmr = self.program.cmr();
+        let leaf = SimplicityLeaf::new(cmr.to_byte_array())?;
+        if self.prevout.script_pubkey != leaf.script_pubkey {
+            return Err(SprayError::TestError(
+                "UTXO is not locked by this program".into(),
+            ));
+        }
+        let utxo = ElementsUtxo {
+            script_pubkey: self.prevout.script_pubkey.clone(),
+            asset: self.prevout.asset,
+            value: self.prevout.value,
+        };
+        let env = ElementsEnv::new(
+            Arc::new(self.transaction()),
+            vec![utxo],
+            0,
+            cmr,
+            leaf.control_block.clone(),
+            None,
+            self.genesis_hash,
+        );
+        Ok((env, leaf))
+    }
+}
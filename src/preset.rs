@@ -0,0 +1,189 @@
+//! Per-network default parameters
+//!
+//! Fees, funding amounts, dust limits, confirmation policies and block
+//! explorers differ between networks. A [`Preset`] gathers the defaults of
+//! one network, and commands take their defaults from the preset of the
+//! network they run against. spray ships a built-in preset for each network;
+//! any field can be overridden per network in the `[presets]` table of
+//! `spray.toml`:
+//!
+//! ```toml
+//! [presets.testnet]
+//! fee = 500
+//! min_feerate = 0.1
+//! funding_amount = 100000
+//! confirmations = 2
+//! confirmation_timeout_secs = 1800
+//! explorer = "https://liquid.network/testnet"
+//! ```
+//!
+//! # Example
+//!
+//! ```
+//! use musk::Network;
+//! use spray::preset::{Preset, PresetOverrides};
+//!
+//! let regtest = Preset::builtin(Network::Regtest);
+//! assert_eq!(regtest.fee, 3_000);
+//! assert!(regtest.tx_url("00").is_none());
+//!
+//! let testnet = Preset::builtin(Network::Testnet).with_overrides(&PresetOverrides {
+//!     fee: Some(500),
+//!     ..Default::default()
+//! });
+//! assert_eq!(testnet.fee, 500);
+//! assert_eq!(
+//!     testnet.tx_url("ab").unwrap(),
+//!     "https://blockstream.info/liquidtestnet/tx/ab"
+//! );
+//! ```
+
+use crate::error::SprayError;
+use crate::fee::{DEFAULT_FEE, FALLBACK_FEERATE};
+use crate::project::ProjectConfig;
+use musk::elements::AddressParams;
+use musk::Network;
+use serde::Deserialize;
+use std::time::Duration;
+
+/// Default parameters of a network
+#[derive(Debug, Clone, PartialEq)]
+pub struct Preset {
+    /// Name of the network's table under `[presets]`, e.g. `"testnet"`
+    pub name: &'static str,
+    /// Fixed fee of spends that give no fee policy, in satoshis
+    pub fee: u64,
+    /// Lowest fee rate used for rate-based fees, and the rate used when the
+    /// node has no estimate, in sat/vB
+    pub min_feerate: f64,
+    /// Smallest output amount spends may create, in satoshis
+    pub dust_limit: u64,
+    /// Amount deployments and tests fund contracts with, in satoshis
+    pub funding_amount: u64,
+    /// Confirmations to wait for when funding on an external node
+    pub confirmations: u32,
+    /// How long to wait for those confirmations
+    pub confirmation_timeout: Duration,
+    /// Base URL of a block explorer, without a trailing slash
+    pub explorer: Option<String>,
+    /// Address parameters of the network
+    pub address_params: &'static AddressParams,
+}
+
+/// Overrides of a built-in preset, from `[presets.<network>]` in `spray.toml`
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct PresetOverrides {
+    /// Fixed fee in satoshis
+    pub fee: Option<u64>,
+    /// Fee rate floor in sat/vB
+    pub min_feerate: Option<f64>,
+    /// Dust limit in satoshis
+    pub dust_limit: Option<u64>,
+    /// Funding amount in satoshis
+    pub funding_amount: Option<u64>,
+    /// Confirmations to wait for
+    pub confirmations: Option<u32>,
+    /// Confirmation timeout in seconds
+    pub confirmation_timeout_secs: Option<u64>,
+    /// Block explorer base URL
+    pub explorer: Option<String>,
+}
+
+impl Preset {
+    /// The built-in preset of `network`
+    #[must_use]
+    pub fn builtin(network: Network) -> Self {
+        let (name, explorer, address_params) = match network {
+            Network::Regtest => ("regtest", None, &AddressParams::ELEMENTS),
+            Network::Testnet => (
+                "testnet",
+                Some("https://blockstream.info/liquidtestnet"),
+                &AddressParams::LIQUID_TESTNET,
+            ),
+            Network::Liquid => (
+                "liquid",
+                Some("https://blockstream.info/liquid"),
+                &AddressParams::LIQUID,
+            ),
+        };
+        Self {
+            name,
+            fee: DEFAULT_FEE,
+            min_feerate: FALLBACK_FEERATE,
+            dust_limit: 546,
+            funding_amount: 100_000_000,
+            confirmations: 1,
+            confirmation_timeout: Duration::from_secs(600),
+            explorer: explorer.map(str::to_string),
+            address_params,
+        }
+    }
+
+    /// The preset of `network`: the built-in one, with the overrides of the
+    /// nearest `spray.toml`, if any
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a project file exists but cannot be loaded.
+    pub fn for_network(network: Network) -> Result<Self, SprayError> {
+        let preset = Self::builtin(network);
+        Ok(match ProjectConfig::discover()? {
+            Some((_, project)) => match project.presets.get(preset.name) {
+                Some(overrides) => preset.with_overrides(overrides),
+                None => preset,
+            },
+            None => preset,
+        })
+    }
+
+    /// This preset with the fields set in `overrides` replaced
+    #[must_use]
+    pub fn with_overrides(self, overrides: &PresetOverrides) -> Self {
+        Self {
+            fee: overrides.fee.unwrap_or(self.fee),
+            min_feerate: overrides.min_feerate.unwrap_or(self.min_feerate),
+            dust_limit: overrides.dust_limit.unwrap_or(self.dust_limit),
+            funding_amount: overrides.funding_amount.unwrap_or(self.funding_amount),
+            confirmations: overrides.confirmations.unwrap_or(self.confirmations),
+            confirmation_timeout: overrides
+                .confirmation_timeout_secs
+                .map_or(self.confirmation_timeout, Duration::from_secs),
+            explorer: overrides
+                .explorer
+                .as_ref()
+                .map(|url| url.trim_end_matches('/').to_string())
+                .or(self.explorer),
+            ..self
+        }
+    }
+
+    /// The fee rate to use given the node's `estimate`: the estimate, but no
+    /// less than [`Preset::min_feerate`]
+    #[must_use]
+    pub fn feerate(&self, estimate: Option<f64>) -> f64 {
+        estimate.map_or(self.min_feerate, |rate| rate.max(self.min_feerate))
+    }
+
+    /// Check that an output of `amount` satoshis is not dust
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `amount` is below [`Preset::dust_limit`].
+    pub fn check_dust(&self, amount: u64) -> Result<(), SprayError> {
+        if amount < self.dust_limit {
+            return Err(SprayError::ConfigError(format!(
+                "Output of {amount} sat is below the {} dust limit of {} sat",
+                self.name, self.dust_limit
+            )));
+        }
+        Ok(())
+    }
+
+    /// Explorer page of the transaction `txid`, if the network has an explorer
+    #[must_use]
+    pub fn tx_url(&self, txid: &str) -> Option<String> {
+        self.explorer
+            .as_ref()
+            .map(|explorer| format!("{explorer}/tx/{txid}"))
+    }
+}
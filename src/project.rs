@@ -0,0 +1,324 @@
+//! Project configuration (`spray.toml`)
+//!
+//! This module provides the [`ProjectConfig`] type, loaded from a `spray.toml`
+//! file in the working directory or one of its parents. Its `[toolchain]`
+//! table pins the minimum spray and musk versions and the expected Simplicity
+//! consensus version, so that every team member compiles with a toolchain that
+//! produces the same CMRs. Its `[artifacts]` table can require deployed
+//! artifacts to be signed by a trusted key (see [`crate::signing`]). Its
+//! `[presets.<network>]` tables override the per-network defaults of
+//! [`crate::preset`]. Its `[gc]` table sets what `spray gc` prunes (see
+//! [`crate::gc`]). Its `[defaults]` table sets defaults of command flags
+//! (see [`ProjectDefaults`]).
+//!
+//! # Example
+//!
+//! ```toml
+//! [defaults]
+//! network = "testnet"
+//! config = "musk.toml"
+//! fee = 500
+//! contracts_dir = "contracts"
+//! artifacts_dir = "build"
+//!
+//! [toolchain]
+//! min_spray = "0.1.0"
+//! min_musk = "0.1.0"
+//! simplicity = "0.7"
+//! on_mismatch = "warn"
+//!
+//! [artifacts]
+//! require_signature = true
+//! trusted_keys = ["79be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798"]
+//!
+//! [presets.testnet]
+//! fee = 500
+//!
+//! [gc]
+//! max_age_days = 30
+//! ```
+
+use crate::binary::ElementsdConfig;
+use crate::error::SprayError;
+use crate::gc::GcConfig;
+use crate::preset::PresetOverrides;
+use colored::Colorize;
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+/// Project file name
+pub const PROJECT_FILE: &str = "spray.toml";
+
+/// Version of this spray build
+pub const SPRAY_VERSION: &str = env!("CARGO_PKG_VERSION");
+
+/// Version of musk this spray build was compiled against
+pub const MUSK_VERSION: &str = env!("SPRAY_MUSK_VERSION");
+
+/// Version of the Simplicity consensus library (`simplicity-lang`) in this build
+pub const SIMPLICITY_VERSION: &str = env!("SPRAY_SIMPLICITY_VERSION");
+
+/// Contents of a `spray.toml` file
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct ProjectConfig {
+    /// Toolchain version pins
+    #[serde(default)]
+    pub toolchain: ToolchainPins,
+    /// Artifact signing policy
+    #[serde(default)]
+    pub artifacts: ArtifactPolicy,
+    /// Overrides of the built-in network presets, by network name
+    #[serde(default)]
+    pub presets: BTreeMap<String, PresetOverrides>,
+    /// Garbage collection policy
+    #[serde(default)]
+    pub gc: GcConfig,
+    /// Pinned `elementsd` release
+    #[serde(default)]
+    pub elementsd: ElementsdConfig,
+    /// Defaults of command flags
+    #[serde(default)]
+    pub defaults: ProjectDefaults,
+}
+
+/// Defaults of command flags from the `[defaults]` table
+///
+/// Flags and `SPRAY_*` environment variables take precedence. Paths are
+/// relative to `spray.toml`.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+pub struct ProjectDefaults {
+    /// Network, in place of `--network`
+    pub network: Option<String>,
+    /// Node config file, in place of `--config`
+    pub config: Option<PathBuf>,
+    /// Fixed fee in satoshis, in place of `--fee` and the network preset's
+    pub fee: Option<u64>,
+    /// Directory of contracts that `spray test` and `spray tui` run without
+    /// program arguments
+    pub contracts_dir: Option<PathBuf>,
+    /// Directory that `spray compile` writes artifacts to without `--out`
+    pub artifacts_dir: Option<PathBuf>,
+}
+
+impl ProjectDefaults {
+    /// Resolve the relative paths against `dir`, the directory of
+    /// `spray.toml`
+    #[must_use]
+    pub fn resolve(mut self, dir: &Path) -> Self {
+        for path in [
+            &mut self.config,
+            &mut self.contracts_dir,
+            &mut self.artifacts_dir,
+        ]
+        .into_iter()
+        .flatten()
+        {
+            *path = dir.join(&*path);
+        }
+        self
+    }
+
+    /// The node config `--config` defaults to: [`config`](Self::config),
+    /// unless [`crate::network::RPC_URL_ENV`] names a node, which takes
+    /// precedence over the project file
+    #[must_use]
+    pub fn node_config(&self) -> Option<&Path> {
+        if std::env::var_os(crate::network::RPC_URL_ENV).is_some() {
+            return None;
+        }
+        self.config.as_deref()
+    }
+}
+
+/// Environment variable with the default network of commands
+pub const NETWORK_ENV: &str = "SPRAY_NETWORK";
+
+/// Environment variable with the default node config file of commands
+pub const CONFIG_ENV: &str = "SPRAY_CONFIG";
+
+/// Artifact signing policy from the `[artifacts]` table
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct ArtifactPolicy {
+    /// Refuse to deploy artifacts without a valid signature by a trusted key
+    #[serde(default)]
+    pub require_signature: bool,
+    /// X-only public keys (hex) whose signatures are accepted
+    #[serde(default)]
+    pub trusted_keys: Vec<String>,
+}
+
+/// What to do when the toolchain does not satisfy the pins
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MismatchPolicy {
+    /// Refuse to run
+    #[default]
+    Error,
+    /// Print a warning and continue
+    Warn,
+}
+
+/// Toolchain version pins from the `[toolchain]` table
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct ToolchainPins {
+    /// Minimum spray version
+    pub min_spray: Option<String>,
+    /// Minimum musk version
+    pub min_musk: Option<String>,
+    /// Expected Simplicity consensus version, e.g. `"0.7"` or `"0.7.0"`
+    pub simplicity: Option<String>,
+    /// Policy on mismatch
+    #[serde(default)]
+    pub on_mismatch: MismatchPolicy,
+}
+
+impl ProjectConfig {
+    /// Load a project file
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be read or parsed.
+    pub fn load(path: &Path) -> Result<Self, SprayError> {
+        let contents = std::fs::read_to_string(path)?;
+        toml::from_str(&contents)
+            .map_err(|e| SprayError::ConfigError(format!("{}: {e}", path.display())))
+    }
+
+    /// Find the nearest `spray.toml` in `start` or one of its parents
+    #[must_use]
+    pub fn find(start: &Path) -> Option<PathBuf> {
+        start
+            .ancestors()
+            .map(|dir| dir.join(PROJECT_FILE))
+            .find(|path| path.is_file())
+    }
+
+    /// Load the nearest project file above the working directory, if any
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a project file exists but cannot be loaded.
+    pub fn discover() -> Result<Option<(PathBuf, Self)>, SprayError> {
+        let cwd = std::env::current_dir()?;
+        Self::find(&cwd)
+            .map(|path| Self::load(&path).map(|config| (path, config)))
+            .transpose()
+    }
+}
+
+impl ToolchainPins {
+    /// List every pin that the given toolchain versions do not satisfy
+    #[must_use]
+    pub fn mismatches(&self, spray: &str, musk: &str, simplicity: &str) -> Vec<String> {
+        let mut problems = Vec::new();
+
+        if let Some(min) = &self.min_spray {
+            if !version_at_least(spray, min) {
+                problems.push(format!("spray {spray} is older than the required {min}"));
+            }
+        }
+        if let Some(min) = &self.min_musk {
+            if !version_at_least(musk, min) {
+                problems.push(format!("musk {musk} is older than the required {min}"));
+            }
+        }
+        if let Some(expected) = &self.simplicity {
+            if !version_matches(simplicity, expected) {
+                problems.push(format!(
+                    "Simplicity consensus version {simplicity} does not match the expected {expected}"
+                ));
+            }
+        }
+
+        problems
+    }
+
+    /// Check this build against the pins, honoring [`MismatchPolicy`]
+    ///
+    /// # Errors
+    ///
+    /// Returns a configuration error if a pin is not satisfied and the policy
+    /// is [`MismatchPolicy::Error`].
+    pub fn enforce(&self) -> Result<(), SprayError> {
+        let problems = self.mismatches(SPRAY_VERSION, MUSK_VERSION, SIMPLICITY_VERSION);
+        if problems.is_empty() {
+            return Ok(());
+        }
+
+        match self.on_mismatch {
+            MismatchPolicy::Error => Err(SprayError::ConfigError(format!(
+                "Toolchain does not satisfy {PROJECT_FILE}: {}",
+                problems.join("; ")
+            ))),
+            MismatchPolicy::Warn => {
+                for problem in problems {
+                    eprintln!("{} {problem}", "Warning:".yellow().bold());
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+/// Enforce the toolchain pins of the nearest `spray.toml`, if any
+///
+/// # Errors
+///
+/// Returns an error if the project file cannot be loaded or its pins are not
+/// satisfied.
+pub fn enforce_toolchain() -> Result<(), SprayError> {
+    match ProjectConfig::discover()? {
+        Some((_, config)) => config.toolchain.enforce(),
+        None => Ok(()),
+    }
+}
+
+/// The `[defaults]` of the nearest `spray.toml`, with resolved paths
+///
+/// # Errors
+///
+/// Returns an error if the project file cannot be loaded.
+pub fn defaults() -> Result<ProjectDefaults, SprayError> {
+    Ok(ProjectConfig::discover()?
+        .map(|(path, config)| {
+            let dir = path.parent().map(Path::to_path_buf).unwrap_or_default();
+            config.defaults.resolve(&dir)
+        })
+        .unwrap_or_default())
+}
+
+/// Parse the numeric components of a version, ignoring any pre-release suffix
+fn version_parts(version: &str) -> Option<Vec<u64>> {
+    version
+        .split(['-', '+'])
+        .next()?
+        .split('.')
+        .map(|part| part.parse().ok())
+        .collect()
+}
+
+/// Whether `version` is at least `min`
+///
+/// Unparseable versions never satisfy a minimum.
+#[must_use]
+pub fn version_at_least(version: &str, min: &str) -> bool {
+    match (version_parts(version), version_parts(min)) {
+        (Some(mut version), Some(mut min)) => {
+            let len = version.len().max(min.len());
+            version.resize(len, 0);
+            min.resize(len, 0);
+            version >= min
+        }
+        _ => false,
+    }
+}
+
+/// Whether `version` matches `expected`, which may omit trailing components
+#[must_use]
+pub fn version_matches(version: &str, expected: &str) -> bool {
+    match (version_parts(version), version_parts(expected)) {
+        (Some(version), Some(expected)) => version.starts_with(&expected),
+        _ => false,
+    }
+}
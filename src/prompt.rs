@@ -0,0 +1,200 @@
+//! Interactive prompts
+//!
+//! `spray redeem --interactive` asks for everything a spend needs instead of
+//! taking it from flags. [`Prompt`] asks questions on any reader and writer,
+//! re-asking until an answer is valid, and asks for witness values by their
+//! declared [`ValueType`]: an `Either` is a choice between its two sides
+//! (usually the spend path), an `Option` a yes/no question, a tuple one
+//! question per component, and integers and byte arrays are checked as with
+//! `--wit`.
+//!
+//! An answer starting with `@` names a keystore key; it is passed to a
+//! signing callback, whose result (e.g. a signature over the spend's sighash)
+//! becomes the value.
+//!
+//! # Example
+//!
+//! ```
+//! use spray::prompt::Prompt;
+//! use spray::value::ValueType;
+//!
+//! let input = b"2\n0x01\n";
+//! let mut output = Vec::new();
+//! let mut prompt = Prompt::new(&input[..], &mut output);
+//!
+//! let ty: ValueType = "Either<u8, u16>".parse().unwrap();
+//! let value = prompt.value("path", &ty, &mut |_| unreachable!()).unwrap();
+//! assert_eq!(value, "Right(1)");
+//! ```
+
+use crate::error::SprayError;
+use crate::value::ValueType;
+use std::io::{BufRead, Write};
+
+/// Asks questions on a reader and writer
+pub struct Prompt<R, W> {
+    input: R,
+    output: W,
+}
+
+impl<R: BufRead, W: Write> Prompt<R, W> {
+    /// Prompt on `input` and `output`, e.g. locked stdin and stdout
+    pub const fn new(input: R, output: W) -> Self {
+        Self { input, output }
+    }
+
+    /// Ask a question, returning the trimmed answer or `default` if the
+    /// answer is empty
+    ///
+    /// Without a default, the question is asked again until answered.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the input ends or cannot be read.
+    pub fn ask(&mut self, question: &str, default: Option<&str>) -> Result<String, SprayError> {
+        loop {
+            match default {
+                Some(default) => write!(self.output, "{question} [{default}]: ")?,
+                None => write!(self.output, "{question}: ")?,
+            }
+            self.output.flush()?;
+
+            let mut line = String::new();
+            if self.input.read_line(&mut line)? == 0 {
+                return Err(SprayError::ConfigError(
+                    "Input ended before all questions were answered".into(),
+                ));
+            }
+            let answer = line.trim();
+            match (answer.is_empty(), default) {
+                (false, _) => return Ok(answer.to_string()),
+                (true, Some(default)) => return Ok(default.to_string()),
+                (true, None) => {}
+            }
+        }
+    }
+
+    /// Ask until `parse` accepts the answer, printing its errors
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the input ends or cannot be read.
+    pub fn ask_parsed<T>(
+        &mut self,
+        question: &str,
+        default: Option<&str>,
+        mut parse: impl FnMut(&str) -> Result<T, SprayError>,
+    ) -> Result<T, SprayError> {
+        loop {
+            let answer = self.ask(question, default)?;
+            match parse(&answer) {
+                Ok(value) => return Ok(value),
+                Err(e) => writeln!(self.output, "  {e}")?,
+            }
+        }
+    }
+
+    /// Ask to choose one of `options`, listed with numbers from 1, returning
+    /// the index of the chosen option
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `options` is empty or the input ends.
+    pub fn choose(&mut self, question: &str, options: &[String]) -> Result<usize, SprayError> {
+        if options.is_empty() {
+            return Err(SprayError::ConfigError(format!(
+                "Nothing to choose for: {question}"
+            )));
+        }
+        writeln!(self.output, "{question}")?;
+        for (i, option) in options.iter().enumerate() {
+            writeln!(self.output, "  {}) {option}", i + 1)?;
+        }
+        let count = options.len();
+        self.ask_parsed("Choice", None, |answer| {
+            answer
+                .parse::<usize>()
+                .ok()
+                .filter(|n| (1..=count).contains(n))
+                .map(|n| n - 1)
+                .ok_or_else(|| SprayError::ParseError(format!("Enter a number from 1 to {count}")))
+        })
+    }
+
+    /// Ask a yes/no question
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the input ends or cannot be read.
+    pub fn confirm(&mut self, question: &str, default: bool) -> Result<bool, SprayError> {
+        self.ask_parsed(
+            question,
+            Some(if default { "y" } else { "n" }),
+            |answer| match answer.to_lowercase().as_str() {
+                "y" | "yes" => Ok(true),
+                "n" | "no" => Ok(false),
+                _ => Err(SprayError::ParseError("Answer y or n".into())),
+            },
+        )
+    }
+
+    /// Ask for a value of type `ty` named `name`, returning it as a
+    /// SimplicityHL literal
+    ///
+    /// Answers starting with `@` are passed, without the `@`, to `sign`,
+    /// which returns the literal to use.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the input ends or cannot be read.
+    pub fn value(
+        &mut self,
+        name: &str,
+        ty: &ValueType,
+        sign: &mut dyn FnMut(&str) -> Result<String, SprayError>,
+    ) -> Result<String, SprayError> {
+        match ty {
+            ValueType::Unit => Ok("()".to_string()),
+            ValueType::Either(left, right) => {
+                let side = self.choose(
+                    &format!("{name}: {ty}"),
+                    &[format!("Left({left})"), format!("Right({right})")],
+                )?;
+                let (label, inner) = if side == 0 {
+                    ("Left", left)
+                } else {
+                    ("Right", right)
+                };
+                let value = self.value(&format!("{name}.{label}"), inner, sign)?;
+                Ok(format!("{label}({value})"))
+            }
+            ValueType::Option(inner) => {
+                if self.confirm(&format!("{name}: {ty}, provide a value?"), true)? {
+                    let value = self.value(&format!("{name}.Some"), inner, sign)?;
+                    Ok(format!("Some({value})"))
+                } else {
+                    Ok("None".to_string())
+                }
+            }
+            ValueType::Tuple(items) => {
+                let values = items
+                    .iter()
+                    .enumerate()
+                    .map(|(i, item)| self.value(&format!("{name}.{i}"), item, sign))
+                    .collect::<Result<Vec<_>, _>>()?;
+                Ok(format!("({})", values.join(", ")))
+            }
+            _ => self.ask_parsed(&format!("{name} ({ty})"), None, |answer| {
+                let literal = match answer.strip_prefix('@') {
+                    Some(key) => sign(key)?,
+                    None => answer.to_string(),
+                };
+                let parsed = ty.parse_literal(&literal)?;
+                Ok(match parsed {
+                    serde_json::Value::String(literal) => literal,
+                    other => other.to_string(),
+                })
+            }),
+        }
+    }
+}
@@ -0,0 +1,423 @@
+//! Property-based testing
+//!
+//! A property test states what a contract must do for a whole family of
+//! inputs rather than for one: a generator draws the arguments to
+//! instantiate the program with, the witness to spend it with, and whether
+//! the spend should be accepted, and the property is checked against many
+//! randomized draws on one regtest daemon (see [`TestCase::property`]).
+//!
+//! [`Gen`] is the random number generator handed to the generator. It
+//! records every number drawn on a tape, so that a failing case can be
+//! replayed from its tape and shrunk: the tape is cut short and its numbers
+//! lowered towards zero, see [`shrink_candidates`], for as long as the
+//! property still fails. Generators that derive sizes and values from the
+//! numbers they draw, as `gen_range` does, then shrink towards small values
+//! and short inputs.
+//!
+//! Each run draws its cases from a seed, which is reported with the failure
+//! and can be passed back with [`Property::seed`] to reproduce it.
+//!
+//! [`TestCase::property`]: crate::TestCase::property
+//!
+//! # Example
+//!
+//! ```
+//! use rand::Rng;
+//! use spray::property::Gen;
+//!
+//! let mut rng = Gen::from_seed(42);
+//! let drawn: Vec<u32> = (0..4).map(|_| rng.gen_range(0..1000)).collect();
+//!
+//! // The same tape draws the same values
+//! let mut replay = Gen::replay(rng.tape().to_vec());
+//! let replayed: Vec<u32> = (0..4).map(|_| replay.gen_range(0..1000)).collect();
+//! assert_eq!(drawn, replayed);
+//!
+//! // Past the end of the tape, every number drawn is zero
+//! assert_eq!(Gen::replay(Vec::new()).gen_range(0..1000), 0);
+//! ```
+
+use crate::fuzz::Outcome;
+use rand::rngs::StdRng;
+use rand::{RngCore, SeedableRng};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+#[cfg(feature = "daemon")]
+use {
+    crate::env::TestEnv,
+    crate::error::SprayError,
+    crate::test::{FailureStage, TestCase, TestResult},
+    colored::Colorize,
+    musk::{Arguments, WitnessValues},
+};
+
+/// Default number of cases checked
+pub const DEFAULT_CASES: usize = 32;
+
+/// Default number of shrinking attempts
+pub const DEFAULT_SHRINK_LIMIT: usize = 64;
+
+/// Whether a generated spend should be accepted
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Expectation {
+    /// The spend is accepted by the node
+    Accept,
+    /// The spend is rejected, at any stage
+    Reject,
+}
+
+impl Expectation {
+    /// Whether `outcome` meets the expectation
+    #[must_use]
+    pub const fn holds(self, outcome: &Outcome) -> bool {
+        matches!(
+            (self, outcome),
+            (Self::Accept, Outcome::Accepted) | (Self::Reject, Outcome::Rejected { .. })
+        )
+    }
+}
+
+impl fmt::Display for Expectation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Accept => "accepted",
+            Self::Reject => "rejected",
+        })
+    }
+}
+
+/// Random number generator recording what it draws
+///
+/// Numbers are drawn from the tape first, then from the seeded source, if
+/// any, and are zero once both run out.
+#[derive(Debug, Clone)]
+pub struct Gen {
+    tape: Vec<u64>,
+    position: usize,
+    source: Option<StdRng>,
+}
+
+impl Gen {
+    /// A generator drawing fresh numbers from `seed`
+    #[must_use]
+    pub fn from_seed(seed: u64) -> Self {
+        Self {
+            tape: Vec::new(),
+            position: 0,
+            source: Some(StdRng::seed_from_u64(seed)),
+        }
+    }
+
+    /// A generator replaying `tape`
+    #[must_use]
+    pub const fn replay(tape: Vec<u64>) -> Self {
+        Self {
+            tape,
+            position: 0,
+            source: None,
+        }
+    }
+
+    /// The numbers drawn so far
+    #[must_use]
+    pub fn tape(&self) -> &[u64] {
+        &self.tape[..self.position.min(self.tape.len())]
+    }
+}
+
+impl RngCore for Gen {
+    #[allow(clippy::cast_possible_truncation)]
+    fn next_u32(&mut self) -> u32 {
+        // The high half, so that lowering a number lowers what is drawn
+        (self.next_u64() >> 32) as u32
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        let value = if let Some(&value) = self.tape.get(self.position) {
+            value
+        } else if let Some(source) = &mut self.source {
+            let value = source.next_u64();
+            self.tape.push(value);
+            value
+        } else {
+            0
+        };
+        self.position += 1;
+        value
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        for chunk in dest.chunks_mut(8) {
+            let bytes = self.next_u64().to_be_bytes();
+            chunk.copy_from_slice(&bytes[..chunk.len()]);
+        }
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand::Error> {
+        self.fill_bytes(dest);
+        Ok(())
+    }
+}
+
+/// Smaller variants of `tape` to try when shrinking, simplest first
+///
+/// The tape is cut to its first half and with its last number dropped, and
+/// then each number is set to zero and halved.
+#[must_use]
+pub fn shrink_candidates(tape: &[u64]) -> Vec<Vec<u64>> {
+    let mut candidates = Vec::new();
+    if tape.len() > 1 {
+        candidates.push(tape[..tape.len() / 2].to_vec());
+    }
+    if !tape.is_empty() {
+        candidates.push(tape[..tape.len() - 1].to_vec());
+    }
+    for (i, &value) in tape.iter().enumerate() {
+        for smaller in [0, value / 2] {
+            if smaller < value {
+                let mut candidate = tape.to_vec();
+                candidate[i] = smaller;
+                candidates.push(candidate);
+            }
+        }
+    }
+    candidates.dedup();
+    candidates
+}
+
+/// A case that broke the property, after shrinking
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PropertyFailure {
+    /// Index of the case that failed first
+    pub case: usize,
+    /// Number of successful shrinking steps
+    pub shrinks: usize,
+    /// Tape the shrunk case is drawn from, for [`Gen::replay`]
+    pub tape: Vec<u64>,
+    /// The generated arguments
+    pub arguments: String,
+    /// The generated witness
+    pub witness: String,
+    /// What the generator expected
+    pub expected: Expectation,
+    /// What happened instead
+    pub outcome: Outcome,
+}
+
+impl fmt::Display for PropertyFailure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let outcome = match &self.outcome {
+            Outcome::Accepted => "accepted".to_string(),
+            Outcome::Rejected { stage, reason } => format!("rejected at {stage}: {reason}"),
+        };
+        write!(
+            f,
+            "case {} (shrunk {} times): expected the spend to be {}, but it was {outcome}\n  arguments: {}\n  witness: {}",
+            self.case, self.shrinks, self.expected, self.arguments, self.witness
+        )
+    }
+}
+
+/// Result of a property test
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PropertyReport {
+    /// Seed the cases were drawn from
+    pub seed: u64,
+    /// Number of cases checked
+    pub cases: usize,
+    /// The first failing case, shrunk
+    pub failure: Option<PropertyFailure>,
+}
+
+impl PropertyReport {
+    /// Whether every case held
+    #[must_use]
+    pub const fn passed(&self) -> bool {
+        self.failure.is_none()
+    }
+}
+
+impl fmt::Display for PropertyReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.failure {
+            None => write!(f, "{} cases passed (seed {})", self.cases, self.seed),
+            Some(failure) => write!(f, "property failed at {failure}\n  seed: {}", self.seed),
+        }
+    }
+}
+
+/// A property checked over randomized instantiations of a program
+///
+/// Built with [`TestCase::property`].
+#[cfg(feature = "daemon")]
+pub struct Property<'env, F> {
+    env: &'env TestEnv,
+    program: &'env musk::Program,
+    generator: F,
+    cases: usize,
+    seed: Option<u64>,
+    shrink_limit: usize,
+}
+
+#[cfg(feature = "daemon")]
+impl<'env, F> Property<'env, F>
+where
+    F: Fn(&mut Gen) -> (Arguments, WitnessValues, Expectation),
+{
+    pub(crate) const fn new(
+        env: &'env TestEnv,
+        program: &'env musk::Program,
+        generator: F,
+    ) -> Self {
+        Self {
+            env,
+            program,
+            generator,
+            cases: DEFAULT_CASES,
+            seed: None,
+            shrink_limit: DEFAULT_SHRINK_LIMIT,
+        }
+    }
+
+    /// Set the number of cases checked
+    #[must_use]
+    pub const fn cases(mut self, cases: usize) -> Self {
+        self.cases = cases;
+        self
+    }
+
+    /// Draw the cases from `seed`, e.g. to reproduce a reported failure
+    ///
+    /// Without a seed, a random one is drawn, or
+    /// [`deterministic::SEED`](crate::deterministic::SEED) in deterministic
+    /// mode.
+    #[must_use]
+    pub const fn seed(mut self, seed: u64) -> Self {
+        self.seed = Some(seed);
+        self
+    }
+
+    /// Set the number of shrinking attempts for a failing case
+    #[must_use]
+    pub const fn shrink_limit(mut self, limit: usize) -> Self {
+        self.shrink_limit = limit;
+        self
+    }
+
+    /// Check the property, stopping at the first failing case
+    ///
+    /// Each case is spent from a fresh UTXO. A failing case is shrunk
+    /// before it is reported.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a UTXO cannot be funded or confirmed. Cases that
+    /// do not meet their expectation are reported, not returned.
+    pub fn run(self) -> Result<PropertyReport, SprayError> {
+        let seed = self
+            .seed
+            .unwrap_or_else(|| crate::deterministic::seed(self.env.is_deterministic()));
+        let mut seeds = StdRng::seed_from_u64(seed);
+        crate::status!(
+            "  {} {} cases (seed {seed})",
+            "Property:".dimmed(),
+            self.cases
+        );
+
+        for case in 0..self.cases {
+            let mut rng = Gen::from_seed(seeds.next_u64());
+            let Some(failure) = self.check(&mut rng)? else {
+                continue;
+            };
+
+            let failure = self.shrink(PropertyFailure { case, ..failure })?;
+            return Ok(PropertyReport {
+                seed,
+                cases: case + 1,
+                failure: Some(failure),
+            });
+        }
+
+        Ok(PropertyReport {
+            seed,
+            cases: self.cases,
+            failure: None,
+        })
+    }
+
+    /// Generate a case from `rng` and check it
+    fn check(&self, rng: &mut Gen) -> Result<Option<PropertyFailure>, SprayError> {
+        let (arguments, witness, expected) = (self.generator)(rng);
+        let description = (format!("{arguments:?}"), format!("{witness:?}"));
+        let outcome = self.spend(arguments, witness)?;
+
+        Ok((!expected.holds(&outcome)).then(|| PropertyFailure {
+            case: 0,
+            shrinks: 0,
+            tape: rng.tape().to_vec(),
+            arguments: description.0,
+            witness: description.1,
+            expected,
+            outcome,
+        }))
+    }
+
+    /// Replace `failure` by smaller failing cases for as long as some are
+    /// found, within the shrink limit
+    fn shrink(&self, mut failure: PropertyFailure) -> Result<PropertyFailure, SprayError> {
+        let mut attempts = 0;
+        'shrink: while attempts < self.shrink_limit {
+            for candidate in shrink_candidates(&failure.tape) {
+                if attempts == self.shrink_limit {
+                    break 'shrink;
+                }
+                attempts += 1;
+                if let Some(smaller) = self.check(&mut Gen::replay(candidate))? {
+                    failure = PropertyFailure {
+                        case: failure.case,
+                        shrinks: failure.shrinks + 1,
+                        ..smaller
+                    };
+                    continue 'shrink;
+                }
+            }
+            break;
+        }
+        Ok(failure)
+    }
+
+    /// Instantiate the program with `arguments` and spend a fresh UTXO of it
+    /// with `witness`
+    fn spend(&self, arguments: Arguments, witness: WitnessValues) -> Result<Outcome, SprayError> {
+        let program = match self.program.instantiate(arguments) {
+            Ok(program) => program,
+            Err(e) => {
+                return Ok(Outcome::Rejected {
+                    stage: FailureStage::Compile,
+                    reason: e.to_string(),
+                })
+            }
+        };
+
+        let mut test = TestCase::new(self.env, program)
+            .name("property")
+            .witness(move |_| witness.clone());
+        test.create_utxo()?;
+        self.env.generate(1)?;
+
+        Ok(match test.run_staged() {
+            Ok(TestResult::Success { .. }) => Outcome::Accepted,
+            Ok(TestResult::Failure { error }) => Outcome::Rejected {
+                stage: FailureStage::Satisfaction,
+                reason: error,
+            },
+            Err((FailureStage::Funding, e)) => return Err(e),
+            Err((stage, e)) => Outcome::Rejected {
+                stage,
+                reason: e.to_string(),
+            },
+        })
+    }
+}
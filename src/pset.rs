@@ -0,0 +1,119 @@
+//! PSET export for offline signing
+//!
+//! This module provides the [`SimplicityLeaf`] type, the taproot leaf that
+//! commits to a Simplicity program, and builds unsigned v2 PSETs spending a
+//! contract UTXO with the leaf data attached. The PSET can then be completed
+//! on an air-gapped machine or passed between the parties of a multi-party
+//! spend.
+
+use crate::error::SprayError;
+use musk::elements::pset::PartiallySignedTransaction;
+use musk::elements::secp256k1_zkp::{Secp256k1, XOnlyPublicKey};
+use musk::elements::taproot::{ControlBlock, LeafVersion, TapNodeHash, TaprootBuilder};
+use musk::elements::{LockTime, OutPoint, Script, Sequence, Transaction, TxIn, TxOut};
+use std::str::FromStr;
+
+/// Provably unspendable internal key used for Simplicity taproot outputs
+pub const UNSPENDABLE_INTERNAL_KEY: &str =
+    "50929b74c1a04954b78b4b6035e97a5e078a5a0f28ec96d547bfee9ace803ac0";
+
+/// Taproot leaf version of Simplicity leaves
+pub const SIMPLICITY_LEAF_VERSION: u8 = 0xbe;
+
+/// Taproot leaf committing to a Simplicity program
+#[derive(Debug, Clone)]
+pub struct SimplicityLeaf {
+    /// Leaf script (the program CMR)
+    pub script: Script,
+    /// Leaf version, see [`SIMPLICITY_LEAF_VERSION`]
+    pub version: LeafVersion,
+    /// Taproot internal key, see [`UNSPENDABLE_INTERNAL_KEY`]
+    pub internal_key: XOnlyPublicKey,
+    /// Control block proving the leaf's inclusion in the output key
+    pub control_block: ControlBlock,
+    /// Merkle root of the taproot tree
+    pub merkle_root: Option<TapNodeHash>,
+    /// Output script of the contract address
+    pub script_pubkey: Script,
+}
+
+impl SimplicityLeaf {
+    /// Build the leaf for a program with the given CMR
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the taproot tree cannot be built.
+    pub fn new(cmr: [u8; 32]) -> Result<Self, SprayError> {
+        let secp = Secp256k1::verification_only();
+        let script = Script::from(cmr.to_vec());
+        let version = LeafVersion::from_u8(SIMPLICITY_LEAF_VERSION)
+            .map_err(|e| SprayError::TestError(format!("Invalid leaf version: {e}")))?;
+        let internal_key = XOnlyPublicKey::from_str(UNSPENDABLE_INTERNAL_KEY)
+            .map_err(|e| SprayError::ParseError(format!("Invalid internal key: {e}")))?;
+
+        let info = TaprootBuilder::new()
+            .add_leaf_with_ver(0, script.clone(), version)
+            .and_then(|builder| builder.finalize(&secp, internal_key))
+            .map_err(|e| SprayError::TestError(format!("Failed to build taproot tree: {e}")))?;
+
+        let control_block = info
+            .control_block(&(script.clone(), version))
+            .ok_or_else(|| SprayError::TestError("Missing control block for leaf".into()))?;
+        let script_pubkey = Script::new_v1_p2tr_tweaked(info.output_key());
+
+        Ok(Self {
+            script,
+            version,
+            internal_key,
+            control_block,
+            merkle_root: info.merkle_root(),
+            script_pubkey,
+        })
+    }
+
+    /// Build an unsigned v2 PSET spending `prevout` (the output at `outpoint`)
+    ///
+    /// The input carries the prevout as its witness UTXO together with the
+    /// internal key, merkle root and leaf script of this program.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the prevout is not locked by this program.
+    pub fn spend_pset(
+        &self,
+        outpoint: OutPoint,
+        prevout: TxOut,
+        sequence: Sequence,
+        lock_time: LockTime,
+        outputs: Vec<TxOut>,
+    ) -> Result<PartiallySignedTransaction, SprayError> {
+        if prevout.script_pubkey != self.script_pubkey {
+            return Err(SprayError::TestError(
+                "UTXO is not locked by this program".into(),
+            ));
+        }
+
+        let tx = Transaction {
+            version: 2,
+            lock_time,
+            input: vec![TxIn {
+                previous_output: outpoint,
+                sequence,
+                ..TxIn::default()
+            }],
+            output: outputs,
+        };
+
+        let mut pset = PartiallySignedTransaction::from_tx(tx);
+        let input = &mut pset.inputs_mut()[0];
+        input.witness_utxo = Some(prevout);
+        input.tap_internal_key = Some(self.internal_key);
+        input.tap_merkle_root = self.merkle_root;
+        input.tap_scripts.insert(
+            self.control_block.clone(),
+            (self.script.clone(), self.version),
+        );
+
+        Ok(pset)
+    }
+}
@@ -0,0 +1,509 @@
+//! Deployment registry
+//!
+//! This module provides the [`Registry`] type, a JSON file recording every
+//! deployment made with `spray deploy`. The registry lets spray recognize a
+//! deployment that was already broadcast when a command is re-run, and lets
+//! `spray redeem` find the program of a contract UTXO without `--compiled`.
+//!
+//! Alongside the registry file, a lease directory holds one [`Lease`] file
+//! per outpoint being spent, so that concurrent runs targeting the same
+//! deployment fail fast instead of broadcasting conflicting spends.
+//!
+//! Runs that record deployments do so through [`Registry::update`], which
+//! holds a lock file while it reloads, changes and saves the registry, so
+//! that concurrent runs never drop each other's records. Saves replace the
+//! file in one rename, so readers never see a partly written registry.
+//!
+//! # Example
+//!
+//! ```
+//! use spray::registry::{DeploymentRecord, Registry};
+//!
+//! let dir = std::env::temp_dir().join("spray-registry-doc");
+//! let mut registry = Registry::load(&dir.join("deployments.json")).unwrap();
+//!
+//! registry.add(DeploymentRecord {
+//!     txid: "00".repeat(32),
+//!     vout: 0,
+//!     cmr: "deadbeef".into(),
+//!     network: "regtest".into(),
+//!     amount: 100_000,
+//!     asset: None,
+//!     timestamp: 0,
+//!     ..Default::default()
+//! });
+//!
+//! assert!(registry.find_matching("deadbeef", 100_000, None, "regtest").next().is_some());
+//! ```
+
+use crate::error::SprayError;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs::OpenOptions;
+use std::io::{ErrorKind, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+/// Default registry file name, relative to the working directory
+pub const DEFAULT_REGISTRY_FILE: &str = "deployments.json";
+
+/// How long [`Registry::update`] waits for another run's registry lock
+pub const DEFAULT_LOCK_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Age after which a registry lock is taken to be left by a crashed run
+///
+/// An update holds the lock for milliseconds, so a lock this old is not in
+/// use.
+pub const STALE_LOCK_AGE: Duration = Duration::from_secs(60);
+
+/// Delay between two attempts at taking a registry lock
+const LOCK_POLL_INTERVAL: Duration = Duration::from_millis(25);
+
+/// A single deployment
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DeploymentRecord {
+    /// Funding transaction ID
+    pub txid: String,
+    /// Output index of the contract UTXO
+    pub vout: u32,
+    /// Commitment Merkle Root of the deployed program (hex)
+    pub cmr: String,
+    /// Network name
+    pub network: String,
+    /// Funded amount in satoshis
+    pub amount: u64,
+    /// Funded asset ID (hex), `None` for the policy asset
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub asset: Option<String>,
+    /// Unix timestamp of the deployment
+    pub timestamp: u64,
+    /// Outpoints (`txid:vout`, comma-separated) of the previous contract
+    /// version's UTXOs swept into this deployment, if it was created by
+    /// `spray migrate-funds`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub migrated_from: Option<String>,
+    /// Absolute path of the program file (`.simf` or compiled `.json`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub artifact: Option<String>,
+    /// Absolute path of the arguments file the program was instantiated with
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub args: Option<String>,
+    /// Argument values given with `--arg`, merged over the arguments file
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub arg_overrides: BTreeMap<String, String>,
+    /// Chain height when the funding transaction was broadcast; it confirms
+    /// at or above this height
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub height: Option<u64>,
+}
+
+/// On-disk registry of deployments
+#[derive(Debug, Clone)]
+pub struct Registry {
+    path: PathBuf,
+    records: Vec<DeploymentRecord>,
+}
+
+impl Registry {
+    /// Load a registry from `path`
+    ///
+    /// A missing file yields an empty registry that will be created on save.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file exists but cannot be read or parsed.
+    pub fn load(path: &Path) -> Result<Self, SprayError> {
+        let records = if path.exists() {
+            let contents = std::fs::read_to_string(path)?;
+            serde_json::from_str(&contents)?
+        } else {
+            Vec::new()
+        };
+
+        Ok(Self {
+            path: path.to_path_buf(),
+            records,
+        })
+    }
+
+    /// Write the registry back to its file
+    ///
+    /// The records are written in full to a temporary file next to the
+    /// registry, which is then renamed over it. Use [`update`](Self::update)
+    /// to add records while other runs may do the same.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if serialization or writing the file fails.
+    pub fn save(&self) -> Result<(), SprayError> {
+        let json = serde_json::to_string_pretty(&self.records)?;
+        let temp = TempFile(sibling(&self.path, &format!("{}.tmp", unique_suffix())));
+        {
+            let mut file = OpenOptions::new()
+                .write(true)
+                .create_new(true)
+                .open(&temp.0)?;
+            file.write_all(json.as_bytes())?;
+            file.sync_all()?;
+        }
+        std::fs::rename(&temp.0, &self.path)?;
+        Ok(())
+    }
+
+    /// Load the registry at `path`, change it with `update` and save it,
+    /// holding the registry's [lock](Self::lock) throughout
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the lock is not released within
+    /// [`DEFAULT_LOCK_TIMEOUT`], the registry cannot be loaded or saved, or
+    /// `update` fails, in which case nothing is saved.
+    pub fn update<T>(
+        path: &Path,
+        update: impl FnOnce(&mut Self) -> Result<T, SprayError>,
+    ) -> Result<T, SprayError> {
+        let _lock = Self::lock(path, DEFAULT_LOCK_TIMEOUT)?;
+        let mut registry = Self::load(path)?;
+        let value = update(&mut registry)?;
+        registry.save()?;
+        Ok(value)
+    }
+
+    /// Take the lock file of the registry at `path`, waiting up to
+    /// `timeout` for another run to release it
+    ///
+    /// The lock is taken like a [lease](Self::lease): written to a
+    /// temporary file and hard-linked into place. A lock older than
+    /// [`STALE_LOCK_AGE`] is taken over.
+    ///
+    /// # Errors
+    ///
+    /// Returns an IO error if the lock is still held after `timeout` or the
+    /// lock file cannot be written.
+    pub fn lock(path: &Path, timeout: Duration) -> Result<RegistryLock, SprayError> {
+        let lock = path.with_extension("lock");
+        let dir = lock
+            .parent()
+            .filter(|dir| !dir.as_os_str().is_empty())
+            .unwrap_or_else(|| Path::new("."));
+        std::fs::create_dir_all(dir)?;
+
+        let temp = TempFile(sibling(&lock, &format!("{}.tmp", unique_suffix())));
+        {
+            let mut file = OpenOptions::new()
+                .write(true)
+                .create_new(true)
+                .open(&temp.0)?;
+            file.write_all(format!("{} {}", run_id(), now()).as_bytes())?;
+            file.sync_all()?;
+        }
+
+        let deadline = Instant::now() + timeout;
+        loop {
+            match std::fs::hard_link(&temp.0, &lock) {
+                Ok(()) => return Ok(RegistryLock { path: lock }),
+                Err(e) if e.kind() == ErrorKind::AlreadyExists => {}
+                Err(e) => return Err(e.into()),
+            }
+
+            let Some(contents) = read_if_exists(&lock)? else {
+                continue;
+            };
+            let age = std::fs::metadata(&lock)
+                .and_then(|metadata| metadata.modified())
+                .ok()
+                .and_then(|modified| modified.elapsed().ok());
+            if age.is_some_and(|age| age >= STALE_LOCK_AGE) {
+                take_stale(&lock, &contents, dir)?;
+                continue;
+            }
+            if Instant::now() >= deadline {
+                return Err(std::io::Error::new(
+                    ErrorKind::TimedOut,
+                    format!(
+                        "registry {} is locked by run {} ({})",
+                        path.display(),
+                        String::from_utf8_lossy(&contents),
+                        lock.display()
+                    ),
+                )
+                .into());
+            }
+            std::thread::sleep(LOCK_POLL_INTERVAL);
+        }
+    }
+
+    /// Path of the registry file
+    #[must_use]
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// All records, oldest first
+    #[must_use]
+    pub fn records(&self) -> &[DeploymentRecord] {
+        &self.records
+    }
+
+    /// Append a record
+    pub fn add(&mut self, record: DeploymentRecord) {
+        self.records.push(record);
+    }
+
+    /// Find the record for a given outpoint
+    #[must_use]
+    pub fn find(&self, txid: &str, vout: u32) -> Option<&DeploymentRecord> {
+        self.records
+            .iter()
+            .find(|r| r.txid == txid && r.vout == vout)
+    }
+
+    /// Find all records for a contract on a network, oldest first
+    pub fn find_by_cmr<'a>(
+        &'a self,
+        cmr: &'a str,
+        network: &'a str,
+    ) -> impl Iterator<Item = &'a DeploymentRecord> {
+        self.records
+            .iter()
+            .filter(move |r| r.cmr == cmr && r.network == network)
+    }
+
+    /// Find records for an identical deployment, newest first
+    pub fn find_matching<'a>(
+        &'a self,
+        cmr: &'a str,
+        amount: u64,
+        asset: Option<&'a str>,
+        network: &'a str,
+    ) -> impl Iterator<Item = &'a DeploymentRecord> {
+        self.records.iter().rev().filter(move |r| {
+            r.cmr == cmr
+                && r.amount == amount
+                && r.asset.as_deref() == asset
+                && r.network == network
+        })
+    }
+}
+
+/// An exclusive claim on a registry file, released when dropped
+#[derive(Debug)]
+pub struct RegistryLock {
+    path: PathBuf,
+}
+
+impl Drop for RegistryLock {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
+
+/// How long a lease is held before other runs may take it over
+pub const DEFAULT_LEASE_TTL: Duration = Duration::from_secs(600);
+
+/// Contents of a lease file
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LeaseInfo {
+    /// Outpoint (`txid:vout`)
+    pub outpoint: String,
+    /// Identifier of the run holding the lease
+    pub run_id: String,
+    /// Unix timestamp at which the lease was acquired
+    pub acquired_at: u64,
+    /// Unix timestamp after which the lease is stale
+    pub expires_at: u64,
+}
+
+/// An exclusive claim on an outpoint, released when dropped
+#[derive(Debug)]
+pub struct Lease {
+    path: PathBuf,
+    info: LeaseInfo,
+}
+
+impl Lease {
+    /// Contents of the lease
+    #[must_use]
+    pub const fn info(&self) -> &LeaseInfo {
+        &self.info
+    }
+}
+
+impl Drop for Lease {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
+
+impl Registry {
+    /// Directory holding the lease files of this registry
+    #[must_use]
+    pub fn lease_dir(&self) -> PathBuf {
+        self.path.with_extension("leases")
+    }
+
+    /// Lease the outpoint `txid:vout` for `run_id` for `ttl`
+    ///
+    /// The lease is written in full to a temporary file and then hard-linked
+    /// into place, which fails if a lease exists, so at most one run holds a
+    /// given outpoint and no run ever reads a partly written lease. A lease
+    /// that has expired is taken over. One that cannot be read or parsed is
+    /// held until it is older than `ttl`, so a lease being replaced is not
+    /// mistaken for an abandoned one.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`SprayError::OutpointLocked`] if another run holds an
+    /// unexpired lease, or an IO error if the lease file cannot be written.
+    pub fn lease(
+        &self,
+        txid: &str,
+        vout: u32,
+        run_id: &str,
+        ttl: Duration,
+    ) -> Result<Lease, SprayError> {
+        let dir = self.lease_dir();
+        std::fs::create_dir_all(&dir)?;
+        let name = format!("{txid}_{vout}");
+        let path = dir.join(format!("{name}.json"));
+
+        let acquired_at = now();
+        let info = LeaseInfo {
+            outpoint: format!("{txid}:{vout}"),
+            run_id: run_id.to_string(),
+            acquired_at,
+            expires_at: acquired_at + ttl.as_secs(),
+        };
+
+        let temp = TempFile(dir.join(format!("{name}.{}.tmp", unique_suffix())));
+        {
+            let mut file = OpenOptions::new()
+                .write(true)
+                .create_new(true)
+                .open(&temp.0)?;
+            file.write_all(serde_json::to_string_pretty(&info)?.as_bytes())?;
+            file.sync_all()?;
+        }
+
+        loop {
+            match std::fs::hard_link(&temp.0, &path) {
+                Ok(()) => return Ok(Lease { path, info }),
+                Err(e) if e.kind() == ErrorKind::AlreadyExists => {}
+                Err(e) => return Err(e.into()),
+            }
+
+            let Some(contents) = read_if_exists(&path)? else {
+                continue;
+            };
+            match serde_json::from_slice::<LeaseInfo>(&contents) {
+                Ok(holder) if holder.expires_at > acquired_at => {
+                    return Err(SprayError::OutpointLocked(format!(
+                        "{} locked by run {} since {} (expires at {})",
+                        holder.outpoint, holder.run_id, holder.acquired_at, holder.expires_at
+                    )));
+                }
+                Ok(_) => {}
+                Err(_) => {
+                    let age = std::fs::metadata(&path)
+                        .and_then(|metadata| metadata.modified())
+                        .ok()
+                        .and_then(|modified| modified.elapsed().ok());
+                    if age.map_or(true, |age| age < ttl) {
+                        return Err(SprayError::OutpointLocked(format!(
+                            "{} locked by a run whose lease cannot be read ({})",
+                            info.outpoint,
+                            path.display()
+                        )));
+                    }
+                }
+            }
+            take_stale(&path, &contents, &dir)?;
+        }
+    }
+}
+
+/// A temporary file, removed when dropped
+struct TempFile(PathBuf);
+
+impl Drop for TempFile {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.0);
+    }
+}
+
+/// The contents of `path`, or `None` if it does not exist
+fn read_if_exists(path: &Path) -> Result<Option<Vec<u8>>, SprayError> {
+    match std::fs::read(path) {
+        Ok(contents) => Ok(Some(contents)),
+        Err(e) if e.kind() == ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Remove the stale lease at `path`, which held `stale`
+///
+/// The lease is first moved aside, so that if another run replaced it in
+/// the meantime, its fresh lease is put back instead of being deleted.
+fn take_stale(path: &Path, stale: &[u8], dir: &Path) -> Result<(), SprayError> {
+    let aside = TempFile(dir.join(format!("stale.{}.tmp", unique_suffix())));
+    match std::fs::rename(path, &aside.0) {
+        Ok(()) => {}
+        Err(e) if e.kind() == ErrorKind::NotFound => return Ok(()),
+        Err(e) => return Err(e.into()),
+    }
+    if read_if_exists(&aside.0)?.as_deref() != Some(stale) {
+        // Not the lease we judged stale: restore it unless yet another
+        // run has taken the outpoint since
+        match std::fs::hard_link(&aside.0, path) {
+            Err(e) if e.kind() != ErrorKind::AlreadyExists => return Err(e.into()),
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+/// The path of a file next to `path`, named after it with `suffix` added
+fn sibling(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(".");
+    name.push(suffix);
+    path.with_file_name(name)
+}
+
+/// A file name suffix unique to this process and call
+fn unique_suffix() -> String {
+    static COUNTER: AtomicU64 = AtomicU64::new(0);
+    format!(
+        "{}-{}",
+        std::process::id(),
+        COUNTER.fetch_add(1, Ordering::Relaxed)
+    )
+}
+
+/// Identifier of the current run, used as the holder of its leases
+///
+/// Taken from `SPRAY_RUN_ID` if set, otherwise derived from the process ID.
+#[must_use]
+pub fn run_id() -> String {
+    std::env::var("SPRAY_RUN_ID").unwrap_or_else(|_| format!("pid-{}", std::process::id()))
+}
+
+/// Absolute form of `path` for recording in the registry
+///
+/// Falls back to `path` itself if it cannot be resolved.
+#[must_use]
+pub fn record_path(path: &Path) -> String {
+    std::fs::canonicalize(path)
+        .unwrap_or_else(|_| path.to_path_buf())
+        .display()
+        .to_string()
+}
+
+/// Current Unix timestamp in seconds
+#[must_use]
+pub fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs())
+}
@@ -0,0 +1,385 @@
+//! Suite reports with timing data
+//!
+//! This module provides the [`SuiteReport`] type which records the outcome and
+//! duration of every test in a suite, and derives a duration histogram and a
+//! slowest-tests list from them.
+//!
+//! # Example
+//!
+//! ```
+//! use spray::report::{SuiteReport, TestRecord};
+//!
+//! let records = vec![
+//!     TestRecord::failure("slow", 4_000, "boom".into()),
+//!     TestRecord::failure("fast", 50, "boom".into()),
+//! ];
+//! let report = SuiteReport::new(records, 1);
+//!
+//! assert_eq!(report.slowest.len(), 1);
+//! assert_eq!(report.slowest[0].name, "slow");
+//! ```
+
+use crate::budget::Budget;
+use crate::coverage::Coverage;
+use crate::error::SprayError;
+use crate::test::{FailureStage, SpendDetails, TestResult};
+use colored::Colorize;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::Path;
+
+/// Upper bounds (exclusive, in milliseconds) of the histogram buckets
+///
+/// Durations at or above the last bound fall into a final open-ended bucket.
+pub const HISTOGRAM_BOUNDS_MS: [u64; 7] = [100, 500, 1_000, 2_000, 5_000, 10_000, 30_000];
+
+/// Default number of tests listed in the slowest-tests report
+pub const DEFAULT_SLOWEST_COUNT: usize = 5;
+
+/// Outcome and timing of a single test
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TestRecord {
+    /// Test name
+    pub name: String,
+    /// Whether the test passed
+    pub success: bool,
+    /// Wall-clock duration in milliseconds
+    pub duration_ms: u64,
+    /// Spending transaction ID, if the test passed
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub txid: Option<String>,
+    /// Error message, if the test failed
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    /// Stage at which the test failed, if known
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stage: Option<FailureStage>,
+    /// Confirmation target the fee was estimated for, if any
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub conf_target: Option<u16>,
+    /// Cost of the spend's program against its budget, if the test passed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub budget: Option<Budget>,
+    /// Number of failed attempts the test was re-run after
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub retries: usize,
+    /// Whether the test passed only after failing, see [`Self::retries`]
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub flaky: bool,
+}
+
+impl TestRecord {
+    /// Create a record from a test result
+    #[must_use]
+    pub fn from_result(name: &str, duration_ms: u64, result: &TestResult) -> Self {
+        match result {
+            TestResult::Success { txid, spend } => Self {
+                name: name.to_string(),
+                success: true,
+                duration_ms,
+                txid: Some(txid.to_string()),
+                error: None,
+                stage: None,
+                conf_target: None,
+                budget: spend.as_ref().and_then(SpendDetails::budget),
+                retries: 0,
+                flaky: false,
+            },
+            TestResult::Failure { error } => Self::failure(name, duration_ms, error.clone()),
+        }
+    }
+
+    /// Create a record for a failed test
+    #[must_use]
+    pub fn failure(name: &str, duration_ms: u64, error: String) -> Self {
+        Self {
+            name: name.to_string(),
+            success: false,
+            duration_ms,
+            txid: None,
+            error: Some(error),
+            stage: None,
+            conf_target: None,
+            budget: None,
+            retries: 0,
+            flaky: false,
+        }
+    }
+
+    /// Set the stage at which the test failed
+    #[must_use]
+    pub const fn with_stage(mut self, stage: FailureStage) -> Self {
+        self.stage = Some(stage);
+        self
+    }
+
+    /// Record that the test was re-run after `retries` failed attempts
+    ///
+    /// A passing test that needed retries is flaky: its failures came from
+    /// the environment, e.g. a slow node or a lost funding transaction,
+    /// rather than from the contract.
+    #[must_use]
+    pub const fn with_retries(mut self, retries: usize) -> Self {
+        self.retries = retries;
+        self.flaky = self.success && retries > 0;
+        self
+    }
+}
+
+/// Whether a count is zero, for skipping default fields when serializing
+#[allow(clippy::trivially_copy_pass_by_ref)]
+const fn is_zero(n: &usize) -> bool {
+    *n == 0
+}
+
+/// A single histogram bucket
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HistogramBucket {
+    /// Exclusive upper bound in milliseconds, `None` for the open-ended bucket
+    pub upper_ms: Option<u64>,
+    /// Number of tests whose duration falls into this bucket
+    pub count: usize,
+}
+
+impl HistogramBucket {
+    /// Human-readable label for the bucket, e.g. `"< 500ms"` or `">= 30s"`
+    #[must_use]
+    pub fn label(&self) -> String {
+        self.upper_ms.map_or_else(
+            || {
+                let last = HISTOGRAM_BOUNDS_MS[HISTOGRAM_BOUNDS_MS.len() - 1];
+                format!(">= {}", format_ms(last))
+            },
+            |upper| format!("< {}", format_ms(upper)),
+        )
+    }
+}
+
+/// Report for a complete test suite
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SuiteReport {
+    /// All test records, in execution order
+    pub tests: Vec<TestRecord>,
+    /// Sum of all test durations in milliseconds
+    pub total_duration_ms: u64,
+    /// The slowest tests, in descending order of duration
+    pub slowest: Vec<TestRecord>,
+    /// Duration histogram
+    pub histogram: Vec<HistogramBucket>,
+    /// Number of failures per stage
+    #[serde(default)]
+    pub failures_by_stage: BTreeMap<FailureStage, usize>,
+    /// Branch coverage of each program, when measured
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub coverage: Vec<Coverage>,
+}
+
+impl SuiteReport {
+    /// Build a report from test records, keeping the `slowest_count` slowest tests
+    #[must_use]
+    pub fn new(tests: Vec<TestRecord>, slowest_count: usize) -> Self {
+        let total_duration_ms = tests.iter().map(|t| t.duration_ms).sum();
+
+        let mut slowest = tests.clone();
+        slowest.sort_by(|a, b| b.duration_ms.cmp(&a.duration_ms));
+        slowest.truncate(slowest_count);
+
+        let histogram = histogram(&tests);
+
+        let mut failures_by_stage = BTreeMap::new();
+        for stage in tests.iter().filter_map(|t| t.stage) {
+            *failures_by_stage.entry(stage).or_insert(0) += 1;
+        }
+
+        Self {
+            tests,
+            total_duration_ms,
+            slowest,
+            histogram,
+            failures_by_stage,
+            coverage: Vec::new(),
+        }
+    }
+
+    /// Number of passed tests
+    #[must_use]
+    pub fn passed(&self) -> usize {
+        self.tests.iter().filter(|t| t.success).count()
+    }
+
+    /// Number of failed tests
+    #[must_use]
+    pub fn failed(&self) -> usize {
+        self.tests.iter().filter(|t| !t.success).count()
+    }
+
+    /// Number of tests that passed only on a retry
+    #[must_use]
+    pub fn flaky(&self) -> usize {
+        self.tests.iter().filter(|t| t.flaky).count()
+    }
+
+    /// Print the tests that passed only on a retry
+    pub fn print_flaky(&self) {
+        if self.flaky() == 0 {
+            return;
+        }
+
+        crate::status!();
+        crate::status!("{}", "Flaky tests (passed on retry):".bold());
+        for record in self.tests.iter().filter(|t| t.flaky) {
+            crate::status!(
+                "  {}  {}",
+                record.name,
+                format!("{} failed attempts", record.retries).yellow()
+            );
+        }
+    }
+
+    /// Print failure counts grouped by stage
+    pub fn print_failure_stages(&self) {
+        if self.failures_by_stage.is_empty() {
+            return;
+        }
+
+        crate::status!();
+        crate::status!("{}", "Failures by stage:".bold());
+        for (stage, count) in &self.failures_by_stage {
+            crate::status!("  {:>14}  {}", stage.to_string(), count.to_string().red());
+        }
+    }
+
+    /// Print the slowest tests and the duration histogram
+    pub fn print_timing(&self) {
+        if self.tests.is_empty() {
+            return;
+        }
+
+        crate::status!();
+        crate::status!("{}", "Slowest tests:".bold());
+        for record in &self.slowest {
+            crate::status!(
+                "  {:>8}  {}",
+                format_ms(record.duration_ms).yellow(),
+                record.name
+            );
+        }
+
+        crate::status!();
+        crate::status!("{}", "Duration histogram:".bold());
+        let max = self.histogram.iter().map(|b| b.count).max().unwrap_or(0);
+        for bucket in &self.histogram {
+            let width = if max == 0 { 0 } else { bucket.count * 40 / max };
+            crate::status!(
+                "  {:>8}  {} {}",
+                bucket.label(),
+                "█".repeat(width).cyan(),
+                bucket.count
+            );
+        }
+    }
+
+    /// Render the report as a TAP stream, see [`tap`]
+    #[must_use]
+    pub fn to_tap(&self) -> String {
+        tap(&self.tests)
+    }
+
+    /// Write the report as pretty-printed JSON
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if serialization or writing the file fails.
+    pub fn write_json(&self, path: &Path) -> Result<(), SprayError> {
+        let json = serde_json::to_string_pretty(self)?;
+        std::fs::write(path, json)?;
+        Ok(())
+    }
+}
+
+/// Render test records as a TAP (Test Anything Protocol) version 13 stream
+///
+/// Each test is a numbered `ok` or `not ok` line, followed by diagnostic
+/// lines with its txid or failure stage and error, and its duration.
+///
+/// # Example
+///
+/// ```
+/// use spray::report::{tap, TestRecord};
+///
+/// let record = TestRecord::failure("spend", 12, "Assertion failed".into());
+/// assert_eq!(
+///     tap(&[record]),
+///     "TAP version 13\n1..1\nnot ok 1 - spend\n# error: Assertion failed\n# duration: 12ms\n"
+/// );
+/// ```
+#[must_use]
+pub fn tap(tests: &[TestRecord]) -> String {
+    let mut out = format!("TAP version 13\n1..{}\n", tests.len());
+    for (i, record) in tests.iter().enumerate() {
+        let status = if record.success { "ok" } else { "not ok" };
+        // `#` would start a TAP directive
+        let name = record.name.replace('#', "\\#");
+        out.push_str(&format!("{status} {} - {name}\n", i + 1));
+
+        if let Some(txid) = &record.txid {
+            out.push_str(&format!("# txid: {txid}\n"));
+        }
+        if let Some(stage) = record.stage {
+            out.push_str(&format!("# stage: {stage}\n"));
+        }
+        if record.flaky {
+            out.push_str(&format!(
+                "# flaky: passed after {} retries\n",
+                record.retries
+            ));
+        }
+        if let Some(error) = &record.error {
+            let mut lines = error.lines();
+            out.push_str(&format!("# error: {}\n", lines.next().unwrap_or_default()));
+            for line in lines {
+                out.push_str(&format!("#   {line}\n"));
+            }
+        }
+        out.push_str(&format!("# duration: {}\n", format_ms(record.duration_ms)));
+    }
+    out
+}
+
+/// Count test durations into the fixed histogram buckets
+#[must_use]
+pub fn histogram(tests: &[TestRecord]) -> Vec<HistogramBucket> {
+    let mut buckets: Vec<HistogramBucket> = HISTOGRAM_BOUNDS_MS
+        .iter()
+        .map(|&upper| HistogramBucket {
+            upper_ms: Some(upper),
+            count: 0,
+        })
+        .chain(std::iter::once(HistogramBucket {
+            upper_ms: None,
+            count: 0,
+        }))
+        .collect();
+
+    for test in tests {
+        let index = HISTOGRAM_BOUNDS_MS
+            .iter()
+            .position(|&upper| test.duration_ms < upper)
+            .unwrap_or(HISTOGRAM_BOUNDS_MS.len());
+        buckets[index].count += 1;
+    }
+
+    buckets
+}
+
+/// Format a millisecond duration compactly (`"250ms"`, `"1.5s"`)
+#[must_use]
+pub fn format_ms(ms: u64) -> String {
+    if ms < 1_000 {
+        format!("{ms}ms")
+    } else if ms % 1_000 == 0 {
+        format!("{}s", ms / 1_000)
+    } else {
+        format!("{}.{}s", ms / 1_000, (ms % 1_000) / 100)
+    }
+}
@@ -0,0 +1,116 @@
+//! Wallet imports with bounded rescans
+//!
+//! Importing a contract address into an external node's wallet only finds
+//! its UTXOs after a rescan. Skipping the rescan misses existing UTXOs, and
+//! a full rescan of a long chain takes hours. This module imports contract
+//! addresses without a rescan and then rescans only from the earliest
+//! deployment height recorded in the registry, in chunks so that progress can
+//! be reported.
+//!
+//! # Example
+//!
+//! ```
+//! use spray::rescan::chunks;
+//!
+//! assert_eq!(chunks(100, 250, 100), vec![(100, 199), (200, 250)]);
+//! ```
+
+use crate::error::SprayError;
+use crate::network::NetworkBackend;
+use crate::registry::DeploymentRecord;
+use musk::elements::Address;
+use serde_json::json;
+
+/// Default number of blocks rescanned per `rescanblockchain` call
+pub const DEFAULT_CHUNK_SIZE: u64 = 1_000;
+
+/// Height to start rescanning from for a contract
+///
+/// Returns the lowest recorded deployment height among the records for `cmr`
+/// on `network`, or `None` if there are no such records or any of them has
+/// no recorded height, in which case the whole chain must be rescanned.
+#[must_use]
+pub fn rescan_start(records: &[DeploymentRecord], cmr: &str, network: &str) -> Option<u64> {
+    records
+        .iter()
+        .filter(|r| r.cmr == cmr && r.network == network)
+        .map(|r| r.height)
+        .collect::<Option<Vec<u64>>>()?
+        .into_iter()
+        .min()
+}
+
+/// Split the inclusive height range `start..=stop` into chunks of `size`
+#[must_use]
+pub fn chunks(start: u64, stop: u64, size: u64) -> Vec<(u64, u64)> {
+    let size = size.max(1);
+    (start..=stop)
+        .step_by(usize::try_from(size).unwrap_or(usize::MAX))
+        .map(|from| (from, from.saturating_add(size - 1).min(stop)))
+        .collect()
+}
+
+/// Import `address` into the node's wallet as watch-only, without a rescan
+///
+/// Descriptor wallets import an `addr()` descriptor; legacy wallets use
+/// `importaddress`.
+///
+/// # Errors
+///
+/// Returns an error if the wallet rejects the import.
+pub fn import_address(
+    backend: &NetworkBackend,
+    address: &Address,
+    label: &str,
+) -> Result<(), SprayError> {
+    let descriptors = backend.call("getwalletinfo", &[])?["descriptors"]
+        .as_bool()
+        .unwrap_or(false);
+
+    if descriptors {
+        let info = backend.call("getdescriptorinfo", &[format!("addr({address})").into()])?;
+        let descriptor = info["descriptor"]
+            .as_str()
+            .ok_or_else(|| SprayError::RpcError("Invalid getdescriptorinfo response".into()))?;
+        let imported = backend.call(
+            "importdescriptors",
+            &[json!([{ "desc": descriptor, "timestamp": "now", "label": label }])],
+        )?;
+        if imported[0]["success"].as_bool() != Some(true) {
+            return Err(SprayError::RpcError(format!(
+                "Failed to import {address}: {}",
+                imported[0]["error"]["message"]
+            )));
+        }
+    } else {
+        // importaddress address label rescan
+        backend.call(
+            "importaddress",
+            &[address.to_string().into(), label.into(), false.into()],
+        )?;
+    }
+
+    Ok(())
+}
+
+/// Rescan the wallet from `start` to the chain tip in chunks of `chunk_size`
+///
+/// `progress` is called after each chunk with the last rescanned height and
+/// the tip height.
+///
+/// # Errors
+///
+/// Returns an error if a `rescanblockchain` call fails.
+pub fn rescan(
+    backend: &NetworkBackend,
+    start: u64,
+    chunk_size: u64,
+    mut progress: impl FnMut(u64, u64),
+) -> Result<(), SprayError> {
+    let tip = backend.block_count()?;
+    for (from, to) in chunks(start, tip, chunk_size) {
+        backend.call("rescanblockchain", &[from.into(), to.into()])?;
+        progress(to, tip);
+    }
+    Ok(())
+}
@@ -0,0 +1,221 @@
+//! Retry with exponential backoff
+//!
+//! A spend broadcast to an external node can race its funding transaction:
+//! if the funding transaction has not yet propagated to (or confirmed on)
+//! that node, the spend is rejected for missing inputs. [`Backoff`] describes
+//! how often and how long to wait before retrying such transient failures.
+//!
+//! External nodes also time out or answer with transient server errors now
+//! and then. RPC calls to them are retried by [`retry_transient`] with the
+//! backoff of their [`BackendConfig`](crate::network::BackendConfig),
+//! [`DEFAULT_RPC_BACKOFF`] unless set otherwise, but only calls that are
+//! safe to repeat
+//! ([`is_idempotent`]): read-only methods are retried, a
+//! `sendrawtransaction` that timed out may have reached the node, so it is
+//! only sent again after checking that the node does not know the
+//! transaction, and every other method is never retried.
+//!
+//! # Example
+//!
+//! ```
+//! use spray::retry::{is_missing_inputs, Backoff};
+//! use std::time::Duration;
+//!
+//! let backoff = Backoff::new(4, Duration::from_secs(2));
+//! assert_eq!(backoff.delay(0), Duration::from_secs(2));
+//! assert_eq!(backoff.delay(2), Duration::from_secs(8));
+//!
+//! assert!(is_missing_inputs("bad-txns-inputs-missingorspent"));
+//! assert!(!is_missing_inputs("non-mandatory-script-verify-flag"));
+//! ```
+//!
+//! Retrying a transient RPC failure:
+//!
+//! ```
+//! use spray::retry::{is_idempotent, retry_transient, Backoff};
+//! use std::cell::Cell;
+//! use std::time::Duration;
+//!
+//! let attempts = Cell::new(0);
+//! let result = retry_transient(
+//!     Backoff::new(3, Duration::ZERO),
+//!     || {
+//!         attempts.set(attempts.get() + 1);
+//!         if attempts.get() < 3 { Err("operation timed out") } else { Ok(42) }
+//!     },
+//!     || None,
+//! );
+//! assert_eq!((result, attempts.get()), (Ok(42), 3));
+//!
+//! assert!(is_idempotent("getblockcount"));
+//! assert!(!is_idempotent("sendtoaddress"));
+//! assert!(!is_idempotent("someunknownmethod"));
+//! ```
+
+use std::fmt::Display;
+use std::time::Duration;
+
+/// Default number of retries after the first attempt
+pub const DEFAULT_RETRIES: u32 = 5;
+
+/// Default delay before the first retry
+pub const DEFAULT_INITIAL_DELAY: Duration = Duration::from_secs(2);
+
+/// Upper bound on the delay between two attempts
+pub const MAX_DELAY: Duration = Duration::from_secs(60);
+
+/// Default number of retries of a transient RPC failure
+pub const DEFAULT_RPC_RETRIES: u32 = 3;
+
+/// Default delay before the first retry of a transient RPC failure, in
+/// milliseconds
+pub const DEFAULT_RPC_INITIAL_DELAY_MS: u64 = 500;
+
+/// Default delay before the first retry of a transient RPC failure
+pub const DEFAULT_RPC_INITIAL_DELAY: Duration = Duration::from_millis(DEFAULT_RPC_INITIAL_DELAY_MS);
+
+/// Prefixes of RPC methods that only read state and can be repeated
+pub const READ_ONLY_PREFIXES: &[&str] = &["get", "list", "decode"];
+
+/// RPC methods that only read state but have no read-only prefix
+pub const READ_ONLY_METHODS: &[&str] = &[
+    "estimatesmartfee",
+    "scantxoutset",
+    "testmempoolaccept",
+    "deriveaddresses",
+    "validateaddress",
+    "uptime",
+    "ping",
+    "help",
+];
+
+/// RPC methods with a read-only prefix that change the wallet, handing out
+/// a new address on every call
+const WALLET_GETTERS: &[&str] = &["getnewaddress", "getrawchangeaddress"];
+
+/// Default backoff of transient RPC failures of external nodes
+pub const DEFAULT_RPC_BACKOFF: Backoff =
+    Backoff::new(DEFAULT_RPC_RETRIES, DEFAULT_RPC_INITIAL_DELAY);
+
+/// How many times to retry and how long to wait in between
+///
+/// The delay doubles after every retry, up to [`MAX_DELAY`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Backoff {
+    /// Number of retries after the first attempt
+    pub retries: u32,
+    /// Delay before the first retry
+    pub initial_delay: Duration,
+}
+
+impl Default for Backoff {
+    fn default() -> Self {
+        Self::new(DEFAULT_RETRIES, DEFAULT_INITIAL_DELAY)
+    }
+}
+
+impl Backoff {
+    /// Create a backoff with `retries` retries, starting at `initial_delay`
+    #[must_use]
+    pub const fn new(retries: u32, initial_delay: Duration) -> Self {
+        Self {
+            retries,
+            initial_delay,
+        }
+    }
+
+    /// A backoff that never retries
+    #[must_use]
+    pub const fn none() -> Self {
+        Self::new(0, Duration::ZERO)
+    }
+
+    /// Delay before retry number `attempt` (starting at 0)
+    #[must_use]
+    pub fn delay(&self, attempt: u32) -> Duration {
+        self.initial_delay
+            .saturating_mul(2u32.saturating_pow(attempt))
+            .min(MAX_DELAY)
+    }
+}
+
+/// Whether a broadcast error means an input is not (yet) known to the node
+///
+/// Nodes report this as `missing-inputs` or
+/// `bad-txns-inputs-missingorspent`.
+#[must_use]
+pub fn is_missing_inputs(error: &str) -> bool {
+    error.contains("missing-inputs") || error.contains("missingorspent")
+}
+
+/// Whether an RPC error is likely to go away on its own: a timeout, a
+/// dropped connection, a 5xx response, or a node that is still starting
+#[must_use]
+pub fn is_transient(error: &str) -> bool {
+    const TRANSIENT: &[&str] = &[
+        "timed out",
+        "timeout",
+        "connection reset",
+        "connection refused",
+        "connection closed",
+        "broken pipe",
+        "internal server error",
+        "bad gateway",
+        "service unavailable",
+        "http error 5",
+        "status code 5",
+        "work queue depth exceeded",
+        "loading block index",
+        "verifying blocks",
+        "rpc in warm-up",
+    ];
+    let error = error.to_lowercase();
+    TRANSIENT.iter().any(|pattern| error.contains(pattern))
+}
+
+/// Whether an RPC method can be repeated without changing the outcome
+///
+/// Only read-only methods are: those starting with one of
+/// [`READ_ONLY_PREFIXES`] and those in [`READ_ONLY_METHODS`]. Any other
+/// method, including ones spray does not know, is not repeated.
+#[must_use]
+pub fn is_idempotent(method: &str) -> bool {
+    READ_ONLY_METHODS.contains(&method)
+        || (READ_ONLY_PREFIXES
+            .iter()
+            .any(|prefix| method.starts_with(prefix))
+            && !WALLET_GETTERS.contains(&method))
+}
+
+/// Make a call, retrying [transient](is_transient) failures by `backoff`
+///
+/// Before each retry, `recover` is asked whether the failed attempt took
+/// effect after all, e.g. whether a broadcast that timed out reached the
+/// mempool; if it returns a value, that value is the result.
+///
+/// # Errors
+///
+/// Returns the last error once it is not transient or the retries are
+/// exhausted.
+pub fn retry_transient<T, E: Display>(
+    backoff: Backoff,
+    mut call: impl FnMut() -> Result<T, E>,
+    mut recover: impl FnMut() -> Option<T>,
+) -> Result<T, E> {
+    let mut attempt = 0;
+    loop {
+        match call() {
+            Ok(value) => return Ok(value),
+            Err(error) if attempt < backoff.retries && is_transient(&error.to_string()) => {
+                let delay = backoff.delay(attempt);
+                attempt += 1;
+                tracing::debug!(attempt, ?delay, %error, "retrying RPC call");
+                std::thread::sleep(delay);
+                if let Some(value) = recover() {
+                    return Ok(value);
+                }
+            }
+            Err(error) => return Err(error),
+        }
+    }
+}
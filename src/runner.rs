@@ -2,12 +2,36 @@
 
 use crate::env::TestEnv;
 use crate::error::SprayError;
-use crate::test::{TestCase, TestResult};
+use crate::report::{SuiteReport, TestRecord, DEFAULT_SLOWEST_COUNT};
+use crate::test::{FailureStage, SpendDetails, TestCase, TestResult};
 use colored::Colorize;
+use std::time::Instant;
+
+/// A setup or teardown hook, run against the runner's environment
+pub type Hook = Box<dyn Fn(&TestEnv) -> Result<(), SprayError>>;
 
 /// Test runner for executing multiple test cases
+///
+/// Hooks let a suite prepare the environment, e.g. mine blocks, issue assets
+/// or fund helper wallets: `before_all` and `after_all` hooks bracket each
+/// [`run_suite`](Self::run_suite), and `before_each` and `after_each` hooks
+/// run around every test. Hooks of the same kind run in the order they were
+/// added. A failing hook fails the tests it surrounds at
+/// [`FailureStage::Hook`].
+///
+/// With [`retries`](Self::retries), a test failing at a
+/// [retryable](FailureStage::is_retryable) stage is re-funded and re-run;
+/// a test that passes on a retry is reported as flaky, so that hiccups of
+/// the node stand out from contract failures, which fail every attempt.
 pub struct TestRunner {
     env: TestEnv,
+    slowest_count: usize,
+    retries: usize,
+    log_tail: usize,
+    before_all: Vec<Hook>,
+    before_each: Vec<Hook>,
+    after_each: Vec<Hook>,
+    after_all: Vec<Hook>,
 }
 
 impl TestRunner {
@@ -17,8 +41,110 @@ impl TestRunner {
     ///
     /// Returns an error if the test environment fails to initialize.
     pub fn new() -> Result<Self, SprayError> {
-        let env = TestEnv::new()?;
-        Ok(Self { env })
+        Ok(Self::with_env(TestEnv::new()?))
+    }
+
+    /// Create a test runner on an existing environment, e.g. one configured
+    /// with a [`TestEnvBuilder`](crate::env::TestEnvBuilder)
+    #[must_use]
+    pub fn with_env(env: TestEnv) -> Self {
+        Self {
+            env,
+            slowest_count: DEFAULT_SLOWEST_COUNT,
+            retries: 0,
+            log_tail: 0,
+            before_all: Vec::new(),
+            before_each: Vec::new(),
+            after_each: Vec::new(),
+            after_all: Vec::new(),
+        }
+    }
+
+    /// Add a hook run once before the tests of each suite
+    ///
+    /// If it fails, the suite's tests are not run and are reported as
+    /// failed.
+    #[must_use]
+    pub fn before_all<F>(mut self, hook: F) -> Self
+    where
+        F: Fn(&TestEnv) -> Result<(), SprayError> + 'static,
+    {
+        self.before_all.push(Box::new(hook));
+        self
+    }
+
+    /// Add a hook run before every test
+    ///
+    /// If it fails, the test is not run and is reported as failed.
+    #[must_use]
+    pub fn before_each<F>(mut self, hook: F) -> Self
+    where
+        F: Fn(&TestEnv) -> Result<(), SprayError> + 'static,
+    {
+        self.before_each.push(Box::new(hook));
+        self
+    }
+
+    /// Add a hook run after every test, whether it passed or not
+    ///
+    /// If it fails, a passing test is reported as failed.
+    #[must_use]
+    pub fn after_each<F>(mut self, hook: F) -> Self
+    where
+        F: Fn(&TestEnv) -> Result<(), SprayError> + 'static,
+    {
+        self.after_each.push(Box::new(hook));
+        self
+    }
+
+    /// Add a hook run once after the tests of each suite
+    ///
+    /// Failures are printed; the tests' results are kept.
+    #[must_use]
+    pub fn after_all<F>(mut self, hook: F) -> Self
+    where
+        F: Fn(&TestEnv) -> Result<(), SprayError> + 'static,
+    {
+        self.after_all.push(Box::new(hook));
+        self
+    }
+
+    /// Run `hooks` in order, stopping at the first failure
+    fn run_hooks(&self, hooks: &[Hook], kind: &str) -> Result<(), String> {
+        hooks
+            .iter()
+            .try_for_each(|hook| hook(&self.env))
+            .map_err(|e| format!("{kind} hook failed: {e}"))
+    }
+
+    /// Set how many of the slowest tests are listed in suite reports
+    #[must_use]
+    pub const fn slowest_count(mut self, count: usize) -> Self {
+        self.slowest_count = count;
+        self
+    }
+
+    /// Set how often a failing test is re-funded and re-run
+    ///
+    /// Only failures at a [retryable](FailureStage::is_retryable) stage are
+    /// retried. Tests can override this with
+    /// [`TestCase::retries`](crate::test::TestCase::retries). Hooks run once
+    /// around all attempts of a test.
+    #[must_use]
+    pub const fn retries(mut self, retries: usize) -> Self {
+        self.retries = retries;
+        self
+    }
+
+    /// Attach the last `lines` lines of the daemon's `debug.log` to funding,
+    /// broadcast and timeout failures, or nothing for 0, the default
+    ///
+    /// Node-side rejection reasons, e.g. of a Simplicity program that failed
+    /// to execute, are often only logged.
+    #[must_use]
+    pub const fn log_tail(mut self, lines: usize) -> Self {
+        self.log_tail = lines;
+        self
     }
 
     /// Get a reference to the test environment
@@ -28,75 +154,191 @@ impl TestRunner {
     }
 
     /// Run a single test case
-    pub fn run_test(&self, mut test: TestCase<'_>) -> TestResult {
-        let test_name = test.name.clone();
-        println!("{} {}", "⏳".yellow(), test_name.bold());
+    pub fn run_test(&self, test: TestCase<'_>) -> TestResult {
+        self.execute(test).0
+    }
 
-        // Create UTXO
-        if let Err(e) = test.create_utxo() {
-            let error = format!("Failed to create UTXO: {e}");
-            println!("{} {}: {}", "❌".red(), test_name.bold(), error.red());
-            return TestResult::Failure { error };
-        }
+    /// Run a single test case with its hooks, returning the stage at which
+    /// it failed (if any) and the number of failed attempts it was re-run
+    /// after
+    fn execute(&self, mut test: TestCase<'_>) -> (TestResult, Option<FailureStage>, usize) {
+        let test_name = test.name.clone();
+        let span = tracing::info_span!("test", name = %test_name);
+        let _span = span.enter();
+        crate::status!("{} {}", "⏳".yellow(), test_name.bold());
 
-        // Generate blocks to confirm the funding transaction
-        if let Err(e) = self.env.generate(1) {
-            let error = format!("Failed to generate blocks: {e}");
-            println!("{} {}: {}", "❌".red(), test_name.bold(), error.red());
-            return TestResult::Failure { error };
+        if let Err(error) = self.run_hooks(&self.before_each, "before_each") {
+            let (result, stage) = report_failure(&test_name, FailureStage::Hook, error);
+            return (result, stage, 0);
         }
 
-        // Run the test
-        match test.run() {
-            Ok(TestResult::Success { txid }) => {
-                println!("{} {} (txid: {txid})", "✅".green(), test_name.bold());
-                TestResult::Success { txid }
+        let retries = test.retry_limit().unwrap_or(self.retries);
+        let mut attempt = 0;
+        let (result, stage) = loop {
+            match self.execute_test(&mut test) {
+                Err((stage, error)) if attempt < retries && stage.refine(&error).is_retryable() => {
+                    attempt += 1;
+                    tracing::warn!(%stage, %error, attempt, "test failed, retrying");
+                    crate::status!(
+                        "{} {} [{}]: {} {}",
+                        "↻".yellow(),
+                        test_name.bold(),
+                        stage.refine(&error),
+                        error.yellow(),
+                        format!("(retry {attempt}/{retries})").dimmed()
+                    );
+                }
+                Err((stage, error)) => break report_failure(&test_name, stage, error),
+                Ok(result) => {
+                    let flaky = if attempt > 0 {
+                        format!(" {}", "flaky".yellow().bold())
+                    } else {
+                        String::new()
+                    };
+                    if let TestResult::Success { txid, .. } = &result {
+                        tracing::info!(%txid, retries = attempt, "test passed");
+                        crate::status!(
+                            "{} {} (txid: {txid}){flaky}",
+                            "✅".green(),
+                            test_name.bold()
+                        );
+                    }
+                    if let Some(budget) = result.spend().and_then(SpendDetails::budget) {
+                        crate::status!("   {} {budget}", "Cost:".dimmed());
+                    }
+                    break (result, None);
+                }
             }
-            Ok(TestResult::Failure { error }) => {
-                println!("{} {}: {}", "❌".red(), test_name.bold(), error.red());
-                TestResult::Failure { error }
+        };
+
+        match (self.run_hooks(&self.after_each, "after_each"), &result) {
+            (Err(error), TestResult::Success { .. }) => {
+                let (result, stage) = report_failure(&test_name, FailureStage::Hook, error);
+                (result, stage, attempt)
             }
-            Err(e) => {
-                let error = e.to_string();
-                println!("{} {}: {}", "❌".red(), test_name.bold(), error.red());
-                TestResult::Failure { error }
+            _ => (result, stage, attempt),
+        }
+    }
+
+    /// Fund and spend a single test case once
+    fn execute_test(&self, test: &mut TestCase<'_>) -> Result<TestResult, (FailureStage, String)> {
+        self.attempt_test(test).map_err(|(stage, error)| {
+            if self.log_tail == 0 || !stage.is_node_side() {
+                return (stage, error);
             }
+            (stage, error + &self.env.log_tail(self.log_tail))
+        })
+    }
+
+    /// Fund and spend a test once
+    fn attempt_test(&self, test: &mut TestCase<'_>) -> Result<TestResult, (FailureStage, String)> {
+        // Create UTXO
+        test.create_utxo()
+            .map_err(|e| (FailureStage::Funding, format!("Failed to create UTXO: {e}")))?;
+
+        // Generate blocks to confirm the funding transaction, unless it is
+        // submitted together with the spend
+        if !test.is_package() {
+            self.env.generate(1).map_err(|e| {
+                (
+                    FailureStage::Funding,
+                    format!("Failed to generate blocks: {e}"),
+                )
+            })?;
         }
+
+        // Run the test
+        match test.spend() {
+            Ok(result @ TestResult::Success { .. }) => Ok(result),
+            Ok(TestResult::Failure { error }) => Err((FailureStage::Satisfaction, error)),
+            Err((stage, e)) => Err((stage, e.to_string())),
+        }
+    }
+
+    /// Run a single test case and record its duration
+    pub fn run_timed(&self, test: TestCase<'_>) -> (TestResult, TestRecord) {
+        let name = test.name.clone();
+        let start = Instant::now();
+        let (result, stage, retries) = self.execute(test);
+        #[allow(clippy::cast_possible_truncation)]
+        let duration_ms = start.elapsed().as_millis() as u64;
+        let mut record = TestRecord::from_result(&name, duration_ms, &result).with_retries(retries);
+        record.stage = stage;
+        (result, record)
     }
 
     /// Run multiple test cases
     pub fn run_tests(&self, tests: Vec<TestCase<'_>>) -> Vec<TestResult> {
+        self.run_suite(tests).0
+    }
+
+    /// Run multiple test cases and build a [`SuiteReport`] with timing data
+    pub fn run_suite(&self, tests: Vec<TestCase<'_>>) -> (Vec<TestResult>, SuiteReport) {
         let mut results = Vec::new();
+        let mut records = Vec::new();
 
-        println!("\n{}", "Running tests...".bold().cyan());
-        println!("{}", "─".repeat(60).dimmed());
+        crate::status!("\n{}", "Running tests...".bold().cyan());
+        crate::status!("{}", "─".repeat(60).dimmed());
 
-        for test in tests {
-            results.push(self.run_test(test));
+        match self.run_hooks(&self.before_all, "before_all") {
+            Ok(()) => {
+                for test in tests {
+                    let (result, record) = self.run_timed(test);
+                    results.push(result);
+                    records.push(record);
+                }
+            }
+            Err(error) => {
+                crate::status!("{} {}", "❌".red(), error.red());
+                for test in tests {
+                    records.push(
+                        TestRecord::failure(&test.name, 0, error.clone())
+                            .with_stage(FailureStage::Hook),
+                    );
+                    results.push(TestResult::Failure {
+                        error: error.clone(),
+                    });
+                }
+            }
+        }
+
+        if let Err(error) = self.run_hooks(&self.after_all, "after_all") {
+            crate::status!("{} {}", "⚠".yellow().bold(), error.yellow());
         }
 
-        println!("{}", "─".repeat(60).dimmed());
+        crate::status!("{}", "─".repeat(60).dimmed());
 
         // Summary
         let success_count = results.iter().filter(|r| r.is_success()).count();
         let failure_count = results.iter().filter(|r| r.is_failure()).count();
+        let flaky_count = records.iter().filter(|r| r.flaky).count();
+        let flaky = if flaky_count > 0 {
+            format!(" ({} flaky)", flaky_count.to_string().yellow())
+        } else {
+            String::new()
+        };
 
         if failure_count == 0 {
-            println!(
-                "\n{} {} tests passed",
+            crate::status!(
+                "\n{} {} tests passed{flaky}",
                 "✓".green().bold(),
                 success_count.to_string().green().bold()
             );
         } else {
-            println!(
-                "\n{} {} passed, {} failed",
+            crate::status!(
+                "\n{} {} passed{flaky}, {} failed",
                 "⚠".yellow().bold(),
                 success_count.to_string().green(),
                 failure_count.to_string().red().bold()
             );
         }
 
-        results
+        let report = SuiteReport::new(records, self.slowest_count);
+        report.print_flaky();
+        report.print_failure_stages();
+        report.print_timing();
+
+        (results, report)
     }
 
     /// Generate blocks for lock time testing
@@ -109,6 +351,23 @@ impl TestRunner {
     }
 }
 
+/// Print a test failure and build its outcome
+fn report_failure(
+    test_name: &str,
+    stage: FailureStage,
+    error: String,
+) -> (TestResult, Option<FailureStage>) {
+    let stage = stage.refine(&error);
+    tracing::warn!(%stage, %error, "test failed");
+    crate::status!(
+        "{} {} [{stage}]: {}",
+        "❌".red(),
+        test_name.bold(),
+        error.red()
+    );
+    (TestResult::Failure { error }, Some(stage))
+}
+
 impl Default for TestRunner {
     fn default() -> Self {
         Self::new().expect("Failed to create test runner")
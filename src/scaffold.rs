@@ -0,0 +1,154 @@
+//! Argument and witness file templates
+//!
+//! A program's arguments and witness files must name every parameter and
+//! witness it declares, with values of the declared types. `spray scaffold
+//! <file.simf>` reads the declarations from the program and writes
+//! [`ARGS_TEMPLATE`] and [`WITNESS_TEMPLATE`] with every key in place:
+//!
+//! ```text
+//! {
+//!   "TIMEOUT": {
+//!     "value": "0",
+//!     "type": "u32"
+//!   }
+//! }
+//! ```
+//!
+//! Values are placeholders of the declared type (see
+//! [`ValueType::placeholder`]), to be replaced before use. Types are given
+//! as the compiler resolves them, so aliases such as `Height` appear as the
+//! type they stand for.
+//!
+//! # Example
+//!
+//! ```
+//! use spray::scaffold::Scaffold;
+//!
+//! let source = "fn main() {\n    assert!(jet::eq_32(witness::X, param::LIMIT));\n}\n";
+//! let scaffold = Scaffold::from_source(source).unwrap();
+//! assert_eq!(scaffold.parameters["LIMIT"], "u32");
+//! assert_eq!(scaffold.witnesses["X"], "u32");
+//! assert_eq!(scaffold.witness_template()["X"]["value"], "0");
+//! ```
+
+use crate::error::SprayError;
+use crate::include;
+use crate::value::ValueType;
+use serde_json::{json, Map, Value};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+/// File name of the arguments template
+pub const ARGS_TEMPLATE: &str = "args.template.json";
+
+/// File name of the witness template
+pub const WITNESS_TEMPLATE: &str = "witness.template.json";
+
+/// The parameters and witnesses a program declares
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Scaffold {
+    /// Type of each parameter, by name
+    pub parameters: BTreeMap<String, String>,
+    /// Type of each witness, by name
+    pub witnesses: BTreeMap<String, String>,
+}
+
+impl Scaffold {
+    /// Read the declarations of the program in `path`, with its includes
+    ///
+    /// # Errors
+    ///
+    /// Returns the errors of [`include::expand`] and [`Scaffold::from_source`].
+    pub fn load(path: &Path) -> Result<Self, SprayError> {
+        Self::from_source(&include::expand(path)?)
+    }
+
+    /// Read the declarations of a SimplicityHL program
+    ///
+    /// The witness declarations are read from the program instantiated with
+    /// placeholder arguments.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the program does not compile, or does not
+    /// instantiate with the placeholder arguments.
+    pub fn from_source(source: &str) -> Result<Self, SprayError> {
+        let template = simplicityhl::TemplateProgram::new(source)
+            .map_err(|e| SprayError::ParseError(format!("Failed to compile program: {e}")))?;
+        let parameters: BTreeMap<String, String> = template
+            .parameters()
+            .iter()
+            .map(|(name, ty)| (name.to_string(), ty.to_string()))
+            .collect();
+
+        let arguments: musk::Arguments = serde_json::from_value(entries(&parameters))?;
+        let compiled = musk::Program::from_source(source)?.instantiate(arguments)?;
+        let witnesses = crate::compiled::witness_types(&compiled)
+            .into_iter()
+            .collect();
+
+        Ok(Self {
+            parameters,
+            witnesses,
+        })
+    }
+
+    /// The arguments template
+    #[must_use]
+    pub fn args_template(&self) -> Value {
+        entries(&self.parameters)
+    }
+
+    /// The witness template
+    #[must_use]
+    pub fn witness_template(&self) -> Value {
+        entries(&self.witnesses)
+    }
+
+    /// Write [`ARGS_TEMPLATE`] and [`WITNESS_TEMPLATE`] to `dir`, returning
+    /// the paths written
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a template exists and `force` is not set, or a
+    /// file cannot be written.
+    pub fn write(&self, dir: &Path, force: bool) -> Result<Vec<PathBuf>, SprayError> {
+        let files = [
+            (dir.join(ARGS_TEMPLATE), self.args_template()),
+            (dir.join(WITNESS_TEMPLATE), self.witness_template()),
+        ];
+        if !force {
+            if let Some((path, _)) = files.iter().find(|(path, _)| path.exists()) {
+                return Err(SprayError::ConfigError(format!(
+                    "{} already exists (use --force to overwrite)",
+                    path.display()
+                )));
+            }
+        }
+
+        std::fs::create_dir_all(dir)?;
+        let mut written = Vec::new();
+        for (path, template) in files {
+            std::fs::write(&path, serde_json::to_string_pretty(&template)? + "\n")?;
+            written.push(path);
+        }
+        Ok(written)
+    }
+}
+
+/// `{"NAME": {"value": <placeholder>, "type": <type>}}` for each of `types`
+///
+/// A type spray cannot parse gets an empty placeholder.
+fn entries(types: &BTreeMap<String, String>) -> Value {
+    let entries: Map<String, Value> = types
+        .iter()
+        .map(|(name, ty)| {
+            let value = ty
+                .parse::<ValueType>()
+                .and_then(|ty| ty.placeholder())
+                .unwrap_or_default();
+            (name.clone(), json!({ "value": value, "type": ty }))
+        })
+        .collect();
+    Value::Object(entries)
+}
@@ -0,0 +1,501 @@
+//! HTTP API
+//!
+//! `spray serve` lets web UIs and tools in other languages drive spray over
+//! a small JSON API on localhost:
+//!
+//! - `GET /health`
+//! - `POST /compile` with `source`, `arguments`, `witness`
+//! - `POST /deploy` with `source` or `compiled`, `arguments`, `amount`,
+//!   `asset`
+//! - `POST /redeem` with `utxo`, `witness`, `compiled`, `destination`,
+//!   `fee`, `dry_run`
+//! - `POST /test` with `source`, `arguments`, `witness`, `lock_time`,
+//!   `sequence`
+//!
+//! `source` is SimplicityHL source, `compiled` a compiled artifact, and
+//! `arguments` and `witness` are the contents of arguments and witness
+//! files. The other fields are parsed into outpoints, addresses, asset IDs
+//! and numbers before they are passed on. Each request runs the spray
+//! command of the same name with `--json`, in a directory of its own that
+//! the request's files are written to and that is removed once it is
+//! answered, with the command's JSON document (see [`crate::output`]):
+//! `200` if it succeeded, `422` if it failed.
+//!
+//! Every endpoint but `/health` requires the server's token (see
+//! [`authorize`]) as `Authorization: Bearer <token>`, a JSON body, and no
+//! `Origin` other than a loopback one, so that web pages the user visits
+//! cannot drive the API.
+//!
+//! # Example
+//!
+//! ```
+//! use serde_json::json;
+//! use spray::serve::{Endpoint, Request};
+//!
+//! let body = json!({ "source": "fn main() {}", "amount": 1000 }).to_string();
+//! let raw = format!(
+//!     "POST /deploy HTTP/1.1\r\nAuthorization: Bearer t0ken\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\r\n{body}",
+//!     body.len()
+//! );
+//! let request = Request::read(raw.as_bytes()).unwrap();
+//! let endpoint = Endpoint::route(&request.method, &request.path).unwrap();
+//! assert_eq!(endpoint, Endpoint::Deploy);
+//! assert!(spray::serve::authorize(&request, endpoint, "t0ken").is_ok());
+//!
+//! let dir = std::env::temp_dir().join(format!("spray-serve-doc-{}", std::process::id()));
+//! let args = endpoint.arguments(&request.body, &dir).unwrap();
+//! assert_eq!(args[..3], ["deploy", "--amount", "1000"]);
+//! assert_eq!(args[3], "--");
+//! assert!(dir.join("program.simf").is_file());
+//! # std::fs::remove_dir_all(&dir).unwrap();
+//! ```
+
+use crate::error::SprayError;
+use musk::elements::{Address, AssetId, Txid};
+use serde_json::{json, Value};
+use std::fmt::Display;
+use std::io::{BufRead, Read};
+use std::path::Path;
+use std::str::FromStr;
+use std::time::Duration;
+
+/// Default port of `spray serve`
+pub const DEFAULT_PORT: u16 = 7070;
+
+/// Directory the requests' files are written to, one subdirectory each
+pub const DEFAULT_REQUESTS_DIR: &str = ".spray/serve";
+
+/// Largest request body accepted, in bytes
+pub const MAX_BODY_SIZE: usize = 16 * 1024 * 1024;
+
+/// Largest request line and headers accepted, in bytes
+pub const MAX_HEAD_SIZE: usize = 8 * 1024;
+
+/// Most connections `spray serve` reads and answers at once; others are
+/// answered `503` straight away
+pub const MAX_CONNECTIONS: usize = 16;
+
+/// Time a client has to send its request, and to take the response
+pub const IO_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// An endpoint of the API
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Endpoint {
+    /// `GET /health`
+    Health,
+    /// `POST /compile`
+    Compile,
+    /// `POST /deploy`
+    Deploy,
+    /// `POST /redeem`
+    Redeem,
+    /// `POST /test`
+    Test,
+}
+
+impl Endpoint {
+    /// Every endpoint
+    pub const ALL: [Self; 5] = [
+        Self::Health,
+        Self::Compile,
+        Self::Deploy,
+        Self::Redeem,
+        Self::Test,
+    ];
+
+    /// Method and path of the endpoint
+    #[must_use]
+    pub const fn method_and_path(self) -> (&'static str, &'static str) {
+        match self {
+            Self::Health => ("GET", "/health"),
+            Self::Compile => ("POST", "/compile"),
+            Self::Deploy => ("POST", "/deploy"),
+            Self::Redeem => ("POST", "/redeem"),
+            Self::Test => ("POST", "/test"),
+        }
+    }
+
+    /// The endpoint of a request, if any
+    #[must_use]
+    pub fn route(method: &str, path: &str) -> Option<Self> {
+        let path = path.split('?').next().unwrap_or(path);
+        Self::ALL
+            .into_iter()
+            .find(|endpoint| endpoint.method_and_path() == (method, path))
+    }
+
+    /// Whether the endpoint's command talks to the node, and so takes the
+    /// server's network and config
+    #[must_use]
+    pub const fn uses_node(self) -> bool {
+        matches!(self, Self::Compile | Self::Deploy | Self::Redeem)
+    }
+
+    /// Write the files of a request with `body` to `dir`, and return the
+    /// command line of the spray command that answers it
+    ///
+    /// The command's options come first, then `--` and its positional
+    /// arguments.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a field is missing, has the wrong type or does
+    /// not parse, or a file cannot be written.
+    pub fn arguments(self, body: &Value, dir: &Path) -> Result<Vec<String>, SprayError> {
+        std::fs::create_dir_all(dir)?;
+        let fields = Fields { body, dir };
+        let mut args = Vec::new();
+        let mut positional = Vec::new();
+        match self {
+            Self::Health => {}
+            Self::Compile => {
+                args.push("compile".into());
+                positional.push(fields.program(false)?);
+                fields.file("--args", "arguments", "args.json", &mut args)?;
+                fields.file("--witness", "witness", "witness.json", &mut args)?;
+            }
+            Self::Deploy => {
+                args.push("deploy".into());
+                positional.push(fields.program(true)?);
+                fields.file("--args", "arguments", "args.json", &mut args)?;
+                fields.flag::<u64>("--amount", "amount", &mut args)?;
+                fields.flag::<AssetId>("--asset", "asset", &mut args)?;
+            }
+            Self::Redeem => {
+                args.push("redeem".into());
+                let utxo = fields.string("utxo")?.ok_or_else(|| missing("utxo"))?;
+                positional.push(parse_outpoint(&utxo)?);
+                positional.push(
+                    fields
+                        .write("witness", "witness.json")?
+                        .ok_or_else(|| missing("witness"))?,
+                );
+                fields.file("--compiled", "compiled", "program.json", &mut args)?;
+                fields.flag::<Address>("--dest", "destination", &mut args)?;
+                fields.flag::<u64>("--fee", "fee", &mut args)?;
+                if body.get("dry_run").and_then(Value::as_bool) == Some(true) {
+                    args.push("--dry-run".into());
+                }
+            }
+            Self::Test => {
+                args.push("test".into());
+                args.push("--file".into());
+                args.push(fields.program(false)?);
+                fields.file("--args", "arguments", "args.json", &mut args)?;
+                fields.file("--witness", "witness", "witness.json", &mut args)?;
+                fields.flag::<u32>("--lock-time", "lock_time", &mut args)?;
+                fields.flag::<u32>("--sequence", "sequence", &mut args)?;
+            }
+        }
+        if !positional.is_empty() {
+            args.push("--".into());
+            args.append(&mut positional);
+        }
+        Ok(args)
+    }
+}
+
+/// The fields of a request body, and the directory its files go to
+struct Fields<'a> {
+    body: &'a Value,
+    dir: &'a Path,
+}
+
+impl Fields<'_> {
+    /// Write the program, from `source` or (if `compiled` is allowed)
+    /// `compiled`, and return its path
+    fn program(&self, compiled: bool) -> Result<String, SprayError> {
+        if let Some(source) = self.string("source")? {
+            let path = self.dir.join("program.simf");
+            std::fs::write(&path, source)?;
+            return Ok(path.display().to_string());
+        }
+        match self.body.get("compiled") {
+            Some(artifact) if compiled => {
+                let path = self.dir.join("program.json");
+                std::fs::write(&path, serde_json::to_string_pretty(artifact)?)?;
+                Ok(path.display().to_string())
+            }
+            _ if compiled => Err(missing("source or compiled")),
+            _ => Err(missing("source")),
+        }
+    }
+
+    /// Write the JSON of `field`, if given, to `name` and pass its path
+    /// with `option`
+    fn file(
+        &self,
+        option: &str,
+        field: &str,
+        name: &str,
+        args: &mut Vec<String>,
+    ) -> Result<(), SprayError> {
+        if let Some(path) = self.write(field, name)? {
+            args.push(option.into());
+            args.push(path);
+        }
+        Ok(())
+    }
+
+    /// Write the JSON of `field`, if given, to `name`, returning its path
+    fn write(&self, field: &str, name: &str) -> Result<Option<String>, SprayError> {
+        let Some(value) = self.body.get(field).filter(|value| !value.is_null()) else {
+            return Ok(None);
+        };
+        let path = self.dir.join(name);
+        std::fs::write(&path, serde_json::to_string_pretty(value)?)?;
+        Ok(Some(path.display().to_string()))
+    }
+
+    /// Parse the string or number `field` as a `T`, if given, and pass it
+    /// with `option`
+    fn flag<T>(&self, option: &str, field: &str, args: &mut Vec<String>) -> Result<(), SprayError>
+    where
+        T: FromStr + Display,
+        T::Err: Display,
+    {
+        if let Some(value) = self.string(field)? {
+            let value: T = value
+                .parse()
+                .map_err(|e| SprayError::ParseError(format!("Invalid '{field}': {e}")))?;
+            args.push(option.into());
+            args.push(value.to_string());
+        }
+        Ok(())
+    }
+
+    /// The string or number `field`, if given
+    fn string(&self, field: &str) -> Result<Option<String>, SprayError> {
+        match self.body.get(field) {
+            None | Some(Value::Null) => Ok(None),
+            Some(Value::String(value)) => Ok(Some(value.clone())),
+            Some(Value::Number(value)) => Ok(Some(value.to_string())),
+            Some(_) => Err(SprayError::ParseError(format!(
+                "'{field}' must be a string or a number"
+            ))),
+        }
+    }
+}
+
+/// Parse a `txid:vout` outpoint, returning it in canonical form
+fn parse_outpoint(s: &str) -> Result<String, SprayError> {
+    let invalid = |detail: String| SprayError::InvalidUtxoRef(format!("Invalid 'utxo': {detail}"));
+    let (txid, vout) = s
+        .split_once(':')
+        .ok_or_else(|| invalid(format!("expected 'txid:vout', got {s}")))?;
+    let txid = Txid::from_str(txid).map_err(|e| invalid(e.to_string()))?;
+    let vout: u32 = vout.parse().map_err(|e| invalid(e.to_string()))?;
+    Ok(format!("{txid}:{vout}"))
+}
+
+/// The error of a missing field
+fn missing(field: &str) -> SprayError {
+    SprayError::ParseError(format!("Request body has no '{field}'"))
+}
+
+/// An HTTP request
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Request {
+    /// Method, e.g. `POST`
+    pub method: String,
+    /// Path, e.g. `/deploy`
+    pub path: String,
+    /// Headers, with lowercase names
+    pub headers: Vec<(String, String)>,
+    /// Body, `null` if empty
+    pub body: Value,
+}
+
+impl Request {
+    /// Read a request from `reader`
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the request is malformed, its request line and
+    /// headers are larger than [`MAX_HEAD_SIZE`], its body is larger than
+    /// [`MAX_BODY_SIZE`], or the body is not JSON.
+    pub fn read(mut reader: impl BufRead) -> Result<Self, SprayError> {
+        let malformed = || SprayError::ParseError("Malformed HTTP request".into());
+        let too_large = || {
+            SprayError::ParseError(format!(
+                "Request line and headers are larger than {MAX_HEAD_SIZE} bytes"
+            ))
+        };
+
+        let mut head = (&mut reader).take(MAX_HEAD_SIZE as u64);
+        let mut line = String::new();
+        head.read_line(&mut line)?;
+        if head.limit() == 0 && !line.ends_with('\n') {
+            return Err(too_large());
+        }
+        let mut parts = line.split_whitespace();
+        let (Some(method), Some(path)) = (parts.next(), parts.next()) else {
+            return Err(malformed());
+        };
+        let (method, path) = (method.to_string(), path.to_string());
+
+        let mut headers = Vec::new();
+        let mut length = 0;
+        loop {
+            line.clear();
+            let read = head.read_line(&mut line)?;
+            if head.limit() == 0 && !line.ends_with('\n') {
+                return Err(too_large());
+            }
+            if read == 0 {
+                return Err(malformed());
+            }
+            let header = line.trim_end();
+            if header.is_empty() {
+                break;
+            }
+            if let Some((name, value)) = header.split_once(':') {
+                let (name, value) = (name.trim().to_ascii_lowercase(), value.trim());
+                if name == "content-length" {
+                    length = value.parse().map_err(|_| malformed())?;
+                }
+                headers.push((name, value.to_string()));
+            }
+        }
+        if length > MAX_BODY_SIZE {
+            return Err(SprayError::ParseError(format!(
+                "Request body of {length} bytes is larger than {MAX_BODY_SIZE}"
+            )));
+        }
+
+        let mut body = vec![0; length];
+        reader.read_exact(&mut body)?;
+        let body = if body.iter().all(u8::is_ascii_whitespace) {
+            Value::Null
+        } else {
+            serde_json::from_slice(&body)?
+        };
+        Ok(Self {
+            method,
+            path,
+            headers,
+            body,
+        })
+    }
+
+    /// The value of the header `name`, if the request has it
+    #[must_use]
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(header, _)| header.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+/// A random token for [`authorize`]
+#[must_use]
+pub fn generate_token() -> String {
+    rand::random::<[u8; 16]>()
+        .iter()
+        .map(|b| format!("{b:02x}"))
+        .collect()
+}
+
+/// Check that `request` may run `endpoint`, returning the status and error
+/// to answer it with if not
+///
+/// Requests other than `GET /health` must carry `token` as
+/// `Authorization: Bearer <token>` and a `Content-Type` of
+/// `application/json`, which browsers do not send across origins without
+/// asking first. A request with an `Origin` must come from a page on a
+/// loopback host.
+///
+/// # Errors
+///
+/// Returns `403` for a foreign origin, `401` for a missing or wrong token,
+/// and `415` for a body that is not declared as JSON.
+pub fn authorize(
+    request: &Request,
+    endpoint: Endpoint,
+    token: &str,
+) -> Result<(), (u16, SprayError)> {
+    if let Some(origin) = request.header("origin") {
+        if !is_loopback_origin(origin) {
+            return Err((
+                403,
+                SprayError::ConfigError(format!("Requests from {origin} are not allowed")),
+            ));
+        }
+    }
+    if endpoint == Endpoint::Health {
+        return Ok(());
+    }
+    if request.header("authorization") != Some(format!("Bearer {token}").as_str()) {
+        return Err((
+            401,
+            SprayError::ConfigError("Missing or wrong 'Authorization: Bearer <token>'".into()),
+        ));
+    }
+    let media_type = request
+        .header("content-type")
+        .and_then(|value| value.split(';').next())
+        .map(str::trim);
+    if !media_type.is_some_and(|media_type| media_type.eq_ignore_ascii_case("application/json")) {
+        return Err((
+            415,
+            SprayError::ParseError("Request body must be sent as application/json".into()),
+        ));
+    }
+    Ok(())
+}
+
+/// Whether `origin` is a page on a loopback host, e.g. `http://localhost:3000`
+fn is_loopback_origin(origin: &str) -> bool {
+    let Some((_, authority)) = origin.split_once("://") else {
+        return false;
+    };
+    let host = match authority.strip_prefix('[') {
+        Some(rest) => rest.split(']').next().unwrap_or_default(),
+        None => authority.split(':').next().unwrap_or_default(),
+    };
+    host.eq_ignore_ascii_case("localhost")
+        || host
+            .parse::<std::net::IpAddr>()
+            .is_ok_and(|ip| ip.is_loopback())
+}
+
+/// An HTTP response with status `status` and JSON body `body`
+#[must_use]
+pub fn response(status: u16, body: &Value) -> String {
+    let reason = match status {
+        200 => "OK",
+        400 => "Bad Request",
+        401 => "Unauthorized",
+        403 => "Forbidden",
+        404 => "Not Found",
+        415 => "Unsupported Media Type",
+        422 => "Unprocessable Entity",
+        503 => "Service Unavailable",
+        _ => "Internal Server Error",
+    };
+    let body = format!("{body}\n");
+    format!(
+        "HTTP/1.1 {status} {reason}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+        body.len()
+    )
+}
+
+/// The status answering a command's JSON document: `200` if it succeeded,
+/// `422` if not
+#[must_use]
+pub fn status_of(document: &Value) -> u16 {
+    if document["ok"].as_bool() == Some(true) {
+        200
+    } else {
+        422
+    }
+}
+
+/// The body answering a request that could not be run
+#[must_use]
+pub fn error_body(error: &SprayError) -> Value {
+    let mut body = crate::output::error_document(error);
+    body["command"] = json!("serve");
+    body
+}
@@ -0,0 +1,373 @@
+//! Structured breakdown of the fields committed to by a sighash
+//!
+//! This module provides the [`SighashExplanation`] type, which lists every
+//! field a Simplicity `sig_all_hash` commits to for an Elements spend
+//! (genesis hash, transaction version and lock time, inputs with their
+//! prevouts and issuances, outputs, and the tap leaf), together with the
+//! component hashes the sighash is built from. The hashes are computed by
+//! running the Simplicity jets of the same name (e.g. `jet::outputs_hash`)
+//! in the spend's environment, so they are exactly what a program sees. Two
+//! explanations can be compared field by field to pinpoint which component
+//! differs when two tools disagree on a sighash.
+//!
+//! # Example
+//!
+//! ```
+//! use spray::sighash::diff_json;
+//! use serde_json::json;
+//!
+//! let expected = json!({ "version": 2, "outputs": [{ "value": "1000" }] });
+//! let actual = json!({ "version": 2, "outputs": [{ "value": "999" }] });
+//!
+//! let diffs = diff_json(&expected, &actual);
+//! assert_eq!(diffs.len(), 1);
+//! assert_eq!(diffs[0].path, "outputs[0].value");
+//! ```
+
+use crate::error::SprayError;
+use crate::hex;
+use crate::pset::SimplicityLeaf;
+use crate::trace::Tracer;
+use musk::elements::encode::serialize;
+use musk::elements::{confidential, BlockHash, Transaction, TxOut};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use simplicity::jet::elements::{ElementsEnv, ElementsUtxo};
+use simplicity::Cmr;
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::sync::Arc;
+
+/// Jets computing the components of `sig_all_hash`, from the sighash itself
+/// down to the hashes of individual input and output fields
+pub const COMPONENT_JETS: [&str; 19] = [
+    "sig_all_hash",
+    "genesis_block_hash",
+    "tx_hash",
+    "tap_env_hash",
+    "inputs_hash",
+    "input_outpoints_hash",
+    "input_sequences_hash",
+    "input_annexes_hash",
+    "input_script_sigs_hash",
+    "input_utxos_hash",
+    "input_amounts_hash",
+    "input_scripts_hash",
+    "issuances_hash",
+    "outputs_hash",
+    "output_amounts_hash",
+    "output_nonces_hash",
+    "output_scripts_hash",
+    "output_range_proofs_hash",
+    "output_surjection_proofs_hash",
+];
+
+/// A committed input
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct InputFields {
+    /// Outpoint spent (`txid:vout`)
+    pub outpoint: String,
+    /// Sequence number
+    pub sequence: u32,
+    /// Asset of the prevout
+    pub prevout_asset: String,
+    /// Amount of the prevout
+    pub prevout_value: String,
+    /// Output script of the prevout (hex)
+    pub prevout_script: String,
+    /// Serialized asset issuance (hex), if the input issues an asset
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub issuance: Option<String>,
+}
+
+/// A committed output
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OutputFields {
+    /// Asset
+    pub asset: String,
+    /// Amount
+    pub value: String,
+    /// Nonce (hex), if any
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub nonce: Option<String>,
+    /// Output script (hex)
+    pub script_pubkey: String,
+    /// Whether the output carries range/surjection proofs
+    pub has_proofs: bool,
+}
+
+/// The committed tap leaf
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TapLeafFields {
+    /// Leaf script, i.e. the program CMR (hex)
+    pub script: String,
+    /// Leaf version
+    pub leaf_version: u8,
+    /// Serialized control block (hex)
+    pub control_block: String,
+}
+
+/// Every field committed to by a sighash, with per-component digests
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SighashExplanation {
+    /// The sighash itself (hex)
+    pub sighash: String,
+    /// Genesis block hash
+    pub genesis_hash: String,
+    /// Transaction version
+    pub version: u32,
+    /// Transaction lock time
+    pub lock_time: u32,
+    /// Index of the input being signed
+    pub input_index: u32,
+    /// All inputs, with their prevouts
+    pub inputs: Vec<InputFields>,
+    /// All outputs
+    pub outputs: Vec<OutputFields>,
+    /// Tap leaf of the input being signed
+    pub tap_leaf: TapLeafFields,
+    /// Component hashes by the jet computing them, e.g. `outputs_hash`
+    /// (hex); see [`COMPONENT_JETS`]
+    pub digests: BTreeMap<String, String>,
+}
+
+/// A field whose value differs between two explanations
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FieldDiff {
+    /// Path of the field, e.g. `outputs[1].value`
+    pub path: String,
+    /// Value in the expected explanation, `None` if absent
+    pub expected: Option<String>,
+    /// Value in the actual explanation, `None` if absent
+    pub actual: Option<String>,
+}
+
+impl SighashExplanation {
+    /// Explain the sighash of input `input_index` of `tx`
+    ///
+    /// `prevouts` must hold the spent output of every input, in input order.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the number of prevouts does not match the inputs,
+    /// the leaf script is not a CMR, or the component jets fail to run.
+    pub fn new(
+        sighash: [u8; 32],
+        genesis_hash: BlockHash,
+        tx: &Transaction,
+        prevouts: &[TxOut],
+        input_index: u32,
+        leaf: &SimplicityLeaf,
+    ) -> Result<Self, SprayError> {
+        if prevouts.len() != tx.input.len() {
+            return Err(SprayError::TestError(format!(
+                "Expected {} prevouts, got {}",
+                tx.input.len(),
+                prevouts.len()
+            )));
+        }
+
+        let inputs = tx
+            .input
+            .iter()
+            .zip(prevouts)
+            .map(|(input, prevout)| InputFields {
+                outpoint: input.previous_output.to_string(),
+                sequence: input.sequence.to_consensus_u32(),
+                prevout_asset: describe_asset(prevout.asset),
+                prevout_value: describe_value(prevout.value),
+                prevout_script: hex::encode(prevout.script_pubkey.as_bytes()),
+                issuance: input
+                    .has_issuance()
+                    .then(|| hex::encode(&serialize(&input.asset_issuance))),
+            })
+            .collect();
+
+        let outputs = tx
+            .output
+            .iter()
+            .map(|output| OutputFields {
+                asset: describe_asset(output.asset),
+                value: describe_value(output.value),
+                nonce: (!output.nonce.is_null()).then(|| hex::encode(&serialize(&output.nonce))),
+                script_pubkey: hex::encode(output.script_pubkey.as_bytes()),
+                has_proofs: !output.witness.is_empty(),
+            })
+            .collect();
+
+        let tap_leaf = TapLeafFields {
+            script: hex::encode(leaf.script.as_bytes()),
+            leaf_version: leaf.version.to_consensus(),
+            control_block: hex::encode(&leaf.control_block.serialize()),
+        };
+
+        let cmr: [u8; 32] = leaf
+            .script
+            .as_bytes()
+            .try_into()
+            .map_err(|_| SprayError::TestError("Leaf script is not a Simplicity CMR".into()))?;
+        let env = ElementsEnv::new(
+            Arc::new(tx.clone()),
+            prevouts
+                .iter()
+                .map(|prevout| ElementsUtxo {
+                    script_pubkey: prevout.script_pubkey.clone(),
+                    asset: prevout.asset,
+                    value: prevout.value,
+                })
+                .collect(),
+            input_index,
+            Cmr::from_byte_array(cmr),
+            leaf.control_block.clone(),
+            None,
+            genesis_hash,
+        );
+        let digests = component_hashes(&env)?;
+
+        Ok(Self {
+            sighash: hex::encode(&sighash),
+            genesis_hash: genesis_hash.to_string(),
+            version: tx.version,
+            lock_time: tx.lock_time.to_consensus_u32(),
+            input_index,
+            inputs,
+            outputs,
+            tap_leaf,
+            digests,
+        })
+    }
+
+    /// Load an explanation from a JSON file
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be read or parsed.
+    pub fn load(path: &Path) -> Result<Self, SprayError> {
+        let contents = std::fs::read_to_string(path)?;
+        Ok(serde_json::from_str(&contents)?)
+    }
+
+    /// Compare against an expected explanation, field by field
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if either explanation cannot be serialized.
+    pub fn diff(&self, expected: &Self) -> Result<Vec<FieldDiff>, SprayError> {
+        Ok(diff_json(
+            &serde_json::to_value(expected)?,
+            &serde_json::to_value(self)?,
+        ))
+    }
+}
+
+/// Run the [`COMPONENT_JETS`] in `env`, returning the hash each computes
+///
+/// The jets are called from a program of their own: the hashes depend only
+/// on the transaction, the input and its tap leaf, not on the program
+/// being run.
+fn component_hashes(
+    env: &ElementsEnv<Arc<Transaction>>,
+) -> Result<BTreeMap<String, String>, SprayError> {
+    let calls: String = COMPONENT_JETS
+        .iter()
+        .map(|jet| format!("    let {jet}: u256 = jet::{jet}();\n"))
+        .collect();
+    let program = musk::Program::from_source(&format!("fn main() {{\n{calls}}}\n"))?
+        .instantiate(musk::Arguments::default())?;
+    let satisfied = program
+        .inner()
+        .satisfy(musk::WitnessValues::default())
+        .map_err(|e| SprayError::TestError(format!("Failed to satisfy component jets: {e}")))?;
+
+    let trace = Tracer::new()
+        .jets_only(true)
+        .trace(satisfied.redeem(), env)?;
+    if let Some(error) = trace.error {
+        return Err(SprayError::TestError(format!(
+            "Component jets failed: {error}"
+        )));
+    }
+    Ok(trace
+        .steps
+        .into_iter()
+        .filter_map(|step| {
+            let jet = step.op.strip_prefix("jet_")?.to_string();
+            let hash = step.output?.trim_start_matches("0x").to_string();
+            Some((jet, hash))
+        })
+        .collect())
+}
+
+/// Compare two JSON documents leaf by leaf
+///
+/// Returns one entry per leaf path whose value differs or that is present in
+/// only one of the documents, in path order.
+#[must_use]
+pub fn diff_json(expected: &Value, actual: &Value) -> Vec<FieldDiff> {
+    let mut expected_leaves = BTreeMap::new();
+    let mut actual_leaves = BTreeMap::new();
+    flatten("", expected, &mut expected_leaves);
+    flatten("", actual, &mut actual_leaves);
+
+    let mut paths: Vec<&String> = expected_leaves.keys().chain(actual_leaves.keys()).collect();
+    paths.sort();
+    paths.dedup();
+
+    paths
+        .into_iter()
+        .filter_map(|path| {
+            let expected = expected_leaves.get(path);
+            let actual = actual_leaves.get(path);
+            (expected != actual).then(|| FieldDiff {
+                path: path.clone(),
+                expected: expected.cloned(),
+                actual: actual.cloned(),
+            })
+        })
+        .collect()
+}
+
+/// Collect the leaves of a JSON document keyed by their path
+fn flatten(path: &str, value: &Value, out: &mut BTreeMap<String, String>) {
+    match value {
+        Value::Object(map) => {
+            for (key, value) in map {
+                let path = if path.is_empty() {
+                    key.clone()
+                } else {
+                    format!("{path}.{key}")
+                };
+                flatten(&path, value, out);
+            }
+        }
+        Value::Array(items) => {
+            for (i, value) in items.iter().enumerate() {
+                flatten(&format!("{path}[{i}]"), value, out);
+            }
+        }
+        Value::String(s) => {
+            out.insert(path.to_string(), s.clone());
+        }
+        other => {
+            out.insert(path.to_string(), other.to_string());
+        }
+    }
+}
+
+/// Describe an asset: the asset ID if explicit, the commitment otherwise
+fn describe_asset(asset: confidential::Asset) -> String {
+    match asset {
+        confidential::Asset::Explicit(id) => id.to_string(),
+        confidential::Asset::Null => "null".to_string(),
+        confidential => format!("commitment:{}", hex::encode(&serialize(&confidential))),
+    }
+}
+
+/// Describe an amount: satoshis if explicit, the commitment otherwise
+fn describe_value(value: confidential::Value) -> String {
+    match value {
+        confidential::Value::Explicit(sats) => sats.to_string(),
+        confidential::Value::Null => "null".to_string(),
+        confidential => format!("commitment:{}", hex::encode(&serialize(&confidential))),
+    }
+}
@@ -0,0 +1,288 @@
+//! Detached signatures over compiled artifacts
+//!
+//! Before an artifact is deployed to mainnet, a team wants to be sure it is
+//! exactly the one that passed review. A reviewer signs the artifact with a
+//! key from their keystore, producing a detached `<artifact>.sig` file with a
+//! BIP 340 Schnorr signature over the SHA-256 digest of the artifact's bytes.
+//! With `require_signature` set in the `[artifacts]` table of `spray.toml`,
+//! `spray deploy` refuses artifacts without a valid signature by one of the
+//! listed `trusted_keys`, and deploys the program bytes of the signed
+//! artifact as they are (see [`verify_deployment`]): source files, whose
+//! includes the signature does not cover, and arguments, which would change
+//! the program, are refused.
+//!
+//! Keys are stored one per file in the keystore directory (`.spray/keys` by
+//! default), as `<name>.key` holding the hex secret key.
+//!
+//! # Example
+//!
+//! ```
+//! use spray::signing::{sign, Keystore};
+//!
+//! let dir = std::env::temp_dir().join(format!("spray-doc-keys-{}", std::process::id()));
+//! let keystore = Keystore::new(&dir);
+//! let pubkey = keystore.generate("reviewer").unwrap();
+//!
+//! let artifact = br#"{"cmr": "..."}"#;
+//! let signature = sign(artifact, &keystore.secret_key("reviewer").unwrap(), "reviewer");
+//! assert!(signature.verify(artifact, &[pubkey.to_string()]).is_ok());
+//! assert!(signature.verify(b"tampered", &[pubkey.to_string()]).is_err());
+//! assert!(signature.verify(artifact, &[]).is_err());
+//! # std::fs::remove_dir_all(&dir).unwrap();
+//! ```
+
+use crate::error::SprayError;
+use musk::elements::hashes::{sha256, Hash};
+use musk::elements::secp256k1_zkp::{
+    schnorr, Keypair, Message, Secp256k1, SecretKey, XOnlyPublicKey,
+};
+use serde::{Deserialize, Serialize};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+/// Default keystore directory, relative to the working directory
+pub const DEFAULT_KEYSTORE_DIR: &str = ".spray/keys";
+
+/// Extension appended to an artifact's file name for its signature
+pub const SIGNATURE_EXTENSION: &str = "sig";
+
+/// Path of the detached signature of `artifact`, e.g. `compiled.json.sig`
+#[must_use]
+pub fn signature_path(artifact: &Path) -> PathBuf {
+    let mut name = artifact.as_os_str().to_owned();
+    name.push(".");
+    name.push(SIGNATURE_EXTENSION);
+    PathBuf::from(name)
+}
+
+/// Signing keys stored as `<name>.key` files in a directory
+#[derive(Debug, Clone)]
+pub struct Keystore {
+    dir: PathBuf,
+}
+
+impl Default for Keystore {
+    fn default() -> Self {
+        Self::new(DEFAULT_KEYSTORE_DIR)
+    }
+}
+
+impl Keystore {
+    /// A keystore in `dir`, which is created when the first key is generated
+    #[must_use]
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    /// Path of the key file for `name`
+    fn key_path(&self, name: &str) -> PathBuf {
+        self.dir.join(format!("{name}.key"))
+    }
+
+    /// Generate and store a new key, returning its public key
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a key named `name` already exists or the key file
+    /// cannot be written.
+    pub fn generate(&self, name: &str) -> Result<XOnlyPublicKey, SprayError> {
+        let path = self.key_path(name);
+        let secret_key = loop {
+            if let Ok(key) = SecretKey::from_slice(&rand::random::<[u8; 32]>()) {
+                break key;
+            }
+        };
+        std::fs::create_dir_all(&self.dir)?;
+
+        // The key is never readable by others, not even before it is written,
+        // and an existing key is never replaced
+        let mut options = std::fs::OpenOptions::new();
+        options.write(true).create_new(true);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::OpenOptionsExt;
+            options.mode(0o600);
+        }
+        let mut file = options.open(&path).map_err(|e| {
+            if e.kind() == std::io::ErrorKind::AlreadyExists {
+                SprayError::ConfigError(format!(
+                    "Key '{name}' already exists at {}",
+                    path.display()
+                ))
+            } else {
+                e.into()
+            }
+        })?;
+        file.write_all(secret_key.display_secret().to_string().as_bytes())?;
+        file.sync_all()?;
+
+        Ok(public_key(&secret_key))
+    }
+
+    /// Load the secret key named `name`
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the key file cannot be read or holds no valid key.
+    pub fn secret_key(&self, name: &str) -> Result<SecretKey, SprayError> {
+        let path = self.key_path(name);
+        let hex = std::fs::read_to_string(&path).map_err(|e| {
+            SprayError::ConfigError(format!(
+                "Cannot read key '{name}' ({}): {e}",
+                path.display()
+            ))
+        })?;
+        SecretKey::from_str(hex.trim())
+            .map_err(|e| SprayError::ParseError(format!("Invalid key '{name}': {e}")))
+    }
+}
+
+/// The x-only public key of `secret_key`
+#[must_use]
+pub fn public_key(secret_key: &SecretKey) -> XOnlyPublicKey {
+    Keypair::from_secret_key(&Secp256k1::signing_only(), secret_key)
+        .x_only_public_key()
+        .0
+}
+
+/// BIP 340 Schnorr signature over a 32-byte digest, e.g. a spend's sighash
+#[must_use]
+pub fn sign_digest(digest: [u8; 32], secret_key: &SecretKey) -> schnorr::Signature {
+    let secp = Secp256k1::signing_only();
+    let keypair = Keypair::from_secret_key(&secp, secret_key);
+    secp.sign_schnorr_no_aux_rand(&Message::from_digest(digest), &keypair)
+}
+
+/// A detached signature over an artifact
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ArtifactSignature {
+    /// Name of the signing key in the signer's keystore
+    pub key: String,
+    /// X-only public key of the signer (hex)
+    pub pubkey: String,
+    /// SHA-256 digest of the artifact (hex)
+    pub digest: String,
+    /// BIP 340 Schnorr signature over the digest (hex)
+    pub signature: String,
+}
+
+/// Sign `artifact` with `secret_key`, recording `key_name` as the signer
+#[must_use]
+pub fn sign(artifact: &[u8], secret_key: &SecretKey, key_name: &str) -> ArtifactSignature {
+    let digest = sha256::Hash::hash(artifact);
+    let signature = sign_digest(digest.to_byte_array(), secret_key);
+
+    ArtifactSignature {
+        key: key_name.to_string(),
+        pubkey: public_key(secret_key).to_string(),
+        digest: digest.to_string(),
+        signature: signature.to_string(),
+    }
+}
+
+impl ArtifactSignature {
+    /// Load a signature file
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be read or parsed.
+    pub fn load(path: &Path) -> Result<Self, SprayError> {
+        Ok(serde_json::from_str(&std::fs::read_to_string(path)?)?)
+    }
+
+    /// Write the signature file
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be written.
+    pub fn save(&self, path: &Path) -> Result<(), SprayError> {
+        std::fs::write(path, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+
+    /// Check that this is a valid signature over `artifact` by one of
+    /// `trusted` (x-only public keys, hex)
+    ///
+    /// # Errors
+    ///
+    /// Returns [`SprayError::SignatureError`] if the artifact does not match
+    /// the signed digest, the signer is not trusted or the signature is
+    /// invalid.
+    pub fn verify(&self, artifact: &[u8], trusted: &[String]) -> Result<(), SprayError> {
+        let digest = sha256::Hash::hash(artifact);
+        if digest.to_string() != self.digest {
+            return Err(SprayError::SignatureError(format!(
+                "Artifact digest {digest} does not match the signed digest {}",
+                self.digest
+            )));
+        }
+        if !trusted
+            .iter()
+            .any(|key| key.eq_ignore_ascii_case(&self.pubkey))
+        {
+            return Err(SprayError::SignatureError(format!(
+                "Signer {} ('{}') is not a trusted key",
+                self.pubkey, self.key
+            )));
+        }
+
+        let invalid = |e: musk::elements::secp256k1_zkp::Error| {
+            SprayError::SignatureError(format!("Invalid signature: {e}"))
+        };
+        let pubkey = XOnlyPublicKey::from_str(&self.pubkey).map_err(invalid)?;
+        let signature = schnorr::Signature::from_str(&self.signature).map_err(invalid)?;
+        Secp256k1::verification_only()
+            .verify_schnorr(
+                &signature,
+                &Message::from_digest(digest.to_byte_array()),
+                &pubkey,
+            )
+            .map_err(invalid)
+    }
+}
+
+/// Verify the detached signature of the artifact at `path`
+///
+/// # Errors
+///
+/// Returns an error if the artifact or its signature file cannot be read, or
+/// the signature does not verify against `trusted`.
+pub fn verify_artifact(path: &Path, trusted: &[String]) -> Result<ArtifactSignature, SprayError> {
+    let sig_path = signature_path(path);
+    let signature = ArtifactSignature::load(&sig_path).map_err(|e| {
+        SprayError::SignatureError(format!("Cannot load signature {}: {e}", sig_path.display()))
+    })?;
+    signature.verify(&std::fs::read(path)?, trusted)?;
+    Ok(signature)
+}
+
+/// Verify that deploying the artifact at `path` with `args` (an arguments
+/// file) and `arg_overrides` deploys exactly what was signed
+///
+/// Only a compiled `.json` artifact, deployed without arguments, is covered
+/// by its signature; its program bytes must then be deployed as they are.
+///
+/// # Errors
+///
+/// Returns [`SprayError::SignatureError`] for a source file or arguments,
+/// and the errors of [`verify_artifact`].
+pub fn verify_deployment(
+    path: &Path,
+    args: Option<&Path>,
+    arg_overrides: &[(String, String)],
+    trusted: &[String],
+) -> Result<ArtifactSignature, SprayError> {
+    if path.extension().and_then(|ext| ext.to_str()) != Some("json") {
+        return Err(SprayError::SignatureError(format!(
+            "{} is not a compiled artifact; compile and sign it first",
+            path.display()
+        )));
+    }
+    if args.is_some() || !arg_overrides.is_empty() {
+        return Err(SprayError::SignatureError(
+            "--args and --arg would change the signed program; sign an artifact compiled with them instead".into(),
+        ));
+    }
+    verify_artifact(path, trusted)
+}
@@ -0,0 +1,146 @@
+//! Soak testing with fault injection
+//!
+//! A soak run is a long stress run (see [`crate::stress`]) during which the
+//! chain is disturbed on a schedule: the daemon is restarted, blocks are
+//! reorganized away, or the mempool is dropped. Spends that lose their place
+//! in the mempool or in the chain are rebroadcast, and the run only passes if
+//! every redeemed instance ends up confirmed.
+//!
+//! # Example
+//!
+//! ```
+//! use spray::soak::{Fault, FaultSchedule};
+//!
+//! let faults: Vec<Fault> = ["restart", "reorg:2", "evict"]
+//!     .iter()
+//!     .map(|s| s.parse().unwrap())
+//!     .collect();
+//! let schedule = FaultSchedule::new(faults, 3);
+//!
+//! assert_eq!(schedule.fault_for_round(0), None);
+//! assert_eq!(schedule.fault_for_round(2), Some(Fault::Restart));
+//! assert_eq!(schedule.fault_for_round(5), Some(Fault::Reorg(2)));
+//! assert_eq!(schedule.fault_for_round(8), Some(Fault::MempoolEviction));
+//! assert_eq!(schedule.fault_for_round(11), Some(Fault::Restart));
+//! ```
+
+use crate::error::SprayError;
+use crate::stress::StressReport;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+
+/// Default number of rounds between two faults
+pub const DEFAULT_FAULT_INTERVAL: usize = 5;
+
+/// Default number of rebroadcast-and-mine attempts before giving up
+pub const DEFAULT_RECOVERY_ATTEMPTS: u32 = 10;
+
+/// A disturbance injected into the chain
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Fault {
+    /// Stop the daemon cleanly and start it again
+    Restart,
+    /// Replace this many blocks at the tip with a longer chain
+    Reorg(u32),
+    /// Restart the daemon without its mempool
+    MempoolEviction,
+}
+
+impl fmt::Display for Fault {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Restart => write!(f, "restart"),
+            Self::Reorg(depth) => write!(f, "reorg:{depth}"),
+            Self::MempoolEviction => write!(f, "evict"),
+        }
+    }
+}
+
+impl FromStr for Fault {
+    type Err = SprayError;
+
+    /// Parse `restart`, `reorg:<depth>` (`reorg` alone is depth 1) or `evict`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim() {
+            "restart" => Ok(Self::Restart),
+            "evict" => Ok(Self::MempoolEviction),
+            "reorg" => Ok(Self::Reorg(1)),
+            other => other
+                .strip_prefix("reorg:")
+                .and_then(|depth| depth.parse().ok())
+                .filter(|&depth| depth > 0)
+                .map(Self::Reorg)
+                .ok_or_else(|| {
+                    SprayError::ParseError(format!(
+                        "Unknown fault '{s}' (expected restart, reorg:<depth> or evict)"
+                    ))
+                }),
+        }
+    }
+}
+
+/// Which fault to inject after which round
+///
+/// Faults are injected after every `interval`th round, cycling through
+/// `faults` in order.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FaultSchedule {
+    faults: Vec<Fault>,
+    interval: usize,
+}
+
+impl FaultSchedule {
+    /// Inject `faults` in turn, one every `interval` rounds
+    #[must_use]
+    pub fn new(faults: Vec<Fault>, interval: usize) -> Self {
+        Self {
+            faults,
+            interval: interval.max(1),
+        }
+    }
+
+    /// The fault to inject after round `round` (starting at 0), if any
+    #[must_use]
+    pub fn fault_for_round(&self, round: usize) -> Option<Fault> {
+        if self.faults.is_empty() || (round + 1) % self.interval != 0 {
+            return None;
+        }
+        let nth = (round + 1) / self.interval - 1;
+        Some(self.faults[nth % self.faults.len()])
+    }
+}
+
+/// A fault injected during a run
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct InjectedFault {
+    /// Round after which the fault was injected
+    pub round: usize,
+    /// The fault
+    pub fault: Fault,
+    /// Error if injecting the fault failed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Results of a soak run
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SoakReport {
+    /// Throughput, latencies, failures and resource usage
+    pub stress: StressReport,
+    /// Faults injected, in order
+    pub faults: Vec<InjectedFault>,
+    /// Spends broadcast again after losing their place
+    pub rebroadcasts: usize,
+    /// Spends still unconfirmed after recovery
+    pub unconfirmed: Vec<String>,
+}
+
+impl SoakReport {
+    /// Whether every instance was redeemed and every spend confirmed
+    #[must_use]
+    pub fn passed(&self) -> bool {
+        self.stress.succeeded() == self.stress.count && self.unconfirmed.is_empty()
+    }
+}
@@ -0,0 +1,371 @@
+//! Source-mapped failures
+//!
+//! When a spend fails, the bit machine stops at a Simplicity node, e.g.
+//! `%41 jet_eq_256 ... -> failed`, which says little about the contract it
+//! was compiled from. [`SourceMap`] maps the failing node of a [`Trace`]
+//! back to the SimplicityHL source and renders an annotated snippet:
+//!
+//! ```text
+//! error: jet_eq_256 failed
+//!   --> htlc.simf:17:21 (in fn main)
+//!    |
+//! 17 |             assert!(jet::eq_256(sha2(preimage), param::HASH));
+//!    |                     ^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^ jet_eq_256 failed
+//! ```
+//!
+//! Simplicity programs carry no source positions, so nodes are mapped by
+//! what they do: a failing jet to the calls of that jet, `jet_verify` also
+//! to `assert!`s, and an execution stopped by an assertion or `fail` node to
+//! `unwrap`s and `panic!`s. If the source has several candidates, each is
+//! shown. Lines spliced in by `include` directives are reported in the file
+//! they came from (see [`crate::include`]).
+//!
+//! # Example
+//!
+//! ```
+//! use spray::sourcemap::SourceMap;
+//! use spray::trace::{Trace, TraceStep};
+//!
+//! let source = "fn main() {\n    assert!(jet::eq_32(witness::X, 2));\n}\n";
+//! let map = SourceMap::new(source, "main.simf");
+//!
+//! let trace = Trace {
+//!     steps: vec![TraceStep {
+//!         index: 7,
+//!         op: "jet_eq_32".into(),
+//!         input: None,
+//!         output: None,
+//!         failed: true,
+//!     }],
+//!     error: Some("Jet failed".into()),
+//! };
+//! let site = map.locate(trace.failure().unwrap())[0];
+//! assert_eq!((site.line, site.column), (2, 13));
+//! assert_eq!(site.function.as_deref(), Some("main"));
+//! assert!(map.render(&trace).unwrap().contains(" --> main.simf:2:13 (in fn main)"));
+//! ```
+
+use crate::error::SprayError;
+use crate::include;
+use crate::trace::{Trace, TraceStep};
+use std::fmt::Write;
+use std::path::{Path, PathBuf};
+
+/// What a call site in the source does
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SiteKind {
+    /// A jet call, e.g. `jet::eq_32(...)`, by jet name
+    Jet(String),
+    /// An `assert!(...)`
+    Assert,
+    /// An `unwrap`, `unwrap_left` or `unwrap_right`
+    Unwrap,
+    /// A `panic!()`
+    Panic,
+}
+
+/// A call site in the source a failing node may come from
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Site {
+    /// What the call does
+    pub kind: SiteKind,
+    /// File the call is in
+    pub file: PathBuf,
+    /// Line number, 1-based
+    pub line: usize,
+    /// Column of the start of the call, 1-based
+    pub column: usize,
+    /// Length of the call, in characters, up to its closing parenthesis if
+    /// that is on the same line
+    pub len: usize,
+    /// Function the call is in
+    pub function: Option<String>,
+    /// The source line
+    pub text: String,
+}
+
+/// Call sites of a SimplicityHL source
+#[derive(Debug, Clone, Default)]
+pub struct SourceMap {
+    sites: Vec<Site>,
+}
+
+impl SourceMap {
+    /// Index the call sites of `source`, read from `file`
+    #[must_use]
+    pub fn new(source: &str, file: impl AsRef<Path>) -> Self {
+        let file = file.as_ref();
+        let origins = (1..=source.lines().count())
+            .map(|line| (file.to_path_buf(), line))
+            .collect();
+        Self::from_expanded(&include::Expanded {
+            source: source.to_string(),
+            origins,
+        })
+    }
+
+    /// Index the call sites of the contract in `path`, with its includes
+    ///
+    /// # Errors
+    ///
+    /// Returns the errors of [`include::expand_mapped`].
+    pub fn load(path: &Path) -> Result<Self, SprayError> {
+        include::expand_mapped(path).map(|expanded| Self::from_expanded(&expanded))
+    }
+
+    /// Index the call sites of an expanded source, reporting each in the
+    /// file it came from
+    #[must_use]
+    pub fn from_expanded(expanded: &include::Expanded) -> Self {
+        let mut scanner = Scanner::default();
+        for (index, text) in expanded.source.lines().enumerate() {
+            let (file, line) = expanded
+                .origins
+                .get(index)
+                .cloned()
+                .unwrap_or_else(|| (PathBuf::new(), index + 1));
+            scanner.line(text, &file, line);
+        }
+        Self {
+            sites: scanner.sites,
+        }
+    }
+
+    /// Every call site, in source order
+    #[must_use]
+    pub fn sites(&self) -> &[Site] {
+        &self.sites
+    }
+
+    /// The call sites `step` may have been compiled from
+    #[must_use]
+    pub fn locate(&self, step: &TraceStep) -> Vec<&Site> {
+        let failed_jet = step.op.strip_prefix("jet_").filter(|_| step.failed);
+        self.sites
+            .iter()
+            .filter(|site| match (failed_jet, &site.kind) {
+                (Some(jet), SiteKind::Jet(name)) => name == jet,
+                (Some(jet), SiteKind::Assert) => jet == "verify",
+                (None, SiteKind::Unwrap | SiteKind::Panic) => true,
+                _ => false,
+            })
+            .collect()
+    }
+
+    /// The call sites of the jet `op`, e.g. `jet_eq_32`, counting
+    /// `assert!`s as calls of `jet_verify`
+    #[must_use]
+    pub fn calls(&self, op: &str) -> Vec<&Site> {
+        let Some(jet) = op.strip_prefix("jet_") else {
+            return Vec::new();
+        };
+        self.sites
+            .iter()
+            .filter(|site| match &site.kind {
+                SiteKind::Jet(name) => name == jet,
+                SiteKind::Assert => jet == "verify",
+                SiteKind::Unwrap | SiteKind::Panic => false,
+            })
+            .collect()
+    }
+
+    /// An annotated snippet of where the execution in `trace` failed, or
+    /// `None` if it succeeded or the failure maps to no call site
+    #[must_use]
+    pub fn render(&self, trace: &Trace) -> Option<String> {
+        let step = trace.failure()?;
+        let sites = self.locate(step);
+        if sites.is_empty() {
+            return None;
+        }
+        let label = if step.failed && step.op.starts_with("jet_") {
+            format!("{} failed", step.op)
+        } else {
+            "execution stopped here".to_string()
+        };
+
+        let mut out = match step.input.as_ref().filter(|_| step.failed) {
+            Some(input) => format!("error: {label} on {input}\n"),
+            None => format!("error: {label}\n"),
+        };
+        if sites.len() > 1 {
+            let _ = writeln!(
+                out,
+                "note: {} possible locations, the program does not say which",
+                sites.len()
+            );
+        }
+        let width = sites
+            .iter()
+            .map(|site| site.line.to_string().len())
+            .max()
+            .unwrap_or(1);
+        for site in sites {
+            let pad = " ".repeat(width);
+            let function = site
+                .function
+                .as_ref()
+                .map(|name| format!(" (in fn {name})"))
+                .unwrap_or_default();
+            let _ = writeln!(
+                out,
+                "{pad}--> {}:{}:{}{function}",
+                site.file.display(),
+                site.line,
+                site.column
+            );
+            let _ = writeln!(out, "{pad} |");
+            let _ = writeln!(out, "{:>width$} | {}", site.line, site.text);
+            let _ = writeln!(
+                out,
+                "{pad} | {}{} {label}",
+                " ".repeat(site.column - 1),
+                "^".repeat(site.len.max(1))
+            );
+        }
+        Some(out)
+    }
+}
+
+/// Finds call sites line by line, tracking comments and enclosing functions
+#[derive(Default)]
+struct Scanner {
+    sites: Vec<Site>,
+    /// Inside a `/* */` comment
+    in_comment: bool,
+    /// Brace depth at the start of the next line
+    depth: usize,
+    /// Function whose body is being scanned
+    function: Option<String>,
+    /// Function declared but whose body has not opened yet
+    pending: Option<String>,
+}
+
+impl Scanner {
+    fn line(&mut self, text: &str, file: &Path, line: usize) {
+        let code = self.mask(text);
+        let mut i = 0;
+        while i < code.len() {
+            let at_word = i == 0 || (!is_ident(code[i - 1]) && code[i - 1] != ':');
+            if at_word {
+                if let Some(name) = keyword(&code, i, "fn").and_then(|end| ident(&code, end)) {
+                    self.pending = Some(name);
+                }
+                if let Some(kind) = site_kind(&code, i) {
+                    self.sites.push(Site {
+                        kind,
+                        file: file.to_path_buf(),
+                        line,
+                        column: i + 1,
+                        len: call_len(&code, i),
+                        function: self.function.clone(),
+                        text: text.to_string(),
+                    });
+                }
+            }
+            match code[i] {
+                '{' => {
+                    if self.depth == 0 {
+                        self.function = self.pending.take();
+                    }
+                    self.depth += 1;
+                }
+                '}' => {
+                    self.depth = self.depth.saturating_sub(1);
+                    if self.depth == 0 {
+                        self.function = None;
+                    }
+                }
+                _ => {}
+            }
+            i += 1;
+        }
+    }
+
+    /// The characters of `text` with comments blanked out, so columns are
+    /// kept
+    fn mask(&mut self, text: &str) -> Vec<char> {
+        let mut code: Vec<char> = text.chars().collect();
+        let mut i = 0;
+        while i < code.len() {
+            let next = code.get(i + 1).copied();
+            if self.in_comment {
+                if code[i] == '*' && next == Some('/') {
+                    self.in_comment = false;
+                    code[i + 1] = ' ';
+                }
+                code[i] = ' ';
+            } else if code[i] == '/' && next == Some('/') {
+                code[i..].fill(' ');
+                break;
+            } else if code[i] == '/' && next == Some('*') {
+                self.in_comment = true;
+                code[i] = ' ';
+                code[i + 1] = ' ';
+                i += 1;
+            }
+            i += 1;
+        }
+        code
+    }
+}
+
+/// The kind of call starting at `i`, if any
+fn site_kind(code: &[char], i: usize) -> Option<SiteKind> {
+    if let Some(end) = keyword(code, i, "jet::") {
+        return ident(code, end).map(SiteKind::Jet);
+    }
+    if keyword(code, i, "assert!").is_some() {
+        return Some(SiteKind::Assert);
+    }
+    if keyword(code, i, "panic!").is_some() {
+        return Some(SiteKind::Panic);
+    }
+    match ident(code, i)?.as_str() {
+        "unwrap" | "unwrap_left" | "unwrap_right" => Some(SiteKind::Unwrap),
+        _ => None,
+    }
+}
+
+/// The index after `word` if it starts at `i` and is not the prefix of a
+/// longer identifier
+fn keyword(code: &[char], i: usize, word: &str) -> Option<usize> {
+    let end = i + word.chars().count();
+    let matches = code.get(i..end)?.iter().copied().eq(word.chars());
+    let whole = word.ends_with(['!', ':']) || !code.get(end).is_some_and(|c| is_ident(*c));
+    (matches && whole).then_some(end)
+}
+
+/// The identifier starting at `i`, skipping leading whitespace
+fn ident(code: &[char], i: usize) -> Option<String> {
+    let name: String = code
+        .get(i..)?
+        .iter()
+        .skip_while(|c| c.is_whitespace())
+        .take_while(|c| is_ident(**c))
+        .collect();
+    (!name.is_empty()).then_some(name)
+}
+
+/// Length of the call starting at `i`: up to its closing parenthesis, or the
+/// end of the line if the call continues on the next
+fn call_len(code: &[char], i: usize) -> usize {
+    let mut depth = 0;
+    for (offset, c) in code[i..].iter().enumerate() {
+        match c {
+            '(' => depth += 1,
+            ')' if depth == 1 => return offset + 1,
+            ')' => depth -= 1,
+            _ => {}
+        }
+    }
+    let end = code
+        .iter()
+        .rposition(|c| !c.is_whitespace())
+        .map_or(i, |end| end + 1);
+    end.saturating_sub(i)
+}
+
+const fn is_ident(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '_'
+}
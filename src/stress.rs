@@ -0,0 +1,228 @@
+//! Throughput and stress testing
+//!
+//! A stress run deploys and redeems many instances of one contract against a
+//! regtest daemon. Instances run in rounds of `concurrency`: all instances of
+//! a round are funded in parallel, a block confirms the funding, and then all
+//! of them are spent in parallel. [`StressReport`] collects per-instance
+//! latencies, failures by stage and samples of the daemon's memory and
+//! mempool usage.
+//!
+//! # Example
+//!
+//! ```
+//! use spray::stress::{Instance, StressReport};
+//! use spray::FailureStage;
+//! use std::time::Duration;
+//!
+//! let mut report = StressReport::new(3, 2);
+//! report.add(Instance::succeeded(Duration::from_millis(10), Duration::from_millis(30)));
+//! report.add(Instance::succeeded(Duration::from_millis(20), Duration::from_millis(50)));
+//! report.add(Instance::failed(FailureStage::NodeRejection, "rejected"));
+//! report.elapsed = Duration::from_secs(2);
+//!
+//! assert_eq!(report.succeeded(), 2);
+//! assert!((report.throughput() - 1.0).abs() < f64::EPSILON);
+//! assert!((report.failure_rate() - 1.0 / 3.0).abs() < 1e-9);
+//! assert_eq!(report.redeem_percentile(50), Some(Duration::from_millis(30)));
+//! ```
+
+use crate::error::SprayError;
+use crate::test::FailureStage;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::time::Duration;
+
+/// Default number of instances deployed and redeemed
+pub const DEFAULT_COUNT: usize = 100;
+
+/// Default number of instances run in parallel
+pub const DEFAULT_CONCURRENCY: usize = 4;
+
+/// Outcome of deploying and redeeming one instance
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Instance {
+    /// Time to fund the contract
+    pub deploy: Option<Duration>,
+    /// Time to build, satisfy and broadcast the spend
+    pub redeem: Option<Duration>,
+    /// Stage and error of a failed instance
+    pub failure: Option<(FailureStage, String)>,
+    /// Spending transaction of a redeemed instance
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub txid: Option<String>,
+}
+
+impl Instance {
+    /// An instance that was deployed and redeemed
+    #[must_use]
+    pub const fn succeeded(deploy: Duration, redeem: Duration) -> Self {
+        Self {
+            deploy: Some(deploy),
+            redeem: Some(redeem),
+            failure: None,
+            txid: None,
+        }
+    }
+
+    /// Record the spending transaction
+    #[must_use]
+    pub fn with_txid(mut self, txid: impl Into<String>) -> Self {
+        self.txid = Some(txid.into());
+        self
+    }
+
+    /// An instance that failed at `stage`
+    #[must_use]
+    pub fn failed(stage: FailureStage, error: impl Into<String>) -> Self {
+        Self {
+            deploy: None,
+            redeem: None,
+            failure: Some((stage, error.into())),
+            txid: None,
+        }
+    }
+}
+
+/// Daemon resource usage at one point of a run
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ResourceSample {
+    /// When the sample was taken, e.g. `round 3 spent`
+    pub label: String,
+    /// Transactions in the mempool
+    pub mempool_txs: u64,
+    /// Memory used by the mempool, in bytes
+    pub mempool_usage: u64,
+    /// Locked memory in use by the daemon, in bytes
+    pub memory_used: u64,
+}
+
+impl ResourceSample {
+    /// Build a sample from `getmemoryinfo` and `getmempoolinfo` responses
+    #[must_use]
+    pub fn from_rpc(label: &str, memory_info: &Value, mempool_info: &Value) -> Self {
+        Self {
+            label: label.to_string(),
+            mempool_txs: mempool_info["size"].as_u64().unwrap_or(0),
+            mempool_usage: mempool_info["usage"].as_u64().unwrap_or(0),
+            memory_used: memory_info["locked"]["used"].as_u64().unwrap_or(0),
+        }
+    }
+}
+
+/// Results of a stress run
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct StressReport {
+    /// Number of instances
+    pub count: usize,
+    /// Instances run in parallel
+    pub concurrency: usize,
+    /// Wall-clock time of the whole run
+    pub elapsed: Duration,
+    /// Outcome of every instance
+    pub instances: Vec<Instance>,
+    /// Daemon resource samples, in the order taken
+    pub resources: Vec<ResourceSample>,
+}
+
+impl StressReport {
+    /// An empty report for `count` instances run `concurrency` at a time
+    #[must_use]
+    pub const fn new(count: usize, concurrency: usize) -> Self {
+        Self {
+            count,
+            concurrency,
+            elapsed: Duration::ZERO,
+            instances: Vec::new(),
+            resources: Vec::new(),
+        }
+    }
+
+    /// Record the outcome of an instance
+    pub fn add(&mut self, instance: Instance) {
+        self.instances.push(instance);
+    }
+
+    /// Number of instances that were deployed and redeemed
+    #[must_use]
+    pub fn succeeded(&self) -> usize {
+        self.instances
+            .iter()
+            .filter(|i| i.failure.is_none())
+            .count()
+    }
+
+    /// Number of failed instances per stage
+    #[must_use]
+    pub fn failures(&self) -> BTreeMap<FailureStage, usize> {
+        let mut failures = BTreeMap::new();
+        for (stage, _) in self.instances.iter().filter_map(|i| i.failure.as_ref()) {
+            *failures.entry(*stage).or_default() += 1;
+        }
+        failures
+    }
+
+    /// Fraction of instances that failed
+    #[must_use]
+    #[allow(clippy::cast_precision_loss)]
+    pub fn failure_rate(&self) -> f64 {
+        if self.instances.is_empty() {
+            return 0.0;
+        }
+        (self.instances.len() - self.succeeded()) as f64 / self.instances.len() as f64
+    }
+
+    /// Redeemed instances per second
+    #[must_use]
+    #[allow(clippy::cast_precision_loss)]
+    pub fn throughput(&self) -> f64 {
+        if self.elapsed.is_zero() {
+            return 0.0;
+        }
+        self.succeeded() as f64 / self.elapsed.as_secs_f64()
+    }
+
+    /// The `p`th percentile of deploy latencies
+    #[must_use]
+    pub fn deploy_percentile(&self, p: usize) -> Option<Duration> {
+        percentile(self.instances.iter().filter_map(|i| i.deploy), p)
+    }
+
+    /// The `p`th percentile of redeem latencies
+    #[must_use]
+    pub fn redeem_percentile(&self, p: usize) -> Option<Duration> {
+        percentile(self.instances.iter().filter_map(|i| i.redeem), p)
+    }
+
+    /// Peak mempool memory usage across the samples, in bytes
+    #[must_use]
+    pub fn peak_mempool_usage(&self) -> u64 {
+        self.resources
+            .iter()
+            .map(|r| r.mempool_usage)
+            .max()
+            .unwrap_or(0)
+    }
+
+    /// Write the report as JSON
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if serialization or writing fails.
+    pub fn write_json(&self, path: &Path) -> Result<(), SprayError> {
+        std::fs::write(path, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+}
+
+/// Nearest-rank percentile of `latencies`
+fn percentile(latencies: impl Iterator<Item = Duration>, p: usize) -> Option<Duration> {
+    let mut sorted: Vec<Duration> = latencies.collect();
+    if sorted.is_empty() {
+        return None;
+    }
+    sorted.sort_unstable();
+    let rank = (p.min(100) * sorted.len()).div_ceil(100).max(1);
+    Some(sorted[rank - 1])
+}
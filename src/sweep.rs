@@ -0,0 +1,239 @@
+//! Sweeping several UTXOs of a contract in one transaction
+//!
+//! `spray migrate-funds` moves every UTXO of a contract to a new address in
+//! a single transaction, so that the move either happens as a whole or not
+//! at all. [`Sweep`] builds that transaction: one input per UTXO, one output
+//! per asset paying the sum of that asset to the destination, and a fee
+//! taken from the first UTXO's asset.
+//!
+//! Each input is satisfied on its own: the program is run with the witness a
+//! [`WitnessProvider`] gives for that input's sighash, which commits to the
+//! input's index. Contracts that check a signature can therefore be swept
+//! with any number of UTXOs, e.g. with a
+//! [`SigningWitness`](crate::witness::SigningWitness). When inputs take
+//! different spend paths, [`Sweep::finalize_each`] gives each input its own
+//! provider.
+//!
+//! # Example
+//!
+//! ```
+//! use musk::client::Utxo;
+//! use musk::elements::{confidential, AssetId, Script, Txid};
+//! use spray::sweep::Sweep;
+//! use std::str::FromStr;
+//!
+//! let program = musk::Program::from_source("fn main() {}")
+//!     .unwrap()
+//!     .instantiate(musk::Arguments::default())
+//!     .unwrap();
+//! let asset = AssetId::from_str(&"5a".repeat(32)).unwrap();
+//! let utxo = |vout, amount| Utxo {
+//!     txid: Txid::from_str(&"aa".repeat(32)).unwrap(),
+//!     vout,
+//!     amount,
+//!     script_pubkey: Script::new(),
+//!     asset: confidential::Asset::Explicit(asset),
+//! };
+//!
+//! let utxos = vec![utxo(0, 5_000), utxo(1, 7_000)];
+//! let sweep = Sweep::new(program, utxos, Script::new(), 500).unwrap();
+//! assert_eq!(sweep.outputs(), [(asset, 11_500)]);
+//! assert_eq!(sweep.transaction().input.len(), 2);
+//! ```
+
+use crate::confidential::explicit_output;
+use crate::error::SprayError;
+use crate::pset::SimplicityLeaf;
+use crate::witness::WitnessProvider;
+use musk::client::Utxo;
+use musk::elements::hashes::Hash;
+use musk::elements::{
+    confidential, AssetId, BlockHash, LockTime, OutPoint, Script, Sequence, Transaction, TxIn,
+    TxOut,
+};
+use musk::InstantiatedProgram;
+use simplicity::jet::elements::{ElementsEnv, ElementsUtxo};
+use std::sync::Arc;
+
+/// A transaction spending several UTXOs of one contract to one destination
+#[derive(Clone)]
+pub struct Sweep {
+    program: InstantiatedProgram,
+    utxos: Vec<Utxo>,
+    destination: Script,
+    fee: u64,
+    /// Amount paid to the destination, per asset, in order of first use
+    outputs: Vec<(AssetId, u64)>,
+    genesis_hash: BlockHash,
+}
+
+impl Sweep {
+    /// Sweep `utxos`, locked by `program`, to `destination`, paying `fee` in
+    /// the asset of the first UTXO
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if there are no UTXOs, a UTXO's asset is
+    /// confidential, or the first UTXO's asset does not cover the fee.
+    pub fn new(
+        program: InstantiatedProgram,
+        utxos: Vec<Utxo>,
+        destination: Script,
+        fee: u64,
+    ) -> Result<Self, SprayError> {
+        let mut outputs: Vec<(AssetId, u64)> = Vec::new();
+        for utxo in &utxos {
+            let confidential::Asset::Explicit(asset) = utxo.asset else {
+                return Err(SprayError::TestError(format!(
+                    "Non-explicit asset in UTXO {}:{}",
+                    utxo.txid, utxo.vout
+                )));
+            };
+            match outputs.iter_mut().find(|(id, _)| *id == asset) {
+                Some((_, amount)) => *amount += utxo.amount,
+                None => outputs.push((asset, utxo.amount)),
+            }
+        }
+        let Some((_, first)) = outputs.first_mut() else {
+            return Err(SprayError::TestError("No UTXOs to sweep".into()));
+        };
+        let total = *first;
+        *first = total.checked_sub(fee).ok_or_else(|| {
+            SprayError::InsufficientFunds(format!(
+                "Swept UTXOs hold {total}, less than the fee of {fee}"
+            ))
+        })?;
+
+        Ok(Self {
+            program,
+            utxos,
+            destination,
+            fee,
+            outputs,
+            genesis_hash: BlockHash::all_zeros(),
+        })
+    }
+
+    /// Set the genesis hash of the chain the sweep is for
+    #[must_use]
+    pub const fn genesis_hash(mut self, genesis_hash: BlockHash) -> Self {
+        self.genesis_hash = genesis_hash;
+        self
+    }
+
+    /// Amount paid to the destination per asset, after the fee; the output
+    /// of each asset is at the index of its entry
+    #[must_use]
+    pub fn outputs(&self) -> &[(AssetId, u64)] {
+        &self.outputs
+    }
+
+    /// The swept UTXOs, in input order
+    #[must_use]
+    pub fn utxos(&self) -> &[Utxo] {
+        &self.utxos
+    }
+
+    /// The unsigned sweep transaction
+    #[must_use]
+    pub fn transaction(&self) -> Transaction {
+        let mut output: Vec<TxOut> = self
+            .outputs
+            .iter()
+            .map(|(asset, amount)| explicit_output(self.destination.clone(), *amount, *asset))
+            .collect();
+        output.push(TxOut::new_fee(self.fee, self.outputs[0].0));
+        Transaction {
+            version: 2,
+            lock_time: LockTime::ZERO,
+            input: self
+                .utxos
+                .iter()
+                .map(|utxo| TxIn {
+                    previous_output: OutPoint::new(utxo.txid, utxo.vout),
+                    sequence: Sequence::MAX,
+                    ..TxIn::default()
+                })
+                .collect(),
+            output,
+        }
+    }
+
+    /// Satisfy the program for every input, each with the witness `witness`
+    /// gives for that input's sighash, and finalize the transaction
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a witness does not fit the program, or the
+    /// program fails for an input.
+    pub fn finalize(&self, witness: &impl WitnessProvider) -> Result<Transaction, SprayError> {
+        let witness: &dyn WitnessProvider = witness;
+        self.finalize_each(&vec![witness; self.utxos.len()])
+    }
+
+    /// Satisfy the program for every input with its own provider, in input
+    /// order, and finalize the transaction
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if there is not one provider per input, a witness
+    /// does not fit the program, or the program fails for an input.
+    pub fn finalize_each(
+        &self,
+        witnesses: &[&dyn WitnessProvider],
+    ) -> Result<Transaction, SprayError> {
+        if witnesses.len() != self.utxos.len() {
+            return Err(SprayError::TestError(format!(
+                "Expected {} witness providers, one per input, got {}",
+                self.utxos.len(),
+                witnesses.len()
+            )));
+        }
+        let cmr = self.program.cmr();
+        let leaf = SimplicityLeaf::new(cmr.to_byte_array())?;
+        let unsigned = Arc::new(self.transaction());
+        let spent: Vec<ElementsUtxo> = self
+            .utxos
+            .iter()
+            .map(|utxo| ElementsUtxo {
+                script_pubkey: utxo.script_pubkey.clone(),
+                asset: utxo.asset,
+                value: confidential::Value::Explicit(utxo.amount),
+            })
+            .collect();
+
+        let mut tx = (*unsigned).clone();
+        for (index, input) in tx.input.iter_mut().enumerate() {
+            let outpoint = input.previous_output;
+            #[allow(clippy::cast_possible_truncation)]
+            let env = ElementsEnv::new(
+                Arc::clone(&unsigned),
+                spent.clone(),
+                index as u32,
+                cmr,
+                leaf.control_block.clone(),
+                None,
+                self.genesis_hash,
+            );
+            let sighash = env.c_tx_env().sighash_all().to_byte_array();
+            let satisfied = self
+                .program
+                .inner()
+                .satisfy(witnesses[index].witness(sighash))
+                .map_err(|e| {
+                    SprayError::TestError(format!("Failed to satisfy program for {outpoint}: {e}"))
+                })?;
+            let pruned = satisfied.redeem().prune(&env).map_err(|e| {
+                SprayError::TestError(format!("Program failed for {outpoint}: {e}"))
+            })?;
+            let (program_bytes, witness_bytes) = pruned.to_vec_with_witness();
+            input.witness.script_witness = vec![
+                witness_bytes,
+                program_bytes,
+                leaf.script.to_bytes(),
+                leaf.control_block.serialize(),
+            ];
+        }
+        Ok(tx)
+    }
+}
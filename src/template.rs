@@ -0,0 +1,155 @@
+//! Contract templates for new projects
+//!
+//! `spray new <name> --template <template>` creates a project directory with
+//! a working contract, its arguments and witness, and a test manifest that
+//! runs it on regtest:
+//!
+//! ```text
+//! <name>/
+//!   contracts/<template>.simf
+//!   contracts/<template>.args.json
+//!   contracts/<template>.wit.json
+//!   spray-tests.toml
+//! ```
+//!
+//! Signatures in the witness files are made per spend with test keys (see
+//! [`crate::witness::SigningWitness`]), so `spray test --manifest
+//! spray-tests.toml` passes right away.
+//!
+//! # Example
+//!
+//! ```
+//! use spray::template::Template;
+//!
+//! let template: Template = "htlc".parse().unwrap();
+//! let paths: Vec<_> = template.files().into_iter().map(|(path, _)| path).collect();
+//! assert!(paths.contains(&"contracts/htlc.simf".into()));
+//! assert!(paths.contains(&"spray-tests.toml".into()));
+//! ```
+
+use crate::error::SprayError;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+/// A contract template
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Template {
+    /// Pay to public key
+    #[default]
+    P2pk,
+    /// Hashed timelock contract
+    Htlc,
+    /// Vault with a delayed hot key and a cold key
+    Vault,
+    /// Covenant committing to the destination of the coins
+    Ctv,
+}
+
+impl Template {
+    /// Every template
+    pub const ALL: [Self; 4] = [Self::P2pk, Self::Htlc, Self::Vault, Self::Ctv];
+
+    /// Name of the template, also the file name of its contract
+    #[must_use]
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::P2pk => "p2pk",
+            Self::Htlc => "htlc",
+            Self::Vault => "vault",
+            Self::Ctv => "ctv",
+        }
+    }
+
+    /// Files of a project from this template, by path relative to the
+    /// project directory
+    #[must_use]
+    pub fn files(self) -> Vec<(PathBuf, &'static str)> {
+        let (source, args, witness, manifest) = match self {
+            Self::P2pk => (
+                include_str!("../templates/p2pk/p2pk.simf"),
+                include_str!("../templates/p2pk/p2pk.args.json"),
+                include_str!("../templates/p2pk/p2pk.wit.json"),
+                include_str!("../templates/p2pk/spray-tests.toml"),
+            ),
+            Self::Htlc => (
+                include_str!("../templates/htlc/htlc.simf"),
+                include_str!("../templates/htlc/htlc.args.json"),
+                include_str!("../templates/htlc/htlc.wit.json"),
+                include_str!("../templates/htlc/spray-tests.toml"),
+            ),
+            Self::Vault => (
+                include_str!("../templates/vault/vault.simf"),
+                include_str!("../templates/vault/vault.args.json"),
+                include_str!("../templates/vault/vault.wit.json"),
+                include_str!("../templates/vault/spray-tests.toml"),
+            ),
+            Self::Ctv => (
+                include_str!("../templates/ctv/ctv.simf"),
+                include_str!("../templates/ctv/ctv.args.json"),
+                include_str!("../templates/ctv/ctv.wit.json"),
+                include_str!("../templates/ctv/spray-tests.toml"),
+            ),
+        };
+        let contracts = Path::new("contracts");
+        let name = self.name();
+        vec![
+            (contracts.join(format!("{name}.simf")), source),
+            (contracts.join(format!("{name}.args.json")), args),
+            (contracts.join(format!("{name}.wit.json")), witness),
+            (PathBuf::from("spray-tests.toml"), manifest),
+        ]
+    }
+}
+
+impl fmt::Display for Template {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for Template {
+    type Err = SprayError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .into_iter()
+            .find(|template| template.name() == s)
+            .ok_or_else(|| {
+                let names: Vec<&str> = Self::ALL.into_iter().map(Self::name).collect();
+                SprayError::ParseError(format!(
+                    "Unknown template '{s}' (expected one of: {})",
+                    names.join(", ")
+                ))
+            })
+    }
+}
+
+/// Create a project from `template` in `dir`, returning the paths written
+///
+/// # Errors
+///
+/// Returns an error if `dir` exists and is not empty, or a file cannot be
+/// written.
+pub fn scaffold(dir: &Path, template: Template) -> Result<Vec<PathBuf>, SprayError> {
+    if dir
+        .read_dir()
+        .is_ok_and(|mut entries| entries.next().is_some())
+    {
+        return Err(SprayError::ConfigError(format!(
+            "{} already exists and is not empty",
+            dir.display()
+        )));
+    }
+
+    let mut written = Vec::new();
+    for (path, contents) in template.files() {
+        let path = dir.join(path);
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(&path, contents)?;
+        written.push(path);
+    }
+    Ok(written)
+}
@@ -3,13 +3,34 @@
 //! This module provides the [`TestCase`] builder for defining contract tests
 //! and the [`TestResult`] enum for test outcomes.
 
-use crate::client::ElementsClient;
-use crate::env::TestEnv;
+use crate::budget::Budget;
+use crate::confidential as blinding;
+use crate::destination::{Destination, Resolved};
 use crate::error::SprayError;
+use crate::fee::FeePolicy;
+use crate::funding::FundingShape;
+use crate::package;
+use crate::preset::Preset;
 use colored::Colorize;
-use musk::client::{NodeClient, Utxo};
-use musk::elements::{confidential, LockTime, Sequence};
+use musk::client::Utxo;
+use musk::elements::encode::serialize_hex;
+use musk::elements::{confidential, Address, BlockHash, LockTime, Script, Sequence, Transaction};
 use musk::{InstantiatedProgram, SpendBuilder, WitnessValues};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::fmt;
+
+#[cfg(feature = "daemon")]
+use {
+    crate::client::ElementsClient,
+    crate::env::TestEnv,
+    crate::fee::parse_estimatesmartfee,
+    crate::property::{Expectation, Gen, Property},
+    crate::witness::WitnessProvider,
+    elementsd::bitcoind::bitcoincore_rpc::RpcApi,
+    musk::client::NodeClient,
+    musk::Network,
+};
 
 /// Result of a test execution
 ///
@@ -24,23 +45,86 @@ use musk::{InstantiatedProgram, SpendBuilder, WitnessValues};
 ///     "0000000000000000000000000000000000000000000000000000000000000000"
 /// ).unwrap();
 ///
-/// let success = TestResult::Success { txid };
+/// let success = TestResult::success(txid, None);
 /// assert!(success.is_success());
 /// assert!(!success.is_failure());
+/// assert_eq!(success.txid(), Some(txid));
 ///
-/// let failure = TestResult::Failure { error: "test failed".into() };
+/// let failure = TestResult::failure("test failed".into());
 /// assert!(failure.is_failure());
 /// assert!(!failure.is_success());
+/// assert_eq!(failure.error(), Some("test failed"));
 /// ```
 #[derive(Debug, Clone)]
+#[non_exhaustive]
 pub enum TestResult {
-    /// Test passed, contains the spending transaction ID
-    Success { txid: musk::Txid },
+    /// Test passed, contains the spending transaction ID and, when known,
+    /// the spending transaction itself
+    #[non_exhaustive]
+    Success {
+        txid: musk::Txid,
+        spend: Option<SpendDetails>,
+    },
     /// Test failed, contains the error message
+    #[non_exhaustive]
     Failure { error: String },
 }
 
+/// The spending transaction of a passed test
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SpendDetails {
+    /// The finalized transaction, as broadcast
+    pub tx: Transaction,
+    /// Fee paid, in satoshis
+    pub fee: u64,
+}
+
+impl SpendDetails {
+    /// Weight of the transaction in weight units
+    #[must_use]
+    pub fn weight(&self) -> usize {
+        self.tx.weight()
+    }
+
+    /// Virtual size of the transaction in vbytes
+    #[must_use]
+    pub fn vsize(&self) -> usize {
+        self.weight().div_ceil(4)
+    }
+
+    /// Total size of the inputs' witness stacks in bytes, i.e. the
+    /// Simplicity witness, program and control block
+    #[must_use]
+    pub fn witness_size(&self) -> usize {
+        self.tx
+            .input
+            .iter()
+            .flat_map(|input| &input.witness.script_witness)
+            .map(Vec::len)
+            .sum()
+    }
+
+    /// Cost of the spent program against the budget of its witness stack
+    #[must_use]
+    pub fn budget(&self) -> Option<Budget> {
+        Budget::for_transaction(&self.tx)
+    }
+}
+
 impl TestResult {
+    /// A passed test, spent by `txid`, with the spending transaction if
+    /// known
+    #[must_use]
+    pub const fn success(txid: musk::Txid, spend: Option<SpendDetails>) -> Self {
+        Self::Success { txid, spend }
+    }
+
+    /// A failed test, with its error message
+    #[must_use]
+    pub const fn failure(error: String) -> Self {
+        Self::Failure { error }
+    }
+
     /// Returns `true` if this is a successful test result
     ///
     /// # Example
@@ -53,7 +137,7 @@ impl TestResult {
     /// let txid = Txid::from_str(
     ///     "0000000000000000000000000000000000000000000000000000000000000000"
     /// ).unwrap();
-    /// let result = TestResult::Success { txid };
+    /// let result = TestResult::success(txid, None);
     /// assert!(result.is_success());
     /// ```
     #[must_use]
@@ -68,16 +152,346 @@ impl TestResult {
     /// ```
     /// use spray::TestResult;
     ///
-    /// let result = TestResult::Failure { error: "assertion failed".into() };
+    /// let result = TestResult::failure("assertion failed".into());
     /// assert!(result.is_failure());
     /// ```
     #[must_use]
     pub const fn is_failure(&self) -> bool {
         matches!(self, Self::Failure { .. })
     }
+
+    /// A passed test that broadcast `spend`
+    #[must_use]
+    pub fn from_spend(spend: SpendDetails) -> Self {
+        Self::Success {
+            txid: spend.tx.txid(),
+            spend: Some(spend),
+        }
+    }
+
+    /// ID of the spending transaction of a passed test
+    #[must_use]
+    pub const fn txid(&self) -> Option<musk::Txid> {
+        match self {
+            Self::Success { txid, .. } => Some(*txid),
+            Self::Failure { .. } => None,
+        }
+    }
+
+    /// Error message of a failed test
+    #[must_use]
+    pub fn error(&self) -> Option<&str> {
+        match self {
+            Self::Success { .. } => None,
+            Self::Failure { error } => Some(error),
+        }
+    }
+
+    /// Details of the spending transaction of a passed test, when known
+    #[must_use]
+    pub const fn spend(&self) -> Option<&SpendDetails> {
+        match self {
+            Self::Success { spend, .. } => spend.as_ref(),
+            Self::Failure { .. } => None,
+        }
+    }
+
+    /// The spending transaction of a passed test
+    #[must_use]
+    pub fn transaction(&self) -> Option<&Transaction> {
+        self.spend().map(|spend| &spend.tx)
+    }
+
+    /// Fee paid by the spending transaction, in satoshis
+    #[must_use]
+    pub fn fee(&self) -> Option<u64> {
+        self.spend().map(|spend| spend.fee)
+    }
+
+    /// Virtual size of the spending transaction in vbytes
+    #[must_use]
+    pub fn vsize(&self) -> Option<usize> {
+        self.spend().map(SpendDetails::vsize)
+    }
+
+    /// Weight of the spending transaction in weight units
+    #[must_use]
+    pub fn weight(&self) -> Option<usize> {
+        self.spend().map(SpendDetails::weight)
+    }
+
+    /// Witness size of the spending transaction in bytes
+    #[must_use]
+    pub fn witness_size(&self) -> Option<usize> {
+        self.spend().map(SpendDetails::witness_size)
+    }
+}
+
+/// Stage of a test at which a failure occurred
+///
+/// Used to tell infrastructure problems (e.g. a dead daemon failing every
+/// funding step) apart from genuine contract failures.
+///
+/// # Example
+///
+/// ```
+/// use spray::test::FailureStage;
+///
+/// let stage = FailureStage::NodeRejection.refine("RPC request timed out");
+/// assert_eq!(stage, FailureStage::Timeout);
+/// assert_eq!(FailureStage::Funding.to_string(), "funding");
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+#[non_exhaustive]
+pub enum FailureStage {
+    /// The program failed to parse or instantiate
+    Compile,
+    /// Creating or confirming the funding UTXO failed
+    Funding,
+    /// Building the spend or satisfying the program with the witness failed
+    Satisfaction,
+    /// The node rejected the spending transaction
+    NodeRejection,
+    /// An operation timed out
+    Timeout,
+    /// A setup or teardown hook of the test runner failed
+    Hook,
+    /// The test is misconfigured, e.g. a destination that cannot be
+    /// resolved or a fee the funded amount does not cover
+    Config,
+}
+
+impl FailureStage {
+    /// Reclassify as [`FailureStage::Timeout`] if the error message indicates a timeout
+    #[must_use]
+    pub fn refine(self, error: &str) -> Self {
+        let lower = error.to_lowercase();
+        if lower.contains("timed out") || lower.contains("timeout") {
+            Self::Timeout
+        } else {
+            self
+        }
+    }
+
+    /// Whether failures at this stage come from the node, whose log may
+    /// tell why
+    #[must_use]
+    pub const fn is_node_side(self) -> bool {
+        matches!(self, Self::Funding | Self::NodeRejection | Self::Timeout)
+    }
+
+    /// Whether failures at this stage may pass on a retry: funding, node
+    /// rejections and timeouts depend on the node, while compile,
+    /// satisfaction, hook and config failures fail every attempt
+    #[must_use]
+    pub const fn is_retryable(self) -> bool {
+        matches!(self, Self::Funding | Self::NodeRejection | Self::Timeout)
+    }
+}
+
+impl fmt::Display for FailureStage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let s = match self {
+            Self::Compile => "compile",
+            Self::Funding => "funding",
+            Self::Satisfaction => "satisfaction",
+            Self::NodeRejection => "node rejection",
+            Self::Timeout => "timeout",
+            Self::Hook => "hook",
+            Self::Config => "config",
+        };
+        f.write_str(s)
+    }
+}
+
+/// Lock `amount` in `address`, the way tests fund their program
+///
+/// With `package`, the funding transaction is only built and signed, and is
+/// returned for submission together with the spend. Otherwise it is
+/// broadcast, through `shape` if given and through `send` if not.
+pub(crate) fn fund(
+    call: impl Fn(&str, &[Value]) -> Result<Value, SprayError>,
+    send: impl FnOnce() -> Result<musk::Txid, SprayError>,
+    address: &Address,
+    amount: u64,
+    shape: Option<&FundingShape>,
+    package: bool,
+) -> Result<(musk::Txid, Option<Transaction>), SprayError> {
+    if package {
+        let shape = shape
+            .cloned()
+            .unwrap_or_else(|| FundingShape::new().contract());
+        let (hex, _) = shape.build(call, address, amount)?;
+        let tx = package::decode_transaction(&hex)?;
+        Ok((tx.txid(), Some(tx)))
+    } else if let Some(shape) = shape {
+        Ok((shape.fund(call, address, amount)?.0, None))
+    } else {
+        Ok((send()?, None))
+    }
+}
+
+/// The explicit output of `tx` paying to `script`, as a UTXO
+pub(crate) fn find_utxo(
+    tx: &Transaction,
+    txid: musk::Txid,
+    script: &Script,
+) -> Result<Utxo, SprayError> {
+    tx.output
+        .iter()
+        .enumerate()
+        .find_map(|(vout, txout)| {
+            let confidential::Value::Explicit(amount) = txout.value else {
+                return None;
+            };
+            (txout.script_pubkey == *script).then(|| Utxo {
+                txid,
+                #[allow(clippy::cast_possible_truncation)]
+                vout: vout as u32,
+                amount,
+                script_pubkey: txout.script_pubkey.clone(),
+                asset: txout.asset,
+            })
+        })
+        .ok_or_else(|| SprayError::TestError("UTXO not found in transaction".into()))
+}
+
+/// A spend of a program's UTXO to a single destination
+///
+/// Shared by [`TestCase`] and manifest tests, so that both build, pay the
+/// fee of and finalize their spends the same way.
+pub(crate) struct Spend<'a> {
+    pub program: &'a InstantiatedProgram,
+    pub utxo: &'a Utxo,
+    pub genesis_hash: BlockHash,
+    pub lock_time: LockTime,
+    pub sequence: Sequence,
+    pub destination: &'a Resolved,
+    /// The destination as configured, if its output is blinded
+    pub blind: Option<&'a Destination>,
+    pub preset: &'a Preset,
+    pub fee_policy: FeePolicy,
+    /// Blind with [deterministic](crate::deterministic) factors
+    pub deterministic: bool,
+}
+
+impl Spend<'_> {
+    /// Build the spend, paying the fee of its policy, with the witness
+    /// `witness` gives for its sighash
+    ///
+    /// `estimate` is the node's fee rate estimate for a confirmation target,
+    /// if it has one.
+    pub(crate) fn build(
+        &self,
+        witness: impl Fn([u8; 32]) -> Result<WitnessValues, SprayError>,
+        estimate: impl FnOnce(u16) -> Option<f64>,
+    ) -> Result<SpendDetails, (FailureStage, SprayError)> {
+        let build = |fee| self.finalize(fee, &witness);
+        let fee = self.fee_policy.compute(
+            |conf_target| Some(self.preset.feerate(estimate(conf_target))),
+            build,
+        )?;
+        Ok(SpendDetails {
+            tx: build(fee)?,
+            fee,
+        })
+    }
+
+    /// Build and finalize the spending transaction paying `fee_amount`
+    fn finalize(
+        &self,
+        fee_amount: u64,
+        witness: &impl Fn([u8; 32]) -> Result<WitnessValues, SprayError>,
+    ) -> Result<Transaction, (FailureStage, SprayError)> {
+        let satisfaction = |e: SprayError| (FailureStage::Satisfaction, e);
+        let amount = self.utxo.amount;
+        let confidential::Asset::Explicit(asset) = self.utxo.asset else {
+            return Err((
+                FailureStage::Config,
+                SprayError::TestError("Non-explicit asset".into()),
+            ));
+        };
+        let output_amount = amount.checked_sub(fee_amount).ok_or_else(|| {
+            (
+                FailureStage::Config,
+                SprayError::TestError(format!(
+                    "UTXO amount {amount} sat does not cover the {fee_amount} sat fee"
+                )),
+            )
+        })?;
+
+        let mut builder = SpendBuilder::new(self.program.clone(), self.utxo.clone())
+            .genesis_hash(self.genesis_hash)
+            .lock_time(self.lock_time)
+            .sequence(self.sequence);
+
+        if let Some(configured) = self.blind {
+            let address = self.destination.address.as_ref().ok_or_else(|| {
+                satisfaction(SprayError::ConfigError(format!(
+                    "Destination {configured} has no address to blind to"
+                )))
+            })?;
+            let output = blinding::blind_last_output(
+                address,
+                output_amount,
+                asset,
+                &[blinding::explicit_secrets(asset, amount)],
+                &[blinding::explicit_secrets(asset, fee_amount)],
+                self.deterministic,
+            )
+            .map_err(satisfaction)?;
+            builder.add_output(output);
+        } else {
+            self.preset
+                .check_dust(output_amount)
+                .map_err(satisfaction)?;
+            builder.add_output_simple(self.destination.script_pubkey.clone(), output_amount, asset);
+        }
+        builder.add_fee(fee_amount, asset);
+
+        // Compute sighash
+        let sighash = builder
+            .sighash_all()
+            .map_err(|e| satisfaction(SprayError::TestError(e.to_string())))?;
+
+        // Finalize the transaction
+        builder
+            .finalize(witness(sighash).map_err(satisfaction)?)
+            .map_err(|e| satisfaction(SprayError::SpendError(e)))
+    }
+}
+
+/// Broadcast `tx`, together with `funding_tx` as a package if the funding
+/// transaction was held back
+pub(crate) fn submit(
+    call: impl Fn(&str, &[Value]) -> Result<Value, SprayError>,
+    broadcast: impl FnOnce(&Transaction) -> Result<(), SprayError>,
+    funding_tx: Option<&Transaction>,
+    tx: &Transaction,
+) -> Result<(), (FailureStage, SprayError)> {
+    if let Some(funding_tx) = funding_tx {
+        let method = package::submit_package(call, &serialize_hex(funding_tx), &serialize_hex(tx))
+            .map_err(|e| {
+                (
+                    FailureStage::NodeRejection,
+                    SprayError::TestError(format!("Failed to submit package: {e}")),
+                )
+            })?;
+        crate::status!("  {} {method}", "Package submitted via".dimmed());
+    } else {
+        broadcast(tx).map_err(|e| {
+            (
+                FailureStage::NodeRejection,
+                SprayError::TestError(format!("Failed to broadcast: {e}")),
+            )
+        })?;
+    }
+    Ok(())
 }
 
 /// A test case for a Simplicity program
+#[cfg(feature = "daemon")]
 pub struct TestCase<'env> {
     pub name: String,
     env: &'env TestEnv,
@@ -85,9 +499,18 @@ pub struct TestCase<'env> {
     witness_fn: Box<dyn Fn([u8; 32]) -> WitnessValues + 'env>,
     lock_time: LockTime,
     sequence: Sequence,
+    destination: Destination,
+    blind_destination: bool,
+    fee_policy: Option<FeePolicy>,
+    preset: Preset,
+    funding_shape: Option<FundingShape>,
+    package: bool,
     funding_txid: Option<musk::Txid>,
+    funding_tx: Option<Transaction>,
+    retries: Option<usize>,
 }
 
+#[cfg(feature = "daemon")]
 impl<'env> TestCase<'env> {
     /// Create a new test case
     pub fn new(env: &'env TestEnv, program: InstantiatedProgram) -> Self {
@@ -98,10 +521,50 @@ impl<'env> TestCase<'env> {
             witness_fn: Box::new(|_| WitnessValues::default()),
             lock_time: LockTime::ZERO,
             sequence: Sequence::MAX,
+            destination: Destination::Wallet,
+            blind_destination: false,
+            fee_policy: None,
+            preset: Preset::builtin(Network::Regtest),
+            funding_shape: None,
+            package: false,
             funding_txid: None,
+            funding_tx: None,
+            retries: None,
         }
     }
 
+    /// Check a property over randomized instantiations of `program`
+    ///
+    /// For each case, `generator` draws the arguments, the witness and
+    /// whether the spend should be accepted; see [`Property`] and
+    /// [`crate::property`].
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// use rand::Rng;
+    /// use spray::property::Expectation;
+    ///
+    /// let report = TestCase::property(&env, &program, |rng| {
+    ///     let amount: u32 = rng.gen_range(0..2_000);
+    ///     let expectation = if amount <= 1_000 { Expectation::Accept } else { Expectation::Reject };
+    ///     (arguments_with(amount), WitnessValues::default(), expectation)
+    /// })
+    /// .cases(50)
+    /// .run()?;
+    /// assert!(report.passed(), "{report}");
+    /// ```
+    pub const fn property<F>(
+        env: &'env TestEnv,
+        program: &'env musk::Program,
+        generator: F,
+    ) -> Property<'env, F>
+    where
+        F: Fn(&mut Gen) -> (musk::Arguments, WitnessValues, Expectation),
+    {
+        Property::new(env, program, generator)
+    }
+
     /// Set the test name
     #[must_use]
     pub fn name(mut self, name: &str) -> Self {
@@ -119,6 +582,16 @@ impl<'env> TestCase<'env> {
         self
     }
 
+    /// Set the witness from a [`WitnessProvider`]
+    #[must_use]
+    pub fn witness_provider<P>(mut self, provider: P) -> Self
+    where
+        P: WitnessProvider + 'env,
+    {
+        self.witness_fn = Box::new(move |sighash| provider.witness(sighash));
+        self
+    }
+
     /// Set the lock time
     #[must_use]
     pub const fn lock_time(mut self, lock_time: LockTime) -> Self {
@@ -133,6 +606,96 @@ impl<'env> TestCase<'env> {
         self
     }
 
+    /// Set where the spend pays to
+    ///
+    /// Defaults to a fresh address from the node's wallet. A fixed address or
+    /// descriptor gives the spend a known output script, e.g. for covenants
+    /// that constrain their outputs.
+    #[must_use]
+    pub fn destination(mut self, destination: Destination) -> Self {
+        self.destination = destination;
+        self
+    }
+
+    /// Blind the destination output
+    ///
+    /// The spend pays to a confidential address and the output carries range
+    /// and surjection proofs, as real Liquid payouts do. The destination
+    /// must be the wallet or a confidential address.
+    #[must_use]
+    pub const fn blind_destination(mut self, blind: bool) -> Self {
+        self.blind_destination = blind;
+        self
+    }
+
+    /// Set the fee policy of the spending transaction
+    ///
+    /// Defaults to a fixed fee of the preset's [`fee`](Preset::fee).
+    /// Rate-based policies are applied to the size of the finalized,
+    /// witness-included transaction.
+    #[must_use]
+    pub const fn fee_policy(mut self, policy: FeePolicy) -> Self {
+        self.fee_policy = Some(policy);
+        self
+    }
+
+    /// Set the preset giving the funding amount, default fee, fee rate floor
+    /// and dust limit
+    ///
+    /// Defaults to the built-in regtest preset.
+    #[must_use]
+    pub fn preset(mut self, preset: Preset) -> Self {
+        self.preset = preset;
+        self
+    }
+
+    /// Fund the program with a transaction of the given shape
+    ///
+    /// By default the program is funded with `sendtoaddress`, which leaves
+    /// the number and order of outputs to the wallet.
+    #[must_use]
+    pub fn funding_shape(mut self, shape: FundingShape) -> Self {
+        self.funding_shape = Some(shape);
+        self
+    }
+
+    /// Submit the funding transaction and the spend together as a package
+    ///
+    /// The funding transaction is signed but not broadcast by
+    /// [`create_utxo`](Self::create_utxo); the spend is then built on top of
+    /// it while unconfirmed, and both are submitted with `submitpackage`, or
+    /// one after the other where the node does not support it. Use this for
+    /// contracts designed around zero-conf chained spends.
+    #[must_use]
+    pub const fn package(mut self, package: bool) -> Self {
+        self.package = package;
+        self
+    }
+
+    /// Set how often the test is re-funded and re-run after a failure,
+    /// overriding [`TestRunner::retries`](crate::runner::TestRunner::retries)
+    #[must_use]
+    pub const fn retries(mut self, retries: usize) -> Self {
+        self.retries = Some(retries);
+        self
+    }
+
+    /// The test's own retry count, if set with [`retries`](Self::retries)
+    #[must_use]
+    pub const fn retry_limit(&self) -> Option<usize> {
+        self.retries
+    }
+
+    /// Whether the funding transaction is submitted together with the spend
+    ///
+    /// No block must be mined between [`create_utxo`](Self::create_utxo) and
+    /// [`run`](Self::run) for such tests, since the funding transaction is not
+    /// broadcast until the spend is.
+    #[must_use]
+    pub const fn is_package(&self) -> bool {
+        self.package
+    }
+
     /// Create a UTXO for this test by funding the program address
     ///
     /// # Errors
@@ -140,20 +703,40 @@ impl<'env> TestCase<'env> {
     /// Returns an error if sending to the program address fails.
     pub fn create_utxo(&mut self) -> Result<(), SprayError> {
         let client = ElementsClient::new(self.env.daemon());
-        let address = self
-            .program
-            .address(&musk::elements::AddressParams::ELEMENTS);
+        let address = self.program.address(self.preset.address_params);
+        let amount = self.preset.funding_amount;
 
-        println!("  {} {address}", "Creating UTXO at:".dimmed());
+        crate::status!("  {} {address}", "Creating UTXO at:".dimmed());
 
-        // Send 1 BTC to the program address
-        let amount = 100_000_000; // 1 BTC in satoshis
-        let txid = client
-            .send_to_address(&address, amount)
-            .map_err(|e| SprayError::TestError(e.to_string()))?;
+        let call = |method: &str, params: &[serde_json::Value]| {
+            self.env
+                .daemon()
+                .client()
+                .call::<serde_json::Value>(method, params)
+                .map_err(|e| SprayError::RpcError(e.to_string()))
+        };
+
+        // Lock the preset's funding amount in the program address
+        let (txid, funding_tx) = fund(
+            call,
+            || {
+                if self.env.is_deterministic() {
+                    crate::deterministic::fund(call, &address, amount)
+                } else {
+                    client
+                        .send_to_address(&address, amount)
+                        .map_err(|e| SprayError::TestError(e.to_string()))
+                }
+            },
+            &address,
+            amount,
+            self.funding_shape.as_ref(),
+            self.package,
+        )?;
 
         self.funding_txid = Some(txid);
-        println!("  {} {txid}", "Funding txid:".dimmed());
+        self.funding_tx = funding_tx;
+        crate::status!("  {} {txid}", "Funding txid:".dimmed());
 
         Ok(())
     }
@@ -164,37 +747,18 @@ impl<'env> TestCase<'env> {
             .funding_txid
             .ok_or_else(|| SprayError::TestError("Test UTXO not created".into()))?;
 
-        let client = ElementsClient::new(self.env.daemon());
-        let tx = client
-            .get_transaction(&txid)
-            .map_err(|e| SprayError::TestError(e.to_string()))?;
+        let tx = match &self.funding_tx {
+            Some(tx) => tx.clone(),
+            None => ElementsClient::new(self.env.daemon())
+                .get_transaction(&txid)
+                .map_err(|e| SprayError::TestError(e.to_string()))?,
+        };
 
-        let address = self
+        let script = self
             .program
-            .address(&musk::elements::AddressParams::ELEMENTS);
-        let script = address.script_pubkey();
-
-        // Find the output that matches our script
-        for (vout, txout) in tx.output.iter().enumerate() {
-            if txout.script_pubkey == script {
-                let confidential::Value::Explicit(amount) = txout.value else {
-                    continue;
-                };
-
-                return Ok(Utxo {
-                    txid,
-                    #[allow(clippy::cast_possible_truncation)]
-                    vout: vout as u32,
-                    amount,
-                    script_pubkey: txout.script_pubkey.clone(),
-                    asset: txout.asset,
-                });
-            }
-        }
-
-        Err(SprayError::TestError(
-            "UTXO not found in transaction".into(),
-        ))
+            .address(self.preset.address_params)
+            .script_pubkey();
+        find_utxo(&tx, txid, &script)
     }
 
     /// Run the test
@@ -204,50 +768,79 @@ impl<'env> TestCase<'env> {
     /// Returns an error if the UTXO cannot be retrieved, the transaction
     /// cannot be built, or broadcasting fails.
     pub fn run(self) -> Result<TestResult, SprayError> {
+        self.run_staged().map_err(|(_, e)| e)
+    }
+
+    /// Run the test, tagging any error with the stage at which it occurred
+    ///
+    /// # Errors
+    ///
+    /// Returns the failing [`FailureStage`] together with the error if the
+    /// UTXO cannot be retrieved, the transaction cannot be built, or
+    /// broadcasting fails.
+    pub fn run_staged(self) -> Result<TestResult, (FailureStage, SprayError)> {
+        self.spend()
+    }
+
+    /// Spend the test UTXO, leaving the test case to be funded and run again
+    pub(crate) fn spend(&self) -> Result<TestResult, (FailureStage, SprayError)> {
         let client = ElementsClient::new(self.env.daemon());
 
         // Get the UTXO
-        let utxo = self.get_utxo()?;
-
-        // Get the asset
-        let confidential::Asset::Explicit(asset) = utxo.asset else {
-            return Err(SprayError::TestError("Non-explicit asset".into()));
+        let utxo = self.get_utxo().map_err(|e| (FailureStage::Funding, e))?;
+
+        let call = |method: &str, params: &[serde_json::Value]| {
+            self.env
+                .daemon()
+                .client()
+                .call::<serde_json::Value>(method, params)
+                .map_err(|e| SprayError::RpcError(e.to_string()))
         };
-
-        // Build the spending transaction
-        let mut builder = SpendBuilder::new(self.program.clone(), utxo)
-            .genesis_hash(self.env.genesis_hash())
-            .lock_time(self.lock_time)
-            .sequence(self.sequence);
-
-        // Add outputs
-        let destination = client
-            .get_new_address()
-            .map_err(|e| SprayError::TestError(e.to_string()))?;
-        let output_amount = 99_997_000; // Leave room for fee
-        let fee_amount = 3_000;
-
-        builder.add_output_simple(destination.script_pubkey(), output_amount, asset);
-        builder.add_fee(fee_amount, asset);
-
-        // Compute sighash
-        let sighash = builder
-            .sighash_all()
-            .map_err(|e| SprayError::TestError(e.to_string()))?;
-
-        // Generate witness values
-        let witness_values = (self.witness_fn)(sighash);
-
-        // Finalize the transaction
-        let tx = builder
-            .finalize(witness_values)
-            .map_err(|e| SprayError::TestError(e.to_string()))?;
-
-        // Broadcast
-        let txid = client
-            .broadcast(&tx)
-            .map_err(|e| SprayError::TestError(format!("Failed to broadcast: {e}")))?;
-
-        Ok(TestResult::Success { txid })
+        let destination = self
+            .destination
+            .resolve(call, || {
+                client
+                    .get_new_address()
+                    .map_err(|e| SprayError::TestError(e.to_string()))
+            })
+            .map_err(|e| (FailureStage::Config, e))?;
+
+        // Build and finalize the spending transaction
+        let spend = Spend {
+            program: &self.program,
+            utxo: &utxo,
+            genesis_hash: self.env.genesis_hash(),
+            lock_time: self.lock_time,
+            sequence: self.sequence,
+            destination: &destination,
+            blind: self.blind_destination.then_some(&self.destination),
+            preset: &self.preset,
+            fee_policy: self.fee_policy.unwrap_or(FeePolicy::Fixed(self.preset.fee)),
+            deterministic: self.env.is_deterministic(),
+        }
+        .build(
+            |sighash| Ok((self.witness_fn)(sighash)),
+            |conf_target| {
+                call("estimatesmartfee", &[conf_target.into()])
+                    .ok()
+                    .as_ref()
+                    .and_then(parse_estimatesmartfee)
+            },
+        )?;
+
+        // Broadcast, together with the funding transaction for packages
+        submit(
+            call,
+            |tx| {
+                client
+                    .broadcast(tx)
+                    .map(|_| ())
+                    .map_err(|e| SprayError::TestError(e.to_string()))
+            },
+            self.funding_tx.as_ref(),
+            &spend.tx,
+        )?;
+
+        Ok(TestResult::from_spend(spend))
     }
 }
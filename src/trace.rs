@@ -0,0 +1,512 @@
+//! Execution traces
+//!
+//! When a redeem is rejected, the node only reports that the program failed.
+//! [`Tracer`] runs the satisfied program on the bit machine itself and
+//! records every node as it finishes executing: its combinator, the input
+//! and output of each jet call, and the error execution stopped with, so the
+//! point of failure can be read off the trace. Frames are rendered by their
+//! type (see [`format_value`]), e.g. `(1, 2)` for the input of `jet_eq_32`.
+//!
+//! The program is satisfied without pruning, so that the branch a failing
+//! spend takes is still present, and is executed against the transaction
+//! `spray verify` builds: the [`SpendContext`]'s UTXO paid back to its own
+//! script, less a fee output. Nodes are numbered as in
+//! `spray inspect --disasm`.
+//!
+//! Execution can also be paused: a [`Tracer`] with breakpoints on jets calls
+//! back as each call of such a jet finishes, with the steps so far, and the
+//! callback decides how execution goes on (see [`Resume`]). A
+//! [`Breakpoint`] on a source line pauses at the jets called on that line.
+//!
+//! # Example
+//!
+//! ```
+//! use spray::trace::{format_bits, format_value, TraceStep};
+//!
+//! assert_eq!(format_bits([true, false, true, false, false, false, false, true]), "0xa1");
+//! assert_eq!(format_bits([true, false]), "0b10");
+//! assert_eq!(format_bits([]), "()");
+//!
+//! let pair = "(u2, u2)".parse().unwrap();
+//! assert_eq!(format_value(Some(&pair), [false, true, true, false]), "(1, 2)");
+//! assert_eq!(format_value(None, [false, true, true, false]), "0b0110");
+//!
+//! let step = TraceStep {
+//!     index: 7,
+//!     op: "jet_eq_32".into(),
+//!     input: Some("0x0000000100000002".into()),
+//!     output: None,
+//!     failed: true,
+//! };
+//! assert_eq!(step.to_string(), "%7 jet_eq_32 0x0000000100000002 -> failed");
+//!
+//! let breakpoint: spray::trace::Breakpoint = "htlc.simf:17".parse().unwrap();
+//! assert_eq!(breakpoint.to_string(), "htlc.simf:17");
+//! ```
+
+use crate::confidential::explicit_output;
+use crate::disasm::op_name;
+use crate::error::SprayError;
+use crate::pset::SimplicityLeaf;
+use crate::sourcemap::{SiteKind, SourceMap};
+use crate::value::ValueType;
+use crate::verify::SpendContext;
+use musk::elements::hashes::Hash;
+use musk::elements::{confidential, OutPoint, Transaction, TxIn, TxOut};
+use musk::{InstantiatedProgram, WitnessValues};
+use serde::Serialize;
+use simplicity::bit_machine::{ExecTracker, FrameIter, NodeOutput};
+use simplicity::dag::{DagLike, InternalSharing};
+use simplicity::jet::elements::{ElementsEnv, ElementsUtxo};
+use simplicity::jet::Elements;
+use simplicity::node::Inner;
+use simplicity::types::Final;
+use simplicity::{BitMachine, Imr, RedeemNode, Tmr};
+use std::collections::HashMap;
+use std::fmt;
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::sync::Arc;
+
+/// A node of the program that finished executing
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct TraceStep {
+    /// Post-order index of the node, as in `spray inspect --disasm`
+    pub index: usize,
+    /// Combinator or jet, e.g. `case` or `jet_eq_32`
+    pub op: String,
+    /// Input of a jet call
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub input: Option<String>,
+    /// Output written by the node itself, e.g. by a jet or constant
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub output: Option<String>,
+    /// Whether the node failed, i.e. a jet's check did not hold
+    pub failed: bool,
+}
+
+impl fmt::Display for TraceStep {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "%{} {}", self.index, self.op)?;
+        if let Some(input) = &self.input {
+            write!(f, " {input}")?;
+        }
+        match (&self.output, self.failed) {
+            (_, true) => write!(f, " -> failed"),
+            (Some(output), false) => write!(f, " -> {output}"),
+            (None, false) => Ok(()),
+        }
+    }
+}
+
+/// Record of one execution of a program
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Trace {
+    /// Nodes in the order they finished executing
+    pub steps: Vec<TraceStep>,
+    /// Error execution stopped with, if it failed
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl Trace {
+    /// Whether the program executed successfully
+    #[must_use]
+    pub const fn is_success(&self) -> bool {
+        self.error.is_none()
+    }
+
+    /// The jet calls of the execution
+    pub fn jets(&self) -> impl Iterator<Item = &TraceStep> {
+        self.steps.iter().filter(|step| step.op.starts_with("jet_"))
+    }
+
+    /// Where execution failed: the failing jet, or else the last node that
+    /// finished before an assertion or `fail` node stopped execution
+    #[must_use]
+    pub fn failure(&self) -> Option<&TraceStep> {
+        if self.is_success() {
+            return None;
+        }
+        self.steps
+            .iter()
+            .rev()
+            .find(|step| step.failed)
+            .or_else(|| self.steps.last())
+    }
+}
+
+/// Where a traced execution pauses
+///
+/// Parsed from a jet name, e.g. `eq_32`, `jet::eq_32` or `jet_eq_32`, or a
+/// source line as `file:line`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Breakpoint {
+    /// Every call of a jet, by op name, e.g. `jet_eq_32`
+    Jet(String),
+    /// The jet calls on a line of a source file
+    ///
+    /// Programs carry no source positions, so this pauses at every call of
+    /// those jets, wherever in the program it comes from.
+    Line {
+        /// Source file, matched against the end of the mapped file's path
+        file: PathBuf,
+        /// Line number, 1-based
+        line: usize,
+    },
+}
+
+impl Breakpoint {
+    /// The ops execution pauses at, resolving a source line with `map`
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a source line calls no jet.
+    pub fn ops(&self, map: &SourceMap) -> Result<Vec<String>, SprayError> {
+        match self {
+            Self::Jet(op) => Ok(vec![op.clone()]),
+            Self::Line { file, line } => {
+                let mut ops: Vec<String> = Vec::new();
+                for site in map.sites() {
+                    if site.line != *line || !site.file.ends_with(file) {
+                        continue;
+                    }
+                    let op = match &site.kind {
+                        SiteKind::Jet(name) => format!("jet_{name}"),
+                        SiteKind::Assert => "jet_verify".to_string(),
+                        SiteKind::Unwrap | SiteKind::Panic => continue,
+                    };
+                    if !ops.contains(&op) {
+                        ops.push(op);
+                    }
+                }
+                if ops.is_empty() {
+                    return Err(SprayError::ConfigError(format!(
+                        "No jet is called on {self}"
+                    )));
+                }
+                Ok(ops)
+            }
+        }
+    }
+}
+
+impl FromStr for Breakpoint {
+    type Err = SprayError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let jet = s.strip_prefix("jet::").unwrap_or(s);
+        if let Some((file, line)) = jet.rsplit_once(':') {
+            return match line.parse() {
+                Ok(line) if line > 0 && !file.is_empty() => Ok(Self::Line {
+                    file: file.into(),
+                    line,
+                }),
+                _ => Err(SprayError::ParseError(format!(
+                    "Invalid breakpoint '{s}': expected a jet or file:line"
+                ))),
+            };
+        }
+        let jet = jet.strip_prefix("jet_").unwrap_or(jet);
+        if jet.is_empty() || !jet.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+            return Err(SprayError::ParseError(format!(
+                "Invalid breakpoint '{s}': expected a jet or file:line"
+            )));
+        }
+        Ok(Self::Jet(format!("jet_{jet}")))
+    }
+}
+
+impl fmt::Display for Breakpoint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Jet(op) => f.write_str(op),
+            Self::Line { file, line } => write!(f, "{}:{line}", file.display()),
+        }
+    }
+}
+
+/// How execution goes on after pausing
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Resume {
+    /// Run to the next breakpoint
+    Continue,
+    /// Pause again at the next recorded node
+    Step,
+    /// Run to the end, ignoring breakpoints
+    Finish,
+}
+
+/// Runs programs on the bit machine, recording a [`Trace`]
+#[derive(Debug, Clone, Default)]
+pub struct Tracer {
+    jets_only: bool,
+    breakpoints: Vec<String>,
+}
+
+impl Tracer {
+    /// A tracer recording every node
+    #[must_use]
+    pub const fn new() -> Self {
+        Self {
+            jets_only: false,
+            breakpoints: Vec::new(),
+        }
+    }
+
+    /// Record only jet calls, which keeps traces of large programs short
+    #[must_use]
+    pub const fn jets_only(mut self, jets_only: bool) -> Self {
+        self.jets_only = jets_only;
+        self
+    }
+
+    /// Pause whenever a call of the jet `op`, e.g. `jet_eq_32`, finishes
+    ///
+    /// Breakpoints only take effect with [`trace_with`](Self::trace_with)
+    /// and [`trace_spend_with`](Self::trace_spend_with).
+    #[must_use]
+    pub fn break_on(mut self, op: impl Into<String>) -> Self {
+        self.breakpoints.push(op.into());
+        self
+    }
+
+    /// Execute a satisfied program in `env`
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the program exceeds the bit machine's limits.
+    /// Failures during execution are recorded in the trace instead.
+    pub fn trace(
+        &self,
+        program: &RedeemNode<Elements>,
+        env: &ElementsEnv<Arc<Transaction>>,
+    ) -> Result<Trace, SprayError> {
+        self.trace_with(program, env, |_| Resume::Continue)
+    }
+
+    /// Execute a satisfied program in `env` like [`trace`](Self::trace),
+    /// pausing at breakpoints
+    ///
+    /// `on_break` is called with the steps so far, the last being the node
+    /// execution paused at, and returns how execution goes on.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the program exceeds the bit machine's limits.
+    pub fn trace_with(
+        &self,
+        program: &RedeemNode<Elements>,
+        env: &ElementsEnv<Arc<Transaction>>,
+        mut on_break: impl FnMut(&[TraceStep]) -> Resume,
+    ) -> Result<Trace, SprayError> {
+        let mut indices = HashMap::new();
+        for item in program.post_order_iter::<InternalSharing>() {
+            indices.entry(item.node.imr()).or_insert(item.index);
+        }
+        let mut recorder = Recorder {
+            indices,
+            jets_only: self.jets_only,
+            steps: Vec::new(),
+            types: HashMap::new(),
+            breakpoints: &self.breakpoints,
+            resume: Resume::Continue,
+            on_break: &mut on_break,
+        };
+
+        let mut machine = BitMachine::for_program(program)
+            .map_err(|e| SprayError::TestError(format!("Program exceeds limits: {e}")))?;
+        let error = machine
+            .exec_with_tracker(program, env, &mut recorder)
+            .err()
+            .map(|e| e.to_string());
+
+        Ok(Trace {
+            steps: recorder.steps,
+            error,
+        })
+    }
+
+    /// Satisfy `program` with `witness`, without pruning, and execute it in
+    /// `context`
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the witness does not fit the program's witness
+    /// types, the UTXO is not explicit or does not cover the fee, or the
+    /// program exceeds the bit machine's limits.
+    pub fn trace_spend(
+        &self,
+        program: &InstantiatedProgram,
+        witness: WitnessValues,
+        context: &SpendContext,
+    ) -> Result<Trace, SprayError> {
+        self.trace_spend_with(program, witness, context, |_| Resume::Continue)
+    }
+
+    /// Satisfy and execute `program` like
+    /// [`trace_spend`](Self::trace_spend), pausing at breakpoints as with
+    /// [`trace_with`](Self::trace_with)
+    ///
+    /// # Errors
+    ///
+    /// Returns the errors of [`trace_spend`](Self::trace_spend).
+    pub fn trace_spend_with(
+        &self,
+        program: &InstantiatedProgram,
+        witness: WitnessValues,
+        context: &SpendContext,
+        on_break: impl FnMut(&[TraceStep]) -> Resume,
+    ) -> Result<Trace, SprayError> {
+        let satisfied = program
+            .inner()
+            .satisfy(witness)
+            .map_err(|e| SprayError::TestError(format!("Failed to satisfy program: {e}")))?;
+        let env = environment(program, context)?;
+        self.trace_with(satisfied.redeem(), &env, on_break)
+    }
+}
+
+/// The transaction environment `program` is spent in with `context`
+///
+/// # Errors
+///
+/// Returns an error if the UTXO is not explicit or does not cover the fee.
+pub fn environment(
+    program: &InstantiatedProgram,
+    context: &SpendContext,
+) -> Result<ElementsEnv<Arc<Transaction>>, SprayError> {
+    let confidential::Asset::Explicit(asset) = context.utxo.asset else {
+        return Err(SprayError::TestError("Non-explicit asset in UTXO".into()));
+    };
+    let output_amount = context
+        .utxo
+        .amount
+        .checked_sub(context.fee)
+        .ok_or_else(|| SprayError::TestError("Insufficient funds for fee".into()))?;
+
+    let tx = Transaction {
+        version: 2,
+        lock_time: context.lock_time,
+        input: vec![TxIn {
+            previous_output: OutPoint::new(context.utxo.txid, context.utxo.vout),
+            sequence: context.sequence,
+            ..TxIn::default()
+        }],
+        output: vec![
+            explicit_output(context.utxo.script_pubkey.clone(), output_amount, asset),
+            TxOut::new_fee(context.fee, asset),
+        ],
+    };
+    let utxo = ElementsUtxo {
+        script_pubkey: context.utxo.script_pubkey.clone(),
+        asset: context.utxo.asset,
+        value: confidential::Value::Explicit(context.utxo.amount),
+    };
+    let cmr = program.cmr();
+    let leaf = SimplicityLeaf::new(cmr.to_byte_array())?;
+
+    Ok(ElementsEnv::new(
+        Arc::new(tx),
+        vec![utxo],
+        0,
+        cmr,
+        leaf.control_block,
+        None,
+        context.genesis_hash,
+    ))
+}
+
+/// Render a frame's bits: as hex if they fill whole bytes, else as binary
+#[must_use]
+pub fn format_bits(bits: impl IntoIterator<Item = bool>) -> String {
+    let bits: Vec<bool> = bits.into_iter().collect();
+    if bits.is_empty() {
+        return "()".to_string();
+    }
+    if bits.len() % 8 == 0 {
+        let hex: String = bits
+            .chunks(8)
+            .map(|byte| {
+                let value = byte
+                    .iter()
+                    .fold(0u8, |acc, &bit| (acc << 1) | u8::from(bit));
+                format!("{value:02x}")
+            })
+            .collect();
+        format!("0x{hex}")
+    } else {
+        let binary: String = bits
+            .iter()
+            .map(|&bit| if bit { '1' } else { '0' })
+            .collect();
+        format!("0b{binary}")
+    }
+}
+
+/// Render a frame's bits as a value of `ty`, e.g. `(1, 2)` for a pair of
+/// `u32`s, or with [`format_bits`] without a type or if they do not fit it
+#[must_use]
+pub fn format_value(ty: Option<&ValueType>, bits: impl IntoIterator<Item = bool>) -> String {
+    let bits: Vec<bool> = bits.into_iter().collect();
+    ty.and_then(|ty| ty.format_bits(&bits).ok())
+        .unwrap_or_else(|| format_bits(bits))
+}
+
+/// Execution tracker collecting [`TraceStep`]s and pausing at breakpoints
+struct Recorder<'a> {
+    indices: HashMap<Imr, usize>,
+    jets_only: bool,
+    steps: Vec<TraceStep>,
+    /// Program types converted so far, `None` where too large
+    types: HashMap<Tmr, Option<ValueType>>,
+    breakpoints: &'a [String],
+    /// How execution went on after the last pause
+    resume: Resume,
+    on_break: &'a mut dyn FnMut(&[TraceStep]) -> Resume,
+}
+
+impl ExecTracker<Elements> for Recorder<'_> {
+    fn visit_node(&mut self, node: &RedeemNode<Elements>, input: FrameIter, output: NodeOutput) {
+        let is_jet = matches!(node.inner(), Inner::Jet(_));
+        if self.jets_only && !is_jet {
+            return;
+        }
+        let arrow = node.arrow();
+        let (output, failed) = match output {
+            NodeOutput::NonTerminal => (None, false),
+            NodeOutput::JetFailed => (None, true),
+            NodeOutput::Success(frame) => (
+                Some(format_value(self.value_type(&arrow.target), frame)),
+                false,
+            ),
+        };
+        let input = is_jet.then(|| format_value(self.value_type(&arrow.source), input));
+        let op = op_name(node.inner());
+        let pause = match self.resume {
+            Resume::Continue => self.breakpoints.contains(&op),
+            Resume::Step => true,
+            Resume::Finish => false,
+        };
+        self.steps.push(TraceStep {
+            index: self.indices.get(&node.imr()).copied().unwrap_or_default(),
+            op,
+            input,
+            output,
+            failed,
+        });
+        if pause {
+            self.resume = (self.on_break)(&self.steps);
+        }
+    }
+}
+
+impl Recorder<'_> {
+    /// The value type of the program type `ty`
+    fn value_type(&mut self, ty: &Final) -> Option<&ValueType> {
+        self.types
+            .entry(ty.tmr())
+            .or_insert_with(|| ValueType::from_final(ty).ok())
+            .as_ref()
+    }
+}
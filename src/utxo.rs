@@ -0,0 +1,167 @@
+//! Self-contained UTXO descriptions for offline flows
+//!
+//! This module provides the [`UtxoDescription`] type, which captures
+//! everything needed to build a spend of a contract UTXO without access to a
+//! node: the prevout itself, the funding transaction, and the chain context
+//! (network and genesis hash) required for sighash computation.
+
+use crate::error::SprayError;
+use crate::hex;
+use musk::client::Utxo;
+use musk::elements::encode::{deserialize, serialize_hex};
+use musk::elements::hex::FromHex;
+use musk::elements::{confidential, BlockHash, Transaction, TxOut, Txid};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::str::FromStr;
+
+/// Format identifier written into every description
+pub const UTXO_DESCRIPTION_FORMAT: &str = "spray-utxo/1";
+
+/// Self-contained description of a contract UTXO
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct UtxoDescription {
+    /// Format identifier, see [`UTXO_DESCRIPTION_FORMAT`]
+    pub format: String,
+    /// Network name
+    pub network: String,
+    /// Genesis block hash of the chain the UTXO lives on
+    pub genesis_hash: String,
+    /// Funding transaction ID
+    pub txid: String,
+    /// Output index
+    pub vout: u32,
+    /// Explicit amount in satoshis, if the output is unblinded
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub amount: Option<u64>,
+    /// Explicit asset ID, if the output is unblinded
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub asset: Option<String>,
+    /// Output script (hex)
+    pub script_pubkey: String,
+    /// Consensus-serialized prevout (hex)
+    pub txout: String,
+    /// Consensus-serialized funding transaction (hex)
+    pub funding_tx: String,
+    /// Confirmations at export time, if known
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub confirmations: Option<i64>,
+}
+
+impl UtxoDescription {
+    /// Describe output `vout` of the funding transaction `tx`
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `vout` is out of range.
+    pub fn new(
+        network: &str,
+        genesis_hash: BlockHash,
+        tx: &Transaction,
+        vout: u32,
+        confirmations: Option<i64>,
+    ) -> Result<Self, SprayError> {
+        let output = tx.output.get(vout as usize).ok_or_else(|| {
+            SprayError::InvalidUtxoRef(format!("Vout {vout} not found in transaction"))
+        })?;
+
+        let amount = match output.value {
+            confidential::Value::Explicit(amount) => Some(amount),
+            _ => None,
+        };
+        let asset = match output.asset {
+            confidential::Asset::Explicit(asset) => Some(asset.to_string()),
+            _ => None,
+        };
+
+        Ok(Self {
+            format: UTXO_DESCRIPTION_FORMAT.to_string(),
+            network: network.to_string(),
+            genesis_hash: genesis_hash.to_string(),
+            txid: tx.txid().to_string(),
+            vout,
+            amount,
+            asset,
+            script_pubkey: hex::encode(output.script_pubkey.as_bytes()),
+            txout: serialize_hex(output),
+            funding_tx: serialize_hex(tx),
+            confirmations,
+        })
+    }
+
+    /// Load a description from a JSON file
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be read or parsed, or has an
+    /// unknown format identifier.
+    pub fn load(path: &Path) -> Result<Self, SprayError> {
+        let contents = std::fs::read_to_string(path)?;
+        let description: Self = serde_json::from_str(&contents)?;
+
+        if description.format != UTXO_DESCRIPTION_FORMAT {
+            return Err(SprayError::FileFormatError(format!(
+                "Unsupported UTXO description format: {}",
+                description.format
+            )));
+        }
+
+        Ok(description)
+    }
+
+    /// Write the description as pretty-printed JSON
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if serialization or writing the file fails.
+    pub fn save(&self, path: &Path) -> Result<(), SprayError> {
+        std::fs::write(path, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+
+    /// Decode the genesis block hash
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the stored hash is invalid.
+    pub fn genesis_hash(&self) -> Result<BlockHash, SprayError> {
+        BlockHash::from_str(&self.genesis_hash)
+            .map_err(|e| SprayError::ParseError(format!("Invalid genesis hash: {e}")))
+    }
+
+    /// Decode the prevout
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the stored prevout is not valid hex or not a
+    /// valid serialized output.
+    pub fn txout(&self) -> Result<TxOut, SprayError> {
+        let bytes = Vec::<u8>::from_hex(&self.txout)
+            .map_err(|e| SprayError::ParseError(format!("Invalid prevout hex: {e}")))?;
+        deserialize(&bytes).map_err(|e| SprayError::ParseError(format!("Invalid prevout: {e}")))
+    }
+
+    /// Convert into a musk [`Utxo`] ready for `SpendBuilder`
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the description is malformed or the output is
+    /// confidential.
+    pub fn to_utxo(&self) -> Result<Utxo, SprayError> {
+        let txid = Txid::from_str(&self.txid)
+            .map_err(|e| SprayError::ParseError(format!("Invalid txid: {e}")))?;
+        let txout = self.txout()?;
+
+        let confidential::Value::Explicit(amount) = txout.value else {
+            return Err(SprayError::TestError("Non-explicit value in UTXO".into()));
+        };
+
+        Ok(Utxo {
+            txid,
+            vout: self.vout,
+            amount,
+            script_pubkey: txout.script_pubkey,
+            asset: txout.asset,
+        })
+    }
+}
@@ -0,0 +1,553 @@
+//! Type-directed formatting of Simplicity values
+//!
+//! This module provides the [`ValueType`] type, parsed from SimplicityHL type
+//! syntax, which renders raw bit-level values the way they are declared:
+//! integers up to `u64` as decimal, `u128`/`u256` as hex, arrays and tuples
+//! elementwise, and sum types by their variant.
+//!
+//! Values are laid out as on the Simplicity bit machine: products are the
+//! concatenation of their components, and sums are a tag bit (`0` = left)
+//! followed by padding up to the wider variant and then the variant's value.
+//!
+//! Types are bounded by [`MAX_SIZE`], both in bits and in the number of
+//! components a value renders as, so that no type spray parses or converts
+//! from a program makes rendering a value or placeholder expensive. Program
+//! types convert with [`ValueType::from_final`], which is how execution
+//! traces render the frames of jets.
+//!
+//! # Example
+//!
+//! ```
+//! use spray::value::ValueType;
+//!
+//! let ty: ValueType = "(u32, [u8; 2])".parse().unwrap();
+//! assert_eq!(ty.bit_width(), 48);
+//! assert_eq!(
+//!     ty.format_hex("000000ff0102").unwrap(),
+//!     "(255, [1, 2])"
+//! );
+//! ```
+
+use crate::error::SprayError;
+use simplicity::types::{CompleteBound, Final};
+use std::fmt;
+use std::str::FromStr;
+
+/// Largest bit width of a type, and largest number of components a value of
+/// it renders as
+///
+/// Far above any value a transaction can carry.
+pub const MAX_SIZE: usize = 1 << 23;
+
+/// A Simplicity value type
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ValueType {
+    /// The unit type `()`
+    Unit,
+    /// `bool`
+    Bool,
+    /// An unsigned integer `uN` of the given bit width
+    UInt(u16),
+    /// A tuple `(A, B, ...)`
+    Tuple(Vec<ValueType>),
+    /// An array `[T; N]`
+    Array(Box<ValueType>, usize),
+    /// `Either<A, B>`
+    Either(Box<ValueType>, Box<ValueType>),
+    /// `Option<T>`
+    Option(Box<ValueType>),
+}
+
+impl ValueType {
+    /// The type of a program's values, e.g. a jet's input
+    ///
+    /// Programs only know units, sums and products: a sum of two units is
+    /// read as `u1`, a product of two equal integers as the integer twice
+    /// as wide, and a sum with a unit on the left as an `Option`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the type exceeds [`MAX_SIZE`].
+    pub fn from_final(ty: &Final) -> Result<Self, SprayError> {
+        let converted = Self::convert(ty);
+        converted.check_size()?;
+        Ok(converted)
+    }
+
+    fn convert(ty: &Final) -> Self {
+        match ty.bound() {
+            CompleteBound::Unit => Self::Unit,
+            CompleteBound::Sum(left, right) => match (Self::convert(left), Self::convert(right)) {
+                (Self::Unit, Self::Unit) => Self::UInt(1),
+                (Self::Unit, right) => Self::Option(Box::new(right)),
+                (left, right) => Self::Either(Box::new(left), Box::new(right)),
+            },
+            CompleteBound::Product(left, right) => {
+                match (Self::convert(left), Self::convert(right)) {
+                    (Self::UInt(l), Self::UInt(r)) if l == r && l < 256 => Self::UInt(l * 2),
+                    (left, right) => Self::Tuple(vec![left, right]),
+                }
+            }
+        }
+    }
+
+    /// Number of bits a value of this type occupies
+    ///
+    /// Saturates at `usize::MAX` for types too large to count, which only
+    /// types built by hand can be (see [`MAX_SIZE`]).
+    #[must_use]
+    pub fn bit_width(&self) -> usize {
+        self.size().map_or(usize::MAX, |(bits, _)| bits)
+    }
+
+    /// Bit width and number of rendered components, or `None` if either
+    /// overflows
+    fn size(&self) -> Option<(usize, usize)> {
+        Some(match self {
+            Self::Unit => (0, 1),
+            Self::Bool => (1, 1),
+            Self::UInt(bits) => (usize::from(*bits), 1),
+            Self::Tuple(items) => items.iter().try_fold((0, 1), |(bits, count), item| {
+                let (item_bits, item_count) = item.size()?;
+                Some((bits.checked_add(item_bits)?, count.checked_add(item_count)?))
+            })?,
+            Self::Array(item, len) => {
+                let (bits, count) = item.size()?;
+                (
+                    bits.checked_mul(*len)?,
+                    count.checked_mul(*len)?.checked_add(1)?,
+                )
+            }
+            Self::Either(left, right) => {
+                let (left_bits, left_count) = left.size()?;
+                let (right_bits, right_count) = right.size()?;
+                (
+                    left_bits.max(right_bits).checked_add(1)?,
+                    left_count.max(right_count).checked_add(1)?,
+                )
+            }
+            Self::Option(inner) => {
+                let (bits, count) = inner.size()?;
+                (bits.checked_add(1)?, count.checked_add(1)?)
+            }
+        })
+    }
+
+    /// Check that the type is within [`MAX_SIZE`]
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a value of the type has more than [`MAX_SIZE`]
+    /// bits or components.
+    pub fn check_size(&self) -> Result<(), SprayError> {
+        match self.size() {
+            Some((bits, count)) if bits <= MAX_SIZE && count <= MAX_SIZE => Ok(()),
+            _ => Err(SprayError::ParseError(format!(
+                "Type {self} is too large: values are limited to {MAX_SIZE} bits and components"
+            ))),
+        }
+    }
+
+    /// Render a value given as bits, most significant first
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the type exceeds [`MAX_SIZE`] or `bits` is shorter
+    /// than [`ValueType::bit_width`].
+    pub fn format_bits(&self, bits: &[bool]) -> Result<String, SprayError> {
+        self.check_size()?;
+        let width = self.bit_width();
+        if bits.len() < width {
+            return Err(SprayError::ParseError(format!(
+                "Value of type {self} needs {width} bits, got {}",
+                bits.len()
+            )));
+        }
+        let mut out = String::new();
+        self.render(&bits[..width], &mut out);
+        Ok(out)
+    }
+
+    /// Render a value given as hex, read from the most significant bit
+    ///
+    /// Bits beyond [`ValueType::bit_width`] are ignored.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `hex` is not valid hex or holds too few bits.
+    pub fn format_hex(&self, hex: &str) -> Result<String, SprayError> {
+        let hex = hex.trim_start_matches("0x");
+        if hex.len() % 2 != 0 {
+            return Err(SprayError::ParseError("Odd-length hex value".into()));
+        }
+        let bytes = (0..hex.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&hex[i..i + 2], 16))
+            .collect::<Result<Vec<u8>, _>>()
+            .map_err(|e| SprayError::ParseError(format!("Invalid hex value: {e}")))?;
+
+        let bits: Vec<bool> = bytes
+            .iter()
+            .flat_map(|byte| (0..8).rev().map(move |i| byte & (1 << i) != 0))
+            .collect();
+        self.format_bits(&bits)
+    }
+
+    /// Parse a command-line literal into a witness or argument value
+    ///
+    /// `bool` accepts `true`/`false`/`1`/`0`. Integers accept decimal or
+    /// `0x` hex; hex is zero-padded to the full width, and integers up to
+    /// `u64` become JSON numbers. Hex for any other type must have exactly
+    /// the type's width. Everything else is passed through as a string.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the literal is not valid for the type or does not
+    /// fit in it.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use serde_json::json;
+    /// use spray::value::ValueType;
+    ///
+    /// let u16: ValueType = "u16".parse().unwrap();
+    /// assert_eq!(u16.parse_literal("0xff").unwrap(), json!(255));
+    ///
+    /// let u128: ValueType = "u128".parse().unwrap();
+    /// assert_eq!(
+    ///     u128.parse_literal("1").unwrap(),
+    ///     json!(format!("0x{:032x}", 1))
+    /// );
+    /// assert!(u16.parse_literal("70000").is_err());
+    /// ```
+    pub fn parse_literal(&self, raw: &str) -> Result<serde_json::Value, SprayError> {
+        let invalid = |reason: &str| {
+            SprayError::ParseError(format!("Invalid {self} value '{raw}': {reason}"))
+        };
+
+        match self {
+            Self::Bool => match raw {
+                "true" | "1" => Ok(true.into()),
+                "false" | "0" => Ok(false.into()),
+                _ => Err(invalid("expected true or false")),
+            },
+            Self::UInt(bits) => {
+                let digits = usize::from(*bits).div_ceil(4);
+                let hex = if let Some(hex) = raw.strip_prefix("0x") {
+                    if hex.is_empty() || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+                        return Err(invalid("not hex"));
+                    }
+                    hex.trim_start_matches('0').to_lowercase()
+                } else {
+                    let value: u128 = raw.parse().map_err(|_| invalid("not a number"))?;
+                    format!("{value:x}").trim_start_matches('0').to_string()
+                };
+
+                // Bits below and including the most significant set bit
+                let significant_bits = hex.chars().next().map_or(0, |first| {
+                    let top = first.to_digit(16).unwrap_or(0);
+                    4 * (hex.len() - 1) + (u32::BITS - top.leading_zeros()) as usize
+                });
+                if significant_bits > usize::from(*bits) {
+                    return Err(invalid("does not fit"));
+                }
+
+                if *bits <= 64 {
+                    Ok(
+                        u64::from_str_radix(if hex.is_empty() { "0" } else { &hex }, 16)
+                            .map_err(|_| invalid("does not fit"))?
+                            .into(),
+                    )
+                } else {
+                    Ok(format!("0x{hex:0>digits$}").into())
+                }
+            }
+            _ => {
+                let width = self.bit_width();
+                if let Some(hex) = raw.strip_prefix("0x") {
+                    if width % 4 == 0 && hex.len() != width / 4 {
+                        return Err(invalid(&format!("expected {} hex digits", width / 4)));
+                    }
+                }
+                Ok(raw.into())
+            }
+        }
+    }
+
+    /// A placeholder literal of this type, for files to be filled in: zero,
+    /// `false`, `None` or `Left` throughout, with byte arrays in hex
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use spray::value::ValueType;
+    ///
+    /// let ty: ValueType = "(Option<u32>, [u8; 2], Either<bool, u8>)".parse().unwrap();
+    /// assert_eq!(ty.placeholder().unwrap(), "(None, 0x0000, Left(false))");
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the type exceeds [`MAX_SIZE`].
+    pub fn placeholder(&self) -> Result<String, SprayError> {
+        self.check_size()?;
+        Ok(self.render_placeholder())
+    }
+
+    /// [`placeholder`](Self::placeholder) of a type within [`MAX_SIZE`]
+    fn render_placeholder(&self) -> String {
+        match self {
+            Self::Unit => "()".to_string(),
+            Self::Bool => "false".to_string(),
+            Self::UInt(bits) if *bits <= 64 => "0".to_string(),
+            Self::UInt(bits) => format!("0x{}", "0".repeat(usize::from(*bits).div_ceil(4))),
+            Self::Tuple(items) => {
+                let items: Vec<String> = items.iter().map(Self::render_placeholder).collect();
+                if items.len() == 1 {
+                    format!("({},)", items[0])
+                } else {
+                    format!("({})", items.join(", "))
+                }
+            }
+            Self::Array(item, len) if **item == Self::UInt(8) => {
+                format!("0x{}", "00".repeat(*len))
+            }
+            Self::Array(item, len) => {
+                format!("[{}]", vec![item.render_placeholder(); *len].join(", "))
+            }
+            Self::Either(left, _) => format!("Left({})", left.render_placeholder()),
+            Self::Option(_) => "None".to_string(),
+        }
+    }
+
+    /// Render `bits`, which must be exactly [`ValueType::bit_width`] long
+    fn render(&self, bits: &[bool], out: &mut String) {
+        match self {
+            Self::Unit => out.push_str("()"),
+            Self::Bool => out.push_str(if bits[0] { "true" } else { "false" }),
+            Self::UInt(width) if *width <= 64 => {
+                let value = bits
+                    .iter()
+                    .fold(0u64, |acc, &bit| (acc << 1) | u64::from(bit));
+                out.push_str(&value.to_string());
+            }
+            Self::UInt(_) => {
+                out.push_str("0x");
+                for nibble in bits.chunks(4) {
+                    let value = nibble
+                        .iter()
+                        .fold(0u32, |acc, &bit| (acc << 1) | u32::from(bit));
+                    out.push(char::from_digit(value, 16).unwrap_or('?'));
+                }
+            }
+            Self::Tuple(items) => {
+                out.push('(');
+                let mut offset = 0;
+                for (i, item) in items.iter().enumerate() {
+                    if i > 0 {
+                        out.push_str(", ");
+                    }
+                    let width = item.bit_width();
+                    item.render(&bits[offset..offset + width], out);
+                    offset += width;
+                }
+                out.push(')');
+            }
+            Self::Array(item, len) => {
+                out.push('[');
+                let width = item.bit_width();
+                for i in 0..*len {
+                    if i > 0 {
+                        out.push_str(", ");
+                    }
+                    item.render(&bits[i * width..(i + 1) * width], out);
+                }
+                out.push(']');
+            }
+            Self::Either(left, right) => {
+                let (name, variant) = if bits[0] {
+                    ("Right", right)
+                } else {
+                    ("Left", left)
+                };
+                let start = bits.len() - variant.bit_width();
+                out.push_str(name);
+                out.push('(');
+                variant.render(&bits[start..], out);
+                out.push(')');
+            }
+            Self::Option(inner) => {
+                if bits[0] {
+                    out.push_str("Some(");
+                    inner.render(&bits[1..], out);
+                    out.push(')');
+                } else {
+                    out.push_str("None");
+                }
+            }
+        }
+    }
+}
+
+impl fmt::Display for ValueType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Unit => f.write_str("()"),
+            Self::Bool => f.write_str("bool"),
+            Self::UInt(bits) => write!(f, "u{bits}"),
+            Self::Tuple(items) => {
+                f.write_str("(")?;
+                for (i, item) in items.iter().enumerate() {
+                    if i > 0 {
+                        f.write_str(", ")?;
+                    }
+                    write!(f, "{item}")?;
+                }
+                if items.len() == 1 {
+                    f.write_str(",")?;
+                }
+                f.write_str(")")
+            }
+            Self::Array(item, len) => write!(f, "[{item}; {len}]"),
+            Self::Either(left, right) => write!(f, "Either<{left}, {right}>"),
+            Self::Option(inner) => write!(f, "Option<{inner}>"),
+        }
+    }
+}
+
+impl FromStr for ValueType {
+    type Err = SprayError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parser = Parser { input: s, pos: 0 };
+        let ty = parser.parse_type()?;
+        parser.skip_whitespace();
+        if parser.pos != s.len() {
+            return Err(parser.error("unexpected trailing input"));
+        }
+        ty.check_size()?;
+        Ok(ty)
+    }
+}
+
+/// Recursive descent parser for SimplicityHL type syntax
+struct Parser<'a> {
+    input: &'a str,
+    pos: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn error(&self, message: &str) -> SprayError {
+        SprayError::ParseError(format!(
+            "Invalid type '{}' at {}: {message}",
+            self.input, self.pos
+        ))
+    }
+
+    fn skip_whitespace(&mut self) {
+        let rest = &self.input[self.pos..];
+        self.pos += rest.len() - rest.trim_start().len();
+    }
+
+    fn eat(&mut self, token: char) -> bool {
+        self.skip_whitespace();
+        if self.input[self.pos..].starts_with(token) {
+            self.pos += token.len_utf8();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, token: char) -> Result<(), SprayError> {
+        if self.eat(token) {
+            Ok(())
+        } else {
+            Err(self.error(&format!("expected '{token}'")))
+        }
+    }
+
+    fn ident(&mut self) -> &'a str {
+        self.skip_whitespace();
+        let rest = &self.input[self.pos..];
+        let len = rest
+            .find(|c: char| !c.is_ascii_alphanumeric() && c != '_')
+            .unwrap_or(rest.len());
+        self.pos += len;
+        &rest[..len]
+    }
+
+    fn parse_type(&mut self) -> Result<ValueType, SprayError> {
+        if self.eat('(') {
+            let mut items = Vec::new();
+            while !self.eat(')') {
+                items.push(self.parse_type()?);
+                if !self.eat(',') {
+                    self.expect(')')?;
+                    break;
+                }
+            }
+            return Ok(if items.is_empty() {
+                ValueType::Unit
+            } else {
+                ValueType::Tuple(items)
+            });
+        }
+
+        if self.eat('[') {
+            let item = self.parse_type()?;
+            self.expect(';')?;
+            let len = self
+                .ident()
+                .parse()
+                .map_err(|_| self.error("expected array length"))?;
+            self.expect(']')?;
+            return Ok(ValueType::Array(Box::new(item), len));
+        }
+
+        let name = self.ident().to_string();
+        match name.as_str() {
+            "bool" => Ok(ValueType::Bool),
+            "Either" => {
+                self.expect('<')?;
+                let left = self.parse_type()?;
+                self.expect(',')?;
+                let right = self.parse_type()?;
+                self.expect('>')?;
+                Ok(ValueType::Either(Box::new(left), Box::new(right)))
+            }
+            "Option" => {
+                self.expect('<')?;
+                let inner = self.parse_type()?;
+                self.expect('>')?;
+                Ok(ValueType::Option(Box::new(inner)))
+            }
+            _ => uint(&name)
+                .or_else(|| alias(&name))
+                .ok_or_else(|| self.error(&format!("unknown type '{name}'"))),
+        }
+    }
+}
+
+/// Parse an unsigned integer type name such as `u32`
+fn uint(name: &str) -> Option<ValueType> {
+    let bits: u16 = name.strip_prefix('u')?.parse().ok()?;
+    matches!(bits, 1 | 2 | 4 | 8 | 16 | 32 | 64 | 128 | 256).then_some(ValueType::UInt(bits))
+}
+
+/// Resolve a builtin SimplicityHL type alias
+fn alias(name: &str) -> Option<ValueType> {
+    let u256 = || ValueType::UInt(256);
+    Some(match name {
+        "Pubkey" | "Message" | "Scalar" | "Fe" | "ExplicitAsset" | "ExplicitNonce" => u256(),
+        "Message64" | "Signature" => ValueType::Array(Box::new(ValueType::UInt(8)), 64),
+        "Ge" => ValueType::Tuple(vec![u256(), u256()]),
+        "Point" => ValueType::Tuple(vec![ValueType::UInt(1), u256()]),
+        "Height" | "Time" | "Lock" => ValueType::UInt(32),
+        "Distance" | "Duration" => ValueType::UInt(16),
+        "ExplicitAmount" => ValueType::UInt(64),
+        "Outpoint" => ValueType::Tuple(vec![u256(), ValueType::UInt(32)]),
+        _ => return None,
+    })
+}
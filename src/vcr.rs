@@ -0,0 +1,406 @@
+//! Recording and replaying node traffic
+//!
+//! With `spray --record <FILE>`, every backend the command creates talks to
+//! its node as usual, and each request and its response is appended to a
+//! fixture file (a [`Cassette`]). With `spray --replay <FILE>`, backends are
+//! [`NetworkBackend::Replay`](crate::network::NetworkBackend::Replay)s that
+//! answer from the fixture instead, so that a command or manifest suite
+//! recorded once against `elementsd` runs deterministically in CI without
+//! one. Either is a [`Mode`] of the command's
+//! [`BackendConfig`](crate::network::BackendConfig).
+//!
+//! Requests are recorded at the level of the
+//! [`NetworkBackend`](crate::network::NetworkBackend): raw RPC calls by
+//! their method name, and [`NodeClient`](musk::client::NodeClient) calls by
+//! the name of the trait method, e.g. `broadcast`. A request is answered
+//! with the first unused recorded response to the same method and
+//! parameters, or else to the same method, in recording order, since
+//! parameters such as signed transactions differ between runs. Replayed
+//! backends do not mine or wait for confirmations. Tests run through a
+//! [`TestEnv`](crate::env::TestEnv) talk to their daemon directly and are
+//! not recorded, so the commands built on one (`spray test --file`,
+//! `stress`, `fuzz`, `soak` and `serve`) reject `--record` and `--replay`.
+//!
+//! # Example
+//!
+//! ```
+//! use serde_json::{json, Value};
+//! use spray::vcr::{Cassette, Interaction, Replay};
+//!
+//! let cassette = Cassette {
+//!     network: "regtest".into(),
+//!     interactions: vec![
+//!         Interaction::ok("getblockcount", vec![], json!(101)),
+//!         Interaction::ok("getblockcount", vec![], json!(102)),
+//!     ],
+//! };
+//! let replay = Replay::new(cassette);
+//! assert_eq!(replay.replay::<Value>("getblockcount", &[]).unwrap(), json!(101));
+//! assert_eq!(replay.replay::<Value>("getblockcount", &[]).unwrap(), json!(102));
+//! assert!(replay.replay::<Value>("getblockcount", &[]).is_err());
+//! ```
+
+use crate::error::SprayError;
+use crate::hex;
+use musk::client::Utxo;
+use musk::elements::encode::{deserialize, serialize_hex};
+use musk::elements::hex::FromHex;
+use musk::elements::{Address, BlockHash, Script, Transaction, Txid};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::{Arc, Mutex, PoisonError};
+
+/// A recorded request and its response
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Interaction {
+    /// RPC or node client method
+    pub method: String,
+    /// Parameters of the request
+    #[serde(default)]
+    pub params: Vec<Value>,
+    /// Response, if the request succeeded
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub result: Option<Value>,
+    /// Error message, if the request failed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl Interaction {
+    /// A request that succeeded with `result`
+    #[must_use]
+    pub fn ok(method: &str, params: Vec<Value>, result: Value) -> Self {
+        Self {
+            method: method.to_string(),
+            params,
+            result: Some(result),
+            error: None,
+        }
+    }
+
+    /// A request that failed with `error`
+    #[must_use]
+    pub fn failed(method: &str, params: Vec<Value>, error: String) -> Self {
+        Self {
+            method: method.to_string(),
+            params,
+            result: None,
+            error: Some(error),
+        }
+    }
+}
+
+/// A fixture file of recorded interactions
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Cassette {
+    /// Network of the recorded backend: `regtest`, `testnet` or `liquid`
+    #[serde(default)]
+    pub network: String,
+    /// Interactions, in the order they happened
+    #[serde(default)]
+    pub interactions: Vec<Interaction>,
+}
+
+impl Cassette {
+    /// Load a fixture file
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be read or is not a cassette.
+    pub fn load(path: &Path) -> Result<Self, SprayError> {
+        let contents = std::fs::read_to_string(path)?;
+        serde_json::from_str(&contents).map_err(|e| {
+            SprayError::FileFormatError(format!("Invalid fixture {}: {e}", path.display()))
+        })
+    }
+
+    /// Write the cassette as pretty-printed JSON
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if serialization or writing the file fails.
+    pub fn save(&self, path: &Path) -> Result<(), SprayError> {
+        if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(path, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+}
+
+/// A response that can be recorded as JSON and decoded again on replay
+pub trait Recordable: Sized {
+    /// Encode the response
+    fn to_value(&self) -> Value;
+
+    /// Decode a recorded response
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the value does not encode a response of this type.
+    fn from_value(value: Value) -> Result<Self, SprayError>;
+}
+
+impl Recordable for Value {
+    fn to_value(&self) -> Value {
+        self.clone()
+    }
+
+    fn from_value(value: Value) -> Result<Self, SprayError> {
+        Ok(value)
+    }
+}
+
+impl Recordable for Txid {
+    fn to_value(&self) -> Value {
+        json!(self.to_string())
+    }
+
+    fn from_value(value: Value) -> Result<Self, SprayError> {
+        Self::from_str(as_str(&value)?).map_err(|e| invalid(&e))
+    }
+}
+
+impl Recordable for BlockHash {
+    fn to_value(&self) -> Value {
+        json!(self.to_string())
+    }
+
+    fn from_value(value: Value) -> Result<Self, SprayError> {
+        Self::from_str(as_str(&value)?).map_err(|e| invalid(&e))
+    }
+}
+
+impl Recordable for Address {
+    fn to_value(&self) -> Value {
+        json!(self.to_string())
+    }
+
+    fn from_value(value: Value) -> Result<Self, SprayError> {
+        Self::from_str(as_str(&value)?).map_err(|e| invalid(&e))
+    }
+}
+
+impl Recordable for Transaction {
+    fn to_value(&self) -> Value {
+        json!(serialize_hex(self))
+    }
+
+    fn from_value(value: Value) -> Result<Self, SprayError> {
+        crate::package::decode_transaction(as_str(&value)?)
+    }
+}
+
+impl Recordable for Utxo {
+    fn to_value(&self) -> Value {
+        json!({
+            "txid": self.txid.to_string(),
+            "vout": self.vout,
+            "amount": self.amount,
+            "script_pubkey": hex::encode(self.script_pubkey.as_bytes()),
+            "asset": serialize_hex(&self.asset),
+        })
+    }
+
+    fn from_value(value: Value) -> Result<Self, SprayError> {
+        let bytes =
+            |name: &str| Vec::<u8>::from_hex(as_str(&value[name])?).map_err(|e| invalid(&e));
+        Ok(Self {
+            txid: Txid::from_value(value["txid"].clone())?,
+            vout: value["vout"]
+                .as_u64()
+                .and_then(|vout| u32::try_from(vout).ok())
+                .ok_or_else(|| invalid(&"missing vout"))?,
+            amount: value["amount"]
+                .as_u64()
+                .ok_or_else(|| invalid(&"missing amount"))?,
+            script_pubkey: Script::from(bytes("script_pubkey")?),
+            asset: deserialize(&bytes("asset")?).map_err(|e| invalid(&e))?,
+        })
+    }
+}
+
+impl<T: Recordable> Recordable for Vec<T> {
+    fn to_value(&self) -> Value {
+        Value::Array(self.iter().map(Recordable::to_value).collect())
+    }
+
+    fn from_value(value: Value) -> Result<Self, SprayError> {
+        match value {
+            Value::Array(items) => items.into_iter().map(T::from_value).collect(),
+            _ => Err(invalid(&"expected an array")),
+        }
+    }
+}
+
+/// Records interactions to a fixture file
+///
+/// Clones share the cassette, which is written out after every
+/// interaction, so that all backends of a process record to the same file.
+#[derive(Debug, Clone)]
+pub struct Recorder {
+    path: PathBuf,
+    cassette: Arc<Mutex<Cassette>>,
+}
+
+impl Recorder {
+    /// Record to `path`, overwriting it
+    #[must_use]
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            cassette: Arc::default(),
+        }
+    }
+
+    /// The fixture file
+    #[must_use]
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Set the network recorded in the fixture
+    pub fn set_network(&self, network: &str) {
+        self.lock().network = network.to_string();
+    }
+
+    /// Record a request and its outcome, and write the fixture
+    ///
+    /// Failures to write the fixture are printed, not returned, so that
+    /// recording never changes the outcome of the request.
+    pub fn record<T: Recordable>(
+        &self,
+        method: &str,
+        params: &[Value],
+        result: Result<&T, String>,
+    ) {
+        let interaction = match result {
+            Ok(value) => Interaction::ok(method, params.to_vec(), value.to_value()),
+            Err(error) => Interaction::failed(method, params.to_vec(), error),
+        };
+        let mut cassette = self.lock();
+        cassette.interactions.push(interaction);
+        if let Err(e) = cassette.save(&self.path) {
+            eprintln!(
+                "Warning: failed to write fixture {}: {e}",
+                self.path.display()
+            );
+        }
+    }
+
+    /// A copy of the interactions recorded so far
+    #[must_use]
+    pub fn cassette(&self) -> Cassette {
+        self.lock().clone()
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Cassette> {
+        self.cassette.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+/// Serves recorded interactions back
+///
+/// Clones share which interactions have been used.
+#[derive(Debug, Clone)]
+pub struct Replay {
+    cassette: Arc<Cassette>,
+    used: Arc<Mutex<Vec<bool>>>,
+}
+
+impl Replay {
+    /// Replay `cassette`
+    #[must_use]
+    pub fn new(cassette: Cassette) -> Self {
+        let used = vec![false; cassette.interactions.len()];
+        Self {
+            cassette: Arc::new(cassette),
+            used: Arc::new(Mutex::new(used)),
+        }
+    }
+
+    /// Replay the fixture file at `path`
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be loaded.
+    pub fn load(path: &Path) -> Result<Self, SprayError> {
+        Cassette::load(path).map(Self::new)
+    }
+
+    /// Network the fixture was recorded on
+    #[must_use]
+    pub fn network(&self) -> &str {
+        &self.cassette.network
+    }
+
+    /// Number of recorded interactions not served yet
+    #[must_use]
+    pub fn remaining(&self) -> usize {
+        self.lock().iter().filter(|used| !**used).count()
+    }
+
+    /// Answer a request with the first unused interaction of the same method
+    /// and parameters, or else of the same method
+    ///
+    /// # Errors
+    ///
+    /// Returns the recorded error if the request failed when it was recorded,
+    /// or an error if no interaction is left for the method or its response
+    /// cannot be decoded.
+    pub fn replay<T: Recordable>(&self, method: &str, params: &[Value]) -> Result<T, SprayError> {
+        let interactions = &self.cassette.interactions;
+        let mut used = self.lock();
+        let unused = |i: &usize| !used[*i] && interactions[*i].method == method;
+        let index = (0..interactions.len())
+            .filter(unused)
+            .find(|&i| interactions[i].params == params)
+            .or_else(|| (0..interactions.len()).find(unused))
+            .ok_or_else(|| {
+                SprayError::RpcError(format!("No recorded response left for {method}"))
+            })?;
+        used[index] = true;
+        drop(used);
+
+        let interaction = &interactions[index];
+        match (&interaction.result, &interaction.error) {
+            (_, Some(error)) => Err(SprayError::rpc(error.clone())),
+            (Some(result), None) => T::from_value(result.clone()),
+            (None, None) => T::from_value(Value::Null),
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Vec<bool>> {
+        self.used.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+/// What the backends of a [`BackendConfig`](crate::network::BackendConfig)
+/// do with their traffic
+#[derive(Debug, Clone)]
+pub enum Mode {
+    /// Record it
+    Record(Recorder),
+    /// Answer from a recording instead of a node
+    Replay(Replay),
+}
+
+/// The detail of an error, without the `RPC error:` prefix of RPC errors,
+/// for recording
+#[must_use]
+pub fn error_detail(error: &SprayError) -> String {
+    match error {
+        SprayError::RpcError(detail) | SprayError::InsufficientFunds(detail) => detail.clone(),
+        error => error.to_string(),
+    }
+}
+
+fn as_str(value: &Value) -> Result<&str, SprayError> {
+    value.as_str().ok_or_else(|| invalid(&"expected a string"))
+}
+
+fn invalid(e: &dyn std::fmt::Display) -> SprayError {
+    SprayError::FileFormatError(format!("Invalid recorded response: {e}"))
+}
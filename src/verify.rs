@@ -0,0 +1,170 @@
+//! Local witness verification
+//!
+//! This module checks a witness against a program without any node: the
+//! program is spent from a synthetic UTXO (or one exported with
+//! `spray utxo export`) and the spend is finalized, which satisfies the
+//! program and executes it against the spending transaction. A failure is
+//! classified by what went wrong, e.g. a failing jet or assertion.
+//!
+//! # Example
+//!
+//! ```
+//! use spray::verify::FailureKind;
+//!
+//! let kind = FailureKind::classify("Assertion failed inside jet `eq_32`");
+//! assert_eq!(kind, FailureKind::Jet("eq_32".to_string()));
+//! ```
+
+use crate::error::SprayError;
+use crate::preset::Preset;
+use musk::client::Utxo;
+use musk::elements::hashes::Hash;
+use musk::elements::{confidential, AssetId, BlockHash, LockTime, Sequence, Txid};
+use musk::{InstantiatedProgram, Network, SpendBuilder, WitnessValues};
+use std::fmt;
+
+/// Default amount of the synthetic UTXO, in satoshis
+pub const DEFAULT_AMOUNT: u64 = 100_000;
+
+/// Transaction context a witness is verified in
+#[derive(Debug, Clone)]
+pub struct SpendContext {
+    /// UTXO being spent
+    pub utxo: Utxo,
+    /// Genesis hash of the chain, committed to by the sighash
+    pub genesis_hash: BlockHash,
+    /// Lock time of the spending transaction
+    pub lock_time: LockTime,
+    /// Sequence number of the spending input
+    pub sequence: Sequence,
+    /// Fee of the spending transaction, in satoshis
+    pub fee: u64,
+}
+
+impl SpendContext {
+    /// A synthetic context spending `amount` of the Liquid policy asset
+    /// locked in `program`
+    ///
+    /// The funding txid and genesis hash are all zeros, so signatures in the
+    /// witness only verify if they were made over this context's sighash.
+    #[must_use]
+    pub fn synthetic(program: &InstantiatedProgram, amount: u64) -> Self {
+        let preset = Preset::builtin(Network::Regtest);
+        let address = program.address(preset.address_params);
+        Self {
+            utxo: Utxo {
+                txid: Txid::all_zeros(),
+                vout: 0,
+                amount,
+                script_pubkey: address.script_pubkey(),
+                asset: confidential::Asset::Explicit(AssetId::LIQUID_BTC),
+            },
+            genesis_hash: BlockHash::all_zeros(),
+            lock_time: LockTime::ZERO,
+            sequence: Sequence::MAX,
+            fee: preset.fee,
+        }
+    }
+}
+
+/// What made a witness fail to verify
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FailureKind {
+    /// A jet failed, e.g. a signature or equality check
+    Jet(String),
+    /// An `assert!` or explicit failure in the program
+    Assertion,
+    /// The witness values do not match the declared witness types
+    Witness,
+    /// Any other error
+    Other,
+}
+
+impl FailureKind {
+    /// Classify an error message from satisfying or executing a program
+    #[must_use]
+    pub fn classify(error: &str) -> Self {
+        let lower = error.to_lowercase();
+        if let Some(jet) = jet_name(error) {
+            Self::Jet(jet)
+        } else if lower.contains("assert") || lower.contains("fail node") {
+            Self::Assertion
+        } else if lower.contains("witness") {
+            Self::Witness
+        } else {
+            Self::Other
+        }
+    }
+}
+
+impl fmt::Display for FailureKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Jet(name) => write!(f, "jet `{name}` failed"),
+            Self::Assertion => write!(f, "assertion failed"),
+            Self::Witness => write!(f, "invalid witness"),
+            Self::Other => write!(f, "error"),
+        }
+    }
+}
+
+/// Find a jet name in an error message, e.g. "jet `eq_32`" or "jet eq_32"
+fn jet_name(error: &str) -> Option<String> {
+    let lower = error.to_lowercase();
+    let rest = &error[lower.find("jet ")? + 4..];
+    let name: String = rest
+        .trim_start_matches(['`', '\'', '"'])
+        .chars()
+        .take_while(|c| c.is_ascii_alphanumeric() || *c == '_')
+        .collect();
+    (!name.is_empty() && name != "failed").then_some(name)
+}
+
+/// Outcome of verifying a witness
+#[derive(Debug, Clone)]
+pub struct Verification {
+    /// Sighash of the spend the witness was verified against
+    pub sighash: [u8; 32],
+    /// Weight of the finalized transaction, or the failure
+    pub result: Result<usize, (FailureKind, String)>,
+}
+
+/// Satisfy and execute `program` with `witness` in `context`
+///
+/// # Errors
+///
+/// Returns an error if the spend cannot be built or its sighash cannot be
+/// computed. A witness that fails to satisfy the program is reported in the
+/// returned [`Verification`] instead.
+pub fn verify(
+    program: &InstantiatedProgram,
+    witness: WitnessValues,
+    context: &SpendContext,
+) -> Result<Verification, SprayError> {
+    let confidential::Asset::Explicit(asset) = context.utxo.asset else {
+        return Err(SprayError::TestError("Non-explicit asset in UTXO".into()));
+    };
+    let output_amount = context
+        .utxo
+        .amount
+        .checked_sub(context.fee)
+        .ok_or_else(|| SprayError::TestError("Insufficient funds for fee".into()))?;
+
+    let mut builder = SpendBuilder::new(program.clone(), context.utxo.clone())
+        .genesis_hash(context.genesis_hash)
+        .lock_time(context.lock_time)
+        .sequence(context.sequence);
+    builder.add_output_simple(context.utxo.script_pubkey.clone(), output_amount, asset);
+    builder.add_fee(context.fee, asset);
+
+    let sighash = builder.sighash_all().map_err(SprayError::SpendError)?;
+    let result = builder
+        .finalize(witness)
+        .map(|tx| tx.weight())
+        .map_err(|e| {
+            let error = e.to_string();
+            (FailureKind::classify(&error), error)
+        });
+
+    Ok(Verification { sighash, result })
+}
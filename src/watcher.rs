@@ -0,0 +1,99 @@
+//! Polling file watcher
+//!
+//! `spray test --watch` reruns a test whenever its program, arguments or
+//! witness file changes. [`FileWatcher`] detects changes by polling each
+//! file's modification time and length, which works the same on every
+//! platform and needs no extra dependencies.
+//!
+//! # Example
+//!
+//! ```
+//! use spray::watcher::FileWatcher;
+//!
+//! let path = std::env::temp_dir().join(format!("spray-doc-watch-{}.simf", std::process::id()));
+//! std::fs::write(&path, "fn main() {}").unwrap();
+//!
+//! let mut watcher = FileWatcher::new([path.clone()]);
+//! assert!(watcher.poll().is_empty());
+//!
+//! std::fs::write(&path, "fn main() { assert!(true); }").unwrap();
+//! assert_eq!(watcher.poll(), vec![path.clone()]);
+//! assert!(watcher.poll().is_empty());
+//! # std::fs::remove_file(&path).unwrap();
+//! ```
+
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+/// Default interval between polls
+pub const DEFAULT_POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Modification time and length of a file, `None` if it does not exist
+type Stamp = Option<(Option<SystemTime>, u64)>;
+
+/// Watches a set of files for changes
+#[derive(Debug, Clone)]
+pub struct FileWatcher {
+    files: Vec<(PathBuf, Stamp)>,
+}
+
+impl FileWatcher {
+    /// Watch `paths`, taking their current state as unchanged
+    #[must_use]
+    pub fn new(paths: impl IntoIterator<Item = PathBuf>) -> Self {
+        let files = paths
+            .into_iter()
+            .map(|path| {
+                let stamp = stamp(&path);
+                (path, stamp)
+            })
+            .collect();
+        Self { files }
+    }
+
+    /// The watched files
+    pub fn paths(&self) -> impl Iterator<Item = &Path> {
+        self.files.iter().map(|(path, _)| path.as_path())
+    }
+
+    /// Files that were modified, created or removed since the last poll
+    pub fn poll(&mut self) -> Vec<PathBuf> {
+        let mut changed = Vec::new();
+        for (path, last) in &mut self.files {
+            let current = stamp(path);
+            if current != *last {
+                *last = current;
+                changed.push(path.clone());
+            }
+        }
+        changed
+    }
+
+    /// Block until at least one file changes, polling every `interval`
+    ///
+    /// Once a change is seen, polling continues until the files have been
+    /// stable for one interval, so that an editor's save (often a write
+    /// followed by a rename) is reported as a single change.
+    pub fn wait(&mut self, interval: Duration) -> Vec<PathBuf> {
+        let mut changed = Vec::new();
+        loop {
+            std::thread::sleep(interval);
+            let batch = self.poll();
+            if batch.is_empty() && !changed.is_empty() {
+                return changed;
+            }
+            for path in batch {
+                if !changed.contains(&path) {
+                    changed.push(path);
+                }
+            }
+        }
+    }
+}
+
+/// Current modification time and length of `path`
+fn stamp(path: &Path) -> Stamp {
+    std::fs::metadata(path)
+        .ok()
+        .map(|meta| (meta.modified().ok(), meta.len()))
+}
@@ -0,0 +1,125 @@
+//! Witness providers
+//!
+//! A spend's witness often depends on the transaction it appears in, e.g. a
+//! signature over the sighash. A [`WitnessProvider`] computes the witness
+//! from the sighash of the finalized spend. Closures taking the sighash are
+//! providers; [`FixedWitness`] provides the same values for every spend.
+//!
+//! Witness files may ask for signatures instead of spelling them out: an
+//! entry `{"type": "Signature", "sign": "<secret key hex>"}` stands for a
+//! BIP 340 signature of the spend's sighash with that key, which
+//! [`SigningWitness`] makes for each spend. This keeps file-based tests of
+//! signature-checking contracts runnable; such keys are for tests only.
+//!
+//! # Example
+//!
+//! ```
+//! use musk::WitnessValues;
+//! use spray::witness::{FixedWitness, WitnessProvider};
+//!
+//! let fixed = FixedWitness(WitnessValues::default());
+//! let _ = fixed.witness([0; 32]);
+//!
+//! let from_sighash = |sighash: [u8; 32]| {
+//!     assert_eq!(sighash, [1; 32]);
+//!     WitnessValues::default()
+//! };
+//! let _ = from_sighash.witness([1; 32]);
+//! ```
+
+use crate::error::SprayError;
+use crate::signing::sign_digest;
+use musk::elements::secp256k1_zkp::SecretKey;
+use musk::WitnessValues;
+use serde_json::{json, Map, Value};
+use std::str::FromStr;
+
+/// Computes the witness of a spend from its sighash
+pub trait WitnessProvider {
+    /// Witness values for a spend with `sighash` (`SIGHASH_ALL`)
+    fn witness(&self, sighash: [u8; 32]) -> WitnessValues;
+}
+
+impl<F> WitnessProvider for F
+where
+    F: Fn([u8; 32]) -> WitnessValues,
+{
+    fn witness(&self, sighash: [u8; 32]) -> WitnessValues {
+        self(sighash)
+    }
+}
+
+/// The same witness for every spend
+#[derive(Clone, Default)]
+pub struct FixedWitness(pub WitnessValues);
+
+impl WitnessProvider for FixedWitness {
+    fn witness(&self, _sighash: [u8; 32]) -> WitnessValues {
+        self.0.clone()
+    }
+}
+
+/// Witness values from a file, with signatures made over each spend's
+/// sighash
+///
+/// Entries with a `sign` key are replaced by a signature with that secret
+/// key; the others are passed through. Entries of a TOML file's `[witness]`
+/// table are handled the same way.
+#[derive(Debug, Clone)]
+pub struct SigningWitness {
+    template: Value,
+}
+
+impl SigningWitness {
+    /// Witness values from the contents of a witness file
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a `sign` entry holds no valid secret key or the
+    /// values are not valid witness values.
+    pub fn new(template: Value) -> Result<Self, SprayError> {
+        let witness = Self { template };
+        witness.resolve([0; 32])?;
+        Ok(witness)
+    }
+
+    /// The witness values of a spend with `sighash`
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a `sign` entry holds no valid secret key or the
+    /// values are not valid witness values.
+    pub fn resolve(&self, sighash: [u8; 32]) -> Result<WitnessValues, SprayError> {
+        let mut value = self.template.clone();
+        for (name, entry) in entries(&mut value).into_iter().flatten() {
+            let Some(key) = entry.get("sign") else {
+                continue;
+            };
+            let secret_key = key
+                .as_str()
+                .and_then(|key| SecretKey::from_str(key.trim_start_matches("0x")).ok())
+                .ok_or_else(|| {
+                    SprayError::ParseError(format!("Invalid secret key to sign witness '{name}'"))
+                })?;
+            let signature = sign_digest(sighash, &secret_key);
+            *entry = json!({ "value": format!("0x{signature}"), "type": "Signature" });
+        }
+        Ok(serde_json::from_value(value)?)
+    }
+}
+
+impl WitnessProvider for SigningWitness {
+    fn witness(&self, sighash: [u8; 32]) -> WitnessValues {
+        // `new` checked the keys and values, and signing cannot fail
+        self.resolve(sighash).unwrap_or_default()
+    }
+}
+
+/// The entries of a witness file, inside its `[witness]` table if it has one
+fn entries(value: &mut Value) -> Option<&mut Map<String, Value>> {
+    if value.get("witness").is_some_and(Value::is_object) {
+        value["witness"].as_object_mut()
+    } else {
+        value.as_object_mut()
+    }
+}
@@ -0,0 +1,165 @@
+//! Unit tests for alert rules
+
+use spray::alerts::{AlertRules, Condition};
+use spray::monitor::{SpendOutput, WatchEvent};
+
+/// A valid regtest address and its output script
+const ADDRESS: &str = "ert1qw508d6qejxtdg4y5r3zarvary0c5xw7kuu73e0";
+const SCRIPT: &str = "0014751e76e8199196d454941c45d1b3a323f1433bd6";
+
+fn spend(outputs: &[&str], before: u64, after: u64) -> WatchEvent {
+    WatchEvent::Spent {
+        contract: "vault".into(),
+        txid: "cc".into(),
+        outpoints: vec!["aa:0".into()],
+        outputs: outputs
+            .iter()
+            .map(|script| SpendOutput {
+                script_pubkey: (*script).to_string(),
+                amount: Some(1000),
+            })
+            .collect(),
+        height: 200,
+        balance_before: before,
+        balance_after: after,
+    }
+}
+
+#[test]
+fn test_conditions() {
+    let funded = WatchEvent::Funded {
+        contract: "vault".into(),
+        txid: "aa".into(),
+        vout: 1,
+        amount: None,
+        height: 100,
+        balance_before: 0,
+        balance_after: 0,
+    };
+    assert_eq!(
+        Condition::Funded.check(&funded).unwrap().as_deref(),
+        Some("funded by aa:1 (confidential)")
+    );
+    assert_eq!(Condition::Spent.check(&funded).unwrap(), None);
+    assert!(Condition::Spent.check(&spend(&[], 0, 0)).unwrap().is_some());
+
+    let without = Condition::SpentWithoutOutputTo(ADDRESS.into());
+    assert!(without.check(&spend(&["51"], 0, 0)).unwrap().is_some());
+    assert_eq!(without.check(&spend(&["51", SCRIPT], 0, 0)).unwrap(), None);
+    assert!(Condition::SpentWithoutOutputTo("nope".into())
+        .check(&spend(&[], 0, 0))
+        .is_err());
+
+    let below = Condition::BalanceBelow(500);
+    assert!(below.check(&spend(&[], 1000, 400)).unwrap().is_some());
+    assert_eq!(below.check(&spend(&[], 400, 300)).unwrap(), None);
+    assert_eq!(below.check(&spend(&[], 1000, 500)).unwrap(), None);
+}
+
+#[test]
+fn test_rules_evaluate() {
+    let rules = AlertRules::from_toml(&format!(
+        r#"
+        [[contracts]]
+        name = "vault"
+        address = "{ADDRESS}"
+
+        [[contracts]]
+        name = "escrow"
+        address = "{ADDRESS}"
+
+        [[rules]]
+        name = "any-spend"
+        contract = "vault"
+        when = "spent"
+
+        [[rules]]
+        name = "drain"
+        contract = "vault"
+        when = {{ balance_below = 1 }}
+        exit_code = 3
+
+        [[rules]]
+        name = "escrow-spend"
+        contract = "escrow"
+        when = "spent"
+        exit_code = 9
+        "#
+    ))
+    .unwrap();
+
+    let alerts = rules.evaluate(&spend(&[], 1000, 1000));
+    assert_eq!(alerts.len(), 1);
+    assert_eq!(alerts[0].rule, "any-spend");
+    assert_eq!(rules.exit_code(&alerts), None);
+
+    let alerts = rules.evaluate(&spend(&[], 1000, 0));
+    let names: Vec<&str> = alerts.iter().map(|a| a.rule.as_str()).collect();
+    assert_eq!(names, ["any-spend", "drain"]);
+    assert_eq!(rules.exit_code(&alerts), Some(3));
+
+    let json = serde_json::to_value(&alerts[1]).unwrap();
+    assert_eq!(json["event"]["kind"], "spent");
+    assert_eq!(json["event"]["balance_after"], 0);
+}
+
+#[test]
+fn test_rules_validation() {
+    let unknown = AlertRules::from_toml(
+        r#"
+        [[rules]]
+        name = "orphan"
+        contract = "missing"
+        when = "funded"
+        "#,
+    );
+    assert!(unknown
+        .unwrap_err()
+        .to_string()
+        .contains("unknown contract"));
+
+    let both = AlertRules::from_toml(&format!(
+        r#"
+        [[contracts]]
+        name = "vault"
+        file = "vault.simf"
+        address = "{ADDRESS}"
+        "#
+    ));
+    assert!(both.unwrap_err().to_string().contains("exactly one"));
+
+    let bad_address = AlertRules::from_toml(
+        r#"
+        [[contracts]]
+        name = "vault"
+        file = "vault.simf"
+
+        [[rules]]
+        name = "leak"
+        contract = "vault"
+        when = { spent_without_output_to = "not-an-address" }
+        "#,
+    );
+    assert!(bad_address.is_err());
+}
+
+#[test]
+fn test_load_resolves_paths() {
+    let dir = tempfile::tempdir().expect("Failed to create temp dir");
+    let path = dir.path().join("alerts.toml");
+    std::fs::write(
+        &path,
+        "[[contracts]]\nname = \"vault\"\nfile = \"vault.simf\"\nargs = \"/abs/vault.args.json\"\n",
+    )
+    .unwrap();
+
+    let rules = AlertRules::load(&path).unwrap();
+    assert_eq!(
+        rules.contracts[0].file.as_deref(),
+        Some(dir.path().join("vault.simf").as_path())
+    );
+    assert_eq!(
+        rules.contracts[0].args.as_deref(),
+        Some(std::path::Path::new("/abs/vault.args.json"))
+    );
+}
@@ -0,0 +1,83 @@
+//! Unit tests for audit bundles
+
+use spray::audit::{AuditBundle, CostReport, MANIFEST_FILE};
+
+fn bundle() -> AuditBundle {
+    let mut bundle = AuditBundle::new("p2pk", "deadbeef");
+    bundle.add("source/p2pk.simf", b"fn main() {}".to_vec());
+    bundle.add("cmr.txt", b"deadbeef\n".to_vec());
+    bundle.add("deployments.json", vec![b'x'; 1500]);
+    bundle
+}
+
+#[test]
+fn test_round_trip_preserves_files() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("audit.tar");
+    let bundle = bundle();
+    bundle.write(&path).unwrap();
+
+    let read = AuditBundle::read(&path).unwrap();
+    assert_eq!(read.files(), bundle.files());
+    assert_eq!(read.manifest(), bundle.manifest());
+    assert_eq!(std::fs::metadata(&path).unwrap().len() % 512, 0);
+}
+
+#[test]
+fn test_add_replaces_existing_file() {
+    let mut bundle = bundle();
+    bundle.add("cmr.txt", b"cafebabe\n".to_vec());
+    let manifest = bundle.manifest();
+    assert_eq!(manifest.files.len(), 3);
+    assert_eq!(manifest.files[2].path, "cmr.txt");
+    assert_eq!(manifest.files[2].size, 9);
+}
+
+#[test]
+fn test_read_rejects_tampered_file() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("audit.tar");
+    bundle().write(&path).unwrap();
+
+    let mut archive = std::fs::read(&path).unwrap();
+    let at = archive
+        .windows(12)
+        .position(|w| w == b"fn main() {}")
+        .unwrap();
+    archive[at] = b'F';
+    std::fs::write(&path, archive).unwrap();
+
+    let err = AuditBundle::read(&path).unwrap_err().to_string();
+    assert!(err.contains("source/p2pk.simf"), "{err}");
+}
+
+#[test]
+fn test_manifest_is_first_entry() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("audit.tar");
+    bundle().write(&path).unwrap();
+
+    let archive = std::fs::read(&path).unwrap();
+    assert!(archive.starts_with(MANIFEST_FILE.as_bytes()));
+    assert_eq!(&archive[257..263], b"ustar\0");
+}
+
+#[test]
+fn test_write_rejects_long_names() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut bundle = AuditBundle::new("p2pk", "deadbeef");
+    bundle.add(&"a".repeat(101), Vec::new());
+    assert!(bundle.write(&dir.path().join("audit.tar")).is_err());
+}
+
+#[test]
+fn test_cost_report_omits_unknown_spend() {
+    let cost = CostReport {
+        program_size: 42,
+        ..CostReport::default()
+    };
+    assert_eq!(
+        serde_json::to_value(&cost).unwrap(),
+        serde_json::json!({ "program_size": 42 })
+    );
+}
@@ -0,0 +1,41 @@
+//! Unit tests for program benchmarks
+
+use spray::bench::{bench_program, Timing};
+use std::time::Duration;
+
+#[test]
+fn test_timing_from_samples() {
+    assert_eq!(Timing::from_samples(&[]), None);
+
+    let samples = [10, 40, 20, 30].map(Duration::from_micros);
+    let timing = Timing::from_samples(&samples).unwrap();
+    assert_eq!(timing.min_us, 10);
+    assert_eq!(timing.median_us, 30);
+    assert_eq!(timing.mean_us, 25);
+    assert_eq!(timing.max_us, 40);
+    assert_eq!(timing.to_string(), "0.030 ms (min 0.010 ms, max 0.040 ms)");
+}
+
+#[test]
+fn test_bench_program() {
+    let dir = tempfile::tempdir().expect("Failed to create temp dir");
+    let program = dir.path().join("trivial.simf");
+    std::fs::write(&program, "fn main() { assert!(true); }").unwrap();
+    let witness = dir.path().join("trivial.wit.json");
+    std::fs::write(&witness, "{}").unwrap();
+
+    let compiled_only = bench_program(&program, None, None, 3).unwrap();
+    assert_eq!(compiled_only.iterations, 3);
+    assert!(compiled_only.program_size > 0);
+    assert!(compiled_only.satisfy.is_none());
+    assert!(compiled_only.budget.is_none());
+
+    let result = bench_program(&program, None, Some(&witness), 0).unwrap();
+    assert_eq!(result.iterations, 1);
+    assert!(result.satisfy.is_some());
+    assert!(result.satisfied_size.is_some());
+    assert!(result.budget.unwrap().is_within());
+
+    let json = serde_json::to_value(&result).unwrap();
+    assert!(json["compile"]["median_us"].is_u64());
+}
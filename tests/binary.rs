@@ -0,0 +1,89 @@
+//! Unit tests for managed elementsd binaries
+
+use spray::binary::{self, parse_checksums, ElementsdConfig, Release};
+use std::path::Path;
+
+#[test]
+fn test_release_urls() {
+    let release = Release::new("23.2.4", "aarch64-linux-gnu");
+    assert_eq!(
+        release.url(),
+        "https://github.com/ElementsProject/elements/releases/download/elements-23.2.4/elements-23.2.4-aarch64-linux-gnu.tar.gz"
+    );
+    assert_eq!(
+        release.checksums_url(),
+        "https://github.com/ElementsProject/elements/releases/download/elements-23.2.4/SHA256SUMS.asc"
+    );
+    assert!(release
+        .exe_path(Path::new("cache"))
+        .starts_with("cache/23.2.4/bin"));
+
+    let mirrored = release.with_url("https://mirror.example.com/elements.tar.gz");
+    assert_eq!(mirrored.url(), "https://mirror.example.com/elements.tar.gz");
+}
+
+#[test]
+fn test_parse_checksums() {
+    let hash = "0123456789abcdef".repeat(4);
+    let sums = format!(
+        "-----BEGIN PGP SIGNED MESSAGE-----\nHash: SHA256\n\n{}  elements-23.2.4-osx64.tar.gz\n{} *elements-23.2.4-x86_64-linux-gnu.tar.gz\n",
+        "f".repeat(64),
+        hash.to_uppercase()
+    );
+
+    assert_eq!(
+        parse_checksums(&sums, "elements-23.2.4-x86_64-linux-gnu.tar.gz"),
+        Some(hash)
+    );
+    assert_eq!(parse_checksums(&sums, "elements-23.2.4-win64.zip"), None);
+    assert_eq!(parse_checksums("Hash: SHA256", "SHA256"), None);
+}
+
+#[test]
+fn test_verify() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("elementsd");
+    std::fs::write(&path, b"abc").unwrap();
+
+    let expected = "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad";
+    assert_eq!(binary::sha256_file(&path).unwrap(), expected);
+    assert!(binary::verify(&path, &expected.to_uppercase()).is_ok());
+    let error = binary::verify(&path, &"0".repeat(64)).unwrap_err();
+    assert!(error.to_string().contains("Checksum mismatch"));
+}
+
+#[test]
+fn test_install_uses_existing_release() {
+    let dir = tempfile::tempdir().unwrap();
+    let release = Release::new("23.2.4", "x86_64-linux-gnu");
+    let sha256 = "ab".repeat(32);
+    let exe = release.exe_path(&dir.path().join(&sha256));
+    std::fs::create_dir_all(exe.parent().unwrap()).unwrap();
+    std::fs::write(&exe, b"").unwrap();
+
+    assert_eq!(
+        binary::install(&release, &sha256.to_uppercase(), dir.path()).unwrap(),
+        exe
+    );
+}
+
+#[test]
+fn test_install_rejects_invalid_pin() {
+    let dir = tempfile::tempdir().unwrap();
+    let release = Release::new("23.2.4", "x86_64-linux-gnu");
+    let exe = release.exe_path(dir.path());
+    std::fs::create_dir_all(exe.parent().unwrap()).unwrap();
+    std::fs::write(&exe, b"").unwrap();
+
+    let error = binary::install(&release, "", dir.path()).unwrap_err();
+    assert!(error.to_string().contains("Invalid sha256"));
+    assert!(binary::install(&release, "../23.2.4", dir.path()).is_err());
+}
+
+#[test]
+fn test_config() {
+    let config: ElementsdConfig = toml::from_str("version = \"23.2.4\"").unwrap();
+    assert_eq!(config.version.as_deref(), Some("23.2.4"));
+    assert!(config.sha256.is_none());
+    assert!(config.cache_dir.is_none());
+}
@@ -0,0 +1,45 @@
+//! Unit tests for Simplicity cost budgets
+
+use musk::elements::{LockTime, Transaction, TxIn};
+use spray::budget::{budget_mwu, Budget, BUDGET_OVERHEAD_WU};
+
+#[test]
+fn test_budget_counts_serialized_witness_stack() {
+    assert_eq!(budget_mwu(&[]), (1 + BUDGET_OVERHEAD_WU) * 1000);
+    assert_eq!(budget_mwu(&[vec![], vec![0; 2]]), (1 + 1 + 3 + 50) * 1000);
+    // Elements of 253 bytes or more take a 3-byte length prefix
+    assert_eq!(budget_mwu(&[vec![0; 253]]), (1 + 3 + 253 + 50) * 1000);
+}
+
+#[test]
+fn test_usage_and_headroom() {
+    let budget = Budget {
+        cost_mwu: 1_500,
+        budget_mwu: 1_000,
+    };
+    assert!(!budget.is_within());
+    assert_eq!(budget.headroom_mwu(), 0);
+    assert!((budget.usage() - 150.0).abs() < f64::EPSILON);
+    assert_eq!(budget.to_string(), "1.500 WU of 1.000 WU budget (150.0%)");
+
+    let budget = Budget {
+        cost_mwu: 250,
+        budget_mwu: 1_000,
+    };
+    assert!(budget.is_within());
+    assert_eq!(budget.headroom_mwu(), 750);
+}
+
+#[test]
+fn test_non_simplicity_spends_have_no_budget() {
+    assert!(Budget::for_witness_stack(&[vec![1]]).unwrap().is_none());
+    assert!(Budget::for_witness_stack(&[vec![], vec![]]).is_err());
+
+    let tx = Transaction {
+        version: 2,
+        lock_time: LockTime::ZERO,
+        input: vec![TxIn::default()],
+        output: vec![],
+    };
+    assert!(Budget::for_transaction(&tx).is_none());
+}
@@ -0,0 +1,96 @@
+//! Unit tests for the compile cache
+
+use musk::Network;
+use serde_json::json;
+use spray::cache::{CacheEntry, CompileCache, ProgramCache};
+use spray::compiled::CompiledOutput;
+
+#[test]
+fn test_key_depends_on_every_input() {
+    let args = json!({ "N": 1 });
+    let key = CompileCache::key("fn main() {}", &args, "regtest");
+
+    assert_eq!(key, CompileCache::key("fn main() {}", &args, "regtest"));
+    assert_ne!(key, CompileCache::key("fn main() { }", &args, "regtest"));
+    assert_ne!(
+        key,
+        CompileCache::key("fn main() {}", &json!({}), "regtest")
+    );
+    assert_ne!(key, CompileCache::key("fn main() {}", &args, "testnet"));
+}
+
+#[test]
+fn test_key_ignores_argument_order() {
+    let a = json!({ "A": 1, "B": "0x02" });
+    let b = json!({ "B": "0x02", "A": 1 });
+    assert_eq!(
+        CompileCache::key("src", &a, "regtest"),
+        CompileCache::key("src", &b, "regtest")
+    );
+}
+
+#[test]
+fn test_put_and_get() {
+    let dir = tempfile::tempdir().unwrap();
+    let cache = CompileCache::new(dir.path().join("cache"));
+    let key = CompileCache::key("src", &json!({}), "regtest");
+    assert!(cache.get(&key).is_none());
+
+    let entry = CacheEntry {
+        address: "ert1p...".into(),
+        output: CompiledOutput::from_program_text("48656c6c6f").unwrap(),
+    };
+    cache.put(&key, &entry).unwrap();
+
+    let cached = cache.get(&key).unwrap();
+    assert_eq!(cached.address, "ert1p...");
+    assert_eq!(cached.output.program, entry.output.program);
+}
+
+#[test]
+fn test_corrupt_entry_is_a_miss() {
+    let dir = tempfile::tempdir().unwrap();
+    let cache = CompileCache::new(dir.path());
+    std::fs::write(dir.path().join("abc.json"), "not json").unwrap();
+    assert!(cache.get("abc").is_none());
+}
+
+#[test]
+fn test_compile_stores_and_reuses() {
+    let dir = tempfile::tempdir().unwrap();
+    let cache = CompileCache::new(dir.path());
+    let entry = cache
+        .compile("fn main() {}", None, &[], Network::Regtest)
+        .unwrap();
+    let key = CompileCache::key("fn main() {}", &json!({}), "regtest");
+    assert_eq!(cache.get(&key).unwrap().output.cmr, entry.output.cmr);
+
+    // A hit is served from disk, even if the entry no longer matches
+    let stale = CacheEntry {
+        address: "cached".into(),
+        ..entry
+    };
+    cache.put(&key, &stale).unwrap();
+    let hit = cache
+        .compile("fn main() {}", None, &[], Network::Regtest)
+        .unwrap();
+    assert_eq!(hit.address, "cached");
+}
+
+#[test]
+fn test_program_cache() {
+    let dir = tempfile::tempdir().unwrap();
+    let file = dir.path().join("main.simf");
+    std::fs::write(&file, "fn main() {}").unwrap();
+
+    let programs = ProgramCache::default();
+    let program = programs.load(&file, None, &[]).unwrap();
+    assert_eq!(
+        programs
+            .instantiate("fn main() {}", None, &[])
+            .unwrap()
+            .cmr(),
+        program.cmr()
+    );
+    assert!(programs.instantiate("fn main() {", None, &[]).is_err());
+}
@@ -0,0 +1,66 @@
+//! Unit tests for custom chain parameters
+
+use musk::elements::{AssetId, BlockHash};
+use spray::chain::ChainParams;
+use std::str::FromStr;
+
+const GENESIS: &str = "a771da8e52ee6ad581ed1e9a99825e5b3b7992225534eaa2ae23244fe26ab1c1";
+
+#[test]
+fn test_node_config_without_chain_table() {
+    let chain = ChainParams::from_toml(
+        r#"
+        network = "testnet"
+
+        [rpc]
+        url = "http://localhost:7041"
+        "#,
+    )
+    .unwrap();
+    assert!(!chain.is_custom());
+    assert_eq!(chain.tag(), None);
+}
+
+#[test]
+fn test_federation_chain() {
+    let asset = "6f0279e9ed041c3d710a9f57d0c02928416460c4b722ae3457a11eec381c526d";
+    let chain = ChainParams::from_toml(&format!(
+        r#"
+        [chain]
+        genesis_hash = "{GENESIS}"
+        policy_asset = "{asset}"
+        magic = "A1B2C3D4"
+
+        [chain.address_params]
+        p2pkh_prefix = 56
+        p2sh_prefix = 58
+        blinded_prefix = 12
+        bech_hrp = "fed"
+        blech_hrp = "fedc"
+        "#
+    ))
+    .unwrap();
+
+    assert_eq!(chain.policy_asset, Some(AssetId::from_str(asset).unwrap()));
+    assert_eq!(chain.address_params.unwrap().p2sh_prefix, 58);
+    assert_eq!(chain.tag().as_deref(), Some("a1b2c3d4"));
+
+    let genesis = BlockHash::from_str(GENESIS).unwrap();
+    assert!(chain.check_genesis(genesis).is_ok());
+    let other = BlockHash::from_str(&"00".repeat(32)).unwrap();
+    let error = chain.check_genesis(other).unwrap_err().to_string();
+    assert!(error.contains("different chain"), "{error}");
+}
+
+#[test]
+fn test_invalid_chain_values() {
+    for table in [
+        r#"magic = "a1b2c3""#,
+        r#"magic = "zzzzzzzz""#,
+        r#"policy_asset = "not hex""#,
+        r#"genesis_hash = "00""#,
+    ] {
+        let result = ChainParams::from_toml(&format!("[chain]\n{table}"));
+        assert!(result.is_err(), "{table}");
+    }
+}
@@ -0,0 +1,32 @@
+//! Unit tests for the ElementsD client helpers
+
+use spray::client::{btc_to_sats, format_btc_amount};
+
+#[test]
+fn test_format_btc_amount_whole_coins() {
+    assert_eq!(format_btc_amount(0), "0.00000000");
+    assert_eq!(format_btc_amount(100_000_000), "1.00000000");
+    assert_eq!(
+        format_btc_amount(2_100_000_000_000_000),
+        "21000000.00000000"
+    );
+}
+
+#[test]
+fn test_format_btc_amount_is_exact() {
+    // Amounts that do not round-trip exactly through f64 division
+    assert_eq!(format_btc_amount(1), "0.00000001");
+    assert_eq!(format_btc_amount(99_999_999), "0.99999999");
+    assert_eq!(
+        format_btc_amount(2_099_999_999_999_999),
+        "20999999.99999999"
+    );
+}
+
+#[test]
+fn test_btc_to_sats_round_trip() {
+    for sats in [0, 1, 4_540, 99_999_999, 100_000_000, 2_099_999_999_999_999] {
+        let btc: f64 = format_btc_amount(sats).parse().unwrap();
+        assert_eq!(btc_to_sats(btc), sats);
+    }
+}
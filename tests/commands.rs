@@ -1,6 +1,9 @@
 //! Unit tests for command helpers
 
-use spray::commands::compile::OutputFormat;
+#![cfg(feature = "cli")]
+
+use spray::commands::compile::{artifact_path, read_source, OutputFormat};
+use std::path::Path;
 
 #[test]
 fn test_output_format_parse_json() {
@@ -46,6 +49,26 @@ fn test_output_format_equality() {
     assert_ne!(OutputFormat::Base64, OutputFormat::Hex);
 }
 
+#[test]
+fn test_artifact_path() {
+    assert_eq!(
+        artifact_path(
+            Path::new("build"),
+            Path::new("contracts/p2pk.simf"),
+            OutputFormat::Json
+        ),
+        Path::new("build/p2pk.json")
+    );
+    assert_eq!(
+        artifact_path(
+            Path::new("build"),
+            Path::new("vault.v2.simf"),
+            OutputFormat::Hex
+        ),
+        Path::new("build/vault.v2.hex")
+    );
+}
+
 // Tests for parse_utxo_ref
 use spray::commands::parse_utxo_ref;
 
@@ -112,3 +135,20 @@ fn test_parse_utxo_ref_too_many_parts() {
 
     assert!(result.is_err(), "Should fail with too many parts");
 }
+
+#[test]
+fn test_read_source_from_stdin() {
+    let source = read_source(Path::new("-"), "fn main() {}".as_bytes()).unwrap();
+    assert_eq!(source, "fn main() {}");
+}
+
+#[test]
+fn test_read_source_from_file() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("program.simf");
+    std::fs::write(&path, "fn main() {}").unwrap();
+
+    // stdin is not read when a path is given
+    let source = read_source(&path, "ignored".as_bytes()).unwrap();
+    assert_eq!(source, "fn main() {}");
+}
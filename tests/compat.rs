@@ -0,0 +1,59 @@
+//! Unit tests for node compatibility checks
+
+use serde_json::json;
+use spray::compat::NodeInfo;
+use spray::SprayError;
+
+fn network_info(version: u64) -> serde_json::Value {
+    json!({ "version": version, "subversion": "/Elements Core:test/" })
+}
+
+#[test]
+fn test_compatible_node() {
+    let deployments = json!({ "deployments": { "simplicity": { "active": true } } });
+    let info = NodeInfo::from_rpc(&network_info(230_300), &deployments).unwrap();
+
+    assert!(info.is_active("simplicity"));
+    assert!(info.check().is_ok());
+}
+
+#[test]
+fn test_softforks_fallback() {
+    let blockchain_info = json!({ "softforks": { "simplicity": { "active": true } } });
+    let info = NodeInfo::from_rpc(&network_info(230_300), &blockchain_info).unwrap();
+
+    assert!(info.is_active("simplicity"));
+}
+
+#[test]
+fn test_old_version_rejected() {
+    let deployments = json!({ "deployments": { "simplicity": { "active": true } } });
+    let info = NodeInfo::from_rpc(&network_info(220_100), &deployments).unwrap();
+
+    assert_eq!(info.version_string(), "22.1.0");
+    let err = info.check().unwrap_err();
+    assert!(matches!(err, SprayError::IncompatibleNode(_)));
+    assert!(err.to_string().contains("22.1.0"));
+}
+
+#[test]
+fn test_inactive_deployment_rejected() {
+    let deployments = json!({ "deployments": { "simplicity": { "active": false } } });
+    let info = NodeInfo::from_rpc(&network_info(230_300), &deployments).unwrap();
+
+    let err = info.check().unwrap_err();
+    assert!(err.to_string().contains("not active"));
+}
+
+#[test]
+fn test_unknown_deployment_rejected() {
+    let info = NodeInfo::from_rpc(&network_info(230_300), &json!({})).unwrap();
+
+    let err = info.check().unwrap_err();
+    assert!(err.to_string().contains("does not know"));
+}
+
+#[test]
+fn test_missing_version() {
+    assert!(NodeInfo::from_rpc(&json!({}), &json!({})).is_err());
+}
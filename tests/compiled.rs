@@ -1,18 +1,12 @@
 //! Unit tests for CompiledOutput serialization
 
-use spray::compiled::CompiledOutput;
-use std::collections::HashMap;
+use spray::compiled::{CompiledOutput, FORMAT_VERSION};
 
 #[test]
 fn test_compiled_output_serialization() {
-    let output = CompiledOutput {
-        cmr: "deadbeef".to_string(),
-        program: "SGVsbG8gV29ybGQ=".to_string(), // "Hello World" in base64
-        witness: Some("dGVzdA==".to_string()),   // "test" in base64
-        witness_types: HashMap::new(),
-        program_size: 11,
-        source: Some("fn main() { assert!(true); }".to_string()),
-    };
+    let mut output = CompiledOutput::new("deadbeef", b"Hello World");
+    output.witness = Some("dGVzdA==".to_string());
+    output.source = Some("fn main() { assert!(true); }".to_string());
 
     // Serialize to JSON
     let json = serde_json::to_string(&output).expect("Failed to serialize");
@@ -57,14 +51,7 @@ fn test_from_compiled_creates_valid_output() {
 
 #[test]
 fn test_decode_program_base64() {
-    let output = CompiledOutput {
-        cmr: "deadbeef".to_string(),
-        program: "SGVsbG8gV29ybGQ=".to_string(), // "Hello World" in base64
-        witness: None,
-        witness_types: HashMap::new(),
-        program_size: 11,
-        source: None,
-    };
+    let output = CompiledOutput::new("deadbeef", b"Hello World");
 
     let decoded = output.decode_program().expect("Failed to decode");
     assert_eq!(decoded, b"Hello World");
@@ -72,14 +59,8 @@ fn test_decode_program_base64() {
 
 #[test]
 fn test_decode_witness_base64() {
-    let output = CompiledOutput {
-        cmr: "deadbeef".to_string(),
-        program: "SGVsbG8=".to_string(),
-        witness: Some("dGVzdCB3aXRuZXNz".to_string()), // "test witness" in base64
-        witness_types: HashMap::new(),
-        program_size: 5,
-        source: None,
-    };
+    let mut output = CompiledOutput::new("deadbeef", b"Hello");
+    output.witness = Some("dGVzdCB3aXRuZXNz".to_string());
 
     let decoded = output.decode_witness().expect("Failed to decode");
     assert_eq!(decoded, b"test witness");
@@ -87,29 +68,41 @@ fn test_decode_witness_base64() {
 
 #[test]
 fn test_decode_empty_witness() {
-    let output = CompiledOutput {
-        cmr: "deadbeef".to_string(),
-        program: "SGVsbG8=".to_string(),
-        witness: None,
-        witness_types: HashMap::new(),
-        program_size: 5,
-        source: None,
-    };
+    let output = CompiledOutput::new("deadbeef", b"Hello");
 
     let decoded = output.decode_witness().expect("Failed to decode");
     assert!(decoded.is_empty());
 }
 
+#[test]
+fn test_from_compiled_extracts_witness_types() {
+    let program = musk::Program::from_source(
+        "fn main() { let x: u32 = witness::X; assert!(jet::eq_32(x, 42)); }",
+    )
+    .expect("Failed to parse program");
+    let compiled = program
+        .instantiate(musk::Arguments::default())
+        .expect("Failed to compile");
+
+    let output = CompiledOutput::from_compiled(&compiled, None);
+    assert_eq!(output.witness_types.len(), 1);
+    assert_eq!(
+        output.witness_types.get("X").map(String::as_str),
+        Some("u32")
+    );
+
+    let none = musk::Program::from_source("fn main() { assert!(true); }")
+        .expect("Failed to parse program")
+        .instantiate(musk::Arguments::default())
+        .expect("Failed to compile");
+    assert!(CompiledOutput::from_compiled(&none, None)
+        .witness_types
+        .is_empty());
+}
+
 #[test]
 fn test_serialization_skips_none_fields() {
-    let output = CompiledOutput {
-        cmr: "deadbeef".to_string(),
-        program: "SGVsbG8=".to_string(),
-        witness: None,
-        witness_types: HashMap::new(),
-        program_size: 5,
-        source: None,
-    };
+    let output = CompiledOutput::new("deadbeef", b"Hello");
 
     let json = serde_json::to_string(&output).expect("Failed to serialize");
 
@@ -118,3 +111,69 @@ fn test_serialization_skips_none_fields() {
     assert!(!json.contains("\"witness\":"));
     assert!(!json.contains("\"source\":"));
 }
+
+#[test]
+fn test_from_program_text_accepts_hex_and_base64() {
+    let from_hex = CompiledOutput::from_program_text("0xdeadbeef").expect("Failed to parse hex");
+    let from_base64 =
+        CompiledOutput::from_program_text("3q2+7w==\n").expect("Failed to parse base64");
+
+    assert_eq!(from_hex.decode_program().unwrap(), [0xde, 0xad, 0xbe, 0xef]);
+    assert_eq!(from_hex.program, from_base64.program);
+    assert_eq!(from_hex.program_size, 4);
+    assert!(from_hex.cmr.is_empty());
+    assert!(from_hex.witness.is_none());
+
+    assert!(CompiledOutput::from_program_text("not a program!").is_err());
+}
+
+#[test]
+fn test_format_version_compatibility() {
+    // Artifacts from before the format was versioned, and with fields this
+    // version does not know, still load
+    let legacy = r#"{"cmr":"deadbeef","program":"SGVsbG8=","witness_types":{},"program_size":5,"added_later":true}"#;
+    let output = CompiledOutput::from_json(legacy).expect("Failed to load legacy artifact");
+    assert_eq!(output.format_version, 0);
+    assert_eq!(output.program_size, 5);
+
+    let current = CompiledOutput::from_program_text("SGVsbG8=").unwrap();
+    assert_eq!(current.format_version, FORMAT_VERSION);
+    let json = serde_json::to_string(&current).unwrap();
+    assert!(json.contains(&format!("\"format_version\":{FORMAT_VERSION}")));
+
+    let newer = legacy.replacen(
+        '{',
+        &format!("{{\"format_version\":{},", FORMAT_VERSION + 1),
+        1,
+    );
+    let error = CompiledOutput::from_json(&newer).unwrap_err().to_string();
+    assert!(error.contains("newer than this spray reads"), "{error}");
+    assert!(serde_json::from_str::<CompiledOutput>(&newer).is_err());
+}
+
+#[test]
+fn test_addresses_embedded() {
+    let program = musk::Program::from_source("fn main() { assert!(true); }")
+        .expect("Failed to parse program")
+        .instantiate(musk::Arguments::default())
+        .expect("Failed to compile");
+    let output = CompiledOutput::from_compiled(&program, None);
+
+    let networks: Vec<&str> = output.addresses.keys().map(String::as_str).collect();
+    assert_eq!(networks, ["liquid", "regtest", "testnet"]);
+    assert_eq!(
+        output.addresses["regtest"],
+        spray::interop::derive_address(&output.cmr, &musk::elements::AddressParams::ELEMENTS)
+            .unwrap()
+            .to_string()
+    );
+    assert_eq!(output.script_pubkey.len(), 68);
+
+    let json = serde_json::to_value(&output).unwrap();
+    assert_eq!(json["addresses"]["liquid"], output.addresses["liquid"]);
+
+    // Without a CMR there is nothing to derive
+    let bare = CompiledOutput::from_program_text("SGVsbG8=").unwrap();
+    assert!(bare.addresses.is_empty());
+    assert!(bare.script_pubkey.is_empty());
+}
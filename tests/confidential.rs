@@ -0,0 +1,28 @@
+//! Unit tests for confidential transaction helpers
+
+use spray::confidential::{explicit_secrets, parse_blinding_key};
+
+#[test]
+fn test_parse_blinding_key() {
+    let hex = "01".repeat(32);
+    assert!(parse_blinding_key(&hex).is_ok());
+    assert!(parse_blinding_key(&format!("0x{hex}")).is_ok());
+}
+
+#[test]
+fn test_parse_blinding_key_invalid() {
+    assert!(parse_blinding_key("not-a-key").is_err());
+    assert!(parse_blinding_key(&"00".repeat(32)).is_err());
+    assert!(parse_blinding_key(&"01".repeat(16)).is_err());
+}
+
+#[test]
+fn test_explicit_secrets() {
+    let asset = "25b251070e29ca19043cf33ccd7324e2ddab03ecc4ae0b5e77c4fc0e5cf6c95a"
+        .parse()
+        .unwrap();
+    let secrets = explicit_secrets(asset, 100_000);
+
+    assert_eq!(secrets.asset, asset);
+    assert_eq!(secrets.value, 100_000);
+}
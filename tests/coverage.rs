@@ -0,0 +1,60 @@
+//! Unit tests for branch coverage
+
+use spray::coverage::{BranchPoint, Coverage};
+use spray::report::{SuiteReport, TestRecord};
+
+fn coverage(branches: &[(bool, bool)]) -> Coverage {
+    Coverage {
+        program: "vault.simf".into(),
+        cmr: "00".repeat(32),
+        spends: 2,
+        branches: branches
+            .iter()
+            .enumerate()
+            .map(|(index, &(left, right))| BranchPoint {
+                index,
+                cmr: format!("{index:02x}").repeat(32),
+                left,
+                right,
+            })
+            .collect(),
+    }
+}
+
+#[test]
+fn test_taken_and_total() {
+    let coverage = coverage(&[(true, true), (true, false), (false, false)]);
+    assert_eq!(coverage.taken(), 3);
+    assert_eq!(coverage.total(), 6);
+}
+
+#[test]
+fn test_dead_branches_in_order() {
+    let coverage = coverage(&[(true, false), (false, true)]);
+    let dead: Vec<(usize, &str)> = coverage.dead().map(|(b, side)| (b.index, side)).collect();
+    assert_eq!(dead, vec![(0, "right"), (1, "left")]);
+}
+
+#[test]
+fn test_program_without_cases_is_fully_covered() {
+    let coverage = coverage(&[]);
+    assert_eq!(coverage.total(), 0);
+    assert_eq!(coverage.dead().count(), 0);
+}
+
+#[test]
+fn test_invalid_program_is_rejected() {
+    assert!(Coverage::new(&[0xff, 0xff]).is_err());
+}
+
+#[test]
+fn test_report_coverage_roundtrip() {
+    let mut report = SuiteReport::new(vec![TestRecord::failure("t", 1, "e".into())], 1);
+    let json = serde_json::to_value(&report).unwrap();
+    assert!(json.get("coverage").is_none(), "Empty coverage is omitted");
+
+    report.coverage.push(coverage(&[(true, false)]));
+    let json = serde_json::to_string(&report).unwrap();
+    let parsed: SuiteReport = serde_json::from_str(&json).unwrap();
+    assert_eq!(parsed.coverage, report.coverage);
+}
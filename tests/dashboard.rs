@@ -0,0 +1,94 @@
+//! Unit tests for the test dashboard
+
+use musk::elements::{LockTime, Script, Transaction, TxIn, TxOut};
+use serde_json::json;
+use spray::dashboard::{spend_events, Dashboard, TestStatus, RECENT_EVENTS};
+use spray::events::EventSchema;
+use spray::report::TestRecord;
+use spray::SpendDetails;
+
+fn passed(name: &str) -> TestRecord {
+    TestRecord {
+        name: name.into(),
+        success: true,
+        duration_ms: 10,
+        txid: Some("aa".repeat(32)),
+        error: None,
+        stage: None,
+        conf_target: None,
+        budget: None,
+    }
+}
+
+#[test]
+fn test_progress_counts() {
+    let mut dashboard = Dashboard::new([("local", "a"), ("local", "b"), ("remote", "c")]);
+    assert_eq!(dashboard.total(), 3);
+    assert_eq!(dashboard.status(0), TestStatus::Pending);
+
+    dashboard.start(0);
+    dashboard.finish(0, passed("a"));
+    dashboard.start(1);
+    dashboard.finish(1, TestRecord::failure("b", 5, "boom".into()));
+    dashboard.start(2);
+
+    assert_eq!(dashboard.status(0), TestStatus::Passed);
+    assert_eq!(dashboard.status(1), TestStatus::Failed);
+    assert_eq!(dashboard.status(2), TestStatus::Running);
+    assert_eq!(dashboard.finished(), 2);
+    assert_eq!(dashboard.failed(), 1);
+}
+
+#[test]
+fn test_expand_failures_shows_every_error_line() {
+    colored::control::set_override(false);
+    let mut dashboard = Dashboard::new([("local", "a"), ("local", "b")]);
+    dashboard.finish(0, passed("a"));
+    dashboard.finish(1, TestRecord::failure("b", 5, "first\nsecond".into()));
+
+    dashboard.expand_failures();
+    let frame = dashboard.render();
+    assert!(frame.contains("error: first"));
+    assert!(frame.contains("second"));
+    assert!(!frame.contains("txid:"), "Passed tests stay collapsed");
+
+    dashboard.toggle(1);
+    assert!(!dashboard.render().contains("first"));
+}
+
+#[test]
+fn test_events_are_bounded() {
+    let mut dashboard = Dashboard::new([("local", "a")]);
+    for i in 0..RECENT_EVENTS + 2 {
+        dashboard.event(format!("event {i}"));
+    }
+    let frame = dashboard.render();
+    assert!(!frame.contains("event 1\n"), "Oldest events are dropped");
+    assert!(frame.contains(&format!("event {}", RECENT_EVENTS + 1)));
+}
+
+#[test]
+fn test_spend_events_decode_contract_events() {
+    let schema: EventSchema = serde_json::from_value(json!({
+        "events": [{ "name": "Deposit", "tag": "0x01", "fields": [{ "name": "amount", "type": "u8" }] }]
+    }))
+    .unwrap();
+    let tx = Transaction {
+        version: 2,
+        lock_time: LockTime::ZERO,
+        input: vec![TxIn::default()],
+        output: vec![TxOut {
+            script_pubkey: Script::new_op_return(&[0x01, 0x07]),
+            ..TxOut::default()
+        }],
+    };
+    let spend = SpendDetails { tx, fee: 300 };
+
+    let events = spend_events(&spend, None);
+    assert_eq!(events.len(), 2);
+    assert!(events[0].starts_with("spent "));
+    assert!(events[1].contains("fee 300 sat"));
+
+    let events = spend_events(&spend, Some(&schema));
+    assert_eq!(events[2], r#"event Deposit {"amount":7}"#);
+}
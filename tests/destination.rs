@@ -0,0 +1,67 @@
+//! Unit tests for spend destinations
+
+use serde_json::{json, Value};
+use spray::destination::Destination;
+use spray::error::SprayError;
+
+fn no_call(_: &str, _: &[Value]) -> Result<Value, SprayError> {
+    panic!("unexpected RPC call")
+}
+
+#[test]
+fn test_parse_round_trips() {
+    for s in ["wallet", "raw(0014deadbeef)", "wpkh(02abc)#checksum"] {
+        assert_eq!(s.parse::<Destination>().unwrap().to_string(), s);
+    }
+    assert!("raw(00".parse::<Destination>().is_err());
+}
+
+#[test]
+fn test_raw_descriptor_resolves_locally() {
+    let destination: Destination = "raw(0014deadbeef)#abcd1234".parse().unwrap();
+    let resolved = destination
+        .resolve(no_call, || panic!("unexpected wallet call"))
+        .unwrap();
+    assert_eq!(
+        resolved.script_pubkey.as_bytes(),
+        &[0x00, 0x14, 0xde, 0xad, 0xbe, 0xef]
+    );
+    assert!(resolved.address.is_none());
+}
+
+#[test]
+fn test_descriptor_derives_through_node() {
+    let destination: Destination = "wpkh(xpub/0/*)".parse().unwrap();
+    let err = destination
+        .resolve(
+            |method, params| match method {
+                "getdescriptorinfo" => {
+                    assert_eq!(params, [json!("wpkh(xpub/0/*)")]);
+                    Ok(json!({ "descriptor": "wpkh(xpub/0/*)#c", "isrange": true }))
+                }
+                "deriveaddresses" => {
+                    assert_eq!(params, [json!("wpkh(xpub/0/*)#c"), json!([0, 0])]);
+                    Ok(json!([]))
+                }
+                _ => panic!("unexpected call {method}"),
+            },
+            || panic!("unexpected wallet call"),
+        )
+        .unwrap_err();
+    assert!(err.to_string().contains("single address"), "{err}");
+}
+
+#[test]
+fn test_deserialize_from_manifest_string() {
+    #[derive(serde::Deserialize)]
+    struct Spec {
+        #[serde(default)]
+        destination: Destination,
+    }
+
+    let spec: Spec = toml::from_str(r#"destination = "raw(51)""#).unwrap();
+    assert_eq!(spec.destination, Destination::Descriptor("raw(51)".into()));
+    let spec: Spec = toml::from_str("").unwrap();
+    assert_eq!(spec.destination, Destination::Wallet);
+    assert!(toml::from_str::<Spec>(r#"destination = "nope""#).is_err());
+}
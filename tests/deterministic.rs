@@ -0,0 +1,49 @@
+//! Unit tests for deterministic mode
+
+use rand::Rng;
+use serde_json::json;
+use spray::deterministic::{self, select_input, FundingInput, SEED};
+
+#[test]
+fn test_select_input_skips_confidential_and_unspendable() {
+    let unspent = json!([
+        { "txid": "aa", "vout": 0, "amount": 0.1, "asset": "btc", "amountcommitment": "08ff" },
+        { "txid": "bb", "vout": 0, "amount": 0.1, "asset": "btc", "spendable": false },
+        { "txid": "cc", "vout": 0, "amount": 0.1, "asset": "other" },
+        { "txid": "dd", "vout": 2, "amount": 0.2, "asset": "btc" },
+    ]);
+
+    assert_eq!(
+        select_input(&unspent, "btc", 1_000_000),
+        Some(FundingInput {
+            txid: "dd".into(),
+            vout: 2,
+            amount: 20_000_000,
+        })
+    );
+    assert!(select_input(&json!({}), "btc", 1).is_none());
+}
+
+#[test]
+fn test_select_input_breaks_ties_by_outpoint() {
+    let unspent = json!([
+        { "txid": "ff", "vout": 0, "amount": 1.0, "asset": "btc" },
+        { "txid": "ee", "vout": 1, "amount": 1.0, "asset": "btc" },
+        { "txid": "ee", "vout": 0, "amount": 1.0, "asset": "btc" },
+    ]);
+
+    let input = select_input(&unspent, "btc", 100_000_000).unwrap();
+    assert_eq!((input.txid.as_str(), input.vout), ("ee", 0));
+}
+
+#[test]
+fn test_seeds_are_fixed_when_enabled() {
+    assert_eq!(deterministic::seed(true), SEED);
+    let a: [u8; 32] = deterministic::rng(true, b"output 0").gen();
+    let b: [u8; 32] = deterministic::rng(true, b"output 0").gen();
+    assert_eq!(a, b);
+    let other: [u8; 32] = deterministic::rng(true, b"output 1").gen();
+    assert_ne!(a, other);
+    let c: [u8; 32] = deterministic::rng(false, b"output 0").gen();
+    assert_ne!(a, c);
+}
@@ -0,0 +1,69 @@
+//! Unit tests for program disassembly
+
+use musk::{Arguments, Program};
+use spray::compiled::CompiledOutput;
+use spray::disasm::{DisasmNode, Disassembly};
+
+fn node(index: usize, op: &str, children: Vec<usize>) -> DisasmNode {
+    DisasmNode {
+        index,
+        op: op.to_string(),
+        children,
+        cmr: format!("{index:064x}"),
+        imr: None,
+    }
+}
+
+#[test]
+fn test_tree_expands_shared_nodes_once() {
+    let disassembly = Disassembly {
+        nodes: vec![
+            node(0, "iden", vec![]),
+            node(1, "pair", vec![0, 0]),
+            node(2, "comp", vec![1, 0]),
+        ],
+    };
+
+    assert_eq!(
+        disassembly.tree(),
+        "%2 comp\n  %1 pair\n    %0 iden\n    %0 (shared)\n  %0 (shared)\n"
+    );
+}
+
+#[test]
+fn test_display_lists_nodes_in_post_order() {
+    let mut root = node(1, "take", vec![0]);
+    root.imr = Some("ab".repeat(32));
+    let disassembly = Disassembly {
+        nodes: vec![node(0, "unit", vec![]), root],
+    };
+
+    let listing = disassembly.to_string();
+    let lines: Vec<&str> = listing.lines().collect();
+    assert_eq!(lines.len(), 2);
+    assert!(lines[0].starts_with("%0  unit"));
+    assert!(lines[0].ends_with(&format!("cmr {:064x}", 0)));
+    assert!(lines[1].starts_with("%1  take %0"));
+    assert!(lines[1].ends_with(&format!("imr {}", "ab".repeat(32))));
+}
+
+#[test]
+fn test_disassemble_compiled_program() {
+    let program = Program::from_source("fn main() { assert!(true); }").unwrap();
+    let compiled = program.instantiate(Arguments::default()).unwrap();
+    let output = CompiledOutput::from_compiled(&compiled, None);
+
+    let disassembly = output.disassemble().expect("Failed to disassemble");
+    let root = disassembly.root().expect("Program has no nodes");
+    assert_eq!(root.cmr, output.cmr);
+    assert!(root.imr.is_none(), "IMRs need a witness");
+    assert!(disassembly
+        .nodes
+        .iter()
+        .all(|n| n.children.iter().all(|&c| c < n.index)));
+}
+
+#[test]
+fn test_decode_rejects_truncated_program() {
+    assert!(Disassembly::decode(&[], None).is_err());
+}
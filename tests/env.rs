@@ -0,0 +1,29 @@
+//! Unit tests for test environment helpers
+
+#![cfg(feature = "daemon")]
+
+use spray::env::log_excerpt;
+use spray::FailureStage;
+
+#[test]
+fn test_log_excerpt_takes_last_lines() {
+    let log = "2024-01-01T00:00:00Z Loaded wallet\n\
+               2024-01-01T00:00:01Z AcceptToMemoryPool: non-mandatory-script-verify-flag\n";
+
+    assert_eq!(
+        log_excerpt(log, 1),
+        "\n  debug.log (last 1 lines):\n    2024-01-01T00:00:01Z AcceptToMemoryPool: non-mandatory-script-verify-flag"
+    );
+    assert_eq!(log_excerpt(log, 10).lines().count(), 3);
+    assert_eq!(log_excerpt(log, 0), "");
+}
+
+#[test]
+fn test_node_side_stages() {
+    assert!(FailureStage::Funding.is_node_side());
+    assert!(FailureStage::NodeRejection.is_node_side());
+    assert!(FailureStage::Timeout.is_node_side());
+    assert!(!FailureStage::Satisfaction.is_node_side());
+    assert!(!FailureStage::Hook.is_node_side());
+    assert!(!FailureStage::Config.is_node_side());
+}
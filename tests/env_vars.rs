@@ -0,0 +1,34 @@
+//! Unit tests for configuring the node through environment variables
+//!
+//! These set process-wide environment variables, so they live in their own
+//! test binary.
+
+use musk::Network;
+use spray::network::{rpc_config_from_env, RPC_PASSWORD_ENV, RPC_URL_ENV, RPC_USER_ENV};
+
+#[test]
+fn test_rpc_config_from_env() {
+    std::env::remove_var(RPC_URL_ENV);
+    std::env::set_var(RPC_USER_ENV, "ci");
+    assert_eq!(rpc_config_from_env(Network::Testnet, None), None);
+
+    std::env::set_var(RPC_URL_ENV, "http://node:7041");
+    std::env::set_var(RPC_PASSWORD_ENV, "secret");
+    let config: toml::Table =
+        toml::from_str(&rpc_config_from_env(Network::Testnet, None).unwrap()).unwrap();
+    assert_eq!(config["network"].as_str(), Some("testnet"));
+    assert_eq!(config["rpc"]["url"].as_str(), Some("http://node:7041"));
+    assert_eq!(config["rpc"]["user"].as_str(), Some("ci"));
+    assert_eq!(config["rpc"]["password"].as_str(), Some("secret"));
+
+    let config: toml::Table =
+        toml::from_str(&rpc_config_from_env(Network::Testnet, Some("hot")).unwrap()).unwrap();
+    assert_eq!(
+        config["rpc"]["url"].as_str(),
+        Some("http://node:7041/wallet/hot")
+    );
+
+    for name in [RPC_URL_ENV, RPC_USER_ENV, RPC_PASSWORD_ENV] {
+        std::env::remove_var(name);
+    }
+}
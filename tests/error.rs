@@ -0,0 +1,92 @@
+//! Unit tests for error kinds, codes and context
+
+use spray::error::{ExitCode, ResultExt};
+use spray::SprayError;
+
+#[test]
+fn test_exit_codes_by_class() {
+    let cases = [
+        (
+            SprayError::ConfigError("missing".into()),
+            ExitCode::ConfigError,
+        ),
+        (
+            SprayError::FileFormatError("bad".into()),
+            ExitCode::ConfigError,
+        ),
+        (
+            SprayError::DaemonError("no elementsd".into()),
+            ExitCode::NodeUnavailable,
+        ),
+        (
+            SprayError::NodeUnavailable("refused".into()),
+            ExitCode::NodeUnavailable,
+        ),
+        (
+            SprayError::MempoolReject("missing-inputs".into()),
+            ExitCode::BroadcastRejected,
+        ),
+        (SprayError::RpcError("timeout".into()), ExitCode::Failure),
+        (SprayError::TestError("failed".into()), ExitCode::Failure),
+    ];
+    for (error, expected) in cases {
+        assert_eq!(error.exit_code(), expected, "{error}");
+    }
+}
+
+#[test]
+fn test_exit_codes_are_distinct() {
+    let codes = [
+        ExitCode::Failure,
+        ExitCode::TestFailure,
+        ExitCode::CompileError,
+        ExitCode::ConfigError,
+        ExitCode::NodeUnavailable,
+        ExitCode::BroadcastRejected,
+    ]
+    .map(ExitCode::code);
+    let mut sorted = codes.to_vec();
+    sorted.sort_unstable();
+    sorted.dedup();
+    assert_eq!(sorted.len(), codes.len());
+    assert!(!codes.contains(&0) && !codes.contains(&2));
+}
+
+#[test]
+fn test_context_keeps_kind() {
+    let error = SprayError::MempoolReject("bad-txns-inputs-missingorspent".into())
+        .context("Failed to broadcast the spend")
+        .context("Test 'transfer' failed");
+    assert_eq!(error.to_string(), "Test 'transfer' failed");
+    assert_eq!(error.message_id(), "error.mempool_reject");
+    assert_eq!(error.code(), 201);
+    assert_eq!(error.exit_code(), ExitCode::BroadcastRejected);
+    assert!(matches!(error.root_cause(), SprayError::MempoolReject(_)));
+    assert_eq!(
+        error.detail(),
+        "Test 'transfer' failed: Failed to broadcast the spend: bad-txns-inputs-missingorspent"
+    );
+
+    let source = std::error::Error::source(&error).unwrap();
+    assert_eq!(source.to_string(), "Failed to broadcast the spend");
+}
+
+#[test]
+fn test_result_context() {
+    let result: Result<(), std::io::Error> = Err(std::io::Error::other("denied"));
+    let error = result.context("Failed to read spray.toml").unwrap_err();
+    assert_eq!(error.message_id(), "error.io");
+    assert_eq!(error.detail(), "Failed to read spray.toml: denied");
+}
+
+#[test]
+fn test_rpc_classifies_insufficient_funds() {
+    assert!(matches!(
+        SprayError::rpc("Insufficient funds"),
+        SprayError::InsufficientFunds(_)
+    ));
+    assert!(matches!(
+        SprayError::rpc("Block not found"),
+        SprayError::RpcError(_)
+    ));
+}
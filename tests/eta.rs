@@ -0,0 +1,85 @@
+//! Unit tests for completion-time estimates
+
+use spray::eta::{DurationHistory, Eta};
+use spray::report::{SuiteReport, TestRecord};
+
+fn passed(name: &str, duration_ms: u64) -> TestRecord {
+    TestRecord {
+        success: true,
+        error: None,
+        ..TestRecord::failure(name, duration_ms, String::new())
+    }
+}
+
+fn history(records: Vec<TestRecord>) -> DurationHistory {
+    let mut history = DurationHistory::new();
+    history.add(&SuiteReport::new(records, 1));
+    history
+}
+
+#[test]
+fn test_expected_is_median_of_passing_runs() {
+    let history = history(vec![
+        passed("spend", 100),
+        passed("spend", 900),
+        passed("spend", 300),
+        TestRecord::failure("spend", 10, "boom".into()),
+        TestRecord::failure("broken", 10, "boom".into()),
+    ]);
+    assert_eq!(history.expected_ms("spend"), Some(300));
+    assert_eq!(history.expected_ms("broken"), None);
+    assert!(DurationHistory::new().is_empty());
+}
+
+#[test]
+fn test_load_report_files_and_directories() {
+    let temp = tempfile::tempdir().unwrap();
+    let dir = temp.path();
+    std::fs::create_dir_all(dir.join("reports")).unwrap();
+    SuiteReport::new(vec![passed("a", 100)], 1)
+        .write_json(&dir.join("reports/local.json"))
+        .unwrap();
+    std::fs::write(dir.join("reports/notes.txt"), "not a report").unwrap();
+    SuiteReport::new(vec![passed("b", 200)], 1)
+        .write_json(&dir.join("report.json"))
+        .unwrap();
+
+    let history = DurationHistory::load(&[dir.join("reports"), dir.join("report.json")]).unwrap();
+    assert_eq!(history.expected_ms("a"), Some(100));
+    assert_eq!(history.expected_ms("b"), Some(200));
+
+    std::fs::write(dir.join("bad.json"), "{}").unwrap();
+    assert!(DurationHistory::load(&[dir.join("bad.json")]).is_err());
+}
+
+#[test]
+fn test_remaining_uses_history_then_run_average() {
+    let history = history(vec![passed("a", 1_000), passed("b", 3_000)]);
+    let mut eta = Eta::new(&history, ["a", "b", "c"], 2.0);
+    assert_eq!(eta.total(), 3);
+    // c has no history: the average expected duration stands in
+    assert_eq!(eta.remaining_ms(), 1_000 + 3_000 + 2_000);
+
+    assert!(eta.finish(0, 500).is_none());
+    assert_eq!(eta.finished(), 1);
+    // c now counts with the average of finished tests
+    assert_eq!(eta.remaining_ms(), 3_000 + 500);
+
+    eta.finish(1, 2_500);
+    eta.finish(2, 10);
+    assert_eq!(eta.remaining_ms(), 0);
+}
+
+#[test]
+fn test_flags_slow_tests() {
+    let history = history(vec![passed("a", 1_000), passed("b", 1_000)]);
+    let mut eta = Eta::new(&history, ["a", "b", "c"], 1.5);
+
+    assert!(eta.finish(0, 1_500).is_none());
+    let slow = eta.finish(1, 1_600).unwrap();
+    assert_eq!(slow.expected_ms, 1_000);
+    assert_eq!(slow.to_string(), "b took 1.6s, 1.6x its usual 1s");
+    // Without history, a test is never slow
+    assert!(eta.finish(2, 60_000).is_none());
+    assert_eq!(eta.slow(), [slow]);
+}
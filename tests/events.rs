@@ -0,0 +1,77 @@
+//! Unit tests for contract event extraction
+
+use serde_json::json;
+use spray::events::{block_events, op_return_payload, EventSchema};
+use spray::musk::elements::Script;
+use std::collections::HashSet;
+
+fn schema() -> EventSchema {
+    serde_json::from_value(json!({
+        "events": [
+            { "name": "Open", "tag": "0xaa01", "fields": [{ "name": "id", "type": "u16" }] },
+            { "name": "Close", "tag": "0xaa02" }
+        ]
+    }))
+    .unwrap()
+}
+
+fn output(hex: &str) -> serde_json::Value {
+    json!({ "scriptPubKey": { "hex": hex } })
+}
+
+#[test]
+fn test_op_return_payload() {
+    // OP_RETURN <aa01> <0007>
+    let script = Script::from(vec![0x6a, 0x02, 0xaa, 0x01, 0x02, 0x00, 0x07]);
+    assert_eq!(
+        op_return_payload(&script),
+        Some(vec![0xaa, 0x01, 0x00, 0x07])
+    );
+
+    let p2wpkh = Script::from(vec![0x00, 0x01, 0xff]);
+    assert_eq!(op_return_payload(&p2wpkh), None);
+}
+
+#[test]
+fn test_decode_unknown_and_truncated() {
+    let schema = schema();
+    assert!(schema.decode(&[0xbb]).is_none());
+    assert!(schema.decode(&[0xaa, 0x01, 0x00]).unwrap().is_err());
+
+    let (name, fields) = schema.decode(&[0xaa, 0x02]).unwrap().unwrap();
+    assert_eq!(name, "Close");
+    assert!(fields.is_empty());
+}
+
+#[test]
+fn test_block_events_follow_contract_outputs() {
+    let contract = Script::from(vec![0x51, 0x20, 0x01]);
+    let schema = schema();
+    let mut outpoints = HashSet::new();
+
+    // Funding pays to the contract and opens it
+    let funding = json!({ "tx": [{
+        "txid": "f0",
+        "vin": [{ "coinbase": "00" }],
+        "vout": [output("512001"), output("6a04aa010007")]
+    }]});
+    let events = block_events(&funding, 10, &contract, &mut outpoints, &schema).unwrap();
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].event, "Open");
+    assert_eq!(events[0].fields["id"], json!(7));
+    assert_eq!(
+        (events[0].txid.as_str(), events[0].vout, events[0].height),
+        ("f0", 1, 10)
+    );
+
+    // An unrelated transaction with a matching tag is ignored, the spend is not
+    let spend = json!({ "tx": [
+        { "txid": "01", "vin": [{ "txid": "ee", "vout": 0 }], "vout": [output("6a02aa02")] },
+        { "txid": "02", "vin": [{ "txid": "f0", "vout": 0 }], "vout": [output("6a02aa02")] }
+    ]});
+    let events = block_events(&spend, 11, &contract, &mut outpoints, &schema).unwrap();
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].txid, "02");
+    assert_eq!(events[0].event, "Close");
+    assert!(outpoints.is_empty());
+}
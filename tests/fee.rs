@@ -0,0 +1,72 @@
+//! Unit tests for fee policies
+
+use serde_json::json;
+use spray::fee::{
+    fee_for_vsize, parse_estimatesmartfee, parse_feerate, FeePolicy, FALLBACK_FEERATE,
+};
+
+#[test]
+fn test_fee_for_vsize_rounds_up() {
+    assert_eq!(fee_for_vsize(1.0, 250), 250);
+    assert_eq!(fee_for_vsize(0.1, 1_001), 101);
+    assert_eq!(fee_for_vsize(2.5, 3), 8);
+    assert_eq!(fee_for_vsize(0.0, 500), 0);
+}
+
+#[test]
+fn test_feerate() {
+    assert_eq!(FeePolicy::Fixed(1_000).feerate(|_| Some(5.0)), None);
+    assert_eq!(FeePolicy::Rate(2.0).feerate(|_| Some(5.0)), Some(2.0));
+    assert_eq!(
+        FeePolicy::Estimate { conf_target: 6 }.feerate(|target| {
+            assert_eq!(target, 6);
+            Some(5.0)
+        }),
+        Some(5.0)
+    );
+    assert_eq!(
+        FeePolicy::Estimate { conf_target: 6 }.feerate(|_| None),
+        Some(FALLBACK_FEERATE)
+    );
+}
+
+#[test]
+fn test_conf_target() {
+    assert_eq!(
+        FeePolicy::Estimate { conf_target: 3 }.conf_target(),
+        Some(3)
+    );
+    assert_eq!(FeePolicy::Rate(1.0).conf_target(), None);
+    assert_eq!(FeePolicy::default().conf_target(), None);
+}
+
+#[test]
+fn test_fixed_fee_does_not_build() {
+    let fee = FeePolicy::Fixed(1_234)
+        .compute(
+            |_| None,
+            |_| -> Result<_, ()> { panic!("should not build") },
+        )
+        .unwrap();
+    assert_eq!(fee, 1_234);
+}
+
+#[test]
+fn test_parse_estimatesmartfee() {
+    // 0.00001 BTC/kvB = 1 sat/vB
+    let response = json!({ "feerate": 0.00001, "blocks": 2 });
+    let rate = parse_estimatesmartfee(&response).unwrap();
+    assert!((rate - 1.0).abs() < 1e-9);
+
+    let no_estimate = json!({ "errors": ["Insufficient data or no feerate found"], "blocks": 0 });
+    assert_eq!(parse_estimatesmartfee(&no_estimate), None);
+}
+
+#[test]
+fn test_parse_feerate() {
+    assert_eq!(parse_feerate("0.1").unwrap(), 0.1);
+    assert_eq!(parse_feerate(" 2 ").unwrap(), 2.0);
+    for invalid in ["", "abc", "0", "-1", "NaN", "inf", "-inf"] {
+        assert!(parse_feerate(invalid).is_err(), "{invalid}");
+    }
+}
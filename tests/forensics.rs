@@ -0,0 +1,87 @@
+//! Unit tests for forensic analysis of past spends
+
+use musk::elements::hashes::Hash;
+use musk::elements::secp256k1_zkp::SecretKey;
+use musk::elements::{OutPoint, Transaction, TxIn, TxOut};
+use spray::forensics::{find_input, identify_signer, parse_key, SpendAnalysis};
+use spray::signing::{public_key, sign_digest};
+
+fn key(byte: u8) -> SecretKey {
+    SecretKey::from_slice(&[byte; 32]).unwrap()
+}
+
+fn spend(stack: Vec<Vec<u8>>) -> Transaction {
+    let mut input = TxIn {
+        previous_output: OutPoint::default(),
+        ..TxIn::default()
+    };
+    input.witness.script_witness = stack;
+    Transaction {
+        version: 2,
+        lock_time: musk::elements::LockTime::ZERO,
+        input: vec![TxIn::default(), input],
+        output: vec![],
+    }
+}
+
+#[test]
+fn test_parse_key() {
+    let pubkey = public_key(&key(1));
+    assert_eq!(
+        parse_key(&format!("alice = {pubkey}")).unwrap(),
+        ("alice".to_string(), pubkey)
+    );
+    assert_eq!(
+        parse_key(&pubkey.to_string()).unwrap(),
+        (pubkey.to_string(), pubkey)
+    );
+    assert!(parse_key("alice=").is_err());
+}
+
+#[test]
+fn test_identify_signer() {
+    let keys = vec![
+        ("alice".to_string(), public_key(&key(1))),
+        ("bob".to_string(), public_key(&key(2))),
+    ];
+    let sighash = [7; 32];
+
+    let signature = sign_digest(sighash, &key(2));
+    assert_eq!(identify_signer(sighash, &signature, &keys), Some("bob"));
+    assert_eq!(identify_signer([8; 32], &signature, &keys), None);
+
+    let stranger = sign_digest(sighash, &key(3));
+    assert_eq!(identify_signer(sighash, &stranger, &keys), None);
+}
+
+#[test]
+fn test_find_input() {
+    let cmr = simplicity::Cmr::from_byte_array([5; 32]);
+    let tx = spend(vec![
+        vec![],
+        vec![1],
+        cmr.to_byte_array().to_vec(),
+        vec![0xbe],
+    ]);
+    assert_eq!(find_input(&tx, cmr), Some(1));
+    assert_eq!(
+        find_input(&tx, simplicity::Cmr::from_byte_array([6; 32])),
+        None
+    );
+}
+
+#[test]
+fn test_analysis_rejects_non_simplicity_inputs() {
+    let tx = spend(vec![vec![1], vec![2]]);
+    let prevouts = vec![TxOut::default(), TxOut::default()];
+    let genesis = musk::elements::BlockHash::all_zeros();
+
+    let error = SpendAnalysis::new(&tx, &prevouts, 1, genesis, &[]).unwrap_err();
+    assert!(error.to_string().contains("expected 4 witness stack items"));
+
+    let error = SpendAnalysis::new(&tx, &prevouts[..1], 1, genesis, &[]).unwrap_err();
+    assert!(error.to_string().contains("Expected 2 prevouts"));
+
+    let error = SpendAnalysis::new(&tx, &prevouts, 5, genesis, &[]).unwrap_err();
+    assert!(error.to_string().contains("Input 5 not found"));
+}
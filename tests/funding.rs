@@ -0,0 +1,81 @@
+//! Unit tests for funding transaction shapes
+
+use spray::funding::{FundingOutput, FundingShape};
+use spray::interop::derive_address;
+
+fn contract_address() -> musk::elements::Address {
+    let cmr = "01".repeat(32);
+    derive_address(&cmr, &musk::elements::AddressParams::ELEMENTS).unwrap()
+}
+
+#[test]
+fn test_raw_outputs_follow_shape_order() {
+    let shape = FundingShape::new()
+        .wallet(5_000)
+        .contract()
+        .op_return(vec![0xde, 0xad]);
+
+    let mut next = 0;
+    let outputs = shape
+        .raw_outputs(&contract_address(), 100_000, || {
+            next += 1;
+            Ok(format!("wallet-{next}"))
+        })
+        .unwrap();
+
+    assert_eq!(
+        outputs,
+        serde_json::json!([
+            { "wallet-1": "0.00005000" },
+            { contract_address().to_string(): "0.00100000" },
+            { "data": "dead" },
+        ])
+    );
+}
+
+#[test]
+fn test_contract_index_requires_exactly_one_contract() {
+    assert!(FundingShape::new().wallet(1_000).contract_index().is_err());
+    assert!(FundingShape::new()
+        .contract()
+        .contract()
+        .contract_index()
+        .is_err());
+    assert_eq!(
+        FundingShape::new()
+            .op_return(Vec::new())
+            .op_return(Vec::new())
+            .contract()
+            .contract_index()
+            .unwrap(),
+        2
+    );
+}
+
+#[test]
+fn test_raw_outputs_rejects_invalid_shape() {
+    let result = FundingShape::new()
+        .wallet(1_000)
+        .raw_outputs(&contract_address(), 1_000, || Ok(String::new()));
+    assert!(result.is_err());
+}
+
+#[test]
+fn test_shape_serde_round_trip() {
+    let shape = FundingShape::new()
+        .contract()
+        .address(&contract_address(), 2_000);
+
+    let json = serde_json::to_string(&shape).unwrap();
+    assert!(json.starts_with('['), "Shape should serialize as a list");
+
+    let parsed: FundingShape = serde_json::from_str(&json).unwrap();
+    assert_eq!(parsed, shape);
+    assert_eq!(
+        parsed.outputs()[1],
+        FundingOutput::Address {
+            address: contract_address().to_string(),
+            amount: 2_000,
+        }
+    );
+}
@@ -0,0 +1,125 @@
+//! Unit tests for witness mutation fuzzing
+
+use serde_json::{json, Map, Value};
+use spray::fuzz::{FuzzReport, MutationKind, Mutator, Outcome};
+use spray::test::FailureStage;
+use spray::value::ValueType;
+use std::collections::HashMap;
+
+fn witness(value: Value) -> Map<String, Value> {
+    value.as_object().unwrap().clone()
+}
+
+fn types(pairs: &[(&str, &str)]) -> HashMap<String, ValueType> {
+    pairs
+        .iter()
+        .map(|(name, ty)| ((*name).to_string(), ty.parse().unwrap()))
+        .collect()
+}
+
+#[test]
+fn test_mutations_are_reproducible() {
+    let witness = witness(json!({ "SIG": format!("0x{}", "ab".repeat(64)), "N": 7 }));
+    let types = types(&[("SIG", "[u8; 64]"), ("N", "u8")]);
+
+    let draw = |seed| {
+        let mut mutator = Mutator::new(seed);
+        (0..20)
+            .map(|_| mutator.mutate(&witness, &types).unwrap())
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(draw(1), draw(1));
+    assert_ne!(draw(1), draw(2));
+}
+
+#[test]
+fn test_mutants_differ_from_original() {
+    let original = format!("0x{}", "00".repeat(32));
+    let witness = witness(json!({ "KEY": original, "FLAG": true, "N": 0 }));
+    let types = types(&[("KEY", "u256"), ("FLAG", "bool"), ("N", "u32")]);
+
+    let mut mutator = Mutator::new(42);
+    for _ in 0..100 {
+        let mutant = mutator.mutate(&witness, &types).unwrap();
+        assert_ne!(&mutant.value, &witness[&mutant.witness], "{mutant}");
+        if mutant.witness == "FLAG" {
+            assert_eq!(mutant.kind, MutationKind::BitFlip);
+            assert_eq!(mutant.value, json!(false));
+        }
+    }
+}
+
+#[test]
+fn test_integer_mutations_stay_near_type() {
+    let witness = witness(json!({ "N": 5 }));
+    let types = types(&[("N", "u8")]);
+
+    let mut mutator = Mutator::new(3);
+    for _ in 0..100 {
+        let mutant = mutator.mutate(&witness, &types).unwrap();
+        let n = mutant.value.as_u64().unwrap();
+        match mutant.kind {
+            MutationKind::BitFlip => assert_eq!((n ^ 5).count_ones(), 1),
+            MutationKind::Boundary => assert!([0, 1, 254, 255].contains(&n)),
+            MutationKind::WrongLength => assert_eq!(n, 256),
+        }
+    }
+}
+
+#[test]
+fn test_byte_string_lengths() {
+    let witness = witness(json!({ "HASH": "0x0102" }));
+
+    let mut mutator = Mutator::new(9);
+    let mut lengths = Vec::new();
+    for _ in 0..100 {
+        let mutant = mutator.mutate(&witness, &HashMap::new()).unwrap();
+        let literal = mutant.value.as_str().unwrap();
+        assert!(literal.starts_with("0x"));
+        if mutant.kind == MutationKind::WrongLength {
+            lengths.push(literal.len());
+        } else {
+            assert_eq!(literal.len(), 6);
+        }
+    }
+    assert!(lengths.iter().all(|&len| len == 4 || len == 8));
+    assert!(lengths.contains(&4) && lengths.contains(&8));
+}
+
+#[test]
+fn test_nothing_to_mutate() {
+    let witness = witness(json!({ "UNIT": "()" }));
+    assert!(Mutator::new(0).mutate(&witness, &HashMap::new()).is_none());
+    assert!(Mutator::new(0)
+        .mutate(&Map::new(), &HashMap::new())
+        .is_none());
+}
+
+#[test]
+fn test_report() {
+    let original = witness(json!({ "N": 5 }));
+    let mut mutator = Mutator::new(0);
+    let types = types(&[("N", "u8")]);
+    let mut report = FuzzReport::new(0);
+
+    let rejected = mutator.mutate(&original, &types).unwrap();
+    report.add(
+        rejected,
+        &Outcome::Rejected {
+            stage: FailureStage::NodeRejection,
+            reason: "non-mandatory-script-verify-flag".into(),
+        },
+    );
+    assert!(report.passed());
+
+    let accepted = mutator.mutate(&original, &types).unwrap();
+    assert_eq!(accepted.apply(&original)["N"], accepted.value);
+    report.add(accepted.clone(), &Outcome::Accepted);
+    assert!(!report.passed());
+    assert_eq!(report.mutants, 2);
+    assert_eq!(report.rejected[&FailureStage::NodeRejection], 1);
+    assert_eq!(report.accepted, vec![accepted]);
+
+    let json = serde_json::to_value(&report).unwrap();
+    assert_eq!(json["rejected"]["node_rejection"], 1);
+}
@@ -0,0 +1,46 @@
+//! Unit tests for the bundled example programs
+
+#![cfg(feature = "examples")]
+
+use spray::gallery::{self, EXAMPLES};
+use spray::witness::SigningWitness;
+
+#[test]
+fn test_gallery_names() {
+    let names: Vec<&str> = EXAMPLES.iter().map(|example| example.name).collect();
+    assert_eq!(names, ["cat", "p2pk", "ctv", "htlc", "presigned_vault"]);
+    assert_eq!(gallery::find("cat").unwrap().name, "cat");
+    assert!(gallery::find("p2ms").is_err());
+}
+
+#[test]
+fn test_example_files_load() {
+    for example in EXAMPLES {
+        assert!(example.source.contains("fn main()"), "{}", example.name);
+        if let Some(witness) = example.witness {
+            let value = serde_json::from_str(witness).unwrap();
+            assert!(SigningWitness::new(value).is_ok(), "{}", example.name);
+        }
+        if let Some(args) = example.args {
+            assert!(serde_json::from_str::<serde_json::Value>(args).is_ok());
+        }
+    }
+}
+
+#[test]
+fn test_example_write() {
+    let dir = tempfile::tempdir().unwrap();
+    let ctv = gallery::find("ctv").unwrap();
+
+    let manifest = ctv.write(dir.path()).unwrap();
+    let manifest = spray::manifest::Manifest::load(&manifest).unwrap();
+    let test = &manifest.suites[0].tests[0];
+    assert_eq!(test.file, dir.path().join("ctv.simf"));
+    assert!(test.file.is_file());
+    assert_eq!(test.args, Some(dir.path().join("ctv.args.json")));
+    assert_eq!(test.witness, None);
+    assert_eq!(
+        test.destination.to_string(),
+        "raw(0014751e76e8199196d454941c45d1b3a323f1433bd6)"
+    );
+}
@@ -0,0 +1,149 @@
+//! Unit tests for garbage collection
+
+use spray::gc::{self, format_size, GcConfig, GcEntry, GcKind, GcPolicy};
+use std::time::{Duration, SystemTime};
+
+const DAY: Duration = Duration::from_secs(24 * 60 * 60);
+
+fn entry(kind: GcKind, name: &str, size: u64, age_days: u32, now: SystemTime) -> GcEntry {
+    GcEntry {
+        kind,
+        path: name.into(),
+        size,
+        modified: now - DAY * age_days,
+        owner_alive: None,
+    }
+}
+
+fn names(selected: &[&GcEntry]) -> Vec<String> {
+    selected
+        .iter()
+        .map(|entry| entry.path.display().to_string())
+        .collect()
+}
+
+#[test]
+fn test_select_by_age() {
+    let now = SystemTime::now();
+    let entries = [
+        entry(GcKind::Cache, "old", 10, 20, now),
+        entry(GcKind::Report, "new", 10, 1, now),
+    ];
+
+    assert_eq!(names(&GcPolicy::default().select(&entries, now)), ["old"]);
+    let keep_all = GcPolicy {
+        max_age: None,
+        max_size: None,
+    };
+    assert!(keep_all.select(&entries, now).is_empty());
+}
+
+#[test]
+fn test_size_limit_applies_per_kind_oldest_first() {
+    let now = SystemTime::now();
+    let entries = [
+        entry(GcKind::Cache, "cache-new", 400, 0, now),
+        entry(GcKind::Cache, "cache-old", 400, 3, now),
+        entry(GcKind::Cache, "cache-mid", 400, 1, now),
+        entry(GcKind::Report, "report", 900, 5, now),
+    ];
+    let policy = GcPolicy {
+        max_age: None,
+        max_size: Some(1_000),
+    };
+
+    assert_eq!(names(&policy.select(&entries, now)), ["cache-old"]);
+}
+
+#[test]
+fn test_datadirs_follow_their_owner() {
+    let now = SystemTime::now();
+    let mut exited = entry(GcKind::Datadir, "exited", 10, 0, now);
+    exited.owner_alive = Some(false);
+    let mut running = entry(GcKind::Datadir, "running", 10_000, 100, now);
+    running.owner_alive = Some(true);
+    let policy = GcPolicy {
+        max_age: Some(DAY),
+        max_size: Some(100),
+    };
+
+    assert_eq!(names(&policy.select(&[exited, running], now)), ["exited"]);
+}
+
+#[test]
+fn test_owner_alive() {
+    assert_eq!(
+        gc::owner_alive(&format!("spray-pid-{}", std::process::id())),
+        Some(true)
+    );
+    assert_eq!(gc::owner_alive("spray-ci-build-7"), None);
+    assert_eq!(gc::owner_alive("spray-pid-abc"), None);
+    assert_eq!(
+        gc::owner_alive(&format!("spray-pid-{}-3", std::process::id())),
+        Some(true)
+    );
+}
+
+#[test]
+fn test_create_datadir_is_unique_per_call() {
+    let first = gc::create_datadir().unwrap();
+    let second = gc::create_datadir().unwrap();
+    assert_ne!(first, second);
+    for dir in [&first, &second] {
+        assert!(dir.join(gc::DATADIR_MARKER).is_file());
+        let name = dir.file_name().unwrap().to_str().unwrap();
+        assert_eq!(gc::owner_alive(name), Some(true));
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
+
+#[test]
+fn test_policy_from_config() {
+    let config: GcConfig = toml::from_str("max_size_mb = 2").unwrap();
+    let policy = GcPolicy::from_config(&config);
+    assert_eq!(policy.max_age, Some(DAY * 14));
+    assert_eq!(policy.max_size, Some(2 * 1024 * 1024));
+}
+
+#[test]
+fn test_scan_and_remove() {
+    let temp = tempfile::tempdir().unwrap();
+    let dir = temp.path().join("spray-gc");
+    std::fs::create_dir_all(dir.join("cache/nested")).unwrap();
+    std::fs::write(dir.join("cache/a.json"), [0u8; 100]).unwrap();
+    std::fs::write(dir.join("cache/nested/b.json"), [0u8; 50]).unwrap();
+
+    let entries = gc::scan(&dir.join("cache"), GcKind::Cache).unwrap();
+    assert_eq!(entries.len(), 2);
+    assert_eq!(entries[0].size, 100);
+    assert_eq!(entries[1].size, 50);
+    assert_eq!(gc::disk_size(&dir), 150);
+
+    for entry in &entries {
+        gc::remove(entry).unwrap();
+    }
+    assert!(gc::scan(&dir.join("cache"), GcKind::Cache)
+        .unwrap()
+        .is_empty());
+    assert!(gc::scan(&dir.join("missing"), GcKind::Cache)
+        .unwrap()
+        .is_empty());
+
+    // Only marked directories are data directories
+    std::fs::create_dir_all(temp.path().join("spray-other")).unwrap();
+    assert!(gc::scan_datadirs(temp.path()).unwrap().is_empty());
+
+    gc::mark_datadir(&dir).unwrap();
+    let datadirs = gc::scan_datadirs(temp.path()).unwrap();
+    assert_eq!(datadirs.len(), 1);
+    assert_eq!(datadirs[0].path, dir);
+    assert_eq!(datadirs[0].kind, GcKind::Datadir);
+    assert_eq!(datadirs[0].owner_alive, None);
+}
+
+#[test]
+fn test_format_size() {
+    assert_eq!(format_size(512), "512 B");
+    assert_eq!(format_size(1_536), "1.5 KB");
+    assert_eq!(format_size(3 * 1024 * 1024), "3.0 MB");
+}
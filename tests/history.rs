@@ -0,0 +1,75 @@
+//! Unit tests for historical deployment status
+
+use serde_json::json;
+use spray::history::{scan_start, DeploymentHistory, Status};
+use spray::registry::DeploymentRecord;
+
+fn record(txid: &str, vout: u32, height: Option<u64>) -> DeploymentRecord {
+    DeploymentRecord {
+        txid: txid.repeat(32),
+        vout,
+        network: "regtest".into(),
+        height,
+        ..Default::default()
+    }
+}
+
+fn block(txs: &[(&str, &[(&str, u32)])]) -> serde_json::Value {
+    let txs: Vec<_> = txs
+        .iter()
+        .map(|(txid, inputs)| {
+            let vin: Vec<_> = inputs
+                .iter()
+                .map(|(prev, vout)| json!({ "txid": prev.repeat(32), "vout": vout }))
+                .collect();
+            json!({ "txid": txid.repeat(32), "vin": vin })
+        })
+        .collect();
+    json!({ "tx": txs })
+}
+
+#[test]
+fn test_outputs_of_one_funding_tracked_separately() {
+    let mut history = DeploymentHistory::new(&[record("aa", 0, None), record("aa", 1, None)]);
+    history.apply_block(&block(&[("aa", &[])]), 10).unwrap();
+    history
+        .apply_block(&block(&[("bb", &[("aa", 1)])]), 12)
+        .unwrap();
+
+    assert_eq!(history.status_at(0, 20), Status::Unspent { funded_at: 10 });
+    assert_eq!(
+        history.status_at(1, 20),
+        Status::Spent {
+            funded_at: 10,
+            spent_at: 12,
+            spending_txid: "bb".repeat(32),
+        }
+    );
+    assert_eq!(history.status_at(1, 11), Status::Unspent { funded_at: 10 });
+}
+
+#[test]
+fn test_coinbase_inputs_are_ignored() {
+    let mut history = DeploymentHistory::new(&[record("aa", 0, None)]);
+    let coinbase = json!({ "tx": [{ "txid": "cc".repeat(32), "vin": [{ "coinbase": "00" }] }] });
+    history.apply_block(&coinbase, 5).unwrap();
+    assert_eq!(history.status_at(0, 5), Status::Unfunded);
+}
+
+#[test]
+fn test_malformed_block_is_an_error() {
+    let mut history = DeploymentHistory::new(&[record("aa", 0, None)]);
+    assert!(history.apply_block(&json!({}), 1).is_err());
+}
+
+#[test]
+fn test_scan_start() {
+    assert_eq!(
+        scan_start(&[record("aa", 0, Some(30)), record("bb", 0, Some(20))]),
+        20
+    );
+    assert_eq!(
+        scan_start(&[record("aa", 0, Some(30)), record("bb", 0, None)]),
+        0
+    );
+}
@@ -0,0 +1,125 @@
+//! Unit tests for contract holdings
+
+use musk::elements::AddressParams;
+use serde_json::json;
+use spray::holdings::{
+    from_mempool_tx, from_scan, resolve_address, Balance, FundingEvent, FundingWatch, Holding,
+};
+
+const CMR: &str = "c40a10263f7436b4160acbef1c36fba4be4d95df181a968afeab5eac247adff7";
+
+#[test]
+fn test_from_scan_confirmations() {
+    let scan = json!({
+        "unspents": [
+            { "txid": "aa", "vout": 1, "amount": 0.5, "asset": "5ac9", "height": 100 },
+            { "txid": "bb", "vout": 0, "height": 110 },
+        ]
+    });
+    let holdings = from_scan(&scan, 110);
+    assert_eq!(holdings[0].confirmations, 11);
+    assert_eq!(holdings[0].amount, Some(50_000_000));
+    assert_eq!(holdings[1].confirmations, 1);
+    assert_eq!(holdings[1].amount, None);
+}
+
+#[test]
+fn test_from_mempool_tx() {
+    let tx = json!({
+        "txid": "cc",
+        "vout": [
+            { "n": 0, "value": 0.001, "asset": "5ac9", "scriptPubKey": { "hex": "5120ab" } },
+            { "n": 1, "value": 0.002, "asset": "5ac9", "scriptPubKey": { "hex": "0014cd" } },
+        ]
+    });
+    let holdings = from_mempool_tx(&tx, "5120ab");
+    assert_eq!(holdings.len(), 1);
+    assert_eq!((holdings[0].vout, holdings[0].amount), (0, Some(100_000)));
+    assert!(!holdings[0].is_confirmed());
+}
+
+#[test]
+fn test_balance_sums_per_asset() {
+    let holding = |asset: Option<&str>, amount, confirmations| Holding {
+        txid: "aa".into(),
+        vout: 0,
+        amount,
+        asset: asset.map(str::to_string),
+        confirmations,
+    };
+    let balance = Balance::new(&[
+        holding(Some("5ac9"), Some(1_000), 2),
+        holding(Some("5ac9"), Some(500), 0),
+        holding(Some("ce09"), Some(7), 1),
+        holding(None, None, 1),
+    ]);
+    assert_eq!(balance.utxos, 4);
+    assert_eq!(balance.confidential, 1);
+    assert_eq!(balance.assets["5ac9"].confirmed, 1_000);
+    assert_eq!(balance.assets["5ac9"].unconfirmed, 500);
+    assert_eq!(balance.assets["ce09"].confirmed, 7);
+}
+
+#[test]
+fn test_resolve_address() {
+    let from_cmr = resolve_address(CMR, &AddressParams::ELEMENTS).unwrap();
+    let from_address = resolve_address(&from_cmr.to_string(), &AddressParams::ELEMENTS).unwrap();
+    assert_eq!(from_cmr, from_address);
+
+    // An address of another network is rejected
+    assert!(resolve_address(&from_cmr.to_string(), &AddressParams::LIQUID).is_err());
+    assert!(resolve_address("not-a-contract", &AddressParams::ELEMENTS).is_err());
+}
+
+#[test]
+fn test_holding_to_utxo() {
+    let asset = "5ac9f65c0efcc4775e0baec4ec03abdde22473cd3cf33c0419ca290e0751b225";
+    let txid = "2f7b7a36e0e6a6c8fa0e8cf0b6ad0d5d1bf0f25d3a8e0d9b1c6a3e8d7f4b2a10";
+    let script = musk::elements::Script::new();
+    let explicit = Holding {
+        txid: txid.into(),
+        vout: 2,
+        amount: Some(5_000),
+        asset: Some(asset.into()),
+        confirmations: 1,
+    };
+    let utxo = explicit.to_utxo(script.clone()).unwrap();
+    assert_eq!(utxo.txid.to_string(), txid);
+    assert_eq!((utxo.vout, utxo.amount), (2, 5_000));
+
+    let confidential = Holding {
+        amount: None,
+        ..explicit
+    };
+    assert!(confidential.to_utxo(script).is_none());
+}
+
+#[test]
+fn test_funding_watch_events() {
+    let holding = |txid: &str, confirmations| Holding {
+        txid: txid.into(),
+        vout: 0,
+        amount: Some(1_000),
+        asset: Some("5ac9".into()),
+        confirmations,
+    };
+    let mut watch = FundingWatch::new(&[holding("aa", 3)]);
+    assert!(watch.update(vec![holding("aa", 4)]).is_empty());
+
+    let events = watch.update(vec![holding("aa", 4), holding("bb", 0)]);
+    assert_eq!(events, [FundingEvent::Received(holding("bb", 0))]);
+    assert!(watch.update(vec![holding("bb", 0)]).is_empty());
+
+    let events = watch.update(vec![holding("bb", 1), holding("cc", 1)]);
+    assert_eq!(
+        events,
+        [
+            FundingEvent::Confirmed(holding("bb", 1)),
+            FundingEvent::Received(holding("cc", 1)),
+        ]
+    );
+
+    let record = serde_json::to_value(&events[0]).unwrap();
+    assert_eq!(record["event"], "confirmed");
+    assert_eq!(record["txid"], "bb");
+}
@@ -0,0 +1,62 @@
+//! Unit tests for multi-file contracts
+
+use spray::include::{expand, expand_source, includes, parse_directive};
+
+#[test]
+fn test_parse_directive() {
+    assert_eq!(parse_directive("include \"a.simf\";"), Ok(Some("a.simf")));
+    assert_eq!(
+        parse_directive("  include   \"lib/b.simf\" ;"),
+        Ok(Some("lib/b.simf"))
+    );
+    assert_eq!(parse_directive("fn include_me() {}"), Ok(None));
+    assert_eq!(parse_directive("let includes: u8 = 1;"), Ok(None));
+    assert!(parse_directive("include a.simf;").is_err());
+    assert!(parse_directive("include \"a.simf\"").is_err());
+}
+
+#[test]
+fn test_expand_splices_each_file_once() {
+    let dir = tempfile::tempdir().unwrap();
+    std::fs::create_dir(dir.path().join("lib")).unwrap();
+    std::fs::write(dir.path().join("lib/base.simf"), "fn base() {}\n").unwrap();
+    std::fs::write(
+        dir.path().join("lib/sig.simf"),
+        "include \"base.simf\";\nfn sig() {}\n",
+    )
+    .unwrap();
+    std::fs::write(
+        dir.path().join("main.simf"),
+        "include \"lib/base.simf\";\ninclude \"lib/sig.simf\";\nfn main() {}\n",
+    )
+    .unwrap();
+
+    let main = dir.path().join("main.simf");
+    assert_eq!(
+        expand(&main).unwrap(),
+        "fn base() {}\nfn sig() {}\nfn main() {}\n"
+    );
+    assert_eq!(
+        includes(&main).unwrap(),
+        [
+            dir.path().join("lib/base.simf"),
+            dir.path().join("lib/sig.simf")
+        ]
+    );
+
+    let stdin = expand_source("include \"lib/sig.simf\";\n", dir.path()).unwrap();
+    assert_eq!(stdin, "fn base() {}\nfn sig() {}\n");
+}
+
+#[test]
+fn test_expand_errors() {
+    let dir = tempfile::tempdir().unwrap();
+    std::fs::write(dir.path().join("a.simf"), "include \"b.simf\";\n").unwrap();
+    std::fs::write(dir.path().join("b.simf"), "include \"a.simf\";\n").unwrap();
+    std::fs::write(dir.path().join("c.simf"), "include \"missing.simf\";\n").unwrap();
+
+    let cycle = expand(&dir.path().join("a.simf")).unwrap_err();
+    assert!(cycle.to_string().contains("Include cycle"));
+    let missing = expand(&dir.path().join("c.simf")).unwrap_err();
+    assert!(missing.to_string().contains("c.simf:1"));
+}
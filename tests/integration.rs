@@ -3,8 +3,10 @@
 //! These tests require a running elementsd daemon and are marked as `#[ignore]`
 //! by default. Run with `cargo test -- --ignored` to execute them.
 
+#![cfg(feature = "daemon")]
+
 use musk::{Arguments, Program, WitnessValues};
-use spray::{TestCase, TestEnv, TestRunner};
+use spray::{TestCase, TestEnv, TestEnvBuilder, TestRunner};
 
 /// Test that TestEnv successfully creates and configures a wallet
 #[test]
@@ -33,6 +35,86 @@ fn test_env_generates_blocks() {
     // If we got here, block generation works
 }
 
+/// Test that TestEnv can simulate a reorg and restore the original chain
+#[test]
+#[ignore = "Requires elementsd daemon"]
+fn test_env_reorg() {
+    let env = TestEnv::new().expect("Failed to create test environment");
+
+    env.generate(5).expect("Failed to generate blocks");
+    let height = env.block_count().expect("Failed to get block count");
+    let old_tip = env.block_hash(height).expect("Failed to get tip");
+
+    let fork = env.reorg(2).expect("Failed to reorg");
+
+    // The replacement chain is one block longer and has a different tip
+    assert_eq!(
+        env.block_count().expect("Failed to get block count"),
+        height + 1
+    );
+    assert_ne!(
+        env.block_hash(height).expect("Failed to get block"),
+        old_tip
+    );
+
+    // Reconsidering keeps the longer replacement chain active
+    env.reconsider_block(&fork)
+        .expect("Failed to reconsider block");
+    assert_eq!(
+        env.block_count().expect("Failed to get block count"),
+        height + 1
+    );
+}
+
+/// Test that a builder-configured environment keeps its data directory and
+/// mines in the background
+#[test]
+#[ignore = "Requires elementsd daemon"]
+fn test_env_builder_options() {
+    let temp = tempfile::tempdir().expect("Failed to create temporary directory");
+    let dir = temp.path().join("datadir");
+    let env = TestEnvBuilder::new()
+        .data_dir(&dir)
+        .block_time(std::time::Duration::from_millis(500))
+        .arg("-acceptnonstdtxn=1")
+        .build()
+        .expect("Failed to create test environment");
+    assert_eq!(env.data_dir(), Some(dir.as_path()));
+
+    let height = env.block_count().expect("Failed to get block count");
+    std::thread::sleep(std::time::Duration::from_secs(2));
+    assert!(env.block_count().expect("Failed to get block count") > height);
+
+    drop(env);
+    assert!(dir.exists());
+}
+
+/// Test that a cluster propagates transactions between nodes
+#[test]
+#[ignore = "Requires elementsd daemon"]
+fn test_env_cluster_propagates_transactions() {
+    use musk::client::NodeClient;
+
+    let env = TestEnv::new_cluster(2).expect("Failed to create cluster");
+    assert_eq!(env.node_count(), 2);
+
+    let node_a = env.client(0).expect("Missing node 0");
+    let node_b = env.client(1).expect("Missing node 1");
+
+    let address = node_b.get_new_address().expect("Failed to get address");
+    let txid = node_a
+        .send_to_address(&address, 10_000_000)
+        .expect("Failed to send");
+
+    env.sync_mempools().expect("Mempools did not sync");
+    env.generate(1).expect("Failed to generate blocks");
+    env.sync_blocks().expect("Blocks did not sync");
+
+    node_b
+        .get_transaction(&txid)
+        .expect("Node B should see the transaction");
+}
+
 /// Test the TestCase builder pattern
 #[test]
 #[ignore = "Requires elementsd daemon"]
@@ -103,6 +185,27 @@ fn test_runner_executes_simple_program() {
     assert!(result.is_success(), "Simple program should succeed");
 }
 
+/// Test that a spend can pay to a blinded destination output
+#[test]
+#[ignore = "Requires elementsd daemon"]
+fn test_runner_blinded_destination() {
+    let runner = TestRunner::new().expect("Failed to create test runner");
+
+    let program =
+        Program::from_source("fn main() { assert!(true); }").expect("Failed to parse program");
+    let compiled = program
+        .instantiate(Arguments::default())
+        .expect("Failed to compile");
+
+    let test = TestCase::new(runner.env(), compiled)
+        .name("Blinded destination test")
+        .blind_destination(true)
+        .witness(|_| WitnessValues::default());
+
+    let result = runner.run_test(test);
+    assert!(result.is_success(), "Blinded spend should succeed");
+}
+
 /// Test network backend creation for regtest
 #[test]
 #[ignore = "Requires elementsd daemon"]
@@ -113,3 +216,134 @@ fn test_network_backend_regtest() {
     let backend = create_backend(Network::Regtest, None);
     assert!(backend.is_ok(), "Should create ephemeral regtest backend");
 }
+
+/// Test that runner hooks run around the suite and each test
+#[test]
+#[ignore = "Requires elementsd daemon"]
+fn test_runner_hooks() {
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    let calls = Rc::new(RefCell::new(Vec::new()));
+    let hook = |name: &'static str| {
+        let calls = Rc::clone(&calls);
+        move |env: &TestEnv| {
+            calls.borrow_mut().push(name);
+            env.generate(1)
+        }
+    };
+    let runner = TestRunner::new()
+        .expect("Failed to create test runner")
+        .before_all(hook("before_all"))
+        .before_each(hook("before_each"))
+        .after_each(hook("after_each"))
+        .after_all(hook("after_all"));
+
+    let program =
+        Program::from_source("fn main() { assert!(true); }").expect("Failed to parse program");
+    let compiled = program
+        .instantiate(Arguments::default())
+        .expect("Failed to compile");
+    let tests = vec![
+        TestCase::new(runner.env(), compiled.clone()).name("first"),
+        TestCase::new(runner.env(), compiled).name("second"),
+    ];
+
+    let results = runner.run_tests(tests);
+    assert!(results.iter().all(spray::TestResult::is_success));
+    assert_eq!(
+        *calls.borrow(),
+        [
+            "before_all",
+            "before_each",
+            "after_each",
+            "before_each",
+            "after_each",
+            "after_all"
+        ]
+    );
+}
+
+/// Test that a failing before_all hook fails every test without running it
+#[test]
+#[ignore = "Requires elementsd daemon"]
+fn test_runner_failing_before_all() {
+    let runner = TestRunner::new()
+        .expect("Failed to create test runner")
+        .before_all(|_| Err(spray::SprayError::TestError("no assets".into())));
+
+    let program =
+        Program::from_source("fn main() { assert!(true); }").expect("Failed to parse program");
+    let compiled = program
+        .instantiate(Arguments::default())
+        .expect("Failed to compile");
+
+    let (results, report) =
+        runner.run_suite(vec![TestCase::new(runner.env(), compiled).name("skipped")]);
+    assert!(results[0].is_failure());
+    assert_eq!(report.tests[0].stage, Some(spray::FailureStage::Hook));
+}
+
+/// Test that restartable environments of one run keep separate chains
+#[test]
+#[ignore = "Requires elementsd daemon"]
+fn test_restartable_envs_do_not_share_a_data_dir() {
+    let mut first = TestEnv::new_restartable().expect("Failed to create first environment");
+    let second = TestEnv::new_restartable().expect("Failed to create second environment");
+    assert_ne!(first.data_dir(), second.data_dir());
+
+    let height = second.block_count().expect("Failed to read block count");
+    first.generate(5).expect("Failed to generate blocks");
+    first
+        .restart(false)
+        .expect("Failed to restart first environment");
+    assert_eq!(second.block_count().unwrap(), height);
+    assert!(first.block_count().unwrap() >= height + 5);
+}
+
+/// Test that a fee the funded amount does not cover fails once, at the
+/// config stage, instead of being retried
+#[test]
+#[ignore = "Requires elementsd daemon"]
+fn test_runner_does_not_retry_config_failures() {
+    let runner = TestRunner::new()
+        .expect("Failed to create test runner")
+        .retries(3);
+
+    let program =
+        Program::from_source("fn main() { assert!(true); }").expect("Failed to parse program");
+    let compiled = program
+        .instantiate(Arguments::default())
+        .expect("Failed to compile");
+
+    let (results, report) = runner.run_suite(vec![TestCase::new(runner.env(), compiled)
+        .name("fee above amount")
+        .fee_policy(spray::fee::FeePolicy::Fixed(u64::MAX))]);
+    assert!(results[0].is_failure());
+    assert_eq!(report.tests[0].stage, Some(spray::FailureStage::Config));
+    assert_eq!(report.tests[0].retries, 0);
+}
+
+/// Test that a destination that cannot be resolved fails at the config
+/// stage, without being retried
+#[test]
+#[ignore = "Requires elementsd daemon"]
+fn test_runner_does_not_retry_unresolvable_destinations() {
+    let runner = TestRunner::new()
+        .expect("Failed to create test runner")
+        .retries(3);
+
+    let program =
+        Program::from_source("fn main() { assert!(true); }").expect("Failed to parse program");
+    let compiled = program
+        .instantiate(Arguments::default())
+        .expect("Failed to compile");
+    let destination = "raw(not-hex)".parse().expect("Failed to parse destination");
+
+    let (results, report) = runner.run_suite(vec![TestCase::new(runner.env(), compiled)
+        .name("bad destination")
+        .destination(destination)]);
+    assert!(results[0].is_failure());
+    assert_eq!(report.tests[0].stage, Some(spray::FailureStage::Config));
+    assert_eq!(report.tests[0].retries, 0);
+}
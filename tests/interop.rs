@@ -0,0 +1,74 @@
+//! Unit tests for cross-toolchain artifact normalization
+
+use spray::compiled::CompiledOutput;
+use spray::interop::{address_params, derive_address, normalize, ArtifactFormat};
+
+const CMR: &str = "0101010101010101010101010101010101010101010101010101010101010101";
+
+#[test]
+fn test_normalize_hal_simplicity() {
+    let liquid = derive_address(CMR, address_params("liquid").unwrap()).unwrap();
+    let json = format!(
+        r#"{{"cmr":"{}","commit_base64":"AAE=","liquid_address_unconf":"{liquid}","is_redeem":false}}"#,
+        CMR.to_uppercase()
+    );
+
+    let artifact = normalize(&json).unwrap();
+    assert_eq!(artifact.format, ArtifactFormat::HalSimplicity);
+    assert_eq!(artifact.compiled.cmr, CMR);
+    assert_eq!(artifact.compiled.program_size, 2);
+    assert_eq!(
+        artifact.addresses,
+        vec![("liquid".to_string(), liquid.to_string())]
+    );
+
+    let checks = artifact.check(None).unwrap();
+    assert_eq!(checks.len(), 1);
+    assert!(checks[0].passed());
+}
+
+#[test]
+fn test_mismatched_address_is_reported() {
+    let other = derive_address(&"02".repeat(32), address_params("testnet").unwrap()).unwrap();
+    let json = format!(
+        r#"{{"cmr":"{CMR}","commit_base64":"AA==","liquid_testnet_address_unconf":"{other}"}}"#
+    );
+
+    let checks = normalize(&json).unwrap().check(None).unwrap();
+    assert_eq!(checks[0].name, "address (testnet)");
+    assert!(!checks[0].passed());
+}
+
+#[test]
+fn test_normalize_simc() {
+    let text = normalize("Program:\nAAE=\nWitness:\nAA==\n").unwrap();
+    assert_eq!(text.format, ArtifactFormat::Simc);
+    assert_eq!(text.compiled.program, "AAE=");
+    assert_eq!(text.compiled.witness.as_deref(), Some("AA=="));
+    assert!(text.compiled.cmr.is_empty());
+
+    let json = normalize(r#"{"program":"AAE=","witness":"AA=="}"#).unwrap();
+    assert_eq!(json.format, ArtifactFormat::Simc);
+    assert_eq!(json.compiled.program, text.compiled.program);
+}
+
+#[test]
+fn test_normalize_spray() {
+    let program = musk::Program::from_source("fn main() { assert!(true); }").unwrap();
+    let compiled = program.instantiate(musk::Arguments::default()).unwrap();
+    let output = CompiledOutput::from_compiled(&compiled, None);
+
+    let artifact = normalize(&serde_json::to_string(&output).unwrap()).unwrap();
+    assert_eq!(artifact.format, ArtifactFormat::Spray);
+
+    let checks = artifact.check(Some(&output)).unwrap();
+    assert_eq!(checks.len(), 2);
+    assert!(checks.iter().all(|c| c.passed()));
+}
+
+#[test]
+fn test_normalize_rejects_unknown_formats() {
+    assert!(normalize(r#"{"foo": 1}"#).is_err());
+    assert!(normalize("not an artifact").is_err());
+    assert!(normalize("Program:\n!!!\n").is_err());
+}
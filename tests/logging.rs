@@ -0,0 +1,17 @@
+//! Unit tests for structured logging
+
+use spray::logging::elapsed_ms;
+use std::time::{Duration, Instant};
+
+#[test]
+fn test_elapsed_ms() {
+    let start = Instant::now() - Duration::from_millis(250);
+    assert!(elapsed_ms(start) >= 250);
+}
+
+#[cfg(feature = "cli")]
+#[test]
+fn test_init_rejects_invalid_filter() {
+    let error = spray::logging::init(Some("spray=[")).unwrap_err();
+    assert!(error.to_string().contains("Invalid log filter"));
+}
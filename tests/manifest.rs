@@ -0,0 +1,185 @@
+//! Unit tests for test manifests
+
+use musk::Network;
+use spray::fee::FeePolicy;
+use spray::manifest::{discover_programs, parse_network, Manifest, SuiteSpec, TestSpec};
+use std::io::Write;
+
+fn write_manifest(contents: &str) -> tempfile::TempDir {
+    let dir = tempfile::tempdir().expect("Failed to create temp dir");
+    let mut file =
+        std::fs::File::create(dir.path().join("spray-tests.toml")).expect("Failed to create file");
+    file.write_all(contents.as_bytes())
+        .expect("Failed to write manifest");
+    dir
+}
+
+#[test]
+fn test_parse_network() {
+    assert!(matches!(parse_network("regtest"), Ok(Network::Regtest)));
+    assert!(matches!(parse_network("testnet"), Ok(Network::Testnet)));
+    assert!(matches!(parse_network("liquidv1"), Ok(Network::Liquid)));
+    assert!(parse_network("mainnet").is_err());
+}
+
+#[test]
+fn test_load_manifest_resolves_paths() {
+    let dir = write_manifest(
+        r#"
+[[suite]]
+name = "local"
+
+[[suite.test]]
+name = "cat"
+file = "cat.simf"
+witness = "cat.wit.json"
+
+[[suite]]
+name = "remote"
+network = "testnet"
+config = "musk.toml"
+"#,
+    );
+
+    let manifest = Manifest::load(&dir.path().join("spray-tests.toml")).expect("Failed to load");
+    assert_eq!(manifest.suites.len(), 2);
+
+    let local = &manifest.suites[0];
+    assert_eq!(
+        local.network, "regtest",
+        "Network should default to regtest"
+    );
+    assert_eq!(local.tests[0].file, dir.path().join("cat.simf"));
+    assert_eq!(
+        local.tests[0].witness,
+        Some(dir.path().join("cat.wit.json"))
+    );
+
+    let remote = &manifest.suites[1];
+    assert_eq!(remote.config, Some(dir.path().join("musk.toml")));
+
+    let local_names: Vec<_> = manifest.local_suites().map(|s| s.name.as_str()).collect();
+    assert_eq!(local_names, vec!["local"]);
+}
+
+#[test]
+fn test_load_manifest_rejects_unknown_network() {
+    let dir = write_manifest(
+        r#"
+[[suite]]
+name = "bad"
+network = "signet"
+"#,
+    );
+
+    assert!(Manifest::load(&dir.path().join("spray-tests.toml")).is_err());
+}
+
+#[test]
+fn test_load_manifest_with_funding_shape() {
+    let dir = write_manifest(
+        r#"
+[[suite]]
+name = "local"
+
+[[suite.test]]
+name = "covenant"
+file = "covenant.simf"
+funding = [
+    { kind = "op_return", data = "deadbeef" },
+    { kind = "contract" },
+    { kind = "wallet", amount = 50000 },
+]
+"#,
+    );
+
+    let manifest =
+        Manifest::load(&dir.path().join("spray-tests.toml")).expect("Failed to load manifest");
+    let shape = manifest.suites[0].tests[0]
+        .funding
+        .as_ref()
+        .expect("Funding shape missing");
+    assert_eq!(shape.len(), 3);
+    assert_eq!(shape.contract_index().unwrap(), 1);
+}
+
+#[test]
+fn test_manifest_conf_target() {
+    let dir = write_manifest(
+        r#"
+[[suite]]
+name = "local"
+conf_target = 6
+
+[[suite.test]]
+name = "inherits"
+file = "a.simf"
+
+[[suite.test]]
+name = "fixed"
+file = "a.simf"
+fee = 1000
+
+[[suite.test]]
+name = "own target"
+file = "a.simf"
+conf_target = 2
+"#,
+    );
+
+    let mut manifest =
+        Manifest::load(&dir.path().join("spray-tests.toml")).expect("Failed to load manifest");
+    let policies: Vec<_> = manifest.suites[0]
+        .tests
+        .iter()
+        .map(TestSpec::fee_policy)
+        .collect();
+    assert_eq!(
+        policies,
+        [
+            FeePolicy::Estimate { conf_target: 6 },
+            FeePolicy::Fixed(1_000),
+            FeePolicy::Estimate { conf_target: 2 },
+        ]
+    );
+
+    manifest.set_conf_target(12);
+    assert!(manifest.suites[0]
+        .tests
+        .iter()
+        .all(|t| t.fee_policy() == FeePolicy::Estimate { conf_target: 12 }));
+}
+
+#[test]
+fn test_discover_programs_with_companion_files() {
+    let dir = tempfile::tempdir().expect("Failed to create temp dir");
+    for name in [
+        "p2pk.simf",
+        "p2pk.args.json",
+        "p2pk.wit.toml",
+        "cat.simf",
+        "notes.md",
+    ] {
+        std::fs::write(dir.path().join(name), "").expect("Failed to write file");
+    }
+    std::fs::create_dir(dir.path().join("nested.simf")).expect("Failed to create dir");
+
+    let programs = discover_programs(dir.path()).expect("Failed to discover programs");
+    assert_eq!(
+        programs,
+        vec![dir.path().join("cat.simf"), dir.path().join("p2pk.simf")]
+    );
+
+    let suite = SuiteSpec::for_programs("musk", &programs);
+    assert_eq!(suite.network, "regtest");
+    assert_eq!(suite.tests.len(), 2);
+
+    let cat = &suite.tests[0];
+    assert_eq!(cat.name, "cat");
+    assert!(cat.args.is_none() && cat.witness.is_none());
+
+    let p2pk = &suite.tests[1];
+    assert_eq!(p2pk.name, "p2pk");
+    assert_eq!(p2pk.args, Some(dir.path().join("p2pk.args.json")));
+    assert_eq!(p2pk.witness, Some(dir.path().join("p2pk.wit.toml")));
+}
@@ -0,0 +1,82 @@
+//! Unit tests for the message catalog
+
+use serde_json::json;
+use spray::messages::{Catalog, ENGLISH};
+use spray::SprayError;
+use std::collections::HashSet;
+
+fn errors() -> Vec<SprayError> {
+    vec![
+        SprayError::EnvironmentError("a".into()),
+        SprayError::DaemonError("b".into()),
+        SprayError::TestError("c".into()),
+        SprayError::IoError(std::io::Error::other("d")),
+        SprayError::JsonError(serde_json::from_str::<u8>("x").unwrap_err()),
+        SprayError::RpcError("e".into()),
+        SprayError::ConfigError("f".into()),
+        SprayError::ParseError("g".into()),
+        SprayError::FileFormatError("h".into()),
+        SprayError::InvalidUtxoRef("i".into()),
+        SprayError::IncompatibleNode("j".into()),
+        SprayError::OutpointLocked("k".into()),
+        SprayError::SignatureError("l".into()),
+        SprayError::NodeUnavailable("m".into()),
+        SprayError::MempoolReject("n".into()),
+        SprayError::InsufficientFunds("o".into()),
+        SprayError::WitnessTypeMismatch("p".into()),
+        SprayError::DaemonStartupTimeout("q".into()),
+    ]
+}
+
+#[test]
+fn test_english_errors_match_display() {
+    let english = Catalog::english();
+    for error in errors() {
+        assert_eq!(english.error(&error), error.to_string(), "{error:?}");
+    }
+}
+
+#[test]
+fn test_error_ids_are_in_catalog() {
+    let ids: HashSet<&str> = ENGLISH.iter().map(|(id, _)| *id).collect();
+    assert_eq!(ids.len(), ENGLISH.len(), "Message IDs must be unique");
+    for error in errors() {
+        assert!(ids.contains(error.message_id()), "{}", error.message_id());
+    }
+}
+
+#[test]
+fn test_overrides_flatten_tables() {
+    let catalog = Catalog::english().with_overrides(&json!({
+        "deploy": { "success": "Bereitgestellt!" },
+        "redeem.success": "Eingelöst!",
+        "ignored": 5,
+    }));
+    assert_eq!(catalog.render("deploy.success", &[]), "Bereitgestellt!");
+    assert_eq!(catalog.render("redeem.success", &[]), "Eingelöst!");
+    assert_eq!(catalog.render("ignored", &[]), "ignored");
+}
+
+#[test]
+fn test_unknown_id_renders_as_id() {
+    assert_eq!(Catalog::english().render("no.such.id", &[]), "no.such.id");
+}
+
+#[test]
+fn test_load_toml() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("de.toml");
+    std::fs::write(
+        &path,
+        "[error]\nconfig = \"Konfigurationsfehler: {detail}\"\n",
+    )
+    .unwrap();
+
+    let catalog = Catalog::load(&path).unwrap();
+    let error = SprayError::ConfigError("fehlt".into());
+    assert_eq!(catalog.error(&error), "Konfigurationsfehler: fehlt");
+    assert_eq!(
+        catalog.render("compile.success", &[]),
+        "Compilation successful!"
+    );
+}
@@ -0,0 +1,76 @@
+//! Unit tests for contract monitoring
+
+use musk::elements::Script;
+use serde_json::json;
+use spray::monitor::{Monitor, WatchEvent};
+
+fn monitor() -> Monitor {
+    Monitor::new([
+        ("vault".to_string(), Script::from(vec![0x51])),
+        ("escrow".to_string(), Script::from(vec![0x52])),
+    ])
+}
+
+#[test]
+fn test_funding_and_spending() {
+    let mut monitor = monitor();
+    monitor.add_unspent("vault", "00", 0, Some(500));
+
+    let funding = json!({ "tx": [{
+        "txid": "aa",
+        "vin": [{ "coinbase": "01" }],
+        "vout": [
+            { "value": 0.00001, "scriptPubKey": { "hex": "51" } },
+            { "valuecommitment": "08ff", "scriptPubKey": { "hex": "52" } },
+            { "value": 1.0, "scriptPubKey": { "hex": "53" } },
+        ],
+    }]});
+    let events = monitor.process_block(&funding, 101).unwrap();
+    assert_eq!(events.len(), 2);
+    assert_eq!(events[0].contract(), "vault");
+    assert_eq!(events[0].balances(), (500, 1500));
+    assert!(matches!(
+        events[1],
+        WatchEvent::Funded {
+            amount: None,
+            vout: 1,
+            ..
+        }
+    ));
+    assert_eq!(monitor.utxo_count("escrow"), 1);
+    assert_eq!(monitor.balance("escrow"), 0);
+
+    let spending = json!({ "tx": [{
+        "txid": "bb",
+        "vin": [{ "txid": "aa", "vout": 0 }, { "txid": "00", "vout": 0 }],
+        "vout": [{ "value": 0.000015, "scriptPubKey": { "hex": "51" } }],
+    }]});
+    let events = monitor.process_block(&spending, 102).unwrap();
+    assert_eq!(events.len(), 2);
+    match &events[0] {
+        WatchEvent::Spent {
+            contract,
+            outpoints,
+            outputs,
+            balance_before,
+            balance_after,
+            ..
+        } => {
+            assert_eq!(contract, "vault");
+            assert_eq!(outpoints, &["aa:0", "00:0"]);
+            assert_eq!(outputs[0].amount, Some(1500));
+            assert_eq!((*balance_before, *balance_after), (1500, 0));
+        }
+        other => panic!("Expected a spend, got {other:?}"),
+    }
+    assert_eq!(events[1].balances(), (0, 1500));
+    assert_eq!(monitor.balance("vault"), 1500);
+}
+
+#[test]
+fn test_malformed_block() {
+    assert!(monitor().process_block(&json!({}), 1).is_err());
+    assert!(monitor()
+        .process_block(&json!({ "tx": [{ "vout": [] }] }), 1)
+        .is_err());
+}
@@ -1,7 +1,7 @@
 //! Unit tests for network backend abstraction
 
 use musk::Network;
-use spray::network::create_backend;
+use spray::network::{create_backend, wallet_url, BackendConfig};
 
 #[test]
 fn test_create_backend_testnet_requires_config() {
@@ -62,3 +62,33 @@ fn test_create_backend_with_nonexistent_config() {
 
     assert!(result.is_err(), "Should fail with nonexistent config file");
 }
+
+#[test]
+fn test_wallet_url() {
+    assert_eq!(
+        wallet_url("http://localhost:7041", "deployer"),
+        "http://localhost:7041/wallet/deployer"
+    );
+    assert_eq!(
+        wallet_url("http://localhost:7041/", "a/b"),
+        "http://localhost:7041/wallet/a%2Fb"
+    );
+    assert_eq!(BackendConfig::default().wallet, None);
+}
+
+#[test]
+fn test_backend_config_with_config_keeps_settings() {
+    use std::path::PathBuf;
+
+    let backend = BackendConfig {
+        wallet: Some("treasury".into()),
+        deterministic: true,
+        ..BackendConfig::default()
+    };
+    let suite = backend.with_config(Some(PathBuf::from("musk.toml")));
+
+    assert_eq!(suite.config, Some(PathBuf::from("musk.toml")));
+    assert_eq!(suite.wallet.as_deref(), Some("treasury"));
+    assert!(suite.deterministic);
+    assert_eq!(backend.config, None);
+}
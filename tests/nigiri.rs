@@ -0,0 +1,41 @@
+//! Unit tests for the Nigiri integration
+
+use spray::nigiri::{self, parse_faucet_response, BackendKind, Nigiri};
+
+const TXID: &str = "f7f9c1b5a1e1b9d5e0f6c0e5f2b1d6e4c3a2b1f0e9d8c7b6a5f4e3d2c1b0a9f8";
+
+#[test]
+fn test_parse_faucet_response() {
+    let txid = parse_faucet_response(&format!("{{\"txId\":\"{TXID}\"}}")).unwrap();
+    assert_eq!(txid.to_string(), TXID);
+
+    let error = parse_faucet_response("{\"error\":\"bad address\"}").unwrap_err();
+    assert!(error.to_string().contains("bad address"));
+    let error = parse_faucet_response("Internal Server Error").unwrap_err();
+    assert!(error
+        .to_string()
+        .contains("Faucet error: Internal Server Error"));
+}
+
+#[test]
+fn test_rpc_config() {
+    let config: toml::Value = toml::from_str(&Nigiri::default().rpc_config()).unwrap();
+    assert_eq!(config["network"].as_str(), Some("regtest"));
+    assert_eq!(config["rpc"]["url"].as_str(), Some(nigiri::RPC_URL));
+    assert_eq!(config["rpc"]["user"].as_str(), Some("admin1"));
+    assert_eq!(config["rpc"]["password"].as_str(), Some("123"));
+}
+
+#[test]
+fn test_chopsticks_url() {
+    assert_eq!(
+        Nigiri::new("http://127.0.0.1:3001/").chopsticks_url(),
+        "http://127.0.0.1:3001"
+    );
+    assert!(!Nigiri::new("http://127.0.0.1:9").is_running());
+}
+
+#[test]
+fn test_backend_defaults_to_ephemeral() {
+    assert_eq!(BackendKind::default(), BackendKind::Ephemeral);
+}
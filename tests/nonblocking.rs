@@ -0,0 +1,44 @@
+//! Unit tests for the async API
+#![cfg(feature = "tokio")]
+
+use musk::elements::Txid;
+use serde_json::{json, Value};
+use spray::network::NetworkBackend;
+use spray::nonblocking::{self, AsyncNodeClient};
+use spray::vcr::{Cassette, Interaction, Replay};
+use std::str::FromStr;
+use std::sync::Arc;
+
+const TXID: &str = "f7f9c1b5a1e1b9d5e0f6c0e5f2b1d6e4c3a2b1f0e9d8c7b6a5f4e3d2c1b0a9f8";
+
+fn backend(interactions: Vec<Interaction>) -> Arc<NetworkBackend> {
+    Arc::new(NetworkBackend::Replay(Replay::new(Cassette {
+        network: "regtest".into(),
+        interactions,
+    })))
+}
+
+#[tokio::test]
+async fn test_async_call() {
+    let backend = backend(vec![Interaction::ok("getblockcount", vec![], json!(150))]);
+    let count: Value = nonblocking::call(&backend, "getblockcount", vec![])
+        .await
+        .unwrap();
+    assert_eq!(count, json!(150));
+    assert!(nonblocking::call(&backend, "getblockcount", vec![])
+        .await
+        .is_err());
+}
+
+#[tokio::test]
+async fn test_async_node_client() {
+    let backend = backend(vec![
+        Interaction::ok("generate_blocks", vec![json!(2)], json!([])),
+        Interaction::ok("get_transaction", vec![json!(TXID)], Value::Null),
+    ]);
+    assert!(backend.generate_blocks(2).await.unwrap().is_empty());
+    assert!(backend
+        .get_transaction(&Txid::from_str(TXID).unwrap())
+        .await
+        .is_err());
+}
@@ -0,0 +1,66 @@
+//! Unit tests for machine-readable output
+
+use serde::Serialize;
+use serde_json::json;
+use spray::output::{document, error_document};
+use spray::SprayError;
+
+#[derive(Serialize)]
+struct Fields {
+    txid: String,
+    fee: u64,
+}
+
+#[test]
+fn test_document_flattens_fields() {
+    let fields = Fields {
+        txid: "ab".repeat(32),
+        fee: 300,
+    };
+    let doc = document("redeem.success", true, &fields).unwrap();
+    assert_eq!(
+        doc,
+        json!({
+            "command": "redeem",
+            "ok": true,
+            "message_id": "redeem.success",
+            "message": "Redemption successful!",
+            "txid": "ab".repeat(32),
+            "fee": 300,
+        })
+    );
+}
+
+#[test]
+fn test_document_reports_failure() {
+    let doc = document("test.failed", false, &json!({ "tests": [] })).unwrap();
+    assert_eq!(doc["ok"], false);
+    assert_eq!(doc["tests"], json!([]));
+}
+
+#[test]
+fn test_document_wraps_non_objects() {
+    let doc = document("compile.success", true, &vec![1, 2]).unwrap();
+    assert_eq!(doc["result"], json!([1, 2]));
+}
+
+#[test]
+fn test_error_document() {
+    let doc = error_document(&SprayError::RpcError("connection refused".into()));
+    assert_eq!(
+        doc,
+        json!({
+            "ok": false,
+            "message_id": "error.rpc",
+            "code": 200,
+            "exit_code": 1,
+            "detail": "connection refused",
+            "error": "RPC error: connection refused",
+        })
+    );
+}
+
+#[test]
+fn test_no_color_flag_disables_color() {
+    assert!(!spray::output::use_color(true));
+}
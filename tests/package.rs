@@ -0,0 +1,92 @@
+//! Unit tests for package submission
+
+use serde_json::{json, Value};
+use spray::package::{check_submitpackage, decode_transaction, submit_package, SubmitMethod};
+use spray::SprayError;
+use std::cell::RefCell;
+
+#[test]
+fn test_check_submitpackage() {
+    // Nodes before package_msg was added report only tx-results
+    assert!(check_submitpackage(&json!({ "tx-results": { "ab": { "txid": "cd" } } })).is_ok());
+    assert!(check_submitpackage(&json!({ "package_msg": "success" })).is_ok());
+
+    let error = check_submitpackage(&json!({
+        "package_msg": "transaction failed",
+        "tx-results": {
+            "01": { "txid": "02" },
+            "03": { "txid": "04", "error": "bad-txns-inputs-missingorspent" },
+        },
+    }))
+    .unwrap_err();
+    assert!(error.to_string().contains("bad-txns-inputs-missingorspent"));
+
+    let error = check_submitpackage(&json!({ "package_msg": "package-not-child-with-parents" }))
+        .unwrap_err();
+    assert!(error.to_string().contains("package-not-child-with-parents"));
+}
+
+#[test]
+fn test_submit_package_uses_submitpackage() {
+    let calls = RefCell::new(Vec::new());
+    let call = |method: &str, params: &[Value]| {
+        calls
+            .borrow_mut()
+            .push((method.to_string(), params.to_vec()));
+        Ok(json!({ "package_msg": "success", "tx-results": {} }))
+    };
+
+    let method = submit_package(call, "aa", "bb").expect("Package rejected");
+    assert_eq!(method, SubmitMethod::Package);
+    assert_eq!(
+        calls.into_inner(),
+        [("submitpackage".to_string(), vec![json!(["aa", "bb"])])]
+    );
+}
+
+#[test]
+fn test_submit_package_falls_back_to_sequential() {
+    let calls = RefCell::new(Vec::new());
+    let call = |method: &str, params: &[Value]| {
+        calls
+            .borrow_mut()
+            .push((method.to_string(), params.to_vec()));
+        if method == "submitpackage" {
+            Err(SprayError::RpcError(
+                "JSON-RPC error: Method not found".into(),
+            ))
+        } else {
+            Ok(json!("txid"))
+        }
+    };
+
+    let method = submit_package(call, "aa", "bb").expect("Submission failed");
+    assert_eq!(method, SubmitMethod::Sequential);
+
+    let calls = calls.into_inner();
+    assert_eq!(calls.len(), 3);
+    assert_eq!(
+        calls[1],
+        ("sendrawtransaction".to_string(), vec![json!("aa")])
+    );
+    assert_eq!(
+        calls[2],
+        ("sendrawtransaction".to_string(), vec![json!("bb")])
+    );
+}
+
+#[test]
+fn test_submit_package_propagates_rejection() {
+    let call = |_: &str, _: &[Value]| -> Result<Value, SprayError> {
+        Err(SprayError::RpcError(
+            "bad-txns-inputs-missingorspent".into(),
+        ))
+    };
+    assert!(submit_package(call, "aa", "bb").is_err());
+}
+
+#[test]
+fn test_decode_transaction_rejects_invalid_hex() {
+    assert!(decode_transaction("zz").is_err());
+    assert!(decode_transaction("00").is_err());
+}
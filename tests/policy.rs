@@ -0,0 +1,45 @@
+//! Unit tests for descriptor policy comparison cases
+
+use spray::policy::{CaseOutcome, PolicyCases};
+
+#[test]
+fn test_load_cases_resolves_witness_paths() {
+    let dir = tempfile::tempdir().expect("Failed to create temp dir");
+    let path = dir.path().join("cases.toml");
+    std::fs::write(
+        &path,
+        r#"
+[[case]]
+name = "owner"
+witness = "owner.wit"
+
+[[case]]
+name = "recovery"
+witness = "recovery.wit"
+lock_time = 200
+sequence = 4294967294
+"#,
+    )
+    .expect("Failed to write cases");
+
+    let cases = PolicyCases::load(&path).expect("Failed to load cases");
+    assert_eq!(cases.cases.len(), 2);
+    assert_eq!(cases.cases[0].witness, Some(dir.path().join("owner.wit")));
+    assert_eq!(cases.cases[0].lock_time, None);
+    assert_eq!(cases.cases[1].lock_time, Some(200));
+    assert_eq!(cases.cases[1].sequence, Some(0xffff_fffe));
+}
+
+#[test]
+fn test_case_outcome_agreement() {
+    let outcome = |simplicity: Result<&str, &str>, descriptor: Result<&str, &str>| CaseOutcome {
+        name: "case".to_string(),
+        simplicity: simplicity.map(str::to_string).map_err(str::to_string),
+        descriptor: descriptor.map(str::to_string).map_err(str::to_string),
+    };
+
+    assert!(outcome(Ok("aa"), Ok("bb")).agrees());
+    assert!(outcome(Err("non-final"), Err("non-final")).agrees());
+    assert!(!outcome(Ok("aa"), Err("non-final")).agrees());
+    assert!(!outcome(Err("assertion failed"), Ok("bb")).agrees());
+}
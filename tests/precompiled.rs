@@ -0,0 +1,110 @@
+//! Unit tests for spending programs without their source
+
+use musk::elements::hashes::Hash;
+use musk::elements::{confidential, AssetId, OutPoint, TxOut, Txid};
+use musk::{Arguments, Program};
+use serde_json::json;
+use spray::compiled::CompiledOutput;
+use spray::precompiled::{PrecompiledProgram, PrecompiledSpend, WitnessEntry, WitnessList};
+use spray::pset::SimplicityLeaf;
+
+fn precompiled(source: &str) -> PrecompiledProgram {
+    let compiled = Program::from_source(source)
+        .unwrap()
+        .instantiate(Arguments::default())
+        .unwrap();
+    PrecompiledProgram::from_output(&CompiledOutput::from_compiled(&compiled, None)).unwrap()
+}
+
+fn spend(program: &PrecompiledProgram) -> PrecompiledSpend {
+    let leaf = SimplicityLeaf::new(program.cmr().to_byte_array()).unwrap();
+    let prevout = TxOut {
+        script_pubkey: leaf.script_pubkey,
+        value: confidential::Value::Explicit(100_000),
+        asset: confidential::Asset::Explicit(AssetId::LIQUID_BTC),
+        ..TxOut::default()
+    };
+    let mut spend = PrecompiledSpend::new(
+        program.clone(),
+        OutPoint::new(Txid::all_zeros(), 0),
+        prevout.clone(),
+    );
+    spend.add_output(TxOut {
+        value: confidential::Value::Explicit(99_000),
+        ..prevout
+    });
+    spend.add_fee(1_000, AssetId::LIQUID_BTC);
+    spend
+}
+
+#[test]
+fn test_witness_list_parse() {
+    let list = WitnessList::parse(&json!([
+        "0x0a0b",
+        "0b101",
+        {"value": "ff"},
+        {"sign": "0000000000000000000000000000000000000000000000000000000000000001"},
+    ]))
+    .unwrap();
+    assert_eq!(list.entries().len(), 4);
+    assert_eq!(
+        list.entries()[1],
+        WitnessEntry::Bits(vec![true, false, true])
+    );
+    assert!(matches!(list.entries()[3], WitnessEntry::Sign(_)));
+
+    assert!(WitnessList::parse(&json!({"X": "0x01"})).is_err());
+    assert!(WitnessList::parse(&json!(["0x123"])).is_err());
+    assert!(WitnessList::parse(&json!(["0b102"])).is_err());
+    assert!(WitnessList::parse(&json!([{"sign": "not a key"}])).is_err());
+}
+
+#[test]
+fn test_witness_list_encode() {
+    let list = WitnessList::parse(&json!(["0x0a", "0b1", "0b01"])).unwrap();
+    // 0000 1010, then 1 and 01 padded with zeros
+    assert_eq!(list.encode([0; 32]), vec![0x0a, 0b1010_0000]);
+
+    let signed = WitnessList::parse(&json!([
+        {"sign": "0000000000000000000000000000000000000000000000000000000000000001"}
+    ]))
+    .unwrap();
+    assert_eq!(signed.encode([0; 32]).len(), 64);
+}
+
+#[test]
+fn test_decode_program_bytes() {
+    let program = precompiled("fn main() { assert!(jet::eq_32(witness::X, 5)); }");
+    assert_eq!(program.witness_types().len(), 1);
+
+    let mut output = CompiledOutput::from_program_text("SGVsbG8=").unwrap();
+    assert!(PrecompiledProgram::from_output(&output).is_err());
+
+    let compiled = Program::from_source("fn main() { assert!(true); }")
+        .unwrap()
+        .instantiate(Arguments::default())
+        .unwrap();
+    output = CompiledOutput::from_compiled(&compiled, None);
+    output.cmr = "00".repeat(32);
+    assert!(PrecompiledProgram::from_output(&output).is_err());
+}
+
+#[test]
+fn test_spend_without_source() {
+    let program = precompiled("fn main() { assert!(jet::eq_32(witness::X, 5)); }");
+    let spend = spend(&program);
+
+    let tx = spend
+        .finalize(&WitnessList::parse(&json!(["0x00000005"])).unwrap())
+        .unwrap();
+    assert_eq!(tx.input[0].witness.script_witness.len(), 4);
+    assert_eq!(
+        tx.input[0].witness.script_witness[2],
+        program.cmr().to_byte_array().to_vec()
+    );
+
+    assert!(spend
+        .finalize(&WitnessList::parse(&json!(["0x00000006"])).unwrap())
+        .is_err());
+    assert!(spend.finalize(&WitnessList::default()).is_err());
+}
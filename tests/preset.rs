@@ -0,0 +1,75 @@
+//! Unit tests for network presets
+
+use musk::elements::AddressParams;
+use musk::Network;
+use spray::preset::{Preset, PresetOverrides};
+use spray::project::ProjectConfig;
+use std::time::Duration;
+
+#[test]
+fn test_builtin_presets() {
+    let regtest = Preset::builtin(Network::Regtest);
+    assert_eq!(regtest.name, "regtest");
+    assert_eq!(regtest.fee, spray::fee::DEFAULT_FEE);
+    assert_eq!(regtest.funding_amount, 100_000_000);
+    assert_eq!(regtest.confirmation_timeout, Duration::from_secs(600));
+    assert_eq!(regtest.address_params, &AddressParams::ELEMENTS);
+    assert!(regtest.explorer.is_none());
+
+    let testnet = Preset::builtin(Network::Testnet);
+    assert_eq!(testnet.address_params, &AddressParams::LIQUID_TESTNET);
+    assert_eq!(
+        testnet.tx_url("ab").unwrap(),
+        "https://blockstream.info/liquidtestnet/tx/ab"
+    );
+
+    let liquid = Preset::builtin(Network::Liquid);
+    assert_eq!(liquid.name, "liquid");
+    assert_eq!(liquid.address_params, &AddressParams::LIQUID);
+}
+
+#[test]
+fn test_overrides_from_project_file() {
+    let config: ProjectConfig = toml::from_str(
+        r#"
+        [presets.testnet]
+        fee = 500
+        confirmations = 2
+        confirmation_timeout_secs = 1800
+        explorer = "https://liquid.network/testnet/"
+        "#,
+    )
+    .unwrap();
+
+    let preset = Preset::builtin(Network::Testnet).with_overrides(&config.presets["testnet"]);
+    assert_eq!(preset.fee, 500);
+    assert_eq!(preset.confirmations, 2);
+    assert_eq!(preset.confirmation_timeout, Duration::from_secs(1800));
+    assert_eq!(
+        preset.tx_url("ab").unwrap(),
+        "https://liquid.network/testnet/tx/ab"
+    );
+    // Fields not overridden keep their built-in values
+    assert_eq!(preset.dust_limit, 546);
+    assert_eq!(preset.funding_amount, 100_000_000);
+    assert!(!config.presets.contains_key("regtest"));
+}
+
+#[test]
+fn test_feerate_floor() {
+    let preset = Preset::builtin(Network::Liquid).with_overrides(&PresetOverrides {
+        min_feerate: Some(0.5),
+        ..Default::default()
+    });
+    assert!((preset.feerate(None) - 0.5).abs() < f64::EPSILON);
+    assert!((preset.feerate(Some(0.1)) - 0.5).abs() < f64::EPSILON);
+    assert!((preset.feerate(Some(2.0)) - 2.0).abs() < f64::EPSILON);
+}
+
+#[test]
+fn test_dust_limit() {
+    let preset = Preset::builtin(Network::Regtest);
+    assert!(preset.check_dust(546).is_ok());
+    let error = preset.check_dust(545).unwrap_err().to_string();
+    assert!(error.contains("dust limit of 546 sat"), "{error}");
+}
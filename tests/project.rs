@@ -0,0 +1,114 @@
+//! Unit tests for project configuration
+
+use spray::project::{
+    version_at_least, version_matches, MismatchPolicy, ProjectConfig, ProjectDefaults,
+};
+use std::path::{Path, PathBuf};
+
+#[test]
+fn test_version_at_least() {
+    assert!(version_at_least("0.2.0", "0.1.0"));
+    assert!(version_at_least("0.1.0", "0.1.0"));
+    assert!(version_at_least("0.1.0", "0.1"));
+    assert!(version_at_least("1.0.0-rc1", "1.0.0"));
+    assert!(!version_at_least("0.1.9", "0.2.0"));
+    assert!(!version_at_least("0.10.0", "1.0"));
+    assert!(!version_at_least("unknown", "0.1.0"));
+}
+
+#[test]
+fn test_version_matches() {
+    assert!(version_matches("0.7.0", "0.7"));
+    assert!(version_matches("0.7.0", "0.7.0"));
+    assert!(!version_matches("0.7.0", "0.6"));
+    assert!(!version_matches("0.7", "0.7.0"));
+}
+
+#[test]
+fn test_toolchain_mismatches() {
+    let config: ProjectConfig = toml::from_str(
+        r#"
+        [toolchain]
+        min_spray = "0.2.0"
+        min_musk = "0.1.0"
+        simplicity = "0.7"
+        "#,
+    )
+    .unwrap();
+
+    assert_eq!(config.toolchain.on_mismatch, MismatchPolicy::Error);
+    assert!(config
+        .toolchain
+        .mismatches("0.2.1", "0.1.0", "0.7.0")
+        .is_empty());
+
+    let problems = config.toolchain.mismatches("0.1.0", "0.1.0", "0.6.0");
+    assert_eq!(problems.len(), 2);
+    assert!(problems[0].contains("spray 0.1.0"));
+    assert!(problems[1].contains("Simplicity"));
+}
+
+#[test]
+fn test_empty_project_config() {
+    let config: ProjectConfig = toml::from_str("").unwrap();
+    assert!(config
+        .toolchain
+        .mismatches("0.0.1", "unknown", "unknown")
+        .is_empty());
+}
+
+#[test]
+fn test_find_project_file() {
+    let dir = tempfile::tempdir().unwrap();
+    let nested = dir.path().join("contracts/vault");
+    std::fs::create_dir_all(&nested).unwrap();
+    std::fs::write(dir.path().join("spray.toml"), "[toolchain]\n").unwrap();
+
+    assert_eq!(
+        ProjectConfig::find(&nested),
+        Some(dir.path().join("spray.toml"))
+    );
+}
+
+#[test]
+fn test_artifact_policy() {
+    let config: ProjectConfig = toml::from_str(
+        r#"
+        [artifacts]
+        require_signature = true
+        trusted_keys = ["aa", "bb"]
+        "#,
+    )
+    .unwrap();
+    assert!(config.artifacts.require_signature);
+    assert_eq!(config.artifacts.trusted_keys, ["aa", "bb"]);
+
+    let config: ProjectConfig = toml::from_str("[toolchain]\n").unwrap();
+    assert!(!config.artifacts.require_signature);
+}
+
+#[test]
+fn test_project_defaults() {
+    let config: ProjectConfig = toml::from_str(
+        r#"
+        [defaults]
+        network = "testnet"
+        config = "musk.toml"
+        fee = 500
+        contracts_dir = "contracts"
+        "#,
+    )
+    .unwrap();
+    let defaults = config.defaults.resolve(Path::new("/project"));
+    assert_eq!(defaults.network.as_deref(), Some("testnet"));
+    assert_eq!(defaults.config, Some(PathBuf::from("/project/musk.toml")));
+    assert_eq!(defaults.fee, Some(500));
+    assert_eq!(
+        defaults.contracts_dir,
+        Some(PathBuf::from("/project/contracts"))
+    );
+    assert_eq!(defaults.artifacts_dir, None);
+
+    let config: ProjectConfig = toml::from_str("[toolchain]\n").unwrap();
+    assert_eq!(config.defaults, ProjectDefaults::default());
+}
@@ -0,0 +1,94 @@
+//! Unit tests for interactive prompts
+
+use spray::error::SprayError;
+use spray::prompt::Prompt;
+use spray::value::ValueType;
+
+fn no_keys(key: &str) -> Result<String, SprayError> {
+    Err(SprayError::ConfigError(format!("no key {key}")))
+}
+
+fn answer(input: &str, ty: &str) -> Result<String, SprayError> {
+    let mut output = Vec::new();
+    let mut prompt = Prompt::new(input.as_bytes(), &mut output);
+    let ty: ValueType = ty.parse().unwrap();
+    prompt.value("W", &ty, &mut no_keys)
+}
+
+#[test]
+fn test_ask_default_and_retry() {
+    let mut output = Vec::new();
+    let mut prompt = Prompt::new("\n\nanswer\n".as_bytes(), &mut output);
+    assert_eq!(prompt.ask("Fee", Some("3000")).unwrap(), "3000");
+    assert_eq!(prompt.ask("Name", None).unwrap(), "answer");
+
+    let output = String::from_utf8(output).unwrap();
+    assert_eq!(output, "Fee [3000]: Name: Name: ");
+}
+
+#[test]
+fn test_ask_end_of_input() {
+    let mut output = Vec::new();
+    let mut prompt = Prompt::new("".as_bytes(), &mut output);
+    assert!(prompt.ask("Name", None).is_err());
+}
+
+#[test]
+fn test_choose_rejects_out_of_range() {
+    let mut output = Vec::new();
+    let mut prompt = Prompt::new("0\nthree\n2\n".as_bytes(), &mut output);
+    let options = ["a".to_string(), "b".to_string()];
+    assert_eq!(prompt.choose("Pick", &options).unwrap(), 1);
+
+    let output = String::from_utf8(output).unwrap();
+    assert!(output.contains("  1) a\n  2) b\n"));
+    assert_eq!(output.matches("Enter a number from 1 to 2").count(), 2);
+}
+
+#[test]
+fn test_confirm() {
+    let mut output = Vec::new();
+    let mut prompt = Prompt::new("\nmaybe\nN\n".as_bytes(), &mut output);
+    assert!(prompt.confirm("Sure?", true).unwrap());
+    assert!(!prompt.confirm("Sure?", true).unwrap());
+}
+
+#[test]
+fn test_value_scalars() {
+    assert_eq!(answer("1\n", "bool").unwrap(), "true");
+    assert_eq!(answer("300\n0xff\n", "u8").unwrap(), "255");
+    assert_eq!(answer("", "()").unwrap(), "()");
+}
+
+#[test]
+fn test_value_composites() {
+    assert_eq!(answer("1\n7\n", "Either<u8, (u8, u8)>").unwrap(), "Left(7)");
+    assert_eq!(
+        answer("2\n1\n2\n", "Either<u8, (u8, u8)>").unwrap(),
+        "Right((1, 2))"
+    );
+    assert_eq!(answer("n\n", "Option<u8>").unwrap(), "None");
+    assert_eq!(answer("y\n5\n", "Option<u8>").unwrap(), "Some(5)");
+}
+
+#[test]
+fn test_value_signs_with_key() {
+    let mut output = Vec::new();
+    let mut prompt = Prompt::new("@alice\n".as_bytes(), &mut output);
+    let ty: ValueType = "[u8; 2]".parse().unwrap();
+    let mut keys = Vec::new();
+    let value = prompt
+        .value("SIG", &ty, &mut |key| {
+            keys.push(key.to_string());
+            Ok("0xabcd".into())
+        })
+        .unwrap();
+    assert_eq!(value, "0xabcd");
+    assert_eq!(keys, ["alice"]);
+}
+
+#[test]
+fn test_value_reasks_on_signing_error() {
+    assert!(answer("@bob\n", "[u8; 2]").is_err());
+    assert_eq!(answer("@bob\n0x0102\n", "[u8; 2]").unwrap(), "0x0102");
+}
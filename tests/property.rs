@@ -0,0 +1,83 @@
+//! Unit tests for property-based testing
+
+use rand::{Rng, RngCore};
+use spray::fuzz::Outcome;
+use spray::property::{shrink_candidates, Expectation, Gen, PropertyFailure, PropertyReport};
+use spray::FailureStage;
+
+#[test]
+fn test_gen_records_and_replays() {
+    let mut rng = Gen::from_seed(5);
+    let drawn: Vec<bool> = (0..8).map(|_| rng.gen()).collect();
+    assert_eq!(rng.tape().len(), 8);
+    assert_eq!(Gen::from_seed(5).next_u64(), rng.tape()[0]);
+
+    let mut replay = Gen::replay(rng.tape().to_vec());
+    let replayed: Vec<bool> = (0..8).map(|_| replay.gen()).collect();
+    assert_eq!(drawn, replayed);
+
+    let mut short = Gen::replay(vec![u64::MAX]);
+    assert_eq!(short.next_u64(), u64::MAX);
+    assert_eq!(short.next_u64(), 0);
+    assert_eq!(short.tape(), &[u64::MAX]);
+}
+
+#[test]
+fn test_gen_fill_bytes() {
+    let mut rng = Gen::replay(vec![0x0102_0304_0506_0708, 0x0900_0000_0000_0000]);
+    let mut bytes = [0; 9];
+    rng.fill_bytes(&mut bytes);
+    assert_eq!(bytes, [1, 2, 3, 4, 5, 6, 7, 8, 9]);
+}
+
+#[test]
+fn test_shrink_candidates() {
+    assert!(shrink_candidates(&[]).is_empty());
+    assert_eq!(shrink_candidates(&[0]), vec![Vec::<u64>::new()]);
+    assert_eq!(
+        shrink_candidates(&[4, 1]),
+        vec![vec![4], vec![0, 1], vec![2, 1], vec![4, 0]]
+    );
+}
+
+#[test]
+fn test_expectation_holds() {
+    let rejected = Outcome::Rejected {
+        stage: FailureStage::NodeRejection,
+        reason: "non-final".into(),
+    };
+    assert!(Expectation::Accept.holds(&Outcome::Accepted));
+    assert!(!Expectation::Accept.holds(&rejected));
+    assert!(Expectation::Reject.holds(&rejected));
+    assert!(!Expectation::Reject.holds(&Outcome::Accepted));
+}
+
+#[test]
+fn test_report_display() {
+    let passed = PropertyReport {
+        seed: 9,
+        cases: 32,
+        failure: None,
+    };
+    assert!(passed.passed());
+    assert_eq!(passed.to_string(), "32 cases passed (seed 9)");
+
+    let failed = PropertyReport {
+        seed: 9,
+        cases: 4,
+        failure: Some(PropertyFailure {
+            case: 3,
+            shrinks: 2,
+            tape: vec![0],
+            arguments: "{}".into(),
+            witness: "{}".into(),
+            expected: Expectation::Reject,
+            outcome: Outcome::Accepted,
+        }),
+    };
+    assert!(!failed.passed());
+    let message = failed.to_string();
+    assert!(message.contains("case 3 (shrunk 2 times)"));
+    assert!(message.contains("expected the spend to be rejected, but it was accepted"));
+    assert!(message.ends_with("seed: 9"));
+}
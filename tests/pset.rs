@@ -0,0 +1,71 @@
+//! Unit tests for PSET export
+
+use musk::elements::{AssetId, LockTime, OutPoint, Script, Sequence, TxOut, Txid};
+use spray::confidential::explicit_output;
+use spray::pset::{SimplicityLeaf, SIMPLICITY_LEAF_VERSION};
+
+fn asset() -> AssetId {
+    "25b251070e29ca19043cf33ccd7324e2ddab03ecc4ae0b5e77c4fc0e5cf6c95a"
+        .parse()
+        .unwrap()
+}
+
+fn outpoint() -> OutPoint {
+    let txid: Txid = "00".repeat(32).parse().unwrap();
+    OutPoint::new(txid, 0)
+}
+
+#[test]
+fn test_leaf_is_deterministic() {
+    let a = SimplicityLeaf::new([1; 32]).unwrap();
+    let b = SimplicityLeaf::new([1; 32]).unwrap();
+    let c = SimplicityLeaf::new([2; 32]).unwrap();
+
+    assert_eq!(a.script_pubkey, b.script_pubkey);
+    assert_ne!(a.script_pubkey, c.script_pubkey);
+    assert_eq!(a.version.to_consensus(), SIMPLICITY_LEAF_VERSION);
+    assert!(a.script_pubkey.is_v1_p2tr());
+}
+
+#[test]
+fn test_spend_pset_attaches_leaf() {
+    let leaf = SimplicityLeaf::new([1; 32]).unwrap();
+    let prevout = explicit_output(leaf.script_pubkey.clone(), 100_000, asset());
+
+    let pset = leaf
+        .spend_pset(
+            outpoint(),
+            prevout.clone(),
+            Sequence::MAX,
+            LockTime::ZERO,
+            vec![
+                explicit_output(Script::new(), 97_000, asset()),
+                TxOut::new_fee(3_000, asset()),
+            ],
+        )
+        .unwrap();
+
+    assert_eq!(pset.n_inputs(), 1);
+    assert_eq!(pset.n_outputs(), 2);
+
+    let input = &pset.inputs()[0];
+    assert_eq!(input.witness_utxo, Some(prevout));
+    assert_eq!(input.tap_internal_key, Some(leaf.internal_key));
+    assert!(input.tap_scripts.contains_key(&leaf.control_block));
+}
+
+#[test]
+fn test_spend_pset_rejects_foreign_utxo() {
+    let leaf = SimplicityLeaf::new([1; 32]).unwrap();
+    let other = SimplicityLeaf::new([2; 32]).unwrap();
+    let prevout = explicit_output(other.script_pubkey, 100_000, asset());
+
+    let result = leaf.spend_pset(
+        outpoint(),
+        prevout,
+        Sequence::MAX,
+        LockTime::ZERO,
+        Vec::new(),
+    );
+    assert!(result.is_err());
+}
@@ -0,0 +1,255 @@
+//! Unit tests for the deployment registry
+
+use spray::error::SprayError;
+use spray::registry::{DeploymentRecord, Registry, DEFAULT_LEASE_TTL};
+use std::time::Duration;
+
+fn record(txid_byte: &str, amount: u64, asset: Option<&str>) -> DeploymentRecord {
+    DeploymentRecord {
+        txid: txid_byte.repeat(32),
+        vout: 0,
+        cmr: "deadbeef".to_string(),
+        network: "regtest".to_string(),
+        amount,
+        asset: asset.map(str::to_string),
+        timestamp: 0,
+        ..Default::default()
+    }
+}
+
+#[test]
+fn test_load_missing_file_is_empty() {
+    let dir = tempfile::tempdir().expect("Failed to create temp dir");
+    let registry = Registry::load(&dir.path().join("deployments.json")).expect("Failed to load");
+    assert!(registry.records().is_empty());
+}
+
+#[test]
+fn test_save_and_reload() {
+    let dir = tempfile::tempdir().expect("Failed to create temp dir");
+    let path = dir.path().join("deployments.json");
+
+    let mut registry = Registry::load(&path).expect("Failed to load");
+    registry.add(record("aa", 1_000, None));
+    registry.save().expect("Failed to save");
+
+    let reloaded = Registry::load(&path).expect("Failed to reload");
+    assert_eq!(reloaded.records(), registry.records());
+}
+
+#[test]
+fn test_save_leaves_no_temporary_files() {
+    let dir = tempfile::tempdir().expect("Failed to create temp dir");
+    let path = dir.path().join("deployments.json");
+
+    let mut registry = Registry::load(&path).expect("Failed to load");
+    registry.add(record("aa", 1_000, None));
+    registry.save().expect("Failed to save");
+    registry.add(record("bb", 2_000, None));
+    registry.save().expect("Failed to save again");
+
+    let files: Vec<_> = std::fs::read_dir(dir.path())
+        .expect("Failed to read dir")
+        .map(|entry| entry.expect("Failed to read entry").file_name())
+        .collect();
+    assert_eq!(files, ["deployments.json"]);
+    assert_eq!(Registry::load(&path).unwrap().records().len(), 2);
+}
+
+#[test]
+fn test_update_keeps_records_of_concurrent_runs() {
+    let dir = tempfile::tempdir().expect("Failed to create temp dir");
+    let path = dir.path().join("deployments.json");
+
+    std::thread::scope(|scope| {
+        for i in 0..8u64 {
+            let path = &path;
+            scope.spawn(move || {
+                Registry::update(path, |registry| {
+                    registry.add(record(&format!("{i:02x}"), 1_000 + i, None));
+                    Ok(())
+                })
+                .expect("Failed to update");
+            });
+        }
+    });
+
+    let registry = Registry::load(&path).expect("Failed to reload");
+    assert_eq!(registry.records().len(), 8);
+    assert!(!path.with_extension("lock").exists());
+}
+
+#[test]
+fn test_lock_is_exclusive_until_dropped() {
+    let dir = tempfile::tempdir().expect("Failed to create temp dir");
+    let path = dir.path().join("deployments.json");
+
+    let lock = Registry::lock(&path, Duration::ZERO).expect("Failed to lock");
+    let err = Registry::lock(&path, Duration::ZERO).expect_err("Registry should be locked");
+    assert!(err.to_string().contains("is locked by run"));
+
+    drop(lock);
+    Registry::lock(&path, Duration::ZERO).expect("Lock should be free after drop");
+}
+
+#[test]
+fn test_find_matching_requires_identical_deployment() {
+    let dir = tempfile::tempdir().expect("Failed to create temp dir");
+    let mut registry =
+        Registry::load(&dir.path().join("deployments.json")).expect("Failed to load");
+    registry.add(record("aa", 1_000, None));
+    registry.add(record("bb", 1_000, None));
+    registry.add(record("cc", 2_000, None));
+    registry.add(record("dd", 1_000, Some("ff")));
+
+    let matches: Vec<_> = registry
+        .find_matching("deadbeef", 1_000, None, "regtest")
+        .collect();
+    assert_eq!(matches.len(), 2);
+    assert_eq!(
+        matches[0].txid,
+        "bb".repeat(32),
+        "Newest match should come first"
+    );
+
+    assert_eq!(
+        registry
+            .find_matching("deadbeef", 1_000, Some("ff"), "regtest")
+            .count(),
+        1
+    );
+    assert_eq!(
+        registry
+            .find_matching("deadbeef", 1_000, None, "testnet")
+            .count(),
+        0
+    );
+}
+
+#[test]
+fn test_find_by_outpoint() {
+    let dir = tempfile::tempdir().expect("Failed to create temp dir");
+    let mut registry =
+        Registry::load(&dir.path().join("deployments.json")).expect("Failed to load");
+    registry.add(record("aa", 1_000, None));
+
+    assert!(registry.find(&"aa".repeat(32), 0).is_some());
+    assert!(registry.find(&"aa".repeat(32), 1).is_none());
+}
+
+#[test]
+fn test_artifact_paths_round_trip() {
+    let dir = tempfile::tempdir().expect("Failed to create temp dir");
+    let path = dir.path().join("deployments.json");
+
+    let mut registry = Registry::load(&path).expect("Failed to load");
+    registry.add(DeploymentRecord {
+        artifact: Some("/work/compiled.json".to_string()),
+        args: Some("/work/args.toml".to_string()),
+        ..record("aa", 1_000, None)
+    });
+    registry.save().expect("Failed to save");
+
+    let reloaded = Registry::load(&path).expect("Failed to reload");
+    let found = reloaded.find(&"aa".repeat(32), 0).expect("Record missing");
+    assert_eq!(found.artifact.as_deref(), Some("/work/compiled.json"));
+    assert_eq!(found.args.as_deref(), Some("/work/args.toml"));
+}
+
+#[test]
+fn test_records_without_artifact_still_load() {
+    let dir = tempfile::tempdir().expect("Failed to create temp dir");
+    let path = dir.path().join("deployments.json");
+    std::fs::write(
+        &path,
+        r#"[{"txid":"aa","vout":1,"cmr":"deadbeef","network":"regtest","amount":1000,"timestamp":0}]"#,
+    )
+    .expect("Failed to write registry");
+
+    let registry = Registry::load(&path).expect("Failed to load");
+    assert_eq!(registry.records()[0].artifact, None);
+    assert_eq!(registry.records()[0].args, None);
+}
+
+#[test]
+fn test_record_path_is_absolute() {
+    let dir = tempfile::tempdir().expect("Failed to create temp dir");
+    let file = dir.path().join("program.simf");
+    std::fs::write(&file, "fn main() {}").expect("Failed to write program");
+
+    assert!(std::path::Path::new(&spray::registry::record_path(&file)).is_absolute());
+}
+
+#[test]
+fn test_lease_is_exclusive_until_dropped() {
+    let dir = tempfile::tempdir().expect("Failed to create temp dir");
+    let registry = Registry::load(&dir.path().join("deployments.json")).expect("Failed to load");
+    let txid = "aa".repeat(32);
+
+    let lease = registry
+        .lease(&txid, 0, "run-a", DEFAULT_LEASE_TTL)
+        .expect("Failed to lease");
+    assert_eq!(lease.info().run_id, "run-a");
+
+    let err = registry
+        .lease(&txid, 0, "run-b", DEFAULT_LEASE_TTL)
+        .expect_err("Outpoint should be locked");
+    assert!(matches!(err, SprayError::OutpointLocked(_)));
+    assert!(err.to_string().contains("locked by run run-a"));
+
+    // A different outpoint is unaffected
+    let _other = registry
+        .lease(&txid, 1, "run-b", DEFAULT_LEASE_TTL)
+        .expect("Failed to lease other outpoint");
+
+    drop(lease);
+    registry
+        .lease(&txid, 0, "run-b", DEFAULT_LEASE_TTL)
+        .expect("Lease should be free after drop");
+}
+
+#[test]
+fn test_expired_lease_is_taken_over() {
+    let dir = tempfile::tempdir().expect("Failed to create temp dir");
+    let registry = Registry::load(&dir.path().join("deployments.json")).expect("Failed to load");
+    let txid = "aa".repeat(32);
+
+    let stale = registry
+        .lease(&txid, 0, "run-a", Duration::ZERO)
+        .expect("Failed to lease");
+    std::mem::forget(stale);
+
+    let lease = registry
+        .lease(&txid, 0, "run-b", DEFAULT_LEASE_TTL)
+        .expect("Stale lease should be taken over");
+    assert_eq!(lease.info().run_id, "run-b");
+}
+
+#[test]
+fn test_unreadable_lease_is_held_until_ttl() {
+    let dir = tempfile::tempdir().expect("Failed to create temp dir");
+    let registry = Registry::load(&dir.path().join("deployments.json")).expect("Failed to load");
+    let txid = "aa".repeat(32);
+    std::fs::create_dir_all(registry.lease_dir()).expect("Failed to create lease dir");
+    std::fs::write(
+        registry.lease_dir().join(format!("{txid}_0.json")),
+        "{\"outp",
+    )
+    .expect("Failed to write partial lease");
+
+    let err = registry
+        .lease(&txid, 0, "run-b", DEFAULT_LEASE_TTL)
+        .expect_err("Partly written lease should be held");
+    assert!(matches!(err, SprayError::OutpointLocked(_)));
+
+    let lease = registry
+        .lease(&txid, 0, "run-b", Duration::ZERO)
+        .expect("Unreadable lease older than the TTL should be taken over");
+    assert_eq!(lease.info().run_id, "run-b");
+    drop(lease);
+
+    let leftovers: Vec<_> = std::fs::read_dir(registry.lease_dir())
+        .expect("Failed to read lease dir")
+        .collect();
+    assert!(leftovers.is_empty());
+}
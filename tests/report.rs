@@ -0,0 +1,180 @@
+//! Unit tests for suite reports
+
+use spray::report::{format_ms, histogram, SuiteReport, TestRecord, HISTOGRAM_BOUNDS_MS};
+
+fn record(name: &str, duration_ms: u64) -> TestRecord {
+    TestRecord::failure(name, duration_ms, "error".to_string())
+}
+
+#[test]
+fn test_histogram_has_open_ended_bucket() {
+    let buckets = histogram(&[]);
+    assert_eq!(buckets.len(), HISTOGRAM_BOUNDS_MS.len() + 1);
+    assert!(buckets.last().unwrap().upper_ms.is_none());
+    assert!(buckets.iter().all(|b| b.count == 0));
+}
+
+#[test]
+fn test_histogram_bucketing() {
+    let tests = vec![
+        record("a", 0),
+        record("b", 99),
+        record("c", 100),
+        record("d", 45_000),
+    ];
+    let buckets = histogram(&tests);
+
+    assert_eq!(buckets[0].count, 2, "0ms and 99ms fall below 100ms");
+    assert_eq!(buckets[1].count, 1, "100ms falls in the < 500ms bucket");
+    assert_eq!(buckets.last().unwrap().count, 1);
+}
+
+#[test]
+fn test_slowest_sorted_and_truncated() {
+    let tests = vec![record("a", 10), record("b", 300), record("c", 20)];
+    let report = SuiteReport::new(tests, 2);
+
+    let names: Vec<&str> = report.slowest.iter().map(|t| t.name.as_str()).collect();
+    assert_eq!(names, vec!["b", "c"]);
+    assert_eq!(report.total_duration_ms, 330);
+    assert_eq!(report.tests.len(), 3, "All records should be kept");
+}
+
+#[test]
+fn test_report_json_includes_timing() {
+    let report = SuiteReport::new(vec![record("a", 1_500)], 5);
+    let json = serde_json::to_string(&report).expect("Failed to serialize");
+
+    assert!(json.contains("\"histogram\""));
+    assert!(json.contains("\"slowest\""));
+    assert!(json.contains("\"duration_ms\":1500"));
+}
+
+#[test]
+fn test_format_ms() {
+    assert_eq!(format_ms(250), "250ms");
+    assert_eq!(format_ms(2_000), "2s");
+    assert_eq!(format_ms(1_500), "1.5s");
+}
+
+#[test]
+fn test_failures_grouped_by_stage() {
+    use spray::FailureStage;
+
+    let tests = vec![
+        record("a", 10).with_stage(FailureStage::Funding),
+        record("b", 10).with_stage(FailureStage::Funding),
+        record("c", 10).with_stage(FailureStage::NodeRejection),
+    ];
+    let report = SuiteReport::new(tests, 5);
+
+    assert_eq!(
+        report.failures_by_stage.get(&FailureStage::Funding),
+        Some(&2)
+    );
+    assert_eq!(
+        report.failures_by_stage.get(&FailureStage::NodeRejection),
+        Some(&1)
+    );
+    assert_eq!(report.failures_by_stage.get(&FailureStage::Compile), None);
+
+    let json = serde_json::to_string(&report).expect("Failed to serialize");
+    assert!(json.contains("\"node_rejection\":1"));
+}
+
+#[test]
+fn test_flaky_only_when_passed_on_retry() {
+    let mut passed = record("spend", 10);
+    passed.success = true;
+    passed.error = None;
+
+    let flaky = passed.clone().with_retries(2);
+    assert!(flaky.flaky);
+    assert_eq!(flaky.retries, 2);
+    assert!(!passed.clone().with_retries(0).flaky);
+    assert!(!record("broken", 10).with_retries(2).flaky);
+
+    let report = SuiteReport::new(vec![flaky, passed, record("broken", 10)], 5);
+    assert_eq!(report.flaky(), 1);
+    assert_eq!(report.passed(), 2);
+
+    let json = serde_json::to_string(&report.tests).expect("Failed to serialize");
+    assert_eq!(json.matches("\"flaky\":true").count(), 1);
+    assert_eq!(json.matches("\"retries\"").count(), 1);
+}
+
+#[test]
+fn test_tap_marks_flaky_tests() {
+    let mut passed = record("spend", 10);
+    passed.success = true;
+    passed.error = None;
+
+    let tap = spray::report::tap(&[passed.with_retries(1)]);
+    assert!(tap.contains("ok 1 - spend\n# flaky: passed after 1 retries\n"));
+}
+
+#[test]
+fn test_stage_refine_detects_timeouts() {
+    use spray::FailureStage;
+
+    assert_eq!(
+        FailureStage::Funding.refine("Operation timed out"),
+        FailureStage::Timeout
+    );
+    assert_eq!(
+        FailureStage::Funding.refine("insufficient funds"),
+        FailureStage::Funding
+    );
+}
+
+#[test]
+fn test_stage_is_retryable() {
+    use spray::FailureStage;
+
+    assert!(FailureStage::Funding.is_retryable());
+    assert!(FailureStage::NodeRejection.is_retryable());
+    assert!(FailureStage::Satisfaction
+        .refine("RPC request timed out")
+        .is_retryable());
+    assert!(!FailureStage::Compile.is_retryable());
+    assert!(!FailureStage::Satisfaction.is_retryable());
+    assert!(!FailureStage::Hook.is_retryable());
+    assert!(!FailureStage::Config.is_retryable());
+}
+
+#[test]
+fn test_tap_stream() {
+    let mut passed = record("spend ok", 250);
+    passed.success = true;
+    passed.error = None;
+    passed.txid = Some("ab".repeat(32));
+    let failed = record("timelock #2", 1_500).with_stage(spray::FailureStage::Satisfaction);
+
+    let tap = SuiteReport::new(vec![passed, failed], 1).to_tap();
+    let lines: Vec<&str> = tap.lines().collect();
+    assert_eq!(
+        lines,
+        vec![
+            "TAP version 13",
+            "1..2",
+            "ok 1 - spend ok",
+            &format!("# txid: {}", "ab".repeat(32)),
+            "# duration: 250ms",
+            "not ok 2 - timelock \\#2",
+            "# stage: satisfaction",
+            "# error: error",
+            "# duration: 1.5s",
+        ]
+    );
+}
+
+#[test]
+fn test_tap_multiline_error() {
+    let tap = spray::report::tap(&[TestRecord::failure("t", 0, "first\nsecond".into())]);
+    assert!(tap.contains("# error: first\n#   second\n"));
+}
+
+#[test]
+fn test_tap_empty_plan() {
+    assert_eq!(spray::report::tap(&[]), "TAP version 13\n1..0\n");
+}
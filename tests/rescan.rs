@@ -0,0 +1,52 @@
+//! Unit tests for bounded wallet rescans
+
+use spray::registry::DeploymentRecord;
+use spray::rescan::{chunks, rescan_start};
+
+fn record(cmr: &str, network: &str, height: Option<u64>) -> DeploymentRecord {
+    DeploymentRecord {
+        txid: "aa".repeat(32),
+        cmr: cmr.to_string(),
+        network: network.to_string(),
+        height,
+        ..Default::default()
+    }
+}
+
+#[test]
+fn test_rescan_start_uses_earliest_deployment() {
+    let records = [
+        record("deadbeef", "testnet", Some(500)),
+        record("deadbeef", "testnet", Some(120)),
+        record("deadbeef", "liquid", Some(10)),
+        record("cafebabe", "testnet", Some(5)),
+    ];
+    assert_eq!(rescan_start(&records, "deadbeef", "testnet"), Some(120));
+}
+
+#[test]
+fn test_rescan_start_without_heights() {
+    let records = [
+        record("deadbeef", "testnet", Some(500)),
+        record("deadbeef", "testnet", None),
+    ];
+    assert_eq!(rescan_start(&records, "deadbeef", "testnet"), None);
+    assert_eq!(rescan_start(&records, "cafebabe", "testnet"), None);
+}
+
+#[test]
+fn test_chunks_cover_range() {
+    assert_eq!(chunks(0, 9, 5), vec![(0, 4), (5, 9)]);
+    assert_eq!(chunks(7, 7, 1_000), vec![(7, 7)]);
+    assert_eq!(chunks(3, 2, 10), Vec::new());
+    assert_eq!(chunks(0, 2, 0), vec![(0, 0), (1, 1), (2, 2)]);
+}
+
+#[test]
+fn test_chunks_do_not_overflow() {
+    assert_eq!(chunks(5, 100, u64::MAX), vec![(5, 100)]);
+    assert_eq!(
+        chunks(u64::MAX - 1, u64::MAX, 10),
+        vec![(u64::MAX - 1, u64::MAX)]
+    );
+}
@@ -0,0 +1,92 @@
+//! Unit tests for retry backoff and transient RPC failures
+
+use spray::retry::{
+    is_idempotent, is_missing_inputs, is_transient, retry_transient, Backoff, DEFAULT_RPC_BACKOFF,
+    DEFAULT_RETRIES, DEFAULT_RPC_RETRIES, MAX_DELAY,
+};
+use std::time::Duration;
+
+#[test]
+fn test_delay_doubles_up_to_max() {
+    let backoff = Backoff::new(10, Duration::from_secs(1));
+    let delays: Vec<u64> = (0..8).map(|i| backoff.delay(i).as_secs()).collect();
+    assert_eq!(delays, [1, 2, 4, 8, 16, 32, 60, 60]);
+    assert_eq!(backoff.delay(u32::MAX), MAX_DELAY);
+}
+
+#[test]
+fn test_default_and_none() {
+    assert_eq!(Backoff::default().retries, DEFAULT_RETRIES);
+    assert_eq!(Backoff::none().retries, 0);
+    assert_eq!(Backoff::none().delay(3), Duration::ZERO);
+}
+
+#[test]
+fn test_is_missing_inputs() {
+    assert!(is_missing_inputs(
+        "RPC error: JSON-RPC error: RPC error response: RpcError { code: -25, message: \"missing-inputs\" }"
+    ));
+    assert!(is_missing_inputs("bad-txns-inputs-missingorspent"));
+    assert!(!is_missing_inputs("txn-mempool-conflict"));
+    assert!(!is_missing_inputs("non-final"));
+}
+
+#[test]
+fn test_is_transient() {
+    assert!(is_transient("Operation timed out (os error 110)"));
+    assert!(is_transient(
+        "transport error: HTTP error 503 Service Unavailable"
+    ));
+    assert!(is_transient("RPC error: Loading block index…"));
+    assert!(!is_transient("bad-txns-inputs-missingorspent"));
+    assert!(!is_transient("Insufficient funds"));
+}
+
+#[test]
+fn test_retry_transient_stops_on_permanent_error() {
+    let mut attempts = 0;
+    let result: Result<(), &str> = retry_transient(
+        Backoff::new(5, Duration::ZERO),
+        || {
+            attempts += 1;
+            Err(if attempts == 1 {
+                "timed out"
+            } else {
+                "non-final"
+            })
+        },
+        || None,
+    );
+    assert_eq!((result, attempts), (Err("non-final"), 2));
+}
+
+#[test]
+fn test_retry_transient_recovers() {
+    let mut attempts = 0;
+    let result = retry_transient(
+        Backoff::new(5, Duration::ZERO),
+        || {
+            attempts += 1;
+            Err::<&str, _>("timed out")
+        },
+        || Some("already in mempool"),
+    );
+    assert_eq!((result, attempts), (Ok("already in mempool"), 1));
+}
+
+#[test]
+fn test_non_idempotent_methods() {
+    assert!(!is_idempotent("sendrawtransaction"));
+    assert!(!is_idempotent("sendtoaddress"));
+    assert!(is_idempotent("getrawtransaction"));
+    assert!(is_idempotent("listunspent"));
+    assert!(is_idempotent("decoderawtransaction"));
+    assert!(is_idempotent("estimatesmartfee"));
+    assert!(is_idempotent("testmempoolaccept"));
+    // Anything not known to be read-only is not repeated
+    assert!(!is_idempotent("getnewaddress"));
+    assert!(!is_idempotent("signrawtransactionwithwallet"));
+    assert!(!is_idempotent("walletpassphrase"));
+    assert!(!is_idempotent("someunknownmethod"));
+    assert_eq!(DEFAULT_RPC_BACKOFF.retries, DEFAULT_RPC_RETRIES);
+}
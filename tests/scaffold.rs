@@ -0,0 +1,72 @@
+//! Unit tests for argument and witness templates
+
+use spray::scaffold::{Scaffold, ARGS_TEMPLATE, WITNESS_TEMPLATE};
+use spray::value::ValueType;
+
+const HTLC: &str = include_str!("../templates/htlc/htlc.simf");
+
+#[test]
+fn test_placeholders() {
+    for (ty, placeholder) in [
+        ("()", "()"),
+        ("bool", "false"),
+        ("u8", "0"),
+        ("u128", "0x00000000000000000000000000000000"),
+        ("[u8; 3]", "0x000000"),
+        ("[u16; 2]", "[0, 0]"),
+        ("(u1,)", "(0,)"),
+        ("Either<Option<u8>, bool>", "Left(None)"),
+    ] {
+        let ty: ValueType = ty.parse().unwrap();
+        assert_eq!(ty.placeholder().unwrap(), placeholder, "{ty}");
+    }
+}
+
+#[test]
+fn test_scaffold_htlc() {
+    let scaffold = Scaffold::from_source(HTLC).unwrap();
+    let parameters: Vec<&str> = scaffold.parameters.keys().map(String::as_str).collect();
+    assert_eq!(
+        parameters,
+        [
+            "HASH",
+            "RECIPIENT_PUBLIC_KEY",
+            "SENDER_PUBLIC_KEY",
+            "TIMEOUT"
+        ]
+    );
+    assert_eq!(scaffold.witnesses["PREIMAGE"], "Option<u256>");
+
+    let witness = scaffold.witness_template();
+    assert_eq!(witness["PREIMAGE"]["value"], "None");
+    assert_eq!(witness["PREIMAGE"]["type"], "Option<u256>");
+
+    // The placeholder arguments load as arguments
+    let args: musk::Arguments = serde_json::from_value(scaffold.args_template()).unwrap();
+    assert!(musk::Program::from_source(HTLC)
+        .unwrap()
+        .instantiate(args)
+        .is_ok());
+}
+
+#[test]
+fn test_scaffold_write() {
+    let dir = tempfile::tempdir().unwrap();
+    let scaffold = Scaffold::from_source(HTLC).unwrap();
+
+    let written = scaffold.write(dir.path(), false).unwrap();
+    assert_eq!(
+        written,
+        [
+            dir.path().join(ARGS_TEMPLATE),
+            dir.path().join(WITNESS_TEMPLATE)
+        ]
+    );
+    let args: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(&written[0]).unwrap()).unwrap();
+    assert_eq!(args["TIMEOUT"]["value"], "0");
+
+    // Existing templates are kept unless forced
+    assert!(scaffold.write(dir.path(), false).is_err());
+    assert!(scaffold.write(dir.path(), true).is_ok());
+}
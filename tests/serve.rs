@@ -0,0 +1,247 @@
+//! Unit tests for the HTTP API
+
+use serde_json::{json, Value};
+use spray::serve::{
+    authorize, response, status_of, Endpoint, Request, MAX_BODY_SIZE, MAX_HEAD_SIZE,
+};
+use spray::SprayError;
+
+const TXID: &str = "0000000000000000000000000000000000000000000000000000000000000001";
+const ADDRESS: &str = "ert1qw508d6qejxtdg4y5r3zarvary0c5xw7kuu73e0";
+
+fn request(method: &str, path: &str, body: &str) -> String {
+    format!(
+        "{method} {path} HTTP/1.1\r\nHost: localhost\r\ncontent-length: {}\r\n\r\n{body}",
+        body.len()
+    )
+}
+
+fn with_headers(headers: &str) -> Request {
+    let raw = format!("POST /deploy HTTP/1.1\r\n{headers}Content-Length: 2\r\n\r\n{{}}");
+    Request::read(raw.as_bytes()).unwrap()
+}
+
+#[test]
+fn test_route() {
+    for endpoint in Endpoint::ALL {
+        let (method, path) = endpoint.method_and_path();
+        assert_eq!(Endpoint::route(method, path), Some(endpoint));
+    }
+    assert_eq!(
+        Endpoint::route("POST", "/test?verbose=1"),
+        Some(Endpoint::Test)
+    );
+    assert_eq!(Endpoint::route("GET", "/deploy"), None);
+    assert_eq!(Endpoint::route("POST", "/broadcast"), None);
+}
+
+#[test]
+fn test_read_request() {
+    let raw = request("POST", "/compile", r#"{"source": "fn main() {}"}"#);
+    let request = Request::read(raw.as_bytes()).unwrap();
+    assert_eq!(request.method, "POST");
+    assert_eq!(request.path, "/compile");
+    assert_eq!(request.body, json!({ "source": "fn main() {}" }));
+    assert_eq!(request.header("HOST"), Some("localhost"));
+    assert_eq!(request.header("origin"), None);
+
+    let raw = "GET /health HTTP/1.1\r\nHost: localhost\r\n\r\n";
+    assert_eq!(Request::read(raw.as_bytes()).unwrap().body, Value::Null);
+}
+
+#[test]
+fn test_read_bad_request() {
+    assert!(Request::read("\r\n".as_bytes()).is_err());
+    assert!(Request::read("POST /deploy HTTP/1.1\r\n".as_bytes()).is_err());
+    assert!(Request::read(request("POST", "/deploy", "{not json").as_bytes()).is_err());
+
+    let raw = format!(
+        "POST /deploy HTTP/1.1\r\nContent-Length: {}\r\n\r\n",
+        MAX_BODY_SIZE + 1
+    );
+    assert!(Request::read(raw.as_bytes()).is_err());
+}
+
+#[test]
+fn test_read_large_head() {
+    let long_line = format!("GET /{} HTTP/1.1\r\n\r\n", "a".repeat(MAX_HEAD_SIZE));
+    let err = Request::read(long_line.as_bytes()).unwrap_err();
+    assert!(err.to_string().contains("larger than"), "{err}");
+
+    let headers = "X-Filler: 0123456789abcdef\r\n".repeat(MAX_HEAD_SIZE / 16);
+    let many_headers = format!("GET /health HTTP/1.1\r\n{headers}\r\n");
+    assert!(Request::read(many_headers.as_bytes()).is_err());
+
+    // Headers just under the limit are read, and the body after them in full
+    let body = json!({ "source": "x".repeat(MAX_HEAD_SIZE) }).to_string();
+    let filler = "x".repeat(MAX_HEAD_SIZE - 200);
+    let raw = format!(
+        "POST /compile HTTP/1.1\r\nX-Filler: {filler}\r\nContent-Length: {}\r\n\r\n{body}",
+        body.len()
+    );
+    let request = Request::read(raw.as_bytes()).unwrap();
+    assert_eq!(
+        request.body["source"].as_str().unwrap().len(),
+        MAX_HEAD_SIZE
+    );
+}
+
+#[test]
+fn test_compile_arguments() {
+    let temp = tempfile::tempdir().unwrap();
+    let dir = temp.path();
+    let body = json!({
+        "source": "fn main() {}",
+        "arguments": { "X": { "value": "1", "type": "u32" } },
+    });
+    let args = Endpoint::Compile.arguments(&body, dir).unwrap();
+    assert_eq!(
+        args,
+        [
+            "compile".to_string(),
+            "--args".to_string(),
+            dir.join("args.json").display().to_string(),
+            "--".to_string(),
+            dir.join("program.simf").display().to_string(),
+        ]
+    );
+    let written: Value =
+        serde_json::from_str(&std::fs::read_to_string(dir.join("args.json")).unwrap()).unwrap();
+    assert_eq!(written, body["arguments"]);
+}
+
+#[test]
+fn test_deploy_arguments() {
+    let temp = tempfile::tempdir().unwrap();
+    let dir = temp.path();
+    let body = json!({ "compiled": { "program": "", "cmr": "" }, "amount": 5000 });
+    let args = Endpoint::Deploy.arguments(&body, dir).unwrap();
+    assert_eq!(args[1..4], ["--amount", "5000", "--"]);
+    assert_eq!(args[4], dir.join("program.json").display().to_string());
+
+    let body = json!({ "source": "fn main() {}", "amount": "5000 --dry-run" });
+    assert!(Endpoint::Deploy.arguments(&body, dir).is_err());
+    let body = json!({ "source": "fn main() {}", "asset": "--help" });
+    assert!(Endpoint::Deploy.arguments(&body, dir).is_err());
+
+    let error = Endpoint::Deploy.arguments(&json!({}), dir).unwrap_err();
+    assert!(error.to_string().contains("'source or compiled'"));
+}
+
+#[test]
+fn test_redeem_arguments() {
+    let temp = tempfile::tempdir().unwrap();
+    let dir = temp.path();
+    let utxo = format!("{TXID}:0");
+    let body = json!({
+        "utxo": utxo,
+        "witness": {},
+        "destination": ADDRESS,
+        "fee": 300,
+        "dry_run": true,
+    });
+    let args = Endpoint::Redeem.arguments(&body, dir).unwrap();
+    assert_eq!(
+        args,
+        [
+            "redeem".to_string(),
+            "--dest".to_string(),
+            ADDRESS.to_string(),
+            "--fee".to_string(),
+            "300".to_string(),
+            "--dry-run".to_string(),
+            "--".to_string(),
+            utxo.clone(),
+            dir.join("witness.json").display().to_string(),
+        ]
+    );
+
+    assert!(Endpoint::Redeem
+        .arguments(&json!({ "utxo": utxo }), dir)
+        .is_err());
+    assert!(Endpoint::Redeem
+        .arguments(&json!({ "utxo": [utxo], "witness": {} }), dir)
+        .is_err());
+    assert!(Endpoint::Redeem
+        .arguments(&json!({ "utxo": "--help", "witness": {} }), dir)
+        .is_err());
+    let body = json!({ "utxo": utxo, "witness": {}, "destination": "--fee=1" });
+    assert!(Endpoint::Redeem.arguments(&body, dir).is_err());
+}
+
+#[test]
+fn test_test_arguments() {
+    let temp = tempfile::tempdir().unwrap();
+    let dir = temp.path();
+    let body = json!({ "source": "fn main() {}", "lock_time": 100 });
+    let args = Endpoint::Test.arguments(&body, dir).unwrap();
+    assert_eq!(args[..2], ["test", "--file"]);
+    assert_eq!(args[3..], ["--lock-time", "100"]);
+    let body = json!({ "source": "fn main() {}", "sequence": -1 });
+    assert!(Endpoint::Test.arguments(&body, dir).is_err());
+    assert!(!Endpoint::Test.uses_node());
+    assert!(Endpoint::Deploy.uses_node());
+}
+
+#[test]
+fn test_authorize() {
+    let json = "Content-Type: application/json; charset=utf-8\r\n";
+    let authorized = |headers: &str| {
+        authorize(&with_headers(headers), Endpoint::Deploy, "secret").map_err(|(status, _)| status)
+    };
+    assert_eq!(
+        authorized(&format!("Authorization: Bearer secret\r\n{json}")),
+        Ok(())
+    );
+    assert_eq!(
+        authorized(&format!(
+            "Origin: http://localhost:3000\r\nAuthorization: Bearer secret\r\n{json}"
+        )),
+        Ok(())
+    );
+    assert_eq!(authorized(json), Err(401));
+    assert_eq!(
+        authorized(&format!("Authorization: Bearer wrong\r\n{json}")),
+        Err(401)
+    );
+    assert_eq!(authorized("Authorization: Bearer secret\r\n"), Err(415));
+    assert_eq!(
+        authorized("Authorization: Bearer secret\r\nContent-Type: text/plain\r\n"),
+        Err(415)
+    );
+    assert_eq!(
+        authorized(&format!(
+            "Origin: https://evil.example\r\nAuthorization: Bearer secret\r\n{json}"
+        )),
+        Err(403)
+    );
+
+    let health = Request::read("GET /health HTTP/1.1\r\n\r\n".as_bytes()).unwrap();
+    assert!(authorize(&health, Endpoint::Health, "secret").is_ok());
+    assert_eq!(spray::serve::generate_token().len(), 32);
+}
+
+#[test]
+fn test_response() {
+    let ok = response(200, &json!({ "ok": true }));
+    assert!(ok.starts_with("HTTP/1.1 200 OK\r\n"));
+    assert!(ok.contains("Content-Type: application/json\r\n"));
+    assert!(ok.contains("Content-Length: 12\r\n"));
+    assert!(ok.ends_with("\r\n\r\n{\"ok\":true}\n"));
+
+    let failed = response(422, &json!({ "ok": false }));
+    assert!(failed.starts_with("HTTP/1.1 422 Unprocessable Entity\r\n"));
+    assert!(response(401, &json!({})).starts_with("HTTP/1.1 401 Unauthorized\r\n"));
+    assert!(response(503, &json!({})).starts_with("HTTP/1.1 503 Service Unavailable\r\n"));
+}
+
+#[test]
+fn test_status_of() {
+    assert_eq!(status_of(&json!({ "ok": true })), 200);
+    assert_eq!(status_of(&json!({ "ok": false })), 422);
+    assert_eq!(status_of(&json!({})), 422);
+
+    let body = spray::serve::error_body(&SprayError::ParseError("bad".into()));
+    assert_eq!(body["ok"], false);
+    assert_eq!(body["command"], "serve");
+}
@@ -0,0 +1,97 @@
+//! Unit tests for sighash explanation comparison
+
+use musk::elements::hashes::Hash;
+use musk::elements::{
+    AssetId, BlockHash, LockTime, OutPoint, Script, Sequence, Transaction, TxIn, TxOut, Txid,
+};
+use serde_json::json;
+use spray::confidential::explicit_output;
+use spray::pset::SimplicityLeaf;
+use spray::sighash::{diff_json, SighashExplanation, COMPONENT_JETS};
+use std::str::FromStr;
+
+#[test]
+fn test_diff_json_identical() {
+    let value = json!({
+        "version": 2,
+        "inputs": [{ "outpoint": "00:0", "sequence": 4294967295u32 }],
+        "digests": { "outputs": "ab" }
+    });
+    assert!(diff_json(&value, &value).is_empty());
+}
+
+#[test]
+fn test_diff_json_changed_fields() {
+    let expected = json!({ "lock_time": 0, "tap_leaf": { "leaf_version": 190 } });
+    let actual = json!({ "lock_time": 100, "tap_leaf": { "leaf_version": 190 } });
+
+    let diffs = diff_json(&expected, &actual);
+    assert_eq!(diffs.len(), 1);
+    assert_eq!(diffs[0].path, "lock_time");
+    assert_eq!(diffs[0].expected.as_deref(), Some("0"));
+    assert_eq!(diffs[0].actual.as_deref(), Some("100"));
+}
+
+#[test]
+fn test_diff_json_array_paths() {
+    let expected = json!({ "outputs": [{ "value": "1000" }, { "value": "3000" }] });
+    let actual = json!({ "outputs": [{ "value": "1000" }, { "value": "2500" }] });
+
+    let diffs = diff_json(&expected, &actual);
+    assert_eq!(diffs.len(), 1);
+    assert_eq!(diffs[0].path, "outputs[1].value");
+}
+
+#[test]
+fn test_diff_json_missing_fields() {
+    let expected = json!({ "inputs": [{ "issuance": "00" }] });
+    let actual = json!({ "inputs": [{}], "outputs": [{ "nonce": "02" }] });
+
+    let diffs = diff_json(&expected, &actual);
+    let paths: Vec<&str> = diffs.iter().map(|d| d.path.as_str()).collect();
+    assert_eq!(paths, ["inputs[0].issuance", "outputs[0].nonce"]);
+    assert_eq!(diffs[0].actual, None);
+    assert_eq!(diffs[1].expected, None);
+}
+
+#[test]
+fn test_component_hashes() {
+    let program = musk::Program::from_source("fn main() {}")
+        .unwrap()
+        .instantiate(musk::Arguments::default())
+        .unwrap();
+    let leaf = SimplicityLeaf::new(program.cmr().to_byte_array()).unwrap();
+    let asset = AssetId::from_str(&"5a".repeat(32)).unwrap();
+    let prevout = explicit_output(leaf.script_pubkey.clone(), 10_000, asset);
+    let explain = |fee: u64| {
+        let tx = Transaction {
+            version: 2,
+            lock_time: LockTime::ZERO,
+            input: vec![TxIn {
+                previous_output: OutPoint::new(Txid::all_zeros(), 0),
+                sequence: Sequence::MAX,
+                ..TxIn::default()
+            }],
+            output: vec![
+                explicit_output(Script::new(), 10_000 - fee, asset),
+                TxOut::new_fee(fee, asset),
+            ],
+        };
+        SighashExplanation::new(
+            [0; 32],
+            BlockHash::all_zeros(),
+            &tx,
+            &[prevout.clone()],
+            0,
+            &leaf,
+        )
+        .unwrap()
+    };
+
+    let (cheap, dear) = (explain(100), explain(200));
+    assert_eq!(cheap.digests.len(), COMPONENT_JETS.len());
+    assert!(cheap.digests.values().all(|hash| hash.len() == 64));
+    assert_eq!(cheap.digests["inputs_hash"], dear.digests["inputs_hash"]);
+    assert_ne!(cheap.digests["outputs_hash"], dear.digests["outputs_hash"]);
+    assert_ne!(cheap.digests["sig_all_hash"], dear.digests["sig_all_hash"]);
+}
@@ -0,0 +1,115 @@
+//! Unit tests for artifact signing
+
+use spray::signing::{
+    public_key, sign, signature_path, verify_artifact, verify_deployment, Keystore,
+};
+use std::path::Path;
+
+#[test]
+fn test_signature_path() {
+    assert_eq!(
+        signature_path(Path::new("out/compiled.json")),
+        Path::new("out/compiled.json.sig")
+    );
+}
+
+#[test]
+fn test_keystore_round_trip() {
+    let dir = tempfile::tempdir().unwrap();
+    let keystore = Keystore::new(dir.path().join("keys"));
+
+    let pubkey = keystore.generate("alice").unwrap();
+    assert_eq!(public_key(&keystore.secret_key("alice").unwrap()), pubkey);
+
+    // Keys are never overwritten
+    assert!(keystore.generate("alice").is_err());
+    assert!(keystore.secret_key("bob").is_err());
+}
+
+#[cfg(unix)]
+#[test]
+fn test_key_file_is_private() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let dir = tempfile::tempdir().unwrap();
+    let keystore = Keystore::new(dir.path());
+    keystore.generate("alice").unwrap();
+    let mode = std::fs::metadata(dir.path().join("alice.key"))
+        .unwrap()
+        .permissions()
+        .mode();
+    assert_eq!(mode & 0o777, 0o600);
+}
+
+#[test]
+fn test_verify_rejects_untrusted_and_forged() {
+    let dir = tempfile::tempdir().unwrap();
+    let keystore = Keystore::new(dir.path());
+    let alice = keystore.generate("alice").unwrap().to_string();
+    let mallory = keystore.generate("mallory").unwrap().to_string();
+
+    let artifact = b"compiled";
+    let signature = sign(
+        artifact,
+        &keystore.secret_key("mallory").unwrap(),
+        "mallory",
+    );
+    assert!(signature.verify(artifact, &[alice.clone()]).is_err());
+    assert!(signature.verify(artifact, &[mallory]).is_ok());
+
+    // Claiming a trusted key does not help without its secret key
+    let mut forged = signature;
+    forged.pubkey = alice.clone();
+    assert!(forged.verify(artifact, &[alice]).is_err());
+}
+
+#[test]
+fn test_verify_artifact_file() {
+    let dir = tempfile::tempdir().unwrap();
+    let keystore = Keystore::new(dir.path().join("keys"));
+    let pubkey = keystore.generate("alice").unwrap().to_string();
+
+    let artifact = dir.path().join("compiled.json");
+    std::fs::write(&artifact, r#"{"cmr": "00"}"#).unwrap();
+    let trusted = [pubkey];
+    assert!(verify_artifact(&artifact, &trusted).is_err());
+
+    let signature = sign(
+        &std::fs::read(&artifact).unwrap(),
+        &keystore.secret_key("alice").unwrap(),
+        "alice",
+    );
+    signature.save(&signature_path(&artifact)).unwrap();
+    assert_eq!(verify_artifact(&artifact, &trusted).unwrap(), signature);
+
+    std::fs::write(&artifact, r#"{"cmr": "01"}"#).unwrap();
+    assert!(verify_artifact(&artifact, &trusted).is_err());
+}
+
+#[test]
+fn test_verify_deployment_rejects_arguments_and_sources() {
+    let dir = tempfile::tempdir().unwrap();
+    let keystore = Keystore::new(dir.path().join("keys"));
+    let trusted = [keystore.generate("alice").unwrap().to_string()];
+    let secret_key = keystore.secret_key("alice").unwrap();
+
+    let artifact = dir.path().join("compiled.json");
+    std::fs::write(&artifact, r#"{"cmr": "00", "source": "fn main() {}"}"#).unwrap();
+    sign(&std::fs::read(&artifact).unwrap(), &secret_key, "alice")
+        .save(&signature_path(&artifact))
+        .unwrap();
+    assert!(verify_deployment(&artifact, None, &[], &trusted).is_ok());
+
+    let arg = [("N".to_string(), "2".to_string())];
+    let error = verify_deployment(&artifact, None, &arg, &trusted).unwrap_err();
+    assert!(error.to_string().contains("--arg"));
+    let args = dir.path().join("args.json");
+    assert!(verify_deployment(&artifact, Some(&args), &[], &trusted).is_err());
+
+    let source = dir.path().join("p.simf");
+    std::fs::write(&source, "fn main() {}").unwrap();
+    sign(b"fn main() {}", &secret_key, "alice")
+        .save(&signature_path(&source))
+        .unwrap();
+    assert!(verify_deployment(&source, None, &[], &trusted).is_err());
+}
@@ -0,0 +1,56 @@
+//! Unit tests for soak fault scheduling
+
+use spray::soak::{Fault, FaultSchedule, SoakReport};
+use spray::stress::{Instance, StressReport};
+use std::time::Duration;
+
+#[test]
+fn test_parse_faults() {
+    assert_eq!("restart".parse::<Fault>().unwrap(), Fault::Restart);
+    assert_eq!("evict".parse::<Fault>().unwrap(), Fault::MempoolEviction);
+    assert_eq!("reorg".parse::<Fault>().unwrap(), Fault::Reorg(1));
+    assert_eq!("reorg:3".parse::<Fault>().unwrap(), Fault::Reorg(3));
+
+    assert!("reorg:0".parse::<Fault>().is_err());
+    assert!("reorg:x".parse::<Fault>().is_err());
+    assert!("crash".parse::<Fault>().is_err());
+}
+
+#[test]
+fn test_fault_display_round_trips() {
+    for fault in [Fault::Restart, Fault::Reorg(2), Fault::MempoolEviction] {
+        assert_eq!(fault.to_string().parse::<Fault>().unwrap(), fault);
+    }
+}
+
+#[test]
+fn test_schedule_every_round() {
+    let schedule = FaultSchedule::new(vec![Fault::Reorg(1)], 1);
+    assert!((0..5).all(|round| schedule.fault_for_round(round) == Some(Fault::Reorg(1))));
+
+    // An interval of zero is treated as one
+    assert_eq!(
+        FaultSchedule::new(vec![Fault::Restart], 0).fault_for_round(0),
+        Some(Fault::Restart)
+    );
+}
+
+#[test]
+fn test_schedule_without_faults() {
+    let schedule = FaultSchedule::new(Vec::new(), 2);
+    assert!((0..10).all(|round| schedule.fault_for_round(round).is_none()));
+}
+
+#[test]
+fn test_report_passes_only_when_everything_confirmed() {
+    let mut stress = StressReport::new(1, 1);
+    stress.add(Instance::succeeded(Duration::ZERO, Duration::ZERO).with_txid("ab"));
+    let mut report = SoakReport {
+        stress,
+        ..SoakReport::default()
+    };
+    assert!(report.passed());
+
+    report.unconfirmed.push("ab".into());
+    assert!(!report.passed());
+}
@@ -0,0 +1,120 @@
+//! Unit tests for source-mapped failures
+
+use spray::sourcemap::{SiteKind, SourceMap};
+use spray::trace::{Trace, TraceStep};
+
+const SOURCE: &str = "\
+/* jet::eq_8 in a comment */
+fn check(x: u8) {
+    assert!(jet::eq_8(x, 1)); // jet::eq_8 again
+}
+
+fn main() {
+    let x: u8 = unwrap(witness::X);
+    check(x);
+    jet::verify(jet::eq_8(x, 1));
+}
+";
+
+fn failed_at(op: &str, failed: bool) -> Trace {
+    Trace {
+        steps: vec![TraceStep {
+            index: 3,
+            op: op.into(),
+            input: None,
+            output: None,
+            failed,
+        }],
+        error: Some("failed".into()),
+    }
+}
+
+#[test]
+fn test_sites_skip_comments_and_track_functions() {
+    let map = SourceMap::new(SOURCE, "main.simf");
+    let sites: Vec<_> = map
+        .sites()
+        .iter()
+        .map(|site| (site.kind.clone(), site.line, site.function.as_deref()))
+        .collect();
+    assert_eq!(
+        sites,
+        vec![
+            (SiteKind::Assert, 3, Some("check")),
+            (SiteKind::Jet("eq_8".into()), 3, Some("check")),
+            (SiteKind::Unwrap, 7, Some("main")),
+            (SiteKind::Jet("verify".into()), 9, Some("main")),
+            (SiteKind::Jet("eq_8".into()), 9, Some("main")),
+        ]
+    );
+    assert_eq!(map.sites()[0].len, "assert!(jet::eq_8(x, 1))".len());
+}
+
+#[test]
+fn test_locate_by_failing_node() {
+    let map = SourceMap::new(SOURCE, "main.simf");
+    let lines = |trace: &Trace| -> Vec<(usize, usize)> {
+        map.locate(trace.failure().unwrap())
+            .iter()
+            .map(|site| (site.line, site.column))
+            .collect()
+    };
+
+    assert_eq!(lines(&failed_at("jet_eq_8", true)), vec![(3, 13), (9, 17)]);
+    assert_eq!(lines(&failed_at("jet_verify", true)), vec![(3, 5), (9, 5)]);
+    assert_eq!(lines(&failed_at("case", false)), vec![(7, 17)]);
+    assert!(lines(&failed_at("jet_add_32", true)).is_empty());
+}
+
+#[test]
+fn test_render_snippet() {
+    let map = SourceMap::new(SOURCE, "main.simf");
+    let snippet = map.render(&failed_at("case", false)).unwrap();
+    assert_eq!(
+        snippet,
+        "error: execution stopped here\n \
+         --> main.simf:7:17 (in fn main)\n  \
+         |\n\
+         7 |     let x: u8 = unwrap(witness::X);\n  \
+         |                 ^^^^^^^^^^^^^^^^^^ execution stopped here\n"
+    );
+
+    let snippet = map.render(&failed_at("jet_eq_8", true)).unwrap();
+    assert!(snippet.contains("note: 2 possible locations"));
+
+    let mut trace = failed_at("jet_eq_8", true);
+    trace.steps[0].input = Some("(2, 1)".into());
+    let snippet = map.render(&trace).unwrap();
+    assert!(snippet.starts_with("error: jet_eq_8 failed on (2, 1)\n"));
+
+    let success = Trace {
+        steps: Vec::new(),
+        error: None,
+    };
+    assert!(map.render(&success).is_none());
+}
+
+#[test]
+fn test_included_lines_map_to_their_file() {
+    let dir = tempfile::tempdir().unwrap();
+    std::fs::write(
+        dir.path().join("helpers.simf"),
+        "fn one() -> u8 {\n    jet::max_8(0, 1)\n}\n",
+    )
+    .unwrap();
+    std::fs::write(
+        dir.path().join("main.simf"),
+        "include \"helpers.simf\";\n\nfn main() {\n    assert!(jet::eq_8(one(), 1));\n}\n",
+    )
+    .unwrap();
+
+    let map = SourceMap::load(&dir.path().join("main.simf")).unwrap();
+    let max = &map.sites()[0];
+    assert_eq!(max.kind, SiteKind::Jet("max_8".into()));
+    assert_eq!(max.file, dir.path().join("helpers.simf"));
+    assert_eq!((max.line, max.function.as_deref()), (2, Some("one")));
+
+    let eq = &map.locate(&failed_at("jet_eq_8", true).steps[0])[0];
+    assert_eq!(eq.file, dir.path().join("main.simf"));
+    assert_eq!((eq.line, eq.column), (4, 13));
+}
@@ -0,0 +1,62 @@
+//! Unit tests for stress test reports
+
+use serde_json::json;
+use spray::stress::{Instance, ResourceSample, StressReport};
+use spray::FailureStage;
+use std::time::Duration;
+
+fn ms(n: u64) -> Duration {
+    Duration::from_millis(n)
+}
+
+#[test]
+fn test_failures_by_stage() {
+    let mut report = StressReport::new(4, 2);
+    report.add(Instance::succeeded(ms(1), ms(1)));
+    report.add(Instance::failed(FailureStage::Funding, "no funds"));
+    report.add(Instance::failed(FailureStage::NodeRejection, "rejected"));
+    report.add(Instance::failed(FailureStage::NodeRejection, "rejected"));
+
+    let failures = report.failures();
+    assert_eq!(failures[&FailureStage::Funding], 1);
+    assert_eq!(failures[&FailureStage::NodeRejection], 2);
+    assert!((report.failure_rate() - 0.75).abs() < f64::EPSILON);
+}
+
+#[test]
+fn test_percentiles() {
+    let mut report = StressReport::new(10, 1);
+    for n in 1..=10 {
+        report.add(Instance::succeeded(ms(n), ms(n * 10)));
+    }
+
+    assert_eq!(report.deploy_percentile(50), Some(ms(5)));
+    assert_eq!(report.deploy_percentile(95), Some(ms(10)));
+    assert_eq!(report.redeem_percentile(100), Some(ms(100)));
+    assert_eq!(report.redeem_percentile(0), Some(ms(10)));
+    assert_eq!(StressReport::new(0, 1).redeem_percentile(50), None);
+}
+
+#[test]
+fn test_empty_report() {
+    let report = StressReport::new(0, 1);
+    assert!(report.failure_rate().abs() < f64::EPSILON);
+    assert!(report.throughput().abs() < f64::EPSILON);
+    assert_eq!(report.peak_mempool_usage(), 0);
+}
+
+#[test]
+fn test_resource_sample_from_rpc() {
+    let memory = json!({ "locked": { "used": 1024, "free": 64 } });
+    let mempool = json!({ "size": 8, "bytes": 4000, "usage": 12000 });
+    let sample = ResourceSample::from_rpc("round 0 spent", &memory, &mempool);
+
+    assert_eq!(sample.mempool_txs, 8);
+    assert_eq!(sample.mempool_usage, 12000);
+    assert_eq!(sample.memory_used, 1024);
+
+    let mut report = StressReport::new(1, 1);
+    report.resources.push(sample);
+    report.resources.push(ResourceSample::default());
+    assert_eq!(report.peak_mempool_usage(), 12000);
+}
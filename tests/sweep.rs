@@ -0,0 +1,107 @@
+//! Unit tests for multi-input sweeps
+
+use musk::client::Utxo;
+use musk::elements::{confidential, AddressParams, AssetId, Script, Txid};
+use musk::WitnessValues;
+use spray::sweep::Sweep;
+use std::cell::RefCell;
+use std::str::FromStr;
+
+fn program() -> musk::InstantiatedProgram {
+    musk::Program::from_source("fn main() {}")
+        .unwrap()
+        .instantiate(musk::Arguments::default())
+        .unwrap()
+}
+
+fn asset(byte: &str) -> AssetId {
+    AssetId::from_str(&byte.repeat(32)).unwrap()
+}
+
+fn utxo(script: &Script, vout: u32, amount: u64, asset: AssetId) -> Utxo {
+    Utxo {
+        txid: Txid::from_str(&"ab".repeat(32)).unwrap(),
+        vout,
+        amount,
+        script_pubkey: script.clone(),
+        asset: confidential::Asset::Explicit(asset),
+    }
+}
+
+#[test]
+fn test_sweep_outputs() {
+    let script = Script::new();
+    let (lbtc, usdt) = (asset("5a"), asset("ce"));
+    let utxos = vec![
+        utxo(&script, 0, 4_000, lbtc),
+        utxo(&script, 1, 9_000, usdt),
+        utxo(&script, 2, 6_000, lbtc),
+    ];
+    let sweep = Sweep::new(program(), utxos, script, 1_000).unwrap();
+    assert_eq!(sweep.outputs(), [(lbtc, 9_000), (usdt, 9_000)]);
+
+    let tx = sweep.transaction();
+    assert_eq!(tx.input.len(), 3);
+    assert_eq!(tx.output.len(), 3);
+    assert!(tx.output[2].is_fee());
+}
+
+#[test]
+fn test_sweep_errors() {
+    let script = Script::new();
+    assert!(Sweep::new(program(), Vec::new(), script.clone(), 100).is_err());
+
+    let utxos = vec![utxo(&script, 0, 50, asset("5a"))];
+    assert!(Sweep::new(program(), utxos, script, 100).is_err());
+}
+
+#[test]
+fn test_sweep_witness_per_input() {
+    let program = program();
+    let script = program.address(&AddressParams::ELEMENTS).script_pubkey();
+    let utxos = (0..3)
+        .map(|vout| utxo(&script, vout, 10_000, asset("5a")))
+        .collect();
+    let sweep = Sweep::new(program, utxos, Script::new(), 500).unwrap();
+
+    let sighashes = RefCell::new(Vec::new());
+    let witness = |sighash: [u8; 32]| {
+        sighashes.borrow_mut().push(sighash);
+        WitnessValues::default()
+    };
+    let tx = sweep.finalize(&witness).unwrap();
+
+    assert!(tx
+        .input
+        .iter()
+        .all(|input| input.witness.script_witness.len() == 4));
+    let sighashes = sighashes.into_inner();
+    assert_eq!(sighashes.len(), 3);
+    assert_ne!(sighashes[0], sighashes[1]);
+    assert_ne!(sighashes[1], sighashes[2]);
+}
+
+#[test]
+fn test_sweep_finalize_each() {
+    let program = program();
+    let script = program.address(&AddressParams::ELEMENTS).script_pubkey();
+    let utxos = (0..2)
+        .map(|vout| utxo(&script, vout, 10_000, asset("5a")))
+        .collect();
+    let sweep = Sweep::new(program, utxos, Script::new(), 500).unwrap();
+
+    let calls = RefCell::new(Vec::new());
+    let first = |_: [u8; 32]| {
+        calls.borrow_mut().push(0);
+        WitnessValues::default()
+    };
+    let second = |_: [u8; 32]| {
+        calls.borrow_mut().push(1);
+        WitnessValues::default()
+    };
+    let tx = sweep.finalize_each(&[&first, &second]).unwrap();
+    assert_eq!(tx.input.len(), 2);
+    assert_eq!(calls.into_inner(), [0, 1]);
+
+    assert!(sweep.finalize_each(&[&first]).is_err());
+}
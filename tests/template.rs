@@ -0,0 +1,39 @@
+//! Unit tests for contract templates
+
+use spray::template::{scaffold, Template};
+use spray::witness::SigningWitness;
+
+#[test]
+fn test_template_names() {
+    for template in Template::ALL {
+        assert_eq!(template.name().parse::<Template>().unwrap(), template);
+    }
+    assert!("multisig".parse::<Template>().is_err());
+}
+
+#[test]
+fn test_scaffold_writes_project() {
+    let dir = tempfile::tempdir().unwrap();
+    let project = dir.path().join("vault-demo");
+
+    let written = scaffold(&project, Template::Vault).unwrap();
+    assert_eq!(written.len(), 4);
+    assert!(project.join("contracts/vault.simf").is_file());
+    let manifest = std::fs::read_to_string(project.join("spray-tests.toml")).unwrap();
+    assert!(manifest.contains("contracts/vault.simf"));
+
+    // A non-empty directory is left alone
+    assert!(scaffold(&project, Template::P2pk).is_err());
+}
+
+#[test]
+fn test_template_witnesses_load() {
+    for template in Template::ALL {
+        for (path, contents) in template.files() {
+            if path.to_string_lossy().ends_with(".wit.json") {
+                let value = serde_json::from_str(contents).unwrap();
+                assert!(SigningWitness::new(value).is_ok(), "{template}");
+            }
+        }
+    }
+}
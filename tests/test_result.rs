@@ -1,14 +1,14 @@
 //! Unit tests for TestResult enum
 
-use musk::elements::Txid;
-use spray::TestResult;
+use musk::elements::{LockTime, Transaction, TxIn, TxInWitness, Txid};
+use spray::{SpendDetails, TestResult};
 use std::str::FromStr;
 
 #[test]
 fn test_result_is_success() {
     let txid = Txid::from_str("0000000000000000000000000000000000000000000000000000000000000000")
         .expect("Valid txid");
-    let result = TestResult::Success { txid };
+    let result = TestResult::success(txid, None);
 
     assert!(result.is_success());
     assert!(!result.is_failure());
@@ -16,9 +16,7 @@ fn test_result_is_success() {
 
 #[test]
 fn test_result_is_failure() {
-    let result = TestResult::Failure {
-        error: "Test failed".to_string(),
-    };
+    let result = TestResult::failure("Test failed".to_string());
 
     assert!(result.is_failure());
     assert!(!result.is_success());
@@ -28,10 +26,8 @@ fn test_result_is_failure() {
 fn test_result_success_and_failure_mutually_exclusive() {
     let txid = Txid::from_str("0000000000000000000000000000000000000000000000000000000000000000")
         .expect("Valid txid");
-    let success = TestResult::Success { txid };
-    let failure = TestResult::Failure {
-        error: "error".to_string(),
-    };
+    let success = TestResult::success(txid, None);
+    let failure = TestResult::failure("error".to_string());
 
     // Success is not failure
     assert!(success.is_success() && !success.is_failure());
@@ -44,7 +40,7 @@ fn test_result_success_and_failure_mutually_exclusive() {
 fn test_result_clone() {
     let txid = Txid::from_str("0000000000000000000000000000000000000000000000000000000000000000")
         .expect("Valid txid");
-    let result = TestResult::Success { txid };
+    let result = TestResult::success(txid, None);
     let cloned = result.clone();
 
     assert!(cloned.is_success());
@@ -54,8 +50,51 @@ fn test_result_clone() {
 fn test_result_debug() {
     let txid = Txid::from_str("0000000000000000000000000000000000000000000000000000000000000000")
         .expect("Valid txid");
-    let result = TestResult::Success { txid };
+    let result = TestResult::success(txid, None);
     let debug_str = format!("{:?}", result);
 
     assert!(debug_str.contains("Success"));
 }
+
+#[test]
+fn test_result_accessors() {
+    let txid = Txid::from_str("0000000000000000000000000000000000000000000000000000000000000000")
+        .expect("Valid txid");
+    let success = TestResult::success(txid, None);
+    assert_eq!(success.txid(), Some(txid));
+    assert_eq!(success.error(), None);
+    assert!(success.transaction().is_none());
+    assert_eq!(success.fee(), None);
+
+    let failure = TestResult::failure("error".to_string());
+    assert_eq!(failure.txid(), None);
+    assert_eq!(failure.error(), Some("error"));
+    assert_eq!(failure.vsize(), None);
+}
+
+#[test]
+fn test_result_from_spend() {
+    let tx = Transaction {
+        version: 2,
+        lock_time: LockTime::ZERO,
+        input: vec![TxIn {
+            witness: TxInWitness {
+                script_witness: vec![vec![0; 10], vec![0; 32]],
+                ..TxInWitness::default()
+            },
+            ..TxIn::default()
+        }],
+        output: Vec::new(),
+    };
+    let result = TestResult::from_spend(SpendDetails {
+        tx: tx.clone(),
+        fee: 250,
+    });
+
+    assert_eq!(result.txid(), Some(tx.txid()));
+    assert_eq!(result.fee(), Some(250));
+    assert_eq!(result.witness_size(), Some(42));
+    assert_eq!(result.weight(), Some(tx.weight()));
+    assert_eq!(result.vsize(), Some(tx.weight().div_ceil(4)));
+    assert_eq!(result.transaction(), Some(&tx));
+}
@@ -0,0 +1,157 @@
+//! Unit tests for execution traces
+
+use musk::WitnessValues;
+use spray::sourcemap::SourceMap;
+use spray::trace::{format_bits, Breakpoint, Resume, Trace, TraceStep, Tracer};
+use spray::verify::SpendContext;
+
+fn step(index: usize, op: &str, failed: bool) -> TraceStep {
+    TraceStep {
+        index,
+        op: op.into(),
+        input: None,
+        output: None,
+        failed,
+    }
+}
+
+#[test]
+fn test_format_bits() {
+    assert_eq!(format_bits([false; 16]), "0x0000");
+    assert_eq!(format_bits([true, true, false]), "0b110");
+    assert_eq!(format_bits(std::iter::empty()), "()");
+}
+
+#[test]
+fn test_step_display() {
+    let mut jet = step(3, "jet_add_32", false);
+    jet.input = Some("0x0000000100000002".into());
+    jet.output = Some("0b0".into());
+    assert_eq!(jet.to_string(), "%3 jet_add_32 0x0000000100000002 -> 0b0");
+    assert_eq!(step(4, "comp", false).to_string(), "%4 comp");
+}
+
+#[test]
+fn test_failure_is_failing_jet() {
+    let trace = Trace {
+        steps: vec![
+            step(0, "iden", false),
+            step(1, "jet_eq_32", true),
+            step(2, "comp", false),
+        ],
+        error: Some("Jet failed during execution".into()),
+    };
+    assert!(!trace.is_success());
+    assert_eq!(trace.failure().map(|s| s.index), Some(1));
+    assert_eq!(trace.jets().count(), 1);
+}
+
+#[test]
+fn test_failure_without_failing_jet_is_last_step() {
+    let trace = Trace {
+        steps: vec![step(0, "iden", false), step(1, "unit", false)],
+        error: Some("Reached a fail node".into()),
+    };
+    assert_eq!(trace.failure().map(|s| s.index), Some(1));
+
+    let success = Trace {
+        error: None,
+        ..trace
+    };
+    assert!(success.failure().is_none());
+    let json = serde_json::to_value(&success).unwrap();
+    assert!(json.get("error").is_none());
+}
+
+#[test]
+fn test_parse_breakpoint() {
+    let jet = |op: &str| Breakpoint::Jet(op.into());
+    assert_eq!("eq_32".parse::<Breakpoint>().unwrap(), jet("jet_eq_32"));
+    assert_eq!(
+        "jet::eq_32".parse::<Breakpoint>().unwrap(),
+        jet("jet_eq_32")
+    );
+    assert_eq!("jet_eq_32".parse::<Breakpoint>().unwrap(), jet("jet_eq_32"));
+    assert_eq!(
+        "jet_vault.simf:12".parse::<Breakpoint>().unwrap(),
+        Breakpoint::Line {
+            file: "jet_vault.simf".into(),
+            line: 12
+        }
+    );
+    assert!("vault.simf:0".parse::<Breakpoint>().is_err());
+    assert!("vault.simf:x".parse::<Breakpoint>().is_err());
+    assert!("eq 32".parse::<Breakpoint>().is_err());
+}
+
+#[test]
+fn test_line_breakpoint_ops() {
+    let source = "fn main() {\n    assert!(jet::eq_32(witness::X, jet::add_32(1, 2)));\n}\n";
+    let map = SourceMap::new(source, "src/main.simf");
+    let line = |line| Breakpoint::Line {
+        file: "main.simf".into(),
+        line,
+    };
+    assert_eq!(
+        line(2).ops(&map).unwrap(),
+        ["jet_verify", "jet_eq_32", "jet_add_32"]
+    );
+    assert!(line(1).ops(&map).is_err());
+    assert_eq!(
+        Breakpoint::Jet("jet_sha_256_ctx_8_init".into())
+            .ops(&map)
+            .unwrap(),
+        ["jet_sha_256_ctx_8_init"]
+    );
+}
+
+#[test]
+fn test_breakpoints_pause_and_resume() {
+    let source = "fn main() {\n    assert!(jet::eq_32(1, 1));\n    assert!(jet::eq_32(2, 2));\n}\n";
+    let program = musk::Program::from_source(source)
+        .unwrap()
+        .instantiate(musk::Arguments::default())
+        .unwrap();
+    let context = SpendContext::synthetic(&program, 10_000);
+    let tracer = Tracer::new().jets_only(true).break_on("jet_eq_32");
+
+    let mut paused = Vec::new();
+    let trace = tracer
+        .trace_spend_with(&program, WitnessValues::default(), &context, |steps| {
+            paused.push(steps.last().unwrap().clone());
+            Resume::Continue
+        })
+        .unwrap();
+    assert!(trace.is_success());
+    assert_eq!(paused.len(), 2);
+    assert!(paused.iter().all(|step| step.op == "jet_eq_32"));
+    assert_eq!(paused[0].input.as_deref(), Some("(1, 1)"));
+    assert_eq!(paused[1].input.as_deref(), Some("(2, 2)"));
+    assert_eq!(paused[0].output.as_deref(), Some("1"));
+
+    let mut pauses = 0;
+    tracer
+        .trace_spend_with(&program, WitnessValues::default(), &context, |_| {
+            pauses += 1;
+            Resume::Finish
+        })
+        .unwrap();
+    assert_eq!(pauses, 1);
+
+    let mut pauses = 0;
+    let stepped = tracer
+        .trace_spend_with(&program, WitnessValues::default(), &context, |_| {
+            pauses += 1;
+            Resume::Step
+        })
+        .unwrap();
+    assert_eq!(
+        pauses,
+        stepped.steps.len()
+            - stepped
+                .steps
+                .iter()
+                .position(|s| s.op == "jet_eq_32")
+                .unwrap()
+    );
+}
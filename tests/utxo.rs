@@ -0,0 +1,80 @@
+//! Unit tests for UTXO descriptions
+
+use musk::elements::{confidential, LockTime, Transaction, TxOut};
+use spray::error::SprayError;
+use spray::utxo::{UtxoDescription, UTXO_DESCRIPTION_FORMAT};
+
+fn funding_tx(amount: u64) -> Transaction {
+    Transaction {
+        version: 2,
+        lock_time: LockTime::ZERO,
+        input: vec![],
+        output: vec![
+            TxOut::default(),
+            TxOut {
+                value: confidential::Value::Explicit(amount),
+                ..TxOut::default()
+            },
+        ],
+    }
+}
+
+fn description(tx: &Transaction, vout: u32) -> Result<UtxoDescription, SprayError> {
+    UtxoDescription::new(
+        "regtest",
+        musk::elements::BlockHash::all_zeros(),
+        tx,
+        vout,
+        Some(1),
+    )
+}
+
+#[test]
+fn test_description_round_trips_to_utxo() {
+    let tx = funding_tx(12_345);
+    let description = description(&tx, 1).expect("Failed to describe UTXO");
+
+    assert_eq!(description.format, UTXO_DESCRIPTION_FORMAT);
+    assert_eq!(description.amount, Some(12_345));
+
+    let utxo = description.to_utxo().expect("Failed to convert");
+    assert_eq!(utxo.txid, tx.txid());
+    assert_eq!(utxo.vout, 1);
+    assert_eq!(utxo.amount, 12_345);
+}
+
+#[test]
+fn test_description_rejects_missing_vout() {
+    let tx = funding_tx(1);
+    assert!(matches!(
+        description(&tx, 5),
+        Err(SprayError::InvalidUtxoRef(_))
+    ));
+}
+
+#[test]
+fn test_description_save_and_load() {
+    let dir = tempfile::tempdir().expect("Failed to create temp dir");
+    let path = dir.path().join("utxo.json");
+
+    let original = description(&funding_tx(42), 1).expect("Failed to describe UTXO");
+    original.save(&path).expect("Failed to save");
+
+    let loaded = UtxoDescription::load(&path).expect("Failed to load");
+    assert_eq!(loaded, original);
+}
+
+#[test]
+fn test_description_load_rejects_unknown_format() {
+    let dir = tempfile::tempdir().expect("Failed to create temp dir");
+    let path = dir.path().join("utxo.json");
+
+    let mut original = description(&funding_tx(42), 1).expect("Failed to describe UTXO");
+    original.format = "something-else".to_string();
+    original.save(&path).expect("Failed to save");
+
+    assert!(matches!(
+        UtxoDescription::load(&path),
+        Err(SprayError::FileFormatError(_))
+    ));
+}
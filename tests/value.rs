@@ -0,0 +1,133 @@
+//! Unit tests for type-directed value formatting
+
+use spray::value::ValueType;
+
+fn format(ty: &str, hex: &str) -> String {
+    ty.parse::<ValueType>().unwrap().format_hex(hex).unwrap()
+}
+
+#[test]
+fn test_parse_types() {
+    for ty in [
+        "()",
+        "bool",
+        "u32",
+        "u256",
+        "(u8, bool)",
+        "[u16; 3]",
+        "Either<u8, (u8, u8)>",
+        "Option<u64>",
+        "[(u1, u256); 2]",
+    ] {
+        let parsed: ValueType = ty.parse().unwrap();
+        assert_eq!(parsed.to_string(), ty);
+    }
+}
+
+#[test]
+fn test_parse_invalid_types() {
+    assert!("u3".parse::<ValueType>().is_err());
+    assert!("Foo".parse::<ValueType>().is_err());
+    assert!("[u8; x]".parse::<ValueType>().is_err());
+    assert!("(u8, u8".parse::<ValueType>().is_err());
+    assert!("u8 u8".parse::<ValueType>().is_err());
+}
+
+#[test]
+fn test_aliases() {
+    assert_eq!("Pubkey".parse::<ValueType>().unwrap(), ValueType::UInt(256));
+    assert_eq!("Height".parse::<ValueType>().unwrap().bit_width(), 32);
+    assert_eq!("Signature".parse::<ValueType>().unwrap().bit_width(), 512);
+}
+
+#[test]
+fn test_bit_width() {
+    assert_eq!("()".parse::<ValueType>().unwrap().bit_width(), 0);
+    assert_eq!(
+        "Either<u8, u32>".parse::<ValueType>().unwrap().bit_width(),
+        33
+    );
+    assert_eq!("Option<u16>".parse::<ValueType>().unwrap().bit_width(), 17);
+    assert_eq!(
+        "[(u8, bool); 4]".parse::<ValueType>().unwrap().bit_width(),
+        36
+    );
+}
+
+#[test]
+fn test_format_integers() {
+    assert_eq!(format("u8", "ff"), "255");
+    assert_eq!(format("u32", "00010000"), "65536");
+    assert_eq!(format("u4", "a0"), "10");
+    assert_eq!(
+        format("u128", &"ab".repeat(16)),
+        format!("0x{}", "ab".repeat(16))
+    );
+}
+
+#[test]
+fn test_format_compound() {
+    assert_eq!(format("[u8; 3]", "010203"), "[1, 2, 3]");
+    assert_eq!(format("(u8, bool)", "0780"), "(7, true)");
+    assert_eq!(format("bool", "00"), "false");
+}
+
+#[test]
+fn test_format_sums() {
+    // Tag bit, then padding up to the wider variant, then the value
+    assert_eq!(format("Either<u8, u16>", "007f80"), "Left(255)");
+    assert_eq!(format("Either<u8, u16>", "ffff80"), "Right(65535)");
+    assert_eq!(format("Option<u8>", "0000"), "None");
+    assert_eq!(format("Option<u8>", "ff80"), "Some(255)");
+}
+
+#[test]
+fn test_format_too_short() {
+    let ty: ValueType = "u32".parse().unwrap();
+    assert!(ty.format_hex("ffff").is_err());
+    assert!(ty.format_hex("fff").is_err());
+}
+
+#[test]
+fn test_parse_literal_integers() {
+    let u8_ty: ValueType = "u8".parse().unwrap();
+    assert_eq!(u8_ty.parse_literal("255").unwrap(), serde_json::json!(255));
+    assert_eq!(u8_ty.parse_literal("0xff").unwrap(), serde_json::json!(255));
+    assert!(u8_ty.parse_literal("256").is_err());
+    assert!(u8_ty.parse_literal("0x100").is_err());
+
+    let u256: ValueType = "u256".parse().unwrap();
+    assert_eq!(
+        u256.parse_literal("1").unwrap(),
+        serde_json::json!(format!("0x{}1", "0".repeat(63)))
+    );
+}
+
+#[test]
+fn test_parse_literal_other_types() {
+    let flag: ValueType = "bool".parse().unwrap();
+    assert_eq!(flag.parse_literal("true").unwrap(), serde_json::json!(true));
+    assert!(flag.parse_literal("yes").is_err());
+
+    let bytes: ValueType = "[u8; 4]".parse().unwrap();
+    assert_eq!(
+        bytes.parse_literal("0xdeadbeef").unwrap(),
+        serde_json::json!("0xdeadbeef")
+    );
+    assert!(bytes.parse_literal("0xdead").is_err());
+}
+
+#[test]
+fn test_size_bound() {
+    assert!("[u256; 100000]".parse::<ValueType>().is_err());
+    assert!("[[(); 10000]; 10000]".parse::<ValueType>().is_err());
+    assert!("[u8; 99999999999999999999999]"
+        .parse::<ValueType>()
+        .is_err());
+
+    let huge = ValueType::Array(Box::new(ValueType::UInt(8)), usize::MAX);
+    assert_eq!(huge.bit_width(), usize::MAX);
+    assert!(huge.check_size().is_err());
+    assert!(huge.placeholder().is_err());
+    assert!(huge.format_bits(&[]).is_err());
+}
@@ -0,0 +1,109 @@
+//! Unit tests for recording and replaying node traffic
+
+use musk::client::{NodeClient, Utxo};
+use musk::elements::{confidential, AssetId, BlockHash, Script, Txid};
+use serde_json::{json, Value};
+use spray::network::NetworkBackend;
+use spray::vcr::{Cassette, Interaction, Recordable, Recorder, Replay};
+use std::str::FromStr;
+
+const TXID: &str = "f7f9c1b5a1e1b9d5e0f6c0e5f2b1d6e4c3a2b1f0e9d8c7b6a5f4e3d2c1b0a9f8";
+
+fn replay(interactions: Vec<Interaction>) -> Replay {
+    Replay::new(Cassette {
+        network: "regtest".into(),
+        interactions,
+    })
+}
+
+#[test]
+fn test_replay_prefers_matching_params() {
+    let replay = replay(vec![
+        Interaction::ok("getblockhash", vec![json!(1)], json!("one")),
+        Interaction::ok("getblockhash", vec![json!(0)], json!("zero")),
+    ]);
+
+    let zero: Value = replay.replay("getblockhash", &[json!(0)]).unwrap();
+    assert_eq!(zero, json!("zero"));
+    // Falls back to the next unused interaction of the method
+    let other: Value = replay.replay("getblockhash", &[json!(7)]).unwrap();
+    assert_eq!(other, json!("one"));
+    assert_eq!(replay.remaining(), 0);
+    assert!(replay.replay::<Value>("getblockhash", &[json!(0)]).is_err());
+}
+
+#[test]
+fn test_replay_recorded_error() {
+    let replay = replay(vec![Interaction::failed(
+        "sendrawtransaction",
+        vec![],
+        "bad-txns-inputs-missingorspent".into(),
+    )]);
+
+    let error = replay
+        .replay::<Value>("sendrawtransaction", &[])
+        .unwrap_err();
+    assert_eq!(
+        error.to_string(),
+        "RPC error: bad-txns-inputs-missingorspent"
+    );
+}
+
+#[test]
+fn test_recordable_roundtrip() {
+    let txid = Txid::from_str(TXID).unwrap();
+    assert_eq!(Txid::from_value(txid.to_value()).unwrap(), txid);
+
+    let utxo = Utxo {
+        txid,
+        vout: 3,
+        amount: 100_000,
+        script_pubkey: Script::from(vec![0x51, 0x20, 0xab]),
+        asset: confidential::Asset::Explicit(AssetId::LIQUID_BTC),
+    };
+    let decoded = Vec::<Utxo>::from_value(vec![utxo.clone()].to_value()).unwrap();
+    assert_eq!(decoded.len(), 1);
+    assert_eq!(decoded[0].txid, utxo.txid);
+    assert_eq!(decoded[0].vout, 3);
+    assert_eq!(decoded[0].amount, 100_000);
+    assert_eq!(decoded[0].script_pubkey, utxo.script_pubkey);
+    assert_eq!(decoded[0].asset, utxo.asset);
+
+    assert!(Txid::from_value(json!(42)).is_err());
+    assert!(Vec::<Txid>::from_value(json!("not an array")).is_err());
+}
+
+#[test]
+fn test_recorder_writes_fixture() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("cassette.json");
+    let recorder = Recorder::new(&path);
+    recorder.set_network("testnet");
+    recorder.record("getblockcount", &[], Ok(&json!(101)));
+    recorder.record::<Value>("getblock", &[json!("00")], Err("Block not found".into()));
+
+    let cassette = Cassette::load(&path).unwrap();
+    assert_eq!(cassette, recorder.cassette());
+    assert_eq!(cassette.network, "testnet");
+    assert_eq!(cassette.interactions.len(), 2);
+    assert_eq!(
+        cassette.interactions[1].error.as_deref(),
+        Some("Block not found")
+    );
+}
+
+#[test]
+fn test_replay_backend_without_node() {
+    let genesis = BlockHash::from_str(TXID).unwrap();
+    let mut backend = NetworkBackend::Replay(replay(vec![
+        Interaction::ok("genesis_hash", vec![], genesis.to_value()),
+        Interaction::ok("getblockcount", vec![], json!(150)),
+        Interaction::ok("get_transaction", vec![json!(TXID)], Value::Null),
+    ]));
+
+    assert_eq!(backend.genesis_hash().unwrap(), genesis);
+    assert_eq!(backend.block_count().unwrap(), 150);
+    assert!(backend
+        .get_transaction(&Txid::from_str(TXID).unwrap())
+        .is_err());
+}
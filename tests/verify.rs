@@ -0,0 +1,57 @@
+//! Unit tests for local witness verification
+
+use musk::{Arguments, Program, WitnessValues};
+use spray::verify::{verify, FailureKind, SpendContext};
+
+#[test]
+fn test_classify_failures() {
+    assert_eq!(
+        FailureKind::classify("Jet `bip_0340_verify` failed"),
+        FailureKind::Jet("bip_0340_verify".to_string())
+    );
+    assert_eq!(
+        FailureKind::classify("Execution reached a fail node"),
+        FailureKind::Assertion
+    );
+    assert_eq!(
+        FailureKind::classify("Witness `sig` has the wrong type"),
+        FailureKind::Witness
+    );
+    assert_eq!(FailureKind::classify("Jet failed"), FailureKind::Other);
+    assert_eq!(FailureKind::classify("Broken pipe"), FailureKind::Other);
+}
+
+#[test]
+fn test_verify_passing_program() {
+    let program = Program::from_source("fn main() { assert!(true); }")
+        .unwrap()
+        .instantiate(Arguments::default())
+        .unwrap();
+    let context = SpendContext::synthetic(&program, 100_000);
+
+    let verification = verify(&program, WitnessValues::default(), &context).unwrap();
+    assert!(verification.result.is_ok());
+}
+
+#[test]
+fn test_verify_failing_program() {
+    let program = Program::from_source("fn main() { assert!(false); }")
+        .unwrap()
+        .instantiate(Arguments::default())
+        .unwrap();
+    let context = SpendContext::synthetic(&program, 100_000);
+
+    let verification = verify(&program, WitnessValues::default(), &context).unwrap();
+    assert!(verification.result.is_err());
+}
+
+#[test]
+fn test_verify_rejects_fee_above_amount() {
+    let program = Program::from_source("fn main() { assert!(true); }")
+        .unwrap()
+        .instantiate(Arguments::default())
+        .unwrap();
+    let context = SpendContext::synthetic(&program, 1_000);
+
+    assert!(verify(&program, WitnessValues::default(), &context).is_err());
+}
@@ -0,0 +1,67 @@
+//! Unit tests for the polling file watcher
+
+#![cfg(feature = "cli")]
+
+use spray::commands::test::watched_files;
+use spray::watcher::FileWatcher;
+use std::path::Path;
+use std::time::Duration;
+
+#[test]
+fn test_poll_reports_each_change_once() {
+    let dir = tempfile::tempdir().unwrap();
+    let program = dir.path().join("p.simf");
+    let witness = dir.path().join("p.wit.json");
+    std::fs::write(&program, "fn main() {}").unwrap();
+    std::fs::write(&witness, "{}").unwrap();
+
+    let mut watcher = FileWatcher::new([program.clone(), witness.clone()]);
+    assert!(watcher.poll().is_empty());
+
+    std::fs::write(&witness, r#"{"SIG": "0x00"}"#).unwrap();
+    assert_eq!(watcher.poll(), vec![witness]);
+    assert!(watcher.poll().is_empty());
+}
+
+#[test]
+fn test_poll_reports_created_and_removed_files() {
+    let dir = tempfile::tempdir().unwrap();
+    let args = dir.path().join("p.args.json");
+
+    let mut watcher = FileWatcher::new([args.clone()]);
+    std::fs::write(&args, "{}").unwrap();
+    assert_eq!(watcher.poll(), vec![args.clone()]);
+
+    std::fs::remove_file(&args).unwrap();
+    assert_eq!(watcher.poll(), vec![args]);
+}
+
+#[test]
+fn test_wait_returns_after_change() {
+    let dir = tempfile::tempdir().unwrap();
+    let program = dir.path().join("p.simf");
+    std::fs::write(&program, "fn main() {}").unwrap();
+
+    let mut watcher = FileWatcher::new([program.clone()]);
+    let writer = {
+        let program = program.clone();
+        std::thread::spawn(move || {
+            std::thread::sleep(Duration::from_millis(50));
+            std::fs::write(&program, "fn main() { }").unwrap();
+        })
+    };
+    assert_eq!(watcher.wait(Duration::from_millis(20)), vec![program]);
+    writer.join().unwrap();
+}
+
+#[test]
+fn test_watched_files_skips_missing_inputs() {
+    let files = watched_files(Path::new("p.simf"), None, Some(Path::new("p.wit")));
+    assert_eq!(
+        files,
+        vec![
+            Path::new("p.simf").to_path_buf(),
+            Path::new("p.wit").to_path_buf()
+        ]
+    );
+}
@@ -0,0 +1,47 @@
+//! Unit tests for witness providers
+
+use musk::WitnessValues;
+use serde_json::json;
+use spray::witness::{FixedWitness, SigningWitness, WitnessProvider};
+use std::cell::RefCell;
+
+#[test]
+fn test_closure_receives_sighash() {
+    let seen = RefCell::new(Vec::new());
+    let provider = |sighash: [u8; 32]| {
+        seen.borrow_mut().push(sighash);
+        WitnessValues::default()
+    };
+    let _ = provider.witness([7; 32]);
+    let _ = provider.witness([9; 32]);
+    assert_eq!(*seen.borrow(), vec![[7; 32], [9; 32]]);
+}
+
+#[test]
+fn test_providers_are_object_safe() {
+    let providers: Vec<Box<dyn WitnessProvider>> = vec![
+        Box::new(FixedWitness::default()),
+        Box::new(|_sighash: [u8; 32]| WitnessValues::default()),
+    ];
+    for provider in &providers {
+        let _ = provider.witness([0; 32]);
+    }
+}
+
+#[test]
+fn test_signing_witness() {
+    let key = "0x0000000000000000000000000000000000000000000000000000000000000001";
+    let flat = json!({
+        "SIG": { "sign": key, "type": "Signature" },
+        "FLAG": { "value": "true", "type": "bool" },
+    });
+    let witness = SigningWitness::new(flat.clone()).unwrap();
+    assert!(witness.resolve([7; 32]).is_ok());
+
+    // Entries of a TOML file's [witness] table
+    let sectioned = SigningWitness::new(json!({ "witness": flat })).unwrap();
+    assert!(sectioned.resolve([7; 32]).is_ok());
+
+    let invalid = json!({ "SIG": { "sign": "zz", "type": "Signature" } });
+    assert!(SigningWitness::new(invalid).is_err());
+}